@@ -0,0 +1,73 @@
+//! Wall time of the per-element batch exports at 100k elements
+//!
+//! `cargo bench --bench batch` prints the median of several runs of each,
+//! timed with `Instant` like the `delaunay` bench: one `update_motes` step
+//! over 100k motes, and 100k samples of 3D noise and of 4-octave fBm. A
+//! step over 10k motes, the heavy profile of `run_benchmark`, shows the
+//! per-call overhead at a typical scene size.
+//!
+//! Run it with and without `--features simd` to compare the four-lane
+//! kernels with the scalar loops; on x86_64 the lanes are SSE2 registers,
+//! as they are SIMD128 ones on wasm.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use voronoi_graphics::{
+    compute_delaunay, compute_edges, fbm_noise_2d_batch, generate_r2_seeds,
+    simplex_noise_3d_batch, update_motes, FractalMode,
+};
+
+const CANVAS: (f64, f64) = (1920.0, 1080.0);
+const ELEMENTS: usize = 100_000;
+const SCENE_MOTES: usize = 10_000;
+const RUNS: usize = 7;
+
+/// Median wall time of `RUNS` calls of `f`, in milliseconds
+fn median_ms(mut f: impl FnMut()) -> f64 {
+    let mut times: Vec<f64> = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed().as_secs_f64() * 1000.0
+        })
+        .collect();
+    times.sort_by(f64::total_cmp);
+    times[RUNS / 2]
+}
+
+fn main() {
+    let (width, height) = CANVAS;
+    let seeds = generate_r2_seeds(width, height, 2_000, 0);
+    let edges = compute_edges(&seeds, &compute_delaunay(&seeds, width, height)).unwrap();
+    let edge_count = edges.len() / 4;
+    let mut motes: Vec<f64> = (0..ELEMENTS)
+        .flat_map(|i| {
+            [(i % edge_count) as f64, (i % 97) as f64 / 97.0, 0.2 + (i % 13) as f64 * 0.05]
+        })
+        .collect();
+    let coords = generate_r2_seeds(width, height, ELEMENTS, 1);
+    let scaled: Vec<f64> = coords.iter().map(|v| v * 0.01).collect();
+
+    println!("{:>24} {:>10} {:>12}", "batch", "elements", "median ms");
+    let mut time = 0.0;
+    let ms = median_ms(|| {
+        time += 1.0 / 60.0;
+        black_box(update_motes(&mut motes, black_box(&edges), 1.0 / 60.0, time).unwrap());
+    });
+    println!("{:>24} {ELEMENTS:>10} {ms:>12.2}", "update_motes");
+    let mut scene = motes[..SCENE_MOTES * 3].to_vec();
+    let ms = median_ms(|| {
+        time += 1.0 / 60.0;
+        black_box(update_motes(&mut scene, black_box(&edges), 1.0 / 60.0, time).unwrap());
+    });
+    println!("{:>24} {SCENE_MOTES:>10} {ms:>12.2}", "update_motes");
+    let ms = median_ms(|| {
+        black_box(simplex_noise_3d_batch(black_box(&scaled), 0.5).unwrap());
+    });
+    println!("{:>24} {ELEMENTS:>10} {ms:>12.2}", "simplex_noise_3d_batch");
+    let ms = median_ms(|| {
+        black_box(fbm_noise_2d_batch(black_box(&scaled), 4, 2.0, 0.5, FractalMode::Fbm).unwrap());
+    });
+    println!("{:>24} {ELEMENTS:>10} {ms:>12.2}", "fbm_noise_2d_batch");
+}
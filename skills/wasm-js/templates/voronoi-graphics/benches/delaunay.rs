@@ -0,0 +1,34 @@
+//! Wall time of `compute_delaunay` at a few seed counts
+//!
+//! `cargo bench --bench delaunay` prints the median of several runs per
+//! size. The crate has no benchmark framework dependency, so this is a
+//! plain `harness = false` binary timed with `Instant`; `run_benchmark`
+//! covers the same ground from inside the browser. Add `--features
+//! parallel` to time the striped multi-threaded build instead.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use voronoi_graphics::{compute_delaunay, generate_r2_seeds};
+
+const CANVAS: (f64, f64) = (1920.0, 1080.0);
+const SIZES: [usize; 5] = [1_000, 10_000, 50_000, 100_000, 200_000];
+const RUNS: usize = 7;
+
+fn main() {
+    let (width, height) = CANVAS;
+    println!("{:>8} {:>10} {:>12}", "points", "triangles", "median ms");
+    for n in SIZES {
+        let seeds = generate_r2_seeds(width, height, n, 0);
+        let mut times = Vec::with_capacity(RUNS);
+        let mut triangles = 0;
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            let out = compute_delaunay(black_box(&seeds), width, height);
+            times.push(start.elapsed().as_secs_f64() * 1000.0);
+            triangles = out.len() / 3;
+        }
+        times.sort_by(f64::total_cmp);
+        println!("{n:>8} {triangles:>10} {:>12.2}", times[RUNS / 2]);
+    }
+}
@@ -12,6 +12,22 @@ if ! command -v wasm-pack &> /dev/null; then
     cargo install wasm-pack
 fi
 
+# `./build.sh --sizes` builds the core and each feature on its own (plus
+# everything) and prints the size of each .wasm, to help pick a feature set
+if [ "$1" = "--sizes" ]; then
+    echo "📊 WASM size per feature set:"
+    for features in "" noise motes voronoi image mesh3d sim svg \
+        "noise,motes,voronoi,image,mesh3d,sim,svg"; do
+        out="target/size-report/${features:-core}"
+        wasm-pack build --target web --release --out-dir "$out" -- \
+            --no-default-features --features "$features" > /dev/null 2>&1
+        size=$(stat -c%s "$out"/*.wasm 2>/dev/null | head -1 || echo 0)
+        printf "  %-44s %10s\n" "${features:-core}" \
+            "$(numfmt --to=iec-i --suffix=B "$size" 2>/dev/null || echo "${size} bytes")"
+    done
+    exit 0
+fi
+
 # Build for web target (ES modules)
 echo "📦 Building for web target..."
 wasm-pack build --target web --release
@@ -13,7 +13,15 @@ interface VoronoiWasm {
   generate_golden_seeds(width: number, height: number, count: number): Float64Array;
   compute_delaunay(points: Float64Array, width: number, height: number): Uint32Array;
   compute_edges(points: Float64Array, triangles: Uint32Array): Float64Array;
+  compute_edge_indices(triangles: Uint32Array): Uint32Array;
   simplex_noise_2d(x: number, y: number): number;
+  fbm_noise_2d_batch(
+    coords: Float64Array, octaves: number, lacunarity: number, gain: number, mode: number,
+  ): Float64Array;
+  animate_points(
+    points: Float64Array, time: number, amplitude: number, frequency: number,
+    bounds?: object,
+  ): Float64Array;
   update_motes(motes: Float64Array, edges: Float64Array, dt: number, time: number): Float64Array;
 }
 
@@ -0,0 +1,306 @@
+//! Mesh topology for JS-side smoothing, region growing and cell coloring
+//!
+//! Every query takes only the triangle list, so they apply equally to a
+//! full triangulation and to one filtered by `alpha_shape`.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_triangles, unique_edges, Csr};
+use crate::rng::Rng;
+
+/// Neighbouring triangle across each side, 3 entries per triangle
+///
+/// Entry `3 * t + k` is the triangle sharing side `(v_k, v_{k+1})` of
+/// triangle `t`, with `k + 1` taken mod 3, or -1 on the boundary. A side
+/// used by more than two triangles (a non-manifold mesh) also gets -1 in
+/// every one of them, since no single neighbour is right.
+#[wasm_bindgen]
+pub fn triangle_neighbors(triangles: &[u32]) -> Result<Vec<i32>> {
+    if !triangles.len().is_multiple_of(3) {
+        return Err(Error::InvalidLength(format!(
+            "triangle index count {} is not a multiple of 3",
+            triangles.len()
+        )));
+    }
+    // Side -> the slots `3 * t + k` using it; a third use marks it shared
+    const SHARED: u32 = u32::MAX - 1;
+    let mut sides: HashMap<(u32, u32), [u32; 2]> = HashMap::with_capacity(triangles.len());
+    let slots = triangles.chunks_exact(3).enumerate().flat_map(|(i, t)| {
+        (0..3).map(move |k| (i * 3 + k, (t[k], t[(k + 1) % 3])))
+    });
+    for (slot, (a, b)) in slots {
+        let uses = sides.entry((a.min(b), a.max(b))).or_insert([u32::MAX; 2]);
+        match uses {
+            [u32::MAX, _] => uses[0] = slot as u32,
+            [_, u32::MAX] => uses[1] = slot as u32,
+            _ => uses[1] = SHARED,
+        }
+    }
+    let mut neighbors = vec![-1i32; triangles.len()];
+    for [first, second] in sides.into_values() {
+        if second < SHARED {
+            neighbors[first as usize] = (second / 3) as i32;
+            neighbors[second as usize] = (first / 3) as i32;
+        }
+    }
+    Ok(neighbors)
+}
+
+/// Vertex neighbour lists from `vertex_neighbors`, in CSR form
+#[wasm_bindgen]
+pub struct VertexNeighbors {
+    offsets: Vec<u32>,
+    neighbors: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl VertexNeighbors {
+    /// `point_count + 1` entries; the neighbours of `v` are
+    /// `neighbors()[offsets[v]..offsets[v + 1]]`
+    pub fn offsets(&self) -> Vec<u32> {
+        self.offsets.clone()
+    }
+
+    /// All neighbour lists back to back, each in ascending order
+    pub fn neighbors(&self) -> Vec<u32> {
+        self.neighbors.clone()
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.offsets.len() - 1
+    }
+}
+
+/// Vertices joined to each vertex by a triangle side
+///
+/// Points used by no triangle get an empty list.
+#[wasm_bindgen]
+pub fn vertex_neighbors(triangles: &[u32], point_count: usize) -> Result<VertexNeighbors> {
+    check_triangles(triangles, point_count)?;
+    let Csr { offsets, targets } = Csr::from_edges(point_count, &unique_edges(triangles));
+    Ok(VertexNeighbors { offsets, neighbors: targets })
+}
+
+/// Voronoi cell adjacency: two seeds' cells share a side exactly when
+/// the seeds share a Delaunay edge, so this is `vertex_neighbors` under
+/// the name a cell-coloring caller looks for
+#[wasm_bindgen]
+pub fn cell_adjacency(triangles: &[u32], point_count: usize) -> Result<VertexNeighbors> {
+    vertex_neighbors(triangles, point_count)
+}
+
+/// Result of `greedy_color_cells`
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct CellColoring {
+    colors: Vec<u32>,
+    conflicts: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl CellColoring {
+    /// Color index in `0..num_colors` per seed
+    pub fn colors(&self) -> Vec<u32> {
+        self.colors.clone()
+    }
+
+    /// `[i0, j0, i1, j1, ...]` adjacent seeds left sharing a color, `i < j`
+    /// and sorted; empty whenever the palette was large enough
+    pub fn conflicts(&self) -> Vec<u32> {
+        self.conflicts.clone()
+    }
+
+    pub fn conflict_count(&self) -> usize {
+        self.conflicts.len() / 2
+    }
+}
+
+/// Min-conflict sweeps `greedy_color_cells` runs after the greedy pass
+const REPAIR_SWEEPS: usize = 8;
+
+/// Color each seed so that seeds sharing a Delaunay edge differ
+///
+/// Seeds are colored in smallest-last order, each taking the least used
+/// color its colored neighbours leave free, so the palette stays
+/// balanced. A planar graph always has a seed with at most five
+/// neighbours, so six colors never conflict; with fewer the greedy pass
+/// can get stuck, and a seed with no free color takes the one fewest
+/// neighbours share. A few min-conflict sweeps then recolor the seeds
+/// left in conflict, and whatever remains is reported. `seed` breaks the
+/// ties, so the same triangles and seed always give the same colors.
+#[wasm_bindgen]
+pub fn greedy_color_cells(
+    triangles: &[u32],
+    point_count: usize,
+    num_colors: u32,
+    seed: u32,
+) -> Result<CellColoring> {
+    check_triangles(triangles, point_count)?;
+    if num_colors == 0 {
+        return Err(Error::InvalidArgument("num_colors must be at least 1".into()));
+    }
+    let edges = unique_edges(triangles);
+    let csr = Csr::from_edges(point_count, &edges);
+    let mut rng = Rng::new(seed as u64);
+    let keys: Vec<u32> = (0..point_count).map(|_| rng.next_u32()).collect();
+
+    // Smallest-last: repeatedly remove the seed of least remaining degree
+    let mut degree: Vec<usize> = (0..point_count as u32).map(|v| csr.neighbors(v).len()).collect();
+    let entry = |v: u32, degree: &[usize]| Reverse((degree[v as usize], keys[v as usize], v));
+    let mut heap: BinaryHeap<_> = (0..point_count as u32).map(|v| entry(v, &degree)).collect();
+    let mut removed = vec![false; point_count];
+    let mut order = Vec::with_capacity(point_count);
+    while let Some(Reverse((d, _, v))) = heap.pop() {
+        if removed[v as usize] || d != degree[v as usize] {
+            continue;
+        }
+        removed[v as usize] = true;
+        order.push(v);
+        for &w in csr.neighbors(v) {
+            if !removed[w as usize] {
+                degree[w as usize] -= 1;
+                heap.push(entry(w, &degree));
+            }
+        }
+    }
+
+    const UNSET: u32 = u32::MAX;
+    let k = num_colors as usize;
+    let mut colors = vec![UNSET; point_count];
+    let mut usage = vec![0usize; k];
+    // Color for `v` sharing the fewest neighbours, then least used, then by
+    // a per-seed rotation so ties do not all fall on color 0
+    let pick = |v: u32, colors: &[u32], usage: &[usize]| {
+        let mut shared = vec![0usize; k];
+        for &w in csr.neighbors(v) {
+            if let Some(n) = shared.get_mut(colors[w as usize] as usize) {
+                *n += 1;
+            }
+        }
+        let rotation = keys[v as usize] as usize % k;
+        (0..k).min_by_key(|&c| (shared[c], usage[c], (c + k - rotation) % k)).unwrap_or(0) as u32
+    };
+    for &v in order.iter().rev() {
+        let c = pick(v, &colors, &usage);
+        colors[v as usize] = c;
+        usage[c as usize] += 1;
+    }
+    let clashes = |v: u32, c: u32, colors: &[u32]| {
+        csr.neighbors(v).iter().filter(|&&w| colors[w as usize] == c).count()
+    };
+    for _ in 0..REPAIR_SWEEPS {
+        let mut improved = false;
+        for v in 0..point_count as u32 {
+            let old = colors[v as usize];
+            if clashes(v, old, &colors) == 0 {
+                continue;
+            }
+            usage[old as usize] -= 1;
+            let new = pick(v, &colors, &usage);
+            improved |= clashes(v, new, &colors) < clashes(v, old, &colors);
+            colors[v as usize] = new;
+            usage[new as usize] += 1;
+        }
+        if !improved {
+            break;
+        }
+    }
+    let conflicts = edges
+        .iter()
+        .filter(|&&(a, b)| colors[a as usize] == colors[b as usize])
+        .flat_map(|&(a, b)| [a, b])
+        .collect();
+    Ok(CellColoring { colors, conflicts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::{compute_delaunay, generate_golden_seeds};
+
+    #[test]
+    fn test_adjacency_of_a_fan_and_a_mesh() {
+        // Three triangles around vertex 0, the last side left open
+        let fan = [0, 1, 2, 0, 2, 3, 0, 3, 4];
+        assert_eq!(triangle_neighbors(&fan).unwrap(), vec![-1, -1, 1, 0, -1, 2, 1, -1, -1]);
+        let around = vertex_neighbors(&fan, 6).unwrap();
+        assert_eq!(around.offsets(), vec![0, 4, 6, 9, 12, 14, 14]);
+        assert_eq!(&around.neighbors()[..4], &[1, 2, 3, 4]);
+
+        // A side in three triangles pairs with none of them
+        let fin = [0, 1, 2, 1, 0, 3, 0, 1, 4];
+        assert_eq!(triangle_neighbors(&fin).unwrap(), vec![-1; 9]);
+
+        let points = generate_golden_seeds(400.0, 300.0, 80);
+        let triangles = compute_delaunay(&points, 400.0, 300.0);
+        let across = triangle_neighbors(&triangles).unwrap();
+        for (slot, &n) in across.iter().enumerate() {
+            if n < 0 {
+                continue;
+            }
+            let (t, k) = (&triangles[slot / 3 * 3..][..3], slot % 3);
+            let (a, b) = (t[k], t[(k + 1) % 3]);
+            // Symmetric, and the neighbour holds the side reversed
+            let u = &triangles[n as usize * 3..][..3];
+            let k = (0..3).find(|&k| (u[k], u[(k + 1) % 3]) == (b, a)).unwrap();
+            assert_eq!(across[n as usize * 3 + k], (slot / 3) as i32);
+        }
+        let boundary = across.iter().filter(|&&n| n < 0).count();
+        assert_eq!(boundary, crate::compute_boundary_edges(&triangles).len() / 2);
+
+        assert_eq!(code_of(triangle_neighbors(&[0, 1])), ErrorCode::InvalidLength);
+        assert_eq!(code_of(vertex_neighbors(&[0, 1, 2], 2)), ErrorCode::IndexOutOfRange);
+    }
+
+    #[test]
+    fn test_six_colors_never_conflict() {
+        let points = generate_golden_seeds(800.0, 600.0, 500);
+        let triangles = compute_delaunay(&points, 800.0, 600.0);
+        let adjacency = cell_adjacency(&triangles, 500).unwrap();
+        assert_eq!(adjacency.neighbors(), vertex_neighbors(&triangles, 500).unwrap().neighbors());
+        for seed in 0..4 {
+            let coloring = greedy_color_cells(&triangles, 500, 6, seed).unwrap();
+            assert_eq!(coloring.conflict_count(), 0);
+            let colors = coloring.colors();
+            for (a, b) in unique_edges(&triangles) {
+                assert_ne!(colors[a as usize], colors[b as usize]);
+            }
+            // Least-used picks keep the palette balanced
+            for c in 0..6 {
+                let used = colors.iter().filter(|&&x| x == c).count();
+                assert!((60..=110).contains(&used), "color {c} used {used} times");
+            }
+        }
+        let a = greedy_color_cells(&triangles, 500, 4, 7).unwrap().colors();
+        assert_eq!(a, greedy_color_cells(&triangles, 500, 4, 7).unwrap().colors());
+        assert_ne!(a, greedy_color_cells(&triangles, 500, 4, 8).unwrap().colors());
+        assert!(a.iter().all(|&c| c < 4));
+    }
+
+    #[test]
+    fn test_too_few_colors_report_conflicts() {
+        let points = generate_golden_seeds(400.0, 300.0, 100);
+        let triangles = compute_delaunay(&points, 400.0, 300.0);
+        // Every triangle needs three colors, so two must clash somewhere
+        let coloring = greedy_color_cells(&triangles, 102, 2, 1).unwrap();
+        let colors = coloring.colors();
+        let clashing: Vec<u32> = unique_edges(&triangles)
+            .into_iter()
+            .filter(|&(a, b)| colors[a as usize] == colors[b as usize])
+            .flat_map(|(a, b)| [a, b])
+            .collect();
+        assert!(!clashing.is_empty());
+        assert_eq!(coloring.conflicts(), clashing);
+        assert_eq!(colors.len(), 102, "unused points get a color too");
+        let single = greedy_color_cells(&[], 3, 1, 0).unwrap();
+        assert_eq!((single.colors(), single.conflict_count()), (vec![0, 0, 0], 0));
+
+        assert_eq!(code_of(greedy_color_cells(&triangles, 100, 0, 0)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(greedy_color_cells(&triangles, 10, 3, 0)), ErrorCode::IndexOutOfRange);
+    }
+}
@@ -0,0 +1,164 @@
+//! Triangle filters for point clouds that are not convex
+//!
+//! A Delaunay triangulation always fills the convex hull, so a concave
+//! cloud gets long slivers bridging every bay. Both filters here drop
+//! triangles by size and keep the `compute_delaunay` layout, so the
+//! result feeds `compute_edges`, the motes and `compute_boundary_loops`
+//! unchanged.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles, edge_length};
+use crate::hull::boundary_loops;
+
+fn check_mesh(points_flat: &[f64], triangles: &[u32]) -> Result<()> {
+    let n = check_points(points_flat)?;
+    check_triangles(triangles, n)
+}
+
+fn keep_triangles(triangles: &[u32], keep: impl Fn(&[u32]) -> bool) -> Vec<u32> {
+    triangles.chunks_exact(3).filter(|t| keep(t)).flatten().copied().collect()
+}
+
+/// `triangles` without those having a side longer than `max_length`
+#[wasm_bindgen]
+pub fn filter_triangles_by_edge_length(
+    points_flat: &[f64],
+    triangles: &[u32],
+    max_length: f64,
+) -> Result<Vec<u32>> {
+    check_mesh(points_flat, triangles)?;
+    if max_length.is_nan() || max_length < 0.0 {
+        return Err(Error::InvalidArgument(format!(
+            "max_length must be non-negative, got {max_length}"
+        )));
+    }
+    Ok(keep_triangles(triangles, |t| {
+        (0..3).all(|k| edge_length(points_flat, t[k], t[(k + 1) % 3]) <= max_length)
+    }))
+}
+
+/// Output of `alpha_shape`
+#[wasm_bindgen]
+pub struct AlphaShape {
+    triangles: Vec<u32>,
+    loop_vertices: Vec<u32>,
+    loop_offsets: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl AlphaShape {
+    /// Kept triangles in the `compute_delaunay` layout
+    pub fn triangles(&self) -> Vec<u32> {
+        self.triangles.clone()
+    }
+
+    /// Outline of the kept triangles, as `BoundaryLoops::vertices`
+    pub fn loop_vertices(&self) -> Vec<u32> {
+        self.loop_vertices.clone()
+    }
+
+    /// Start of each loop in `loop_vertices()`, plus a final end entry
+    pub fn loop_offsets(&self) -> Vec<u32> {
+        self.loop_offsets.clone()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len() / 3
+    }
+
+    pub fn loop_count(&self) -> usize {
+        self.loop_offsets.len() - 1
+    }
+}
+
+/// Alpha shape of a Delaunay triangulation, with its outline
+///
+/// Keeps the triangles whose circumradius is at most `alpha`, so `alpha`
+/// is in canvas units: about the radius of the largest gap that should
+/// stay open. Thin slivers have large circumcircles and go first, even
+/// when their sides are short; collinear triangles are always dropped. The
+/// outline loops run CCW around the kept region and CW around holes, as
+/// in `compute_boundary_loops`.
+#[wasm_bindgen]
+pub fn alpha_shape(points_flat: &[f64], triangles: &[u32], alpha: f64) -> Result<AlphaShape> {
+    check_mesh(points_flat, triangles)?;
+    if alpha.is_nan() || alpha < 0.0 {
+        return Err(Error::InvalidArgument(format!("alpha must be non-negative, got {alpha}")));
+    }
+    let kept = keep_triangles(triangles, |t| {
+        let [a, b, c] = [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])]
+            .map(|(u, v)| edge_length(points_flat, u, v));
+        let xy = |v: u32| (points_flat[v as usize * 2], points_flat[v as usize * 2 + 1]);
+        let [(ax, ay), (bx, by), (cx, cy)] = [xy(t[0]), xy(t[1]), xy(t[2])];
+        let area2 = ((bx - ax) * (cy - ay) - (by - ay) * (cx - ax)).abs();
+        // R = abc / 4A, compared without dividing
+        area2 > 0.0 && a * b * c <= 2.0 * alpha * area2
+    });
+    let (loop_vertices, loop_offsets) = boundary_loops(&kept);
+    Ok(AlphaShape { triangles: kept, loop_vertices, loop_offsets })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::{compute_delaunay, compute_hull, generate_jittered_grid};
+
+    /// A jittered grid with the top middle cut out, leaving a U
+    fn u_shape() -> Vec<f64> {
+        let grid = generate_jittered_grid(300.0, 300.0, 15, 15, 0.3, 2).unwrap();
+        let bay = |p: &[f64]| (100.0..200.0).contains(&p[0]) && p[1] > 100.0;
+        grid.chunks(2).filter(|p| !bay(p)).flatten().copied().collect()
+    }
+
+    #[test]
+    fn test_filters_open_the_concavity() {
+        let points = u_shape();
+        let triangles = compute_delaunay(&points, 300.0, 300.0);
+        let spans = |t: &[u32]| {
+            t.chunks(3).any(|t| {
+                let xs = t.iter().map(|&v| points[v as usize * 2]);
+                let (lo, hi) = xs.fold((f64::MAX, f64::MIN), |(l, h), x| (l.min(x), h.max(x)));
+                lo < 100.0 && hi > 200.0 && t.iter().all(|&v| points[v as usize * 2 + 1] > 110.0)
+            })
+        };
+        assert!(spans(&triangles), "the hull should bridge the bay");
+
+        let short = filter_triangles_by_edge_length(&points, &triangles, 40.0).unwrap();
+        assert!(!spans(&short) && short.len() < triangles.len());
+        assert!(short.len() / 3 > triangles.len() / 4);
+        let all = filter_triangles_by_edge_length(&points, &triangles, f64::INFINITY).unwrap();
+        assert_eq!(all, triangles);
+
+        let shape = alpha_shape(&points, &triangles, 25.0).unwrap();
+        let kept = shape.triangles();
+        assert!(!spans(&kept));
+        assert!(kept.chunks(3).all(|t| triangles.chunks(3).any(|u| u == t)));
+        // One outline, now reaching into the bay where the hull does not
+        assert_eq!(shape.loop_count(), 1);
+        let outline = shape.loop_vertices();
+        assert!(outline.len() > compute_hull(&points).unwrap().len());
+        assert!(outline.iter().any(|&v| {
+            let (x, y) = (points[v as usize * 2], points[v as usize * 2 + 1]);
+            (110.0..190.0).contains(&x) && (90.0..120.0).contains(&y)
+        }));
+    }
+
+    #[test]
+    fn test_filters_reject_bad_input() {
+        let points = [0.0, 0.0, 10.0, 0.0, 0.0, 10.0, 20.0, 0.0];
+        let flat = [0, 1, 3];
+        assert!(alpha_shape(&points, &flat, 1e9).unwrap().triangles().is_empty());
+        let shape = alpha_shape(&points, &[0, 1, 2], 10.0).unwrap();
+        assert_eq!((shape.triangle_count(), shape.loop_offsets()), (1, vec![0, 3]));
+        assert_eq!(alpha_shape(&points, &[0, 1, 2], 7.0).unwrap().loop_count(), 0);
+
+        let edge = |t: &[u32], l| code_of(filter_triangles_by_edge_length(&points, t, l));
+        assert_eq!(edge(&[0, 1], 1.0), ErrorCode::InvalidLength);
+        assert_eq!(edge(&[0, 1, 9], 1.0), ErrorCode::IndexOutOfRange);
+        assert_eq!(edge(&[0, 1, 2], f64::NAN), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(alpha_shape(&points, &[0, 1, 2], -1.0)), ErrorCode::InvalidArgument);
+    }
+}
@@ -0,0 +1,219 @@
+//! Seeds pushed out from a centre by an audio spectrum
+//!
+//! Each seed reads the spectrum at its angle around the centre, bins
+//! wrapping around so the first and last meet at angle 0, and moves
+//! along the ray from the centre by `amount` times the interpolated bin.
+//! `RadialSpectrum` keeps the angles and last frame's displacements, so
+//! a frame is one call that can go straight to `compute_delaunay` or
+//! `DynamicTriangulation::update_points`.
+
+use std::f64::consts::TAU;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::Point;
+
+/// Where a seed reads the spectrum: the unit ray from the centre, zero
+/// for a seed on the centre, and its angle as a fraction of a turn
+fn ray(p: Point, center: Point) -> (Point, f64) {
+    let (dx, dy) = (p.x - center.x, p.y - center.y);
+    let len = dx.hypot(dy);
+    if len == 0.0 {
+        return (Point::new(0.0, 0.0), 0.0);
+    }
+    (Point::new(dx / len, dy / len), dy.atan2(dx).rem_euclid(TAU) / TAU)
+}
+
+/// The spectrum at `turn` of the way round, interpolated between bins
+fn sample(spectrum: &[f32], turn: f64) -> f64 {
+    let u = turn * spectrum.len() as f64;
+    let i = (u.floor() as usize).min(spectrum.len() - 1);
+    let t = u - i as f64;
+    let (a, b) = (spectrum[i] as f64, spectrum[(i + 1) % spectrum.len()] as f64);
+    a + (b - a) * t
+}
+
+fn check_frame(spectrum: &[f32], amount: f64, smoothing: f64) -> Result<()> {
+    if spectrum.is_empty() {
+        return Err(Error::InvalidLength("spectrum must have at least one bin".into()));
+    }
+    if spectrum.iter().any(|v| !v.is_finite()) || !amount.is_finite() {
+        return Err(Error::NonFiniteInput("spectrum and amount must be finite".into()));
+    }
+    if !(0.0..=1.0).contains(&smoothing) {
+        return Err(Error::InvalidArgument(format!(
+            "smoothing must be in [0, 1], got {smoothing}"
+        )));
+    }
+    Ok(())
+}
+
+fn check_finite_points(points_flat: &[f64]) -> Result<usize> {
+    let n = check_points(points_flat)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    Ok(n)
+}
+
+/// Base seeds displaced along their rays by a spectrum, frame after frame
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct RadialSpectrum {
+    base: Vec<Point>,
+    rays: Vec<(Point, f64)>,
+    /// Displacement of each seed after the last `apply`
+    offsets: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl RadialSpectrum {
+    /// Rays from `(center_x, center_y)` through every base seed, all at
+    /// rest
+    #[wasm_bindgen(constructor)]
+    pub fn new(base_points_flat: &[f64], center_x: f64, center_y: f64) -> Result<RadialSpectrum> {
+        let n = check_finite_points(base_points_flat)?;
+        if !(center_x.is_finite() && center_y.is_finite()) {
+            return Err(Error::NonFiniteInput("center must be finite".into()));
+        }
+        let center = Point::new(center_x, center_y);
+        let base: Vec<Point> =
+            base_points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+        let rays = base.iter().map(|&p| ray(p, center)).collect();
+        Ok(RadialSpectrum { base, rays, offsets: vec![0.0; n] })
+    }
+
+    /// Seeds as `[x0, y0, x1, y1, ...]` for this frame's `spectrum`
+    ///
+    /// The target displacement is `amount` times the spectrum at the
+    /// seed's angle; `smoothing` in `[0, 1]` is how much of last frame's
+    /// displacement is kept, 0 following the spectrum exactly and 1
+    /// freezing the seeds. Negative bins or `amount` pull seeds inward.
+    pub fn apply(&mut self, spectrum: &[f32], amount: f64, smoothing: f64) -> Result<Vec<f64>> {
+        check_frame(spectrum, amount, smoothing)?;
+        let mut out = Vec::with_capacity(self.base.len() * 2);
+        for ((p, &(dir, turn)), d) in self.base.iter().zip(&self.rays).zip(&mut self.offsets) {
+            let target = amount * sample(spectrum, turn);
+            *d = smoothing * *d + (1.0 - smoothing) * target;
+            out.extend([p.x + dir.x * *d, p.y + dir.y * *d]);
+        }
+        Ok(out)
+    }
+
+    /// Put every seed back at its base position
+    pub fn reset(&mut self) {
+        self.offsets.fill(0.0);
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.base.len()
+    }
+}
+
+/// One `RadialSpectrum::apply` frame without the state
+///
+/// `points_flat` is the previous output, or the base seeds on the first
+/// frame, and stands in for the kept displacement: the result is
+/// `smoothing * points + (1 - smoothing) * target`, which matches the
+/// struct as long as every frame is fed back. The struct saves working
+/// out every seed's angle and passing the last frame back each time.
+#[wasm_bindgen]
+pub fn apply_radial_spectrum(
+    points_flat: &[f64],
+    base_points_flat: &[f64],
+    center_x: f64,
+    center_y: f64,
+    spectrum: &[f32],
+    amount: f64,
+    smoothing: f64,
+) -> Result<Vec<f64>> {
+    let n = check_finite_points(base_points_flat)?;
+    if check_finite_points(points_flat)? != n {
+        return Err(Error::InvalidLength(format!(
+            "{} points given for {n} base points",
+            points_flat.len() / 2
+        )));
+    }
+    check_frame(spectrum, amount, smoothing)?;
+    let mut spectra = RadialSpectrum::new(base_points_flat, center_x, center_y)?;
+    let target = spectra.apply(spectrum, amount, 0.0)?;
+    let blend = |(p, t): (&f64, &f64)| smoothing * p + (1.0 - smoothing) * t;
+    Ok(points_flat.iter().zip(&target).map(blend).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::generate_golden_seeds;
+
+    #[test]
+    fn test_spectrum_displaces_along_rays() {
+        // Seeds at angles 0, 1/4, 1/2 and 3/4 of a turn, 10 from the centre
+        let base = [60.0, 50.0, 50.0, 60.0, 40.0, 50.0, 50.0, 40.0, 50.0, 50.0];
+        let mut spectra = RadialSpectrum::new(&base, 50.0, 50.0).unwrap();
+        // Two bins: 0 at angle 0 and 1 at half a turn, wrapping back to 0
+        let out = spectra.apply(&[0.0, 1.0], 4.0, 0.0).unwrap();
+        let expected = [60.0, 50.0, 50.0, 62.0, 36.0, 50.0, 50.0, 38.0, 50.0, 50.0];
+        for (o, e) in out.iter().zip(expected) {
+            assert!((o - e).abs() < 1e-12, "{out:?}");
+        }
+
+        // A flat spectrum keeps a golden spiral's seeds on their rays; the
+        // first one sits on the centre and stays
+        let seeds = generate_golden_seeds(400.0, 400.0, 200);
+        let mut spectra = RadialSpectrum::new(&seeds, 200.0, 200.0).unwrap();
+        let moved = spectra.apply(&[0.5; 64], 6.0, 0.0).unwrap();
+        assert_eq!(moved[..2], seeds[..2]);
+        for (p, q) in seeds.chunks(2).zip(moved.chunks(2)).skip(1) {
+            let (r0, r1) = ((p[0] - 200.0).hypot(p[1] - 200.0), (q[0] - 200.0).hypot(q[1] - 200.0));
+            let cross = (p[0] - 200.0) * (q[1] - 200.0) - (p[1] - 200.0) * (q[0] - 200.0);
+            assert!((r1 - r0 - 3.0).abs() < 1e-9 && cross.abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_smoothing_matches_stateless_frames() {
+        let base = generate_golden_seeds(300.0, 200.0, 50);
+        let mut spectra = RadialSpectrum::new(&base, 150.0, 100.0).unwrap();
+        let mut points = base.clone();
+        let bins: Vec<f32> = (0..64).map(|k| (k as f32 * 0.3).sin().abs()).collect();
+        for frame in 0..5 {
+            let amount = 5.0 + frame as f64;
+            let stateful = spectra.apply(&bins, amount, 0.6).unwrap();
+            points = apply_radial_spectrum(&points, &base, 150.0, 100.0, &bins, amount, 0.6)
+                .unwrap();
+            assert!(stateful.iter().zip(&points).all(|(a, b)| (a - b).abs() < 1e-9));
+        }
+        // Constant input converges towards the target, 60% kept each frame
+        let mut spectra = RadialSpectrum::new(&[20.0, 10.0], 10.0, 10.0).unwrap();
+        assert_eq!(spectra.apply(&[1.0], 10.0, 0.6).unwrap(), vec![24.0, 10.0]);
+        assert_eq!(spectra.apply(&[1.0], 10.0, 0.6).unwrap(), vec![26.4, 10.0]);
+        assert_eq!(spectra.apply(&[1.0], 10.0, 1.0).unwrap(), vec![26.4, 10.0]);
+        spectra.reset();
+        assert_eq!(spectra.apply(&[0.0], 10.0, 0.0).unwrap(), vec![20.0, 10.0]);
+        // A seed on the centre has nowhere to go
+        let mut centred = RadialSpectrum::new(&[10.0, 10.0], 10.0, 10.0).unwrap();
+        assert_eq!(centred.apply(&[1.0], 10.0, 0.0).unwrap(), vec![10.0, 10.0]);
+    }
+
+    #[test]
+    fn test_spectrum_errors() {
+        let base = [1.0, 2.0, 3.0, 4.0];
+        let mut spectra = RadialSpectrum::new(&base, 0.0, 0.0).unwrap();
+        assert_eq!(spectra.point_count(), 2);
+        assert_eq!(code_of(spectra.apply(&[], 1.0, 0.0)), ErrorCode::InvalidLength);
+        assert_eq!(code_of(spectra.apply(&[f32::NAN], 1.0, 0.0)), ErrorCode::NonFiniteInput);
+        assert_eq!(code_of(spectra.apply(&[1.0], f64::INFINITY, 0.0)), ErrorCode::NonFiniteInput);
+        assert_eq!(code_of(spectra.apply(&[1.0], 1.0, 1.5)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(RadialSpectrum::new(&base[..3], 0.0, 0.0)), ErrorCode::InvalidLength);
+        assert_eq!(code_of(RadialSpectrum::new(&base, f64::NAN, 0.0)), ErrorCode::NonFiniteInput);
+        let stateless = |points: &[f64]| {
+            code_of(apply_radial_spectrum(points, &base, 0.0, 0.0, &[1.0], 1.0, 0.5))
+        };
+        assert_eq!(stateless(&base[..2]), ErrorCode::InvalidLength);
+        assert_eq!(stateless(&[f64::NAN, 0.0, 0.0, 0.0]), ErrorCode::NonFiniteInput);
+    }
+}
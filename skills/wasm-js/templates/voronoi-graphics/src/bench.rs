@@ -0,0 +1,165 @@
+//! Fixed-workload throughput probe for picking effect tiers at runtime
+//!
+//! Workload, per profile (`seeds` points, `motes` motes):
+//!
+//! | profile   | seeds  | motes  |
+//! |-----------|--------|--------|
+//! | 0 `light` |  5 000 |  1 000 |
+//! | 1 `heavy` | 50 000 | 10 000 |
+//!
+//! 1. `seeds`: `generate_golden_seeds` on a 1920×1080 canvas
+//! 2. `triangulate`: incremental Delaunay of those seeds
+//! 3. `edges`: unique edges as flat `[x0, y0, x1, y1, ...]` coordinates
+//! 4. `motes`: 60 `update_motes` frames at `dt = 1/60`, motes placed on
+//!    edges by the crate RNG with a fixed seed
+//!
+//! Inputs are deterministic, so any change in the timings comes from the
+//! device or the library version. Every buffer is dropped before the
+//! report is returned; WASM linear memory never shrinks, but a repeated
+//! run must not grow it further, which `memory_before` / `memory_after`
+//! let callers check.
+
+use wasm_bindgen::prelude::*;
+
+use crate::dynamic::DynamicTriangulation;
+use crate::error::{Error, Result};
+use crate::rng::Rng;
+use crate::timing::now_ms;
+use crate::{generate_golden_seeds, update_motes};
+
+const CANVAS: (f64, f64) = (1920.0, 1080.0);
+const MOTE_FRAMES: u32 = 60;
+const MOTE_SEED: u64 = 0x5EED;
+
+/// Size of linear memory in bytes; 0 outside WASM
+fn memory_bytes() -> f64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        (core::arch::wasm32::memory_size(0) * 65536) as f64
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        0.0
+    }
+}
+
+/// Wall time per phase of `run_benchmark`, in milliseconds
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct BenchmarkReport {
+    pub profile: u32,
+    pub seed_count: u32,
+    pub mote_count: u32,
+    pub seeds_ms: f64,
+    pub triangulate_ms: f64,
+    pub edges_ms: f64,
+    pub motes_ms: f64,
+    /// Thousands of seeds pushed through the whole pipeline per second;
+    /// higher is faster, and profiles are roughly comparable
+    pub score: f64,
+    /// Triangles in the workload mesh, as a sanity check across runs
+    pub triangle_count: u32,
+    pub memory_before: f64,
+    pub memory_after: f64,
+}
+
+#[wasm_bindgen]
+impl BenchmarkReport {
+    pub fn total_ms(&self) -> f64 {
+        self.seeds_ms + self.triangulate_ms + self.edges_ms + self.motes_ms
+    }
+}
+
+/// Run the documented workload for `profile` (0 light, 1 heavy)
+#[wasm_bindgen]
+pub fn run_benchmark(profile: u32) -> Result<BenchmarkReport> {
+    let (seed_count, mote_count) = match profile {
+        0 => (5_000u32, 1_000u32),
+        1 => (50_000, 10_000),
+        _ => {
+            return Err(Error::InvalidArgument(format!(
+                "unknown benchmark profile {profile}, expected 0 or 1"
+            )))
+        }
+    };
+    let memory_before = memory_bytes();
+    let mut report = BenchmarkReport {
+        profile,
+        seed_count,
+        mote_count,
+        seeds_ms: 0.0,
+        triangulate_ms: 0.0,
+        edges_ms: 0.0,
+        motes_ms: 0.0,
+        score: 0.0,
+        triangle_count: 0,
+        memory_before,
+        memory_after: memory_before,
+    };
+    run_workload(&mut report)?;
+    report.memory_after = memory_bytes();
+    let total = report.total_ms().max(1e-3);
+    report.score = seed_count as f64 / total;
+    Ok(report)
+}
+
+/// All workload buffers live and die inside this call
+fn run_workload(report: &mut BenchmarkReport) -> Result<()> {
+    let (width, height) = CANVAS;
+
+    let t = now_ms();
+    let seeds = generate_golden_seeds(width, height, report.seed_count as usize);
+    report.seeds_ms = now_ms() - t;
+
+    let t = now_ms();
+    let tri = DynamicTriangulation::new(&seeds, width, height)?;
+    let triangles = tri.triangles();
+    report.triangulate_ms = now_ms() - t;
+    report.triangle_count = (triangles.len() / 3) as u32;
+
+    let t = now_ms();
+    let indexed = tri.edges();
+    let edges: Vec<f64> = indexed
+        .iter()
+        .flat_map(|&v| [seeds[v as usize * 2], seeds[v as usize * 2 + 1]])
+        .collect();
+    report.edges_ms = now_ms() - t;
+
+    let edge_count = (edges.len() / 4) as u32;
+    let mut rng = Rng::new(MOTE_SEED);
+    let mut motes: Vec<f64> = (0..report.mote_count)
+        .flat_map(|_| {
+            let edge = rng.below(edge_count.max(1)) as f64;
+            [edge, rng.next_f64(), rng.range(0.2, 0.6)]
+        })
+        .collect();
+    let t = now_ms();
+    let dt = 1.0 / MOTE_FRAMES as f64;
+    for frame in 0..MOTE_FRAMES {
+        let positions = update_motes(&mut motes, &edges, dt, frame as f64 * dt)?;
+        std::hint::black_box(positions);
+    }
+    report.motes_ms = now_ms() - t;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_light_profile_is_deterministic() {
+        let a = run_benchmark(0).unwrap();
+        let b = run_benchmark(0).unwrap();
+        assert_eq!((a.seed_count, a.mote_count), (5_000, 1_000));
+        assert!(a.triangle_count > 9_000);
+        assert_eq!(a.triangle_count, b.triangle_count);
+        for r in [&a, &b] {
+            let phases = [r.seeds_ms, r.triangulate_ms, r.edges_ms, r.motes_ms];
+            assert!(phases.iter().all(|&ms| ms >= 0.0 && ms.is_finite()));
+            assert!(r.score > 0.0);
+        }
+        assert_eq!(code_of(run_benchmark(2)), ErrorCode::InvalidArgument);
+    }
+}
@@ -0,0 +1,541 @@
+//! Typed-array plumbing: precision conversion, (de)interleaving and
+//! ready-to-draw mesh buffers
+//!
+//! Every function allocates its output once at the exact final size.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles};
+use crate::mesh::hilbert_order;
+use crate::{Point, Triangulation};
+
+/// Most points whose indices fit `u16` element buffers; WebGL 2 always
+/// treats index 65535 as a primitive restart, so that value is unusable
+pub const MAX_U16_POINTS: usize = u16::MAX as usize;
+
+/// `indices` narrowed for `UNSIGNED_SHORT` element buffers, or an error
+/// naming the first index at or past `MAX_U16_POINTS`
+pub(crate) fn narrow_u16(indices: &[u32]) -> Result<Vec<u16>> {
+    if let Some(&v) = indices.iter().find(|&&v| v as usize >= MAX_U16_POINTS) {
+        return Err(Error::TooLarge(format!(
+            "index {v} does not fit u16 element buffers, which reach {}; \
+             use 32-bit indices or split_mesh_for_u16",
+            MAX_U16_POINTS - 1
+        )));
+    }
+    Ok(indices.iter().map(|&v| v as u16).collect())
+}
+
+/// Error unless a caller-provided `out` buffer of `available` values can
+/// take the `needed` values an `_into` export writes
+pub(crate) fn check_out_len(needed: usize, available: usize) -> Result<()> {
+    if available < needed {
+        return Err(Error::InvalidLength(format!(
+            "output buffer holds {available} values but {needed} are needed"
+        )));
+    }
+    Ok(())
+}
+
+/// Narrow to `f32` for GPU upload
+#[wasm_bindgen]
+pub fn to_f32(values: &[f64]) -> Vec<f32> {
+    values.iter().map(|&v| v as f32).collect()
+}
+
+/// `[x0, y0, x1, y1, ...]` from separate `xs` and `ys`
+#[wasm_bindgen]
+pub fn interleave2(xs: &[f64], ys: &[f64]) -> Result<Vec<f64>> {
+    if xs.len() != ys.len() {
+        return Err(Error::InvalidLength(format!(
+            "interleaving {} xs with {} ys",
+            xs.len(),
+            ys.len()
+        )));
+    }
+    let mut out = Vec::with_capacity(xs.len() * 2);
+    for (&x, &y) in xs.iter().zip(ys) {
+        out.push(x);
+        out.push(y);
+    }
+    Ok(out)
+}
+
+/// Planar `[x0, x1, ..., y0, y1, ...]` from `[x0, y0, x1, y1, ...]`
+///
+/// The first half is the xs and the second the ys, so JS can take both
+/// with `subarray` without copying again.
+#[wasm_bindgen]
+pub fn deinterleave2(pairs: &[f64]) -> Result<Vec<f64>> {
+    if !pairs.len().is_multiple_of(2) {
+        return Err(Error::InvalidLength(format!("pair array length {} is odd", pairs.len())));
+    }
+    let mut out = Vec::with_capacity(pairs.len());
+    out.extend(pairs.iter().step_by(2));
+    out.extend(pairs.iter().skip(1).step_by(2));
+    Ok(out)
+}
+
+/// `[x0, y0, x1, y1, ..., z0, z1, ...]` from `[x0, y0, z0, ...]`
+///
+/// The first `2n` values are the xy pairs and the last `n` the zs.
+#[wasm_bindgen]
+pub fn split_xyz(xyz: &[f64]) -> Result<Vec<f64>> {
+    if !xyz.len().is_multiple_of(3) {
+        return Err(Error::InvalidLength(format!(
+            "xyz array length {} is not a multiple of 3",
+            xyz.len()
+        )));
+    }
+    let mut out = Vec::with_capacity(xyz.len());
+    for p in xyz.chunks_exact(3) {
+        out.push(p[0]);
+        out.push(p[1]);
+    }
+    out.extend(xyz.iter().skip(2).step_by(3));
+    Ok(out)
+}
+
+/// Interleave per-vertex attributes into one vertex buffer
+///
+/// Attribute `k` holds `strides[k]` floats per vertex; every attribute
+/// must describe the same number of vertices. The output has
+/// `sum(strides)` floats per vertex, attributes in the given order.
+pub fn pack_interleaved(attrs: &[&[f32]], strides: &[u32]) -> Result<Vec<f32>> {
+    if attrs.len() != strides.len() {
+        return Err(Error::InvalidLength(format!(
+            "{} attributes but {} strides",
+            attrs.len(),
+            strides.len()
+        )));
+    }
+    let mut vertices = None;
+    for (k, (data, &stride)) in attrs.iter().zip(strides).enumerate() {
+        let n = attribute_vertices(k, data, stride)?;
+        check_vertices(k, n, *vertices.get_or_insert(n))?;
+    }
+    let vertices = vertices.unwrap_or(0);
+    let total: usize = strides.iter().map(|&s| s as usize).sum();
+    let mut out = Vec::with_capacity(vertices * total);
+    for v in 0..vertices {
+        for (data, &stride) in attrs.iter().zip(strides) {
+            let s = stride as usize;
+            out.extend_from_slice(&data[v * s..(v + 1) * s]);
+        }
+    }
+    Ok(out)
+}
+
+fn attribute_vertices(k: usize, data: &[f32], stride: u32) -> Result<usize> {
+    if stride == 0 || !data.len().is_multiple_of(stride as usize) {
+        return Err(Error::InvalidLength(format!(
+            "attribute {k} has {} floats, not a multiple of stride {stride}",
+            data.len()
+        )));
+    }
+    Ok(data.len() / stride as usize)
+}
+
+fn check_vertices(k: usize, n: usize, expected: usize) -> Result<()> {
+    if n != expected {
+        return Err(Error::InvalidLength(format!(
+            "attribute {k} has {n} vertices, expected {expected}"
+        )));
+    }
+    Ok(())
+}
+
+/// JS-side builder for `pack_interleaved`
+///
+/// ```js
+/// const b = new VertexBufferBuilder();
+/// b.add(positions, 2); b.add(colors, 4);
+/// const buffer = b.build();   // 6 floats per vertex
+/// ```
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct VertexBufferBuilder {
+    attrs: Vec<Vec<f32>>,
+    strides: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl VertexBufferBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> VertexBufferBuilder {
+        VertexBufferBuilder::default()
+    }
+
+    /// Append an attribute of `stride` floats per vertex
+    ///
+    /// Checked against the attributes added so far, so a mismatch is
+    /// reported at the offending call.
+    pub fn add(&mut self, data: &[f32], stride: u32) -> Result<()> {
+        let k = self.attrs.len();
+        let n = attribute_vertices(k, data, stride)?;
+        if k > 0 {
+            check_vertices(k, n, self.vertex_count())?;
+        }
+        self.attrs.push(data.to_vec());
+        self.strides.push(stride);
+        Ok(())
+    }
+
+    /// Floats per vertex across all attributes added so far
+    pub fn vertex_stride(&self) -> u32 {
+        self.strides.iter().sum()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        match (self.attrs.first(), self.strides.first()) {
+            (Some(a), Some(&s)) => a.len() / s as usize,
+            _ => 0,
+        }
+    }
+
+    /// The interleaved buffer; the builder keeps its attributes
+    pub fn build(&self) -> Vec<f32> {
+        let attrs: Vec<&[f32]> = self.attrs.iter().map(|a| a.as_slice()).collect();
+        // Shapes were checked as each attribute was added
+        pack_interleaved(&attrs, &self.strides).unwrap_or_default()
+    }
+}
+
+/// Vertex and element buffers from `build_mesh_buffers`
+#[wasm_bindgen]
+pub struct MeshBuffers {
+    positions: Vec<f32>,
+    triangles: Vec<u32>,
+    edges: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl MeshBuffers {
+    /// `[x0, y0, x1, y1, ...]` for a 2-component position attribute
+    pub fn positions_f32(&self) -> Vec<f32> {
+        self.positions.clone()
+    }
+
+    /// Triangle indices, 3 per triangle, for `gl.TRIANGLES`
+    pub fn indices_u32(&self) -> Vec<u32> {
+        self.triangles.clone()
+    }
+
+    /// `indices_u32()` narrowed for `UNSIGNED_SHORT` element buffers;
+    /// errors unless `fits_u16()`
+    pub fn indices_u16(&self) -> Result<Vec<u16>> {
+        self.narrow(&self.triangles)
+    }
+
+    /// Unique edges as index pairs for `gl.LINES`, in
+    /// `compute_edge_indices` order
+    pub fn edge_indices_u32(&self) -> Vec<u32> {
+        self.edges.clone()
+    }
+
+    /// `edge_indices_u32()` narrowed like `indices_u16()`
+    pub fn edge_indices_u16(&self) -> Result<Vec<u16>> {
+        self.narrow(&self.edges)
+    }
+
+    /// Whether the `_u16` getters are available, i.e. there are at most
+    /// `MAX_U16_POINTS` points
+    pub fn fits_u16(&self) -> bool {
+        self.point_count() <= MAX_U16_POINTS
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.positions.len() / 2
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len() / 3
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len() / 2
+    }
+}
+
+impl MeshBuffers {
+    fn narrow(&self, indices: &[u32]) -> Result<Vec<u16>> {
+        if !self.fits_u16() {
+            return Err(Error::TooLarge(format!(
+                "{} points need 32-bit indices, u16 holds at most {MAX_U16_POINTS}",
+                self.point_count()
+            )));
+        }
+        narrow_u16(indices)
+    }
+}
+
+/// Triangulate `points_flat` once and return everything needed to draw
+/// it with WebGL: positions, triangle indices and edge indices
+///
+/// The triangles and edges are those of `compute_delaunay` and
+/// `compute_edge_indices` for the same canvas. Errors on non-finite
+/// input rather than returning an empty mesh.
+#[wasm_bindgen]
+pub fn build_mesh_buffers(points_flat: &[f64], _width: f64, _height: f64) -> Result<MeshBuffers> {
+    check_points(points_flat)?;
+    let points: Vec<Point> = points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+    let tri = Triangulation::new(&points)?;
+    let mut triangles = Vec::with_capacity(tri.triangle_count() * 3);
+    triangles.extend(tri.triangles().flat_map(|t| t.vertices().map(|v| v as u32)));
+    let mut edges = Vec::with_capacity(tri.edges().len() * 2);
+    edges.extend(tri.edges().flat_map(|(a, b)| [a as u32, b as u32]));
+    Ok(MeshBuffers { positions: to_f32(points_flat), triangles, edges })
+}
+
+/// A mesh cut into pieces small enough for `u16` indices, from
+/// `split_mesh_for_u16`
+///
+/// Chunk `k` is drawn from `positions_f32(k)` and `indices_u16(k)`;
+/// `source_vertices(k)` and `source_triangles(k)` map its vertices and
+/// triangles back to the input, so other attributes can be gathered to
+/// match.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct MeshChunks {
+    positions: Vec<f32>,
+    vertices: Vec<u32>,
+    vertex_offsets: Vec<u32>,
+    indices: Vec<u16>,
+    triangles: Vec<u32>,
+    triangle_offsets: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl MeshChunks {
+    /// `[x0, y0, x1, y1, ...]` of chunk `k`'s vertices
+    pub fn positions_f32(&self, k: usize) -> Result<Vec<f32>> {
+        let r = self.span(&self.vertex_offsets, k)?;
+        Ok(self.positions[r.start * 2..r.end * 2].to_vec())
+    }
+
+    /// Chunk `k`'s triangles, 3 indices each into its own vertices
+    pub fn indices_u16(&self, k: usize) -> Result<Vec<u16>> {
+        let r = self.span(&self.triangle_offsets, k)?;
+        Ok(self.indices[r.start * 3..r.end * 3].to_vec())
+    }
+
+    /// Input point of each of chunk `k`'s vertices
+    pub fn source_vertices(&self, k: usize) -> Result<Vec<u32>> {
+        Ok(self.vertices[self.span(&self.vertex_offsets, k)?].to_vec())
+    }
+
+    /// Input triangle of each of chunk `k`'s triangles
+    pub fn source_triangles(&self, k: usize) -> Result<Vec<u32>> {
+        Ok(self.triangles[self.span(&self.triangle_offsets, k)?].to_vec())
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.vertex_offsets.len() - 1
+    }
+}
+
+impl MeshChunks {
+    fn span(&self, offsets: &[u32], k: usize) -> Result<std::ops::Range<usize>> {
+        if k >= self.chunk_count() {
+            return Err(Error::IndexOutOfRange(format!(
+                "chunk {k} out of range for {} chunks",
+                self.chunk_count()
+            )));
+        }
+        Ok(offsets[k] as usize..offsets[k + 1] as usize)
+    }
+}
+
+/// Cut the mesh `points_flat` + `triangles` into chunks of at most
+/// `max_vertices` vertices each, for WebGL 1's `u16` element buffers
+///
+/// Triangles are taken along a Hilbert curve through their centroids and
+/// a chunk is closed once the next triangle would bring in too many
+/// vertices, so chunks are compact patches and few vertices on their
+/// borders are repeated in a neighbour. `max_vertices` is at most
+/// `MAX_U16_POINTS`; points no triangle uses appear in no chunk.
+#[wasm_bindgen]
+pub fn split_mesh_for_u16(
+    points_flat: &[f64],
+    triangles: &[u32],
+    max_vertices: u32,
+) -> Result<MeshChunks> {
+    let n = check_points(points_flat)?;
+    check_triangles(triangles, n)?;
+    let limit = max_vertices as usize;
+    if !(3..=MAX_U16_POINTS).contains(&limit) {
+        return Err(Error::InvalidArgument(format!(
+            "max_vertices must be in 3..={MAX_U16_POINTS}, got {max_vertices}"
+        )));
+    }
+    let at = |v: u32| Point::new(points_flat[v as usize * 2], points_flat[v as usize * 2 + 1]);
+    let centroids: Vec<Point> = triangles
+        .chunks_exact(3)
+        .map(|t| {
+            let (a, b, c) = (at(t[0]), at(t[1]), at(t[2]));
+            Point::new((a.x + b.x + c.x) / 3.0, (a.y + b.y + c.y) / 3.0)
+        })
+        .collect();
+
+    let mut chunks = MeshChunks {
+        positions: Vec::new(),
+        vertices: Vec::new(),
+        vertex_offsets: vec![0],
+        indices: Vec::with_capacity(triangles.len()),
+        triangles: Vec::with_capacity(centroids.len()),
+        triangle_offsets: vec![0],
+    };
+    // Index of each point in the open chunk, or `u32::MAX` outside it
+    let mut local = vec![u32::MAX; n];
+    let mut start = 0;
+    for t in hilbert_order(&centroids) {
+        let tri = &triangles[t as usize * 3..t as usize * 3 + 3];
+        let mut fresh = tri.to_vec();
+        fresh.sort_unstable();
+        fresh.dedup();
+        fresh.retain(|&v| local[v as usize] == u32::MAX);
+        if chunks.vertices.len() - start + fresh.len() > limit {
+            for &v in &chunks.vertices[start..] {
+                local[v as usize] = u32::MAX;
+            }
+            start = chunks.vertices.len();
+            chunks.vertex_offsets.push(start as u32);
+            chunks.triangle_offsets.push(chunks.triangles.len() as u32);
+        }
+        for &v in tri {
+            if local[v as usize] == u32::MAX {
+                local[v as usize] = (chunks.vertices.len() - start) as u32;
+                chunks.vertices.push(v);
+                let p = at(v);
+                chunks.positions.extend([p.x as f32, p.y as f32]);
+            }
+            // Below `limit`, so within u16
+            chunks.indices.push(local[v as usize] as u16);
+        }
+        chunks.triangles.push(t);
+    }
+    if chunks.vertices.len() > start {
+        chunks.vertex_offsets.push(chunks.vertices.len() as u32);
+        chunks.triangle_offsets.push(chunks.triangles.len() as u32);
+    }
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_interleave_round_trips() {
+        let xs = [1.0, 2.0, 3.0];
+        let ys = [4.0, 5.0, 6.0];
+        let pairs = interleave2(&xs, &ys).unwrap();
+        assert_eq!(pairs, vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+        assert_eq!(deinterleave2(&pairs).unwrap(), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let xyz = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        assert_eq!(split_xyz(&xyz).unwrap(), vec![1.0, 2.0, 4.0, 5.0, 3.0, 6.0]);
+        assert_eq!(to_f32(&[0.5, 1e40]), vec![0.5f32, f32::INFINITY]);
+
+        assert_eq!(code_of(interleave2(&xs, &ys[..2])), ErrorCode::InvalidLength);
+        assert_eq!(code_of(deinterleave2(&xs)), ErrorCode::InvalidLength);
+        assert_eq!(code_of(split_xyz(&xs[..2])), ErrorCode::InvalidLength);
+    }
+
+    #[test]
+    fn test_mesh_buffers_match_the_separate_calls() {
+        let points = crate::generate_golden_seeds(800.0, 600.0, 500);
+        let mesh = build_mesh_buffers(&points, 800.0, 600.0).unwrap();
+        let triangles = crate::compute_delaunay(&points, 800.0, 600.0);
+        let edges = crate::compute_edge_indices(&triangles);
+        assert_eq!(mesh.positions_f32(), to_f32(&points));
+        assert_eq!(mesh.indices_u32(), triangles);
+        assert_eq!(mesh.edge_indices_u32(), edges);
+        assert_eq!((mesh.point_count(), mesh.triangle_count()), (500, triangles.len() / 3));
+        assert_eq!(mesh.edge_count(), edges.len() / 2);
+        assert!(mesh.fits_u16());
+        let wide: Vec<u32> = mesh.indices_u16().unwrap().into_iter().map(u32::from).collect();
+        assert_eq!(wide, triangles);
+        let wide: Vec<u32> = mesh.edge_indices_u16().unwrap().into_iter().map(u32::from).collect();
+        assert_eq!(wide, edges);
+
+        // One point past the u16 limit keeps only the 32-bit getters
+        let mut rng = crate::rng::Rng::new(5);
+        let count = 2 * (MAX_U16_POINTS + 1);
+        let many: Vec<f64> = (0..count).map(|_| rng.range(0.0, 4096.0)).collect();
+        let mesh = build_mesh_buffers(&many, 4096.0, 4096.0).unwrap();
+        assert!(!mesh.fits_u16());
+        assert!(mesh.indices_u32().iter().any(|&v| v as usize == MAX_U16_POINTS));
+        assert_eq!(code_of(mesh.indices_u16()), ErrorCode::TooLarge);
+        assert_eq!(code_of(mesh.edge_indices_u16()), ErrorCode::TooLarge);
+
+        assert_eq!(code_of(build_mesh_buffers(&[1.0], 10.0, 10.0)), ErrorCode::InvalidLength);
+        let bad = build_mesh_buffers(&[0.0, 0.0, 1.0, f64::NAN], 10.0, 10.0);
+        assert_eq!(code_of(bad), ErrorCode::NonFiniteInput);
+    }
+
+    #[test]
+    fn test_split_mesh_chunks_cover_the_mesh() {
+        let points = crate::generate_golden_seeds(800.0, 600.0, 2000);
+        let triangles = crate::compute_delaunay(&points, 800.0, 600.0);
+        let chunks = split_mesh_for_u16(&points, &triangles, 300).unwrap();
+        assert!(chunks.chunk_count() > 2000 / 300);
+        let mut rebuilt = Vec::new();
+        let mut sources = Vec::new();
+        let mut repeated = 0;
+        for k in 0..chunks.chunk_count() {
+            let vertices = chunks.source_vertices(k).unwrap();
+            assert!(vertices.len() <= 300);
+            repeated += vertices.len();
+            let positions = chunks.positions_f32(k).unwrap();
+            for (p, &v) in positions.chunks(2).zip(&vertices) {
+                let v = v as usize;
+                assert_eq!(p, [points[v * 2] as f32, points[v * 2 + 1] as f32]);
+            }
+            let indices = chunks.indices_u16(k).unwrap();
+            rebuilt.extend(indices.iter().map(|&i| vertices[i as usize]));
+            sources.extend(chunks.source_triangles(k).unwrap());
+        }
+        // Every triangle lands in exactly one chunk, in its own winding
+        for (t, &s) in sources.iter().enumerate() {
+            let s = s as usize;
+            assert_eq!(rebuilt[t * 3..t * 3 + 3], triangles[s * 3..s * 3 + 3]);
+        }
+        sources.sort_unstable();
+        assert!(sources.iter().copied().eq(0..(triangles.len() / 3) as u32));
+        // Compact chunks repeat only a thin border of vertices
+        assert!(repeated < 2000 * 3 / 2, "{repeated} vertices across chunks");
+
+        let one = split_mesh_for_u16(&points, &triangles, MAX_U16_POINTS as u32).unwrap();
+        assert_eq!(one.chunk_count(), 1);
+        assert_eq!(one.source_vertices(0).unwrap().len(), 2000);
+        assert_eq!(code_of(one.indices_u16(1)), ErrorCode::IndexOutOfRange);
+        assert_eq!(split_mesh_for_u16(&points, &[], 3).unwrap().chunk_count(), 0);
+        let code = |t: &[u32], m: u32| code_of(split_mesh_for_u16(&points, t, m));
+        assert_eq!(code(&triangles, 2), ErrorCode::InvalidArgument);
+        assert_eq!(code(&triangles, MAX_U16_POINTS as u32 + 1), ErrorCode::InvalidArgument);
+        assert_eq!(code(&[0, 1, 2000], 300), ErrorCode::IndexOutOfRange);
+    }
+
+    #[test]
+    fn test_pack_interleaved_matches_builder() {
+        let pos = [0.0, 1.0, 2.0, 3.0];
+        let color = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6];
+        let packed = pack_interleaved(&[&pos, &color], &[2, 3]).unwrap();
+        assert_eq!(packed, vec![0.0, 1.0, 0.1, 0.2, 0.3, 2.0, 3.0, 0.4, 0.5, 0.6]);
+
+        let mut b = VertexBufferBuilder::new();
+        b.add(&pos, 2).unwrap();
+        b.add(&color, 3).unwrap();
+        assert_eq!((b.vertex_stride(), b.vertex_count()), (5, 2));
+        assert_eq!(b.build(), packed);
+
+        // Three vertices of one float against two of two
+        assert_eq!(code_of(b.add(&[1.0, 2.0, 3.0], 1)), ErrorCode::InvalidLength);
+        assert_eq!(code_of(b.add(&pos, 0)), ErrorCode::InvalidLength);
+        assert_eq!(code_of(pack_interleaved(&[&pos], &[3])), ErrorCode::InvalidLength);
+        assert_eq!(code_of(pack_interleaved(&[&pos], &[])), ErrorCode::InvalidLength);
+        assert_eq!(b.vertex_stride(), 5);
+    }
+}
@@ -0,0 +1,209 @@
+//! Incidence between Delaunay triangles and the Voronoi cells at their corners
+//!
+//! A triangle's three vertices are the seeds of the three cells that meet
+//! at its circumcenter, so the index below is the vertex → triangle
+//! incidence of the mesh, stored CSR-style, with the inverse read straight
+//! off the triangle list.
+//!
+//! A wedge is the part of triangle `t` that is closer to seed `s` than to
+//! the other two corners: triangle `t` clipped by the two bisectors
+//! through `s`. When the circumcenter lies inside `t`, the wedge is the
+//! quad (seed, edge midpoint, circumcenter, edge midpoint). An obtuse
+//! triangle has its circumcenter outside, so the wedge is cut off at the
+//! triangle's sides instead. That cut is what keeps wedges inside the
+//! hull: the wedges around one seed tile the part of its cell that lies
+//! within the triangulation, and nothing beyond the hull.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles};
+use crate::predicates::orient2d;
+use crate::voronoi::{clip_half_plane, dedup_ring};
+use crate::Point;
+
+/// Triangles incident to each cell, and the cells of each triangle
+#[wasm_bindgen]
+pub struct CellTriangleIndex {
+    /// One entry per cell plus one, into `incident`
+    offsets: Vec<u32>,
+    /// Triangle ids, ascending within each cell
+    incident: Vec<u32>,
+    triangles: Vec<u32>,
+}
+
+/// Index `triangles` (a flat CCW index list) by the cells at their corners
+#[wasm_bindgen]
+pub fn build_cell_triangle_index(triangles: &[u32], point_count: u32) -> Result<CellTriangleIndex> {
+    check_triangles(triangles, point_count as usize)?;
+    let n = point_count as usize;
+    let mut offsets = vec![0u32; n + 1];
+    for &v in triangles {
+        offsets[v as usize + 1] += 1;
+    }
+    for i in 0..n {
+        offsets[i + 1] += offsets[i];
+    }
+    // Filling in triangle order leaves every list ascending
+    let mut fill = offsets.clone();
+    let mut incident = vec![0u32; triangles.len()];
+    for (t, tri) in triangles.chunks_exact(3).enumerate() {
+        for &v in tri {
+            incident[fill[v as usize] as usize] = t as u32;
+            fill[v as usize] += 1;
+        }
+    }
+    Ok(CellTriangleIndex { offsets, incident, triangles: triangles.to_vec() })
+}
+
+impl CellTriangleIndex {
+    fn corners(&self, triangle: u32) -> Result<[u32; 3]> {
+        let t = triangle as usize;
+        if t >= self.triangles.len() / 3 {
+            return Err(Error::IndexOutOfRange(format!(
+                "triangle {triangle} out of range for {} triangles",
+                self.triangles.len() / 3
+            )));
+        }
+        Ok([self.triangles[3 * t], self.triangles[3 * t + 1], self.triangles[3 * t + 2]])
+    }
+}
+
+#[wasm_bindgen]
+impl CellTriangleIndex {
+    pub fn cell_count(&self) -> u32 {
+        (self.offsets.len() - 1) as u32
+    }
+
+    pub fn triangle_count(&self) -> u32 {
+        (self.triangles.len() / 3) as u32
+    }
+
+    /// Triangles with `seed` as a corner, ascending
+    pub fn triangles_of_cell(&self, seed: u32) -> Result<Vec<u32>> {
+        if seed >= self.cell_count() {
+            return Err(Error::IndexOutOfRange(format!(
+                "seed {seed} out of range for {} cells",
+                self.cell_count()
+            )));
+        }
+        let s = seed as usize;
+        Ok(self.incident[self.offsets[s] as usize..self.offsets[s + 1] as usize].to_vec())
+    }
+
+    /// The three cell ids of `triangle`, in its vertex order
+    pub fn cells_of_triangle(&self, triangle: u32) -> Result<Vec<u32>> {
+        Ok(self.corners(triangle)?.to_vec())
+    }
+
+    /// Flat CCW polygon of the wedge of `seed`'s cell inside `triangle`
+    ///
+    /// `points` are the coordinates the index was built for. The result is
+    /// the (seed, midpoint, circumcenter, midpoint) quad unless the
+    /// triangle is obtuse, in which case it is clipped to the triangle (3 to
+    /// 5 vertices). A degenerate triangle gives an empty wedge.
+    pub fn cell_wedge(&self, points: &[f64], seed: u32, triangle: u32) -> Result<Vec<f64>> {
+        if check_points(points)? != self.cell_count() as usize {
+            return Err(Error::InvalidLength(format!(
+                "{} points given for an index of {} cells",
+                points.len() / 2,
+                self.cell_count()
+            )));
+        }
+        let corners = self.corners(triangle)?;
+        let Some(k) = corners.iter().position(|&v| v == seed) else {
+            return Err(Error::InvalidArgument(format!(
+                "seed {seed} is not a corner of triangle {triangle}"
+            )));
+        };
+        let at = |i: usize| {
+            let v = corners[(k + i) % 3] as usize;
+            Point::new(points[2 * v], points[2 * v + 1])
+        };
+        // Rotated so the seed comes first
+        let (s, mut a, mut b) = (at(0), at(1), at(2));
+        let turn = orient2d(s, a, b);
+        if turn.is_nan() || turn == 0.0 {
+            return Ok(Vec::new());
+        }
+        if turn < 0.0 {
+            std::mem::swap(&mut a, &mut b);
+        }
+        let mut poly = vec![s, a, b];
+        let mut scratch = Vec::with_capacity(5);
+        for other in [a, b] {
+            let mid = Point::new((s.x + other.x) / 2.0, (s.y + other.y) / 2.0);
+            let normal = Point::new(other.x - s.x, other.y - s.y);
+            clip_half_plane(&poly, mid, normal, &mut scratch);
+            std::mem::swap(&mut poly, &mut scratch);
+        }
+        dedup_ring(&mut poly);
+        // The seed is on the kept side of both cuts; start the ring there
+        if let Some(first) = poly.iter().position(|&p| p == s) {
+            poly.rotate_left(first);
+        }
+        Ok(poly.iter().flat_map(|p| [p.x, p.y]).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::predicates::signed_area2;
+
+    fn polygon(flat: &[f64]) -> Vec<Point> {
+        flat.chunks(2).map(|c| Point::new(c[0], c[1])).collect()
+    }
+
+    #[test]
+    fn test_index_both_ways() {
+        // A square split along its 0-2 diagonal
+        let triangles = [0, 1, 2, 0, 2, 3];
+        let index = build_cell_triangle_index(&triangles, 5).unwrap();
+        assert_eq!(index.triangles_of_cell(0).unwrap(), vec![0, 1]);
+        assert_eq!(index.triangles_of_cell(1).unwrap(), vec![0]);
+        assert_eq!(index.triangles_of_cell(4).unwrap(), Vec::<u32>::new());
+        assert_eq!(index.cells_of_triangle(1).unwrap(), vec![0, 2, 3]);
+        assert_eq!(code_of(index.triangles_of_cell(5)), ErrorCode::IndexOutOfRange);
+        assert_eq!(code_of(index.cells_of_triangle(2)), ErrorCode::IndexOutOfRange);
+        assert_eq!(code_of(build_cell_triangle_index(&triangles, 3)), ErrorCode::IndexOutOfRange);
+    }
+
+    #[test]
+    fn test_acute_wedge_is_the_circumcenter_quad() {
+        let points = [0.0, 0.0, 4.0, 0.0, 2.0, 3.0];
+        let index = build_cell_triangle_index(&[0, 1, 2], 3).unwrap();
+        let wedge = polygon(&index.cell_wedge(&points, 0, 0).unwrap());
+        // Circumcenter of the isosceles triangle: (2, 5/6)
+        let expected = [(0.0, 0.0), (2.0, 0.0), (2.0, 5.0 / 6.0), (1.0, 1.5)];
+        assert_eq!(wedge.len(), 4);
+        for (p, (x, y)) in wedge.iter().zip(expected) {
+            assert!((p.x - x).abs() < 1e-12 && (p.y - y).abs() < 1e-12, "{wedge:?}");
+        }
+        let wrong = index.cell_wedge(&points[..4], 0, 0);
+        assert_eq!(code_of(wrong), ErrorCode::InvalidLength);
+    }
+
+    #[test]
+    fn test_obtuse_wedges_stay_inside_and_tile_the_triangle() {
+        // Obtuse at vertex 2; the circumcenter lies below the 0-1 side.
+        // Listed clockwise to check the winding is normalised.
+        let points = [0.0, 0.0, 10.0, 0.0, 5.0, 1.0, 3.0, 7.0];
+        let index = build_cell_triangle_index(&[0, 2, 1], 4).unwrap();
+        let mut total = 0.0;
+        for seed in [0, 1, 2] {
+            let wedge = polygon(&index.cell_wedge(&points, seed, 0).unwrap());
+            assert!((3..=5).contains(&wedge.len()), "{wedge:?}");
+            let area2 = signed_area2(&wedge);
+            assert!(area2 > 0.0);
+            total += area2 / 2.0;
+            for p in &wedge {
+                assert!(p.y >= -1e-12, "wedge of {seed} leaves the triangle: {wedge:?}");
+            }
+        }
+        assert!((total - 5.0).abs() < 1e-9);
+        let code = code_of(index.cell_wedge(&points, 3, 0));
+        assert_eq!(code, ErrorCode::InvalidArgument);
+    }
+}
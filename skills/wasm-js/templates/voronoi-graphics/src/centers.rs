@@ -0,0 +1,249 @@
+//! Per-triangle centers and radii for dot, glow and bubble effects
+//!
+//! `circumcircle` is the one circumcenter formula in the crate; the
+//! natural-neighbour and tiling code call it too, so a Voronoi vertex
+//! comes out the same wherever it is computed.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles};
+use crate::predicates::orient2d;
+use crate::Point;
+
+/// Circumcenter and squared circumradius of `abc`, either winding
+///
+/// Not finite for a collinear triangle; callers that may see one check.
+pub(crate) fn circumcircle(a: Point, b: Point, c: Point) -> (Point, f64) {
+    let (bx, by) = (b.x - a.x, b.y - a.y);
+    let (cx, cy) = (c.x - a.x, c.y - a.y);
+    let d = 2.0 * (bx * cy - by * cx);
+    let (b2, c2) = (bx * bx + by * by, cx * cx + cy * cy);
+    let ux = (cy * b2 - by * c2) / d;
+    let uy = (bx * c2 - cx * b2) / d;
+    (Point::new(a.x + ux, a.y + uy), ux * ux + uy * uy)
+}
+
+fn check_input(points_flat: &[f64], triangles: &[u32]) -> Result<()> {
+    let n = check_points(points_flat)?;
+    check_triangles(triangles, n)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    Ok(())
+}
+
+/// `f` over the corners of every triangle, or `degenerate` over them for
+/// collinear ones, flattened
+fn per_triangle<const N: usize>(
+    points_flat: &[f64],
+    triangles: &[u32],
+    f: impl Fn(Point, Point, Point) -> [f64; N],
+    degenerate: impl Fn(Point, Point, Point) -> [f64; N],
+) -> Result<Vec<f64>> {
+    check_input(points_flat, triangles)?;
+    let point = |i: u32| Point::new(points_flat[i as usize * 2], points_flat[i as usize * 2 + 1]);
+    let mut out = Vec::with_capacity(triangles.len() / 3 * N);
+    for t in triangles.chunks_exact(3) {
+        let [a, b, c] = [t[0], t[1], t[2]].map(point);
+        let values = f(a, b, c);
+        // Nearly flat triangles can still overflow the formulas
+        if orient2d(a, b, c) == 0.0 || values.iter().any(|v| !v.is_finite()) {
+            out.extend(degenerate(a, b, c));
+        } else {
+            out.extend(values);
+        }
+    }
+    Ok(out)
+}
+
+fn centroid(a: Point, b: Point, c: Point) -> [f64; 2] {
+    [(a.x + b.x + c.x) / 3.0, (a.y + b.y + c.y) / 3.0]
+}
+
+/// `[x0, y0, x1, y1, ...]` circumcenter of each triangle, in order
+///
+/// These are the Voronoi vertices of a Delaunay triangulation. A
+/// collinear triangle, which has none, gives its centroid instead.
+#[wasm_bindgen]
+pub fn triangle_circumcenters(points_flat: &[f64], triangles: &[u32]) -> Result<Vec<f64>> {
+    let center = |a, b, c| {
+        let (p, _) = circumcircle(a, b, c);
+        [p.x, p.y]
+    };
+    per_triangle(points_flat, triangles, center, centroid)
+}
+
+/// Circumradius of each triangle, in order; 0 for collinear ones
+#[wasm_bindgen]
+pub fn triangle_circumradii(points_flat: &[f64], triangles: &[u32]) -> Result<Vec<f64>> {
+    let radius = |a, b, c| [circumcircle(a, b, c).1.sqrt()];
+    per_triangle(points_flat, triangles, radius, |_, _, _| [0.0])
+}
+
+/// `[x0, y0, x1, y1, ...]` incenter of each triangle, in order
+///
+/// The incenter is the corners averaged by the length of the opposite
+/// side and always lies inside, unlike the circumcenter of an obtuse
+/// triangle. A collinear triangle gives its centroid.
+#[wasm_bindgen]
+pub fn triangle_incenters(points_flat: &[f64], triangles: &[u32]) -> Result<Vec<f64>> {
+    let center = |a: Point, b: Point, c: Point| {
+        let side = |p: Point, q: Point| (p.x - q.x).hypot(p.y - q.y);
+        let (la, lb, lc) = (side(b, c), side(c, a), side(a, b));
+        let sum = la + lb + lc;
+        [(la * a.x + lb * b.x + lc * c.x) / sum, (la * a.y + lb * b.y + lc * c.y) / sum]
+    };
+    per_triangle(points_flat, triangles, center, centroid)
+}
+
+/// Circles kept by `compute_circumcircles` and the triangles they came from
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Circumcircles {
+    circles: Vec<f32>,
+    triangles: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl Circumcircles {
+    /// `[cx0, cy0, r0, cx1, ...]`, one circle per kept triangle
+    pub fn circles(&self) -> Vec<f32> {
+        self.circles.clone()
+    }
+
+    /// Index of the triangle behind each circle, ascending, so per-triangle
+    /// data such as colors can be picked for the kept circles alone
+    pub fn triangle_indices(&self) -> Vec<u32> {
+        self.triangles.clone()
+    }
+
+    pub fn circle_count(&self) -> usize {
+        self.triangles.len()
+    }
+}
+
+/// Circumcircle of every triangle, for drawing bubbles
+///
+/// Circles wider than `max_radius` are dropped, which removes the huge
+/// ones slivers along the hull produce; without it every circle is kept.
+/// Collinear triangles have no circle and are always dropped, so the
+/// output is never NaN.
+#[wasm_bindgen]
+pub fn compute_circumcircles(
+    points_flat: &[f64],
+    triangles: &[u32],
+    max_radius: Option<f64>,
+) -> Result<Circumcircles> {
+    check_input(points_flat, triangles)?;
+    let max_radius = max_radius.unwrap_or(f64::INFINITY);
+    if max_radius.is_nan() || max_radius < 0.0 {
+        return Err(Error::InvalidArgument(format!(
+            "max_radius must be non-negative, got {max_radius}"
+        )));
+    }
+    let point = |i: u32| Point::new(points_flat[i as usize * 2], points_flat[i as usize * 2 + 1]);
+    let mut kept = Circumcircles { circles: Vec::new(), triangles: Vec::new() };
+    for (k, t) in triangles.chunks_exact(3).enumerate() {
+        let [a, b, c] = [t[0], t[1], t[2]].map(point);
+        if orient2d(a, b, c) == 0.0 {
+            continue;
+        }
+        let (center, r2) = circumcircle(a, b, c);
+        let r = r2.sqrt();
+        if center.x.is_finite() && center.y.is_finite() && r <= max_radius {
+            kept.circles.extend([center.x as f32, center.y as f32, r as f32]);
+            kept.triangles.push(k as u32);
+        }
+    }
+    Ok(kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_centers_of_known_triangles() {
+        // A 3-4-5 right triangle, the same one clockwise, and a flat one
+        let points = [0.0, 0.0, 4.0, 0.0, 0.0, 3.0, 2.0, 0.0];
+        let triangles = [0, 1, 2, 0, 2, 1, 0, 3, 1];
+        let circumcenters = triangle_circumcenters(&points, &triangles).unwrap();
+        assert_eq!(circumcenters, vec![2.0, 1.5, 2.0, 1.5, 2.0, 0.0]);
+        let radii = triangle_circumradii(&points, &triangles).unwrap();
+        assert_eq!(radii, vec![2.5, 2.5, 0.0]);
+        // Inradius (a + b - c) / 2 = 1 puts the incenter at (1, 1)
+        let incenters = triangle_incenters(&points, &triangles).unwrap();
+        for v in &incenters[..4] {
+            assert!((v - 1.0).abs() < 1e-12);
+        }
+        assert_eq!(&incenters[4..], &[2.0, 0.0]);
+
+        // Coincident corners are degenerate too
+        let pinched = triangle_incenters(&[1.0, 1.0, 1.0, 1.0], &[0, 1, 0]).unwrap();
+        assert_eq!(pinched, vec![1.0, 1.0]);
+        assert!(triangle_circumcenters(&points, &[]).unwrap().is_empty());
+        let code = |p: &[f64], t: &[u32]| code_of(triangle_circumradii(p, t));
+        assert_eq!(code(&points, &[0, 1]), ErrorCode::InvalidLength);
+        assert_eq!(code(&points, &[0, 1, 4]), ErrorCode::IndexOutOfRange);
+        assert_eq!(code(&[0.0, f64::NAN], &[]), ErrorCode::NonFiniteInput);
+    }
+
+    #[test]
+    fn test_circumcenters_are_equidistant() {
+        let points = crate::generate_golden_seeds(500.0, 400.0, 120);
+        let triangles = crate::compute_delaunay(&points, 500.0, 400.0);
+        let centers = triangle_circumcenters(&points, &triangles).unwrap();
+        let radii = triangle_circumradii(&points, &triangles).unwrap();
+        let incenters = triangle_incenters(&points, &triangles).unwrap();
+        for (k, t) in triangles.chunks(3).enumerate() {
+            let corner = |v: u32| Point::new(points[v as usize * 2], points[v as usize * 2 + 1]);
+            let [a, b, c] = [t[0], t[1], t[2]].map(corner);
+            let center = Point::new(centers[k * 2], centers[k * 2 + 1]);
+            for p in [a, b, c] {
+                let d = (p.x - center.x).hypot(p.y - center.y);
+                assert!((d - radii[k]).abs() < 1e-9 * radii[k]);
+            }
+            let i = Point::new(incenters[k * 2], incenters[k * 2 + 1]);
+            assert!(orient2d(a, b, i) > 0.0 && orient2d(b, c, i) > 0.0 && orient2d(c, a, i) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_circumcircles_filter_and_index() {
+        // The 3-4-5 triangle, a flat one, and a sliver with a huge circle
+        let points = [0.0, 0.0, 4.0, 0.0, 0.0, 3.0, 2.0, 0.0, 8.0, 0.01];
+        let triangles = [0, 1, 2, 0, 3, 1, 0, 1, 4];
+        let all = compute_circumcircles(&points, &triangles, None).unwrap();
+        assert_eq!(all.triangle_indices(), vec![0, 2]);
+        assert_eq!(&all.circles()[..3], &[2.0, 1.5, 2.5]);
+        assert!(all.circles()[5] > 1000.0);
+        let small = compute_circumcircles(&points, &triangles, Some(10.0)).unwrap();
+        assert_eq!((small.circles(), small.triangle_indices()), (vec![2.0, 1.5, 2.5], vec![0]));
+        assert_eq!(small.circle_count(), 1);
+
+        // On a real mesh the circles match the per-triangle exports
+        let points = crate::generate_golden_seeds(500.0, 400.0, 150);
+        let triangles = crate::compute_delaunay(&points, 500.0, 400.0);
+        let centers = triangle_circumcenters(&points, &triangles).unwrap();
+        let radii = triangle_circumradii(&points, &triangles).unwrap();
+        let bubbles = compute_circumcircles(&points, &triangles, Some(40.0)).unwrap();
+        let kept = bubbles.triangle_indices();
+        assert!(!kept.is_empty() && kept.len() < triangles.len() / 3);
+        let small = (0..radii.len() as u32).filter(|&k| radii[k as usize] <= 40.0);
+        assert_eq!(kept, small.collect::<Vec<_>>());
+        for (circle, &k) in bubbles.circles().chunks(3).zip(&kept) {
+            let k = k as usize;
+            let expected = [centers[2 * k] as f32, centers[2 * k + 1] as f32, radii[k] as f32];
+            assert_eq!(circle, expected);
+        }
+
+        let bad = compute_circumcircles(&points, &triangles, Some(f64::NAN));
+        assert_eq!(code_of(bad), ErrorCode::InvalidArgument);
+        let bad = compute_circumcircles(&points, &triangles, Some(-1.0));
+        assert_eq!(code_of(bad), ErrorCode::InvalidArgument);
+        let bad = compute_circumcircles(&points, &[0, 1], None);
+        assert_eq!(code_of(bad), ErrorCode::InvalidLength);
+    }
+}
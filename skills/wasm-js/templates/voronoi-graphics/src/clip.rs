@@ -0,0 +1,360 @@
+//! Clipping edges and triangles to a polygon mask
+//!
+//! The mask is one or more closed loops combined by the even-odd rule, so
+//! a loop inside another is a hole. Its edges are bucketed into
+//! horizontal bands once per call, and both the point-in-polygon test and
+//! the segment crossings only look at the bands they overlap, which keeps
+//! them cheap for outlines of a few hundred vertices.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_edge_indices, check_points, check_triangles};
+use crate::Point;
+
+/// Which triangles `filter_triangles_by_polygon` keeps
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PolygonFilterMode {
+    /// The centroid is inside
+    CentroidInside = 0,
+    /// All three corners are inside and no side crosses the outline
+    FullyInside = 1,
+    /// At least one corner is inside
+    AnyVertexInside = 2,
+}
+
+/// Mask edges bucketed by horizontal band
+struct PolygonMask {
+    edges: Vec<(Point, Point)>,
+    y0: f64,
+    band_height: f64,
+    /// Edges of band `b` are `band_edges[band_offsets[b]..band_offsets[b + 1]]`
+    band_offsets: Vec<u32>,
+    band_edges: Vec<u32>,
+}
+
+impl PolygonMask {
+    /// Validate `polygon_flat` and `loop_offsets` and bucket the edges
+    ///
+    /// An empty `loop_offsets` means a single loop; otherwise it is
+    /// `[0, end0, end1, ...]` vertex offsets, loop `k` running over
+    /// vertices `loop_offsets[k]..loop_offsets[k + 1]`.
+    fn new(polygon_flat: &[f64], loop_offsets: &[u32]) -> Result<PolygonMask> {
+        let n = check_points(polygon_flat)?;
+        if polygon_flat.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("polygon coordinates must be finite".into()));
+        }
+        let single = [0, n as u32];
+        let offsets = if loop_offsets.is_empty() { &single[..] } else { loop_offsets };
+        if offsets[0] != 0 || offsets[offsets.len() - 1] as usize != n {
+            return Err(Error::InvalidArgument(format!(
+                "loop offsets must run from 0 to the {n} polygon vertices"
+            )));
+        }
+        let vertex = |i: usize| Point::new(polygon_flat[i * 2], polygon_flat[i * 2 + 1]);
+        let mut edges = Vec::with_capacity(n);
+        for w in offsets.windows(2) {
+            let (start, end) = (w[0] as usize, w[1] as usize);
+            if end < start + 3 {
+                return Err(Error::InvalidArgument(format!(
+                    "polygon loop {start}..{end} needs at least 3 vertices"
+                )));
+            }
+            edges.extend((start..end).map(|i| {
+                let next = if i + 1 == end { start } else { i + 1 };
+                (vertex(i), vertex(next))
+            }));
+        }
+
+        let (y0, y1) = edges.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), e| {
+            (lo.min(e.0.y), hi.max(e.0.y))
+        });
+        let band_count = edges.len().clamp(1, 1024);
+        let band_height = (y1 - y0) / band_count as f64;
+        let mut mask = PolygonMask {
+            edges,
+            y0,
+            band_height,
+            band_offsets: vec![0; band_count + 1],
+            band_edges: Vec::new(),
+        };
+        // Count, prefix-sum, then fill, as for a CSR adjacency
+        let spans: Vec<(usize, usize)> =
+            mask.edges.iter().map(|e| mask.span(e.0.y, e.1.y)).collect();
+        for &(lo, hi) in &spans {
+            for b in lo..=hi {
+                mask.band_offsets[b + 1] += 1;
+            }
+        }
+        for b in 0..band_count {
+            mask.band_offsets[b + 1] += mask.band_offsets[b];
+        }
+        let mut fill = mask.band_offsets.clone();
+        mask.band_edges = vec![0; mask.band_offsets[band_count] as usize];
+        for (k, &(lo, hi)) in spans.iter().enumerate() {
+            for slot in &mut fill[lo..=hi] {
+                mask.band_edges[*slot as usize] = k as u32;
+                *slot += 1;
+            }
+        }
+        Ok(mask)
+    }
+
+    fn band_count(&self) -> usize {
+        self.band_offsets.len() - 1
+    }
+
+    fn band(&self, y: f64) -> usize {
+        if self.band_height > 0.0 {
+            let b = ((y - self.y0) / self.band_height).floor();
+            b.clamp(0.0, (self.band_count() - 1) as f64) as usize
+        } else {
+            0
+        }
+    }
+
+    /// Bands overlapping the `y` interval between `a` and `b`
+    fn span(&self, a: f64, b: f64) -> (usize, usize) {
+        (self.band(a.min(b)), self.band(a.max(b)))
+    }
+
+    fn band_edges(&self, b: usize) -> impl Iterator<Item = u32> + '_ {
+        let range = self.band_offsets[b] as usize..self.band_offsets[b + 1] as usize;
+        self.band_edges[range].iter().copied()
+    }
+
+    /// Even-odd point-in-polygon test
+    ///
+    /// A point above or below the outline lands in an end band whose
+    /// edges it cannot cross, so it needs no special case.
+    fn contains(&self, p: Point) -> bool {
+        let mut inside = false;
+        for k in self.band_edges(self.band(p.y)) {
+            let (a, b) = self.edges[k as usize];
+            if (a.y > p.y) != (b.y > p.y) {
+                let x = a.x + (p.y - a.y) * (b.x - a.x) / (b.y - a.y);
+                if x > p.x {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+
+    /// Parameters in `(0, 1)` where `a → b` crosses the outline, unsorted
+    fn crossings(&self, a: Point, b: Point, out: &mut Vec<f64>) {
+        let r = Point::new(b.x - a.x, b.y - a.y);
+        let (lo, hi) = self.span(a.y, b.y);
+        for band in lo..=hi {
+            for k in self.band_edges(band) {
+                let (c, d) = self.edges[k as usize];
+                // An edge spanning several of our bands is seen in the first
+                if self.span(c.y, d.y).0.max(lo) != band {
+                    continue;
+                }
+                let s = Point::new(d.x - c.x, d.y - c.y);
+                let denom = r.x * s.y - r.y * s.x;
+                if denom == 0.0 {
+                    continue;
+                }
+                let (qx, qy) = (c.x - a.x, c.y - a.y);
+                let t = (qx * s.y - qy * s.x) / denom;
+                let u = (qx * r.y - qy * r.x) / denom;
+                if t > 0.0 && t < 1.0 && (0.0..=1.0).contains(&u) {
+                    out.push(t);
+                }
+            }
+        }
+    }
+}
+
+fn check_finite(points_flat: &[f64]) -> Result<usize> {
+    let n = check_points(points_flat)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    Ok(n)
+}
+
+/// Segments `[x0, y0, x1, y1, ...]` of the edges `[i0, j0, i1, j1, ...]`
+/// that lie inside the polygon
+///
+/// `polygon_flat` is `[x0, y0, x1, y1, ...]`, closed implicitly, of any
+/// winding and not necessarily convex. Pass an empty `loop_offsets` for a
+/// single outline, or `[0, end0, end1, ...]` to split the vertices into
+/// several loops; loops are combined even-odd, so one inside another cuts
+/// a hole. An edge crossing the outline can come out as several pieces,
+/// each running from `i` towards `j`; pieces of one edge are in order.
+#[wasm_bindgen]
+pub fn clip_edges_to_polygon(
+    points_flat: &[f64],
+    edge_indices: &[u32],
+    polygon_flat: &[f64],
+    loop_offsets: &[u32],
+) -> Result<Vec<f64>> {
+    let n = check_finite(points_flat)?;
+    check_edge_indices(edge_indices, n)?;
+    let mask = PolygonMask::new(polygon_flat, loop_offsets)?;
+    let point = |i: u32| Point::new(points_flat[i as usize * 2], points_flat[i as usize * 2 + 1]);
+    let mut out = Vec::new();
+    let mut ts = Vec::new();
+    for e in edge_indices.chunks_exact(2) {
+        let (a, b) = (point(e[0]), point(e[1]));
+        if a == b {
+            continue;
+        }
+        ts.clear();
+        ts.push(0.0);
+        mask.crossings(a, b, &mut ts);
+        ts.push(1.0);
+        ts.sort_unstable_by(f64::total_cmp);
+        ts.dedup();
+        let at = |t: f64| {
+            if t == 1.0 {
+                b
+            } else {
+                Point::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t)
+            }
+        };
+        // Runs of inside pieces between consecutive crossings merge
+        let mut start = None;
+        for w in ts.windows(2) {
+            let inside = mask.contains(at((w[0] + w[1]) / 2.0));
+            match (inside, start) {
+                (true, None) => start = Some(w[0]),
+                (false, Some(t0)) => {
+                    let (p, q) = (at(t0), at(w[0]));
+                    out.extend_from_slice(&[p.x, p.y, q.x, q.y]);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(t0) = start {
+            let p = at(t0);
+            out.extend_from_slice(&[p.x, p.y, b.x, b.y]);
+        }
+    }
+    Ok(out)
+}
+
+/// `triangles` kept by `mode` against the polygon, in their input order
+///
+/// The polygon and `loop_offsets` are as for `clip_edges_to_polygon`.
+#[wasm_bindgen]
+pub fn filter_triangles_by_polygon(
+    points_flat: &[f64],
+    triangles: &[u32],
+    polygon_flat: &[f64],
+    loop_offsets: &[u32],
+    mode: PolygonFilterMode,
+) -> Result<Vec<u32>> {
+    let n = check_finite(points_flat)?;
+    check_triangles(triangles, n)?;
+    let mask = PolygonMask::new(polygon_flat, loop_offsets)?;
+    let point = |i: u32| Point::new(points_flat[i as usize * 2], points_flat[i as usize * 2 + 1]);
+    let mut ts = Vec::new();
+    let mut keep = |t: &[u32]| {
+        let [a, b, c] = [t[0], t[1], t[2]].map(point);
+        match mode {
+            PolygonFilterMode::CentroidInside => {
+                mask.contains(Point::new((a.x + b.x + c.x) / 3.0, (a.y + b.y + c.y) / 3.0))
+            }
+            PolygonFilterMode::AnyVertexInside => [a, b, c].iter().any(|&p| mask.contains(p)),
+            PolygonFilterMode::FullyInside => {
+                [a, b, c].iter().all(|&p| mask.contains(p))
+                    && [(a, b), (b, c), (c, a)].iter().all(|&(p, q)| {
+                        ts.clear();
+                        mask.crossings(p, q, &mut ts);
+                        ts.is_empty()
+                    })
+            }
+        }
+    };
+    Ok(triangles.chunks_exact(3).filter(|t| keep(t)).flatten().copied().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    /// A 10 × 10 square with a notch cut down to y = 5 between x = 4 and 6
+    const NOTCHED: [f64; 16] =
+        [0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 6.0, 10.0, 6.0, 5.0, 4.0, 5.0, 4.0, 10.0, 0.0, 10.0];
+
+    #[test]
+    fn test_point_in_concave_polygon_with_hole() {
+        let mask = PolygonMask::new(&NOTCHED, &[]).unwrap();
+        let inside = |x, y| mask.contains(Point::new(x, y));
+        assert!(inside(2.0, 8.0) && inside(8.0, 8.0) && inside(5.0, 3.0));
+        assert!(!inside(5.0, 8.0) && !inside(-1.0, 5.0) && !inside(5.0, 11.0));
+
+        // The notched square with a square hole around (8, 2)
+        let mut holed = NOTCHED.to_vec();
+        holed.extend_from_slice(&[7.0, 1.0, 9.0, 1.0, 9.0, 3.0, 7.0, 3.0]);
+        let mask = PolygonMask::new(&holed, &[0, 8, 12]).unwrap();
+        assert!(!mask.contains(Point::new(8.0, 2.0)));
+        assert!(mask.contains(Point::new(8.0, 4.0)));
+        // Same answers as testing every edge, for points all over
+        for k in 0..400 {
+            let p = Point::new((k % 20) as f64 * 0.6 - 0.7, (k / 20) as f64 * 0.6 - 0.7);
+            let brute = mask.edges.iter().fold(false, |inside, &(a, b)| {
+                let crosses = (a.y > p.y) != (b.y > p.y)
+                    && a.x + (p.y - a.y) * (b.x - a.x) / (b.y - a.y) > p.x;
+                inside ^ crosses
+            });
+            assert_eq!(mask.contains(p), brute, "{p:?}");
+        }
+    }
+
+    #[test]
+    fn test_clip_edges_splits_at_the_outline() {
+        // A horizontal line through the notch, one fully inside, one outside
+        let points = [-2.0, 8.0, 14.0, 8.0, 1.0, 1.0, 9.0, 2.0, 20.0, 0.0, 30.0, 0.0];
+        let clipped = clip_edges_to_polygon(&points, &[0, 1, 2, 3, 4, 5], &NOTCHED, &[]).unwrap();
+        assert_eq!(
+            clipped,
+            vec![0.0, 8.0, 4.0, 8.0, 6.0, 8.0, 10.0, 8.0, 1.0, 1.0, 9.0, 2.0]
+        );
+        // Reversed, the pieces follow the edge direction
+        let reversed = clip_edges_to_polygon(&points, &[1, 0], &NOTCHED, &[]).unwrap();
+        assert_eq!(reversed, vec![10.0, 8.0, 6.0, 8.0, 4.0, 8.0, 0.0, 8.0]);
+    }
+
+    #[test]
+    fn test_filter_triangles_modes() {
+        // One triangle per case: straddling the notch, inside, half out
+        let points = [
+            3.0, 7.0, 7.0, 7.0, 5.0, 4.0, // corners inside, bridges the notch
+            1.0, 1.0, 3.0, 1.0, 2.0, 3.0, // inside
+            9.0, 1.0, 12.0, 1.0, 12.0, 4.0, // one corner inside
+        ];
+        let triangles = [0, 1, 2, 3, 4, 5, 6, 7, 8];
+        let filter = |mode| {
+            filter_triangles_by_polygon(&points, &triangles, &NOTCHED, &[], mode).unwrap()
+        };
+        assert_eq!(filter(PolygonFilterMode::FullyInside), vec![3, 4, 5]);
+        assert_eq!(filter(PolygonFilterMode::CentroidInside), vec![3, 4, 5]);
+        assert_eq!(filter(PolygonFilterMode::AnyVertexInside), triangles.to_vec());
+    }
+
+    #[test]
+    fn test_clip_rejects_bad_input() {
+        let square = [0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0];
+        let clip = |p: &[f64], e: &[u32], poly: &[f64], loops: &[u32]| {
+            code_of(clip_edges_to_polygon(p, e, poly, loops))
+        };
+        assert_eq!(clip(&[0.0; 4], &[0], &square, &[]), ErrorCode::InvalidLength);
+        assert_eq!(clip(&[0.0; 4], &[0, 2], &square, &[]), ErrorCode::IndexOutOfRange);
+        assert_eq!(clip(&[f64::NAN, 0.0], &[], &square, &[]), ErrorCode::NonFiniteInput);
+        assert_eq!(clip(&[], &[], &square[..4], &[]), ErrorCode::InvalidArgument);
+        assert_eq!(clip(&[], &[], &square, &[0, 3]), ErrorCode::InvalidArgument);
+        assert_eq!(clip(&[], &[], &square, &[1, 4]), ErrorCode::InvalidArgument);
+        assert_eq!(clip(&[], &[], &[0.0, f64::INFINITY], &[]), ErrorCode::NonFiniteInput);
+        let mode = PolygonFilterMode::CentroidInside;
+        let result = filter_triangles_by_polygon(&[0.0; 4], &[0, 1, 2], &square, &[], mode);
+        assert_eq!(code_of(result), ErrorCode::IndexOutOfRange);
+    }
+}
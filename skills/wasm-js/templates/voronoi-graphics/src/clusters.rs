@@ -0,0 +1,162 @@
+//! Connected blobs of active cells, for outlining and animating each one
+//!
+//! A cell is active where its seed's mask byte is non-zero, and two active
+//! cells join a cluster when their seeds share a Delaunay edge. Clusters
+//! are numbered from 1 in order of their smallest seed index, so a label
+//! only moves when that seed leaves its cluster; a field animating across
+//! the mask mostly keeps its colors as blobs grow, merge and split.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles, unique_edges, UnionFind};
+
+/// Clusters found by `cell_clusters`
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct CellClusters {
+    labels: Vec<u32>,
+    member_counts: Vec<u32>,
+    bounds: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl CellClusters {
+    /// Cluster of each seed, 1-based, or 0 for an inactive seed
+    pub fn labels(&self) -> Vec<u32> {
+        self.labels.clone()
+    }
+
+    /// Seeds in cluster `k + 1` at index `k`
+    pub fn member_counts(&self) -> Vec<u32> {
+        self.member_counts.clone()
+    }
+
+    /// `[min_x, min_y, max_x, max_y]` of cluster `k + 1`'s seeds at
+    /// `4 × k`
+    pub fn bounds(&self) -> Vec<f64> {
+        self.bounds.clone()
+    }
+
+    pub fn cluster_count(&self) -> usize {
+        self.member_counts.len()
+    }
+}
+
+/// Labels per seed and the number of clusters
+fn label(triangles: &[u32], n: usize, active_mask: &[u8]) -> Result<(Vec<u32>, usize)> {
+    check_triangles(triangles, n)?;
+    if active_mask.len() != n {
+        return Err(Error::InvalidLength(format!(
+            "active mask needs one byte per seed ({n}), got {}",
+            active_mask.len()
+        )));
+    }
+    let active = |v: u32| active_mask[v as usize] != 0;
+    let mut sets = UnionFind::new(n);
+    for (a, b) in unique_edges(triangles) {
+        if active(a) && active(b) {
+            sets.union(a, b);
+        }
+    }
+    // Visiting seeds in index order numbers each cluster by its smallest
+    let mut label_of_root = vec![0u32; n];
+    let mut labels = vec![0u32; n];
+    let mut count = 0;
+    for v in (0..n as u32).filter(|&v| active(v)) {
+        let root = sets.find(v) as usize;
+        if label_of_root[root] == 0 {
+            count += 1;
+            label_of_root[root] = count as u32;
+        }
+        labels[v as usize] = label_of_root[root];
+    }
+    Ok((labels, count))
+}
+
+/// Cluster of each of `point_count` seeds over the Delaunay `triangles`,
+/// 0 where `active_mask` is 0 and 1 to the cluster count elsewhere
+///
+/// An active seed whose neighbours are all inactive, or that no triangle
+/// references, is a cluster of its own.
+#[wasm_bindgen]
+pub fn label_cell_clusters(
+    triangles: &[u32],
+    point_count: u32,
+    active_mask: &[u8],
+) -> Result<Vec<u32>> {
+    Ok(label(triangles, point_count as usize, active_mask)?.0)
+}
+
+/// `label_cell_clusters` for the seeds `points_flat`, along with each
+/// cluster's size and the bounding box of its seeds
+#[wasm_bindgen]
+pub fn cell_clusters(
+    points_flat: &[f64],
+    triangles: &[u32],
+    active_mask: &[u8],
+) -> Result<CellClusters> {
+    let n = check_points(points_flat)?;
+    let (labels, count) = label(triangles, n, active_mask)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let mut member_counts = vec![0u32; count];
+    let mut bounds = [f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY]
+        .repeat(count);
+    for (p, &l) in points_flat.chunks_exact(2).zip(&labels) {
+        if l == 0 {
+            continue;
+        }
+        let k = l as usize - 1;
+        member_counts[k] += 1;
+        let b = &mut bounds[k * 4..k * 4 + 4];
+        b[0] = b[0].min(p[0]);
+        b[1] = b[1].min(p[1]);
+        b[2] = b[2].max(p[0]);
+        b[3] = b[3].max(p[1]);
+    }
+    Ok(CellClusters { labels, member_counts, bounds })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_clusters_are_numbered_by_smallest_seed() {
+        // Two unit quads side by side: 0-1-2 on the bottom row, 3-4-5 above
+        let points = [0.0, 0.0, 1.0, 0.0, 2.0, 0.0, 0.0, 1.0, 1.0, 1.0, 2.0, 1.0];
+        let triangles = [0, 1, 4, 0, 4, 3, 1, 2, 5, 1, 5, 4];
+        // With the middle column off, 0-3 and 2-5 are apart
+        let mask = [1, 0, 1, 1, 0, 1];
+        let labels = label_cell_clusters(&triangles, 6, &mask).unwrap();
+        assert_eq!(labels, [1, 0, 2, 1, 0, 2]);
+
+        // Switching 4 on merges them under the smaller seed's label, and a
+        // seed outside every triangle is its own cluster
+        let clusters = cell_clusters(&points, &triangles, &[1, 0, 1, 1, 1, 1]).unwrap();
+        assert_eq!(clusters.labels(), [1, 0, 1, 1, 1, 1]);
+        let lone = [points.as_slice(), &[5.0, 5.0]].concat();
+        let clusters = cell_clusters(&lone, &triangles, &[0, 0, 1, 0, 0, 1, 1]).unwrap();
+        assert_eq!(clusters.labels(), [0, 0, 1, 0, 0, 1, 2]);
+        assert_eq!(clusters.cluster_count(), 2);
+        assert_eq!(clusters.member_counts(), [2, 1]);
+        assert_eq!(clusters.bounds(), [2.0, 0.0, 2.0, 1.0, 5.0, 5.0, 5.0, 5.0]);
+
+        let none = cell_clusters(&points, &triangles, &[0; 6]).unwrap();
+        assert_eq!((none.labels(), none.cluster_count()), (vec![0; 6], 0));
+    }
+
+    #[test]
+    fn test_cluster_errors() {
+        let triangles = [0, 1, 2];
+        assert_eq!(code_of(label_cell_clusters(&triangles, 3, &[1, 1])), ErrorCode::InvalidLength);
+        let out_of_range = label_cell_clusters(&triangles, 2, &[1, 1]);
+        assert_eq!(code_of(out_of_range), ErrorCode::IndexOutOfRange);
+        let points = [0.0, 0.0, 1.0, f64::NAN, 0.0, 1.0];
+        let bad = cell_clusters(&points, &triangles, &[1, 1, 1]);
+        assert_eq!(code_of(bad), ErrorCode::NonFiniteInput);
+    }
+}
@@ -0,0 +1,200 @@
+//! Little-endian byte encoding for snapshots
+//!
+//! Writers append fixed-width fields; readers bounds-check every read and
+//! report truncation as an error, so corrupt input can never panic.
+
+use crate::error::{Error, Result};
+
+#[derive(Default)]
+pub(crate) struct ByteWriter {
+    pub(crate) buf: Vec<u8>,
+}
+
+impl ByteWriter {
+    pub(crate) fn new() -> ByteWriter {
+        ByteWriter::default()
+    }
+
+    pub(crate) fn bytes(&mut self, v: &[u8]) {
+        self.buf.extend_from_slice(v);
+    }
+
+    pub(crate) fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+
+    pub(crate) fn u32(&mut self, v: u32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, v: u64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn f32(&mut self, v: f32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn f64(&mut self, v: f64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    /// Length-prefixed sequences
+    pub(crate) fn u8s(&mut self, v: &[u8]) {
+        self.u32(v.len() as u32);
+        self.bytes(v);
+    }
+
+    pub(crate) fn u32s(&mut self, v: &[u32]) {
+        self.u32(v.len() as u32);
+        v.iter().for_each(|&x| self.u32(x));
+    }
+
+    #[cfg_attr(not(feature = "motes"), allow(dead_code))]
+    pub(crate) fn f32s(&mut self, v: &[f32]) {
+        self.u32(v.len() as u32);
+        v.iter().for_each(|&x| self.f32(x));
+    }
+
+    pub(crate) fn f64s(&mut self, v: &[f64]) {
+        self.u32(v.len() as u32);
+        v.iter().for_each(|&x| self.f64(x));
+    }
+}
+
+pub(crate) struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> ByteReader<'a> {
+        ByteReader { data, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).filter(|&e| e <= self.data.len());
+        let Some(end) = end else {
+            return Err(Error::InvalidData(format!(
+                "truncated input: needed {n} bytes at offset {}, have {}",
+                self.pos,
+                self.data.len() - self.pos
+            )));
+        };
+        let out = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    /// Check a magic tag and return the version byte that follows it
+    pub(crate) fn header(&mut self, magic: &[u8; 4]) -> Result<u8> {
+        if self.take(4)? != magic {
+            return Err(Error::InvalidData(format!(
+                "bad magic, expected {:?}",
+                String::from_utf8_lossy(magic)
+            )));
+        }
+        self.u8()
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn f64(&mut self) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Length prefix, refusing counts the remaining bytes cannot hold
+    fn len(&mut self, elem_size: usize) -> Result<usize> {
+        let n = self.u32()? as usize;
+        if n.saturating_mul(elem_size) > self.data.len() - self.pos {
+            return Err(Error::InvalidData(format!(
+                "sequence of {n} elements overruns the input"
+            )));
+        }
+        Ok(n)
+    }
+
+    pub(crate) fn u8s(&mut self) -> Result<Vec<u8>> {
+        let n = self.len(1)?;
+        Ok(self.take(n)?.to_vec())
+    }
+
+    pub(crate) fn u32s(&mut self) -> Result<Vec<u32>> {
+        let n = self.len(4)?;
+        (0..n).map(|_| self.u32()).collect()
+    }
+
+    #[cfg_attr(not(feature = "motes"), allow(dead_code))]
+    pub(crate) fn f32s(&mut self) -> Result<Vec<f32>> {
+        let n = self.len(4)?;
+        (0..n).map(|_| self.f32()).collect()
+    }
+
+    pub(crate) fn f64s(&mut self) -> Result<Vec<f64>> {
+        let n = self.len(8)?;
+        (0..n).map(|_| self.f64()).collect()
+    }
+
+    /// Error unless every byte has been consumed
+    pub(crate) fn finish(&self) -> Result<()> {
+        if self.pos != self.data.len() {
+            return Err(Error::InvalidData(format!(
+                "{} trailing bytes",
+                self.data.len() - self.pos
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_codec_round_trip() {
+        let mut w = ByteWriter::new();
+        w.bytes(b"TEST");
+        w.u8(3);
+        w.u64(u64::MAX - 1);
+        w.f64s(&[1.5, -0.0, f64::INFINITY]);
+        w.u32s(&[7, 8]);
+
+        let mut r = ByteReader::new(&w.buf);
+        assert_eq!(r.header(b"TEST").unwrap(), 3);
+        assert_eq!(r.u64().unwrap(), u64::MAX - 1);
+        let f = r.f64s().unwrap();
+        assert_eq!(f[0], 1.5);
+        assert!(f[1].is_sign_negative());
+        assert_eq!(r.u32s().unwrap(), vec![7, 8]);
+        r.finish().unwrap();
+    }
+
+    #[test]
+    fn test_codec_rejects_truncation_and_huge_lengths() {
+        let mut r = ByteReader::new(&[1, 2]);
+        assert_eq!(code_of(r.u32()), ErrorCode::InvalidData);
+
+        let mut w = ByteWriter::new();
+        w.u32(u32::MAX);
+        let mut r = ByteReader::new(&w.buf);
+        assert_eq!(code_of(r.f64s()), ErrorCode::InvalidData);
+
+        assert_eq!(code_of(ByteReader::new(b"NOPE\x01").header(b"TEST")), ErrorCode::InvalidData);
+    }
+}
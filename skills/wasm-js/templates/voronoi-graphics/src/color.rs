@@ -0,0 +1,294 @@
+//! Color ramps for mapping scalar fields to RGBA
+//!
+//! A ramp is a sorted list of stops; sampling finds the surrounding pair
+//! and blends them either in gamma-encoded sRGB (cheap, matches CSS
+//! gradients) or in OkLab (perceptually even, no muddy midpoints).
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+
+/// Blend curve applied between two neighbouring stops
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear = 0,
+    Smoothstep = 1,
+}
+
+/// Space in which stop colors are blended
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorSpace {
+    Srgb = 0,
+    Oklab = 1,
+}
+
+/// Scalar → RGBA lookup built from positioned color stops
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ColorRamp {
+    positions: Vec<f32>,
+    /// Stop colors in the blend space, alpha last, all in [0, 1]
+    stops: Vec<[f64; 4]>,
+    interpolation: Interpolation,
+    space: ColorSpace,
+}
+
+#[wasm_bindgen]
+impl ColorRamp {
+    /// Build a ramp from stop positions and RGBA bytes (4 per stop)
+    ///
+    /// Positions must be finite and strictly increasing.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        positions: &[f32],
+        colors_rgba: &[u8],
+        interpolation: Interpolation,
+        space: ColorSpace,
+    ) -> Result<ColorRamp> {
+        if positions.is_empty() {
+            return Err(Error::InvalidLength("color ramp needs at least one stop".into()));
+        }
+        if colors_rgba.len() != positions.len() * 4 {
+            return Err(Error::InvalidLength(format!(
+                "expected {} color bytes for {} stops, got {}",
+                positions.len() * 4,
+                positions.len(),
+                colors_rgba.len()
+            )));
+        }
+        if positions.iter().any(|p| !p.is_finite()) {
+            return Err(Error::NonFiniteInput("stop positions must be finite".into()));
+        }
+        if positions.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(Error::InvalidArgument(
+                "stop positions must be strictly increasing".into(),
+            ));
+        }
+
+        let stops = colors_rgba
+            .chunks_exact(4)
+            .map(|c| {
+                let rgb = [c[0], c[1], c[2]].map(|v| v as f64 / 255.0);
+                let [x, y, z] = match space {
+                    ColorSpace::Srgb => rgb,
+                    ColorSpace::Oklab => srgb_to_oklab(rgb),
+                };
+                [x, y, z, c[3] as f64 / 255.0]
+            })
+            .collect();
+
+        Ok(ColorRamp {
+            positions: positions.to_vec(),
+            stops,
+            interpolation,
+            space,
+        })
+    }
+
+    /// Number of stops in the ramp
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Always false; a ramp cannot be constructed without stops
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Sample the ramp at `t`, packed as `0xRRGGBBAA`
+    ///
+    /// `t` outside the stop range clamps to the end stops.
+    pub fn sample(&self, t: f32) -> u32 {
+        u32::from_be_bytes(self.sample_rgba(t))
+    }
+
+    /// Map every value to RGBA bytes in one call (4 bytes per value)
+    ///
+    /// The result can be uploaded directly as a `Uint8ClampedArray`.
+    pub fn map(&self, values: &[f32]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(values.len() * 4);
+        for &v in values {
+            out.extend_from_slice(&self.sample_rgba(v));
+        }
+        out
+    }
+}
+
+impl ColorRamp {
+    /// Sample the ramp at `t` as `[r, g, b, a]` bytes
+    pub fn sample_rgba(&self, t: f32) -> [u8; 4] {
+        let n = self.positions.len();
+        // NaN falls through to the first stop rather than poisoning the blend
+        if n == 1 || t.is_nan() || t <= self.positions[0] {
+            return self.encode(self.stops[0]);
+        }
+        if t >= self.positions[n - 1] {
+            return self.encode(self.stops[n - 1]);
+        }
+
+        // First stop strictly greater than t; guaranteed in 1..n by the clamps above
+        let hi = self.positions.partition_point(|&p| p <= t);
+        let lo = hi - 1;
+        let span = (self.positions[hi] - self.positions[lo]) as f64;
+        let mut f = (t - self.positions[lo]) as f64 / span;
+        if self.interpolation == Interpolation::Smoothstep {
+            f = f * f * (3.0 - 2.0 * f);
+        }
+
+        let a = self.stops[lo];
+        let b = self.stops[hi];
+        self.encode([
+            a[0] + (b[0] - a[0]) * f,
+            a[1] + (b[1] - a[1]) * f,
+            a[2] + (b[2] - a[2]) * f,
+            a[3] + (b[3] - a[3]) * f,
+        ])
+    }
+
+    fn encode(&self, c: [f64; 4]) -> [u8; 4] {
+        let rgb = match self.space {
+            ColorSpace::Srgb => [c[0], c[1], c[2]],
+            ColorSpace::Oklab => oklab_to_srgb([c[0], c[1], c[2]]),
+        };
+        [
+            unit_to_byte(rgb[0]),
+            unit_to_byte(rgb[1]),
+            unit_to_byte(rgb[2]),
+            unit_to_byte(c[3]),
+        ]
+    }
+}
+
+fn unit_to_byte(v: f64) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Gamma-encoded sRGB in [0, 1] → OkLab (Björn Ottosson's matrices)
+pub(crate) fn srgb_to_oklab(rgb: [f64; 3]) -> [f64; 3] {
+    let [r, g, b] = rgb.map(srgb_to_linear);
+
+    let l = 0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b;
+    let m = 0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b;
+    let s = 0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b;
+
+    let (l, m, s) = (l.cbrt(), m.cbrt(), s.cbrt());
+
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+/// OkLab → gamma-encoded sRGB, unclamped (blends can leave the gamut)
+pub(crate) fn oklab_to_srgb(lab: [f64; 3]) -> [f64; 3] {
+    let [lightness, a, b] = lab;
+
+    let l = lightness + 0.3963377774 * a + 0.2158037573 * b;
+    let m = lightness - 0.1055613458 * a - 0.0638541728 * b;
+    let s = lightness - 0.0894841775 * a - 1.2914855480 * b;
+
+    let (l, m, s) = (l * l * l, m * m * m, s * s * s);
+
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
+    .map(linear_to_srgb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    const BLACK_WHITE: [u8; 8] = [0, 0, 0, 255, 255, 255, 255, 255];
+
+    #[test]
+    fn test_ramp_clamps_to_end_stops() {
+        let ramp = ColorRamp::new(&[0.2, 0.8], &BLACK_WHITE, Interpolation::Linear, ColorSpace::Srgb)
+            .unwrap();
+        assert_eq!(ramp.sample(-5.0), 0x000000FF);
+        assert_eq!(ramp.sample(0.2), 0x000000FF);
+        assert_eq!(ramp.sample(0.8), 0xFFFFFFFF);
+        assert_eq!(ramp.sample(42.0), 0xFFFFFFFF);
+    }
+
+    #[test]
+    fn test_ramp_linear_and_smoothstep() {
+        let colors = [0, 0, 0, 0, 200, 100, 40, 255];
+        let linear = ColorRamp::new(&[0.0, 1.0], &colors, Interpolation::Linear, ColorSpace::Srgb)
+            .unwrap();
+        assert_eq!(linear.sample_rgba(0.5), [100, 50, 20, 128]);
+
+        let smooth = ColorRamp::new(&[0.0, 1.0], &colors, Interpolation::Smoothstep, ColorSpace::Srgb)
+            .unwrap();
+        // Smoothstep is symmetric about the midpoint but eases near the ends
+        assert_eq!(smooth.sample_rgba(0.5), [100, 50, 20, 128]);
+        assert!(smooth.sample_rgba(0.1)[0] < linear.sample_rgba(0.1)[0]);
+    }
+
+    #[test]
+    fn test_ramp_map_matches_sample() {
+        let ramp = ColorRamp::new(
+            &[0.0, 0.5, 1.0],
+            &[255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255],
+            Interpolation::Linear,
+            ColorSpace::Oklab,
+        )
+        .unwrap();
+        let values = [0.0, 0.25, 0.5, 0.9, 1.0];
+        let bytes = ramp.map(&values);
+        assert_eq!(bytes.len(), values.len() * 4);
+        for (i, &v) in values.iter().enumerate() {
+            assert_eq!(&bytes[i * 4..i * 4 + 4], &ramp.sample_rgba(v));
+        }
+        // Stops reproduce exactly after the OkLab round trip
+        assert_eq!(ramp.sample_rgba(0.5), [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn test_oklab_round_trip() {
+        for &rgb in &[[0.0, 0.0, 0.0], [1.0, 1.0, 1.0], [0.2, 0.6, 0.9], [1.0, 0.0, 0.5]] {
+            let back = oklab_to_srgb(srgb_to_oklab(rgb));
+            for k in 0..3 {
+                assert!((back[k] - rgb[k]).abs() < 1e-6, "{rgb:?} -> {back:?}");
+            }
+        }
+        // Perceptual midpoint of black and white sits below sRGB 128
+        let ramp = ColorRamp::new(&[0.0, 1.0], &BLACK_WHITE, Interpolation::Linear, ColorSpace::Oklab)
+            .unwrap();
+        assert!(ramp.sample_rgba(0.5)[0] < 128);
+    }
+
+    #[test]
+    fn test_ramp_rejects_bad_stops() {
+        let lin = Interpolation::Linear;
+        let srgb = ColorSpace::Srgb;
+        let code = |stops: &[f32], colors: &[u8]| code_of(ColorRamp::new(stops, colors, lin, srgb));
+        assert_eq!(code(&[], &[]), ErrorCode::InvalidLength);
+        assert_eq!(code(&[0.0, 1.0], &[0; 4]), ErrorCode::InvalidLength);
+        assert_eq!(code(&[0.5, 0.5], &BLACK_WHITE), ErrorCode::InvalidArgument);
+        assert_eq!(code(&[1.0, 0.0], &BLACK_WHITE), ErrorCode::InvalidArgument);
+        assert_eq!(code(&[0.0, f32::NAN], &BLACK_WHITE), ErrorCode::NonFiniteInput);
+    }
+}
@@ -0,0 +1,432 @@
+//! Declarative scene setup from JSON
+//!
+//! One versioned document describes a whole scene, so designers can tune
+//! it without a setter call per knob. Every section and field is optional
+//! except `version` and, for `Scene::from_config`, the canvas size and seed
+//! count; unknown fields are rejected so a typo fails loudly instead of
+//! doing nothing.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "canvas": { "width": 1920, "height": 1080 },
+//!   "rng_seed": 7,
+//!   "seeds": { "generator": "golden_spiral", "count": 500 },
+//!   "motes": { "count": 200, "speed_min": 0.2, "speed_max": 0.6 },
+//!   "drift": { "amplitude": 12, "frequency": 0.004, "speed": 0.15 },
+//!   "heat": { "decay": 0.5 },
+//!   "clock": { "speed": 1, "paused": false },
+//!   "palette": {
+//!     "space": "oklab",
+//!     "interpolation": "linear",
+//!     "stops": [
+//!       { "position": 0, "color": [20, 24, 40, 255] },
+//!       { "position": 4, "color": [255, 190, 90, 255] }
+//!     ]
+//!   }
+//! }
+//! ```
+//!
+//! Omitted fields take `Scene::new`'s defaults, with no motes and RNG seed
+//! 0. `palette` maps edge heat to `Scene::edge_colors`; `space` is `srgb`
+//! or `oklab` and `interpolation` is `linear` or `smoothstep`.
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::codec::{ByteReader, ByteWriter};
+use crate::color::{ColorRamp, ColorSpace, Interpolation};
+use crate::error::{Error, Result};
+use crate::scene::{Scene, SceneParams};
+
+/// Schema version `Scene::from_config` and `Scene::apply_config` accept
+pub const SCENE_CONFIG_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneConfig {
+    version: u32,
+    canvas: Option<CanvasConfig>,
+    rng_seed: Option<u32>,
+    seeds: Option<SeedsConfig>,
+    motes: Option<MotesConfig>,
+    drift: Option<DriftConfig>,
+    heat: Option<HeatConfig>,
+    clock: Option<ClockConfig>,
+    palette: Option<PaletteConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CanvasConfig {
+    width: Option<f64>,
+    height: Option<f64>,
+}
+
+/// Seed layout; the golden spiral is the only generator a scene supports
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SeedGenerator {
+    GoldenSpiral,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SeedsConfig {
+    generator: Option<SeedGenerator>,
+    count: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MotesConfig {
+    count: Option<usize>,
+    speed_min: Option<f64>,
+    speed_max: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DriftConfig {
+    amplitude: Option<f64>,
+    frequency: Option<f64>,
+    speed: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HeatConfig {
+    decay: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClockConfig {
+    speed: Option<f64>,
+    paused: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SpaceName {
+    #[default]
+    Srgb,
+    Oklab,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum InterpolationName {
+    #[default]
+    Linear,
+    Smoothstep,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PaletteConfig {
+    #[serde(default)]
+    space: SpaceName,
+    #[serde(default)]
+    interpolation: InterpolationName,
+    stops: Vec<StopConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StopConfig {
+    position: f32,
+    color: [u8; 4],
+}
+
+/// A configured color ramp together with the stops it was built from
+#[derive(Clone, Debug)]
+pub(crate) struct Palette {
+    config: PaletteConfig,
+    pub(crate) ramp: ColorRamp,
+}
+
+impl Palette {
+    fn new(config: PaletteConfig) -> Result<Palette> {
+        let positions: Vec<f32> = config.stops.iter().map(|s| s.position).collect();
+        let colors: Vec<u8> = config.stops.iter().flat_map(|s| s.color).collect();
+        let interpolation = match config.interpolation {
+            InterpolationName::Linear => Interpolation::Linear,
+            InterpolationName::Smoothstep => Interpolation::Smoothstep,
+        };
+        let space = match config.space {
+            SpaceName::Srgb => ColorSpace::Srgb,
+            SpaceName::Oklab => ColorSpace::Oklab,
+        };
+        let ramp = ColorRamp::new(&positions, &colors, interpolation, space)?;
+        Ok(Palette { config, ramp })
+    }
+
+    pub(crate) fn encode(&self, w: &mut ByteWriter) {
+        w.u8(self.config.space as u8);
+        w.u8(self.config.interpolation as u8);
+        w.f32s(&self.config.stops.iter().map(|s| s.position).collect::<Vec<f32>>());
+        let colors: Vec<u32> =
+            self.config.stops.iter().map(|s| u32::from_be_bytes(s.color)).collect();
+        w.u32s(&colors);
+    }
+
+    pub(crate) fn decode(r: &mut ByteReader) -> Result<Palette> {
+        let bad = || Error::InvalidData("snapshot: bad palette".into());
+        let space = [SpaceName::Srgb, SpaceName::Oklab].get(r.u8()? as usize).copied();
+        let interpolation = [InterpolationName::Linear, InterpolationName::Smoothstep]
+            .get(r.u8()? as usize)
+            .copied();
+        let positions = r.f32s()?;
+        let colors = r.u32s()?;
+        let (Some(space), Some(interpolation)) = (space, interpolation) else {
+            return Err(bad());
+        };
+        if positions.len() != colors.len() {
+            return Err(bad());
+        }
+        let stops = positions
+            .iter()
+            .zip(&colors)
+            .map(|(&position, &c)| StopConfig { position, color: c.to_be_bytes() })
+            .collect();
+        Palette::new(PaletteConfig { space, interpolation, stops }).map_err(|_| bad())
+    }
+}
+
+fn parse(json: &str) -> Result<SceneConfig> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| Error::InvalidData(format!("config: {e}")))?;
+    // Checked first so a newer document reports its version, not the
+    // fields this build does not know
+    match value.get("version").and_then(|v| v.as_u64()) {
+        Some(v) if v == SCENE_CONFIG_VERSION as u64 => {}
+        Some(v) => {
+            return Err(Error::VersionMismatch(format!(
+                "config version {v}, expected {SCENE_CONFIG_VERSION}"
+            )))
+        }
+        None => return Err(Error::InvalidData("config: missing numeric `version`".into())),
+    }
+    serde_json::from_value(value).map_err(|e| Error::InvalidData(format!("config: {e}")))
+}
+
+#[wasm_bindgen]
+impl Scene {
+    /// Build a scene from a JSON config; see the module docs for the schema
+    pub fn from_config(json: &str) -> Result<Scene> {
+        let config = parse(json)?;
+        let canvas = config.canvas.as_ref();
+        let (Some(width), Some(height)) =
+            (canvas.and_then(|c| c.width), canvas.and_then(|c| c.height))
+        else {
+            return Err(Error::InvalidArgument(
+                "config: canvas width and height are required".into(),
+            ));
+        };
+        let Some(seed_count) = config.seeds.as_ref().and_then(|s| s.count) else {
+            return Err(Error::InvalidArgument("config: seeds.count is required".into()));
+        };
+        let mote_count = config.motes.as_ref().and_then(|m| m.count).unwrap_or(0);
+        let params = merge_params(SceneParams::default(), &config);
+        let seed = config.rng_seed.unwrap_or(0);
+        let mut scene = Scene::with_params(width, height, seed_count, mote_count, seed, params)?;
+        apply_runtime(&mut scene, &config)?;
+        Ok(scene)
+    }
+
+    /// Apply a (possibly partial) JSON config to the live scene
+    ///
+    /// Only fields present change anything, and a present `palette`
+    /// replaces the current one. The canvas size and RNG seed are fixed
+    /// at construction and must match if given. The update is atomic:
+    /// on error the scene is left untouched.
+    pub fn apply_config(&mut self, json: &str) -> Result<()> {
+        let config = parse(json)?;
+        if let Some(canvas) = &config.canvas {
+            if canvas.width.is_some_and(|w| w != self.width)
+                || canvas.height.is_some_and(|h| h != self.height)
+            {
+                return Err(Error::InvalidArgument(
+                    "config: the canvas size of a live scene cannot change".into(),
+                ));
+            }
+        }
+        if config.rng_seed.is_some_and(|s| s != self.seed) {
+            return Err(Error::InvalidArgument(
+                "config: the RNG seed of a live scene cannot change".into(),
+            ));
+        }
+
+        let mut next = self.clone();
+        let params = merge_params(next.params, &config);
+        params.validate()?;
+        next.params = params;
+        if let Some(count) = config.seeds.as_ref().and_then(|s| s.count) {
+            next.set_seed_count(count)?;
+        }
+        if let Some(count) = config.motes.as_ref().and_then(|m| m.count) {
+            next.set_mote_count(count);
+        }
+        apply_runtime(&mut next, &config)?;
+        *self = next;
+        Ok(())
+    }
+
+    /// The scene's setup as a complete config document
+    ///
+    /// `Scene::from_config` on it builds the same scene as this one was
+    /// built as, with the current counts and tunables.
+    pub fn current_config(&self) -> String {
+        let p = self.params;
+        let config = SceneConfig {
+            version: SCENE_CONFIG_VERSION,
+            canvas: Some(CanvasConfig { width: Some(self.width), height: Some(self.height) }),
+            rng_seed: Some(self.seed),
+            seeds: Some(SeedsConfig {
+                generator: Some(SeedGenerator::GoldenSpiral),
+                count: Some(self.seed_count()),
+            }),
+            motes: Some(MotesConfig {
+                count: Some(self.mote_count()),
+                speed_min: Some(p.mote_speed_min),
+                speed_max: Some(p.mote_speed_max),
+            }),
+            drift: Some(DriftConfig {
+                amplitude: Some(p.drift_amplitude),
+                frequency: Some(p.drift_frequency),
+                speed: Some(p.drift_speed),
+            }),
+            heat: Some(HeatConfig { decay: Some(p.heat_decay) }),
+            clock: Some(ClockConfig {
+                speed: Some(self.clock.speed),
+                paused: Some(self.clock.paused),
+            }),
+            palette: self.palette.as_ref().map(|p| p.config.clone()),
+        };
+        serde_json::to_string(&config).unwrap_or_default()
+    }
+}
+
+/// `params` with every tunable the config sets replaced
+fn merge_params(params: SceneParams, config: &SceneConfig) -> SceneParams {
+    let mut p = params;
+    if let Some(m) = &config.motes {
+        p.mote_speed_min = m.speed_min.unwrap_or(p.mote_speed_min);
+        p.mote_speed_max = m.speed_max.unwrap_or(p.mote_speed_max);
+    }
+    if let Some(d) = &config.drift {
+        p.drift_amplitude = d.amplitude.unwrap_or(p.drift_amplitude);
+        p.drift_frequency = d.frequency.unwrap_or(p.drift_frequency);
+        p.drift_speed = d.speed.unwrap_or(p.drift_speed);
+    }
+    if let Some(h) = &config.heat {
+        p.heat_decay = h.decay.unwrap_or(p.heat_decay);
+    }
+    p
+}
+
+/// Clock and palette settings, shared by construction and live updates
+fn apply_runtime(scene: &mut Scene, config: &SceneConfig) -> Result<()> {
+    if let Some(clock) = &config.clock {
+        if let Some(speed) = clock.speed {
+            scene.clock.set_speed(speed)?;
+        }
+        match clock.paused {
+            Some(true) => scene.clock.pause(),
+            Some(false) => scene.clock.resume(),
+            None => {}
+        }
+    }
+    if let Some(palette) = &config.palette {
+        scene.palette = Some(Palette::new(palette.clone())?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    const FULL: &str = r#"{
+        "version": 1,
+        "canvas": { "width": 800, "height": 600 },
+        "rng_seed": 7,
+        "seeds": { "generator": "golden_spiral", "count": 150 },
+        "motes": { "count": 40, "speed_min": 0.3, "speed_max": 0.9 },
+        "drift": { "amplitude": 20.5, "frequency": 0.01, "speed": 0.7 },
+        "heat": { "decay": 0.25 },
+        "clock": { "speed": 0.5, "paused": false },
+        "palette": {
+            "space": "oklab",
+            "stops": [
+                { "position": 0, "color": [0, 0, 0, 255] },
+                { "position": 3, "color": [255, 200, 80, 255] }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn test_config_round_trips() {
+        let mut scene = Scene::from_config(FULL).unwrap();
+        assert_eq!((scene.seed_count(), scene.mote_count(), scene.seed), (150, 40, 7));
+        assert_eq!(scene.params.drift_amplitude, 20.5);
+        assert_eq!(scene.clock.speed, 0.5);
+        for m in &scene.motes {
+            assert!((0.3..=0.9).contains(&m.speed));
+        }
+
+        let json = scene.current_config();
+        let rebuilt = Scene::from_config(&json).unwrap();
+        assert_eq!(rebuilt.current_config(), json);
+        assert_eq!(rebuilt.snapshot(), scene.snapshot());
+
+        // Applying the current config to a running scene changes nothing
+        for _ in 0..30 {
+            scene.update(1.0 / 60.0).unwrap();
+        }
+        assert_eq!(scene.edge_colors().len(), scene.edge_heat().len() * 4);
+        let before = scene.snapshot();
+        scene.apply_config(&scene.current_config()).unwrap();
+        assert_eq!(scene.snapshot(), before);
+        assert_eq!(Scene::restore(&before).unwrap().current_config(), scene.current_config());
+    }
+
+    #[test]
+    fn test_apply_config_is_partial_and_atomic() {
+        let base = r#"{ "version": 1, "canvas": { "width": 400, "height": 300 },
+            "seeds": { "count": 60 } }"#;
+        let mut scene = Scene::from_config(base).unwrap();
+        assert_eq!((scene.mote_count(), scene.seed), (0, 0));
+        assert!(scene.edge_colors().is_empty());
+
+        scene
+            .apply_config(r#"{ "version": 1, "seeds": { "count": 90 }, "motes": { "count": 12 },
+                "clock": { "paused": true } }"#)
+            .unwrap();
+        assert_eq!((scene.seed_count(), scene.mote_count()), (90, 12));
+        assert!(scene.clock.paused);
+
+        let before = scene.snapshot();
+        let rejected = [
+            (r#"{ "version": 1, "drfit": { "speed": 1 } }"#, ErrorCode::InvalidData),
+            (r#"{ "version": 1, "motes": { "cuont": 3 } }"#, ErrorCode::InvalidData),
+            (r#"{ "version": 2, "anything": true }"#, ErrorCode::VersionMismatch),
+            (r#"{ "motes": { "count": 3 } }"#, ErrorCode::InvalidData),
+            (r#"{ "version": 1, "canvas": { "width": 10 } }"#, ErrorCode::InvalidArgument),
+            (
+                r#"{ "version": 1, "seeds": { "count": 100 }, "heat": { "decay": -1 } }"#,
+                ErrorCode::InvalidArgument,
+            ),
+            (r#"{ "version": 1, "seeds": { "count": 2 } }"#, ErrorCode::InvalidArgument),
+            (r#"{ "version": 1, "palette": { "stops": [] } }"#, ErrorCode::InvalidLength),
+        ];
+        for (json, code) in rejected {
+            assert_eq!(code_of(scene.apply_config(json)), code, "{json}");
+            assert_eq!(scene.snapshot(), before, "{json} left changes behind");
+        }
+        let missing = r#"{ "version": 1, "canvas": { "width": 400, "height": 300 } }"#;
+        assert_eq!(code_of(Scene::from_config(missing)), ErrorCode::InvalidArgument);
+    }
+}
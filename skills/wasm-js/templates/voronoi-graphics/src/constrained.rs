@@ -0,0 +1,458 @@
+//! Constrained Delaunay triangulation: required edges forced into the mesh
+//!
+//! The points are triangulated as usual, then each constraint is recovered
+//! by edge flips (Sloan, 1993): the edges crossing it are queued and
+//! flipped one by one wherever their quad is convex, until none is left.
+//! Those flips are then undone where they need not be, by Lawson flips
+//! that never touch a constraint, so the result is constrained Delaunay:
+//! no circumcircle holds a point visible from inside its triangle.
+//!
+//! No points are added. A constraint running exactly through another
+//! point is split there into two, and constraints that cross each other
+//! cannot both be edges, so they are rejected.
+
+use std::collections::{HashSet, VecDeque};
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::mesh::{Mesh, NONE, SUPER};
+use crate::predicates::orient2d;
+use crate::Point;
+
+fn canonical(a: u32, b: u32) -> (u32, u32) {
+    (a.min(b), a.max(b))
+}
+
+/// Strictly opposite signs
+fn opposite(o1: f64, o2: f64) -> bool {
+    (o1 > 0.0 && o2 < 0.0) || (o1 < 0.0 && o2 > 0.0)
+}
+
+fn stuck() -> Error {
+    Error::DegenerateGeometry("constraint recovery did not converge".into())
+}
+
+/// How a constraint meets the mesh around its first endpoint
+enum Walk {
+    /// The segment runs exactly through this vertex
+    Through(u32),
+    /// These edges cross it, in order from the first endpoint
+    Crossing(Vec<(u32, u32)>),
+}
+
+/// A Delaunay mesh with the set of edges flips must keep, both in
+/// internal vertex numbers
+struct Constrained {
+    mesh: Mesh,
+    fixed: HashSet<(u32, u32)>,
+}
+
+impl Constrained {
+    fn point(&self, v: u32) -> Point {
+        self.mesh.points[v as usize]
+    }
+
+    /// `(t, k)` with edge `k` of triangle `t` joining `x` and `y`
+    fn find_edge(&self, x: u32, y: u32) -> Option<(u32, usize)> {
+        let star = self.mesh.star(x, self.mesh.vert_tri[x as usize])?;
+        star.into_iter().find_map(|t| {
+            let tv = self.mesh.tris[t as usize];
+            let i = tv.iter().position(|&w| w == x)?;
+            if tv[(i + 1) % 3] == y {
+                Some((t, (i + 2) % 3))
+            } else if tv[(i + 2) % 3] == y {
+                Some((t, (i + 1) % 3))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Whether `v` lies strictly between `a` and `b` on their segment
+    fn on_segment(&self, a: u32, b: u32, v: u32) -> bool {
+        let (pa, pb, pv) = (self.point(a), self.point(b), self.point(v));
+        let along = (pv.x - pa.x) * (pb.x - pa.x) + (pv.y - pa.y) * (pb.y - pa.y);
+        let back = (pv.x - pb.x) * (pa.x - pb.x) + (pv.y - pb.y) * (pa.y - pb.y);
+        orient2d(pa, pb, pv) == 0.0 && along > 0.0 && back > 0.0
+    }
+
+    fn crosses(&self, a: u32, b: u32, c: u32, d: u32) -> bool {
+        let [pa, pb, pc, pd] = [a, b, c, d].map(|v| self.point(v));
+        opposite(orient2d(pa, pb, pc), orient2d(pa, pb, pd))
+            && opposite(orient2d(pc, pd, pa), orient2d(pc, pd, pb))
+    }
+
+    /// Force the edge `a`-`b` into the mesh
+    fn recover(&mut self, a: u32, b: u32) -> Result<()> {
+        let mut pending = vec![(a, b)];
+        while let Some((a, b)) = pending.pop() {
+            if self.find_edge(a, b).is_none() {
+                match self.walk(a, b)? {
+                    Walk::Through(v) => {
+                        pending.push((v, b));
+                        pending.push((a, v));
+                        continue;
+                    }
+                    Walk::Crossing(edges) => self.flip_out(a, b, edges)?,
+                }
+            }
+            self.fixed.insert(canonical(a, b));
+        }
+        Ok(())
+    }
+
+    /// Follow the segment `a`-`b` through the triangles it crosses
+    fn walk(&self, a: u32, b: u32) -> Result<Walk> {
+        let (pa, pb) = (self.point(a), self.point(b));
+        let star = self.mesh.star(a, self.mesh.vert_tri[a as usize]).ok_or_else(stuck)?;
+        let opposite_side = |t: u32| {
+            let tv = self.mesh.tris[t as usize];
+            let i = tv.iter().position(|&w| w == a).unwrap_or(0);
+            (tv[(i + 1) % 3], tv[(i + 2) % 3])
+        };
+        let mut neighbors = star.iter().map(|&t| opposite_side(t).0);
+        if let Some(v) = neighbors.find(|&v| self.on_segment(a, b, v)) {
+            return Ok(Walk::Through(v));
+        }
+        // The triangle of the star the segment leaves `a` through; `x` ends
+        // up right of the segment and `y` left of it
+        let (mut t, (mut x, mut y)) = star
+            .iter()
+            .map(|&t| (t, opposite_side(t)))
+            .find(|&(_, (p, q))| {
+                orient2d(pa, self.point(p), pb) > 0.0 && orient2d(pa, self.point(q), pb) < 0.0
+            })
+            .ok_or_else(stuck)?;
+
+        let mut edges = Vec::new();
+        loop {
+            if self.fixed.contains(&canonical(x, y)) {
+                return Err(Error::InvalidArgument(format!(
+                    "constraint {}-{} crosses constraint {}-{}",
+                    a - SUPER,
+                    b - SUPER,
+                    x.min(y) - SUPER,
+                    x.max(y) - SUPER
+                )));
+            }
+            edges.push((x, y));
+            if edges.len() > self.mesh.tris.len() {
+                return Err(stuck());
+            }
+            let tv = self.mesh.tris[t as usize];
+            let k = tv.iter().position(|&w| w != x && w != y).unwrap_or(0);
+            let u = self.mesh.adj[t as usize][k];
+            if u == NONE {
+                return Err(stuck());
+            }
+            let w = self.mesh.tris[u as usize][self.mesh.neighbor_index(u, t)];
+            if w == b {
+                return Ok(Walk::Crossing(edges));
+            }
+            if self.on_segment(a, b, w) {
+                return Ok(Walk::Through(w));
+            }
+            if orient2d(pa, pb, self.point(w)) < 0.0 {
+                x = w;
+            } else {
+                y = w;
+            }
+            t = u;
+        }
+    }
+
+    /// Flip `edges`, all crossing `a`-`b`, until none does
+    fn flip_out(&mut self, a: u32, b: u32, edges: Vec<(u32, u32)>) -> Result<()> {
+        let mut queue: VecDeque<(u32, u32)> = edges.into();
+        let mut created = Vec::new();
+        // Some crossing edge always has a convex quad, so a whole pass
+        // over the queue without a flip means the input is degenerate
+        let mut idle = 0;
+        while let Some((x, y)) = queue.pop_front() {
+            let (t, k) = self.find_edge(x, y).ok_or_else(stuck)?;
+            let u = self.mesh.adj[t as usize][k];
+            let c = self.mesh.tris[t as usize][k];
+            let d = self.mesh.tris[u as usize][self.mesh.neighbor_index(u, t)];
+            let (pc, pd) = (self.point(c), self.point(d));
+            if !opposite(orient2d(pc, pd, self.point(x)), orient2d(pc, pd, self.point(y))) {
+                queue.push_back((x, y));
+                idle += 1;
+                if idle > queue.len() {
+                    return Err(stuck());
+                }
+                continue;
+            }
+            idle = 0;
+            self.mesh.flip(t, k);
+            if self.crosses(a, b, c, d) {
+                queue.push_back((c, d));
+            } else if canonical(c, d) != canonical(a, b) {
+                created.push((c, d));
+            }
+        }
+        self.fixed.insert(canonical(a, b));
+        self.restore(created)
+    }
+
+    /// Lawson flips from `stack` that leave every fixed edge alone
+    fn restore(&mut self, mut stack: Vec<(u32, u32)>) -> Result<()> {
+        let cap = 16 * stack.len() + 4 * self.mesh.tris.len();
+        let mut flips = 0;
+        while let Some((x, y)) = stack.pop() {
+            if self.fixed.contains(&canonical(x, y)) {
+                continue;
+            }
+            let Some((t, k)) = self.find_edge(x, y) else {
+                continue;
+            };
+            if !self.mesh.edge_is_illegal(t, k) {
+                continue;
+            }
+            flips += 1;
+            if flips > cap {
+                return Err(stuck());
+            }
+            let u = self.mesh.adj[t as usize][k];
+            let c = self.mesh.tris[t as usize][k];
+            let d = self.mesh.tris[u as usize][self.mesh.neighbor_index(u, t)];
+            self.mesh.flip(t, k);
+            stack.extend([(c, x), (x, d), (d, y), (y, c)]);
+        }
+        Ok(())
+    }
+
+    /// Live triangles inside the fixed edges by the even-odd rule
+    ///
+    /// Regions are the pieces left when the mesh is cut along every fixed
+    /// edge; a region is inside when the fewest fixed edges crossed to
+    /// reach it from outside the hull is odd. A constraint that closes no
+    /// loop separates nothing, so it does not change the result.
+    fn inside(&self) -> Vec<bool> {
+        let mesh = &self.mesh;
+        let slots = mesh.tris.len();
+        let side = |t: usize, k: usize| {
+            let tv = mesh.tris[t];
+            self.fixed.contains(&canonical(tv[(k + 1) % 3], tv[(k + 2) % 3]))
+        };
+        let mut region = vec![u32::MAX; slots];
+        let mut count = 0;
+        for start in (0..slots).filter(|&t| mesh.alive[t]) {
+            if region[start] != u32::MAX {
+                continue;
+            }
+            region[start] = count;
+            let mut stack = vec![start];
+            while let Some(t) = stack.pop() {
+                for k in (0..3).filter(|&k| !side(t, k)) {
+                    let u = mesh.adj[t][k];
+                    if u != NONE && region[u as usize] == u32::MAX {
+                        region[u as usize] = count;
+                        stack.push(u as usize);
+                    }
+                }
+            }
+            count += 1;
+        }
+
+        let mut depth = vec![u32::MAX; count as usize];
+        let mut queue = VecDeque::new();
+        for t in (0..slots).filter(|&t| mesh.alive[t] && !mesh.is_real_triangle(t)) {
+            if depth[region[t] as usize] == u32::MAX {
+                depth[region[t] as usize] = 0;
+                queue.push_back(region[t]);
+            }
+        }
+        let mut across = vec![Vec::new(); count as usize];
+        for t in (0..slots).filter(|&t| mesh.alive[t]) {
+            for k in (0..3).filter(|&k| side(t, k)) {
+                let u = mesh.adj[t][k];
+                if u != NONE {
+                    across[region[t] as usize].push(region[u as usize]);
+                }
+            }
+        }
+        while let Some(r) = queue.pop_front() {
+            for &s in &across[r as usize] {
+                if depth[s as usize] == u32::MAX {
+                    depth[s as usize] = depth[r as usize] + 1;
+                    queue.push_back(s);
+                }
+            }
+        }
+        (0..slots).map(|t| mesh.alive[t] && depth[region[t] as usize] % 2 == 1).collect()
+    }
+}
+
+/// Delaunay triangulation of `points_flat` in which every pair in
+/// `constraint_edges` (`[i0, j0, i1, j1, ...]`) is an edge
+///
+/// Same flat triangle layout as `compute_delaunay`, with no points added,
+/// so every downstream function applies. A constraint passing exactly
+/// through other points becomes the chain of edges between them, and a
+/// constraint naming a duplicate point uses the copy that was
+/// triangulated. With `inside_only`, only the triangles enclosed by
+/// closed constraint loops are returned, even-odd, so a loop inside a loop
+/// is a hole: a logo outline with its counters cut out.
+///
+/// Constraints that cross each other are an `InvalidArgument` error;
+/// touching at an endpoint is fine. As in `compute_delaunay`, the canvas
+/// size is unused and points may lie anywhere.
+#[wasm_bindgen]
+pub fn compute_constrained_delaunay(
+    points_flat: &[f64],
+    constraint_edges: &[u32],
+    _width: f64,
+    _height: f64,
+    inside_only: bool,
+) -> Result<Vec<u32>> {
+    let n = check_points(points_flat)?;
+    if !constraint_edges.len().is_multiple_of(2) {
+        return Err(Error::InvalidLength(format!(
+            "constraint index count {} is odd",
+            constraint_edges.len()
+        )));
+    }
+    if let Some(&v) = constraint_edges.iter().find(|&&v| v as usize >= n) {
+        return Err(Error::IndexOutOfRange(format!(
+            "constraint index {v} out of range for {n} points"
+        )));
+    }
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let points: Vec<Point> = points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+    let mesh = Mesh::build_closed(&points);
+    let mut cdt = Constrained { mesh, fixed: HashSet::new() };
+
+    for pair in constraint_edges.chunks_exact(2) {
+        let [a, b] = [pair[0], pair[1]].map(|v| {
+            let v = v + SUPER;
+            if cdt.mesh.vert_tri[v as usize] != NONE {
+                return v;
+            }
+            // A duplicate: the vertex at the same spot stands in for it
+            let p = cdt.point(v);
+            let t = cdt.mesh.locate(p).map_or(0, |t| t as usize);
+            let twin = cdt.mesh.tris[t].iter().find(|&&w| cdt.mesh.points[w as usize] == p);
+            twin.copied().unwrap_or(v)
+        });
+        if cdt.point(a) == cdt.point(b) {
+            return Err(Error::InvalidArgument(format!(
+                "constraint {}-{} has zero length",
+                pair[0], pair[1]
+            )));
+        }
+        cdt.recover(a, b)?;
+    }
+
+    let keep = inside_only.then(|| cdt.inside());
+    let mesh = &cdt.mesh;
+    Ok((0..mesh.tris.len())
+        .filter(|&t| mesh.is_real_triangle(t) && keep.as_ref().is_none_or(|k| k[t]))
+        .flat_map(|t| mesh.tris[t].map(|v| v - SUPER))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_delaunay;
+    use crate::error::{code_of, ErrorCode};
+    use crate::predicates::incircle;
+    use crate::rng::Rng;
+
+    fn has_edge(triangles: &[u32], a: u32, b: u32) -> bool {
+        triangles.chunks(3).any(|t| (0..3).any(|k| canonical(t[k], t[(k + 1) % 3]) == (a, b)))
+    }
+
+    fn area(points: &[f64], triangles: &[u32]) -> f64 {
+        let p = |v: u32| Point::new(points[v as usize * 2], points[v as usize * 2 + 1]);
+        triangles.chunks(3).map(|t| orient2d(p(t[0]), p(t[1]), p(t[2])) / 2.0).sum()
+    }
+
+    #[test]
+    fn test_constraints_become_edges_and_the_rest_stays_delaunay() {
+        let mut rng = Rng::new(8);
+        let points: Vec<f64> =
+            (0..400).flat_map(|_| [rng.range(0.0, 500.0), rng.range(0.0, 400.0)]).collect();
+        let plain = compute_delaunay(&points, 500.0, 400.0);
+        // Long chords between far-apart points, sharing endpoint 3
+        let p = |v: u32| Point::new(points[v as usize * 2], points[v as usize * 2 + 1]);
+        let far = |a: u32| {
+            let d = |w: u32| (p(w).x - p(a).x).powi(2) + (p(w).y - p(a).y).powi(2);
+            (0..400).max_by(|&u, &v| d(u).total_cmp(&d(v))).unwrap()
+        };
+        let (b, c) = (far(3), far(17));
+        let constraints = [3, b, 3, c];
+        let triangles = compute_constrained_delaunay(&points, &constraints, 500.0, 400.0, false);
+        let triangles = triangles.unwrap();
+        assert!(!has_edge(&plain, 3.min(b), 3.max(b)));
+        assert!(has_edge(&triangles, 3.min(b), 3.max(b)));
+        assert!(has_edge(&triangles, 3.min(c), 3.max(c)));
+        assert_eq!(triangles.len(), plain.len());
+        assert!((area(&points, &triangles) - area(&points, &plain)).abs() < 1e-6);
+
+        // Every edge except the chords is locally Delaunay
+        let chords = [canonical(3, b), canonical(3, c)];
+        for (i, t) in triangles.chunks(3).enumerate() {
+            assert!(orient2d(p(t[0]), p(t[1]), p(t[2])) > 0.0);
+            for (j, u) in triangles.chunks(3).enumerate().filter(|&(j, _)| j != i) {
+                let Some(k) = (0..3).find(|&k| u.contains(&t[k]) && u.contains(&t[(k + 1) % 3]))
+                else {
+                    continue;
+                };
+                let (x, y) = (t[k], t[(k + 1) % 3]);
+                if chords.contains(&canonical(x, y)) {
+                    continue;
+                }
+                let d = *u.iter().find(|v| !t.contains(v)).unwrap();
+                let [pa, pb, pc] = [t[0], t[1], t[2]].map(p);
+                assert!(incircle(pa, pb, pc, p(d)) <= 1e-6, "edge {x}-{y} of {i}, {j}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_loops_cut_out_an_outline_with_a_hole() {
+        // A lattice, so the hole's sides run through lattice points, plus
+        // the corners of a diamond whose sides cross lattice edges
+        let mut points: Vec<f64> =
+            (0..121).flat_map(|i| [(i % 11) as f64 * 10.0, (i / 11) as f64 * 10.0]).collect();
+        points.extend([50.0, 15.0, 85.0, 50.0, 50.0, 85.0, 15.0, 50.0]);
+        let lattice = |x: u32, y: u32| y * 11 + x;
+        let hole = [lattice(4, 4), lattice(6, 4), lattice(6, 6), lattice(4, 6)];
+        let diamond = [121, 122, 123, 124];
+        let mut constraints = Vec::new();
+        for chain in [&hole, &diamond] {
+            for k in 0..4 {
+                constraints.extend([chain[k], chain[(k + 1) % 4]]);
+            }
+        }
+        let all = compute_constrained_delaunay(&points, &constraints, 100.0, 100.0, false).unwrap();
+        assert!((area(&points, &all) - 100.0 * 100.0).abs() < 1e-9);
+        assert!(has_edge(&all, 121, 122) && has_edge(&all, lattice(4, 4), lattice(5, 4)));
+
+        let cut = compute_constrained_delaunay(&points, &constraints, 100.0, 100.0, true).unwrap();
+        assert!((area(&points, &cut) - (2.0 * 35.0 * 35.0 - 20.0 * 20.0)).abs() < 1e-9);
+        // An open chain encloses nothing
+        let open = compute_constrained_delaunay(&points, &constraints[..6], 100.0, 100.0, true);
+        assert!(open.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_constraint_errors() {
+        let square = [0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0, 0.0, 0.0];
+        let cdt = |c: &[u32]| code_of(compute_constrained_delaunay(&square, c, 10.0, 10.0, false));
+        // Whichever diagonal is chosen, forcing both cannot work
+        assert_eq!(cdt(&[0, 2, 1, 3]), ErrorCode::InvalidArgument);
+        assert_eq!(cdt(&[0, 4]), ErrorCode::InvalidArgument);
+        assert_eq!(cdt(&[0, 1, 2]), ErrorCode::InvalidLength);
+        assert_eq!(cdt(&[0, 5]), ErrorCode::IndexOutOfRange);
+        let both = compute_constrained_delaunay(&square, &[4, 2], 10.0, 10.0, false).unwrap();
+        assert!(has_edge(&both, 0, 2));
+        let other = compute_constrained_delaunay(&square, &[1, 3], 10.0, 10.0, false).unwrap();
+        assert!(has_edge(&other, 1, 3) && !has_edge(&other, 0, 2));
+    }
+}
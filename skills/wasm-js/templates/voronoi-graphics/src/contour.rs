@@ -0,0 +1,383 @@
+//! Iso-lines of a sampled scalar field by marching squares
+//!
+//! The field is a row-major `grid_w × grid_h` array of samples spaced
+//! `cell_size` apart, sample `(i, j)` at `(i · cell_size, j · cell_size)`.
+//! A sample counts as inside a level when it is at or above the iso value.
+//! Each square of four samples adds up to two segments whose ends are
+//! linearly interpolated along the square's sides; in the two saddle
+//! cases the mean of the corners decides, joining the inside corners when
+//! it is inside, so neighbouring squares always agree.
+//!
+//! Segments are also stitched into polylines. Every crossing lies on one
+//! side shared by at most two squares, so the segments form simple chains:
+//! open ones end at the grid border, everything else is a closed loop.
+//! Loops are closed implicitly, as cell polygons are, and flagged in
+//! `path_closed`.
+
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+
+/// Samples `noise_contours` will take in one call
+#[cfg(feature = "noise")]
+const MAX_SAMPLES: u64 = 1 << 24;
+
+/// Contour segments and stitched polylines from `marching_squares`
+#[wasm_bindgen]
+pub struct ContourSet {
+    segments: Vec<f32>,
+    segment_offsets: Vec<u32>,
+    paths: Vec<f32>,
+    path_offsets: Vec<u32>,
+    path_levels: Vec<u32>,
+    path_closed: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl ContourSet {
+    /// `[x0, y0, x1, y1, ...]`, one segment per 4 floats, level by level
+    pub fn segments(&self) -> Vec<f32> {
+        self.segments.clone()
+    }
+
+    /// One entry per iso value plus one, in segment units, so level `k` is
+    /// segments `segment_offsets[k]..segment_offsets[k + 1]`
+    pub fn segment_offsets(&self) -> Vec<u32> {
+        self.segment_offsets.clone()
+    }
+
+    /// Polyline vertices `[x0, y0, x1, y1, ...]`, path after path
+    pub fn paths(&self) -> Vec<f32> {
+        self.paths.clone()
+    }
+
+    /// One entry per path plus one, in vertex units
+    pub fn path_offsets(&self) -> Vec<u32> {
+        self.path_offsets.clone()
+    }
+
+    /// Index into `iso_values` of each path's level
+    pub fn path_levels(&self) -> Vec<u32> {
+        self.path_levels.clone()
+    }
+
+    /// 1 for each path that is a closed loop, 0 for one ending at the
+    /// grid border
+    pub fn path_closed(&self) -> Vec<u8> {
+        self.path_closed.clone()
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len() / 4
+    }
+
+    pub fn path_count(&self) -> usize {
+        self.path_levels.len()
+    }
+}
+
+/// Contour lines of `field` at each of `iso_values`
+///
+/// Coordinates are in the units of `cell_size`, so passing the canvas
+/// spacing of the samples puts the lines straight onto the canvas.
+#[wasm_bindgen]
+pub fn marching_squares(
+    field: &[f32],
+    grid_w: u32,
+    grid_h: u32,
+    iso_values: &[f32],
+    cell_size: f64,
+) -> Result<ContourSet> {
+    let (cols, rows) = (grid_w as usize, grid_h as usize);
+    if cols < 2 || rows < 2 {
+        return Err(Error::InvalidArgument(format!(
+            "a {grid_w}x{grid_h} grid has no squares; it needs at least 2x2 samples"
+        )));
+    }
+    if field.len() as u64 != grid_w as u64 * grid_h as u64 {
+        return Err(Error::InvalidLength(format!(
+            "a {grid_w}x{grid_h} grid needs {} samples, got {}",
+            grid_w as u64 * grid_h as u64,
+            field.len()
+        )));
+    }
+    if field.iter().chain(iso_values).any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("field samples and iso values must be finite".into()));
+    }
+    if !(cell_size.is_finite() && cell_size > 0.0) {
+        return Err(Error::InvalidArgument(format!(
+            "cell_size must be positive, got {cell_size}"
+        )));
+    }
+
+    let mut set = ContourSet {
+        segments: Vec::new(),
+        segment_offsets: vec![0],
+        paths: Vec::new(),
+        path_offsets: vec![0],
+        path_levels: Vec::new(),
+        path_closed: Vec::new(),
+    };
+    for (level, &iso) in iso_values.iter().enumerate() {
+        let links = level_segments(field, cols, rows, iso);
+        for &(a, b) in &links {
+            let (pa, pb) = (crossing(field, cols, iso, a), crossing(field, cols, iso, b));
+            set.segments.extend([pa, pb].iter().flat_map(|p| scaled(*p, cell_size)));
+        }
+        set.segment_offsets.push((set.segments.len() / 4) as u32);
+        for (chain, closed) in stitch(&links) {
+            for key in chain {
+                set.paths.extend(scaled(crossing(field, cols, iso, key), cell_size));
+            }
+            set.path_offsets.push((set.paths.len() / 2) as u32);
+            set.path_levels.push(level as u32);
+            set.path_closed.push(closed as u8);
+        }
+    }
+    Ok(set)
+}
+
+fn scaled((x, y): (f64, f64), cell_size: f64) -> [f32; 2] {
+    [(x * cell_size) as f32, (y * cell_size) as f32]
+}
+
+/// Segments of one level as pairs of crossing keys. A key names a grid
+/// side: `2 · (j · cols + i)` for the one from sample `(i, j)` to its
+/// right, plus 1 for the one down to `(i, j + 1)`.
+fn level_segments(field: &[f32], cols: usize, rows: usize, iso: f32) -> Vec<(usize, usize)> {
+    let mut links = Vec::new();
+    for j in 0..rows - 1 {
+        for i in 0..cols - 1 {
+            let at = |di: usize, dj: usize| field[(j + dj) * cols + i + di];
+            let v = [at(0, 0), at(1, 0), at(1, 1), at(0, 1)];
+            let case = (0..4).fold(0, |c, k| c | ((v[k] >= iso) as usize) << k);
+            let base = 2 * (j * cols + i);
+            // Corners run clockwise from the top left; sides are top,
+            // right, bottom and left
+            let keys = [base, base + 3, base + 2 * cols, base + 1];
+            let centre_inside = (v.iter().sum::<f32>() / 4.0) >= iso;
+            // Sides crossed, paired into segments
+            let pairs: &[(usize, usize)] = match case {
+                0 | 15 => &[],
+                1 | 14 => &[(0, 3)],
+                2 | 13 => &[(0, 1)],
+                3 | 12 => &[(3, 1)],
+                4 | 11 => &[(1, 2)],
+                6 | 9 => &[(0, 2)],
+                7 | 8 => &[(3, 2)],
+                5 if centre_inside => &[(0, 1), (2, 3)],
+                5 => &[(0, 3), (1, 2)],
+                10 if centre_inside => &[(0, 3), (1, 2)],
+                _ => &[(0, 1), (2, 3)],
+            };
+            links.extend(pairs.iter().map(|&(a, b)| (keys[a], keys[b])));
+        }
+    }
+    links
+}
+
+/// Where the level crosses the grid side `key`, in sample units
+fn crossing(field: &[f32], cols: usize, iso: f32, key: usize) -> (f64, f64) {
+    let (start, across) = (key / 2, key.is_multiple_of(2));
+    let end = if across { start + 1 } else { start + cols };
+    let (a, b) = (field[start] as f64, field[end] as f64);
+    // One end is inside and the other is not, so `a != b`
+    let t = (iso as f64 - a) / (b - a);
+    let (i, j) = ((start % cols) as f64, (start / cols) as f64);
+    if across {
+        (i + t, j)
+    } else {
+        (i, j + t)
+    }
+}
+
+/// Chains of crossing keys joined through shared sides, with whether each
+/// closes on itself
+fn stitch(links: &[(usize, usize)]) -> Vec<(Vec<usize>, bool)> {
+    let mut at: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (s, &(a, b)) in links.iter().enumerate() {
+        at.entry(a).or_default().push(s);
+        at.entry(b).or_default().push(s);
+    }
+    let mut used = vec![false; links.len()];
+    let walk = |start: usize, first: usize, used: &mut [bool]| {
+        let mut chain = vec![start];
+        let (mut key, mut s) = (start, first);
+        loop {
+            used[s] = true;
+            let (a, b) = links[s];
+            key = if a == key { b } else { a };
+            let next = at[&key].iter().copied().find(|&t| !used[t]);
+            match next {
+                Some(t) => {
+                    chain.push(key);
+                    s = t;
+                }
+                None => {
+                    let closed = key == start;
+                    if !closed {
+                        chain.push(key);
+                    }
+                    return (chain, closed);
+                }
+            }
+        }
+    };
+    let mut chains = Vec::new();
+    // Open chains first, from their border ends, in segment order
+    for (s, &(a, b)) in links.iter().enumerate() {
+        if used[s] {
+            continue;
+        }
+        if let Some(end) = [a, b].into_iter().find(|k| at[k].len() == 1) {
+            chains.push(walk(end, s, &mut used));
+        }
+    }
+    for (s, &(a, _)) in links.iter().enumerate() {
+        if !used[s] {
+            chains.push(walk(a, s, &mut used));
+        }
+    }
+    chains
+}
+
+/// `marching_squares` over `simplex_noise_3d` sampled on the canvas
+///
+/// Samples sit `grid_res` canvas units apart from the top left, enough of
+/// them to cover `width × height`, with `time` as the noise's `z` so the
+/// lines morph as it advances. The noise runs at the scale
+/// `compute_vertex_colors` uses, about four features across the shorter
+/// side, so iso values belong in [-1, 1].
+#[cfg(feature = "noise")]
+#[wasm_bindgen]
+pub fn noise_contours(
+    width: f64,
+    height: f64,
+    grid_res: f64,
+    iso_values: &[f32],
+    time: f64,
+) -> Result<ContourSet> {
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if !(grid_res.is_finite() && grid_res > 0.0) {
+        return Err(Error::InvalidArgument(format!("grid_res must be positive, got {grid_res}")));
+    }
+    if !time.is_finite() {
+        return Err(Error::NonFiniteInput("time must be finite".into()));
+    }
+    let (cols, rows) = ((width / grid_res).ceil() + 1.0, (height / grid_res).ceil() + 1.0);
+    if cols * rows > MAX_SAMPLES as f64 {
+        return Err(Error::TooLarge(format!("a {cols}x{rows} sample grid is too fine")));
+    }
+    let (cols, rows) = (cols as u32, rows as u32);
+    let frequency = crate::shading::NOISE_FEATURES / width.min(height);
+    let mut field = Vec::with_capacity(cols as usize * rows as usize);
+    for j in 0..rows {
+        for i in 0..cols {
+            let (x, y) = (i as f64 * grid_res * frequency, j as f64 * grid_res * frequency);
+            field.push(crate::simplex_noise_3d(x, y, time) as f32);
+        }
+    }
+    marching_squares(&field, cols, rows, iso_values, grid_res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    /// Samples of `f` on a `cols × rows` grid
+    fn sample(cols: u32, rows: u32, f: impl Fn(f64, f64) -> f64) -> Vec<f32> {
+        let at = |k: u32| f((k % cols) as f64, (k / cols) as f64) as f32;
+        (0..cols * rows).map(at).collect()
+    }
+
+    #[test]
+    fn test_circle_stitches_into_one_loop() {
+        // Distance from (5, 5): the level 3 line is a circle of radius 3
+        let field = sample(11, 11, |x, y| (x - 5.0).hypot(y - 5.0));
+        let set = marching_squares(&field, 11, 11, &[3.0, 20.0], 2.0).unwrap();
+        let count = set.segment_count() as u32;
+        assert_eq!(set.segment_offsets(), vec![0, count, count]);
+        assert_eq!(set.path_count(), 1);
+        assert_eq!(set.path_closed(), vec![1]);
+        assert_eq!(set.path_levels(), vec![0]);
+        let paths = set.paths();
+        assert_eq!(set.path_offsets(), vec![0, paths.len() as u32 / 2]);
+        assert_eq!(paths.len() / 2, set.segment_count(), "a loop has as many vertices as sides");
+        for p in paths.chunks(2) {
+            // Scaled by cell size 2; chords of the circle sit slightly inside
+            let r = (p[0] as f64 - 10.0).hypot(p[1] as f64 - 10.0);
+            assert!((r - 6.0).abs() < 0.3, "{p:?} at radius {r}");
+        }
+        // Consecutive path vertices are the ends of one segment
+        let segments = set.segments();
+        let n = paths.len() / 2;
+        for k in 0..n {
+            let next = (k + 1) % n;
+            let (a, b) = (&paths[2 * k..2 * k + 2], &paths[2 * next..2 * next + 2]);
+            let found = segments.chunks(4).any(|s| {
+                (s[..2] == *a && s[2..] == *b) || (s[..2] == *b && s[2..] == *a)
+            });
+            assert!(found, "no segment joins {a:?} and {b:?}");
+        }
+    }
+
+    #[test]
+    fn test_open_lines_and_saddles() {
+        // A ramp along x: level 1.5 is the vertical line x = 1.5, open at
+        // both borders
+        let ramp = sample(4, 3, |x, _| x);
+        let set = marching_squares(&ramp, 4, 3, &[1.5], 1.0).unwrap();
+        assert_eq!(set.path_closed(), vec![0]);
+        assert_eq!(set.paths(), vec![1.5, 0.0, 1.5, 1.0, 1.5, 2.0]);
+
+        // Checkerboard saddle, resolved by the corner mean
+        let saddle = [1.0, 0.0, 0.0, 1.0];
+        let high = marching_squares(&saddle, 2, 2, &[0.4], 1.0).unwrap();
+        let low = marching_squares(&saddle, 2, 2, &[0.6], 1.0).unwrap();
+        assert_eq!(high.segment_count(), 2);
+        assert_eq!(low.segment_count(), 2);
+        // Mean 0.5 is inside at 0.4, so the two inside corners (top left
+        // and bottom right) join and the top crossing pairs with the right
+        let near = |s: &[f32], expected: [f32; 4]| {
+            s.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-6)
+        };
+        assert!(near(&high.segments()[..4], [0.6, 0.0, 1.0, 0.4]));
+        assert!(near(&low.segments()[..4], [0.4, 0.0, 0.0, 0.4]));
+        assert_eq!(high.path_count(), 2);
+
+        let run = |f: &[f32], w, h, iso: &[f32], cell| {
+            code_of(marching_squares(f, w, h, iso, cell))
+        };
+        assert_eq!(run(&saddle, 1, 4, &[0.5], 1.0), ErrorCode::InvalidArgument);
+        assert_eq!(run(&saddle, 2, 3, &[0.5], 1.0), ErrorCode::InvalidLength);
+        assert_eq!(run(&saddle, 2, 2, &[f32::NAN], 1.0), ErrorCode::NonFiniteInput);
+        assert_eq!(run(&saddle, 2, 2, &[0.5], 0.0), ErrorCode::InvalidArgument);
+    }
+
+    #[cfg(feature = "noise")]
+    #[test]
+    fn test_noise_contours_cover_the_canvas() {
+        let set = noise_contours(400.0, 300.0, 5.0, &[-0.2, 0.0, 0.3], 1.25).unwrap();
+        assert!(set.path_count() > 3);
+        assert_eq!(set.segment_offsets().len(), 4);
+        for p in set.paths().chunks(2) {
+            assert!((0.0..=400.0).contains(&p[0]) && (0.0..=300.0).contains(&p[1]));
+        }
+        // Open paths have at least two vertices, loops at least three
+        let offsets = set.path_offsets();
+        for (w, &closed) in offsets.windows(2).zip(&set.path_closed()) {
+            assert!(w[1] - w[0] >= if closed == 1 { 3 } else { 2 });
+        }
+        let later = noise_contours(400.0, 300.0, 5.0, &[0.0], 2.0).unwrap();
+        assert_ne!(later.segments(), set.segments());
+        let run = |w, h, res| code_of(noise_contours(w, h, res, &[0.0], 0.0));
+        assert_eq!(run(400.0, 0.0, 5.0), ErrorCode::InvalidArgument);
+        assert_eq!(run(400.0, 300.0, f64::NAN), ErrorCode::InvalidArgument);
+        assert_eq!(run(4e6, 4e6, 0.5), ErrorCode::TooLarge);
+    }
+}
@@ -0,0 +1,268 @@
+//! Crossing detection for overlay edge sets
+//!
+//! Two segments cross when they share any point other than a common
+//! endpoint: proper crossings, T-junctions and collinear overlaps all
+//! count, two segments meeting only at an endpoint they share do not.
+//! Zero-length segments never cross anything.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::predicates::orient2d;
+use crate::spatial::{Aabb, Grid};
+use crate::Point;
+
+pub(crate) type Segment = [Point; 2];
+
+fn segment_box(s: &Segment) -> Aabb {
+    [
+        s[0].x.min(s[1].x),
+        s[0].y.min(s[1].y),
+        s[0].x.max(s[1].x),
+        s[0].y.max(s[1].y),
+    ]
+}
+
+/// Whether `p`, known to be collinear with `s`, lies within its box
+fn on_segment(s: &Segment, p: Point) -> bool {
+    let b = segment_box(s);
+    b[0] <= p.x && p.x <= b[2] && b[1] <= p.y && p.y <= b[3]
+}
+
+pub(crate) fn segments_cross(s: &Segment, t: &Segment) -> bool {
+    let [a, b] = *s;
+    let [c, d] = *t;
+    if a == b || c == d {
+        return false;
+    }
+    let o1 = orient2d(a, b, c);
+    let o2 = orient2d(a, b, d);
+    let o3 = orient2d(c, d, a);
+    let o4 = orient2d(c, d, b);
+    let shares_endpoint = a == c || a == d || b == c || b == d;
+
+    if o1 == 0.0 && o2 == 0.0 {
+        // Collinear: cross when the overlap is longer than a single point
+        let (dx, dy) = ((b.x - a.x).abs(), (b.y - a.y).abs());
+        let key = |p: Point| if dx >= dy { p.x } else { p.y };
+        let (s0, s1) = (key(a).min(key(b)), key(a).max(key(b)));
+        let (t0, t1) = (key(c).min(key(d)), key(c).max(key(d)));
+        let lo = s0.max(t0);
+        let hi = s1.min(t1);
+        return hi > lo || (hi == lo && !shares_endpoint);
+    }
+
+    let straddles = |p: f64, q: f64| (p > 0.0 && q < 0.0) || (p < 0.0 && q > 0.0);
+    if straddles(o1, o2) && straddles(o3, o4) {
+        return true;
+    }
+    // Non-collinear segments meet in at most one point; a shared endpoint
+    // is that point, so only touches elsewhere count
+    if shares_endpoint {
+        return false;
+    }
+    (o1 == 0.0 && on_segment(s, c))
+        || (o2 == 0.0 && on_segment(s, d))
+        || (o3 == 0.0 && on_segment(t, a))
+        || (o4 == 0.0 && on_segment(t, b))
+}
+
+pub(crate) fn flat_segments(edges: &[f64], name: &str) -> Result<Vec<Segment>> {
+    if !edges.len().is_multiple_of(4) {
+        return Err(Error::InvalidLength(format!(
+            "{name} length {} is not a multiple of 4",
+            edges.len()
+        )));
+    }
+    if edges.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput(format!("{name} coordinates must be finite")));
+    }
+    Ok(edges
+        .chunks_exact(4)
+        .map(|c| [Point { x: c[0], y: c[1] }, Point { x: c[2], y: c[3] }])
+        .collect())
+}
+
+/// Crossing pairs between two segment sets, each `[x0, y0, x1, y1, ...]`
+///
+/// Returns `[a0, b0, a1, b1, ...]` where segment `a` of `edges_a` crosses
+/// segment `b` of `edges_b`, sorted by `a` then `b`. Candidates come from a
+/// uniform grid over `edges_b`, so cost is near-linear for evenly sized
+/// segments. Pass the same array twice to find self-crossings; each pair
+/// then appears in both orders and identical segments match themselves.
+#[wasm_bindgen]
+pub fn find_edge_crossings(edges_a: &[f64], edges_b: &[f64]) -> Result<Vec<u32>> {
+    let a = flat_segments(edges_a, "edges_a")?;
+    let b = flat_segments(edges_b, "edges_b")?;
+
+    let boxes_b: Vec<Aabb> = b.iter().map(segment_box).collect();
+    let mut grid = Grid::fitted(&boxes_b);
+    for (j, bx) in boxes_b.iter().enumerate() {
+        grid.insert(j as u32, bx);
+    }
+
+    let mut out = Vec::new();
+    let mut candidates = Vec::new();
+    for (i, s) in a.iter().enumerate() {
+        grid.query(&segment_box(s), &mut candidates);
+        for &j in &candidates {
+            if segments_cross(s, &b[j as usize]) {
+                out.extend_from_slice(&[i as u32, j]);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Greedily keep the highest-priority edges that cross nothing kept so far
+///
+/// `edge_indices` is `[i0, j0, i1, j1, ...]` into `points`, with one
+/// `priority` per edge. Edges are considered by descending priority, ties
+/// going to the lower edge index, and the survivors are returned as index
+/// pairs in their original order. The result is crossing-free.
+#[wasm_bindgen]
+pub fn remove_crossings(edge_indices: &[u32], points: &[f64], priority: &[f32]) -> Result<Vec<u32>> {
+    let point_count = check_points(points)?;
+    if !edge_indices.len().is_multiple_of(2) {
+        return Err(Error::InvalidLength(format!(
+            "edge index count {} is odd",
+            edge_indices.len()
+        )));
+    }
+    let edge_count = edge_indices.len() / 2;
+    if priority.len() != edge_count {
+        return Err(Error::InvalidLength(format!(
+            "expected {edge_count} priorities, got {}",
+            priority.len()
+        )));
+    }
+    if let Some(&bad) = edge_indices.iter().find(|&&i| i as usize >= point_count) {
+        return Err(Error::IndexOutOfRange(format!(
+            "edge references vertex {bad} but only {point_count} points were given"
+        )));
+    }
+    if points.iter().any(|v| !v.is_finite()) || priority.iter().any(|p| !p.is_finite()) {
+        return Err(Error::NonFiniteInput("points and priorities must be finite".into()));
+    }
+
+    let point = |i: u32| Point { x: points[i as usize * 2], y: points[i as usize * 2 + 1] };
+    let segments: Vec<Segment> = edge_indices
+        .chunks_exact(2)
+        .map(|e| [point(e[0]), point(e[1])])
+        .collect();
+    let boxes: Vec<Aabb> = segments.iter().map(segment_box).collect();
+
+    let mut order: Vec<usize> = (0..edge_count).collect();
+    order.sort_by(|&x, &y| priority[y].total_cmp(&priority[x]).then(x.cmp(&y)));
+
+    let mut grid = Grid::fitted(&boxes);
+    let mut kept = vec![false; edge_count];
+    let mut candidates = Vec::new();
+    for e in order {
+        grid.query(&boxes[e], &mut candidates);
+        if candidates
+            .iter()
+            .all(|&k| !segments_cross(&segments[e], &segments[k as usize]))
+        {
+            kept[e] = true;
+            grid.insert(e as u32, &boxes[e]);
+        }
+    }
+
+    Ok(edge_indices
+        .chunks_exact(2)
+        .zip(&kept)
+        .filter(|(_, &k)| k)
+        .flat_map(|(e, _)| [e[0], e[1]])
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+
+    fn seg(x0: f64, y0: f64, x1: f64, y1: f64) -> Segment {
+        [Point { x: x0, y: y0 }, Point { x: x1, y: y1 }]
+    }
+
+    #[test]
+    fn test_segment_crossing_cases() {
+        let s = seg(0.0, 0.0, 2.0, 2.0);
+        assert!(segments_cross(&s, &seg(0.0, 2.0, 2.0, 0.0)));
+        // Shared endpoint only
+        assert!(!segments_cross(&s, &seg(2.0, 2.0, 4.0, 0.0)));
+        // T-junction on the interior
+        assert!(segments_cross(&s, &seg(1.0, 1.0, 3.0, 0.0)));
+        // Collinear overlap, even when an endpoint is shared
+        assert!(segments_cross(&s, &seg(1.0, 1.0, 3.0, 3.0)));
+        assert!(segments_cross(&s, &seg(0.0, 0.0, 1.0, 1.0)));
+        // Collinear, touching only at the shared endpoint
+        assert!(!segments_cross(&s, &seg(2.0, 2.0, 3.0, 3.0)));
+        // Collinear but disjoint, and parallel
+        assert!(!segments_cross(&s, &seg(3.0, 3.0, 4.0, 4.0)));
+        assert!(!segments_cross(&s, &seg(0.0, 1.0, 2.0, 3.0)));
+        assert!(!segments_cross(&s, &seg(1.0, 1.0, 1.0, 1.0)));
+    }
+
+    #[test]
+    fn test_find_edge_crossings_matches_brute_force() {
+        let mut rng = Rng::new(11);
+        let mut random_edges = |n: usize| -> Vec<f64> {
+            (0..n)
+                .flat_map(|_| {
+                    let (x, y) = (rng.next_f64() * 500.0, rng.next_f64() * 500.0);
+                    [x, y, x + (rng.next_f64() - 0.5) * 80.0, y + (rng.next_f64() - 0.5) * 80.0]
+                })
+                .collect()
+        };
+        let a = random_edges(300);
+        let b = random_edges(200);
+        let got = find_edge_crossings(&a, &b).unwrap();
+
+        let sa = flat_segments(&a, "a").unwrap();
+        let sb = flat_segments(&b, "b").unwrap();
+        let mut expected = Vec::new();
+        for (i, s) in sa.iter().enumerate() {
+            for (j, t) in sb.iter().enumerate() {
+                if segments_cross(s, t) {
+                    expected.extend_from_slice(&[i as u32, j as u32]);
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(got, expected);
+        assert_eq!(code_of(find_edge_crossings(&a[..5], &b)), ErrorCode::InvalidLength);
+    }
+
+    #[test]
+    fn test_remove_crossings_prefers_priority_then_index() {
+        // Square with both diagonals: the diagonals cross each other only
+        let points = [0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0];
+        let edges = [0, 1, 1, 2, 2, 3, 3, 0, 0, 2, 1, 3];
+        let kept = remove_crossings(&edges, &points, &[1.0, 1.0, 1.0, 1.0, 0.5, 0.5]).unwrap();
+        assert_eq!(kept, vec![0, 1, 1, 2, 2, 3, 3, 0, 0, 2]);
+        let kept = remove_crossings(&edges, &points, &[1.0, 1.0, 1.0, 1.0, 0.5, 0.9]).unwrap();
+        assert_eq!(kept, vec![0, 1, 1, 2, 2, 3, 3, 0, 1, 3]);
+        assert_eq!(code_of(remove_crossings(&edges, &points, &[1.0; 5])), ErrorCode::InvalidLength);
+    }
+
+    #[test]
+    fn test_remove_crossings_result_is_crossing_free() {
+        let mut rng = Rng::new(3);
+        let points: Vec<f64> = (0..120).map(|_| rng.next_f64() * 300.0).collect();
+        let edges: Vec<u32> = (0..150).map(|_| rng.below(60)).collect();
+        let priority: Vec<f32> = (0..75).map(|_| rng.next_f64() as f32).collect();
+        let kept = remove_crossings(&edges, &points, &priority).unwrap();
+        let flat: Vec<f64> = kept
+            .iter()
+            .flat_map(|&i| [points[i as usize * 2], points[i as usize * 2 + 1]])
+            .collect();
+        let pairs = find_edge_crossings(&flat, &flat).unwrap();
+        // Every kept segment overlaps itself; nothing else may cross
+        assert!(pairs.chunks(2).all(|p| p[0] == p[1]));
+        assert!(kept.len() < edges.len());
+    }
+}
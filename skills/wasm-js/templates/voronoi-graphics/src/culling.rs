@@ -0,0 +1,284 @@
+//! Viewport culling of edges and triangles
+//!
+//! A primitive is kept when its bounding box meets the viewport, touching
+//! included. That is conservative: everything partly inside is kept, and
+//! so is the odd long diagonal whose box reaches the corner of the view
+//! while the primitive itself passes by. `cull_edges` and `cull_triangles`
+//! scan once; `CullIndex` buckets the boxes in a uniform grid so a pan or
+//! zoom only tests the primitives in the cells under the view, marking
+//! those it has seen rather than sorting out the ones listed in several
+//! cells.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_edge_indices, check_points, check_triangles};
+use crate::spatial::{Aabb, Grid};
+
+/// What the indices given to a `CullIndex` describe; the value is the
+/// number of corners per primitive
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CullKind {
+    /// `[a0, b0, a1, b1, ...]`
+    Edges = 2,
+    /// `[a0, b0, c0, a1, ...]`
+    Triangles = 3,
+}
+
+fn check_primitives(points_flat: &[f64], indices: &[u32], kind: CullKind) -> Result<()> {
+    let n = check_points(points_flat)?;
+    match kind {
+        CullKind::Edges => check_edge_indices(indices, n)?,
+        CullKind::Triangles => check_triangles(indices, n)?,
+    }
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    Ok(())
+}
+
+fn check_view(view_x: f64, view_y: f64, view_w: f64, view_h: f64) -> Result<Aabb> {
+    if [view_x, view_y, view_w, view_h].iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("viewport must be finite".into()));
+    }
+    if view_w < 0.0 || view_h < 0.0 {
+        return Err(Error::InvalidArgument(format!(
+            "viewport size must be non-negative, got {view_w} × {view_h}"
+        )));
+    }
+    Ok([view_x, view_y, view_x + view_w, view_y + view_h])
+}
+
+fn bounding_box(points_flat: &[f64], corners: &[u32]) -> Aabb {
+    let mut b = [f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY];
+    for &v in corners {
+        let (x, y) = (points_flat[v as usize * 2], points_flat[v as usize * 2 + 1]);
+        b = [b[0].min(x), b[1].min(y), b[2].max(x), b[3].max(y)];
+    }
+    b
+}
+
+fn overlaps(a: &Aabb, b: &Aabb) -> bool {
+    a[0] <= b[2] && b[0] <= a[2] && a[1] <= b[3] && b[1] <= a[3]
+}
+
+fn cull(points_flat: &[f64], indices: &[u32], kind: CullKind, view: [f64; 4]) -> Result<Vec<u32>> {
+    check_primitives(points_flat, indices, kind)?;
+    let view = check_view(view[0], view[1], view[2], view[3])?;
+    let mut out = Vec::new();
+    for corners in indices.chunks_exact(kind as usize) {
+        if overlaps(&bounding_box(points_flat, corners), &view) {
+            out.extend_from_slice(corners);
+        }
+    }
+    Ok(out)
+}
+
+/// The pairs of `edge_indices` whose bounding box meets the viewport, in
+/// their original order
+#[wasm_bindgen]
+pub fn cull_edges(
+    points_flat: &[f64],
+    edge_indices: &[u32],
+    view_x: f64,
+    view_y: f64,
+    view_w: f64,
+    view_h: f64,
+) -> Result<Vec<u32>> {
+    cull(points_flat, edge_indices, CullKind::Edges, [view_x, view_y, view_w, view_h])
+}
+
+/// The triples of `triangles` whose bounding box meets the viewport, in
+/// their original order
+#[wasm_bindgen]
+pub fn cull_triangles(
+    points_flat: &[f64],
+    triangles: &[u32],
+    view_x: f64,
+    view_y: f64,
+    view_w: f64,
+    view_h: f64,
+) -> Result<Vec<u32>> {
+    cull(points_flat, triangles, CullKind::Triangles, [view_x, view_y, view_w, view_h])
+}
+
+/// Edges or triangles bucketed by bounding box for repeated viewport
+/// queries
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct CullIndex {
+    kind: CullKind,
+    indices: Vec<u32>,
+    boxes: Vec<Aabb>,
+    grid: Grid,
+    /// `query` number that last reported each primitive, so one spanning
+    /// several cells is reported once without sorting the candidates
+    seen: Vec<u32>,
+    queries: u32,
+}
+
+#[wasm_bindgen]
+impl CullIndex {
+    #[wasm_bindgen(constructor)]
+    pub fn new(points_flat: &[f64], indices: &[u32], kind: CullKind) -> Result<CullIndex> {
+        check_primitives(points_flat, indices, kind)?;
+        let mut index = CullIndex {
+            kind,
+            indices: indices.to_vec(),
+            boxes: Vec::new(),
+            grid: Grid::fitted(&[]),
+            seen: Vec::new(),
+            queries: 0,
+        };
+        index.fill(points_flat);
+        Ok(index)
+    }
+
+    /// Re-bucket the same primitives after their points moved
+    ///
+    /// `points_flat` must still cover every index; on error the index is
+    /// left as it was.
+    pub fn rebuild(&mut self, points_flat: &[f64]) -> Result<()> {
+        check_primitives(points_flat, &self.indices, self.kind)?;
+        self.fill(points_flat);
+        Ok(())
+    }
+
+    /// Primitive numbers, ascending, whose bounding box meets the viewport
+    pub fn query(
+        &mut self,
+        view_x: f64,
+        view_y: f64,
+        view_w: f64,
+        view_h: f64,
+    ) -> Result<Vec<u32>> {
+        let view = check_view(view_x, view_y, view_w, view_h)?;
+        self.queries = self.queries.wrapping_add(1);
+        if self.queries == 0 {
+            self.seen.fill(0);
+            self.queries = 1;
+        }
+        let mut out = Vec::new();
+        let (seen, boxes, stamp) = (&mut self.seen, &self.boxes, self.queries);
+        self.grid.for_each_candidate(&view, |k| {
+            let k = k as usize;
+            if seen[k] != stamp && overlaps(&boxes[k], &view) {
+                seen[k] = stamp;
+                out.push(k as u32);
+            }
+        });
+        out.sort_unstable();
+        Ok(out)
+    }
+
+    /// The indices of the primitives `query` returns, ready to draw
+    pub fn query_indices(
+        &mut self,
+        view_x: f64,
+        view_y: f64,
+        view_w: f64,
+        view_h: f64,
+    ) -> Result<Vec<u32>> {
+        let arity = self.kind as usize;
+        let hits = self.query(view_x, view_y, view_w, view_h)?;
+        let corners = |k: u32| &self.indices[k as usize * arity..][..arity];
+        Ok(hits.into_iter().flat_map(corners).copied().collect())
+    }
+
+    pub fn primitive_count(&self) -> usize {
+        self.boxes.len()
+    }
+}
+
+impl CullIndex {
+    fn fill(&mut self, points_flat: &[f64]) {
+        let arity = self.kind as usize;
+        self.boxes =
+            self.indices.chunks_exact(arity).map(|c| bounding_box(points_flat, c)).collect();
+        self.grid = Grid::fitted(&self.boxes);
+        self.seen = vec![0; self.boxes.len()];
+        for (k, b) in self.boxes.iter().enumerate() {
+            self.grid.insert(k as u32, b);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+    use crate::{compute_delaunay, compute_edge_indices};
+
+    fn scattered(n: usize, seed: u64) -> Vec<f64> {
+        let mut rng = Rng::new(seed);
+        (0..n).flat_map(|_| [rng.range(0.0, 2000.0), rng.range(0.0, 1500.0)]).collect()
+    }
+
+    #[test]
+    fn test_index_matches_scan_and_keeps_crossing_edges() {
+        let points = scattered(3000, 8);
+        let edges = compute_edge_indices(&compute_delaunay(&points, 2000.0, 1500.0));
+        let mut index = CullIndex::new(&points, &edges, CullKind::Edges).unwrap();
+        assert_eq!(index.primitive_count(), edges.len() / 2);
+        let inside = |v: u32, [x, y, w, h]: [f64; 4]| {
+            let (px, py) = (points[v as usize * 2], points[v as usize * 2 + 1]);
+            (x..=x + w).contains(&px) && (y..=y + h).contains(&py)
+        };
+        let views = [[300.0, 200.0, 400.0, 300.0], [-50.0, 1400.0, 200.0, 200.0], [0.0; 4]];
+        for view in views {
+            let [x, y, w, h] = view;
+            let hits = index.query(x, y, w, h).unwrap();
+            let culled = cull_edges(&points, &edges, x, y, w, h).unwrap();
+            assert_eq!(index.query_indices(x, y, w, h).unwrap(), culled);
+            assert!(hits.windows(2).all(|p| p[0] < p[1]));
+            for (k, e) in edges.chunks(2).enumerate() {
+                let kept = hits.binary_search(&(k as u32)).is_ok();
+                // Anything with an end in view is kept, anything boxed
+                // wholly to one side is not
+                if inside(e[0], view) || inside(e[1], view) {
+                    assert!(kept);
+                }
+                let b = bounding_box(&points, e);
+                if b[2] < x || b[0] > x + w || b[3] < y || b[1] > y + h {
+                    assert!(!kept);
+                }
+            }
+        }
+        let all = index.query(-1.0, -1.0, 3000.0, 3000.0).unwrap();
+        assert_eq!(all.len(), edges.len() / 2);
+        assert!(index.query(5000.0, 0.0, 10.0, 10.0).unwrap().is_empty());
+
+        // An edge spanning the view with both ends outside is kept
+        let long = [0.0, 50.0, 100.0, 50.0];
+        assert_eq!(cull_edges(&long, &[0, 1], 40.0, 40.0, 20.0, 20.0).unwrap(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_triangles_rebuild_and_errors() {
+        let mut points = scattered(500, 2);
+        let triangles = compute_delaunay(&points, 2000.0, 1500.0);
+        let mut index = CullIndex::new(&points, &triangles, CullKind::Triangles).unwrap();
+        let view = (800.0, 600.0, 300.0, 200.0);
+        let culled = cull_triangles(&points, &triangles, view.0, view.1, view.2, view.3);
+        assert_eq!(index.query_indices(view.0, view.1, view.2, view.3).unwrap(), culled.unwrap());
+
+        // Shifting everything off to the right empties the view
+        points.iter_mut().step_by(2).for_each(|x| *x += 5000.0);
+        index.rebuild(&points).unwrap();
+        assert!(index.query(view.0, view.1, view.2, view.3).unwrap().is_empty());
+        assert_eq!(index.query(5000.0, 0.0, 2000.0, 1500.0).unwrap().len(), triangles.len() / 3);
+        assert_eq!(code_of(index.rebuild(&points[..10])), ErrorCode::IndexOutOfRange);
+        assert_eq!(index.primitive_count(), triangles.len() / 3);
+
+        let mut empty = CullIndex::new(&[], &[], CullKind::Edges).unwrap();
+        assert!(empty.query(0.0, 0.0, 1.0, 1.0).unwrap().is_empty());
+        assert_eq!(code_of(index.query(0.0, 0.0, -1.0, 1.0)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(index.query(f64::NAN, 0.0, 1.0, 1.0)), ErrorCode::NonFiniteInput);
+        let odd = CullIndex::new(&points, &[0, 1, 2, 3], CullKind::Triangles);
+        assert_eq!(code_of(odd), ErrorCode::InvalidLength);
+        let far = cull_edges(&points, &[0, 500], 0.0, 0.0, 1.0, 1.0);
+        assert_eq!(code_of(far), ErrorCode::IndexOutOfRange);
+    }
+}
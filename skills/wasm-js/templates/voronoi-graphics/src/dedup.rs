@@ -0,0 +1,184 @@
+//! Validating `compute_delaunay` variant that merges coincident points
+//!
+//! `compute_delaunay` keeps its lenient contract: an odd trailing value is
+//! ignored, non-finite input gives no triangles and duplicates are left
+//! out of every triangle without a word. `compute_delaunay_checked`
+//! rejects malformed input instead, and welds points closer than
+//! `epsilon` into one before triangulating, reporting how original
+//! indices map onto the welded set.
+
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::geometry::Triangulation;
+use crate::graph::check_points;
+use crate::Point;
+
+/// Triangulation of the welded points from `compute_delaunay_checked`
+#[wasm_bindgen]
+pub struct CheckedDelaunay {
+    points: Vec<f64>,
+    triangles: Vec<u32>,
+    remap: Vec<u32>,
+    representatives: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl CheckedDelaunay {
+    /// Welded points `[x0, y0, ...]`; each is the first original point of
+    /// its cluster, unmoved
+    pub fn points(&self) -> Vec<f64> {
+        self.points.clone()
+    }
+
+    /// CCW triangles as indices into `points()`
+    pub fn triangles(&self) -> Vec<u32> {
+        self.triangles.clone()
+    }
+
+    /// For each original point, the index of the welded point it joined
+    pub fn remap(&self) -> Vec<u32> {
+        self.remap.clone()
+    }
+
+    /// For each welded point, the original index it was taken from
+    pub fn representatives(&self) -> Vec<u32> {
+        self.representatives.clone()
+    }
+
+    pub fn unique_count(&self) -> usize {
+        self.representatives.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len() / 3
+    }
+}
+
+/// Delaunay triangulation of `[x0, y0, x1, y1, ...]` with input checks
+///
+/// Errors on an odd-length array, non-finite coordinates, a non-positive
+/// canvas or a negative `epsilon`. Points are taken in order, and each
+/// one within `epsilon` (Euclidean) of an earlier kept point is welded to
+/// it rather than kept; `epsilon = 0` welds exact duplicates only. Welding
+/// is greedy, so a chain of points each just under `epsilon` apart is not
+/// collapsed into one.
+#[wasm_bindgen]
+pub fn compute_delaunay_checked(
+    points_flat: &[f64],
+    width: f64,
+    height: f64,
+    epsilon: f64,
+) -> Result<CheckedDelaunay> {
+    check_points(points_flat)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if !(epsilon.is_finite() && epsilon >= 0.0) {
+        return Err(Error::InvalidArgument(format!(
+            "epsilon must be finite and non-negative, got {epsilon}"
+        )));
+    }
+
+    let points: Vec<Point> = points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+    let (remap, representatives) = weld(&points, epsilon);
+    let unique: Vec<Point> = representatives.iter().map(|&i| points[i as usize]).collect();
+    let tri = Triangulation::new(&unique)?;
+    Ok(CheckedDelaunay {
+        points: unique.iter().flat_map(|p| [p.x, p.y]).collect(),
+        triangles: tri.triangles().flat_map(|t| t.vertices().map(|v| v as u32)).collect(),
+        remap,
+        representatives,
+    })
+}
+
+/// `(remap, representatives)` for greedy welding at `epsilon`
+fn weld(points: &[Point], epsilon: f64) -> (Vec<u32>, Vec<u32>) {
+    // Exact duplicates hash by bit pattern; adding 0 folds -0 into +0
+    let key = |p: Point| -> (i64, i64) {
+        if epsilon == 0.0 {
+            ((p.x + 0.0).to_bits() as i64, (p.y + 0.0).to_bits() as i64)
+        } else {
+            ((p.x / epsilon).floor() as i64, (p.y / epsilon).floor() as i64)
+        }
+    };
+    let reach = if epsilon == 0.0 { 0 } else { 1 };
+    let mut cells: HashMap<(i64, i64), Vec<u32>> = HashMap::new();
+    let mut remap = Vec::with_capacity(points.len());
+    let mut representatives: Vec<u32> = Vec::new();
+    for (i, &p) in points.iter().enumerate() {
+        let (cx, cy) = key(p);
+        let mut found = None;
+        'search: for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let Some(kept) = cells.get(&(cx.saturating_add(dx), cy.saturating_add(dy))) else {
+                    continue;
+                };
+                for &u in kept {
+                    let q = points[representatives[u as usize] as usize];
+                    if (q.x - p.x).hypot(q.y - p.y) <= epsilon {
+                        found = Some(u);
+                        break 'search;
+                    }
+                }
+            }
+        }
+        let u = found.unwrap_or_else(|| {
+            let u = representatives.len() as u32;
+            representatives.push(i as u32);
+            cells.entry((cx, cy)).or_default().push(u);
+            u
+        });
+        remap.push(u);
+    }
+    (remap, representatives)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_delaunay;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_duplicates_are_welded_and_mapped_back() {
+        let square = [0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0];
+        let mut clicks = square.to_vec();
+        // A repeated click, a near-repeat and a signed zero
+        clicks.extend([10.0, 10.0, 10.0 + 1e-9, 0.0, -0.0, 0.0, 5.0, 5.0]);
+        let exact = compute_delaunay_checked(&clicks, 10.0, 10.0, 0.0).unwrap();
+        assert_eq!(exact.remap(), vec![0, 1, 2, 3, 2, 4, 0, 5]);
+        assert_eq!(exact.representatives(), vec![0, 1, 2, 3, 5, 7]);
+
+        let welded = compute_delaunay_checked(&clicks, 10.0, 10.0, 1e-6).unwrap();
+        assert_eq!(welded.remap(), vec![0, 1, 2, 3, 2, 1, 0, 4]);
+        assert_eq!(welded.unique_count(), 5);
+        assert_eq!(welded.triangle_count(), 4);
+        let unique = welded.points();
+        assert_eq!(&unique[..8], &square);
+        // No triangle collapses onto a duplicate
+        for t in welded.triangles().chunks(3) {
+            let xy = |v: u32| (unique[2 * v as usize], unique[2 * v as usize + 1]);
+            let [(ax, ay), (bx, by), (cx, cy)] = [xy(t[0]), xy(t[1]), xy(t[2])];
+            assert!((bx - ax) * (cy - ay) - (by - ay) * (cx - ax) > 0.0);
+        }
+        assert_eq!(compute_delaunay(&unique, 10.0, 10.0), welded.triangles());
+    }
+
+    #[test]
+    fn test_malformed_input_is_rejected() {
+        let code = |p: &[f64], eps: f64| code_of(compute_delaunay_checked(p, 10.0, 10.0, eps));
+        assert_eq!(code(&[0.0, 0.0, 1.0], 0.0), ErrorCode::InvalidLength);
+        assert_eq!(code(&[0.0, f64::NAN], 0.0), ErrorCode::NonFiniteInput);
+        assert_eq!(code(&[0.0, f64::INFINITY], 0.0), ErrorCode::NonFiniteInput);
+        assert_eq!(code(&[0.0, 0.0], -1.0), ErrorCode::InvalidArgument);
+        let flat = code_of(compute_delaunay_checked(&[0.0, 0.0], 0.0, 10.0, 0.0));
+        assert_eq!(flat, ErrorCode::InvalidArgument);
+        assert_eq!(compute_delaunay_checked(&[], 10.0, 10.0, 0.0).unwrap().unique_count(), 0);
+    }
+}
@@ -0,0 +1,224 @@
+//! Per-frame heat diffusion of vertex values over the edge graph
+//!
+//! Each iteration moves every vertex a fraction `rate × dt` of the way to
+//! the mean of its neighbours, all from the previous iteration's values.
+//! The new value is a convex blend of old ones, so nothing overshoots the
+//! range it started in. The plain mean counts graph hops, so heat spreads
+//! as fast across a long edge as a short one; the weighted variant weighs
+//! each neighbour by `1 / length`, which at rest interpolates linearly in
+//! space between fixed values.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_edge_indices, edge_length, Csr};
+
+/// Edges shorter than this weigh as if they were this long, as in
+/// `solve_laplace_field`
+const MIN_EDGE_LENGTH: f64 = 1e-9;
+
+fn check_rate(rate: f64, dt: f64) -> Result<f64> {
+    for (name, v) in [("rate", rate), ("dt", dt)] {
+        if !v.is_finite() || v < 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "{name} must be finite and non-negative, got {v}"
+            )));
+        }
+    }
+    Ok((rate * dt).min(1.0))
+}
+
+fn check_values(values: &[f32], edge_indices: &[u32], sources: &[f32]) -> Result<()> {
+    check_edge_indices(edge_indices, values.len())?;
+    if values.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("values must be finite".into()));
+    }
+    if !sources.is_empty() && sources.len() != values.len() {
+        return Err(Error::InvalidLength(format!(
+            "{} sources for {} values",
+            sources.len(),
+            values.len()
+        )));
+    }
+    if sources.iter().any(|v| v.is_infinite()) {
+        return Err(Error::NonFiniteInput("sources must be finite or NaN".into()));
+    }
+    Ok(())
+}
+
+fn csr(point_count: usize, edge_indices: &[u32]) -> Csr {
+    let edges: Vec<(u32, u32)> = edge_indices.chunks_exact(2).map(|e| (e[0], e[1])).collect();
+    Csr::from_edges(point_count, &edges)
+}
+
+/// Jacobi iterations of `current += step × (mean - current)`, with the mean
+/// weighted per CSR slot when `weights` is given
+fn diffuse(
+    values: &mut [f32],
+    csr: &Csr,
+    weights: Option<&[f64]>,
+    step: f64,
+    iterations: u32,
+    sources: &[f32],
+) {
+    let pin = |current: &mut [f64]| {
+        for (v, &s) in current.iter_mut().zip(sources).filter(|(_, s)| !s.is_nan()) {
+            *v = s as f64;
+        }
+    };
+    let mut current: Vec<f64> = values.iter().map(|&v| v as f64).collect();
+    pin(&mut current);
+    let mut next = current.clone();
+    for _ in 0..iterations {
+        for (v, out) in next.iter_mut().enumerate() {
+            let start = csr.offsets[v] as usize;
+            let (mut acc, mut total) = (0.0, 0.0);
+            for (k, &u) in csr.neighbors(v as u32).iter().enumerate() {
+                let w = weights.map_or(1.0, |w| w[start + k]);
+                acc += w * current[u as usize];
+                total += w;
+            }
+            *out = if total > 0.0 {
+                current[v] + step * (acc / total - current[v])
+            } else {
+                current[v]
+            };
+        }
+        pin(&mut next);
+        std::mem::swap(&mut current, &mut next);
+    }
+    for (out, &v) in values.iter_mut().zip(&current) {
+        *out = v as f32;
+    }
+}
+
+/// Diffuse `values` in place across `edge_indices` for `iterations` steps
+///
+/// Every step moves each vertex `rate × dt` of the way to the mean of its
+/// neighbours; above 1 the factor is clamped to 1, past which the blend
+/// would overshoot. `sources` is empty or one entry per vertex: a finite
+/// entry holds that vertex at the value before every step and after it,
+/// NaN leaves it free. Results stay within the range of the initial
+/// values and sources. Vertices without edges keep their value.
+#[wasm_bindgen]
+pub fn diffuse_values(
+    values: &mut [f32],
+    edge_indices: &[u32],
+    rate: f64,
+    dt: f64,
+    iterations: u32,
+    sources: &[f32],
+) -> Result<()> {
+    let step = check_rate(rate, dt)?;
+    check_values(values, edge_indices, sources)?;
+    let csr = csr(values.len(), edge_indices);
+    diffuse(values, &csr, None, step, iterations, sources);
+    Ok(())
+}
+
+/// `diffuse_values` weighing each neighbour by `1 / length` of its edge,
+/// so heat spreads by distance rather than by hops
+///
+/// `points_flat` is `[x0, y0, x1, y1, ...]` with one point per value.
+#[wasm_bindgen]
+pub fn diffuse_values_weighted(
+    values: &mut [f32],
+    points_flat: &[f64],
+    edge_indices: &[u32],
+    rate: f64,
+    dt: f64,
+    iterations: u32,
+    sources: &[f32],
+) -> Result<()> {
+    let step = check_rate(rate, dt)?;
+    check_values(values, edge_indices, sources)?;
+    if points_flat.len() != values.len() * 2 {
+        return Err(Error::InvalidLength(format!(
+            "{} coordinates for {} values",
+            points_flat.len(),
+            values.len()
+        )));
+    }
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let csr = csr(values.len(), edge_indices);
+    let weights: Vec<f64> = (0..values.len() as u32)
+        .flat_map(|v| csr.neighbors(v).iter().map(move |&u| (v, u)))
+        .map(|(v, u)| 1.0 / edge_length(points_flat, v, u).max(MIN_EDGE_LENGTH))
+        .collect();
+    diffuse(values, &csr, Some(&weights), step, iterations, sources);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+    use crate::{compute_delaunay, compute_edge_indices};
+
+    #[test]
+    fn test_diffusion_stays_bounded_and_evens_out() {
+        let mut rng = Rng::new(5);
+        let points: Vec<f64> =
+            (0..400).flat_map(|_| [rng.range(0.0, 300.0), rng.range(0.0, 300.0)]).collect();
+        let edges = compute_edge_indices(&compute_delaunay(&points, 300.0, 300.0));
+        let mut values: Vec<f32> = (0..400).map(|_| rng.range(-2.0, 5.0) as f32).collect();
+        let (lo, hi) = values.iter().fold((f32::MAX, f32::MIN), |(l, h), &v| (l.min(v), h.max(v)));
+        let mut spread = hi - lo;
+        // Even an oversized rate is clamped to a full step, which is bounded
+        for rate in [0.5, 3.0, 100.0] {
+            for _ in 0..20 {
+                diffuse_values_weighted(&mut values, &points, &edges, rate, 0.5, 5, &[]).unwrap();
+                assert!(values.iter().all(|&v| (lo..=hi).contains(&v)));
+                let (l, h) = values.iter().fold((f32::MAX, f32::MIN), |(l, h), &v| {
+                    (l.min(v), h.max(v))
+                });
+                assert!(h - l <= spread);
+                spread = h - l;
+            }
+        }
+        assert!(spread < (hi - lo) / 10.0, "spread {spread}");
+
+        let before = values.clone();
+        diffuse_values(&mut values, &edges, 0.0, 1.0, 10, &[]).unwrap();
+        assert_eq!(values, before);
+        // An isolated vertex keeps its value
+        let mut lone = [1.0, 3.0, 7.0];
+        diffuse_values(&mut lone, &[0, 1], 1.0, 0.5, 1, &[]).unwrap();
+        assert_eq!(lone, [2.0, 2.0, 7.0]);
+    }
+
+    #[test]
+    fn test_sources_hold_and_weighting_follows_distance() {
+        // A chain with uneven spacing, hot at one end and cold at the other
+        let xs = [0.0, 1.0, 4.0, 5.0, 10.0];
+        let points: Vec<f64> = xs.iter().flat_map(|&x| [x, 0.0]).collect();
+        let edges = [0, 1, 1, 2, 2, 3, 3, 4];
+        let sources = [1.0, f32::NAN, f32::NAN, f32::NAN, 0.0];
+        let mut hops = [0.5; 5];
+        diffuse_values(&mut hops, &edges, 1.0, 1.0, 500, &sources).unwrap();
+        let mut spatial = [0.5; 5];
+        diffuse_values_weighted(&mut spatial, &points, &edges, 1.0, 1.0, 500, &sources).unwrap();
+        for (v, (&h, &s)) in hops.iter().zip(&spatial).enumerate() {
+            assert!((h - (1.0 - v as f32 / 4.0)).abs() < 1e-4, "{hops:?}");
+            assert!((s - (1.0 - xs[v] as f32 / 10.0)).abs() < 1e-4, "{spatial:?}");
+        }
+
+        let mut values = [0.0f32; 5];
+        let run = |values: &mut [f32], edges: &[u32], rate: f64, sources: &[f32]| {
+            code_of(diffuse_values(values, edges, rate, 1.0, 1, sources))
+        };
+        assert_eq!(run(&mut values, &edges, -1.0, &[]), ErrorCode::InvalidArgument);
+        assert_eq!(run(&mut values, &edges, f64::NAN, &[]), ErrorCode::InvalidArgument);
+        assert_eq!(run(&mut values, &[0, 5], 1.0, &[]), ErrorCode::IndexOutOfRange);
+        assert_eq!(run(&mut values, &[0], 1.0, &[]), ErrorCode::InvalidLength);
+        assert_eq!(run(&mut values, &edges, 1.0, &[1.0]), ErrorCode::InvalidLength);
+        let hot = [f32::INFINITY, 0.0, 0.0, 0.0, 0.0];
+        assert_eq!(run(&mut values, &edges, 1.0, &hot), ErrorCode::NonFiniteInput);
+        assert_eq!(run(&mut [f32::NAN], &[], 1.0, &[]), ErrorCode::NonFiniteInput);
+        let weighted = diffuse_values_weighted(&mut values, &points[..8], &edges, 1.0, 1.0, 1, &[]);
+        assert_eq!(code_of(weighted), ErrorCode::InvalidLength);
+    }
+}
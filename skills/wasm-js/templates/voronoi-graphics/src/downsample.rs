@@ -0,0 +1,344 @@
+//! Coarse binning and level-of-detail thinning of point sets for
+//! overview rendering
+//!
+//! Thinning ranks every point once: the `important` points first, then
+//! one point per quadtree cell, depth by depth, for cells no earlier
+//! point already covers. Any prefix of that order is spread over the
+//! canvas, so a smaller subset is always contained in a larger one and
+//! switching detail levels never swaps points.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::rng::Rng;
+
+/// Grid cells `downsample_positions` may address
+const MAX_CELLS: f64 = (1u64 << 32) as f64;
+
+/// One representative per occupied `cell_size` bin of the canvas
+///
+/// `positions` holds `stride` floats per item, `[x, y, extra...]`, such as
+/// seeds (stride 2) or `Scene::mote_positions` (stride 3). Each output
+/// item has `stride + 1` floats: the centroid of the bin, every extra
+/// channel averaged over the bin, and the number of items it holds. Bins
+/// come out in row-major order, so the result is stable from frame to
+/// frame while the points are. Items outside the canvas or with
+/// non-finite coordinates are skipped.
+#[wasm_bindgen]
+pub fn downsample_positions(
+    positions: &[f32],
+    stride: u32,
+    cell_size: f64,
+    width: f64,
+    height: f64,
+) -> Result<Vec<f32>> {
+    if stride < 2 {
+        return Err(Error::InvalidArgument(format!("stride must be at least 2, got {stride}")));
+    }
+    let stride = stride as usize;
+    if !positions.len().is_multiple_of(stride) {
+        return Err(Error::InvalidLength(format!(
+            "position array length {} is not a multiple of stride {stride}",
+            positions.len()
+        )));
+    }
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if !(cell_size.is_finite() && cell_size > 0.0) {
+        return Err(Error::InvalidArgument(format!("cell size must be positive, got {cell_size}")));
+    }
+    let (cols, rows) = ((width / cell_size).ceil(), (height / cell_size).ceil());
+    if cols * rows > MAX_CELLS {
+        return Err(Error::TooLarge(format!("a {cols}x{rows} bin grid is too fine")));
+    }
+    let (cols, rows) = (cols as u64, rows as u64);
+
+    // Per bin: channel sums followed by the item count
+    let mut bins: BTreeMap<u64, Vec<f64>> = BTreeMap::new();
+    for item in positions.chunks_exact(stride) {
+        let (x, y) = (item[0] as f64, item[1] as f64);
+        // The range checks also reject NaN
+        if !(0.0..=width).contains(&x) || !(0.0..=height).contains(&y) {
+            continue;
+        }
+        let col = ((x / cell_size) as u64).min(cols - 1);
+        let row = ((y / cell_size) as u64).min(rows - 1);
+        let sums = bins.entry(row * cols + col).or_insert_with(|| vec![0.0; stride + 1]);
+        for (s, &v) in sums.iter_mut().zip(item) {
+            *s += v as f64;
+        }
+        sums[stride] += 1.0;
+    }
+
+    let mut out = Vec::with_capacity(bins.len() * (stride + 1));
+    for sums in bins.values() {
+        let count = sums[stride];
+        out.extend(sums[..stride].iter().map(|&s| (s / count) as f32));
+        out.push(count as f32);
+    }
+    Ok(out)
+}
+
+/// Quadtree depth past which the remaining points, e.g. coincident ones,
+/// are ranked by their random key alone
+const MAX_DEPTH: u32 = 16;
+
+/// Every index of `points_flat`, most representative first
+///
+/// Within a cell the point with the smallest seeded random key is picked,
+/// and the picks of one depth are ranked by that key too, so cutting a
+/// depth short does not favour any part of the canvas.
+fn lod_order(
+    points_flat: &[f64],
+    width: f64,
+    height: f64,
+    seed: u32,
+    important: u32,
+) -> Result<Vec<u32>> {
+    let n = check_points(points_flat)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if important as usize > n {
+        return Err(Error::InvalidArgument(format!(
+            "{important} important points requested but only {n} were given"
+        )));
+    }
+    let mut rng = Rng::new(seed as u64);
+    let keys: Vec<u32> = (0..n).map(|_| rng.next_u32()).collect();
+    // Points off the canvas count towards the nearest border cell
+    let cell = |i: u32, side: u64| {
+        let i = i as usize * 2;
+        let col = ((points_flat[i] / width * side as f64) as u64).min(side - 1);
+        let row = ((points_flat[i + 1] / height * side as f64) as u64).min(side - 1);
+        row * side + col
+    };
+
+    let mut order: Vec<u32> = (0..important).collect();
+    let mut rest: Vec<u32> = (important..n as u32).collect();
+    for depth in 0..=MAX_DEPTH {
+        if rest.is_empty() {
+            break;
+        }
+        let side = 1u64 << depth;
+        let covered: HashSet<u64> = order.iter().map(|&i| cell(i, side)).collect();
+        let mut picks: HashMap<u64, u32> = HashMap::new();
+        for &i in &rest {
+            let c = cell(i, side);
+            if covered.contains(&c) {
+                continue;
+            }
+            let pick = picks.entry(c).or_insert(i);
+            if (keys[i as usize], i) < (keys[*pick as usize], *pick) {
+                *pick = i;
+            }
+        }
+        let mut picked: Vec<u32> = picks.into_values().collect();
+        picked.sort_unstable_by_key(|&i| (keys[i as usize], i));
+        let chosen: HashSet<u32> = picked.iter().copied().collect();
+        rest.retain(|i| !chosen.contains(i));
+        order.extend(picked);
+    }
+    rest.sort_unstable_by_key(|&i| (keys[i as usize], i));
+    order.extend(rest);
+    Ok(order)
+}
+
+/// Indices of about `target_count` points spread evenly over the canvas,
+/// ascending
+///
+/// The first `important` points are always kept, even past
+/// `target_count`; the rest are one per quadtree cell at the depth the
+/// count calls for, so a smaller count gives a subset of a larger one
+/// with the same `seed`. Use the indices to pick per-point attributes on
+/// the JS side.
+#[wasm_bindgen]
+pub fn decimate_points(
+    points_flat: &[f64],
+    width: f64,
+    height: f64,
+    target_count: u32,
+    seed: u32,
+    important: u32,
+) -> Result<Vec<u32>> {
+    let mut order = lod_order(points_flat, width, height, seed, important)?;
+    order.truncate((target_count as usize).max(important as usize));
+    order.sort_unstable();
+    Ok(order)
+}
+
+/// Nested subsets from `lod_levels`, as prefixes of one ranking
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct LodLevels {
+    order: Vec<u32>,
+    ends: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl LodLevels {
+    /// Every point index, most representative first
+    pub fn order(&self) -> Vec<u32> {
+        self.order.clone()
+    }
+
+    /// Level `k` is `order[..level_ends[k]]`; level 0 is the coarsest
+    pub fn level_ends(&self) -> Vec<u32> {
+        self.ends.clone()
+    }
+
+    /// Indices of level `k`, in ranking order
+    pub fn level(&self, k: u32) -> Result<Vec<u32>> {
+        let end = self.ends.get(k as usize).ok_or_else(|| {
+            Error::IndexOutOfRange(format!("level {k} of {}", self.ends.len()))
+        })?;
+        Ok(self.order[..*end as usize].to_vec())
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.ends.len()
+    }
+}
+
+/// `levels` nested detail levels of `points_flat`, each four times the
+/// size of the one before and the last holding every point
+///
+/// Ranking and `important` are those of `decimate_points`: no level has
+/// fewer points than `important`, and every level contains the coarser
+/// ones, so stepping between them only adds or removes points.
+#[wasm_bindgen]
+pub fn lod_levels(
+    points_flat: &[f64],
+    width: f64,
+    height: f64,
+    levels: u32,
+    seed: u32,
+    important: u32,
+) -> Result<LodLevels> {
+    if levels == 0 {
+        return Err(Error::InvalidArgument("levels must be at least 1".into()));
+    }
+    let order = lod_order(points_flat, width, height, seed, important)?;
+    let n = order.len() as f64;
+    let ends = (0..levels)
+        .map(|k| {
+            let size = (n / 4f64.powi((levels - 1 - k) as i32)).ceil() as u32;
+            size.max(important)
+        })
+        .collect();
+    Ok(LodLevels { order, ends })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+
+    #[test]
+    fn test_bins_average_channels_and_count() {
+        let motes = [
+            1.0, 1.0, 0.2, // bin (0, 0)
+            3.0, 5.0, 0.6, // bin (0, 0)
+            15.0, 2.0, 1.0, // bin (1, 0)
+            20.0, 20.0, 0.5, // bottom-right corner, clamped into bin (1, 1)
+            -1.0, 4.0, 0.9, // off canvas
+            f32::NAN, 4.0, 0.9,
+        ];
+        let out = downsample_positions(&motes, 3, 10.0, 20.0, 20.0).unwrap();
+        assert_eq!(
+            out,
+            vec![2.0, 3.0, 0.4, 2.0, 15.0, 2.0, 1.0, 1.0, 20.0, 20.0, 0.5, 1.0]
+        );
+
+        let code = |p: &[f32], stride: u32, cell: f64| {
+            code_of(downsample_positions(p, stride, cell, 20.0, 20.0))
+        };
+        assert_eq!(code(&motes, 1, 10.0), ErrorCode::InvalidArgument);
+        assert_eq!(code(&motes[..4], 3, 10.0), ErrorCode::InvalidLength);
+        assert_eq!(code(&motes, 3, 0.0), ErrorCode::InvalidArgument);
+        assert_eq!(code(&motes, 3, 1e-9), ErrorCode::TooLarge);
+    }
+
+    #[test]
+    fn test_output_is_bounded_by_the_grid() {
+        let mut rng = Rng::new(11);
+        let seeds: Vec<f32> = (0..100_000).map(|_| rng.range(0.0, 1000.0) as f32).collect();
+        let out = downsample_positions(&seeds, 2, 100.0, 1000.0, 1000.0).unwrap();
+        assert_eq!(out.len(), 100 * 3);
+        let total: f32 = out.chunks(3).map(|b| b[2]).sum();
+        assert_eq!(total, 50_000.0);
+        for b in out.chunks(3) {
+            assert!((0.0..=1000.0).contains(&b[0]) && (0.0..=1000.0).contains(&b[1]));
+        }
+    }
+
+    #[test]
+    fn test_decimation_is_spread_nested_and_deterministic() {
+        let mut rng = Rng::new(4);
+        let points: Vec<f64> =
+            (0..4000).flat_map(|_| [rng.range(0.0, 800.0), rng.range(0.0, 800.0)]).collect();
+        let picked = decimate_points(&points, 800.0, 800.0, 64, 7, 0).unwrap();
+        assert_eq!(picked.len(), 64);
+        assert!(picked.windows(2).all(|w| w[0] < w[1]));
+        // One point per 100 × 100 cell of the depth-3 quadtree
+        let mut cells: Vec<u32> = picked
+            .iter()
+            .map(|&i| {
+                let i = i as usize * 2;
+                (points[i + 1] / 100.0) as u32 * 8 + (points[i] / 100.0) as u32
+            })
+            .collect();
+        cells.sort_unstable();
+        assert_eq!(cells, (0..64).collect::<Vec<_>>());
+        assert_eq!(decimate_points(&points, 800.0, 800.0, 64, 7, 0).unwrap(), picked);
+        assert_ne!(decimate_points(&points, 800.0, 800.0, 64, 8, 0).unwrap(), picked);
+        let larger = decimate_points(&points, 800.0, 800.0, 500, 7, 0).unwrap();
+        assert!(picked.iter().all(|i| larger.binary_search(i).is_ok()));
+        let all = decimate_points(&points, 800.0, 800.0, 10_000, 7, 0).unwrap();
+        assert_eq!(all, (0..4000).collect::<Vec<_>>());
+
+        // Important points survive any target, even one below their count
+        let kept = decimate_points(&points, 800.0, 800.0, 10, 7, 20).unwrap();
+        assert_eq!(kept, (0..20).collect::<Vec<_>>());
+        let kept = decimate_points(&points, 800.0, 800.0, 30, 7, 20).unwrap();
+        assert_eq!(&kept[..20], &(0..20).collect::<Vec<_>>()[..]);
+        assert_eq!(kept.len(), 30);
+    }
+
+    #[test]
+    fn test_lod_levels_are_nested_prefixes() {
+        let mut points: Vec<f64> =
+            (0..1000).flat_map(|i| [(i % 40) as f64, (i / 40) as f64]).collect();
+        // Coincident and off-canvas points are ranked too
+        points.extend_from_slice(&[5.0, 5.0, 5.0, 5.0, -10.0, 50.0]);
+        let lod = lod_levels(&points, 40.0, 25.0, 3, 1, 5).unwrap();
+        assert_eq!(lod.level_count(), 3);
+        assert_eq!(lod.level_ends(), vec![63, 251, 1003]);
+        let mut order = lod.order();
+        assert_eq!(&order[..5], &[0, 1, 2, 3, 4]);
+        order.sort_unstable();
+        assert_eq!(order, (0..1003).collect::<Vec<_>>());
+        let (coarse, fine) = (lod.level(0).unwrap(), lod.level(1).unwrap());
+        assert_eq!(&fine[..coarse.len()], &coarse[..]);
+        assert_eq!(code_of(lod.level(3)), ErrorCode::IndexOutOfRange);
+        assert_eq!(lod_levels(&points, 40.0, 25.0, 6, 1, 5).unwrap().level_ends()[0], 5);
+
+        let lod = |p: &[f64], levels, important| {
+            code_of(lod_levels(p, 40.0, 25.0, levels, 0, important))
+        };
+        assert_eq!(lod(&points, 0, 0), ErrorCode::InvalidArgument);
+        assert_eq!(lod(&points, 2, 2000), ErrorCode::InvalidArgument);
+        assert_eq!(lod(&points[..3], 2, 0), ErrorCode::InvalidLength);
+        assert_eq!(lod(&[f64::NAN, 0.0], 2, 0), ErrorCode::NonFiniteInput);
+        let flat = decimate_points(&points, 0.0, 25.0, 10, 0, 0);
+        assert_eq!(code_of(flat), ErrorCode::InvalidArgument);
+    }
+}
@@ -0,0 +1,174 @@
+//! Stable triangle orders for painter's-algorithm fills
+//!
+//! Translucent fills stack in draw order, so `sort_triangles` reorders the
+//! index buffer by a per-triangle key and reports the permutation for any
+//! attribute arrays that have to follow. The sort is stable: triangles
+//! with equal keys keep their input order, so a frame that ties the same
+//! way as the last stacks the same way too.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles};
+
+/// The key `sort_triangles` orders by
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriangleOrder {
+    /// Centroid nearest the focal point first
+    RadialAsc = 0,
+    /// Centroid farthest from the focal point first
+    RadialDesc = 1,
+    /// Smallest centroid y, the top of the canvas, first
+    YAsc = 2,
+    /// Largest area first
+    AreaDesc = 3,
+    /// `keys[t]` for triangle `t`, smallest first
+    Custom = 4,
+}
+
+/// Triangles reordered by `sort_triangles`
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct SortedTriangles {
+    triangles: Vec<u32>,
+    permutation: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl SortedTriangles {
+    /// `[a0, b0, c0, a1, ...]` in draw order, each triangle wound as it was
+    pub fn triangles(&self) -> Vec<u32> {
+        self.triangles.clone()
+    }
+
+    /// Input triangle drawn in each position, so `attr[permutation[k]]`
+    /// belongs to output triangle `k`
+    pub fn permutation(&self) -> Vec<u32> {
+        self.permutation.clone()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.permutation.len()
+    }
+}
+
+/// Reorder `triangles` by `mode`, stably
+///
+/// `focal_x` and `focal_y` are used by the radial orders and `keys`, one
+/// per triangle, by `Custom`; each is ignored otherwise, and `keys` may
+/// then be empty. Keys compare by `total_cmp`, so a `NaN` key sorts
+/// after every number.
+#[wasm_bindgen]
+pub fn sort_triangles(
+    points_flat: &[f64],
+    triangles: &[u32],
+    mode: TriangleOrder,
+    focal_x: f64,
+    focal_y: f64,
+    keys: &[f32],
+) -> Result<SortedTriangles> {
+    let n = check_points(points_flat)?;
+    check_triangles(triangles, n)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let count = triangles.len() / 3;
+    let radial = matches!(mode, TriangleOrder::RadialAsc | TriangleOrder::RadialDesc);
+    if radial && !(focal_x.is_finite() && focal_y.is_finite()) {
+        return Err(Error::NonFiniteInput("focal point must be finite".into()));
+    }
+    if mode == TriangleOrder::Custom && keys.len() != count {
+        return Err(Error::InvalidLength(format!(
+            "{} keys for {count} triangles",
+            keys.len()
+        )));
+    }
+
+    let at = |v: u32| (points_flat[v as usize * 2], points_flat[v as usize * 2 + 1]);
+    let key = |t: usize| -> f64 {
+        let [a, b, c] = [0, 1, 2].map(|k| at(triangles[t * 3 + k]));
+        let centroid = ((a.0 + b.0 + c.0) / 3.0, (a.1 + b.1 + c.1) / 3.0);
+        match mode {
+            TriangleOrder::RadialAsc | TriangleOrder::RadialDesc => {
+                (centroid.0 - focal_x).powi(2) + (centroid.1 - focal_y).powi(2)
+            }
+            TriangleOrder::YAsc => centroid.1,
+            TriangleOrder::AreaDesc => {
+                ((b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)).abs() / 2.0
+            }
+            TriangleOrder::Custom => keys[t] as f64,
+        }
+    };
+    let values: Vec<f64> = (0..count).map(key).collect();
+    let mut permutation: Vec<u32> = (0..count as u32).collect();
+    let descending = matches!(mode, TriangleOrder::RadialDesc | TriangleOrder::AreaDesc);
+    permutation.sort_by(|&s, &t| {
+        let (s, t) = (values[s as usize], values[t as usize]);
+        if descending {
+            t.total_cmp(&s)
+        } else {
+            s.total_cmp(&t)
+        }
+    });
+    let triangles = permutation
+        .iter()
+        .flat_map(|&t| triangles[t as usize * 3..t as usize * 3 + 3].iter().copied())
+        .collect();
+    Ok(SortedTriangles { triangles, permutation })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    /// A 4 × 1 strip of unit squares, two triangles each, left to right
+    fn strip() -> (Vec<f64>, Vec<u32>) {
+        let points: Vec<f64> = (0..10).flat_map(|v| [(v / 2) as f64, (v % 2) as f64]).collect();
+        let square = |c: u32| [2 * c, 2 * c + 2, 2 * c + 3, 2 * c, 2 * c + 3, 2 * c + 1];
+        (points, (0..4).flat_map(square).collect())
+    }
+
+    #[test]
+    fn test_orders_and_permutation() {
+        let (points, triangles) = strip();
+        let sort = |mode, fx: f64, keys: &[f32]| {
+            sort_triangles(&points, &triangles, mode, fx, 0.5, keys).unwrap()
+        };
+        // From the right end the squares come in reverse
+        let radial = sort(TriangleOrder::RadialAsc, 4.0, &[]);
+        assert_eq!(radial.permutation(), vec![6, 7, 4, 5, 2, 3, 0, 1]);
+        for (k, &t) in radial.permutation().iter().enumerate() {
+            let t = t as usize;
+            assert_eq!(radial.triangles()[k * 3..k * 3 + 3], triangles[t * 3..t * 3 + 3]);
+        }
+        let away = sort(TriangleOrder::RadialDesc, 4.0, &[]);
+        assert_eq!(away.permutation(), vec![1, 0, 3, 2, 5, 4, 7, 6]);
+        // The lower triangle of each square has its centroid at y = 1/3,
+        // and equal keys keep their input order
+        let rows = sort(TriangleOrder::YAsc, 0.0, &[]);
+        assert_eq!(rows.permutation(), vec![0, 2, 4, 6, 1, 3, 5, 7]);
+        let areas = sort(TriangleOrder::AreaDesc, 0.0, &[]);
+        assert_eq!(areas.permutation(), (0..8).collect::<Vec<_>>());
+        let keys = [3.0, 1.0, f32::NAN, 1.0, -2.0, 0.0, 1.0, 5.0];
+        let custom = sort(TriangleOrder::Custom, f64::NAN, &keys);
+        assert_eq!(custom.permutation(), vec![4, 5, 1, 3, 6, 0, 7, 2]);
+        assert_eq!(custom.triangle_count(), 8);
+    }
+
+    #[test]
+    fn test_sort_errors() {
+        let (points, triangles) = strip();
+        let sort = |tris: &[u32], mode, fx: f64, keys: &[f32]| {
+            code_of(sort_triangles(&points, tris, mode, fx, 0.0, keys))
+        };
+        let radial = TriangleOrder::RadialAsc;
+        assert_eq!(sort(&triangles, TriangleOrder::Custom, 0.0, &[1.0]), ErrorCode::InvalidLength);
+        assert_eq!(sort(&triangles, radial, f64::NAN, &[]), ErrorCode::NonFiniteInput);
+        assert_eq!(sort(&triangles[..4], radial, 0.0, &[]), ErrorCode::InvalidLength);
+        assert_eq!(sort(&[0, 1, 10], radial, 0.0, &[]), ErrorCode::IndexOutOfRange);
+        let empty = sort_triangles(&points, &[], radial, 0.0, 0.0, &[]).unwrap();
+        assert!(empty.triangles().is_empty() && empty.permutation().is_empty());
+    }
+}
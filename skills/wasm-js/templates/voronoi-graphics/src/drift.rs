@@ -0,0 +1,536 @@
+//! Seeds that wander, bounce softly off the canvas and keep apart
+//!
+//! Each seed accelerates along a heading that random-walks on the
+//! simulation's RNG, so a run is fixed by its seed and the sequence of
+//! `dt`s. Seeds closer than the repulsion radius push each other apart,
+//! and each canvas edge pushes like a seed sitting on it, so cells
+//! neither collapse nor pile up at the border. Neighbours come from a
+//! uniform grid rebuilt every substep, which keeps a step linear in the
+//! seed count. Damping is implicit, as in `ElasticMesh`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::codec::{ByteReader, ByteWriter};
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::rng::Rng;
+use crate::Point;
+
+/// Upper bound accepted for a single `dt`, in seconds
+pub const DRIFT_MAX_DT: f64 = 0.1;
+
+/// Longest substep `step` integrates, in seconds
+const MAX_SUBSTEP: f64 = 1.0 / 120.0;
+/// Heading random walk, radians per square-root second
+const WANDER: f64 = 2.0;
+/// Grid cells per axis are capped so a tiny radius cannot blow up memory
+const MAX_CELLS_PER_AXIS: usize = 1024;
+
+const STATE_MAGIC: &[u8; 4] = b"VGSS";
+const STATE_VERSION: u8 = 1;
+
+/// Seeds bucketed by cell with a counting sort, buffers kept across
+/// rebuilds
+#[derive(Clone, Debug, Default)]
+struct NeighborGrid {
+    inv_cell: f64,
+    cols: usize,
+    rows: usize,
+    /// Where each cell's seeds start in `ids`, with a final entry for
+    /// the end
+    start: Vec<u32>,
+    ids: Vec<u32>,
+}
+
+impl NeighborGrid {
+    fn cell(&self, p: Point) -> (usize, usize) {
+        let clamp = |v: f64, n: usize| ((v * self.inv_cell).max(0.0) as usize).min(n - 1);
+        (clamp(p.x, self.cols), clamp(p.y, self.rows))
+    }
+
+    fn rebuild(&mut self, positions: &[Point], width: f64, height: f64, cell: f64) {
+        let cell = cell.max(width.max(height) / MAX_CELLS_PER_AXIS as f64);
+        self.inv_cell = 1.0 / cell;
+        self.cols = (width * self.inv_cell) as usize + 1;
+        self.rows = (height * self.inv_cell) as usize + 1;
+        self.start.clear();
+        self.start.resize(self.cols * self.rows + 1, 0);
+        for &p in positions {
+            let (c, r) = self.cell(p);
+            self.start[r * self.cols + c + 1] += 1;
+        }
+        for k in 1..self.start.len() {
+            self.start[k] += self.start[k - 1];
+        }
+        self.ids.clear();
+        self.ids.resize(positions.len(), 0);
+        let mut fill = self.start.clone();
+        for (i, &p) in positions.iter().enumerate() {
+            let (c, r) = self.cell(p);
+            let slot = &mut fill[r * self.cols + c];
+            self.ids[*slot as usize] = i as u32;
+            *slot += 1;
+        }
+    }
+
+    fn bucket(&self, c: usize, r: usize) -> &[u32] {
+        let k = r * self.cols + c;
+        &self.ids[self.start[k] as usize..self.start[k + 1] as usize]
+    }
+}
+
+/// Push between two seeds `d` apart, falling linearly to zero at `radius`
+fn repulsion(strength: f64, radius: f64, d: f64) -> f64 {
+    strength * (1.0 - d / radius)
+}
+
+/// Wandering, mutually repelling seeds inside a canvas
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct SeedSimulation {
+    positions: Vec<Point>,
+    velocities: Vec<Point>,
+    /// Direction each seed is drifting in, radians
+    headings: Vec<f64>,
+    pinned: Vec<bool>,
+    width: f64,
+    height: f64,
+    rng: Rng,
+    drift_strength: f64,
+    repulsion_radius: f64,
+    repulsion_strength: f64,
+    damping: f64,
+    grid: NeighborGrid,
+    accel: Vec<Point>,
+}
+
+#[wasm_bindgen]
+impl SeedSimulation {
+    /// Seeds at rest in a `width × height` canvas, headings drawn from
+    /// `seed`
+    ///
+    /// The defaults drift at 30 px/s², repel within 0.8 times the mean
+    /// seed spacing at 400 px/s², and damp at 2 per second.
+    #[wasm_bindgen(constructor)]
+    pub fn new(points_flat: &[f64], width: f64, height: f64, seed: u32) -> Result<SeedSimulation> {
+        let n = check_points(points_flat)?;
+        if points_flat.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+        }
+        if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+            return Err(Error::InvalidArgument("width and height must be positive".into()));
+        }
+        let mut rng = Rng::new(seed as u64);
+        let headings = (0..n).map(|_| rng.range(0.0, std::f64::consts::TAU)).collect();
+        let positions =
+            points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect::<Vec<_>>();
+        Ok(SeedSimulation {
+            positions,
+            velocities: vec![Point::new(0.0, 0.0); n],
+            headings,
+            pinned: vec![false; n],
+            width,
+            height,
+            rng,
+            drift_strength: 30.0,
+            repulsion_radius: 0.8 * (width * height / n.max(1) as f64).sqrt(),
+            repulsion_strength: 400.0,
+            damping: 2.0,
+            grid: NeighborGrid::default(),
+            accel: vec![Point::new(0.0, 0.0); n],
+        })
+    }
+
+    /// Drift acceleration in px/s², repulsion radius in px and strength
+    /// in px/s² at contact, and damping per second, all non-negative
+    ///
+    /// A free seed's speed settles around `drift_strength / damping`.
+    pub fn set_params(
+        &mut self,
+        drift_strength: f64,
+        repulsion_radius: f64,
+        repulsion_strength: f64,
+        damping: f64,
+    ) -> Result<()> {
+        let params = [drift_strength, repulsion_radius, repulsion_strength, damping];
+        if params.iter().any(|v| !(v.is_finite() && *v >= 0.0)) {
+            return Err(Error::InvalidArgument(format!(
+                "set_params({drift_strength}, {repulsion_radius}, {repulsion_strength}, \
+                 {damping}) needs finite non-negative values"
+            )));
+        }
+        self.drift_strength = drift_strength;
+        self.repulsion_radius = repulsion_radius;
+        self.repulsion_strength = repulsion_strength;
+        self.damping = damping;
+        Ok(())
+    }
+
+    /// Hold `index` where it is, still pushing its neighbours away;
+    /// `false` lets it drift again
+    pub fn pin(&mut self, index: u32, pinned: bool) -> Result<()> {
+        self.check_index(index)?;
+        self.pinned[index as usize] = pinned;
+        self.velocities[index as usize] = Point::new(0.0, 0.0);
+        Ok(())
+    }
+
+    /// Put `index` at `(x, y)` and stop it, e.g. under a pointer drag
+    pub fn move_seed(&mut self, index: u32, x: f64, y: f64) -> Result<()> {
+        self.check_index(index)?;
+        if !(x.is_finite() && y.is_finite()) {
+            return Err(Error::NonFiniteInput("seed position must be finite".into()));
+        }
+        self.positions[index as usize] = Point::new(x, y);
+        self.velocities[index as usize] = Point::new(0.0, 0.0);
+        Ok(())
+    }
+
+    /// Advance by `dt` seconds, in `[0, 0.1]`, in substeps of at most
+    /// 1/120 s
+    pub fn step(&mut self, dt: f64) -> Result<()> {
+        if !(dt.is_finite() && (0.0..=DRIFT_MAX_DT).contains(&dt)) {
+            return Err(Error::InvalidArgument(format!(
+                "dt must be in [0, {DRIFT_MAX_DT}], got {dt}"
+            )));
+        }
+        let substeps = ((dt / MAX_SUBSTEP).ceil() as usize).max(1);
+        for _ in 0..substeps {
+            self.substep(dt / substeps as f64);
+        }
+        Ok(())
+    }
+
+    /// `[x0, y0, x1, y1, ...]` for vertex buffers
+    pub fn positions(&self) -> Vec<f32> {
+        self.positions.iter().flat_map(|p| [p.x as f32, p.y as f32]).collect()
+    }
+
+    /// `positions()` at full precision, for
+    /// `DynamicTriangulation::update_points`
+    pub fn positions_f64(&self) -> Vec<f64> {
+        self.positions.iter().flat_map(|p| [p.x, p.y]).collect()
+    }
+
+    pub fn seed_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Serialize positions, velocities, headings, pins, parameters and
+    /// the RNG
+    ///
+    /// A simulation restored from the bytes steps exactly as this one
+    /// does for the same `dt`s.
+    pub fn save_state(&self) -> Vec<u8> {
+        let flat = |v: &[Point]| v.iter().flat_map(|p| [p.x, p.y]).collect::<Vec<f64>>();
+        let mut w = ByteWriter::new();
+        w.bytes(STATE_MAGIC);
+        w.u8(STATE_VERSION);
+        w.f64(self.width);
+        w.f64(self.height);
+        w.f64s(&flat(&self.positions));
+        w.f64s(&flat(&self.velocities));
+        w.f64s(&self.headings);
+        w.u32s(&self.pinned.iter().map(|&p| p as u32).collect::<Vec<u32>>());
+        w.u64(self.rng.state);
+        w.u64(self.rng.inc);
+        w.f64(self.drift_strength);
+        w.f64(self.repulsion_radius);
+        w.f64(self.repulsion_strength);
+        w.f64(self.damping);
+        w.buf
+    }
+
+    /// Replace this simulation with one saved by `save_state`; errors on
+    /// truncated, corrupt or version-mismatched data and leaves `self`
+    /// unchanged
+    pub fn restore_state(&mut self, bytes: &[u8]) -> Result<()> {
+        let mut r = ByteReader::new(bytes);
+        let version = r.header(STATE_MAGIC)?;
+        if version != STATE_VERSION {
+            return Err(Error::VersionMismatch(format!(
+                "simulation state version {version}, expected {STATE_VERSION}"
+            )));
+        }
+        let width = r.f64()?;
+        let height = r.f64()?;
+        let positions = r.f64s()?;
+        let velocities = r.f64s()?;
+        let headings = r.f64s()?;
+        let pinned = r.u32s()?;
+        let rng = Rng { state: r.u64()?, inc: r.u64()? };
+        let params = [r.f64()?, r.f64()?, r.f64()?, r.f64()?];
+        r.finish()?;
+
+        let bad = |what: &str| Error::InvalidData(format!("simulation state: {what}"));
+        let n = headings.len();
+        if positions.len() != 2 * n || velocities.len() != 2 * n || pinned.len() != n {
+            return Err(bad("seed arrays disagree in length"));
+        }
+        let finite = positions.iter().chain(&velocities).chain(&headings).all(|v| v.is_finite());
+        if !finite || pinned.iter().any(|&p| p > 1) {
+            return Err(bad("seed state out of range"));
+        }
+        if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+            return Err(bad("canvas size out of range"));
+        }
+        if params.iter().any(|v| !(v.is_finite() && *v >= 0.0)) {
+            return Err(bad("parameters out of range"));
+        }
+        if rng.inc & 1 == 0 {
+            return Err(bad("rng increment must be odd"));
+        }
+        let points = |flat: &[f64]| flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+        let [drift_strength, repulsion_radius, repulsion_strength, damping] = params;
+        *self = SeedSimulation {
+            positions: points(&positions),
+            velocities: points(&velocities),
+            headings,
+            pinned: pinned.iter().map(|&p| p == 1).collect(),
+            width,
+            height,
+            rng,
+            drift_strength,
+            repulsion_radius,
+            repulsion_strength,
+            damping,
+            grid: NeighborGrid::default(),
+            accel: vec![Point::new(0.0, 0.0); n],
+        };
+        Ok(())
+    }
+}
+
+impl SeedSimulation {
+    fn check_index(&self, index: u32) -> Result<()> {
+        if index as usize >= self.positions.len() {
+            return Err(Error::IndexOutOfRange(format!(
+                "seed {index} out of range for {} seeds",
+                self.positions.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Pairwise and wall pushes on every seed into `accel`
+    fn add_repulsion(&mut self) {
+        let (radius, strength) = (self.repulsion_radius, self.repulsion_strength);
+        if radius == 0.0 || strength == 0.0 {
+            return;
+        }
+        self.grid.rebuild(&self.positions, self.width, self.height, radius);
+        let grid = &self.grid;
+        for (i, &p) in self.positions.iter().enumerate() {
+            let (c, r) = grid.cell(p);
+            for rr in r.saturating_sub(1)..=(r + 1).min(grid.rows - 1) {
+                for cc in c.saturating_sub(1)..=(c + 1).min(grid.cols - 1) {
+                    for &j in grid.bucket(cc, rr) {
+                        let j = j as usize;
+                        if j <= i {
+                            continue;
+                        }
+                        let q = self.positions[j];
+                        let (dx, dy) = (q.x - p.x, q.y - p.y);
+                        let d = dx.hypot(dy);
+                        if d >= radius {
+                            continue;
+                        }
+                        // Coincident seeds split along x, the later one right
+                        let (ux, uy) = if d > 0.0 { (dx / d, dy / d) } else { (1.0, 0.0) };
+                        let f = repulsion(strength, radius, d);
+                        self.accel[i].x -= f * ux;
+                        self.accel[i].y -= f * uy;
+                        self.accel[j].x += f * ux;
+                        self.accel[j].y += f * uy;
+                    }
+                }
+            }
+            let a = &mut self.accel[i];
+            let wall = |d: f64| {
+                if d < radius {
+                    repulsion(strength, radius, d.max(0.0))
+                } else {
+                    0.0
+                }
+            };
+            a.x += wall(p.x) - wall(self.width - p.x);
+            a.y += wall(p.y) - wall(self.height - p.y);
+        }
+    }
+
+    fn substep(&mut self, h: f64) {
+        let wander = WANDER * h.sqrt();
+        for (a, heading) in self.accel.iter_mut().zip(&mut self.headings) {
+            *heading += self.rng.range(-wander, wander);
+            let (sin, cos) = heading.sin_cos();
+            *a = Point::new(self.drift_strength * cos, self.drift_strength * sin);
+        }
+        self.add_repulsion();
+        let keep = 1.0 / (1.0 + self.damping * h);
+        for i in 0..self.positions.len() {
+            if self.pinned[i] {
+                continue;
+            }
+            let (a, v, p) = (self.accel[i], &mut self.velocities[i], &mut self.positions[i]);
+            v.x = (v.x + h * a.x) * keep;
+            v.y = (v.y + h * a.y) * keep;
+            p.x += h * v.x;
+            p.y += h * v.y;
+            // Past an edge the seed stops on it, moving back in
+            if p.x < 0.0 || p.x > self.width {
+                p.x = p.x.clamp(0.0, self.width);
+                v.x = if p.x == 0.0 { v.x.abs() } else { -v.x.abs() };
+            }
+            if p.y < 0.0 || p.y > self.height {
+                p.y = p.y.clamp(0.0, self.height);
+                v.y = if p.y == 0.0 { v.y.abs() } else { -v.y.abs() };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dynamic::DynamicTriangulation;
+    use crate::error::{code_of, ErrorCode};
+    use crate::generate_golden_seeds;
+
+    #[test]
+    fn test_grid_repulsion_matches_all_pairs() {
+        let mut rng = Rng::new(11);
+        let seeds: Vec<f64> =
+            (0..400).flat_map(|_| [rng.range(0.0, 300.0), rng.range(0.0, 200.0)]).collect();
+        let mut sim = SeedSimulation::new(&seeds, 300.0, 200.0, 1).unwrap();
+        sim.set_params(0.0, 17.0, 50.0, 0.0).unwrap();
+        sim.accel.fill(Point::new(0.0, 0.0));
+        sim.add_repulsion();
+
+        let (p, radius) = (&sim.positions, 17.0);
+        for i in 0..p.len() {
+            let mut a = Point::new(0.0, 0.0);
+            for j in (0..p.len()).filter(|&j| j != i) {
+                let (dx, dy) = (p[i].x - p[j].x, p[i].y - p[j].y);
+                let d = dx.hypot(dy);
+                if d < radius {
+                    a.x += repulsion(50.0, radius, d) * dx / d;
+                    a.y += repulsion(50.0, radius, d) * dy / d;
+                }
+            }
+            let wall = |d: f64| if d < radius { repulsion(50.0, radius, d) } else { 0.0 };
+            a.x += wall(p[i].x) - wall(300.0 - p[i].x);
+            a.y += wall(p[i].y) - wall(200.0 - p[i].y);
+            assert!((a.x - sim.accel[i].x).abs() < 1e-9 && (a.y - sim.accel[i].y).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_simulation_is_deterministic_and_contained() {
+        let seeds = generate_golden_seeds(400.0, 300.0, 300);
+        let run = |seed: u32| {
+            let mut sim = SeedSimulation::new(&seeds, 400.0, 300.0, seed).unwrap();
+            sim.set_params(200.0, 12.0, 400.0, 1.0).unwrap();
+            sim.pin(5, true).unwrap();
+            for _ in 0..240 {
+                sim.step(1.0 / 60.0).unwrap();
+            }
+            sim
+        };
+        let sim = run(7);
+        let positions = sim.positions_f64();
+        assert_eq!(positions, run(7).positions_f64());
+        assert_ne!(positions, run(8).positions_f64());
+        assert_eq!(positions[10..12], seeds[10..12]);
+        assert_ne!(positions[12..14], seeds[12..14]);
+        let inside = |p: &[f64]| (0.0..=400.0).contains(&p[0]) && (0.0..=300.0).contains(&p[1]);
+        assert!(positions.chunks(2).all(inside));
+        assert!(sim.positions().iter().zip(&positions).all(|(&a, &b)| a == b as f32));
+
+        // The output feeds the stateful triangulation directly
+        let mut tri = DynamicTriangulation::new(&seeds, 400.0, 300.0).unwrap();
+        tri.update_points(&positions).unwrap();
+        assert!(tri.is_delaunay());
+    }
+
+    #[test]
+    fn test_repulsion_separates_and_walls_push_back() {
+        // Two coincident seeds and one past the right edge
+        let mut sim = SeedSimulation::new(&[50.0, 50.0, 50.0, 50.0, 120.0, 50.0], 100.0, 100.0, 0)
+            .unwrap();
+        sim.set_params(0.0, 10.0, 500.0, 4.0).unwrap();
+        for _ in 0..120 {
+            sim.step(1.0 / 60.0).unwrap();
+        }
+        let p = sim.positions_f64();
+        assert!(p[2] - p[0] > 9.0 && p[1] == 50.0 && p[3] == 50.0, "{p:?}");
+        assert!(p[4] < 92.0, "{p:?}");
+
+        // A dragged seed stays where it was put while pinned
+        sim.move_seed(0, 30.0, 40.0).unwrap();
+        sim.pin(0, true).unwrap();
+        sim.step(0.1).unwrap();
+        assert_eq!(sim.positions_f64()[..2], [30.0, 40.0]);
+        sim.pin(0, false).unwrap();
+        sim.set_params(100.0, 10.0, 500.0, 4.0).unwrap();
+        sim.step(0.1).unwrap();
+        assert_ne!(sim.positions_f64()[..2], [30.0, 40.0]);
+        assert_eq!(sim.seed_count(), 3);
+    }
+
+    #[test]
+    fn test_simulation_errors() {
+        let new = |p: &[f64], w: f64| code_of(SeedSimulation::new(p, w, 10.0, 0));
+        assert_eq!(new(&[1.0], 10.0), ErrorCode::InvalidLength);
+        assert_eq!(new(&[f64::NAN, 1.0], 10.0), ErrorCode::NonFiniteInput);
+        assert_eq!(new(&[1.0, 1.0], 0.0), ErrorCode::InvalidArgument);
+        let mut sim = SeedSimulation::new(&[1.0, 1.0], 10.0, 10.0, 0).unwrap();
+        assert_eq!(code_of(sim.step(0.2)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(sim.step(-0.01)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(sim.set_params(1.0, -1.0, 1.0, 1.0)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(sim.set_params(1.0, 1.0, f64::NAN, 1.0)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(sim.pin(1, true)), ErrorCode::IndexOutOfRange);
+        assert_eq!(code_of(sim.move_seed(0, f64::INFINITY, 0.0)), ErrorCode::NonFiniteInput);
+        // No seeds is a valid, if quiet, simulation
+        let mut empty = SeedSimulation::new(&[], 10.0, 10.0, 0).unwrap();
+        empty.step(0.1).unwrap();
+        assert!(empty.positions().is_empty());
+    }
+
+    #[test]
+    fn test_restored_state_replays_exactly() {
+        let seeds = generate_golden_seeds(300.0, 200.0, 150);
+        let mut sim = SeedSimulation::new(&seeds, 300.0, 200.0, 3).unwrap();
+        sim.set_params(120.0, 15.0, 300.0, 1.5).unwrap();
+        sim.pin(4, true).unwrap();
+        for _ in 0..30 {
+            sim.step(1.0 / 60.0).unwrap();
+        }
+        let bytes = sim.save_state();
+        let mut replay = SeedSimulation::new(&[1.0, 1.0], 10.0, 10.0, 0).unwrap();
+        replay.restore_state(&bytes).unwrap();
+        for k in 0..60 {
+            let dt = [1.0 / 60.0, 0.05, 0.0][k % 3];
+            sim.step(dt).unwrap();
+            replay.step(dt).unwrap();
+            assert_eq!(replay.positions_f64(), sim.positions_f64());
+        }
+        assert_eq!(replay.save_state(), sim.save_state());
+
+        // Corrupt or mismatched bytes error and leave the target alone
+        let mut version = bytes.clone();
+        version[4] = STATE_VERSION + 1;
+        assert_eq!(code_of(replay.restore_state(&version)), ErrorCode::VersionMismatch);
+        let short = &bytes[..bytes.len() - 3];
+        assert_eq!(code_of(replay.restore_state(short)), ErrorCode::InvalidData);
+        let mut even = bytes.clone();
+        let inc_at = bytes.len() - 4 * 8 - 8;
+        even[inc_at] &= !1;
+        assert_eq!(code_of(replay.restore_state(&even)), ErrorCode::InvalidData);
+        assert_eq!(replay.save_state(), sim.save_state());
+        for i in 0..bytes.len() {
+            let mut flipped = bytes.clone();
+            flipped[i] ^= 0x5A;
+            let _ = replay.restore_state(&flipped);
+        }
+    }
+}
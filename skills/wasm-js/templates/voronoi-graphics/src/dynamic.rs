@@ -0,0 +1,1496 @@
+//! Frame-to-frame triangulation of moving points
+//!
+//! `DynamicTriangulation` keeps its mesh between updates: when points
+//! move without inverting a triangle, Delaunay is restored with a handful
+//! of Lawson flips; otherwise it falls back to a full rebuild. The unique
+//! edge list is maintained alongside, with stable slots so motes and GPU
+//! buffers can be patched from the reported add/remove events, and so are
+//! the triangles, for renderers keeping per-triangle instance data.
+
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet};
+
+use wasm_bindgen::prelude::*;
+
+use crate::codec::{ByteReader, ByteWriter};
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::mesh::{capacity_bytes, EdgeChange, Mesh, TriangleChange, NONE, SUPER};
+use crate::rescale::rescale_transform;
+use crate::seeds::FitMode;
+use crate::Point;
+
+/// Event kind written in the first word of each `edge_events()` record
+pub(crate) const EDGE_REMOVED: u32 = 0;
+pub(crate) const EDGE_ADDED: u32 = 1;
+
+const STATE_MAGIC: &[u8; 4] = b"VGDT";
+const STATE_VERSION: u8 = 1;
+
+/// Canonical edges in stable slots with a free list for reuse
+#[derive(Clone, Debug, Default)]
+pub(crate) struct EdgeSlots {
+    /// `(i, j)` per slot, `(NONE, NONE)` when the slot is free
+    pub(crate) slots: Vec<(u32, u32)>,
+    pub(crate) lookup: HashMap<(u32, u32), u32>,
+    pub(crate) free: Vec<u32>,
+    /// Pending `[kind, slot, i, j]` records
+    pub(crate) events: Vec<u32>,
+}
+
+impl EdgeSlots {
+    pub(crate) fn from_sorted(edges: &[(u32, u32)]) -> EdgeSlots {
+        EdgeSlots {
+            slots: edges.to_vec(),
+            lookup: edges.iter().enumerate().map(|(s, &e)| (e, s as u32)).collect(),
+            free: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    pub(crate) fn add(&mut self, edge: (u32, u32)) {
+        if self.lookup.contains_key(&edge) {
+            return;
+        }
+        let slot = match self.free.pop() {
+            Some(s) => {
+                self.slots[s as usize] = edge;
+                s
+            }
+            None => {
+                self.slots.push(edge);
+                (self.slots.len() - 1) as u32
+            }
+        };
+        self.lookup.insert(edge, slot);
+        self.events.extend_from_slice(&[EDGE_ADDED, slot, edge.0, edge.1]);
+    }
+
+    pub(crate) fn remove(&mut self, edge: (u32, u32)) {
+        if let Some(slot) = self.lookup.remove(&edge) {
+            self.slots[slot as usize] = (NONE, NONE);
+            self.free.push(slot);
+            self.events.extend_from_slice(&[EDGE_REMOVED, slot, edge.0, edge.1]);
+        }
+    }
+
+    pub(crate) fn live_count(&self) -> usize {
+        self.lookup.len()
+    }
+
+    /// Apply a net change set: removals first, then additions, each sorted
+    pub(crate) fn apply(&mut self, mut removed: Vec<(u32, u32)>, mut added: Vec<(u32, u32)>) {
+        removed.sort_unstable();
+        added.sort_unstable();
+        for e in removed {
+            self.remove(e);
+        }
+        for e in added {
+            self.add(e);
+        }
+    }
+
+    /// Replace the contents with `edges` (sorted), keeping surviving slots
+    pub(crate) fn sync_to(&mut self, edges: &[(u32, u32)]) {
+        let mut removed: Vec<(u32, u32)> = self
+            .lookup
+            .keys()
+            .filter(|e| edges.binary_search(e).is_err())
+            .copied()
+            .collect();
+        removed.sort_unstable();
+        let added: Vec<(u32, u32)> = edges
+            .iter()
+            .filter(|e| !self.lookup.contains_key(e))
+            .copied()
+            .collect();
+        self.apply(removed, added);
+    }
+}
+
+pub(crate) type EdgeList = Vec<(u32, u32)>;
+
+/// Corners of `t` in ascending order, the key slots are matched by
+fn triangle_key(t: [u32; 3]) -> [u32; 3] {
+    let mut key = t;
+    key.sort_unstable();
+    key
+}
+
+/// Real triangles in stable slots with a free list for reuse
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TriangleSlots {
+    /// Counter-clockwise corners per slot, `[NONE; 3]` when it is free
+    pub(crate) slots: Vec<[u32; 3]>,
+    pub(crate) lookup: HashMap<[u32; 3], u32>,
+    pub(crate) free: Vec<u32>,
+    /// Slots the current update emptied
+    pub(crate) removed: Vec<u32>,
+    /// Slots the current update filled
+    pub(crate) added: Vec<u32>,
+}
+
+impl TriangleSlots {
+    pub(crate) fn from_triangles(triangles: impl Iterator<Item = [u32; 3]>) -> TriangleSlots {
+        let slots: Vec<[u32; 3]> = triangles.collect();
+        TriangleSlots {
+            lookup: slots.iter().enumerate().map(|(s, &t)| (triangle_key(t), s as u32)).collect(),
+            slots,
+            ..TriangleSlots::default()
+        }
+    }
+
+    fn add(&mut self, t: [u32; 3]) {
+        let key = triangle_key(t);
+        if self.lookup.contains_key(&key) {
+            return;
+        }
+        let slot = match self.free.pop() {
+            Some(s) => {
+                self.slots[s as usize] = t;
+                s
+            }
+            None => {
+                self.slots.push(t);
+                (self.slots.len() - 1) as u32
+            }
+        };
+        self.lookup.insert(key, slot);
+        self.added.push(slot);
+    }
+
+    fn remove(&mut self, key: [u32; 3]) {
+        if let Some(slot) = self.lookup.remove(&key) {
+            self.slots[slot as usize] = [NONE; 3];
+            self.free.push(slot);
+            self.removed.push(slot);
+        }
+    }
+
+    /// Forget the previous update's changes
+    pub(crate) fn begin_update(&mut self) {
+        self.removed.clear();
+        self.added.clear();
+    }
+
+    /// Apply a net change set: removals first, then additions, each sorted
+    /// so the slots they land in do not depend on hash order
+    fn apply(&mut self, mut removed: Vec<[u32; 3]>, mut added: Vec<[u32; 3]>) {
+        removed.sort_unstable();
+        added.sort_unstable_by_key(|&t| triangle_key(t));
+        for key in removed {
+            self.remove(key);
+        }
+        for t in added {
+            self.add(t);
+        }
+    }
+
+    /// Replace the contents with `triangles`, keeping surviving slots
+    ///
+    /// A triangle whose corners came back in the other winding, as after
+    /// a jump turned it over, is replaced rather than kept.
+    fn sync_to(&mut self, triangles: impl Iterator<Item = [u32; 3]>) {
+        let current: HashMap<[u32; 3], [u32; 3]> =
+            triangles.map(|t| (triangle_key(t), t)).collect();
+        let kept = |key: &[u32; 3], slot: u32| {
+            let [a, b, c] = self.slots[slot as usize];
+            let t = current.get(key);
+            t == Some(&[a, b, c]) || t == Some(&[b, c, a]) || t == Some(&[c, a, b])
+        };
+        let removed: Vec<[u32; 3]> =
+            self.lookup.iter().filter(|&(k, &s)| !kept(k, s)).map(|(&k, _)| k).collect();
+        let mut added = Vec::new();
+        for (key, &t) in &current {
+            if !self.lookup.get(key).is_some_and(|&s| kept(key, s)) {
+                added.push(t);
+            }
+        }
+        self.apply(removed, added);
+    }
+
+    /// Slots emptied by the current update, ascending and each once
+    pub(crate) fn removed_slots(&self) -> Vec<u32> {
+        let mut removed = self.removed.clone();
+        removed.sort_unstable();
+        removed.dedup();
+        removed
+    }
+
+    /// `[slot, a, b, c]` records of the slots the current update filled
+    /// that are still live, by ascending slot
+    pub(crate) fn added_records(&self) -> Vec<u32> {
+        let mut added = self.added.clone();
+        added.sort_unstable();
+        added.dedup();
+        added
+            .into_iter()
+            .filter(|&s| self.slots[s as usize][0] != NONE)
+            .flat_map(|s| {
+                let [a, b, c] = self.slots[s as usize];
+                [s, a, b, c]
+            })
+            .collect()
+    }
+
+    /// Inverse of the triangle part of `DynamicTriangulation::encode`,
+    /// requiring the live slots to hold exactly the mesh's triangles
+    fn decode(r: &mut ByteReader, mesh: &Mesh) -> Result<TriangleSlots> {
+        let bad = |what: &str| Error::InvalidData(format!("triangle slots: {what}"));
+        let slots_flat = r.u32s()?;
+        let free = r.u32s()?;
+        let removed = r.u32s()?;
+        let added = r.u32s()?;
+        if slots_flat.len() % 3 != 0 {
+            return Err(bad("odd array length"));
+        }
+        let slots: Vec<[u32; 3]> = slots_flat.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect();
+        let mut lookup = HashMap::with_capacity(slots.len());
+        for (s, &t) in slots.iter().enumerate() {
+            if t == [NONE; 3] {
+                continue;
+            }
+            if lookup.insert(triangle_key(t), s as u32).is_some() {
+                return Err(bad("duplicate triangle"));
+            }
+        }
+        let mut live = 0;
+        for t in mesh.real_triangles() {
+            let matches = lookup.get(&triangle_key(t)).is_some_and(|&s| {
+                let [a, b, c] = slots[s as usize];
+                t == [a, b, c] || t == [b, c, a] || t == [c, a, b]
+            });
+            if !matches {
+                return Err(bad("slots disagree with the mesh"));
+            }
+            live += 1;
+        }
+        let in_range = |list: &[u32]| list.iter().all(|&s| (s as usize) < slots.len());
+        if live != lookup.len()
+            || free.len() + lookup.len() != slots.len()
+            || free.iter().any(|&s| slots.get(s as usize) != Some(&[NONE; 3]))
+            || !in_range(&removed)
+            || !in_range(&added)
+        {
+            return Err(bad("free list does not match slots"));
+        }
+        Ok(TriangleSlots { slots, lookup, free, removed, added })
+    }
+}
+
+/// Buffers `update_points` keeps between frames
+#[derive(Clone, Debug, Default)]
+pub(crate) struct UpdateScratch {
+    /// Per internal vertex, whether this update moved it
+    moved: Vec<bool>,
+    /// Edges still to check for a flip
+    stack: Vec<(u32, u32, u32)>,
+}
+
+/// Net `(removed, added)` edges from a mesh change log, in input indices
+pub(crate) fn net_changes(changes: &[EdgeChange]) -> (EdgeList, EdgeList) {
+    let mut net: HashMap<(u32, u32), i32> = HashMap::new();
+    for &change in changes {
+        let (edge, delta) = match change {
+            EdgeChange::Added(a, b) => ((a, b), 1),
+            EdgeChange::Removed(a, b) => ((a, b), -1),
+        };
+        if edge.0 >= SUPER {
+            *net.entry((edge.0 - SUPER, edge.1 - SUPER)).or_insert(0) += delta;
+        }
+    }
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    for (edge, count) in net {
+        match count.cmp(&0) {
+            std::cmp::Ordering::Less => removed.push(edge),
+            std::cmp::Ordering::Greater => added.push(edge),
+            std::cmp::Ordering::Equal => {}
+        }
+    }
+    (removed, added)
+}
+
+/// Net `(removed, added)` real triangles from a mesh triangle log, in
+/// input indices; removed ones as sorted keys, added ones wound as the
+/// mesh has them
+fn net_triangle_changes(changes: &[TriangleChange]) -> (Vec<[u32; 3]>, Vec<[u32; 3]>) {
+    let mut net: HashMap<[u32; 3], (i32, [u32; 3])> = HashMap::new();
+    for &change in changes {
+        let (t, delta) = match change {
+            TriangleChange::Added(t) => (t, 1),
+            TriangleChange::Removed(t) => (t, -1),
+        };
+        if t.iter().all(|&v| v >= SUPER) {
+            let entry = net.entry(triangle_key(t)).or_insert((0, t));
+            entry.0 += delta;
+            entry.1 = t;
+        }
+    }
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    for (key, (count, t)) in net {
+        match count.cmp(&0) {
+            std::cmp::Ordering::Less => removed.push(key.map(|v| v - SUPER)),
+            std::cmp::Ordering::Greater => added.push(t.map(|v| v - SUPER)),
+            std::cmp::Ordering::Equal => {}
+        }
+    }
+    (removed, added)
+}
+
+pub(crate) fn flat_to_points(points_flat: &[f64]) -> Vec<Point> {
+    points_flat
+        .chunks_exact(2)
+        .map(|c| Point { x: c[0], y: c[1] })
+        .collect()
+}
+
+/// Delaunay triangulation that follows moving points between frames
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct DynamicTriangulation {
+    pub(crate) mesh: Mesh,
+    pub(crate) edges: EdgeSlots,
+    pub(crate) triangle_slots: TriangleSlots,
+    pub(crate) bounds: [f64; 4],
+    pub(crate) last_flips: usize,
+    pub(crate) last_rebuilt: bool,
+    /// Bumped whenever the topology changes
+    pub(crate) generation: u32,
+    /// Sorted live edges, derived on first use after each change
+    pub(crate) sorted: OnceCell<EdgeList>,
+    /// `triangles()` indices the last `update_points` moved or created;
+    /// `None` when every triangle counts as changed
+    pub(crate) changed: Option<Vec<u32>>,
+    pub(crate) scratch: UpdateScratch,
+}
+
+#[wasm_bindgen]
+impl DynamicTriangulation {
+    /// Triangulate the initial points; `width`/`height` size the super
+    /// triangle so points wandering around the canvas rarely force a rebuild
+    #[wasm_bindgen(constructor)]
+    pub fn new(points_flat: &[f64], width: f64, height: f64) -> Result<DynamicTriangulation> {
+        check_points(points_flat)?;
+        if points_flat.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+        }
+        let bounds = [0.0, 0.0, width, height];
+        let mut mesh = Mesh::build(&flat_to_points(points_flat), Some(bounds));
+        mesh.track_changes = true;
+        let real: Vec<(u32, u32)> = mesh
+            .real_edges()
+            .into_iter()
+            .map(|(a, b)| (a - SUPER, b - SUPER))
+            .collect();
+        Ok(DynamicTriangulation {
+            triangle_slots: TriangleSlots::from_triangles(mesh.real_triangles()),
+            mesh,
+            edges: EdgeSlots::from_sorted(&real),
+            bounds,
+            last_flips: 0,
+            last_rebuilt: false,
+            generation: 0,
+            sorted: OnceCell::new(),
+            changed: None,
+            scratch: UpdateScratch::default(),
+        })
+    }
+
+    /// Keep an edge between nearly cocircular points until the opposite
+    /// point is inside the circumcircle by more than `threshold`
+    ///
+    /// The margin is the incircle determinant divided by the shared edge
+    /// length to the fourth power, so it does not depend on zoom. A value
+    /// around `0.01` stops diagonals of slowly moving near-square quads from
+    /// flipping back and forth; 0, the default, is exact Delaunay. Above 0
+    /// the mesh is only approximately Delaunay, and `is_delaunay` accepts
+    /// violations up to the threshold. Takes effect from the next update;
+    /// rebuilds start out exact.
+    pub fn set_flip_hysteresis(&mut self, threshold: f64) -> Result<()> {
+        if !threshold.is_finite() || threshold < 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "flip hysteresis must be finite and non-negative, got {threshold}"
+            )));
+        }
+        self.mesh.flip_tolerance = threshold;
+        Ok(())
+    }
+
+    pub fn flip_hysteresis(&self) -> f64 {
+        self.mesh.flip_tolerance
+    }
+
+    /// Whether no edge would flip under the current hysteresis
+    pub fn is_delaunay(&self) -> bool {
+        (0..self.mesh.tris.len() as u32)
+            .filter(|&t| self.mesh.alive[t as usize])
+            .all(|t| (0..3).all(|k| !self.mesh.edge_is_illegal(t, k)))
+    }
+
+    /// Move every point; the count must match the constructor's
+    pub fn update_points(&mut self, points_flat: &[f64]) -> Result<()> {
+        let n = check_points(points_flat)?;
+        if n != self.mesh.real_count() {
+            return Err(Error::InvalidLength(format!(
+                "expected {} points, got {n}",
+                self.mesh.real_count()
+            )));
+        }
+        if points_flat.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+        }
+
+        let mut moved = std::mem::take(&mut self.scratch.moved);
+        moved.clear();
+        moved.resize(self.mesh.points.len(), false);
+        for (i, c) in points_flat.chunks_exact(2).enumerate() {
+            let v = i + SUPER as usize;
+            let p = Point { x: c[0], y: c[1] };
+            if self.mesh.points[v] != p {
+                self.mesh.points[v] = p;
+                moved[v] = true;
+            }
+        }
+        self.restore_delaunay(&moved);
+        self.scratch.moved = moved;
+        Ok(())
+    }
+
+    /// Add points after the existing ones, inserting each into the mesh
+    ///
+    /// New points get the next indices in order. Edge slots and events are
+    /// patched as for `update_points`; a point outside the super triangle
+    /// forces a rebuild.
+    pub fn append_points(&mut self, points_flat: &[f64]) -> Result<()> {
+        check_points(points_flat)?;
+        if points_flat.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+        }
+        self.last_flips = 0;
+        self.last_rebuilt = false;
+        self.changed = None;
+        self.mesh.clear_changes();
+        self.triangle_slots.begin_update();
+        let mut escaped = false;
+        for p in flat_to_points(points_flat) {
+            let v = self.mesh.points.len() as u32;
+            self.mesh.points.push(p);
+            self.mesh.vert_tri.push(NONE);
+            escaped |= !self.mesh.encloses(p);
+            if !escaped {
+                self.mesh.insert_vertex(v);
+            }
+        }
+        if escaped {
+            self.mesh.clear_changes();
+            self.rebuild();
+        } else {
+            self.apply_mesh_changes();
+        }
+        Ok(())
+    }
+
+    /// Drop every point from index `count` on, keeping the first `count`
+    ///
+    /// Points are unlinked from the last one down, each hole being
+    /// retriangulated in place, so surviving edges keep their slots.
+    pub fn truncate_points(&mut self, count: usize) -> Result<()> {
+        if count > self.mesh.real_count() {
+            return Err(Error::InvalidArgument(format!(
+                "cannot truncate {} points to {count}",
+                self.mesh.real_count()
+            )));
+        }
+        self.last_flips = 0;
+        self.last_rebuilt = false;
+        self.changed = None;
+        self.mesh.clear_changes();
+        self.triangle_slots.begin_update();
+        self.shrink_to(count);
+        Ok(())
+    }
+
+    /// Add one point and return its index
+    ///
+    /// Only the triangles whose circumcircle holds the point are replaced,
+    /// found by walking from the last triangle touched, so points added
+    /// near each other in turn are cheap.
+    pub fn insert_point(&mut self, x: f64, y: f64) -> Result<u32> {
+        let index = self.mesh.real_count() as u32;
+        self.append_points(&[x, y])?;
+        Ok(index)
+    }
+
+    /// Remove the point at `index`, retriangulating the hole it leaves
+    ///
+    /// Like `Vec::swap_remove`, the last point takes over `index` so the
+    /// other indices stay put; the edges it brings along are reported as
+    /// removed and re-added in `edge_events`.
+    pub fn remove_point(&mut self, index: u32) -> Result<()> {
+        let count = self.mesh.real_count();
+        if index as usize >= count {
+            return Err(Error::IndexOutOfRange(format!(
+                "point {index} out of range for {count} points"
+            )));
+        }
+        self.last_flips = 0;
+        self.last_rebuilt = false;
+        self.changed = None;
+        self.mesh.clear_changes();
+        self.triangle_slots.begin_update();
+        let last = (count - 1) as u32 + SUPER;
+        if !self.mesh.swap_vertices(index + SUPER, last) {
+            self.mesh.points.swap((index + SUPER) as usize, last as usize);
+            self.mesh.clear_changes();
+            self.rebuild();
+        }
+        self.shrink_to(count - 1);
+        Ok(())
+    }
+
+    /// Carry the points over to a `new_w × new_h` canvas as
+    /// `rescale_points` would; true when the triangles came through as
+    /// they were, so index buffers built from them can be kept
+    ///
+    /// The whole mesh is mapped, super triangle included, which keeps
+    /// every triangle counter-clockwise. A uniform `mode` leaves it
+    /// Delaunay; after a stretch the flips that restore it are counted in
+    /// `last_flip_count`, with the usual rebuild should they fail. Later
+    /// rebuilds size the super triangle for the new canvas.
+    pub fn rescale(&mut self, new_w: f64, new_h: f64, mode: FitMode) -> Result<bool> {
+        let [x0, y0, x1, y1] = self.bounds;
+        let [sx, sy, tx, ty] = rescale_transform((x1 - x0, y1 - y0), (new_w, new_h), mode)?;
+        for p in &mut self.mesh.points {
+            *p = Point { x: p.x * sx + tx, y: p.y * sy + ty };
+        }
+        self.bounds = [0.0, 0.0, new_w, new_h];
+        self.restore_delaunay(&vec![true; self.mesh.points.len()]);
+        Ok(!self.last_rebuilt && self.last_flips == 0)
+    }
+
+    /// Current triangles as `[a, b, c, ...]` input point indices (CCW)
+    pub fn triangles(&self) -> Vec<u32> {
+        self.mesh.real_triangles().flatten().collect()
+    }
+
+    /// Triangle slots as `[a0, b0, c0, a1, b1, c1, ...]` (CCW)
+    ///
+    /// A slot keeps its triangle for as long as that triangle exists, so it
+    /// can index per-triangle instance data; free slots read
+    /// `[0xFFFFFFFF; 3]` and are reused by later additions.
+    pub fn triangles_indexed(&self) -> Vec<u32> {
+        self.triangle_slots.slots.iter().flatten().copied().collect()
+    }
+
+    /// Number of triangle slots, live or free (length of
+    /// `triangles_indexed() / 3`)
+    pub fn triangle_slot_count(&self) -> usize {
+        self.triangle_slots.slots.len()
+    }
+
+    /// Slots of `triangles_indexed()` whose triangle the last update
+    /// removed, ascending
+    pub fn removed_triangles(&self) -> Vec<u32> {
+        self.triangle_slots.removed_slots()
+    }
+
+    /// Triangles the last update added as `[slot, a, b, c]` records, by
+    /// ascending slot
+    ///
+    /// Removals are made first, so added triangles fill the freed slots
+    /// before any are appended; a slot can be both removed and added.
+    /// With the update's `removed_triangles` this is everything needed to
+    /// patch a copy of the previous `triangles_indexed()`; a small move
+    /// touches a handful of slots whatever the mesh size.
+    pub fn added_triangles(&self) -> Vec<u32> {
+        self.triangle_slots.added_records()
+    }
+
+    /// `update_points`, then the triangle diff as `{ removed, added }`
+    /// `Uint32Array`s, laid out as `removed_triangles` and
+    /// `added_triangles`
+    pub fn update_points_diff(&mut self, points_flat: &[f64]) -> Result<JsValue> {
+        self.update_points(points_flat)?;
+        let diff = js_sys::Object::new();
+        let removed = js_sys::Uint32Array::from(&self.removed_triangles()[..]);
+        let added = js_sys::Uint32Array::from(&self.added_triangles()[..]);
+        for (key, value) in [("removed", removed), ("added", added)] {
+            // Setting a plain property on a fresh object cannot fail
+            let _ = js_sys::Reflect::set(&diff, &key.into(), &value);
+        }
+        Ok(diff.into())
+    }
+
+    /// Edge slots as `[i0, j0, i1, j1, ...]`
+    ///
+    /// A slot keeps its edge for as long as that edge exists; free slots
+    /// read `[0xFFFFFFFF, 0xFFFFFFFF]` and are reused by later additions.
+    pub fn edges_indexed(&self) -> Vec<u32> {
+        self.edges.slots.iter().flat_map(|&(a, b)| [a, b]).collect()
+    }
+
+    /// Live edges compacted and sorted as `[i0, j0, i1, j1, ...]`
+    ///
+    /// The sorted list is kept until the topology next changes, so calling
+    /// this every frame only pays for the copy.
+    pub fn edges(&self) -> Vec<u32> {
+        self.sorted_edges().iter().flat_map(|&(a, b)| [a, b]).collect()
+    }
+
+    /// Endpoints of `edges()` as `[x0, y0, x1, y1, ...]`, ready for a line
+    /// buffer
+    pub fn edge_positions(&self) -> Vec<f32> {
+        let at = |v: u32| self.mesh.points[(v + SUPER) as usize];
+        self.sorted_edges()
+            .iter()
+            .flat_map(|&(a, b)| {
+                let (p, q) = (at(a), at(b));
+                [p.x as f32, p.y as f32, q.x as f32, q.y as f32]
+            })
+            .collect()
+    }
+
+    /// Counter bumped by every update that changes the triangles; while it
+    /// holds still, index buffers built from `triangles()` and `edges()`
+    /// stay valid
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Drain edge changes since the previous call as `[kind, slot, i, j]`
+    /// records, `kind` being 0 for removed and 1 for added
+    pub fn edge_events(&mut self) -> Vec<u32> {
+        std::mem::take(&mut self.edges.events)
+    }
+
+    /// Number of slots, live or free (length of `edges_indexed() / 2`)
+    pub fn edge_slot_count(&self) -> usize {
+        self.edges.slots.len()
+    }
+
+    /// Number of live edges
+    pub fn edge_count(&self) -> usize {
+        self.edges.live_count()
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.mesh.real_count()
+    }
+
+    /// Edge flips performed by the last update
+    pub fn last_flip_count(&self) -> usize {
+        self.last_flips
+    }
+
+    /// Whether the last update had to rebuild from scratch
+    pub fn last_update_rebuilt(&self) -> bool {
+        self.last_rebuilt
+    }
+
+    /// Indices into `triangles()` of the triangles the last
+    /// `update_points` moved or flipped into place, ascending
+    ///
+    /// Together they cover every point whose triangle changed, except
+    /// where the hull shrank, so `triangles_bbox` of them is the region
+    /// to repaint. After a rebuild, or any other kind of update, every
+    /// triangle is listed.
+    pub fn changed_triangles(&self) -> Vec<u32> {
+        match &self.changed {
+            Some(changed) => changed.clone(),
+            None => (0..self.mesh.real_triangles().count() as u32).collect(),
+        }
+    }
+
+    /// Bytes held by the buffers kept between updates, as an object from
+    /// group name to size plus a `total`, e.g. `{ triangles: 640000, ...,
+    /// total: 1250000 }`
+    ///
+    /// Sizes are capacities, so they include room grown for earlier, larger
+    /// frames; they level off once the point count stops changing, and
+    /// `shrink_to_fit` hands the spare room back.
+    pub fn memory_stats(&self) -> JsValue {
+        let stats = js_sys::Object::new();
+        let groups = self.pool_bytes();
+        let total: usize = groups.iter().map(|(_, bytes)| bytes).sum();
+        for (group, bytes) in groups.into_iter().chain([("total", total)]) {
+            // Setting a plain property on a fresh object cannot fail
+            let _ = js_sys::Reflect::set(&stats, &group.into(), &(bytes as f64).into());
+        }
+        stats.into()
+    }
+
+    /// Release the room the pooled buffers hold beyond the current mesh,
+    /// after a burst of points has gone again
+    ///
+    /// The next updates grow the scratch buffers back to what they need.
+    pub fn shrink_to_fit(&mut self) {
+        self.mesh.shrink_to_fit();
+        self.scratch = UpdateScratch::default();
+        self.edges.slots.shrink_to_fit();
+        self.edges.lookup.shrink_to_fit();
+        self.edges.free.shrink_to_fit();
+        self.edges.events.shrink_to_fit();
+        let triangles = &mut self.triangle_slots;
+        triangles.slots.shrink_to_fit();
+        triangles.lookup.shrink_to_fit();
+        triangles.free.shrink_to_fit();
+        triangles.removed.shrink_to_fit();
+        triangles.added.shrink_to_fit();
+        if let Some(changed) = &mut self.changed {
+            changed.shrink_to_fit();
+        }
+        self.sorted = OnceCell::new();
+    }
+
+    /// Serialize the points, topology, edge and triangle slots and the
+    /// last update's diffs
+    ///
+    /// `restore_state` on the bytes gives a triangulation whose later
+    /// updates report the same triangles, slots and events as this one.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = ByteWriter::new();
+        w.bytes(STATE_MAGIC);
+        w.u8(STATE_VERSION);
+        self.encode(&mut w);
+        w.buf
+    }
+
+    /// Replace this triangulation with one saved by `save_state`; errors
+    /// on truncated, corrupt or version-mismatched data and leaves `self`
+    /// unchanged
+    pub fn restore_state(&mut self, bytes: &[u8]) -> Result<()> {
+        let mut r = ByteReader::new(bytes);
+        let version = r.header(STATE_MAGIC)?;
+        if version != STATE_VERSION {
+            return Err(Error::VersionMismatch(format!(
+                "triangulation state version {version}, expected {STATE_VERSION}"
+            )));
+        }
+        let restored = DynamicTriangulation::decode(&mut r)?;
+        r.finish()?;
+        *self = restored;
+        Ok(())
+    }
+}
+
+impl DynamicTriangulation {
+    /// Bytes per buffer group behind `memory_stats`
+    pub(crate) fn pool_bytes(&self) -> Vec<(&'static str, usize)> {
+        let edges = &self.edges;
+        // Entries only; the table's control bytes are left out
+        let lookup = edges.lookup.capacity() * std::mem::size_of::<((u32, u32), u32)>();
+        let mut groups = self.mesh.pool_bytes().to_vec();
+        groups.push((
+            "edges",
+            capacity_bytes(&edges.slots)
+                + lookup
+                + capacity_bytes(&edges.free)
+                + capacity_bytes(&edges.events)
+                + self.sorted.get().map_or(0, capacity_bytes),
+        ));
+        let triangles = &self.triangle_slots;
+        let lookup = triangles.lookup.capacity() * std::mem::size_of::<([u32; 3], u32)>();
+        groups.push((
+            "triangle_slots",
+            capacity_bytes(&triangles.slots)
+                + lookup
+                + capacity_bytes(&triangles.free)
+                + capacity_bytes(&triangles.removed)
+                + capacity_bytes(&triangles.added),
+        ));
+        groups.push((
+            "update_scratch",
+            capacity_bytes(&self.scratch.moved)
+                + capacity_bytes(&self.scratch.stack)
+                + self.changed.as_ref().map_or(0, capacity_bytes),
+        ));
+        groups
+    }
+
+    /// Unlink points from the last one down until `count` remain
+    fn shrink_to(&mut self, count: usize) {
+        let mut failed = false;
+        while self.mesh.real_count() > count {
+            if failed {
+                self.mesh.points.pop();
+                self.mesh.vert_tri.pop();
+                continue;
+            }
+            match self.mesh.remove_last_vertex() {
+                Some(flips) => self.last_flips += flips,
+                None => failed = true,
+            }
+        }
+        // A duplicate left unlinked earlier may now have a place of its own
+        if failed || self.mesh.vert_tri[SUPER as usize..].contains(&NONE) {
+            self.mesh.clear_changes();
+            self.rebuild();
+        } else {
+            self.apply_mesh_changes();
+        }
+    }
+
+    /// Repair the mesh after `mesh.points` changed for the flagged vertices
+    pub(crate) fn restore_delaunay(&mut self, moved: &[bool]) {
+        self.last_flips = 0;
+        self.last_rebuilt = false;
+        self.triangle_slots.begin_update();
+
+        let unlinked = self.mesh.vert_tri[SUPER as usize..].contains(&NONE);
+        let escaped = (SUPER as usize..self.mesh.points.len())
+            .any(|v| moved[v] && !self.mesh.encloses(self.mesh.points[v]));
+        if unlinked || escaped || !self.mesh.orientation_valid() {
+            self.rebuild();
+            return;
+        }
+
+        let mut stack = std::mem::take(&mut self.scratch.stack);
+        stack.clear();
+        for t in 0..self.mesh.tris.len() {
+            if self.mesh.alive[t] && self.mesh.tris[t].iter().any(|&v| moved[v as usize]) {
+                self.mesh.push_edges(t as u32, &mut stack);
+            }
+        }
+        self.mesh.clear_changes();
+        let cap = 16 * self.mesh.tris.len() + 64;
+        let legalized = self.mesh.legalize(&mut stack, cap);
+        self.scratch.stack = stack;
+        match legalized {
+            Some(flips) => {
+                self.last_flips = flips;
+                let reused = self.changed.take().unwrap_or_default();
+                self.changed = Some(self.changed_slots(moved, reused));
+                self.apply_mesh_changes();
+            }
+            None => self.rebuild(),
+        }
+    }
+
+    /// `triangles()` indices of the triangles with a corner in `moved` or a
+    /// side the change log added, written over `changed`
+    fn changed_slots(&self, moved: &[bool], mut changed: Vec<u32>) -> Vec<u32> {
+        let added: HashSet<(u32, u32)> = self
+            .mesh
+            .changes
+            .iter()
+            .filter_map(|&c| match c {
+                EdgeChange::Added(a, b) => Some((a, b)),
+                EdgeChange::Removed(..) => None,
+            })
+            .collect();
+        changed.clear();
+        let real = (0..self.mesh.tris.len()).filter(|&t| self.mesh.is_real_triangle(t));
+        for (k, t) in real.enumerate() {
+            let v = self.mesh.tris[t];
+            let side = |j: usize| (v[j].min(v[(j + 1) % 3]), v[j].max(v[(j + 1) % 3]));
+            if v.iter().any(|&u| moved[u as usize]) || (0..3).any(|j| added.contains(&side(j))) {
+                changed.push(k as u32);
+            }
+        }
+        changed
+    }
+
+    /// Move the mesh change log into the edge and triangle slots as net
+    /// events
+    fn apply_mesh_changes(&mut self) {
+        // Flips that cancel out still change triangle slots
+        if !self.mesh.changes.is_empty() {
+            self.touch();
+        }
+        let (removed, added) = net_triangle_changes(&self.mesh.triangle_changes);
+        self.triangle_slots.apply(removed, added);
+        let (removed, added) = net_changes(&self.mesh.changes);
+        self.mesh.clear_changes();
+        self.edges.apply(removed, added);
+    }
+
+    /// Note a topology change
+    fn touch(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        self.sorted = OnceCell::new();
+    }
+
+    pub(crate) fn rebuild(&mut self) {
+        let tolerance = self.mesh.flip_tolerance;
+        self.mesh.rebuild(Some(self.bounds));
+        self.mesh.track_changes = true;
+        self.mesh.flip_tolerance = tolerance;
+        let real: Vec<(u32, u32)> = self
+            .mesh
+            .real_edges()
+            .into_iter()
+            .map(|(a, b)| (a - SUPER, b - SUPER))
+            .collect();
+        self.edges.sync_to(&real);
+        self.triangle_slots.sync_to(self.mesh.real_triangles());
+        self.last_rebuilt = true;
+        self.changed = None;
+        self.touch();
+    }
+
+    #[cfg(test)]
+    pub(crate) fn live_edges(&self) -> EdgeList {
+        self.sorted_edges().clone()
+    }
+
+    /// Live edges in canonical sorted order
+    pub(crate) fn sorted_edges(&self) -> &EdgeList {
+        self.sorted.get_or_init(|| {
+            let mut live: Vec<(u32, u32)> = self.edges.lookup.keys().copied().collect();
+            live.sort_unstable();
+            live
+        })
+    }
+
+    /// Serialize mesh, edge and triangle slots and pending events
+    pub(crate) fn encode(&self, w: &mut ByteWriter) {
+        self.mesh.encode(w);
+        w.u32s(&self.edges_indexed());
+        w.u32s(&self.edges.free);
+        w.u32s(&self.edges.events);
+        self.bounds.iter().for_each(|&b| w.f64(b));
+        w.u64(self.last_flips as u64);
+        w.u8(self.last_rebuilt as u8);
+        w.f64(self.mesh.flip_tolerance);
+        let triangles = &self.triangle_slots;
+        w.u32s(&self.triangles_indexed());
+        w.u32s(&triangles.free);
+        w.u32s(&triangles.removed);
+        w.u32s(&triangles.added);
+        w.u32(self.generation);
+        w.u8(self.changed.is_some() as u8);
+        if let Some(changed) = &self.changed {
+            w.u32s(changed);
+        }
+    }
+
+    pub(crate) fn decode(r: &mut ByteReader) -> Result<DynamicTriangulation> {
+        let bad = |what: &str| Error::InvalidData(format!("edge slots: {what}"));
+        let mut mesh = Mesh::decode(r)?;
+        let slots_flat = r.u32s()?;
+        let free = r.u32s()?;
+        let events = r.u32s()?;
+        let bounds = [r.f64()?, r.f64()?, r.f64()?, r.f64()?];
+        let last_flips = r.u64()? as usize;
+        let last_rebuilt = r.u8()? != 0;
+        mesh.flip_tolerance = r.f64()?;
+        let triangle_slots = TriangleSlots::decode(r, &mesh)?;
+        let generation = r.u32()?;
+        let changed = match r.u8()? {
+            0 => None,
+            1 => Some(r.u32s()?),
+            _ => return Err(Error::InvalidData("triangle slots: bad changed flag".into())),
+        };
+        if !(mesh.flip_tolerance.is_finite() && mesh.flip_tolerance >= 0.0) {
+            return Err(bad("flip hysteresis out of range"));
+        }
+
+        if slots_flat.len() % 2 != 0 || events.len() % 4 != 0 {
+            return Err(bad("odd array length"));
+        }
+        let n = mesh.real_count() as u32;
+        let slots: Vec<(u32, u32)> = slots_flat.chunks_exact(2).map(|c| (c[0], c[1])).collect();
+        let mut lookup = HashMap::with_capacity(slots.len());
+        for (s, &(a, b)) in slots.iter().enumerate() {
+            if (a, b) == (NONE, NONE) {
+                continue;
+            }
+            if a >= b || b >= n || lookup.insert((a, b), s as u32).is_some() {
+                return Err(bad("invalid edge"));
+            }
+        }
+        if free.len() + lookup.len() != slots.len()
+            || free.iter().any(|&s| slots.get(s as usize) != Some(&(NONE, NONE)))
+        {
+            return Err(bad("free list does not match slots"));
+        }
+        let triangle_count = triangle_slots.lookup.len() as u32;
+        if changed.as_ref().is_some_and(|c| c.iter().any(|&k| k >= triangle_count)) {
+            return Err(Error::InvalidData("triangle slots: changed index out of range".into()));
+        }
+
+        Ok(DynamicTriangulation {
+            triangle_slots,
+            mesh,
+            edges: EdgeSlots { slots, lookup, free, events },
+            bounds,
+            last_flips,
+            last_rebuilt,
+            generation,
+            sorted: OnceCell::new(),
+            changed,
+            scratch: UpdateScratch::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::graph::unique_edges;
+    use crate::rng::Rng;
+    use crate::{generate_golden_seeds, rescale_points, validate_delaunay};
+
+    #[test]
+    fn test_incremental_edges_match_recompute_under_jitter() {
+        let base = generate_golden_seeds(800.0, 600.0, 250);
+        let mut tri = DynamicTriangulation::new(&base, 800.0, 600.0).unwrap();
+        let mut rng = Rng::new(42);
+        let mut points = base.clone();
+        let mut total_flips = 0;
+        let mut rebuilds = 0;
+
+        for frame in 0..400 {
+            for (p, b) in points.iter_mut().zip(&base) {
+                // Slow random walk tethered to the base position
+                *p += (rng.next_f64() - 0.5) * 1.5 + (b - *p) * 0.02;
+            }
+            let before: HashMap<(u32, u32), u32> = tri.edges.lookup.clone();
+            tri.update_points(&points).unwrap();
+            total_flips += tri.last_flip_count();
+            rebuilds += tri.last_update_rebuilt() as usize;
+
+            let expected = unique_edges(&tri.triangles());
+            assert_eq!(tri.live_edges(), expected, "frame {frame}");
+
+            // Surviving edges keep their slots
+            for (edge, slot) in &tri.edges.lookup {
+                if let Some(old) = before.get(edge) {
+                    assert_eq!(old, slot, "edge {edge:?} moved slot on frame {frame}");
+                }
+            }
+            // Events describe exactly the difference
+            let events = tri.edge_events();
+            let adds = events.chunks(4).filter(|e| e[0] == EDGE_ADDED).count();
+            let removes = events.chunks(4).filter(|e| e[0] == EDGE_REMOVED).count();
+            assert_eq!(before.len() + adds - removes, tri.edge_count());
+        }
+        assert!(total_flips > 0, "jitter should have flipped some edges");
+        assert!(rebuilds < 40, "small motion should mostly flip, rebuilt {rebuilds} times");
+    }
+
+    #[test]
+    fn test_cached_edges_follow_the_topology() {
+        let base = generate_golden_seeds(400.0, 400.0, 60);
+        let mut tri = DynamicTriangulation::new(&base, 400.0, 400.0).unwrap();
+        let edges = tri.edges();
+        let generation = tri.generation();
+        tri.update_points(&base).unwrap();
+        assert_eq!(tri.generation(), generation, "a still frame keeps the buffers");
+        assert_eq!(tri.edges(), edges);
+
+        let positions = tri.edge_positions();
+        assert_eq!(positions.len(), edges.len() * 2);
+        for (ends, xy) in edges.chunks(2).zip(positions.chunks(4)) {
+            for (&v, p) in ends.iter().zip(xy.chunks(2)) {
+                let v = v as usize;
+                assert_eq!(p, [base[2 * v] as f32, base[2 * v + 1] as f32]);
+            }
+        }
+
+        // Swapping two seeds forces flips
+        let mut moved = base.clone();
+        moved.swap(0, 40);
+        moved.swap(1, 41);
+        tri.update_points(&moved).unwrap();
+        assert_ne!(tri.generation(), generation);
+        assert_eq!(tri.live_edges(), unique_edges(&tri.triangles()));
+    }
+
+    #[test]
+    fn test_changed_triangles_cover_a_local_update() {
+        let base = generate_golden_seeds(400.0, 400.0, 300);
+        let mut tri = DynamicTriangulation::new(&base, 400.0, 400.0).unwrap();
+        let total = tri.triangles().len() as u32 / 3;
+        assert_eq!(tri.changed_triangles(), (0..total).collect::<Vec<_>>());
+
+        let corners = |t: &[u32]| {
+            let mut c = [t[0], t[1], t[2]];
+            c.sort_unstable();
+            c
+        };
+        let mut rng = Rng::new(9);
+        let mut points = base.clone();
+        for frame in 0..20 {
+            let before: HashSet<[u32; 3]> = tri.triangles().chunks(3).map(corners).collect();
+            // Nudge a couple of nearby seeds far enough to flip edges
+            for v in [150 + frame, 160 + frame] {
+                points[2 * v] += (rng.next_f64() - 0.5) * 12.0;
+                points[2 * v + 1] += (rng.next_f64() - 0.5) * 12.0;
+            }
+            tri.update_points(&points).unwrap();
+            assert!(!tri.last_update_rebuilt());
+            let triangles = tri.triangles();
+            let changed = tri.changed_triangles();
+            assert!(!changed.is_empty() && changed.len() * 4 < triangles.len() / 3);
+            assert!(changed.windows(2).all(|w| w[0] < w[1]));
+            // Anything not listed is a triangle that was already there,
+            // untouched
+            for (k, t) in triangles.chunks(3).enumerate() {
+                if changed.binary_search(&(k as u32)).is_err() {
+                    assert!(before.contains(&corners(t)), "frame {frame}: {t:?}");
+                    assert!(t.iter().all(|&v| ![150 + frame, 160 + frame].contains(&(v as usize))));
+                }
+            }
+            let bbox = crate::triangles_bbox(&points, &triangles, &changed).unwrap();
+            let p = &points[2 * (150 + frame)..];
+            assert!(bbox[0] <= p[0] && p[0] <= bbox[2] && bbox[1] <= p[1] && p[1] <= bbox[3]);
+        }
+        tri.update_points(&points).unwrap();
+        assert!(tri.changed_triangles().is_empty());
+        tri.append_points(&[200.0, 200.0]).unwrap();
+        assert_eq!(tri.changed_triangles().len(), tri.triangles().len() / 3);
+    }
+
+    #[test]
+    fn test_slot_list_reuses_freed_slots() {
+        let base = generate_golden_seeds(400.0, 400.0, 80);
+        let mut tri = DynamicTriangulation::new(&base, 400.0, 400.0).unwrap();
+        let mut rng = Rng::new(5);
+        let mut points = base.clone();
+        for _ in 0..50 {
+            for (p, b) in points.iter_mut().zip(&base) {
+                *p = b + (rng.next_f64() - 0.5) * 20.0;
+            }
+            tri.update_points(&points).unwrap();
+        }
+        // Free slots are refilled before the list grows
+        let slots = tri.edge_slot_count();
+        assert!(slots <= tri.edge_count() + tri.edges.free.len());
+        assert_eq!(slots, tri.edge_count() + tri.edges.free.len());
+        let flat = tri.edges_indexed();
+        assert_eq!(flat.len(), slots * 2);
+
+        // Removing a point frees its edges' slots and the next point added
+        // takes them, so the list does not grow
+        tri.edge_events();
+        tri.remove_point(7).unwrap();
+        let removed: HashSet<u32> =
+            tri.edge_events().chunks(4).filter(|e| e[0] == EDGE_REMOVED).map(|e| e[1]).collect();
+        let freed: Vec<u32> =
+            tri.edges.free.iter().copied().filter(|s| removed.contains(s)).collect();
+        assert!(!freed.is_empty());
+        tri.insert_point(points[14], points[15]).unwrap();
+        let added: Vec<u32> =
+            tri.edge_events().chunks(4).filter(|e| e[0] == EDGE_ADDED).map(|e| e[1]).collect();
+        assert!(added.iter().any(|s| freed.contains(s)), "{added:?} took none of {freed:?}");
+        assert_eq!(tri.edge_slot_count(), slots);
+    }
+
+    #[test]
+    fn test_large_motion_rebuilds() {
+        let base = generate_golden_seeds(400.0, 400.0, 50);
+        let mut tri = DynamicTriangulation::new(&base, 400.0, 400.0).unwrap();
+        // Mirror horizontally: every triangle inverts
+        let mirrored: Vec<f64> = base
+            .chunks(2)
+            .flat_map(|c| [400.0 - c[0], c[1]])
+            .collect();
+        tri.update_points(&mirrored).unwrap();
+        assert!(tri.last_update_rebuilt());
+        assert_eq!(tri.live_edges(), unique_edges(&tri.triangles()));
+    }
+
+    #[test]
+    fn test_append_and_truncate_patch_edges() {
+        let all = generate_golden_seeds(600.0, 600.0, 400);
+        let mut tri = DynamicTriangulation::new(&all[..200], 600.0, 600.0).unwrap();
+        let mut live: HashMap<u32, (u32, u32)> =
+            tri.edges.lookup.iter().map(|(&e, &s)| (s, e)).collect();
+        tri.edge_events();
+        for n in [260, 400, 330, 120, 400, 3] {
+            let count = tri.point_count();
+            if n > count {
+                tri.append_points(&all[count * 2..n * 2]).unwrap();
+            } else {
+                tri.truncate_points(n).unwrap();
+            }
+            assert!(!tri.last_update_rebuilt());
+            assert_eq!(tri.point_count(), n);
+            assert_eq!(tri.live_edges(), unique_edges(&tri.triangles()));
+            let fresh = DynamicTriangulation::new(&all[..n * 2], 600.0, 600.0).unwrap();
+            assert_eq!(tri.live_edges(), fresh.live_edges(), "{n} points");
+            // Replaying the events on the previous slot table reproduces it
+            for e in tri.edge_events().chunks(4) {
+                if e[0] == EDGE_ADDED {
+                    assert!(live.insert(e[1], (e[2], e[3])).is_none());
+                } else {
+                    assert_eq!(live.remove(&e[1]), Some((e[2], e[3])));
+                }
+            }
+            let expected: HashMap<u32, (u32, u32)> =
+                tri.edges.lookup.iter().map(|(&e, &s)| (s, e)).collect();
+            assert_eq!(live, expected);
+        }
+        assert_eq!(code_of(tri.truncate_points(4)), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_insert_and_remove_single_points() {
+        let mut points = generate_golden_seeds(500.0, 500.0, 150);
+        let mut tri = DynamicTriangulation::new(&points, 500.0, 500.0).unwrap();
+        let mut live: HashMap<u32, (u32, u32)> =
+            tri.edges.lookup.iter().map(|(&e, &s)| (s, e)).collect();
+        let mut rng = Rng::new(7);
+        for step in 0..120 {
+            let count = points.len() / 2;
+            if step % 3 == 2 {
+                let (x, y) = (rng.next_f64() * 500.0, rng.next_f64() * 500.0);
+                assert_eq!(tri.insert_point(x, y).unwrap() as usize, count);
+                points.extend([x, y]);
+            } else {
+                let i = rng.below(count as u32) as usize;
+                tri.remove_point(i as u32).unwrap();
+                let last = 2 * (count - 1);
+                points.swap(2 * i, last);
+                points.swap(2 * i + 1, last + 1);
+                points.truncate(last);
+            }
+            assert!(!tri.last_update_rebuilt(), "step {step}");
+            assert_eq!(tri.point_count(), points.len() / 2);
+            let fresh = DynamicTriangulation::new(&points, 500.0, 500.0).unwrap();
+            assert_eq!(tri.live_edges(), fresh.live_edges(), "step {step}");
+            for e in tri.edge_events().chunks(4) {
+                if e[0] == EDGE_ADDED {
+                    assert!(live.insert(e[1], (e[2], e[3])).is_none());
+                } else {
+                    assert_eq!(live.remove(&e[1]), Some((e[2], e[3])));
+                }
+            }
+        }
+        let expected: HashMap<u32, (u32, u32)> =
+            tri.edges.lookup.iter().map(|(&e, &s)| (s, e)).collect();
+        assert_eq!(live, expected);
+        let count = tri.point_count() as u32;
+        assert_eq!(code_of(tri.remove_point(count)), ErrorCode::IndexOutOfRange);
+        assert_eq!(code_of(tri.insert_point(f64::NAN, 0.0)), ErrorCode::NonFiniteInput);
+    }
+
+    #[test]
+    fn test_rescale_keeps_or_repairs_the_mesh() {
+        let base = generate_golden_seeds(400.0, 300.0, 400);
+        let mut tri = DynamicTriangulation::new(&base, 400.0, 300.0).unwrap();
+        let before = tri.triangles();
+        tri.edge_events();
+        assert!(tri.rescale(900.0, 600.0, FitMode::Contain).unwrap());
+        assert_eq!(tri.triangles(), before);
+        assert!(tri.edge_events().is_empty());
+
+        // Squashing flips the spiral's rings; the result is the Delaunay
+        // triangulation of the same points mapped by `rescale_points`
+        let contained = rescale_points(&base, 400.0, 300.0, 900.0, 600.0, FitMode::Contain);
+        let contained = contained.unwrap();
+        let squashed = rescale_points(&contained, 900.0, 600.0, 1800.0, 300.0, FitMode::Stretch);
+        assert!(!tri.rescale(1800.0, 300.0, FitMode::Stretch).unwrap());
+        assert!(tri.last_flip_count() > 0 && !tri.last_update_rebuilt());
+        assert!(validate_delaunay(&squashed.unwrap(), &tri.triangles()).unwrap());
+        assert_eq!(tri.live_edges(), unique_edges(&tri.triangles()));
+        assert_eq!(code_of(tri.rescale(0.0, 300.0, FitMode::Cover)), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_pooled_buffers_stop_growing() {
+        let base = crate::generate_jittered_grid(800.0, 600.0, 100, 50, 0.5, 3).unwrap();
+        let mut tri = DynamicTriangulation::new(&base, 800.0, 600.0).unwrap();
+        let total = |tri: &DynamicTriangulation| -> usize {
+            tri.pool_bytes().iter().map(|(_, bytes)| bytes).sum()
+        };
+        let mut rng = Rng::new(7);
+        let phases: Vec<f64> = (0..5000).map(|_| rng.next_f64() * 6.3).collect();
+        let mut points = base.clone();
+        // Highest pool total over the first and the second half of the run
+        let (mut peaks, mut rebuilds) = ([0; 2], 0);
+        for frame in 0..10_000 {
+            // A different fiftieth of the points drifts each frame, away
+            // from the hull, whose flat triangles would keep inverting
+            let t = frame as f64 * 0.002;
+            for i in (frame % 50..5000).step_by(50) {
+                let (x, y) = (base[i * 2], base[i * 2 + 1]);
+                if (40.0..760.0).contains(&x) && (40.0..560.0).contains(&y) {
+                    points[i * 2] = x + 3.0 * (t + phases[i]).sin();
+                    points[i * 2 + 1] = y + 3.0 * (t + phases[i]).cos();
+                }
+            }
+            // Now and then a jump no flip sequence can follow
+            let jumped: Vec<f64>;
+            let frame_points = if frame % 1000 == 999 {
+                jumped = points.iter().map(|p| p + 40.0 * (rng.next_f64() - 0.5)).collect();
+                &jumped
+            } else {
+                &points
+            };
+            tri.update_points(frame_points).unwrap();
+            tri.edge_events();
+            rebuilds += tri.last_update_rebuilt() as usize;
+            let half = &mut peaks[frame / 5000];
+            *half = (*half).max(total(&tri));
+        }
+        assert!(rebuilds >= 10, "{rebuilds}");
+        // Once every buffer has seen its busiest frame it stops growing
+        assert!(peaks[1] <= peaks[0], "{} bytes in the first half, {} after", peaks[0], peaks[1]);
+        let end = total(&tri);
+
+        tri.shrink_to_fit();
+        assert!(total(&tri) < end);
+        tri.update_points(&base).unwrap();
+        assert!(tri.is_delaunay());
+    }
+
+    /// Patch `copy` of an earlier `triangles_indexed()` with the last
+    /// update's diff, and check it lands on the current slots and that
+    /// these hold exactly the current triangles
+    fn patch_triangle_slots(tri: &DynamicTriangulation, copy: &mut Vec<u32>) {
+        for s in tri.removed_triangles() {
+            copy[s as usize * 3..s as usize * 3 + 3].fill(NONE);
+        }
+        for record in tri.added_triangles().chunks(4) {
+            let s = record[0] as usize;
+            if copy.len() < s * 3 + 3 {
+                copy.resize(s * 3 + 3, NONE);
+            }
+            copy[s * 3..s * 3 + 3].copy_from_slice(&record[1..]);
+        }
+        assert_eq!(*copy, tri.triangles_indexed());
+        // Compare as sets, each triangle rotated to start at its smallest
+        let rotated = |t: &[u32]| {
+            let k = (0..3).min_by_key(|&k| t[k]).unwrap();
+            [t[k], t[(k + 1) % 3], t[(k + 2) % 3]]
+        };
+        let mut live: Vec<[u32; 3]> =
+            copy.chunks(3).filter(|t| t[0] != NONE).map(rotated).collect();
+        let mut current: Vec<[u32; 3]> = tri.triangles().chunks(3).map(rotated).collect();
+        live.sort_unstable();
+        current.sort_unstable();
+        assert_eq!(live, current);
+    }
+
+    #[test]
+    fn test_triangle_diff_is_local_and_keeps_slots() {
+        let base = generate_golden_seeds(800.0, 600.0, 2000);
+        let mut tri = DynamicTriangulation::new(&base, 800.0, 600.0).unwrap();
+        let mut copy = tri.triangles_indexed();
+        let mut rng = Rng::new(11);
+        let mut points = base.clone();
+        let mut flipped = false;
+        for _ in 0..200 {
+            // Nudge one interior point; most nudges flip a few edges
+            let i = 100 + rng.below(1800) as usize;
+            points[i * 2] += 4.0 * (rng.next_f64() - 0.5);
+            points[i * 2 + 1] += 4.0 * (rng.next_f64() - 0.5);
+            let before = tri.triangles_indexed();
+            tri.update_points(&points).unwrap();
+            assert!(!tri.last_update_rebuilt());
+            let removed = tri.removed_triangles();
+            assert!(removed.len() <= 4 * tri.last_flip_count());
+            assert_eq!(tri.added_triangles().len(), 4 * removed.len());
+            flipped |= !removed.is_empty();
+            patch_triangle_slots(&tri, &mut copy);
+            // Everything the diff leaves out stayed in its slot
+            let after = tri.triangles_indexed();
+            assert_eq!(after.len(), before.len());
+            let slot = |v: &[u32], s: usize| [v[s * 3], v[s * 3 + 1], v[s * 3 + 2]];
+            let mut untouched = (0..before.len() / 3).filter(|s| !removed.contains(&(*s as u32)));
+            assert!(untouched.all(|s| slot(&before, s) == slot(&after, s)));
+        }
+        assert!(flipped);
+
+        // The other kinds of update report their diffs the same way
+        tri.insert_point(400.5, 300.5).unwrap();
+        patch_triangle_slots(&tri, &mut copy);
+        assert_eq!(tri.added_triangles().len() / 4, tri.removed_triangles().len() + 2);
+        tri.remove_point(17).unwrap();
+        patch_triangle_slots(&tri, &mut copy);
+        tri.truncate_points(1990).unwrap();
+        patch_triangle_slots(&tri, &mut copy);
+        let jumped: Vec<f64> =
+            points[..3980].iter().map(|p| p + 60.0 * (rng.next_f64() - 0.5)).collect();
+        tri.update_points(&jumped).unwrap();
+        assert!(tri.last_update_rebuilt());
+        patch_triangle_slots(&tri, &mut copy);
+        let unchanged = tri.triangles_indexed();
+        tri.update_points(&jumped).unwrap();
+        assert!(tri.removed_triangles().is_empty() && tri.added_triangles().is_empty());
+        assert_eq!(tri.triangles_indexed(), unchanged);
+        assert_eq!(tri.triangle_slot_count(), unchanged.len() / 3);
+    }
+
+    #[test]
+    fn test_update_rejects_count_change() {
+        let base = generate_golden_seeds(400.0, 400.0, 20);
+        let mut tri = DynamicTriangulation::new(&base, 400.0, 400.0).unwrap();
+        assert_eq!(code_of(tri.update_points(&base[..30])), ErrorCode::InvalidLength);
+        assert_eq!(code_of(tri.update_points(&base[..31])), ErrorCode::InvalidLength);
+    }
+
+    #[test]
+    fn test_hysteresis_keeps_wobbling_diagonal() {
+        // A square rotating slowly while one corner wobbles across the
+        // circle through the other three
+        let square = |frame: u32| -> Vec<f64> {
+            let turn = frame as f64 * 0.002;
+            (0..4)
+                .flat_map(|i| {
+                    let wobble = if i == 0 { 1.0 + 2e-3 * (frame as f64 * 0.3).sin() } else { 1.0 };
+                    let angle = turn + i as f64 * std::f64::consts::FRAC_PI_2;
+                    [50.0 + 20.0 * wobble * angle.cos(), 50.0 + 20.0 * wobble * angle.sin()]
+                })
+                .collect()
+        };
+        let diagonal_flips = |hysteresis: f64| {
+            let mut tri = DynamicTriangulation::new(&square(0), 100.0, 100.0).unwrap();
+            tri.set_flip_hysteresis(hysteresis).unwrap();
+            tri.edge_events();
+            let mut flips = 0;
+            for frame in 1..300 {
+                tri.update_points(&square(frame)).unwrap();
+                assert!(!tri.last_update_rebuilt() && tri.is_delaunay());
+                flips += tri.edge_events().chunks(4).filter(|e| e[0] == EDGE_ADDED).count();
+            }
+            flips
+        };
+        assert!(diagonal_flips(0.0) > 20);
+        assert_eq!(diagonal_flips(0.01), 0);
+
+        let mut tri = DynamicTriangulation::new(&square(0), 100.0, 100.0).unwrap();
+        assert_eq!(code_of(tri.set_flip_hysteresis(-1.0)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(tri.set_flip_hysteresis(f64::NAN)), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_restore_state_rejects_broken_topology() {
+        let base = generate_golden_seeds(400.0, 300.0, 40);
+        let mut tri = DynamicTriangulation::new(&base, 400.0, 300.0).unwrap();
+        let frame = |t: f64| -> Vec<f64> {
+            base.iter().enumerate().map(|(i, &v)| v + 6.0 * (t + i as f64 * 0.37).sin()).collect()
+        };
+        tri.update_points(&frame(0.5)).unwrap();
+        let bytes = tri.save_state();
+        let mut expected = DynamicTriangulation::new(&[], 1.0, 1.0).unwrap();
+        expected.restore_state(&bytes).unwrap();
+        expected.update_points(&frame(0.6)).unwrap();
+        assert!(!expected.last_update_rebuilt());
+
+        // Header, then the point array; the links run from the triangles
+        // up to the trailing `last` and change flag
+        let mut mesh = ByteWriter::new();
+        tri.mesh.encode(&mut mesh);
+        let start = 5 + 4 + 16 * tri.mesh.points.len();
+        let end = 5 + mesh.buf.len() - 5;
+        // A flipped link either errors or still describes the same mesh, as
+        // a dead slot's stale corners or another triangle round a vertex do;
+        // the latter may change which slots the update fills, not the mesh
+        for i in start..end {
+            let mut flipped = bytes.clone();
+            flipped[i] ^= 1;
+            let mut replay = DynamicTriangulation::new(&[], 1.0, 1.0).unwrap();
+            if replay.restore_state(&flipped).is_ok() {
+                replay.update_points(&frame(0.6)).unwrap();
+                assert_eq!(replay.triangles_indexed(), expected.triangles_indexed(), "byte {i}");
+                assert_eq!(replay.live_edges(), expected.live_edges(), "byte {i}");
+            }
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//! Per-edge rendering attributes
+//!
+//! Every function here emits one value (or one fixed-stride record) per
+//! edge: per canonical Delaunay edge, i.e. aligned with the sorted
+//! `(i, j)` order from `graph::unique_edges`, for those taking triangles,
+//! and index for index with the given list for those taking
+//! `edge_indices`, so the result can be bound directly as an instanced
+//! vertex attribute.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{
+    check_edge_indices, check_points, check_triangles, edge_length, graph_distances,
+    unique_edges, Csr,
+};
+
+/// Depth-of-field weight in [0, 1] per canonical edge
+///
+/// Each edge takes the graph distance (Euclidean length along Delaunay
+/// edges) of its nearer endpoint to the closest focus vertex. Distances
+/// are divided by the largest reachable distance so `falloff` is
+/// independent of canvas scale, then mapped through `exp(-falloff · d)`.
+/// Edges touching a focus vertex are exactly 1; edges in components with
+/// no focus vertex are 0.
+#[wasm_bindgen]
+pub fn compute_edge_lod(
+    points_flat: &[f64],
+    triangles: &[u32],
+    focus_vertices: &[u32],
+    falloff: f64,
+) -> Result<Vec<f32>> {
+    let point_count = check_points(points_flat)?;
+    check_triangles(triangles, point_count)?;
+    if focus_vertices.is_empty() {
+        return Err(Error::InvalidLength("at least one focus vertex is required".into()));
+    }
+    if let Some(&bad) = focus_vertices.iter().find(|&&v| v as usize >= point_count) {
+        return Err(Error::IndexOutOfRange(format!(
+            "focus vertex {bad} out of range for {point_count} points"
+        )));
+    }
+    if !falloff.is_finite() || falloff < 0.0 {
+        return Err(Error::InvalidArgument(format!(
+            "falloff must be finite and non-negative, got {falloff}"
+        )));
+    }
+
+    let edges = unique_edges(triangles);
+    let csr = Csr::from_edges(point_count, &edges);
+    let dist = graph_distances(points_flat, &csr, focus_vertices);
+
+    let max_dist = dist
+        .iter()
+        .copied()
+        .filter(|d| d.is_finite())
+        .fold(0.0, f64::max);
+    let scale = if max_dist > 0.0 { 1.0 / max_dist } else { 0.0 };
+
+    Ok(edges
+        .iter()
+        .map(|&(a, b)| {
+            let d = dist[a as usize].min(dist[b as usize]);
+            if d.is_finite() {
+                (-falloff * d * scale).exp() as f32
+            } else {
+                0.0
+            }
+        })
+        .collect())
+}
+
+/// What `edge_centrality_weights` favours
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeWeightMode {
+    /// Proportional to edge length
+    Length = 0,
+    /// Higher for edges whose midpoint is near the point centroid
+    Centrality = 1,
+    /// Product of the two
+    LengthCentrality = 2,
+}
+
+/// Positive sampling weight per canonical edge, ready for `sample_edges`
+///
+/// Lengths are divided by the longest edge. Centrality is
+/// `1 / (1 + d / d_mean)` for midpoint distance `d` to the centroid of
+/// all points, so it stays positive even for the outermost edges.
+#[wasm_bindgen]
+pub fn edge_centrality_weights(
+    points_flat: &[f64],
+    triangles: &[u32],
+    mode: EdgeWeightMode,
+) -> Result<Vec<f32>> {
+    let point_count = check_points(points_flat)?;
+    check_triangles(triangles, point_count)?;
+    let edges = unique_edges(triangles);
+    if edges.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let lengths: Vec<f64> = edges.iter().map(|&(a, b)| edge_length(points_flat, a, b)).collect();
+    let max_len = lengths.iter().copied().fold(0.0, f64::max);
+    let (cx, cy) = points_flat
+        .chunks_exact(2)
+        .fold((0.0, 0.0), |(x, y), c| (x + c[0], y + c[1]));
+    let (cx, cy) = (cx / point_count as f64, cy / point_count as f64);
+    let dist: Vec<f64> = edges
+        .iter()
+        .map(|&(a, b)| {
+            let (a, b) = (a as usize * 2, b as usize * 2);
+            let mx = (points_flat[a] + points_flat[b]) * 0.5;
+            let my = (points_flat[a + 1] + points_flat[b + 1]) * 0.5;
+            (mx - cx).hypot(my - cy)
+        })
+        .collect();
+    let mean = dist.iter().sum::<f64>() / dist.len() as f64;
+
+    Ok(lengths
+        .iter()
+        .zip(&dist)
+        .map(|(&len, &d)| {
+            let length = if max_len > 0.0 { len / max_len } else { 1.0 };
+            let central = if mean > 0.0 { 1.0 / (1.0 + d / mean) } else { 1.0 };
+            (match mode {
+                EdgeWeightMode::Length => length,
+                EdgeWeightMode::Centrality => central,
+                EdgeWeightMode::LengthCentrality => length * central,
+            }) as f32
+        })
+        .collect())
+}
+
+/// Checked points and an iterator over `edge_indices` as endpoint pairs
+fn edge_ends<'a>(
+    points_flat: &'a [f64],
+    edge_indices: &'a [u32],
+) -> Result<impl Iterator<Item = ((f64, f64), (f64, f64))> + 'a> {
+    let point_count = check_points(points_flat)?;
+    check_edge_indices(edge_indices, point_count)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let point = |i: u32| (points_flat[i as usize * 2], points_flat[i as usize * 2 + 1]);
+    Ok(edge_indices.chunks_exact(2).map(move |e| (point(e[0]), point(e[1]))))
+}
+
+/// `[length, midpoint_x, midpoint_y, angle]` per edge of `edge_indices`
+///
+/// `angle` is the direction in `[0, π)`, the same whichever way round
+/// the edge is listed; a zero-length edge gets 0.
+#[wasm_bindgen]
+pub fn edge_metrics(points_flat: &[f64], edge_indices: &[u32]) -> Result<Vec<f32>> {
+    let mut out = Vec::with_capacity(edge_indices.len() * 2);
+    for ((x0, y0), (x1, y1)) in edge_ends(points_flat, edge_indices)? {
+        let (dx, dy) = (x1 - x0, y1 - y0);
+        // `rem_euclid` can round up to exactly π for a tiny negative angle
+        let angle = dy.atan2(dx).rem_euclid(std::f64::consts::PI) as f32;
+        let angle = if angle >= std::f32::consts::PI { 0.0 } else { angle };
+        let mid = ((x0 + x1) * 0.5, (y0 + y1) * 0.5);
+        out.extend([dx.hypot(dy) as f32, mid.0 as f32, mid.1 as f32, angle]);
+    }
+    Ok(out)
+}
+
+/// Length bucket per edge of `edge_indices`, for one draw call per bucket
+///
+/// `thresholds` must be finite and ascending; an edge shorter than
+/// `thresholds[0]` is bucket 0, one at least `thresholds[k - 1]` but
+/// shorter than `thresholds[k]` is bucket `k`, and one at least the last
+/// threshold is bucket `thresholds.len()`.
+#[wasm_bindgen]
+pub fn bucket_edges_by_length(
+    points_flat: &[f64],
+    edge_indices: &[u32],
+    thresholds: &[f32],
+) -> Result<Vec<u32>> {
+    if thresholds.iter().any(|t| !t.is_finite()) {
+        return Err(Error::NonFiniteInput("thresholds must be finite".into()));
+    }
+    if thresholds.windows(2).any(|w| w[0] > w[1]) {
+        return Err(Error::InvalidArgument("thresholds must be ascending".into()));
+    }
+    Ok(edge_ends(points_flat, edge_indices)?
+        .map(|((x0, y0), (x1, y1))| {
+            let length = (x1 - x0).hypot(y1 - y0) as f32;
+            thresholds.partition_point(|&t| t <= length) as u32
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::{compute_delaunay, generate_golden_seeds};
+
+    #[test]
+    fn test_edge_lod_peaks_at_focus_and_decays() {
+        let points = generate_golden_seeds(800.0, 600.0, 120);
+        let triangles = compute_delaunay(&points, 800.0, 600.0);
+        let lod = compute_edge_lod(&points, &triangles, &[0], 3.0).unwrap();
+        let edges = unique_edges(&triangles);
+        assert_eq!(lod.len(), edges.len());
+
+        for (w, &(a, b)) in lod.iter().zip(&edges) {
+            assert!((0.0..=1.0).contains(w));
+            if a == 0 || b == 0 {
+                assert_eq!(*w, 1.0);
+            }
+        }
+        // The spiral starts at the center, so outer edges are fainter
+        let outer = edges.iter().position(|&(a, _)| a > 100).unwrap();
+        assert!(lod[outer] < 0.5);
+    }
+
+    #[test]
+    fn test_edge_lod_multi_focus_takes_minimum() {
+        // Path graph 0 - 1 - 2 - 3 - 4 along a line, split into triangles
+        let points = [0.0, 0.0, 1.0, 0.0, 2.0, 0.0, 3.0, 0.0, 4.0, 0.0, 2.0, 5.0];
+        let triangles = [0, 1, 5, 1, 2, 5, 2, 3, 5, 3, 4, 5];
+        let single = compute_edge_lod(&points, &triangles, &[0], 1.0).unwrap();
+        let both = compute_edge_lod(&points, &triangles, &[0, 4], 1.0).unwrap();
+        let edges = unique_edges(&triangles);
+        let e34 = edges.iter().position(|&e| e == (3, 4)).unwrap();
+        assert!(single[e34] < 1.0);
+        assert_eq!(both[e34], 1.0);
+        for (s, b) in single.iter().zip(&both) {
+            assert!(b >= s);
+        }
+    }
+
+    #[test]
+    fn test_edge_lod_disconnected_component_is_zero() {
+        let points = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 10.0, 10.0, 11.0, 10.0, 10.0, 11.0];
+        let triangles = [0, 1, 2, 3, 4, 5];
+        let lod = compute_edge_lod(&points, &triangles, &[0], 2.0).unwrap();
+        // Edges (3,4), (3,5), (4,5) sort last
+        assert_eq!(&lod[3..], &[0.0, 0.0, 0.0]);
+        assert_eq!(
+            code_of(compute_edge_lod(&points, &triangles, &[9], 2.0)),
+            ErrorCode::IndexOutOfRange
+        );
+        assert_eq!(
+            code_of(compute_edge_lod(&points, &triangles, &[], 2.0)),
+            ErrorCode::InvalidLength
+        );
+    }
+
+    #[test]
+    fn test_centrality_weights_favour_center_and_length() {
+        let points = generate_golden_seeds(600.0, 600.0, 200);
+        let triangles = compute_delaunay(&points, 600.0, 600.0);
+        let edges = unique_edges(&triangles);
+        let central = edge_centrality_weights(&points, &triangles, EdgeWeightMode::Centrality).unwrap();
+        let length = edge_centrality_weights(&points, &triangles, EdgeWeightMode::Length).unwrap();
+        assert_eq!(central.len(), edges.len());
+        assert!(central.iter().chain(&length).all(|&w| w > 0.0 && w <= 1.0));
+        assert_eq!(length.iter().copied().fold(0.0, f32::max), 1.0);
+        // Seed 0 sits at the spiral center
+        let inner = edges.iter().position(|&(a, _)| a == 0).unwrap();
+        let outer = edges.iter().position(|&(a, _)| a > 190).unwrap();
+        assert!(central[inner] > central[outer]);
+
+        let both = edge_centrality_weights(&points, &triangles, EdgeWeightMode::LengthCentrality).unwrap();
+        let picks = crate::sampling::sample_edges(&both, 5, 9, true).unwrap();
+        assert!(picks.iter().all(|&i| (i as usize) < edges.len()));
+    }
+
+    #[test]
+    fn test_edge_metrics_and_length_buckets() {
+        let points = [0.0, 0.0, 4.0, 0.0, 4.0, 3.0, 0.0, 3.0];
+        // Right, up, back down-left along the diagonal, and a repeated point
+        let edges = [0, 1, 1, 2, 2, 0, 3, 3];
+        let metrics = edge_metrics(&points, &edges).unwrap();
+        let diagonal = 0.75f32.atan();
+        let expected = [
+            [4.0, 2.0, 0.0, 0.0],
+            [3.0, 4.0, 1.5, std::f32::consts::FRAC_PI_2],
+            [5.0, 2.0, 1.5, diagonal],
+            [0.0, 0.0, 3.0, 0.0],
+        ];
+        for (m, e) in metrics.chunks(4).zip(expected) {
+            assert!(m.iter().zip(e).all(|(a, b)| (a - b).abs() < 1e-6), "{m:?} != {e:?}");
+        }
+        let reversed = edge_metrics(&points, &[2, 0]).unwrap();
+        assert_eq!(reversed, edge_metrics(&points, &[0, 2]).unwrap());
+
+        let buckets = bucket_edges_by_length(&points, &edges, &[3.0, 4.5]).unwrap();
+        assert_eq!(buckets, vec![1, 1, 2, 0]);
+        assert_eq!(bucket_edges_by_length(&points, &edges, &[]).unwrap(), vec![0; 4]);
+
+        // On a mesh everything lines up with `compute_edge_indices`
+        let points = generate_golden_seeds(500.0, 400.0, 100);
+        let indices = crate::compute_edge_indices(&compute_delaunay(&points, 500.0, 400.0));
+        let metrics = edge_metrics(&points, &indices).unwrap();
+        let buckets = bucket_edges_by_length(&points, &indices, &[20.0, 40.0]).unwrap();
+        assert_eq!((metrics.len(), buckets.len()), (indices.len() * 2, indices.len() / 2));
+        for ((m, e), &bucket) in metrics.chunks(4).zip(indices.chunks(2)).zip(&buckets) {
+            let len = edge_length(&points, e[0], e[1]) as f32;
+            assert_eq!(m[0], len);
+            assert!((0.0..std::f32::consts::PI).contains(&m[3]));
+            assert_eq!(bucket, (len >= 20.0) as u32 + (len >= 40.0) as u32);
+        }
+
+        let code = |e: &[u32], t: &[f32]| code_of(bucket_edges_by_length(&points, e, t));
+        assert_eq!(code(&[0, 1, 2], &[]), ErrorCode::InvalidLength);
+        assert_eq!(code(&[0, 100], &[]), ErrorCode::IndexOutOfRange);
+        assert_eq!(code(&[0, 1], &[2.0, 1.0]), ErrorCode::InvalidArgument);
+        assert_eq!(code(&[0, 1], &[f32::NAN]), ErrorCode::NonFiniteInput);
+        let nan = edge_metrics(&[0.0, f64::NAN], &[0, 0]);
+        assert_eq!(code_of(nan), ErrorCode::NonFiniteInput);
+    }
+}
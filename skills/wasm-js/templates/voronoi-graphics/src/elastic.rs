@@ -0,0 +1,334 @@
+//! Spring-mesh deformation for dragging seeds
+//!
+//! Every Delaunay edge becomes a linear spring whose rest length is the
+//! edge length at construction. Vertices have unit mass and are advanced
+//! with symplectic Euler; damping is applied implicitly, so it can never
+//! add energy. Each `step` is split into substeps small enough for the
+//! stiffest vertex, which keeps the integration stable regardless of
+//! frame rate; a vertex with so many springs that this would take more
+//! than `MAX_SUBSTEPS` makes `step` error instead.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{boundary_edges, check_points, check_triangles, unique_edges, Csr};
+use crate::Point;
+
+/// Upper bound accepted for `stiffness`, per second squared
+pub const ELASTIC_MAX_STIFFNESS: f64 = 10_000.0;
+/// Upper bound accepted for `damping`, per second
+pub const ELASTIC_MAX_DAMPING: f64 = 100.0;
+/// Upper bound accepted for a single `dt`, in seconds
+pub const ELASTIC_MAX_DT: f64 = 0.1;
+
+/// Most substeps one `step` takes, which bounds its cost
+const MAX_SUBSTEPS: usize = 256;
+
+/// Delaunay edges as springs around user-dragged vertices
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ElasticMesh {
+    positions: Vec<Point>,
+    velocities: Vec<Point>,
+    /// `(i, j, rest_length)` per spring
+    springs: Vec<(u32, u32, f64)>,
+    max_degree: usize,
+    /// Pointer target while a vertex is dragged
+    drag: Vec<Option<Point>>,
+    /// Vertices held in place regardless of drags, e.g. the hull
+    fixed: Vec<bool>,
+}
+
+#[wasm_bindgen]
+impl ElasticMesh {
+    /// Springs along every edge of `triangles`, at rest in the given layout
+    #[wasm_bindgen(constructor)]
+    pub fn new(points_flat: &[f64], triangles: &[u32]) -> Result<ElasticMesh> {
+        let n = check_points(points_flat)?;
+        check_triangles(triangles, n)?;
+        if points_flat.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+        }
+        let positions: Vec<Point> = points_flat
+            .chunks_exact(2)
+            .map(|c| Point { x: c[0], y: c[1] })
+            .collect();
+        let edges = unique_edges(triangles);
+        let csr = Csr::from_edges(n, &edges);
+        let max_degree = (0..n as u32).map(|v| csr.neighbors(v).len()).max().unwrap_or(0);
+        let springs = edges
+            .iter()
+            .map(|&(a, b)| {
+                let (p, q) = (positions[a as usize], positions[b as usize]);
+                (a, b, (q.x - p.x).hypot(q.y - p.y))
+            })
+            .collect();
+        Ok(ElasticMesh {
+            velocities: vec![Point { x: 0.0, y: 0.0 }; n],
+            positions,
+            springs,
+            max_degree,
+            drag: vec![None; n],
+            fixed: vec![false; n],
+        })
+    }
+
+    /// Hold every vertex on the mesh boundary at its current position so
+    /// the silhouette cannot move; `false` frees them again
+    pub fn pin_hull(&mut self, pinned: bool, triangles: &[u32]) -> Result<()> {
+        check_triangles(triangles, self.positions.len())?;
+        for (a, b) in boundary_edges(triangles) {
+            self.fixed[a as usize] = pinned;
+            self.fixed[b as usize] = pinned;
+        }
+        if pinned {
+            for (v, f) in self.velocities.iter_mut().zip(&self.fixed) {
+                if *f {
+                    *v = Point { x: 0.0, y: 0.0 };
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Pin `vertex` to the pointer at `(x, y)` until `release`
+    pub fn drag(&mut self, vertex: u32, x: f64, y: f64) -> Result<()> {
+        self.check_vertex(vertex)?;
+        if !x.is_finite() || !y.is_finite() {
+            return Err(Error::NonFiniteInput("drag target must be finite".into()));
+        }
+        self.drag[vertex as usize] = Some(Point { x, y });
+        Ok(())
+    }
+
+    /// Let a dragged vertex spring back
+    pub fn release(&mut self, vertex: u32) -> Result<()> {
+        self.check_vertex(vertex)?;
+        self.drag[vertex as usize] = None;
+        Ok(())
+    }
+
+    /// Advance by `dt` seconds
+    ///
+    /// Accepted ranges: `dt` in `[0, 0.1]`, `stiffness` in `[0, 10000]`
+    /// per s², `damping` in `[0, 100]` per s. Within them the step is
+    /// stable: it takes `ceil(dt·sqrt(2·stiffness·degree))` substeps,
+    /// degree being the most springs on one vertex, and errors instead
+    /// when that is over 256. At the top stiffness and `dt` that allows
+    /// 327 springs, far more than a Delaunay vertex has. Around 200
+    /// stiffness and 10 damping gives a soft, quickly settling wobble at
+    /// 60 fps.
+    pub fn step(&mut self, dt: f64, stiffness: f64, damping: f64) -> Result<()> {
+        let in_range = |v: f64, max: f64| v.is_finite() && (0.0..=max).contains(&v);
+        if !in_range(dt, ELASTIC_MAX_DT)
+            || !in_range(stiffness, ELASTIC_MAX_STIFFNESS)
+            || !in_range(damping, ELASTIC_MAX_DAMPING)
+        {
+            return Err(Error::InvalidArgument(format!(
+                "step(dt={dt}, stiffness={stiffness}, damping={damping}) outside the supported range"
+            )));
+        }
+        // Highest mode of the spring Laplacian is at most 2·k·degree;
+        // substeps of h·ω ≤ 1 stay well inside symplectic Euler's h·ω < 2
+        let omega = (2.0 * stiffness * self.max_degree as f64).sqrt();
+        let needed = (dt * omega).ceil();
+        if needed > MAX_SUBSTEPS as f64 {
+            return Err(Error::InvalidArgument(format!(
+                "step(dt={dt}, stiffness={stiffness}) needs {needed} substeps for a vertex with \
+                 {} springs, more than the {MAX_SUBSTEPS} allowed",
+                self.max_degree
+            )));
+        }
+        let substeps = (needed as usize).max(1);
+        let h = dt / substeps as f64;
+        let keep = 1.0 / (1.0 + damping * h);
+
+        let mut force = vec![Point { x: 0.0, y: 0.0 }; self.positions.len()];
+        for _ in 0..substeps {
+            for (i, target) in self.drag.iter().enumerate() {
+                if let Some(t) = target {
+                    self.positions[i] = *t;
+                }
+            }
+            force.iter_mut().for_each(|f| *f = Point { x: 0.0, y: 0.0 });
+            for &(a, b, rest) in &self.springs {
+                let (p, q) = (self.positions[a as usize], self.positions[b as usize]);
+                let (dx, dy) = (q.x - p.x, q.y - p.y);
+                let len = dx.hypot(dy);
+                if len <= f64::EPSILON {
+                    continue;
+                }
+                let s = stiffness * (len - rest) / len;
+                force[a as usize].x += s * dx;
+                force[a as usize].y += s * dy;
+                force[b as usize].x -= s * dx;
+                force[b as usize].y -= s * dy;
+            }
+            for (i, f) in force.iter().enumerate() {
+                if self.fixed[i] || self.drag[i].is_some() {
+                    self.velocities[i] = Point { x: 0.0, y: 0.0 };
+                    continue;
+                }
+                let v = &mut self.velocities[i];
+                v.x = (v.x + h * f.x) * keep;
+                v.y = (v.y + h * f.y) * keep;
+                self.positions[i].x += h * v.x;
+                self.positions[i].y += h * v.y;
+            }
+        }
+        Ok(())
+    }
+
+    /// Current positions `[x, y, ...]`, in input order
+    pub fn positions(&self) -> Vec<f64> {
+        self.positions.iter().flat_map(|p| [p.x, p.y]).collect()
+    }
+
+    /// Total kinetic energy (unit masses), useful to detect settling
+    pub fn kinetic_energy(&self) -> f64 {
+        self.velocities.iter().map(|v| 0.5 * (v.x * v.x + v.y * v.y)).sum()
+    }
+
+    pub fn spring_count(&self) -> usize {
+        self.springs.len()
+    }
+}
+
+impl ElasticMesh {
+    fn check_vertex(&self, vertex: u32) -> Result<()> {
+        if vertex as usize >= self.positions.len() {
+            return Err(Error::IndexOutOfRange(format!(
+                "vertex {vertex} out of range for {} points",
+                self.positions.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::dynamic::DynamicTriangulation;
+    use crate::{compute_delaunay, generate_golden_seeds};
+
+    fn mesh(n: usize) -> (Vec<f64>, Vec<u32>, ElasticMesh) {
+        let points = generate_golden_seeds(600.0, 600.0, n);
+        let triangles = compute_delaunay(&points, 600.0, 600.0);
+        let elastic = ElasticMesh::new(&points, &triangles).unwrap();
+        (points, triangles, elastic)
+    }
+
+    #[test]
+    fn test_drag_pulls_neighbors_and_release_settles_back() {
+        let (points, _, mut m) = mesh(150);
+        let v = 40u32;
+        let (x, y) = (points[80] + 25.0, points[81] - 15.0);
+        m.drag(v, x, y).unwrap();
+        for _ in 0..120 {
+            m.step(1.0 / 60.0, 200.0, 10.0).unwrap();
+        }
+        let dragged = m.positions();
+        assert_eq!((dragged[80], dragged[81]), (x, y));
+        let moved = dragged.iter().zip(&points).filter(|(a, b)| (*a - *b).abs() > 0.5).count();
+        assert!(moved > 4, "neighbors should follow the drag");
+
+        m.release(v).unwrap();
+        for _ in 0..600 {
+            m.step(1.0 / 60.0, 200.0, 10.0).unwrap();
+        }
+        let back = m.positions();
+        // Unpinned the rest lengths are all satisfied by the original
+        // layout up to a rigid motion, which the springs cannot remove
+        let err: f64 = m
+            .springs
+            .iter()
+            .map(|&(a, b, rest)| {
+                let (a, b) = (a as usize * 2, b as usize * 2);
+                ((back[b] - back[a]).hypot(back[b + 1] - back[a + 1]) - rest).abs()
+            })
+            .fold(0.0, f64::max);
+        assert!(err < 0.05, "max spring error {err}");
+        assert!(m.kinetic_energy() < 1e-3);
+    }
+
+    #[test]
+    fn test_stable_across_documented_range() {
+        for &(k, c) in &[(ELASTIC_MAX_STIFFNESS, 0.0), (ELASTIC_MAX_STIFFNESS, 100.0), (1.0, 0.0)] {
+            for &dt in &[1.0 / 144.0, 1.0 / 60.0, ELASTIC_MAX_DT] {
+                let (points, _, mut m) = mesh(80);
+                m.drag(0, points[0] + 30.0, points[1]).unwrap();
+                for _ in 0..200 {
+                    m.step(dt, k, c).unwrap();
+                }
+                let bound = 600.0 * 4.0;
+                assert!(
+                    m.positions().iter().all(|p| p.is_finite() && p.abs() < bound),
+                    "blew up at k={k} c={c} dt={dt}"
+                );
+            }
+        }
+        let (_, _, mut m) = mesh(20);
+        assert_eq!(code_of(m.step(0.5, 10.0, 1.0)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(m.step(0.01, -1.0, 1.0)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(m.step(0.01, 1.0, f64::NAN)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(m.drag(99, 0.0, 0.0)), ErrorCode::IndexOutOfRange);
+    }
+
+    #[test]
+    fn test_step_refuses_vertices_too_stiff_to_substep() {
+        // A hub joined to every point of a fan around it
+        let fan = |n: u32| {
+            let mut points = vec![0.0, 0.0];
+            points.extend((0..n).flat_map(|i| {
+                let a = i as f64 / n as f64 * std::f64::consts::PI;
+                [100.0 * a.cos(), 100.0 * a.sin()]
+            }));
+            let triangles: Vec<u32> = (1..n).flat_map(|i| [0, i, i + 1]).collect();
+            ElasticMesh::new(&points, &triangles).unwrap()
+        };
+        let (dt, k) = (ELASTIC_MAX_DT, ELASTIC_MAX_STIFFNESS);
+        let mut m = fan(327);
+        m.drag(0, 5.0, 5.0).unwrap();
+        for _ in 0..20 {
+            m.step(dt, k, 0.0).unwrap();
+        }
+        assert!(m.positions().iter().all(|p| p.is_finite() && p.abs() < 400.0));
+        let mut m = fan(328);
+        assert_eq!(code_of(m.step(dt, k, 0.0)), ErrorCode::InvalidArgument);
+        m.step(dt, k / 2.0, 0.0).unwrap();
+    }
+
+    #[test]
+    fn test_pinned_hull_stays_put() {
+        let (points, triangles, mut m) = mesh(100);
+        m.pin_hull(true, &triangles).unwrap();
+        m.drag(0, points[0] + 40.0, points[1] + 40.0).unwrap();
+        for _ in 0..120 {
+            m.step(1.0 / 60.0, 300.0, 8.0).unwrap();
+        }
+        let now = m.positions();
+        for (a, b) in boundary_edges(&triangles) {
+            for v in [a as usize, b as usize] {
+                assert_eq!(now[v * 2], points[v * 2]);
+                assert_eq!(now[v * 2 + 1], points[v * 2 + 1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_drag_keeps_topology() {
+        let (points, _, mut m) = mesh(200);
+        let mut tri = DynamicTriangulation::new(&points, 600.0, 600.0).unwrap();
+        m.drag(100, points[200] + 3.0, points[201] + 2.0).unwrap();
+        let mut flips = 0;
+        for _ in 0..60 {
+            m.step(1.0 / 60.0, 200.0, 10.0).unwrap();
+            tri.update_points(&m.positions()).unwrap();
+            assert!(!tri.last_update_rebuilt());
+            flips += tri.last_flip_count();
+        }
+        assert!(flips <= 4, "small drag caused {flips} flips");
+    }
+}
@@ -0,0 +1,198 @@
+//! Per-edge energy that flows out from source vertices, for glowing edges
+//!
+//! Every frame each vertex takes the mean energy of its edges, or full
+//! energy at a source, and each edge then moves towards the mean of its
+//! two ends. The move is the fraction `1 - e^(-speed × dt)` of the gap and
+//! the decay a factor `e^(-decay × dt)`, both exact for the step length,
+//! so a long frame goes further rather than overshooting: every new value
+//! is a blend of old ones and 1, scaled down, whatever `dt` is.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+
+fn check_rate(name: &str, v: f64) -> Result<()> {
+    if !v.is_finite() || v < 0.0 {
+        return Err(Error::InvalidArgument(format!(
+            "{name} must be finite and non-negative, got {v}"
+        )));
+    }
+    Ok(())
+}
+
+/// Vertex to edge incidence of an edge list, for `update` every frame
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct EdgeEnergy {
+    edges: Vec<u32>,
+    /// Edges at vertex `v` are `incident[offsets[v]..offsets[v + 1]]`
+    offsets: Vec<u32>,
+    incident: Vec<u32>,
+    /// Per-vertex energy, kept between frames to save the allocation
+    level: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl EdgeEnergy {
+    /// Index `[a0, b0, a1, b1, ...]`; vertices are numbered up to the
+    /// largest index used
+    #[wasm_bindgen(constructor)]
+    pub fn new(edge_indices: &[u32]) -> Result<EdgeEnergy> {
+        if !edge_indices.len().is_multiple_of(2) {
+            return Err(Error::InvalidLength(format!(
+                "edge index count {} is odd",
+                edge_indices.len()
+            )));
+        }
+        let n = edge_indices.iter().max().map_or(0, |&v| v as usize + 1);
+        let mut offsets = vec![0u32; n + 1];
+        for &v in edge_indices {
+            offsets[v as usize + 1] += 1;
+        }
+        for i in 0..n {
+            offsets[i + 1] += offsets[i];
+        }
+        let mut fill = offsets.clone();
+        let mut incident = vec![0u32; edge_indices.len()];
+        for (slot, &v) in edge_indices.iter().enumerate() {
+            incident[fill[v as usize] as usize] = (slot / 2) as u32;
+            fill[v as usize] += 1;
+        }
+        Ok(EdgeEnergy { edges: edge_indices.to_vec(), offsets, incident, level: vec![0.0; n] })
+    }
+
+    /// Advance `energy`, one value per edge in edge order, by `dt` seconds
+    ///
+    /// `source_vertices` inject full energy, 1, this frame; a source no
+    /// edge touches injects nothing. `propagation_speed` is the rate per
+    /// second at which an edge closes on its ends' energy, and `decay` the
+    /// rate at which everything fades. Values stay within the range of
+    /// their previous values and 1, and never grow past the larger of 1
+    /// and the initial maximum.
+    pub fn update(
+        &mut self,
+        energy: &mut [f32],
+        source_vertices: &[u32],
+        dt: f64,
+        propagation_speed: f64,
+        decay: f64,
+    ) -> Result<()> {
+        if energy.len() != self.edge_count() {
+            return Err(Error::InvalidLength(format!(
+                "{} energy values for {} edges",
+                energy.len(),
+                self.edge_count()
+            )));
+        }
+        if energy.iter().any(|e| !e.is_finite()) {
+            return Err(Error::NonFiniteInput("energy must be finite".into()));
+        }
+        check_rate("dt", dt)?;
+        check_rate("propagation speed", propagation_speed)?;
+        check_rate("decay", decay)?;
+
+        for (v, level) in self.level.iter_mut().enumerate() {
+            let edges = &self.incident[self.offsets[v] as usize..self.offsets[v + 1] as usize];
+            let sum: f64 = edges.iter().map(|&e| energy[e as usize] as f64).sum();
+            *level = if edges.is_empty() { 0.0 } else { sum / edges.len() as f64 };
+        }
+        for &s in source_vertices {
+            if let Some(level) = self.level.get_mut(s as usize) {
+                *level = 1.0;
+            }
+        }
+        let blend = 1.0 - (-propagation_speed * dt).exp();
+        let fade = (-decay * dt).exp();
+        for (e, ends) in energy.iter_mut().zip(self.edges.chunks_exact(2)) {
+            let target = (self.level[ends[0] as usize] + self.level[ends[1] as usize]) / 2.0;
+            let current = *e as f64;
+            *e = ((current + blend * (target - current)) * fade) as f32;
+        }
+        Ok(())
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len() / 2
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.level.len()
+    }
+}
+
+/// `EdgeEnergy::update` for a single frame, indexing `edge_indices` anew;
+/// keep an `EdgeEnergy` to index it once instead
+#[wasm_bindgen]
+pub fn update_edge_energy(
+    energy: &mut [f32],
+    edge_indices: &[u32],
+    source_vertices: &[u32],
+    dt: f64,
+    propagation_speed: f64,
+    decay: f64,
+) -> Result<()> {
+    EdgeEnergy::new(edge_indices)?.update(energy, source_vertices, dt, propagation_speed, decay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+    use crate::{compute_delaunay, compute_edge_indices};
+
+    #[test]
+    fn test_energy_flows_outward_and_fades() {
+        // A chain 0 - 1 - ... - 10 fed at vertex 0
+        let edges: Vec<u32> = (0..10).flat_map(|v| [v, v + 1]).collect();
+        let mut graph = EdgeEnergy::new(&edges).unwrap();
+        assert_eq!((graph.edge_count(), graph.vertex_count()), (10, 11));
+        let mut energy = vec![0.0f32; 10];
+        for _ in 0..60 {
+            graph.update(&mut energy, &[0], 1.0 / 60.0, 8.0, 0.5).unwrap();
+        }
+        assert!(energy.windows(2).all(|w| w[0] > w[1]), "{energy:?}");
+        assert!(energy[0] < 1.0 && energy[9] > 0.0);
+
+        // Without sources it all fades
+        let lit = energy.clone();
+        graph.update(&mut energy, &[], 0.1, 0.0, 2.0).unwrap();
+        for (e, l) in energy.iter().zip(&lit) {
+            assert!((e - l * (-0.2f32).exp()).abs() < 1e-6);
+        }
+        let mut once = lit.clone();
+        update_edge_energy(&mut once, &edges, &[0, 99], 0.05, 3.0, 1.0).unwrap();
+        let mut cached = lit.clone();
+        graph.update(&mut cached, &[0], 0.05, 3.0, 1.0).unwrap();
+        assert_eq!(once, cached);
+    }
+
+    #[test]
+    fn test_long_frames_stay_bounded() {
+        let mut rng = Rng::new(4);
+        let points: Vec<f64> =
+            (0..300).flat_map(|_| [rng.range(0.0, 300.0), rng.range(0.0, 300.0)]).collect();
+        let edges = compute_edge_indices(&compute_delaunay(&points, 300.0, 300.0));
+        let mut graph = EdgeEnergy::new(&edges).unwrap();
+        let mut energy: Vec<f32> = (0..edges.len() / 2).map(|_| rng.next_f64() as f32).collect();
+        for frame in 0..200 {
+            let sources = [frame % 300, (frame * 7) % 300];
+            for (speed, decay) in [(1e6, 0.0), (50.0, 1e6), (0.0, 0.0), (20.0, 0.3)] {
+                graph.update(&mut energy, &sources, 0.1, speed, decay).unwrap();
+                assert!(energy.iter().all(|e| (0.0..=1.0).contains(e)));
+            }
+        }
+
+        let (mut three, mut bad) = (vec![0.0f32; 3], vec![f32::NAN; edges.len() / 2]);
+        assert_eq!(code_of(graph.update(&mut three, &[], 0.1, 1.0, 1.0)), ErrorCode::InvalidLength);
+        assert_eq!(code_of(graph.update(&mut bad, &[], 0.1, 1.0, 1.0)), ErrorCode::NonFiniteInput);
+        let mut energy = vec![0.0f32; edges.len() / 2];
+        for (dt, speed, decay) in [(-0.1, 1.0, 1.0), (0.1, f64::NAN, 1.0), (0.1, 1.0, -1.0)] {
+            let result = graph.update(&mut energy, &[], dt, speed, decay);
+            assert_eq!(code_of(result), ErrorCode::InvalidArgument);
+        }
+        assert_eq!(code_of(EdgeEnergy::new(&[0, 1, 2])), ErrorCode::InvalidLength);
+        let mut none: [f32; 0] = [];
+        update_edge_energy(&mut none, &[], &[3], 0.1, 1.0, 1.0).unwrap();
+    }
+}
@@ -0,0 +1,161 @@
+//! Error type shared by fallible exports
+//!
+//! Rust callers get a plain enum; across the WASM boundary it is thrown
+//! as a JS `Error` carrying the human-readable message plus a numeric
+//! `code` property holding the [`ErrorCode`] discriminant:
+//!
+//! ```js
+//! try { compute_voronoi(points, w, h) } catch (e) {
+//!   if (e.code === ErrorCode.NonFiniteInput) { ... }
+//! }
+//! ```
+//!
+//! Codes are part of the stable API: a variant keeps its number forever
+//! and new variants only ever take new numbers.
+
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+/// Machine-readable error category, exposed to JS as `e.code`
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    InvalidLength = 1,
+    IndexOutOfRange = 2,
+    NonFiniteInput = 3,
+    InvalidArgument = 4,
+    DegenerateGeometry = 5,
+    TooLarge = 6,
+    Cancelled = 7,
+    VersionMismatch = 8,
+    InvalidData = 9,
+}
+
+/// Errors returned by fallible functions and constructors
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// A flat input array has the wrong length for its stride or partner array
+    InvalidLength(String),
+    /// An index refers past the end of the array it addresses
+    IndexOutOfRange(String),
+    /// A coordinate, weight or parameter is NaN or infinite
+    NonFiniteInput(String),
+    /// An argument is outside its documented domain
+    InvalidArgument(String),
+    /// The geometry has no well-defined answer (zero area, a pole, ...)
+    DegenerateGeometry(String),
+    /// The input exceeds a documented size limit
+    TooLarge(String),
+    /// The operation was cancelled before it finished
+    Cancelled(String),
+    /// Serialized bytes come from an unsupported format version
+    VersionMismatch(String),
+    /// Serialized bytes are truncated or corrupt
+    InvalidData(String),
+}
+
+impl Error {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::InvalidLength(_) => ErrorCode::InvalidLength,
+            Error::IndexOutOfRange(_) => ErrorCode::IndexOutOfRange,
+            Error::NonFiniteInput(_) => ErrorCode::NonFiniteInput,
+            Error::InvalidArgument(_) => ErrorCode::InvalidArgument,
+            Error::DegenerateGeometry(_) => ErrorCode::DegenerateGeometry,
+            Error::TooLarge(_) => ErrorCode::TooLarge,
+            Error::Cancelled(_) => ErrorCode::Cancelled,
+            Error::VersionMismatch(_) => ErrorCode::VersionMismatch,
+            Error::InvalidData(_) => ErrorCode::InvalidData,
+        }
+    }
+
+    /// The message without the category prefix
+    pub fn message(&self) -> &str {
+        match self {
+            Error::InvalidLength(msg)
+            | Error::IndexOutOfRange(msg)
+            | Error::NonFiniteInput(msg)
+            | Error::InvalidArgument(msg)
+            | Error::DegenerateGeometry(msg)
+            | Error::TooLarge(msg)
+            | Error::Cancelled(msg)
+            | Error::VersionMismatch(msg)
+            | Error::InvalidData(msg) => msg,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let category = match self {
+            Error::InvalidLength(_) => "invalid length",
+            Error::IndexOutOfRange(_) => "index out of range",
+            Error::NonFiniteInput(_) => "non-finite input",
+            Error::InvalidArgument(_) => "invalid argument",
+            Error::DegenerateGeometry(_) => "degenerate geometry",
+            Error::TooLarge(_) => "too large",
+            Error::Cancelled(_) => "cancelled",
+            Error::VersionMismatch(_) => "version mismatch",
+            Error::InvalidData(_) => "invalid data",
+        };
+        write!(f, "{category}: {}", self.message())
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Error> for JsValue {
+    fn from(err: Error) -> JsValue {
+        let value: JsValue = JsError::new(&err.to_string()).into();
+        // Only fails if `value` is not an object, which a JS Error always is
+        let _ = js_sys::Reflect::set(&value, &"code".into(), &(err.code() as u32).into());
+        value
+    }
+}
+
+/// The `code` of an error thrown by this module, or 0 for anything else
+///
+/// Equivalent to reading `e.code` directly; provided for callers that
+/// prefer not to depend on the property name.
+#[wasm_bindgen]
+pub fn error_code(err: &JsValue) -> u32 {
+    js_sys::Reflect::get(err, &"code".into())
+        .ok()
+        .and_then(|v| v.as_f64())
+        .map_or(0, |c| c as u32)
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Code of an expected error, for tests pinning failure categories
+#[cfg(test)]
+pub(crate) fn code_of<T>(result: Result<T>) -> ErrorCode {
+    result.err().expect("expected an error").code()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_are_stable() {
+        // Changing any of these numbers breaks JS callers
+        let cases = [
+            (Error::InvalidLength(String::new()), 1),
+            (Error::IndexOutOfRange(String::new()), 2),
+            (Error::NonFiniteInput(String::new()), 3),
+            (Error::InvalidArgument(String::new()), 4),
+            (Error::DegenerateGeometry(String::new()), 5),
+            (Error::TooLarge(String::new()), 6),
+            (Error::Cancelled(String::new()), 7),
+            (Error::VersionMismatch(String::new()), 8),
+            (Error::InvalidData(String::new()), 9),
+        ];
+        for (err, code) in cases {
+            assert_eq!(err.code() as u32, code, "{err:?}");
+        }
+        let err = Error::NonFiniteInput("x is NaN".into());
+        assert_eq!(err.to_string(), "non-finite input: x is NaN");
+        assert_eq!(err.message(), "x is NaN");
+    }
+}
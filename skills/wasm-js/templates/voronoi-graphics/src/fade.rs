@@ -0,0 +1,228 @@
+//! Per-edge ages for fading edges in and out as the topology changes
+//!
+//! `EdgeTracker` follows moving points with a `DynamicTriangulation` and
+//! matches each frame's edges against the previous frame's by their
+//! `(i, j)` pair. Edges are reported in the canonical sorted order, so
+//! the ages line up with `edges()` and with `compute_edges` over the same
+//! triangles.
+
+use wasm_bindgen::prelude::*;
+
+use crate::dynamic::{DynamicTriangulation, EdgeList};
+use crate::error::{Error, Result};
+
+/// Live edges with the time since each appeared, plus recently removed
+/// edges with the time since each went away
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct EdgeTracker {
+    tri: DynamicTriangulation,
+    /// Topology generation `edges` was taken from
+    generation: u32,
+    edges: EdgeList,
+    ages: Vec<f64>,
+    removed: Vec<((u32, u32), f64)>,
+    fade_out: f64,
+}
+
+#[wasm_bindgen]
+impl EdgeTracker {
+    /// Triangulate the initial points, whose edges all start at age 0 so
+    /// the first frames fade the whole mesh in
+    #[wasm_bindgen(constructor)]
+    pub fn new(points_flat: &[f64], width: f64, height: f64) -> Result<EdgeTracker> {
+        let tri = DynamicTriangulation::new(points_flat, width, height)?;
+        let edges = tri.sorted_edges().clone();
+        Ok(EdgeTracker {
+            generation: tri.generation(),
+            ages: vec![0.0; edges.len()],
+            edges,
+            tri,
+            removed: Vec::new(),
+            fade_out: 0.0,
+        })
+    }
+
+    /// Keep removed edges in `removed_edges` for `seconds` after they go
+    /// away; the default, 0, reports none
+    pub fn set_fade_out(&mut self, seconds: f64) -> Result<()> {
+        if !seconds.is_finite() || seconds < 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "fade-out must be finite and non-negative, got {seconds}"
+            )));
+        }
+        self.fade_out = seconds;
+        self.removed.retain(|&(_, age)| age < seconds);
+        Ok(())
+    }
+
+    pub fn fade_out(&self) -> f64 {
+        self.fade_out
+    }
+
+    /// Move every point and age every edge by `dt` seconds
+    ///
+    /// Edges that survive keep counting; new ones start at 0. An edge that
+    /// comes back while still fading out leaves the removed list and
+    /// starts over. The point count must match the constructor's.
+    pub fn update(&mut self, points_flat: &[f64], dt: f64) -> Result<()> {
+        if !dt.is_finite() || dt < 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "dt must be finite and non-negative, got {dt}"
+            )));
+        }
+        self.tri.update_points(points_flat)?;
+        for age in &mut self.ages {
+            *age += dt;
+        }
+        for (_, age) in &mut self.removed {
+            *age += dt;
+        }
+        let fade_out = self.fade_out;
+        self.removed.retain(|&(_, age)| age < fade_out);
+        if self.tri.generation() == self.generation {
+            return Ok(());
+        }
+        self.generation = self.tri.generation();
+
+        // Both lists are sorted, so one merge pass pairs them up
+        let next = self.tri.sorted_edges().clone();
+        let mut ages = Vec::with_capacity(next.len());
+        let mut gone = Vec::new();
+        let mut old = self.edges.iter().zip(&self.ages).peekable();
+        for &edge in &next {
+            while let Some(&(&e, _)) = old.peek() {
+                if e >= edge {
+                    break;
+                }
+                gone.push(e);
+                old.next();
+            }
+            match old.peek() {
+                Some(&(&e, &age)) if e == edge => {
+                    ages.push(age);
+                    old.next();
+                }
+                _ => ages.push(0.0),
+            }
+        }
+        gone.extend(old.map(|(&e, _)| e));
+
+        self.removed.retain(|(e, _)| next.binary_search(e).is_err());
+        if fade_out > 0.0 {
+            self.removed.extend(gone.into_iter().map(|e| (e, 0.0)));
+            self.removed.sort_unstable_by_key(|&(e, _)| e);
+        }
+        self.edges = next;
+        self.ages = ages;
+        Ok(())
+    }
+
+    /// Live edges sorted as `[i0, j0, i1, j1, ...]`
+    pub fn edges(&self) -> Vec<u32> {
+        self.edges.iter().flat_map(|&(a, b)| [a, b]).collect()
+    }
+
+    /// Seconds since each edge in `edges()` appeared
+    pub fn edge_ages(&self) -> Vec<f32> {
+        self.ages.iter().map(|&a| a as f32).collect()
+    }
+
+    /// Endpoints of `edges()` as `[x0, y0, x1, y1, ...]`
+    pub fn edge_positions(&self) -> Vec<f32> {
+        self.tri.edge_positions()
+    }
+
+    /// Edges removed less than `fade_out` seconds ago, sorted as
+    /// `[i0, j0, i1, j1, ...]`; the points are the current ones
+    pub fn removed_edges(&self) -> Vec<u32> {
+        self.removed.iter().flat_map(|&((a, b), _)| [a, b]).collect()
+    }
+
+    /// Seconds since each edge in `removed_edges()` went away
+    pub fn removed_ages(&self) -> Vec<f32> {
+        self.removed.iter().map(|&(_, age)| age as f32).collect()
+    }
+
+    /// Current triangles as `[a, b, c, ...]`, as `DynamicTriangulation`
+    pub fn triangles(&self) -> Vec<u32> {
+        self.tri.triangles()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn removed_count(&self) -> usize {
+        self.removed.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::{compute_edge_indices, generate_golden_seeds};
+
+    #[test]
+    fn test_ages_follow_edges_across_flips() {
+        let base = generate_golden_seeds(400.0, 300.0, 80);
+        let mut tracker = EdgeTracker::new(&base, 400.0, 300.0).unwrap();
+        tracker.set_fade_out(0.5).unwrap();
+        assert!(tracker.edge_ages().iter().all(|&a| a == 0.0));
+        let mut points = base.clone();
+        let pairs = |t: &EdgeTracker| {
+            let edges = t.edges();
+            let ages = t.edge_ages();
+            edges.chunks(2).map(|e| (e[0], e[1])).zip(ages).collect::<Vec<_>>()
+        };
+        let mut previous = pairs(&tracker);
+        let mut changed = false;
+        for frame in 1..=60 {
+            for (k, p) in points.iter_mut().enumerate() {
+                *p += ((k * 7 + frame) as f64 * 0.37).sin() * 1.5;
+            }
+            tracker.update(&points, 0.1).unwrap();
+            assert_eq!(tracker.edges(), compute_edge_indices(&tracker.triangles()));
+            let current = pairs(&tracker);
+            for &(e, age) in &current {
+                match previous.iter().find(|&&(p, _)| p == e) {
+                    Some(&(_, before)) => assert!((age - before - 0.1).abs() < 1e-5),
+                    None => {
+                        assert_eq!(age, 0.0);
+                        changed = true;
+                    }
+                }
+            }
+            // Everything that vanished this frame is fading out from 0
+            let removed = tracker.removed_edges();
+            let removed_ages = tracker.removed_ages();
+            assert_eq!(removed.len(), tracker.removed_count() * 2);
+            for &(e, _) in &previous {
+                if current.iter().all(|&(c, _)| c != e) {
+                    let at = removed.chunks(2).position(|r| (r[0], r[1]) == e).unwrap();
+                    assert_eq!(removed_ages[at], 0.0);
+                }
+            }
+            assert!(removed_ages.iter().all(|&a| a < 0.5));
+            previous = current;
+        }
+        assert!(changed, "the jitter never changed the topology");
+        assert_eq!(tracker.edge_positions().len(), tracker.edge_count() * 4);
+    }
+
+    #[test]
+    fn test_tracker_rejects_bad_input() {
+        let base = generate_golden_seeds(100.0, 100.0, 10);
+        let mut tracker = EdgeTracker::new(&base, 100.0, 100.0).unwrap();
+        assert_eq!(code_of(tracker.update(&base, -1.0)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(tracker.update(&base, f64::NAN)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(tracker.update(&base[..4], 0.1)), ErrorCode::InvalidLength);
+        assert_eq!(code_of(tracker.set_fade_out(-0.1)), ErrorCode::InvalidArgument);
+        // Without a fade-out nothing is reported removed
+        let shuffled: Vec<f64> = base.iter().rev().copied().collect();
+        tracker.update(&shuffled, 0.1).unwrap();
+        assert_eq!(tracker.removed_count(), 0);
+        assert_eq!(tracker.fade_out(), 0.0);
+    }
+}
@@ -0,0 +1,159 @@
+//! Distance-field textures of the edge network, for glow and outlines
+//!
+//! Each edge only touches the texels within `max_distance` of it, since
+//! everything further is clamped anyway, so the field is built by
+//! visiting each edge's padded bounding box in turn and keeping the
+//! smallest squared distance per texel. The cost follows the area the
+//! edges sweep rather than texels times edges.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_edge_indices, check_points};
+use crate::Point;
+
+/// Texels `edges_distance_field` will fill in one call
+const MAX_TEXELS: u64 = 1 << 26;
+
+/// Squared distance from `p` to the segment `a`-`b`
+fn segment_distance2(p: Point, a: Point, b: Point) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 {
+        (((p.x - a.x) * dx + (p.y - a.y) * dy) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (p.x - a.x - t * dx).powi(2) + (p.y - a.y - t * dy).powi(2)
+}
+
+/// Unsigned distance from each texel centre to the nearest edge, as
+/// `min(d, max_distance) / max_distance`
+///
+/// `edge_indices` holds `[i0, j0, i1, j1, ...]` pairs into `points_flat`.
+/// The grid is `grid_w × grid_h` texels spread over a `width × height`
+/// canvas, row-major from the top left, so 0 lies on an edge and 1 at
+/// `max_distance` or beyond; with no edges every texel is 1.
+#[wasm_bindgen]
+pub fn edges_distance_field(
+    points_flat: &[f64],
+    edge_indices: &[u32],
+    grid_w: u32,
+    grid_h: u32,
+    width: f64,
+    height: f64,
+    max_distance: f64,
+) -> Result<Vec<f32>> {
+    let n = check_points(points_flat)?;
+    check_edge_indices(edge_indices, n)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    if grid_w == 0 || grid_h == 0 {
+        return Err(Error::InvalidArgument("grid size must be positive".into()));
+    }
+    if grid_w as u64 * grid_h as u64 > MAX_TEXELS {
+        return Err(Error::TooLarge(format!("a {grid_w}x{grid_h} field is too large")));
+    }
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if !(max_distance.is_finite() && max_distance > 0.0) {
+        return Err(Error::InvalidArgument(format!(
+            "max_distance must be positive, got {max_distance}"
+        )));
+    }
+
+    let (cols, rows) = (grid_w as usize, grid_h as usize);
+    let (sx, sy) = (width / cols as f64, height / rows as f64);
+    let point = |i: u32| {
+        let i = i as usize * 2;
+        Point::new(points_flat[i], points_flat[i + 1])
+    };
+    let limit = max_distance * max_distance;
+    let mut field = vec![limit; cols * rows];
+    // Texels whose centre `(k + 0.5) · step` lies in `[lo, hi]`
+    let span = |lo: f64, hi: f64, step: f64, count: usize| {
+        let first = (lo / step - 0.5).ceil().max(0.0);
+        let last = (hi / step - 0.5).floor().min(count as f64 - 1.0);
+        (first <= last).then_some((first as usize, last as usize))
+    };
+    for e in edge_indices.chunks_exact(2) {
+        let (a, b) = (point(e[0]), point(e[1]));
+        let x_range = span(a.x.min(b.x) - max_distance, a.x.max(b.x) + max_distance, sx, cols);
+        let y_range = span(a.y.min(b.y) - max_distance, a.y.max(b.y) + max_distance, sy, rows);
+        let (Some((c0, c1)), Some((r0, r1))) = (x_range, y_range) else { continue };
+        for row in r0..=r1 {
+            let y = (row as f64 + 0.5) * sy;
+            for col in c0..=c1 {
+                let p = Point::new((col as f64 + 0.5) * sx, y);
+                let texel = &mut field[row * cols + col];
+                *texel = texel.min(segment_distance2(p, a, b));
+            }
+        }
+    }
+    Ok(field.into_iter().map(|d2| (d2.sqrt() / max_distance) as f32).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+
+    #[test]
+    fn test_horizontal_edge_field_is_symmetric() {
+        // A 64 × 64 canvas with the edge along y = 32, between texel rows
+        let points = [8.0, 32.0, 56.0, 32.0];
+        let field = edges_distance_field(&points, &[0, 1], 64, 64, 64.0, 64.0, 10.0).unwrap();
+        assert_eq!(field.len(), 64 * 64);
+        let at = |col: usize, row: usize| field[row * 64 + col];
+        for row in 0..32 {
+            for col in 0..64 {
+                assert_eq!(at(col, row), at(col, 63 - row), "texel ({col}, {row})");
+                let mirrored = at(63 - col, row);
+                assert!((at(col, row) - mirrored).abs() < 1e-6, "texel ({col}, {row})");
+            }
+        }
+        assert_eq!(at(32, 31), 0.05);
+        assert_eq!(at(32, 27), 0.45);
+        assert_eq!(at(32, 10), 1.0);
+        // Rounded ends: beyond the endpoint the distance grows radially
+        let corner = (3.5f32 * 3.5 + 0.5 * 0.5).sqrt() / 10.0;
+        assert!((at(4, 31) - corner).abs() < 1e-6);
+        assert_eq!(at(0, 0), 1.0);
+
+        let empty = edges_distance_field(&points, &[], 4, 4, 64.0, 64.0, 10.0).unwrap();
+        assert_eq!(empty, vec![1.0; 16]);
+    }
+
+    #[test]
+    fn test_field_matches_brute_force() {
+        let mut rng = Rng::new(17);
+        let points: Vec<f64> =
+            (0..60).flat_map(|_| [rng.range(-20.0, 220.0), rng.range(-20.0, 170.0)]).collect();
+        let edges: Vec<u32> = (0..80).map(|_| rng.below(60)).collect();
+        let field = edges_distance_field(&points, &edges, 50, 40, 200.0, 150.0, 12.0).unwrap();
+        let p = |i: u32| Point::new(points[i as usize * 2], points[i as usize * 2 + 1]);
+        for (k, &v) in field.iter().enumerate() {
+            let q = Point::new((k % 50) as f64 * 4.0 + 2.0, (k / 50) as f64 * 3.75 + 1.875);
+            let nearest = edges
+                .chunks(2)
+                .map(|e| segment_distance2(q, p(e[0]), p(e[1])).sqrt())
+                .fold(12.0, f64::min);
+            assert!((v as f64 - nearest / 12.0).abs() < 1e-6, "texel {k}");
+        }
+
+        let field = |e: &[u32], w, max| {
+            code_of(edges_distance_field(&points, e, w, 4, 200.0, 150.0, max))
+        };
+        assert_eq!(field(&[0, 1, 2], 4, 1.0), ErrorCode::InvalidLength);
+        assert_eq!(field(&[0, 60], 4, 1.0), ErrorCode::IndexOutOfRange);
+        assert_eq!(field(&[0, 1], 0, 1.0), ErrorCode::InvalidArgument);
+        assert_eq!(field(&[0, 1], 1 << 30, 1.0), ErrorCode::TooLarge);
+        assert_eq!(field(&[0, 1], 4, 0.0), ErrorCode::InvalidArgument);
+        assert_eq!(field(&[0, 1], 4, f64::INFINITY), ErrorCode::InvalidArgument);
+        let nan = edges_distance_field(&[f64::NAN, 0.0], &[], 4, 4, 1.0, 1.0, 1.0);
+        assert_eq!(code_of(nan), ErrorCode::NonFiniteInput);
+    }
+}
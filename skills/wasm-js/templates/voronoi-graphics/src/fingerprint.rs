@@ -0,0 +1,175 @@
+//! Stable fingerprints of generated geometry, for caches and regression
+//! checks
+//!
+//! Both hashes are 64-bit FNV-1a over a canonical byte form, written as 16
+//! lowercase hex digits. The canonical form, which only changes with a
+//! major version:
+//!
+//! 1. Each coordinate is quantized to `round(v × 10^decimals)` as a
+//!    little-endian `i64`, so values within half a quantum of each other
+//!    hash alike; `-0.0` and `0.0` are the same.
+//! 2. `points_hash` hashes the byte `b'P'`, the decimals as one byte, the
+//!    point count as a little-endian `u64`, then every quantized `x, y`
+//!    in input order.
+//! 3. `geometry_hash` hashes the byte `b'G'`, the points as in 2 at
+//!    `GEOMETRY_HASH_DECIMALS`, the triangle count as a `u64`, then every
+//!    triangle as three little-endian `u32`s. Each triangle is first
+//!    rotated to start at its smallest index, which keeps its winding,
+//!    and the triangles are sorted by those triples, so neither the order
+//!    of the list nor the corner a triangle starts from changes the hash.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles};
+
+/// Decimals `geometry_hash` quantizes coordinates to
+pub const GEOMETRY_HASH_DECIMALS: u32 = 6;
+
+/// Most decimals `points_hash` accepts; beyond this the quantum is below
+/// f64 resolution for canvas-sized coordinates
+const MAX_HASH_DECIMALS: u32 = 12;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Fnv {
+        Fnv(FNV_OFFSET)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn hex(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+/// Feed the quantized points to `hash`, erroring on any that are not
+/// finite or do not fit an `i64` once scaled
+fn write_points(hash: &mut Fnv, points_flat: &[f64], decimals: u32) -> Result<()> {
+    let n = check_points(points_flat)?;
+    if decimals > MAX_HASH_DECIMALS {
+        return Err(Error::InvalidArgument(format!(
+            "precision must be at most {MAX_HASH_DECIMALS} decimals, got {decimals}"
+        )));
+    }
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let scale = 10f64.powi(decimals as i32);
+    hash.write(&[decimals as u8]);
+    hash.write(&(n as u64).to_le_bytes());
+    for &v in points_flat {
+        let q = (v * scale).round();
+        // The cast would saturate at 2^63 instead
+        if q.abs() >= 2f64.powi(63) {
+            return Err(Error::TooLarge(format!("{v} at {decimals} decimals overflows 64 bits")));
+        }
+        hash.write(&(q as i64).to_le_bytes());
+    }
+    Ok(())
+}
+
+/// Fingerprint of `points_flat` quantized to `precision_decimals`
+/// decimal places, in input order
+#[wasm_bindgen]
+pub fn points_hash(points_flat: &[f64], precision_decimals: u32) -> Result<String> {
+    let mut hash = Fnv::new();
+    hash.write(b"P");
+    write_points(&mut hash, points_flat, precision_decimals)?;
+    Ok(hash.hex())
+}
+
+/// Fingerprint of a triangulation: its points at `GEOMETRY_HASH_DECIMALS`
+/// and its triangles as a set, whatever their order in `triangles`
+#[wasm_bindgen]
+pub fn geometry_hash(points_flat: &[f64], triangles: &[u32]) -> Result<String> {
+    check_triangles(triangles, points_flat.len() / 2)?;
+    let mut hash = Fnv::new();
+    hash.write(b"G");
+    write_points(&mut hash, points_flat, GEOMETRY_HASH_DECIMALS)?;
+    let mut canonical: Vec<[u32; 3]> = triangles
+        .chunks_exact(3)
+        .map(|t| {
+            let [a, b, c] = [t[0], t[1], t[2]];
+            if a <= b && a <= c {
+                [a, b, c]
+            } else if b <= c {
+                [b, c, a]
+            } else {
+                [c, a, b]
+            }
+        })
+        .collect();
+    canonical.sort_unstable();
+    hash.write(&(canonical.len() as u64).to_le_bytes());
+    for v in canonical.iter().flatten() {
+        hash.write(&v.to_le_bytes());
+    }
+    Ok(hash.hex())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::{compute_delaunay, generate_golden_seeds};
+
+    #[test]
+    fn test_hashes_are_pinned_and_quantized() {
+        // FNV-1a of the empty input and of "a", from the reference tables
+        assert_eq!(Fnv::new().hex(), "cbf29ce484222325");
+        let mut a = Fnv::new();
+        a.write(b"a");
+        assert_eq!(a.hex(), "af63dc4c8601ec8c");
+
+        let points = [10.0, 20.25, -3.5, 0.0];
+        let hash = points_hash(&points, 2).unwrap();
+        assert_eq!(hash.len(), 16);
+        assert_eq!(points_hash(&[10.001, 20.249, -3.5, -0.0], 2).unwrap(), hash);
+        assert_ne!(points_hash(&[10.02, 20.25, -3.5, 0.0], 2).unwrap(), hash);
+        assert_ne!(points_hash(&points, 3).unwrap(), hash);
+        assert_ne!(points_hash(&[-3.5, 0.0, 10.0, 20.25], 2).unwrap(), hash);
+
+        let code = |p: &[f64], d: u32| code_of(points_hash(p, d));
+        assert_eq!(code(&points[..3], 2), ErrorCode::InvalidLength);
+        assert_eq!(code(&[f64::NAN, 0.0], 2), ErrorCode::NonFiniteInput);
+        assert_eq!(code(&points, 13), ErrorCode::InvalidArgument);
+        assert_eq!(code(&[1e10, 0.0], 12), ErrorCode::TooLarge);
+    }
+
+    #[test]
+    fn test_geometry_hash_ignores_triangle_order() {
+        let points = generate_golden_seeds(400.0, 300.0, 300);
+        let triangles = compute_delaunay(&points, 400.0, 300.0);
+        let hash = geometry_hash(&points, &triangles).unwrap();
+
+        // Reverse the list and start every triangle from another corner
+        let shuffled: Vec<u32> =
+            triangles.chunks(3).rev().flat_map(|t| [t[1], t[2], t[0]]).collect();
+        assert_eq!(geometry_hash(&points, &shuffled).unwrap(), hash);
+        // Flipping a triangle's winding is a different mesh
+        let mut flipped = triangles.clone();
+        flipped.swap(0, 1);
+        assert_ne!(geometry_hash(&points, &flipped).unwrap(), hash);
+        assert_ne!(geometry_hash(&points, &triangles[3..]).unwrap(), hash);
+
+        // Anywhere within half a quantum of the quantized value hashes alike
+        let mut moved = points.clone();
+        moved[7] = (points[7] * 1e6).round() / 1e6 + 3e-7;
+        assert_eq!(geometry_hash(&moved, &triangles).unwrap(), hash);
+        moved[7] += 2e-6;
+        assert_ne!(geometry_hash(&moved, &triangles).unwrap(), hash);
+        assert_ne!(points_hash(&points, GEOMETRY_HASH_DECIMALS).unwrap(), hash);
+
+        let bad = geometry_hash(&points, &[0, 1, 300]);
+        assert_eq!(code_of(bad), ErrorCode::IndexOutOfRange);
+    }
+}
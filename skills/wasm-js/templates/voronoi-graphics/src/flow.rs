@@ -0,0 +1,169 @@
+//! Free-floating motes advected by curl noise
+//!
+//! The velocity at `p` is the curl `(∂ψ/∂y, -∂ψ/∂x)` of a noise potential
+//! `ψ`, so the flow is divergence-free: motes swirl around its contours
+//! without bunching up or thinning out. `ψ` sums two `simplex_noise_2d`
+//! layers drifting in opposite directions over time, which makes the
+//! pattern morph rather than just scroll, and its slope comes from the
+//! analytic noise gradient.
+//!
+//! Motes leaving the canvas wrap to the opposite side. A mote older than
+//! `max_age` respawns at a point of the golden spiral picked by the
+//! `set_mote_seed` hash, so a seed and a sequence of calls replay exactly.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::noise::simplex_noise_2d_gradient_xy;
+use crate::GOLDEN_ANGLE;
+
+/// Noise-space drift of the two potential layers per unit of `time`
+const DRIFT: (f64, f64) = (0.13, 0.07);
+/// Domain shift of the second layer, so the two are uncorrelated
+const LAYER_SHIFT: (f64, f64) = (41.3, -17.9);
+
+/// Curl-noise velocity at canvas point `(x, y)`
+fn velocity(x: f64, y: f64, time: f64, scale: f64, strength: f64) -> (f64, f64) {
+    let (nx, ny) = (x * scale, y * scale);
+    let (ax, ay) = simplex_noise_2d_gradient_xy(nx + DRIFT.0 * time, ny + DRIFT.1 * time);
+    let (bx, by) = simplex_noise_2d_gradient_xy(
+        nx - DRIFT.0 * time + LAYER_SHIFT.0,
+        ny - DRIFT.1 * time + LAYER_SHIFT.1,
+    );
+    (strength * (ay + by), -strength * (ax + bx))
+}
+
+/// Point `k` of a `count`-point golden spiral filling the disc
+/// `generate_golden_seeds` uses
+fn spiral_point(k: u32, count: usize, width: f64, height: f64) -> (f64, f64) {
+    let radius = width.min(height) / 2.0 * 0.85 * (k as f64 / count as f64).sqrt();
+    let theta = k as f64 * GOLDEN_ANGLE;
+    (width / 2.0 + radius * theta.cos(), height / 2.0 + radius * theta.sin())
+}
+
+/// Advance free-floating motes `[x, y, age, ...]` through a curl-noise
+/// flow and return `[x, y, brightness, ...]` for drawing
+///
+/// `scale` converts canvas units to noise units, so `1 / scale` is about
+/// the size of one swirl; `strength` is the speed in canvas units per
+/// second per unit of noise slope. Each step integrates with the
+/// midpoint rule. Brightness is `sin(π · age / max_age)`, so motes fade
+/// in after a respawn and out before the next. Motes with a non-finite
+/// position respawn too.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn update_flow_motes(
+    motes: &mut [f64],
+    width: f64,
+    height: f64,
+    dt: f64,
+    time: f64,
+    scale: f64,
+    strength: f64,
+    max_age: f64,
+) -> Result<Vec<f32>> {
+    if !motes.len().is_multiple_of(3) {
+        return Err(Error::InvalidLength(format!(
+            "flow mote array length {} is not a multiple of 3",
+            motes.len()
+        )));
+    }
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if ![dt, time, scale, strength].iter().all(|v| v.is_finite()) {
+        return Err(Error::InvalidArgument("dt, time, scale and strength must be finite".into()));
+    }
+    if max_age.is_nan() || max_age <= 0.0 {
+        return Err(Error::InvalidArgument(format!("max_age must be positive, got {max_age}")));
+    }
+    let count = motes.len() / 3;
+    let mut out = Vec::with_capacity(count * 3);
+    for (i, m) in motes.chunks_exact_mut(3).enumerate() {
+        let (mut x, mut y, mut age) = (m[0], m[1], m[2] + dt);
+        if age > max_age || !(x.is_finite() && y.is_finite() && age.is_finite()) {
+            // No edge to hash, so 0 stands in for it
+            let k = crate::jump_rng(i, 0, time).below(count as u32);
+            (x, y) = spiral_point(k, count, width, height);
+            age = 0.0;
+        } else {
+            let (vx, vy) = velocity(x, y, time, scale, strength);
+            let (mx, my) = (x + vx * dt * 0.5, y + vy * dt * 0.5);
+            let (vx, vy) = velocity(mx, my, time + dt * 0.5, scale, strength);
+            x = (x + vx * dt).rem_euclid(width);
+            y = (y + vy * dt).rem_euclid(height);
+        }
+        m.copy_from_slice(&[x, y, age]);
+        let brightness = (std::f64::consts::PI * age / max_age).sin();
+        out.extend([x as f32, y as f32, brightness as f32]);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::set_mote_seed;
+
+    #[test]
+    fn test_flow_is_divergence_free_and_wraps() {
+        // Central differences of the velocity sum to ~0 everywhere
+        let h = 1e-4;
+        for k in 0..200 {
+            let (x, y) = (k as f64 * 3.7 % 640.0, k as f64 * 7.3 % 480.0);
+            let v = |x, y| velocity(x, y, 2.5, 0.01, 30.0);
+            let dvx = (v(x + h, y).0 - v(x - h, y).0) / (2.0 * h);
+            let dvy = (v(x, y + h).1 - v(x, y - h).1) / (2.0 * h);
+            assert!((dvx + dvy).abs() < 1e-4 * (dvx.abs() + dvy.abs() + 1.0), "at {x}, {y}");
+        }
+
+        let mut motes: Vec<f64> =
+            (0..300).flat_map(|k| [k as f64 * 2.1, k as f64 * 1.6, 0.0]).collect();
+        let start = motes.clone();
+        for frame in 0..120 {
+            let t = frame as f64 / 60.0;
+            let out = update_flow_motes(&mut motes, 640.0, 480.0, 1.0 / 60.0, t, 0.01, 400.0, 10.0)
+                .unwrap();
+            assert_eq!(out.len(), 900);
+            for m in motes.chunks(3) {
+                assert!((0.0..640.0).contains(&m[0]) && (0.0..480.0).contains(&m[1]));
+            }
+        }
+        assert!(motes.iter().zip(&start).any(|(a, b)| (a - b).abs() > 20.0), "motes moved");
+        assert!((motes[2] - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_old_motes_respawn_deterministically() {
+        let run = |seed| {
+            set_mote_seed(seed);
+            let mut motes = vec![10.0, 10.0, 0.95, 20.0, 20.0, 0.5, f64::NAN, 0.0, 0.0];
+            let out = update_flow_motes(&mut motes, 400.0, 300.0, 0.1, 3.0, 0.02, 50.0, 1.0)
+                .unwrap();
+            (motes, out)
+        };
+        let (motes, out) = run(9);
+        assert_eq!((motes.clone(), out.clone()), run(9));
+        // Motes 0 and 2 respawned on the spiral, at age 0 and dark
+        for i in [0, 2] {
+            let m = &motes[i * 3..i * 3 + 3];
+            assert_eq!(m[2], 0.0);
+            assert_eq!(out[i * 3 + 2], 0.0);
+            let k = (0..3).find(|&k| spiral_point(k, 3, 400.0, 300.0) == (m[0], m[1]));
+            assert!(k.is_some(), "mote {i} at {m:?}");
+        }
+        assert!((motes[5] - 0.6).abs() < 1e-12);
+        assert!((out[5] as f64 - (0.6 * std::f64::consts::PI).sin()).abs() < 1e-6);
+        set_mote_seed(0);
+
+        let flow = |m: &mut [f64], max_age| {
+            code_of(update_flow_motes(m, 400.0, 300.0, 0.1, 0.0, 0.02, 50.0, max_age))
+        };
+        assert_eq!(flow(&mut [0.0; 4], 1.0), ErrorCode::InvalidLength);
+        assert_eq!(flow(&mut [0.0; 3], 0.0), ErrorCode::InvalidArgument);
+        assert_eq!(flow(&mut [0.0; 3], f64::NAN), ErrorCode::InvalidArgument);
+        let flat = update_flow_motes(&mut [0.0; 3], 400.0, 0.0, 0.1, 0.0, 0.02, 50.0, 1.0);
+        assert_eq!(code_of(flat), ErrorCode::InvalidArgument);
+    }
+}
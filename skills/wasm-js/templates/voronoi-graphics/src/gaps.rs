@@ -0,0 +1,127 @@
+//! "Spark gap" edge styling: each edge drawn as two halves around a dot
+
+use wasm_bindgen::prelude::*;
+
+use crate::crossings::flat_segments;
+use crate::error::{Error, Result};
+
+/// Sub-segments and connector dots from `split_edges_with_gaps`
+#[wasm_bindgen]
+pub struct GapSplit {
+    segments: Vec<f64>,
+    counts: Vec<u32>,
+    midpoints: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl GapSplit {
+    /// Drawable pieces `[x0, y0, x1, y1, ...]`, in edge order
+    pub fn segments(&self) -> Vec<f64> {
+        self.segments.clone()
+    }
+
+    /// Pieces per input edge (0, 1 or 2); a running sum maps an edge to
+    /// its first piece in `segments()`
+    pub fn counts(&self) -> Vec<u32> {
+        self.counts.clone()
+    }
+
+    /// Connector dot `[x, y, ...]` per input edge, including collapsed ones
+    pub fn midpoints(&self) -> Vec<f64> {
+        self.midpoints.clone()
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len() / 4
+    }
+}
+
+/// Split every edge symmetrically around its midpoint
+///
+/// `gaps[i]` is the fraction of edge `i`'s length left open in the
+/// middle: 0 keeps the full edge as one piece, values in `(0, 1)` give two
+/// pieces of `(1 - gap) / 2` of the length each, and 1 or more collapses
+/// the edge to just its dot. Zero-length edges never produce pieces, so
+/// no degenerate segment reaches the renderer. Output order follows input
+/// order, with `counts()` giving the mapping back.
+#[wasm_bindgen]
+pub fn split_edges_with_gaps(edges: &[f64], gaps: &[f32]) -> Result<GapSplit> {
+    let segments = flat_segments(edges, "edges")?;
+    if gaps.len() != segments.len() {
+        return Err(Error::InvalidLength(format!(
+            "expected {} gaps, got {}",
+            segments.len(),
+            gaps.len()
+        )));
+    }
+    if let Some(bad) = gaps.iter().find(|g| g.is_nan() || **g < 0.0) {
+        return Err(Error::InvalidArgument(format!("gaps must be non-negative, got {bad}")));
+    }
+
+    let mut out = GapSplit {
+        segments: Vec::with_capacity(edges.len() * 2),
+        counts: Vec::with_capacity(segments.len()),
+        midpoints: Vec::with_capacity(segments.len() * 2),
+    };
+    for (&[a, b], &gap) in segments.iter().zip(gaps) {
+        let (mx, my) = ((a.x + b.x) * 0.5, (a.y + b.y) * 0.5);
+        out.midpoints.extend_from_slice(&[mx, my]);
+        let count = if a == b || gap >= 1.0 {
+            0
+        } else if gap == 0.0 {
+            out.segments.extend_from_slice(&[a.x, a.y, b.x, b.y]);
+            1
+        } else {
+            // Each piece keeps (1 - gap) / 2 of the edge, measured from
+            // its own endpoint so both halves are exact mirror images
+            let keep = (1.0 - gap as f64) * 0.5;
+            let (dx, dy) = ((b.x - a.x) * keep, (b.y - a.y) * keep);
+            out.segments.extend_from_slice(&[a.x, a.y, a.x + dx, a.y + dy]);
+            out.segments.extend_from_slice(&[b.x - dx, b.y - dy, b.x, b.y]);
+            2
+        };
+        out.counts.push(count);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_gaps_split_symmetrically() {
+        // Horizontal, vertical, diagonal and zero-length edges
+        let edges = [
+            0.0, 0.0, 10.0, 0.0, 0.0, 0.0, 0.0, 4.0, 1.0, 1.0, 5.0, 5.0, 2.0, 2.0, 2.0, 2.0,
+        ];
+        let split = split_edges_with_gaps(&edges, &[0.5, 0.0, 1.5, 0.3]).unwrap();
+        assert_eq!(split.counts(), vec![2, 1, 0, 0]);
+        assert_eq!(
+            split.segments(),
+            vec![0.0, 0.0, 2.5, 0.0, 7.5, 0.0, 10.0, 0.0, 0.0, 0.0, 0.0, 4.0]
+        );
+        assert_eq!(split.midpoints(), vec![5.0, 0.0, 0.0, 2.0, 3.0, 3.0, 2.0, 2.0]);
+        assert_eq!(split.segment_count(), 3);
+
+        // Both halves have the same length and leave the dot centered
+        let diagonal = [-3.0, 7.0, 11.0, -2.0];
+        let s = split_edges_with_gaps(&diagonal, &[0.37]).unwrap().segments();
+        let len = |i: usize| (s[i + 2] - s[i]).hypot(s[i + 3] - s[i + 1]);
+        assert!((len(0) - len(4)).abs() < 1e-12);
+        let gap = (s[4] - s[2]).hypot(s[5] - s[3]);
+        assert!((gap / 14.0f64.hypot(9.0) - 0.37).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_split_rejects_bad_input() {
+        let edges = [0.0, 0.0, 1.0, 1.0];
+        assert_eq!(code_of(split_edges_with_gaps(&edges[..3], &[0.1])), ErrorCode::InvalidLength);
+        assert_eq!(code_of(split_edges_with_gaps(&edges, &[])), ErrorCode::InvalidLength);
+        assert_eq!(code_of(split_edges_with_gaps(&edges, &[-0.1])), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(split_edges_with_gaps(&edges, &[f32::NAN])), ErrorCode::InvalidArgument);
+        let nan = [0.0, f64::NAN, 1.0, 1.0];
+        assert_eq!(code_of(split_edges_with_gaps(&nan, &[0.1])), ErrorCode::NonFiniteInput);
+    }
+}
@@ -0,0 +1,266 @@
+//! Rust-native API over the triangulation and Voronoi code
+//!
+//! The WASM exports trade in flat `f64` arrays and index lists, which suits
+//! typed arrays but not Rust callers. The types here own their data,
+//! validate it on construction and hand out iterators; `compute_delaunay`
+//! and `compute_voronoi` are thin adapters over them. Nothing in this
+//! module depends on `wasm_bindgen`.
+//!
+//! ```
+//! use voronoi_graphics::{Point, Triangulation};
+//!
+//! let square = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+//! let tri = Triangulation::new(&square.map(|(x, y)| Point::new(x, y))).unwrap();
+//! assert_eq!(tri.triangle_count(), 2);
+//! assert_eq!(tri.edges().count(), 5);
+//! ```
+
+use crate::error::{Error, Result};
+use crate::graph::Csr;
+use crate::mesh::{Mesh, SUPER};
+use crate::Point;
+
+impl Point {
+    pub const fn new(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+}
+
+/// Three point indices in counter-clockwise order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Triangle {
+    pub a: usize,
+    pub b: usize,
+    pub c: usize,
+}
+
+impl Triangle {
+    pub fn vertices(&self) -> [usize; 3] {
+        [self.a, self.b, self.c]
+    }
+
+    /// The three sides as `(low, high)` index pairs
+    pub fn edges(&self) -> [(usize, usize); 3] {
+        let side = |p: usize, q: usize| (p.min(q), p.max(q));
+        [side(self.a, self.b), side(self.b, self.c), side(self.c, self.a)]
+    }
+}
+
+fn check_finite(points: &[Point]) -> Result<()> {
+    if points.iter().any(|p| !(p.x.is_finite() && p.y.is_finite())) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    Ok(())
+}
+
+/// Delaunay triangulation of a point set
+///
+/// Fewer than three points, or only collinear ones, give a triangulation
+/// without triangles. Duplicate points are kept in `points()` but belong
+/// to no triangle.
+#[derive(Clone, Debug)]
+pub struct Triangulation {
+    points: Vec<Point>,
+    triangles: Vec<Triangle>,
+    /// Unique sides, `(low, high)` and sorted
+    edges: Vec<(usize, usize)>,
+    adjacency: Csr,
+}
+
+impl Triangulation {
+    /// Triangulate `points`, which may lie anywhere in the plane
+    ///
+    /// The super triangle is sized from the points' own bounding box and
+    /// the result covers their whole convex hull. Errors on non-finite
+    /// coordinates, and with `TooLarge` on a spread so wide, near
+    /// `f64::MAX`, that no finite triangle encloses it.
+    ///
+    /// With the `parallel` feature, large point sets are triangulated on
+    /// several threads; the triangles are the same but come in another
+    /// order.
+    pub fn new(points: &[Point]) -> std::result::Result<Triangulation, Error> {
+        check_finite(points)?;
+        #[cfg(feature = "parallel")]
+        if let Some(triangles) = crate::parallel::triangulate(points) {
+            let flat: Vec<u32> = triangles.iter().flatten().copied().collect();
+            let real = crate::graph::unique_edges(&flat);
+            let adjacency = Csr::from_edges(points.len(), &real);
+            return Ok(Triangulation {
+                points: points.to_vec(),
+                triangles: triangles
+                    .into_iter()
+                    .map(|[a, b, c]| Triangle { a: a as usize, b: b as usize, c: c as usize })
+                    .collect(),
+                edges: real.into_iter().map(|(a, b)| (a as usize, b as usize)).collect(),
+                adjacency,
+            });
+        }
+        let mesh = Mesh::build_closed(points);
+        if !points.iter().all(|&p| mesh.encloses(p)) {
+            return Err(Error::TooLarge(
+                "point coordinates span too far to enclose in a super triangle".into(),
+            ));
+        }
+        let triangles: Vec<Triangle> = profiled!("compact", {
+            mesh.real_triangles()
+                .map(|[a, b, c]| Triangle { a: a as usize, b: b as usize, c: c as usize })
+                .collect()
+        });
+        let (edges, adjacency) = profiled!("adjacency", {
+            let real: Vec<(u32, u32)> =
+                mesh.real_edges().into_iter().map(|(a, b)| (a - SUPER, b - SUPER)).collect();
+            let adjacency = Csr::from_edges(points.len(), &real);
+            (real.into_iter().map(|(a, b)| (a as usize, b as usize)).collect(), adjacency)
+        });
+        Ok(Triangulation { points: points.to_vec(), triangles, edges, adjacency })
+    }
+
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    pub fn triangles(&self) -> impl ExactSizeIterator<Item = Triangle> + '_ {
+        self.triangles.iter().copied()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    /// Unique edges as `(low, high)` pairs in ascending order
+    pub fn edges(&self) -> impl ExactSizeIterator<Item = (usize, usize)> + '_ {
+        self.edges.iter().copied()
+    }
+
+    /// Points sharing an edge with point `v`, ascending
+    ///
+    /// # Panics
+    /// If `v` is not an index into `points()`.
+    pub fn neighbors(&self, v: usize) -> impl ExactSizeIterator<Item = usize> + '_ {
+        assert!(v < self.points.len(), "point {v} out of range");
+        self.adjacency.neighbors(v as u32).iter().map(|&u| u as usize)
+    }
+}
+
+/// Voronoi cells of a point set, clipped to a `width × height` canvas
+///
+/// Cell `i` belongs to point `i`. Non-empty cells are convex,
+/// counter-clockwise and not closed (the first vertex is not repeated);
+/// cells of duplicate points, or lying entirely off canvas, are empty.
+#[cfg(feature = "voronoi")]
+#[derive(Clone, Debug)]
+pub struct VoronoiDiagram {
+    vertices: Vec<Point>,
+    /// One entry per cell plus one, into `vertices`
+    offsets: Vec<u32>,
+}
+
+#[cfg(feature = "voronoi")]
+impl VoronoiDiagram {
+    pub fn new(
+        points: &[Point],
+        width: f64,
+        height: f64,
+    ) -> std::result::Result<VoronoiDiagram, Error> {
+        if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+            return Err(Error::InvalidArgument("width and height must be positive".into()));
+        }
+        check_finite(points)?;
+        let (flat, offsets) = crate::voronoi::voronoi_cells(points, width, height);
+        let vertices = flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+        Ok(VoronoiDiagram { vertices, offsets })
+    }
+
+    /// Number of cells, which is the number of input points
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Polygon of cell `i`
+    ///
+    /// # Panics
+    /// If `i >= len()`.
+    pub fn cell(&self, i: usize) -> &[Point] {
+        &self.vertices[self.offsets[i] as usize..self.offsets[i + 1] as usize]
+    }
+
+    pub fn cells(&self) -> impl ExactSizeIterator<Item = &[Point]> + '_ {
+        (0..self.len()).map(|i| self.cell(i))
+    }
+
+    /// Every cell vertex as `[x0, y0, x1, y1, ...]` with the offsets of
+    /// each cell, the layout `VoronoiCells` exposes to JS
+    pub(crate) fn into_flat(self) -> (Vec<f64>, Vec<u32>) {
+        (self.vertices.iter().flat_map(|p| [p.x, p.y]).collect(), self.offsets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    fn lattice(n: usize) -> Vec<Point> {
+        // Jittered so no four points are cocircular
+        (0..n * n)
+            .map(|i| {
+                let (x, y) = ((i % n) as f64, (i / n) as f64);
+                Point::new(x + 0.01 * (y * 1.7).sin(), y + 0.01 * (x * 2.3).cos())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_triangulation_iterators_agree() {
+        let points = lattice(6);
+        let tri = Triangulation::new(&points).unwrap();
+        // At least the 2 (n - 1)^2 grid triangles (jitter can add slivers
+        // along the hull), and Euler's formula ties the counts together
+        assert!(tri.triangle_count() >= 50);
+        assert_eq!(tri.edges().len(), points.len() + tri.triangle_count() - 1);
+        let mut from_triangles: Vec<(usize, usize)> =
+            tri.triangles().flat_map(|t| t.edges()).collect();
+        from_triangles.sort_unstable();
+        from_triangles.dedup();
+        assert_eq!(from_triangles, tri.edges().collect::<Vec<_>>());
+        for t in tri.triangles() {
+            let [a, b, c] = t.vertices().map(|v| points[v]);
+            assert!((b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x) > 0.0);
+        }
+        let degrees: usize = (0..points.len()).map(|v| tri.neighbors(v).len()).sum();
+        assert_eq!(degrees, 2 * tri.edges().len());
+        // An interior point has at least its four grid neighbours
+        assert!(tri.neighbors(14).len() >= 4);
+        assert!(tri.neighbors(0).all(|u| tri.neighbors(u).any(|w| w == 0)));
+
+        assert_eq!(Triangulation::new(&points[..2]).unwrap().triangle_count(), 0);
+        let bad = [Point::new(0.0, f64::NAN)];
+        assert_eq!(code_of(Triangulation::new(&bad)), ErrorCode::NonFiniteInput);
+        let vast = [Point::new(-f64::MAX, 0.0), Point::new(f64::MAX, 1.0), Point::new(0.0, -1.0)];
+        assert_eq!(code_of(Triangulation::new(&vast)), ErrorCode::TooLarge);
+    }
+
+    #[cfg(feature = "voronoi")]
+    #[test]
+    fn test_voronoi_cells_tile_the_canvas() {
+        let points: Vec<Point> =
+            lattice(5).iter().map(|p| Point::new(p.x * 20.0 + 10.0, p.y * 20.0 + 10.0)).collect();
+        let diagram = VoronoiDiagram::new(&points, 100.0, 100.0).unwrap();
+        assert_eq!(diagram.len(), 25);
+        let shoelace = |cell: &[Point]| {
+            let n = cell.len();
+            let cross = |i: usize| {
+                let (p, q) = (cell[i], cell[(i + 1) % n]);
+                p.x * q.y - q.x * p.y
+            };
+            (0..n).map(cross).sum::<f64>() / 2.0
+        };
+        let area: f64 = diagram.cells().map(shoelace).sum();
+        assert!((area - 10_000.0).abs() < 1e-6);
+        assert_eq!(code_of(VoronoiDiagram::new(&points, 0.0, 1.0)), ErrorCode::InvalidArgument);
+    }
+}
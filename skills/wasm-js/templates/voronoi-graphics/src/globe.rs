@@ -0,0 +1,320 @@
+//! Seeds, triangulation and edges on the sphere, for globe rendering
+//!
+//! On the sphere the Delaunay triangulation is the convex hull of the
+//! points: a circle on the sphere is a plane section, and a cap is empty
+//! exactly when no point lies beyond the plane. The hull is built by
+//! quickhull-style incremental insertion, each face holding the points
+//! that can see it and always taking the farthest one next, with every
+//! side-of-plane decision made by the exact `orient3d`.
+
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::check_triangles;
+use crate::predicates::orient3d;
+use crate::{compute_edge_indices, GOLDEN_ANGLE};
+
+/// Spherical Fibonacci lattice: `count` points `[x, y, z, ...]` spread
+/// evenly over the sphere of `radius` about the origin
+///
+/// Point `i` sits at height `z = radius · (1 - (2i + 1) / count)` and
+/// turns by the golden angle from the one before, so the spiral runs
+/// from near the north pole to near the south pole without gaps.
+#[wasm_bindgen]
+pub fn generate_golden_seeds_sphere(count: usize, radius: f64) -> Vec<f64> {
+    (0..count)
+        .flat_map(|i| {
+            let z = 1.0 - (2 * i + 1) as f64 / count as f64;
+            let r = (1.0 - z * z).sqrt();
+            let theta = i as f64 * GOLDEN_ANGLE;
+            [r * theta.cos(), r * theta.sin(), z].map(|c| c * radius)
+        })
+        .collect()
+}
+
+/// Unit directions of `[x, y, z, ...]`
+fn parse_xyz(points_flat_xyz: &[f64]) -> Result<Vec<[f64; 3]>> {
+    if !points_flat_xyz.len().is_multiple_of(3) {
+        return Err(Error::InvalidLength(format!(
+            "xyz array length {} is not a multiple of 3",
+            points_flat_xyz.len()
+        )));
+    }
+    points_flat_xyz
+        .chunks_exact(3)
+        .enumerate()
+        .map(|(i, p)| {
+            if p.iter().any(|v| !v.is_finite()) {
+                return Err(Error::NonFiniteInput(format!("point {i} is not finite")));
+            }
+            let len = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+            if !len.is_finite() || len == 0.0 {
+                return Err(Error::DegenerateGeometry(format!("point {i} has no direction")));
+            }
+            Ok([p[0] / len, p[1] / len, p[2] / len])
+        })
+        .collect()
+}
+
+/// Hull face; edge `k` runs from `v[k]` to `v[(k + 1) % 3]` and `adj[k]`
+/// is the face across it
+struct Face {
+    v: [u32; 3],
+    adj: [u32; 3],
+    /// Points strictly in front of the face, not yet on the hull
+    outside: Vec<u32>,
+    alive: bool,
+}
+
+/// Convex hull of `points` as triangles counter-clockwise from outside
+///
+/// Points that are not hull vertices, such as duplicates, are left out.
+fn convex_hull(points: &[[f64; 3]]) -> Result<Vec<[u32; 3]>> {
+    let flat = || Error::DegenerateGeometry("points on one plane do not span the sphere".into());
+    let n = points.len();
+    if n < 4 {
+        return Err(flat());
+    }
+    let p = |i: u32| points[i as usize];
+    let sub = |a: [f64; 3], b: [f64; 3]| [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+    let norm2 = |a: [f64; 3]| a[0] * a[0] + a[1] * a[1] + a[2] * a[2];
+    let cross = |a: [f64; 3], b: [f64; 3]| {
+        [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+    };
+    // Ties go to the lowest index, here and when picking the apex, so of
+    // coincident points the first one is kept
+    let farthest = |key: &dyn Fn(u32) -> f64| {
+        (0..n as u32).rev().max_by(|&a, &b| key(a).total_cmp(&key(b))).unwrap_or(0)
+    };
+    // Starting tetrahedron: spread out so the first faces are well shaped
+    let i0 = 0;
+    let i1 = farthest(&|i| norm2(sub(p(i), p(i0))));
+    let i2 = farthest(&|i| norm2(cross(sub(p(i1), p(i0)), sub(p(i), p(i0)))));
+    let i3 = farthest(&|i| orient3d(p(i0), p(i1), p(i2), p(i)).abs());
+    let volume = orient3d(p(i0), p(i1), p(i2), p(i3));
+    if volume == 0.0 {
+        return Err(flat());
+    }
+    // `abc` is counter-clockwise from outside when `d` is below it
+    let [a, b, c, d] = if volume > 0.0 { [i0, i1, i2, i3] } else { [i0, i2, i1, i3] };
+    let mut faces: Vec<Face> = [[a, b, c], [a, d, b], [b, d, c], [c, d, a]]
+        .into_iter()
+        .map(|v| Face { v, adj: [0; 3], outside: Vec::new(), alive: true })
+        .collect();
+    let mut starts = HashMap::new();
+    for (f, face) in faces.iter().enumerate() {
+        for k in 0..3 {
+            starts.insert((face.v[k], face.v[(k + 1) % 3]), f as u32);
+        }
+    }
+    for face in &mut faces {
+        for k in 0..3 {
+            face.adj[k] = starts[&(face.v[(k + 1) % 3], face.v[k])];
+        }
+    }
+    // How far `q` is in front of face `f`, positive when it sees it
+    let height = |faces: &[Face], f: u32, q: u32| {
+        let [a, b, c] = faces[f as usize].v.map(p);
+        -orient3d(a, b, c, p(q))
+    };
+    for q in 0..n as u32 {
+        if [a, b, c, d].contains(&q) {
+            continue;
+        }
+        if let Some(f) = (0..4).find(|&f| height(&faces, f, q) > 0.0) {
+            faces[f as usize].outside.push(q);
+        }
+    }
+
+    let mut pending: Vec<u32> = (0..4).collect();
+    let mut visited = vec![0u32; 4];
+    let mut round = 0;
+    while let Some(f) = pending.pop() {
+        let face = &faces[f as usize];
+        if !face.alive || face.outside.is_empty() {
+            continue;
+        }
+        let higher = |x: &&u32, y: &&u32| {
+            height(&faces, f, **x).total_cmp(&height(&faces, f, **y)).then(y.cmp(x))
+        };
+        let apex = *face.outside.iter().max_by(higher).unwrap_or(&face.outside[0]);
+        // Faces the apex sees form a disk around `f`
+        round += 1;
+        visited[f as usize] = round;
+        let mut visible = vec![f];
+        let mut i = 0;
+        while i < visible.len() {
+            for h in faces[visible[i] as usize].adj {
+                if visited[h as usize] != round && height(&faces, h, apex) > 0.0 {
+                    visited[h as usize] = round;
+                    visible.push(h);
+                }
+            }
+            i += 1;
+        }
+        // Cone from the apex over the horizon of the disk
+        let first_new = faces.len() as u32;
+        let mut by_start = HashMap::new();
+        for &g in &visible {
+            for k in 0..3 {
+                let h = faces[g as usize].adj[k];
+                if visited[h as usize] == round {
+                    continue;
+                }
+                let (u, w) = (faces[g as usize].v[k], faces[g as usize].v[(k + 1) % 3]);
+                let new = faces.len() as u32;
+                let back = faces[h as usize].adj.iter().position(|&x| x == g).unwrap_or(0);
+                faces[h as usize].adj[back] = new;
+                by_start.insert(u, new);
+                let outside = Vec::new();
+                faces.push(Face { v: [u, w, apex], adj: [h, 0, 0], outside, alive: true });
+                visited.push(0);
+            }
+        }
+        for new in first_new..faces.len() as u32 {
+            let next = by_start[&faces[new as usize].v[1]];
+            faces[new as usize].adj[1] = next;
+            faces[next as usize].adj[2] = new;
+        }
+        for &g in &visible {
+            let orphans = std::mem::take(&mut faces[g as usize].outside);
+            faces[g as usize].alive = false;
+            for q in orphans.into_iter().filter(|&q| q != apex) {
+                let seen = (first_new..faces.len() as u32).find(|&h| height(&faces, h, q) > 0.0);
+                if let Some(h) = seen {
+                    faces[h as usize].outside.push(q);
+                }
+            }
+        }
+        pending.extend(first_new..faces.len() as u32);
+    }
+    Ok(faces.into_iter().filter(|f| f.alive).map(|f| f.v).collect())
+}
+
+/// Spherical Delaunay triangulation of `[x, y, z, ...]` about the origin
+///
+/// Only the directions of the points matter, so they need not share a
+/// radius. Triangles come as flat indices like `compute_delaunay`,
+/// counter-clockwise seen from outside the sphere; `compute_edge_indices`
+/// works on them unchanged. Coincident directions are triangulated once,
+/// leaving the later copies out. Fails with `DegenerateGeometry` when
+/// the points all lie on one plane, e.g. fewer than four or a single
+/// circle, as they then enclose no volume.
+#[wasm_bindgen]
+pub fn compute_delaunay_sphere(points_flat_xyz: &[f64]) -> Result<Vec<u32>> {
+    let points = parse_xyz(points_flat_xyz)?;
+    Ok(convex_hull(&points)?.into_iter().flatten().collect())
+}
+
+/// `compute_edges` for xyz points: `[x0, y0, z0, x1, y1, z1, ...]` per
+/// edge, in `compute_edge_indices` order
+#[wasm_bindgen]
+pub fn compute_edges_sphere(points_flat_xyz: &[f64], triangles: &[u32]) -> Result<Vec<f64>> {
+    if !points_flat_xyz.len().is_multiple_of(3) {
+        return Err(Error::InvalidLength(format!(
+            "xyz array length {} is not a multiple of 3",
+            points_flat_xyz.len()
+        )));
+    }
+    check_triangles(triangles, points_flat_xyz.len() / 3)?;
+    Ok(compute_edge_indices(triangles)
+        .into_iter()
+        .flat_map(|v| {
+            let i = v as usize * 3;
+            [points_flat_xyz[i], points_flat_xyz[i + 1], points_flat_xyz[i + 2]]
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+
+    fn xyz(points: &[f64], i: u32) -> [f64; 3] {
+        let i = i as usize * 3;
+        [points[i], points[i + 1], points[i + 2]]
+    }
+
+    #[test]
+    fn test_golden_sphere_seeds() {
+        let seeds = generate_golden_seeds_sphere(500, 2.5);
+        assert_eq!(seeds.len(), 1500);
+        for p in seeds.chunks(3) {
+            assert!(((p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt() - 2.5).abs() < 1e-12);
+        }
+        // Equal-area bands: a tenth of the height holds a tenth of the seeds
+        let band = seeds.chunks(3).filter(|p| p[2] > 2.0).count();
+        assert_eq!(band, 50);
+        let centroid = [0, 1, 2].map(|k| seeds.chunks(3).map(|p| p[k]).sum::<f64>() / 500.0);
+        assert!(centroid.iter().all(|c| c.abs() < 1e-2));
+        assert!(generate_golden_seeds_sphere(0, 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_sphere_triangulation_is_delaunay_and_closed() {
+        let mut rng = Rng::new(9);
+        let mut points = generate_golden_seeds_sphere(300, 1.0);
+        // Random directions at random radii
+        while points.len() < 3 * 600 {
+            let p = [0; 3].map(|_| rng.range(-1.0, 1.0));
+            let len2: f64 = p.iter().map(|v| v * v).sum();
+            if len2 > 0.01 && len2 < 1.0 {
+                points.extend(p.map(|v| v * rng.range(0.5, 3.0)));
+            }
+        }
+        let triangles = compute_delaunay_sphere(&points).unwrap();
+        let n = 600;
+        // Euler on the sphere: V - E + F = 2 with 3F = 2E
+        assert_eq!(triangles.len() / 3, 2 * n - 4);
+        assert_eq!(compute_edge_indices(&triangles).len() / 2, 3 * n - 6);
+        let unit = parse_xyz(&points).unwrap();
+        let at = |i: u32| unit[i as usize];
+        for t in triangles.chunks(3) {
+            let [a, b, c] = [t[0], t[1], t[2]].map(at);
+            // Outward winding, and no point beyond the plane of any face
+            assert!(orient3d(a, b, c, [0.0; 3]) > 0.0);
+            for q in 0..n as u32 {
+                assert!(orient3d(a, b, c, at(q)) >= 0.0, "point {q} is inside the cap of {t:?}");
+            }
+        }
+
+        let edges = compute_edges_sphere(&points, &triangles).unwrap();
+        let indices = compute_edge_indices(&triangles);
+        assert_eq!(edges.len(), indices.len() * 3);
+        assert_eq!(&edges[..6], &[xyz(&points, indices[0]), xyz(&points, indices[1])].concat());
+    }
+
+    #[test]
+    fn test_octahedron_duplicates_and_flat_input() {
+        let mut octahedron = vec![
+            1.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0,
+            -1.0,
+        ];
+        assert_eq!(compute_delaunay_sphere(&octahedron).unwrap().len(), 24);
+        octahedron.extend_from_slice(&[0.0, 0.0, 7.0]);
+        let triangles = compute_delaunay_sphere(&octahedron).unwrap();
+        assert_eq!(triangles.len(), 24);
+        assert!(!triangles.contains(&6), "the duplicate pole is left out");
+
+        let sphere = |p: &[f64]| code_of(compute_delaunay_sphere(p));
+        let equator: Vec<f64> = (0..12)
+            .flat_map(|i| {
+                let a = i as f64 * 0.5;
+                [a.cos(), a.sin(), 0.0]
+            })
+            .collect();
+        assert_eq!(sphere(&equator), ErrorCode::DegenerateGeometry);
+        assert_eq!(sphere(&octahedron[..9]), ErrorCode::DegenerateGeometry);
+        assert_eq!(sphere(&[0.0, 0.0]), ErrorCode::InvalidLength);
+        assert_eq!(sphere(&[0.0, 0.0, f64::NAN]), ErrorCode::NonFiniteInput);
+        assert_eq!(sphere(&[0.0; 3]), ErrorCode::DegenerateGeometry);
+        let edges = |t: &[u32]| code_of(compute_edges_sphere(&octahedron, t));
+        assert_eq!(edges(&[0, 1, 7]), ErrorCode::IndexOutOfRange);
+        let short = compute_edges_sphere(&octahedron[..8], &[]);
+        assert_eq!(code_of(short), ErrorCode::InvalidLength);
+    }
+}
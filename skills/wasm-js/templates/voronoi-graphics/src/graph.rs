@@ -0,0 +1,547 @@
+//! Graph views over the Delaunay edge set
+//!
+//! The triangulation is treated as an undirected graph whose vertices are
+//! the seed points and whose edges are the unique triangle sides. Every
+//! list returned here is in canonical order: pairs `(i, j)` with `i < j`,
+//! sorted lexicographically, so indices stay valid between calls.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::spatial::{Aabb, Grid};
+
+/// Unique undirected edges of a triangle list, canonical and sorted
+pub(crate) fn unique_edges(triangles: &[u32]) -> Vec<(u32, u32)> {
+    let mut edges = Vec::with_capacity(triangles.len());
+    for tri in triangles.chunks_exact(3) {
+        for k in 0..3 {
+            let a = tri[k];
+            let b = tri[(k + 1) % 3];
+            edges.push(if a < b { (a, b) } else { (b, a) });
+        }
+    }
+    #[cfg(feature = "parallel")]
+    rayon::slice::ParallelSliceMut::par_sort_unstable(edges.as_mut_slice());
+    #[cfg(not(feature = "parallel"))]
+    edges.sort_unstable();
+    edges.dedup();
+    edges
+}
+
+/// Edges with exactly one incident triangle (the mesh boundary),
+/// canonical and sorted
+pub(crate) fn boundary_edges(triangles: &[u32]) -> Vec<(u32, u32)> {
+    let mut edges = Vec::with_capacity(triangles.len());
+    for tri in triangles.chunks_exact(3) {
+        for k in 0..3 {
+            let (a, b) = (tri[k], tri[(k + 1) % 3]);
+            edges.push(if a < b { (a, b) } else { (b, a) });
+        }
+    }
+    edges.sort_unstable();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < edges.len() {
+        let mut j = i + 1;
+        while j < edges.len() && edges[j] == edges[i] {
+            j += 1;
+        }
+        if j - i == 1 {
+            out.push(edges[i]);
+        }
+        i = j;
+    }
+    out
+}
+
+/// Check that a triangle index list is well-formed for `point_count` points
+pub(crate) fn check_triangles(triangles: &[u32], point_count: usize) -> Result<()> {
+    if !triangles.len().is_multiple_of(3) {
+        return Err(Error::InvalidLength(format!(
+            "triangle index count {} is not a multiple of 3",
+            triangles.len()
+        )));
+    }
+    if let Some(&bad) = triangles.iter().find(|&&i| i as usize >= point_count) {
+        return Err(Error::IndexOutOfRange(format!(
+            "triangle references vertex {bad} but only {point_count} points were given"
+        )));
+    }
+    Ok(())
+}
+
+/// Check that `edge_indices` is whole `[i, j]` pairs of points below
+/// `point_count`
+pub(crate) fn check_edge_indices(edge_indices: &[u32], point_count: usize) -> Result<()> {
+    if !edge_indices.len().is_multiple_of(2) {
+        return Err(Error::InvalidLength(format!(
+            "edge index count {} is odd",
+            edge_indices.len()
+        )));
+    }
+    if let Some(&bad) = edge_indices.iter().find(|&&i| i as usize >= point_count) {
+        return Err(Error::IndexOutOfRange(format!(
+            "edge references vertex {bad} but only {point_count} points were given"
+        )));
+    }
+    Ok(())
+}
+
+/// Check that a flat coordinate array holds whole `[x, y]` pairs
+pub(crate) fn check_points(points_flat: &[f64]) -> Result<usize> {
+    if !points_flat.len().is_multiple_of(2) {
+        return Err(Error::InvalidLength(format!(
+            "point array length {} is odd",
+            points_flat.len()
+        )));
+    }
+    Ok(points_flat.len() / 2)
+}
+
+pub(crate) fn edge_length(points_flat: &[f64], a: u32, b: u32) -> f64 {
+    let (a, b) = (a as usize, b as usize);
+    let dx = points_flat[b * 2] - points_flat[a * 2];
+    let dy = points_flat[b * 2 + 1] - points_flat[a * 2 + 1];
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// Compressed vertex → neighbor lists, built from canonical edges
+#[derive(Clone, Debug)]
+pub(crate) struct Csr {
+    pub(crate) offsets: Vec<u32>,
+    pub(crate) targets: Vec<u32>,
+}
+
+impl Csr {
+    pub(crate) fn from_edges(point_count: usize, edges: &[(u32, u32)]) -> Csr {
+        let mut offsets = vec![0u32; point_count + 1];
+        for &(a, b) in edges {
+            offsets[a as usize + 1] += 1;
+            offsets[b as usize + 1] += 1;
+        }
+        for i in 0..point_count {
+            offsets[i + 1] += offsets[i];
+        }
+        let mut fill = offsets.clone();
+        let mut targets = vec![0u32; edges.len() * 2];
+        for &(a, b) in edges {
+            targets[fill[a as usize] as usize] = b;
+            fill[a as usize] += 1;
+            targets[fill[b as usize] as usize] = a;
+            fill[b as usize] += 1;
+        }
+        // Ascending neighbor lists keep every traversal deterministic
+        for i in 0..point_count {
+            targets[offsets[i] as usize..offsets[i + 1] as usize].sort_unstable();
+        }
+        Csr { offsets, targets }
+    }
+
+    #[inline]
+    pub(crate) fn neighbors(&self, v: u32) -> &[u32] {
+        &self.targets[self.offsets[v as usize] as usize..self.offsets[v as usize + 1] as usize]
+    }
+}
+
+/// Min-heap entry ordered by distance, then vertex for determinism
+#[derive(PartialEq)]
+pub(crate) struct HeapEntry(pub(crate) f64, pub(crate) u32);
+
+impl Eq for HeapEntry {}
+
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.0.total_cmp(&self.0).then(other.1.cmp(&self.1))
+    }
+}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Euclidean shortest-path distance from the nearest source to every
+/// vertex; unreachable vertices stay at infinity
+pub(crate) fn graph_distances(points_flat: &[f64], csr: &Csr, sources: &[u32]) -> Vec<f64> {
+    let n = csr.offsets.len() - 1;
+    let mut dist = vec![f64::INFINITY; n];
+    let mut heap = std::collections::BinaryHeap::new();
+    for &s in sources {
+        if dist[s as usize] > 0.0 {
+            dist[s as usize] = 0.0;
+            heap.push(HeapEntry(0.0, s));
+        }
+    }
+    while let Some(HeapEntry(d, v)) = heap.pop() {
+        if d > dist[v as usize] {
+            continue;
+        }
+        for &u in csr.neighbors(v) {
+            let nd = d + edge_length(points_flat, v, u);
+            if nd < dist[u as usize] {
+                dist[u as usize] = nd;
+                heap.push(HeapEntry(nd, u));
+            }
+        }
+    }
+    dist
+}
+
+/// Disjoint-set forest with path halving and union by size
+pub(crate) struct UnionFind {
+    parent: Vec<u32>,
+    size: Vec<u32>,
+}
+
+impl UnionFind {
+    pub(crate) fn new(n: usize) -> Self {
+        UnionFind {
+            parent: (0..n as u32).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    pub(crate) fn find(&mut self, mut x: u32) -> u32 {
+        while self.parent[x as usize] != x {
+            let grandparent = self.parent[self.parent[x as usize] as usize];
+            self.parent[x as usize] = grandparent;
+            x = grandparent;
+        }
+        x
+    }
+
+    /// Merge the sets containing `a` and `b`; false if already joined
+    pub(crate) fn union(&mut self, a: u32, b: u32) -> bool {
+        let (mut ra, mut rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return false;
+        }
+        if self.size[ra as usize] < self.size[rb as usize] {
+            std::mem::swap(&mut ra, &mut rb);
+        }
+        self.parent[rb as usize] = ra;
+        self.size[ra as usize] += self.size[rb as usize];
+        true
+    }
+}
+
+/// Kruskal over the given edges; returns a keep flag per input edge
+///
+/// Ties in length are broken by edge index so the tree is deterministic.
+/// Disconnected input yields a spanning forest.
+pub(crate) fn minimum_spanning_tree(
+    points_flat: &[f64],
+    point_count: usize,
+    edges: &[(u32, u32)],
+) -> Vec<bool> {
+    let lengths: Vec<f64> = edges
+        .iter()
+        .map(|&(a, b)| edge_length(points_flat, a, b))
+        .collect();
+    let mut order: Vec<usize> = (0..edges.len()).collect();
+    order.sort_by(|&x, &y| lengths[x].total_cmp(&lengths[y]).then(x.cmp(&y)));
+
+    let mut sets = UnionFind::new(point_count);
+    let mut in_tree = vec![false; edges.len()];
+    for ei in order {
+        let (a, b) = edges[ei];
+        if sets.union(a, b) {
+            in_tree[ei] = true;
+        }
+    }
+    in_tree
+}
+
+/// Spanning tree plus the leftover "chord" edges of a triangulation
+#[wasm_bindgen]
+pub struct TreeChordResult {
+    tree_edges: Vec<u32>,
+    chord_edges: Vec<u32>,
+    chord_stubs: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl TreeChordResult {
+    /// MST edges as `[i0, j0, i1, j1, ...]` in canonical order
+    pub fn tree_edges(&self) -> Vec<u32> {
+        self.tree_edges.clone()
+    }
+
+    /// Non-tree edges as `[i0, j0, i1, j1, ...]` in canonical order
+    pub fn chord_edges(&self) -> Vec<u32> {
+        self.chord_edges.clone()
+    }
+
+    /// Two stub segments per chord, aligned with `chord_edges()`
+    ///
+    /// Layout per chord: `[ix, iy, ix', iy', jx, jy, jx', jy']` where each
+    /// stub starts at its endpoint and points into the edge.
+    pub fn chord_stubs(&self) -> Vec<f64> {
+        self.chord_stubs.clone()
+    }
+
+    pub fn tree_count(&self) -> usize {
+        self.tree_edges.len() / 2
+    }
+
+    pub fn chord_count(&self) -> usize {
+        self.chord_edges.len() / 2
+    }
+}
+
+/// Split the Delaunay edges into MST "traces" and open-ended chords
+///
+/// Each chord gets a stub from both endpoints covering `stub_fraction` of
+/// its length. Stubs are trimmed at the midpoint, so fractions above 0.5
+/// draw the full edge and the two stubs of one chord never overlap.
+#[wasm_bindgen]
+pub fn decompose_tree_and_chords(
+    points_flat: &[f64],
+    triangles: &[u32],
+    stub_fraction: f64,
+) -> Result<TreeChordResult> {
+    let point_count = check_points(points_flat)?;
+    check_triangles(triangles, point_count)?;
+    if stub_fraction.is_nan() || stub_fraction < 0.0 {
+        return Err(Error::InvalidArgument(format!(
+            "stub fraction must be non-negative, got {stub_fraction}"
+        )));
+    }
+    let fraction = stub_fraction.min(0.5);
+
+    let edges = unique_edges(triangles);
+    let in_tree = minimum_spanning_tree(points_flat, point_count, &edges);
+
+    let chord_count = in_tree.iter().filter(|&&t| !t).count();
+    let mut tree_edges = Vec::with_capacity((edges.len() - chord_count) * 2);
+    let mut chord_edges = Vec::with_capacity(chord_count * 2);
+    let mut chord_stubs = Vec::with_capacity(chord_count * 8);
+
+    for (&(a, b), &tree) in edges.iter().zip(&in_tree) {
+        if tree {
+            tree_edges.extend_from_slice(&[a, b]);
+            continue;
+        }
+        chord_edges.extend_from_slice(&[a, b]);
+
+        let (ax, ay) = (points_flat[a as usize * 2], points_flat[a as usize * 2 + 1]);
+        let (bx, by) = (points_flat[b as usize * 2], points_flat[b as usize * 2 + 1]);
+        let (dx, dy) = ((bx - ax) * fraction, (by - ay) * fraction);
+        chord_stubs.extend_from_slice(&[ax, ay, ax + dx, ay + dy, bx, by, bx - dx, by - dy]);
+    }
+
+    Ok(TreeChordResult {
+        tree_edges,
+        chord_edges,
+        chord_stubs,
+    })
+}
+
+fn flatten(edges: impl Iterator<Item = (u32, u32)>) -> Vec<u32> {
+    edges.flat_map(|(a, b)| [a, b]).collect()
+}
+
+/// Delaunay edges whose diametral disc holds no other point
+fn gabriel(points_flat: &[f64], triangles: &[u32]) -> Vec<(u32, u32)> {
+    let xy = |v: u32| (points_flat[v as usize * 2], points_flat[v as usize * 2 + 1]);
+    // Each side with the vertex across from it, grouped by side
+    let mut sides: Vec<((u32, u32), u32)> = Vec::with_capacity(triangles.len());
+    for t in triangles.chunks_exact(3) {
+        for k in 0..3 {
+            let (a, b) = (t[k], t[(k + 1) % 3]);
+            sides.push(((a.min(b), a.max(b)), t[(k + 2) % 3]));
+        }
+    }
+    sides.sort_unstable();
+    let mut out: Vec<(u32, u32)> = Vec::new();
+    for group in sides.chunk_by(|x, y| x.0 == y.0) {
+        let (a, b) = group[0].0;
+        let ((ax, ay), (bx, by)) = (xy(a), xy(b));
+        // `c` is off the closed disc exactly when the angle acb is acute
+        let clear = group.iter().all(|&(_, c)| {
+            let (cx, cy) = xy(c);
+            (ax - cx) * (bx - cx) + (ay - cy) * (by - cy) > 0.0
+        });
+        if clear {
+            out.push((a, b));
+        }
+    }
+    out
+}
+
+/// Gabriel graph edges of a Delaunay triangulation, as index pairs
+///
+/// Keeps edge `ab` when no other point lies in or on the circle with `ab`
+/// as diameter. For Delaunay `triangles` only the two vertices facing the
+/// edge can break that, so the test is local; for other triangulations
+/// the result is a subset of theirs but may miss blockers. Pairs are in
+/// the canonical `compute_edge_indices` order.
+#[wasm_bindgen]
+pub fn gabriel_edges(points_flat: &[f64], triangles: &[u32]) -> Result<Vec<u32>> {
+    let n = check_points(points_flat)?;
+    check_triangles(triangles, n)?;
+    Ok(flatten(gabriel(points_flat, triangles).into_iter()))
+}
+
+/// Relative neighborhood graph edges of a Delaunay triangulation
+///
+/// Keeps edge `ab` when no point `c` is closer to both `a` and `b` than
+/// they are to each other. The graph is a subset of the Gabriel graph, so
+/// only Gabriel edges are tested, each against the points near its lune.
+/// Pairs are in the canonical `compute_edge_indices` order.
+#[wasm_bindgen]
+pub fn rng_edges(points_flat: &[f64], triangles: &[u32]) -> Result<Vec<u32>> {
+    let n = check_points(points_flat)?;
+    check_triangles(triangles, n)?;
+    let xy = |v: u32| (points_flat[v as usize * 2], points_flat[v as usize * 2 + 1]);
+    let boxes: Vec<Aabb> = (0..n as u32).map(xy).map(|(x, y)| [x, y, x, y]).collect();
+    let mut grid = Grid::fitted(&boxes);
+    for (v, b) in boxes.iter().enumerate() {
+        grid.insert(v as u32, b);
+    }
+    let mut near = Vec::new();
+    let kept = gabriel(points_flat, triangles).into_iter().filter(|&(a, b)| {
+        let ((ax, ay), (bx, by)) = (xy(a), xy(b));
+        let d2 = (bx - ax).powi(2) + (by - ay).powi(2);
+        // The lune lies within sqrt(3)/2 of the edge's midpoint
+        let (mx, my, reach) = ((ax + bx) / 2.0, (ay + by) / 2.0, 0.87 * d2.sqrt());
+        grid.query(&[mx - reach, my - reach, mx + reach, my + reach], &mut near);
+        !near.iter().any(|&c| {
+            let (cx, cy) = xy(c);
+            let to_a = (cx - ax).powi(2) + (cy - ay).powi(2);
+            let to_b = (cx - bx).powi(2) + (cy - by).powi(2);
+            to_a.max(to_b) < d2
+        })
+    });
+    Ok(flatten(kept))
+}
+
+/// Euclidean minimum spanning tree over the Delaunay edges
+///
+/// Kruskal with ties broken by edge order, so the tree is deterministic;
+/// it is the `decompose_tree_and_chords` tree. A connected triangulation
+/// of `n` points gives `n - 1` edges, otherwise a spanning forest. Pairs
+/// are in the canonical `compute_edge_indices` order.
+#[wasm_bindgen]
+pub fn mst_edges(points_flat: &[f64], triangles: &[u32]) -> Result<Vec<u32>> {
+    let n = check_points(points_flat)?;
+    check_triangles(triangles, n)?;
+    let edges = unique_edges(triangles);
+    let in_tree = minimum_spanning_tree(points_flat, n, &edges);
+    Ok(flatten(edges.into_iter().zip(in_tree).filter(|&(_, t)| t).map(|(e, _)| e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::{compute_delaunay, generate_golden_seeds};
+
+    #[test]
+    fn test_unique_edges_sorted() {
+        let edges = unique_edges(&[0, 1, 2, 2, 1, 3]);
+        assert_eq!(edges, vec![(0, 1), (0, 2), (1, 2), (1, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn test_mst_spans_all_points() {
+        let points = generate_golden_seeds(800.0, 600.0, 60);
+        let triangles = compute_delaunay(&points, 800.0, 600.0);
+        let edges = unique_edges(&triangles);
+        let in_tree = minimum_spanning_tree(&points, 60, &edges);
+        assert_eq!(in_tree.iter().filter(|&&t| t).count(), 59);
+
+        let mut sets = UnionFind::new(60);
+        for (&(a, b), _) in edges.iter().zip(&in_tree).filter(|(_, &t)| t) {
+            sets.union(a, b);
+        }
+        let root = sets.find(0);
+        assert!((0..60).all(|i| sets.find(i) == root));
+    }
+
+    #[test]
+    fn test_tree_and_chords_partition_edges() {
+        let points = generate_golden_seeds(800.0, 600.0, 40);
+        let triangles = compute_delaunay(&points, 800.0, 600.0);
+        let result = decompose_tree_and_chords(&points, &triangles, 0.2).unwrap();
+
+        let total = unique_edges(&triangles).len();
+        assert_eq!(result.tree_count(), 39);
+        assert_eq!(result.tree_count() + result.chord_count(), total);
+        assert_eq!(result.chord_stubs().len(), result.chord_count() * 8);
+
+        // Deterministic across calls
+        let again = decompose_tree_and_chords(&points, &triangles, 0.2).unwrap();
+        assert_eq!(result.chord_edges(), again.chord_edges());
+    }
+
+    #[test]
+    fn test_stubs_trimmed_at_midpoint() {
+        // Square with one diagonal: the diagonal and the last tied side are chords
+        let points = [0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0];
+        let triangles = [0, 1, 2, 0, 2, 3];
+        let result = decompose_tree_and_chords(&points, &triangles, 0.9).unwrap();
+        assert_eq!(result.chord_edges(), vec![0, 2, 2, 3]);
+        let stubs = result.chord_stubs();
+        assert_eq!(&stubs[2..4], &[5.0, 5.0]);
+        assert_eq!(&stubs[6..8], &[5.0, 5.0]);
+    }
+
+    #[test]
+    fn test_decompose_rejects_bad_indices() {
+        let points = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+        assert_eq!(
+            code_of(decompose_tree_and_chords(&points, &[0, 1, 5], 0.2)),
+            ErrorCode::IndexOutOfRange
+        );
+        assert_eq!(
+            code_of(decompose_tree_and_chords(&points[..5], &[0, 1, 2], 0.2)),
+            ErrorCode::InvalidLength
+        );
+        assert_eq!(
+            code_of(decompose_tree_and_chords(&points, &[0, 1, 2], f64::NAN)),
+            ErrorCode::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_proximity_graphs_nest_inside_delaunay() {
+        let points = crate::generate_poisson_seeds(600.0, 400.0, 25.0, 3, None).unwrap();
+        let n = points.len() / 2;
+        let triangles = compute_delaunay(&points, 600.0, 400.0);
+        let pairs = |flat: Vec<u32>| -> Vec<(u32, u32)> {
+            flat.chunks(2).map(|e| (e[0], e[1])).collect()
+        };
+        let delaunay = unique_edges(&triangles);
+        let gabriel = pairs(gabriel_edges(&points, &triangles).unwrap());
+        let rng = pairs(rng_edges(&points, &triangles).unwrap());
+        let mst = pairs(mst_edges(&points, &triangles).unwrap());
+        let subset = |a: &[(u32, u32)], b: &[(u32, u32)]| a.iter().all(|e| b.contains(e));
+        assert!(subset(&mst, &rng) && subset(&rng, &gabriel) && subset(&gabriel, &delaunay));
+        let sizes = [mst.len(), rng.len(), gabriel.len(), delaunay.len()];
+        assert!(sizes.windows(2).all(|w| w[0] < w[1]), "{sizes:?}");
+
+        // Brute-force the definitions
+        let xy = |v: u32| (points[v as usize * 2], points[v as usize * 2 + 1]);
+        let d2 = |p: (f64, f64), q: (f64, f64)| (p.0 - q.0).powi(2) + (p.1 - q.1).powi(2);
+        for &(a, b) in &delaunay {
+            let (pa, pb) = (xy(a), xy(b));
+            let others = (0..n as u32).filter(|&c| c != a && c != b).map(xy);
+            let mid = ((pa.0 + pb.0) / 2.0, (pa.1 + pb.1) / 2.0);
+            let in_disc = others.clone().any(|c| d2(c, mid) <= d2(pa, pb) / 4.0);
+            assert_eq!(gabriel.contains(&(a, b)), !in_disc, "gabriel ({a}, {b})");
+            let in_lune = others.clone().any(|c| d2(c, pa).max(d2(c, pb)) < d2(pa, pb));
+            assert_eq!(rng.contains(&(a, b)), !in_lune, "rng ({a}, {b})");
+        }
+
+        assert_eq!(mst.len(), n - 1);
+        let mut sets = UnionFind::new(n);
+        for &(a, b) in &mst {
+            assert!(sets.union(a, b), "cycle through ({a}, {b})");
+        }
+        let tree = decompose_tree_and_chords(&points, &triangles, 0.0).unwrap();
+        assert_eq!(tree.tree_edges(), mst.iter().flat_map(|&(a, b)| [a, b]).collect::<Vec<_>>());
+        assert_eq!(code_of(rng_edges(&points, &[0, 1])), ErrorCode::InvalidLength);
+    }
+}
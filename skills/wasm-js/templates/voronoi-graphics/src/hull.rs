@@ -0,0 +1,243 @@
+//! Outer boundaries: the convex hull of a point set and the boundary
+//! edges and loops of a triangle list
+//!
+//! `compute_hull` works on the points alone, so it does not need a
+//! triangulation and is exact for any input thanks to `orient2d`.
+//! `compute_boundary_edges` and `compute_boundary_loops` work on triangles
+//! alone, so they also follow concave outlines and holes once triangles
+//! have been filtered out.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{boundary_edges, check_points};
+use crate::predicates::orient2d;
+use crate::Point;
+
+/// Convex hull of `[x0, y0, x1, y1, ...]` as point indices, CCW
+///
+/// Computed by Andrew's monotone chain. The loop starts at the point with
+/// the smallest x (then smallest y) and is not closed. Only strict
+/// corners are included: points lying on a hull side between two corners
+/// are left out. Of several points at the same coordinates, the lowest
+/// index stands for all of them. Fewer than three distinct points give
+/// those points, and a collinear set gives its two extremes.
+#[wasm_bindgen]
+pub fn compute_hull(points_flat: &[f64]) -> Result<Vec<u32>> {
+    check_points(points_flat)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let points: Vec<Point> = points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+    let mut order: Vec<u32> = (0..points.len() as u32).collect();
+    // Adding 0 folds -0 into +0, so signed zeros count as one coordinate
+    let key = |v: u32| (points[v as usize].x + 0.0, points[v as usize].y + 0.0);
+    order.sort_by(|&a, &b| {
+        let ((px, py), (qx, qy)) = (key(a), key(b));
+        px.total_cmp(&qx).then(py.total_cmp(&qy)).then(a.cmp(&b))
+    });
+    // Equal coordinates are adjacent now, lowest index first
+    order.dedup_by(|b, a| points[*a as usize] == points[*b as usize]);
+    if order.len() < 3 {
+        return Ok(order);
+    }
+
+    let mut hull: Vec<u32> = Vec::with_capacity(order.len() + 1);
+    let chain = |hull: &mut Vec<u32>, floor: usize, v: u32| {
+        while hull.len() >= floor + 2 {
+            let (a, b) = (hull[hull.len() - 2], hull[hull.len() - 1]);
+            if orient2d(points[a as usize], points[b as usize], points[v as usize]) > 0.0 {
+                break;
+            }
+            hull.pop();
+        }
+        hull.push(v);
+    };
+    for &v in &order {
+        chain(&mut hull, 0, v);
+    }
+    let lower = hull.len() - 1;
+    for &v in order.iter().rev().skip(1) {
+        chain(&mut hull, lower, v);
+    }
+    // The upper chain ends back at the first point
+    hull.pop();
+    Ok(hull)
+}
+
+/// Edges used by exactly one triangle, as `[i0, j0, i1, j1, ...]`
+///
+/// For a full Delaunay triangulation these are the convex hull sides
+/// (collinear hull points included, since each splits a side); after
+/// triangles have been filtered out they also trace concavities and
+/// holes. Pairs are in the canonical `compute_edge_indices` order.
+#[wasm_bindgen]
+pub fn compute_boundary_edges(triangles: &[u32]) -> Vec<u32> {
+    let edges = profiled!("boundary", boundary_edges(triangles));
+    edges.into_iter().flat_map(|(a, b)| [a, b]).collect()
+}
+
+/// Closed boundary loops of a triangle list
+#[wasm_bindgen]
+pub struct BoundaryLoops {
+    vertices: Vec<u32>,
+    offsets: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl BoundaryLoops {
+    /// Point indices of every loop, back to back; loops are not closed
+    pub fn vertices(&self) -> Vec<u32> {
+        self.vertices.clone()
+    }
+
+    /// Start of each loop in `vertices()`, plus a final end entry
+    pub fn offsets(&self) -> Vec<u32> {
+        self.offsets.clone()
+    }
+
+    pub fn loop_count(&self) -> usize {
+        self.offsets.len() - 1
+    }
+}
+
+/// Boundary edges of CCW `triangles` chained into loops
+///
+/// Each loop keeps the triangles on its left, so the outline of a region
+/// runs counter-clockwise and the rim of a hole clockwise. Where two loops
+/// touch at one vertex, which edges go to which loop is arbitrary, but
+/// every loop is still closed. Loops come in order of their smallest
+/// directed edge, so the output depends only on the topology.
+#[wasm_bindgen]
+pub fn compute_boundary_loops(triangles: &[u32]) -> BoundaryLoops {
+    let (vertices, offsets) = boundary_loops(triangles);
+    BoundaryLoops { vertices, offsets }
+}
+
+/// `(vertices, offsets)` for `compute_boundary_loops`
+pub(crate) fn boundary_loops(triangles: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let mut directed: Vec<(u32, u32)> = triangles
+        .chunks_exact(3)
+        .flat_map(|t| [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])])
+        .collect();
+    directed.sort_unstable();
+    directed.dedup();
+    let mut boundary: Vec<(u32, u32)> = directed
+        .iter()
+        .filter(|&&(a, b)| directed.binary_search(&(b, a)).is_err())
+        .copied()
+        .collect();
+    boundary.sort_unstable();
+
+    let mut used = vec![false; boundary.len()];
+    let (mut vertices, mut offsets) = (Vec::new(), vec![0u32]);
+    for first in 0..boundary.len() {
+        if used[first] {
+            continue;
+        }
+        let start = boundary[first].0;
+        let mut edge = first;
+        loop {
+            used[edge] = true;
+            let (a, b) = boundary[edge];
+            vertices.push(a);
+            if b == start {
+                break;
+            }
+            // Lowest-numbered unused edge leaving `b`
+            let from = boundary.partition_point(|&(u, _)| u < b);
+            let next = (from..boundary.len())
+                .take_while(|&e| boundary[e].0 == b)
+                .find(|&e| !used[e]);
+            match next {
+                Some(e) => edge = e,
+                // Only reachable for a non-manifold mesh; end the loop here
+                None => {
+                    vertices.push(b);
+                    break;
+                }
+            }
+        }
+        offsets.push(vertices.len() as u32);
+    }
+    (vertices, offsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::{compute_delaunay, generate_golden_seeds};
+
+    #[test]
+    fn test_hull_skips_collinear_interior_and_duplicate_points() {
+        #[rustfmt::skip]
+        let points = [
+            5.0, 5.0,   // 0 interior
+            10.0, 0.0,  // 1 corner
+            5.0, 0.0,   // 2 on the bottom side
+            10.0, 10.0, // 3 corner
+            0.0, 10.0,  // 4 corner
+            0.0, 0.0,   // 5 corner
+            10.0, 0.0,  // 6 duplicate of 1
+            0.0, 5.0,   // 7 on the left side
+            -0.0, 0.0,  // 8 duplicate of 5
+        ];
+        assert_eq!(compute_hull(&points).unwrap(), vec![5, 1, 3, 4]);
+        assert_eq!(compute_hull(&[1.0, 1.0, 3.0, 3.0, 2.0, 2.0]).unwrap(), vec![0, 1]);
+        assert_eq!(compute_hull(&[1.0, 1.0, 1.0, 1.0]).unwrap(), vec![0]);
+        assert!(compute_hull(&[]).unwrap().is_empty());
+        assert_eq!(code_of(compute_hull(&[0.0, 0.0, 1.0])), ErrorCode::InvalidLength);
+        assert_eq!(code_of(compute_hull(&[0.0, f64::NAN])), ErrorCode::NonFiniteInput);
+    }
+
+    #[test]
+    fn test_hull_matches_the_triangulation_boundary() {
+        let points = generate_golden_seeds(800.0, 600.0, 200);
+        let hull = compute_hull(&points).unwrap();
+        let xy = |v: u32| Point::new(points[v as usize * 2], points[v as usize * 2 + 1]);
+        for (i, &v) in hull.iter().enumerate() {
+            let (w, u) = (hull[(i + 1) % hull.len()], hull[(i + 2) % hull.len()]);
+            assert!(orient2d(xy(v), xy(w), xy(u)) > 0.0);
+        }
+
+        let triangles = compute_delaunay(&points, 800.0, 600.0);
+        let boundary = compute_boundary_edges(&triangles);
+        let mut sides: Vec<(u32, u32)> =
+            (0..hull.len()).map(|i| (hull[i], hull[(i + 1) % hull.len()])).collect();
+        for side in &mut sides {
+            *side = (side.0.min(side.1), side.0.max(side.1));
+        }
+        sides.sort_unstable();
+        let pairs: Vec<(u32, u32)> = boundary.chunks(2).map(|e| (e[0], e[1])).collect();
+        assert_eq!(pairs, sides);
+        let mut single = triangles[..3].to_vec();
+        single.sort_unstable();
+        let (a, b, c) = (single[0], single[1], single[2]);
+        assert_eq!(compute_boundary_edges(&triangles[..3]), vec![a, b, a, c, b, c]);
+    }
+
+    #[test]
+    fn test_boundary_loops_follow_outlines_and_holes() {
+        // A 4x4 grid of points with the middle square (5, 6, 10, 9) left out
+        let mut triangles = Vec::new();
+        for y in 0..3u32 {
+            for x in 0..3u32 {
+                if (x, y) != (1, 1) {
+                    let v = y * 4 + x;
+                    triangles.extend([v, v + 1, v + 5, v, v + 5, v + 4]);
+                }
+            }
+        }
+        let loops = compute_boundary_loops(&triangles);
+        assert_eq!(loops.loop_count(), 2);
+        assert_eq!(loops.offsets(), vec![0, 12, 16]);
+        let vertices = loops.vertices();
+        // The outline runs CCW from the corner, the hole CW
+        assert_eq!(&vertices[..12], &[0, 1, 2, 3, 7, 11, 15, 14, 13, 12, 8, 4]);
+        assert_eq!(&vertices[12..], &[5, 9, 10, 6]);
+
+        let empty = compute_boundary_loops(&[]);
+        assert_eq!((empty.loop_count(), empty.vertices().len()), (0, 0));
+    }
+}
@@ -0,0 +1,238 @@
+//! Harmonic interpolation of sparse vertex samples over the Delaunay graph
+//!
+//! Unknown vertices solve the graph Laplace equation: each ends up at the
+//! weighted mean of its neighbours, with weight `1 / length` per edge, while
+//! the sampled vertices stay fixed. The system is relaxed by Gauss–Seidel
+//! sweeps in ascending vertex order, so results are deterministic.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles, edge_length, unique_edges, Csr, UnionFind};
+
+/// Edges shorter than this weigh as if they were this long, so coincident
+/// points do not produce infinite weights
+const MIN_EDGE_LENGTH: f64 = 1e-9;
+
+/// Per-vertex field filled in by `solve_laplace_field`
+#[wasm_bindgen]
+pub struct LaplaceField {
+    values: Vec<f32>,
+    unconstrained: Vec<u32>,
+    residual: f64,
+    iterations: u32,
+    converged: bool,
+}
+
+#[wasm_bindgen]
+impl LaplaceField {
+    /// One value per vertex; the sampled ones are returned unchanged
+    pub fn values(&self) -> Vec<f32> {
+        self.values.clone()
+    }
+
+    /// Vertices in components without a single sample, ascending; they are
+    /// set to 0
+    pub fn unconstrained_vertices(&self) -> Vec<u32> {
+        self.unconstrained.clone()
+    }
+
+    /// Largest correction of the final sweep, which is the largest gap
+    /// between a vertex and the weighted mean of its neighbours seen during
+    /// it
+    pub fn residual(&self) -> f64 {
+        self.residual
+    }
+
+    /// Sweeps performed
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    /// Whether the residual reached `tolerance` within the sweep limit
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
+}
+
+/// Smooth field over every vertex that interpolates the samples
+///
+/// `known_indices[k]` is pinned to `known_values[k]`. Other vertices
+/// start at the mean sample of their connected component and are relaxed
+/// for at most `iterations` sweeps, stopping once the residual is at most
+/// `tolerance`. Components without samples have no solution; they are
+/// left at 0 and listed by `unconstrained_vertices`.
+#[wasm_bindgen]
+pub fn solve_laplace_field(
+    points_flat: &[f64],
+    triangles: &[u32],
+    known_indices: &[u32],
+    known_values: &[f64],
+    iterations: u32,
+    tolerance: f64,
+) -> Result<LaplaceField> {
+    let n = check_points(points_flat)?;
+    check_triangles(triangles, n)?;
+    if known_indices.len() != known_values.len() {
+        return Err(Error::InvalidLength(format!(
+            "{} known indices but {} known values",
+            known_indices.len(),
+            known_values.len()
+        )));
+    }
+    if tolerance.is_nan() || tolerance < 0.0 {
+        return Err(Error::InvalidArgument(format!(
+            "tolerance must be non-negative, got {tolerance}"
+        )));
+    }
+    let mut known = vec![false; n];
+    let mut value = vec![0.0f64; n];
+    for (&i, &v) in known_indices.iter().zip(known_values) {
+        if i as usize >= n {
+            return Err(Error::IndexOutOfRange(format!(
+                "known index {i} out of range for {n} points"
+            )));
+        }
+        if !v.is_finite() {
+            return Err(Error::NonFiniteInput(format!("known value must be finite, got {v}")));
+        }
+        if known[i as usize] {
+            return Err(Error::InvalidArgument(format!("vertex {i} is sampled twice")));
+        }
+        known[i as usize] = true;
+        value[i as usize] = v;
+    }
+
+    let edges = unique_edges(triangles);
+    let csr = Csr::from_edges(n, &edges);
+    let weight: Vec<f64> = (0..n as u32)
+        .flat_map(|v| csr.neighbors(v).iter().map(move |&u| (v, u)))
+        .map(|(v, u)| 1.0 / edge_length(points_flat, v, u).max(MIN_EDGE_LENGTH))
+        .collect();
+
+    // Mean sample per component seeds the free vertices
+    let mut sets = UnionFind::new(n);
+    for &(a, b) in &edges {
+        sets.union(a, b);
+    }
+    let root: Vec<usize> = (0..n as u32).map(|v| sets.find(v) as usize).collect();
+    let (mut sum, mut count) = (vec![0.0; n], vec![0u32; n]);
+    for v in (0..n).filter(|&v| known[v]) {
+        sum[root[v]] += value[v];
+        count[root[v]] += 1;
+    }
+    let mut free = Vec::new();
+    let mut unconstrained = Vec::new();
+    for v in (0..n).filter(|&v| !known[v]) {
+        let r = root[v];
+        if count[r] == 0 {
+            unconstrained.push(v as u32);
+        } else {
+            value[v] = sum[r] / count[r] as f64;
+            free.push(v as u32);
+        }
+    }
+
+    let mut residual = 0.0;
+    let mut sweeps = 0;
+    // A sweep with nothing to move is already converged
+    let mut converged = free.is_empty();
+    while !converged && sweeps < iterations {
+        residual = 0.0f64;
+        for &v in &free {
+            let start = csr.offsets[v as usize] as usize;
+            let (mut acc, mut total) = (0.0, 0.0);
+            for (k, &u) in csr.neighbors(v).iter().enumerate() {
+                acc += weight[start + k] * value[u as usize];
+                total += weight[start + k];
+            }
+            let next = acc / total;
+            residual = residual.max((next - value[v as usize]).abs());
+            value[v as usize] = next;
+        }
+        sweeps += 1;
+        converged = residual <= tolerance;
+    }
+
+    Ok(LaplaceField {
+        values: value.iter().map(|&v| v as f32).collect(),
+        unconstrained,
+        residual,
+        iterations: sweeps,
+        converged,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    /// Unit lattice of `cols × rows` corners plus a centre vertex in every
+    /// square, fanned into four triangles; corners are `r * cols + c`
+    fn centred_lattice(cols: u32, rows: u32) -> (Vec<f64>, Vec<u32>) {
+        let mut points: Vec<f64> =
+            (0..rows * cols).flat_map(|v| [(v % cols) as f64, (v / cols) as f64]).collect();
+        let mut triangles = Vec::new();
+        for r in 0..rows - 1 {
+            for c in 0..cols - 1 {
+                let centre = (points.len() / 2) as u32;
+                points.extend_from_slice(&[c as f64 + 0.5, r as f64 + 0.5]);
+                let v = r * cols + c;
+                let ring = [v, v + 1, v + cols + 1, v + cols];
+                for k in 0..4 {
+                    triangles.extend_from_slice(&[ring[k], ring[(k + 1) % 4], centre]);
+                }
+            }
+        }
+        (points, triangles)
+    }
+
+    #[test]
+    fn test_linear_field_between_constrained_columns() {
+        // Both diagonals meet at every centre, so the graph is symmetric in
+        // x around each vertex and the linear ramp is exactly harmonic
+        let (cols, rows) = (11, 5);
+        let (points, triangles) = centred_lattice(cols, rows);
+        let (mut indices, mut values) = (Vec::new(), Vec::new());
+        for r in 0..rows {
+            indices.extend_from_slice(&[r * cols, r * cols + cols - 1]);
+            values.extend_from_slice(&[0.0, 1.0]);
+        }
+        let field = solve_laplace_field(&points, &triangles, &indices, &values, 10_000, 1e-10)
+            .unwrap();
+        assert!(field.converged() && field.residual() <= 1e-10);
+        assert!(field.iterations() > 1 && field.unconstrained_vertices().is_empty());
+        for (p, &v) in points.chunks(2).zip(&field.values()) {
+            let expected = p[0] / (cols - 1) as f64;
+            assert!((v as f64 - expected).abs() < 1e-5, "at {p:?}: {v} vs {expected}");
+        }
+
+        // A sweep limit is honoured and reported
+        let short = solve_laplace_field(&points, &triangles, &indices, &values, 3, 0.0).unwrap();
+        assert!(!short.converged() && short.iterations() == 3 && short.residual() > 0.0);
+    }
+
+    #[test]
+    fn test_unsampled_components_default_to_zero() {
+        // Two separate triangles plus an isolated vertex 6
+        let points = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 5.0, 5.0, 6.0, 5.0, 5.0, 6.0, 9.0, 9.0];
+        let triangles = [0, 1, 2, 3, 4, 5];
+        let field = solve_laplace_field(&points, &triangles, &[0, 1], &[2.0, 4.0], 100, 1e-9)
+            .unwrap();
+        assert_eq!(field.unconstrained_vertices(), vec![3, 4, 5, 6]);
+        let values = field.values();
+        assert_eq!(&values[..2], &[2.0, 4.0]);
+        assert!(values[2] > 2.0 && values[2] < 4.0);
+        assert_eq!(&values[3..], &[0.0; 4]);
+
+        let solve = |i: &[u32], v: &[f64], tol: f64| {
+            code_of(solve_laplace_field(&points, &triangles, i, v, 10, tol))
+        };
+        assert_eq!(solve(&[0], &[1.0, 2.0], 0.0), ErrorCode::InvalidLength);
+        assert_eq!(solve(&[7], &[1.0], 0.0), ErrorCode::IndexOutOfRange);
+        assert_eq!(solve(&[0], &[f64::NAN], 0.0), ErrorCode::NonFiniteInput);
+        assert_eq!(solve(&[0, 0], &[1.0, 2.0], 0.0), ErrorCode::InvalidArgument);
+        assert_eq!(solve(&[0], &[1.0], -1.0), ErrorCode::InvalidArgument);
+    }
+}
@@ -0,0 +1,235 @@
+//! Parallax layers generated and triangulated in one call
+//!
+//! Every layer is a golden spiral of its own size and density, jittered
+//! and triangulated on its own, but all of them live in shared buffers
+//! indexed by per-layer offsets, so a resize costs one boundary crossing
+//! however many layers the scene has.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::unique_edges;
+use crate::mesh::Mesh;
+use crate::rng::Rng;
+use crate::{Point, GOLDEN_ANGLE};
+
+/// Values per layer in `generate_layers`' `layer_params`
+const LAYER_STRIDE: usize = 4;
+
+/// Seeds one `generate_layers` call lays out across all layers
+const MAX_LAYER_SEEDS: f64 = (1u64 << 22) as f64;
+
+/// Points, triangles and edges of every layer from `generate_layers`
+///
+/// Indices in `triangles(k)` and `edges(k)` are local to layer `k`, so
+/// each layer binds as its own vertex and index buffer pair.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct LayerSet {
+    /// `[x, y, z]` per seed, all layers back to back
+    points: Vec<f32>,
+    triangles: Vec<u32>,
+    edges: Vec<u32>,
+    /// Where each layer starts in the three buffers, in seeds, triangle
+    /// indices and edge indices, with a final entry for the end
+    point_offsets: Vec<usize>,
+    triangle_offsets: Vec<usize>,
+    edge_offsets: Vec<usize>,
+}
+
+impl LayerSet {
+    fn check_layer(&self, layer: usize) -> Result<()> {
+        let count = self.layer_count();
+        if layer >= count {
+            return Err(Error::IndexOutOfRange(format!(
+                "layer {layer} out of range for {count} layers"
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl LayerSet {
+    /// `[x0, y0, z0, x1, y1, z1, ...]` seeds of `layer`, `z` being its
+    /// depth
+    pub fn points(&self, layer: usize) -> Result<Vec<f32>> {
+        self.check_layer(layer)?;
+        let range = self.point_offsets[layer] * 3..self.point_offsets[layer + 1] * 3;
+        Ok(self.points[range].to_vec())
+    }
+
+    /// `[a0, b0, c0, a1, ...]` Delaunay triangles of `layer`,
+    /// counter-clockwise
+    pub fn triangles(&self, layer: usize) -> Result<Vec<u32>> {
+        self.check_layer(layer)?;
+        Ok(self.triangles[self.triangle_offsets[layer]..self.triangle_offsets[layer + 1]].to_vec())
+    }
+
+    /// `[i0, j0, i1, j1, ...]` unique edges of `layer`, sorted as
+    /// `compute_edge_indices` sorts them
+    pub fn edges(&self, layer: usize) -> Result<Vec<u32>> {
+        self.check_layer(layer)?;
+        Ok(self.edges[self.edge_offsets[layer]..self.edge_offsets[layer + 1]].to_vec())
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.point_offsets.len() - 1
+    }
+
+    pub fn point_count(&self, layer: usize) -> Result<usize> {
+        self.check_layer(layer)?;
+        Ok(self.point_offsets[layer + 1] - self.point_offsets[layer])
+    }
+}
+
+/// Seeds, triangles and edges for several parallax layers at once
+///
+/// `layer_params` holds `[count, radius_scale, jitter, z_depth]` per layer.
+/// A layer is `count` golden-spiral seeds in a disc `radius_scale` times
+/// the `generate_golden_seeds` radius, so values above 1 spill past the
+/// canvas. Each seed then moves by up to `jitter` times half the mean seed
+/// spacing in a random direction, `jitter` being in `[0, 1]`; the layers
+/// draw from independent streams of `seed`, so the whole set is fixed by
+/// it. `z_depth` is copied into every seed of the layer. Errors on
+/// ragged or out-of-range parameters, and when the layers add up to more
+/// than 4M seeds.
+#[wasm_bindgen]
+pub fn generate_layers(
+    width: f64,
+    height: f64,
+    layer_params: &[f64],
+    seed: u32,
+) -> Result<LayerSet> {
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if !layer_params.len().is_multiple_of(LAYER_STRIDE) {
+        return Err(Error::InvalidLength(format!(
+            "layer params length {} is not a multiple of {LAYER_STRIDE}",
+            layer_params.len()
+        )));
+    }
+    let mut total = 0.0;
+    for (k, p) in layer_params.chunks_exact(LAYER_STRIDE).enumerate() {
+        let [count, radius_scale, jitter, z] = [p[0], p[1], p[2], p[3]];
+        if !(count >= 0.0 && count.fract() == 0.0) {
+            return Err(Error::InvalidArgument(format!(
+                "layer {k}: count must be a non-negative integer, got {count}"
+            )));
+        }
+        if !(radius_scale.is_finite() && radius_scale > 0.0) {
+            return Err(Error::InvalidArgument(format!(
+                "layer {k}: radius_scale must be positive, got {radius_scale}"
+            )));
+        }
+        if !(0.0..=1.0).contains(&jitter) {
+            return Err(Error::InvalidArgument(format!(
+                "layer {k}: jitter must be in [0, 1], got {jitter}"
+            )));
+        }
+        if !z.is_finite() {
+            return Err(Error::NonFiniteInput(format!("layer {k}: z_depth is {z}")));
+        }
+        total += count;
+    }
+    if total > MAX_LAYER_SEEDS {
+        return Err(Error::TooLarge(format!("{total} seeds across all layers are too many")));
+    }
+
+    let mut set = LayerSet {
+        points: Vec::with_capacity(total as usize * 3),
+        triangles: Vec::new(),
+        edges: Vec::new(),
+        point_offsets: vec![0],
+        triangle_offsets: vec![0],
+        edge_offsets: vec![0],
+    };
+    let (cx, cy) = (width / 2.0, height / 2.0);
+    let mut seeds = Vec::new();
+    for (k, p) in layer_params.chunks_exact(LAYER_STRIDE).enumerate() {
+        let (count, radius, jitter, z) = (p[0] as usize, p[1] * cx.min(cy) * 0.85, p[2], p[3]);
+        let reach = jitter * radius * (std::f64::consts::PI / count.max(1) as f64).sqrt() / 2.0;
+        let mut rng = Rng::new(((seed as u64) << 32) | k as u64);
+        seeds.clear();
+        seeds.extend((0..count).map(|n| {
+            let theta = n as f64 * GOLDEN_ANGLE;
+            let r = radius * (n as f64 / count as f64).sqrt();
+            // Uniform over the disc of radius `reach`
+            let (d, phi) = (reach * rng.next_f64().sqrt(), rng.range(0.0, std::f64::consts::TAU));
+            Point::new(cx + r * theta.cos() + d * phi.cos(), cy + r * theta.sin() + d * phi.sin())
+        }));
+        set.points.extend(seeds.iter().flat_map(|s| [s.x as f32, s.y as f32, z as f32]));
+
+        let start = set.triangles.len();
+        set.triangles.extend(Mesh::build_closed(&seeds).real_triangles().flatten());
+        let edges = unique_edges(&set.triangles[start..]);
+        set.edges.extend(edges.into_iter().flat_map(|(a, b)| [a, b]));
+
+        set.point_offsets.push(set.point_offsets[k] + count);
+        set.triangle_offsets.push(set.triangles.len());
+        set.edge_offsets.push(set.edges.len());
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::{compute_delaunay, compute_edge_indices, generate_golden_seeds};
+
+    #[test]
+    fn test_layers_match_separate_generation() {
+        let (w, h) = (800.0, 600.0);
+        let params = [300.0, 1.0, 0.0, 0.0, 120.0, 1.5, 0.6, -2.5, 40.0, 0.5, 1.0, 1.0];
+        let set = generate_layers(w, h, &params, 7).unwrap();
+        assert_eq!(set.layer_count(), 3);
+
+        // Without jitter a unit-scale layer is `generate_golden_seeds`
+        let plain = generate_golden_seeds(w, h, 300);
+        let points = set.points(0).unwrap();
+        assert_eq!(points.len(), 900);
+        for (p, q) in points.chunks(3).zip(plain.chunks(2)) {
+            assert_eq!([p[0], p[1], p[2]], [q[0] as f32, q[1] as f32, 0.0]);
+        }
+        assert_eq!(set.triangles(0).unwrap(), compute_delaunay(&plain, w, h));
+
+        for layer in 0..3 {
+            let n = set.point_count(layer).unwrap();
+            let (points, triangles) = (set.points(layer).unwrap(), set.triangles(layer).unwrap());
+            assert_eq!(points.len(), n * 3);
+            assert!(points.iter().skip(2).step_by(3).all(|&z| z == params[layer * 4 + 3] as f32));
+            assert!(triangles.iter().all(|&v| (v as usize) < n));
+            assert!(triangles.len() / 3 > n);
+            assert_eq!(set.edges(layer).unwrap(), compute_edge_indices(&triangles));
+        }
+        // The jittered layers are off the spiral but fixed by the seed
+        let spiral = crate::generate_golden_seeds_at(400.0, 300.0, 1.5 * 255.0, 120, 0);
+        let moved = set.points(1).unwrap();
+        assert!(moved.chunks(3).zip(spiral.chunks(2)).any(|(p, q)| p[0] != q[0] as f32));
+        let again = generate_layers(w, h, &params, 7).unwrap();
+        assert_eq!(again.points(1).unwrap(), moved);
+        assert_ne!(generate_layers(w, h, &params, 8).unwrap().points(1).unwrap(), moved);
+    }
+
+    #[test]
+    fn test_layer_errors() {
+        let layers = |p: &[f64]| code_of(generate_layers(100.0, 100.0, p, 0));
+        assert_eq!(layers(&[10.0, 1.0, 0.0]), ErrorCode::InvalidLength);
+        assert_eq!(layers(&[10.5, 1.0, 0.0, 0.0]), ErrorCode::InvalidArgument);
+        assert_eq!(layers(&[-1.0, 1.0, 0.0, 0.0]), ErrorCode::InvalidArgument);
+        assert_eq!(layers(&[10.0, 0.0, 0.0, 0.0]), ErrorCode::InvalidArgument);
+        assert_eq!(layers(&[10.0, 1.0, 1.5, 0.0]), ErrorCode::InvalidArgument);
+        assert_eq!(layers(&[10.0, 1.0, 0.0, f64::NAN]), ErrorCode::NonFiniteInput);
+        assert_eq!(layers(&[1e9, 1.0, 0.0, 0.0]), ErrorCode::TooLarge);
+        assert_eq!(code_of(generate_layers(0.0, 1.0, &[], 0)), ErrorCode::InvalidArgument);
+
+        let set = generate_layers(100.0, 100.0, &[0.0, 1.0, 0.0, 0.0, 3.0, 1.0, 0.0, 0.0], 0);
+        let set = set.unwrap();
+        assert!(set.points(0).unwrap().is_empty() && set.triangles(0).unwrap().is_empty());
+        assert_eq!(set.triangles(1).unwrap().len(), 3);
+        assert_eq!(code_of(set.edges(2)), ErrorCode::IndexOutOfRange);
+        assert_eq!(generate_layers(100.0, 100.0, &[], 0).unwrap().layer_count(), 0);
+    }
+}
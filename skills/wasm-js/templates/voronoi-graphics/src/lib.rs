@@ -7,10 +7,343 @@
 //! - WASM handles all mathematical computation
 //! - Returns Float32Array for JS rendering
 //! - Minimizes cross-boundary calls
+//! - `Point`, `Triangulation` and `VoronoiDiagram` are the plain Rust API;
+//!   the flat-array exports are adapters over them
+//! - Stateful animation objects have `save_state`/`restore_state`, and a
+//!   `StateBundle` keeps several of their states for pause and scrubbing
+//!
+//! # Features
+//! Seed generation, triangulation and edge processing are always built.
+//! The rest is split into cargo features, all on by default, so size-bound
+//! builds can use `--no-default-features --features ...`:
+//!
+//! | feature   | contents                                               |
+//! |-----------|--------------------------------------------------------|
+//! | `noise`   | `simplex_noise_2d`, its derivatives and fractal sums:  |
+//! |           | fBm, ridged, turbulence, a batch variant and           |
+//! |           | `NoiseGenerator`; domain-warped fBm, also as a texture |
+//! |           | grid; `simplex_noise_3d` for time-varying              |
+//! |           | fields and `animate_points` to displace whole point    |
+//! |           | sets with it; `compute_vertex_colors` for gradient     |
+//! |           | vertex colors; `triangle_shading` for flat-shaded      |
+//! |           | low-poly terrain; Worley noise over seed points;       |
+//! |           | `noise_contours` for iso-lines of the noise            |
+//! | `motes`   | `update_motes(_f32, _ex)`, `MoteSystem`, `PathTable`,  |
+//! |           | `run_benchmark`; with `noise`, `update_flow_motes`     |
+//! | `voronoi` | Voronoi cells and power diagrams, their rasterizer,    |
+//! |           | cell/triangle wedges, Lloyd relaxation, the medial     |
+//! |           | axis, natural-neighbour interpolation and the outlines |
+//! |           | of labelled regions of cells                           |
+//! | `image`   | mask coverage, the video mosaic, reveal thresholds     |
+//! | `mesh3d`  | stereographic sphere projection; golden seeds,         |
+//! |           | Delaunay triangles and edges on the sphere             |
+//! | `sim`     | `Scene` (clock, config, views, quality tiers) and      |
+//! |           | `ElasticMesh`; implies `noise` and `motes`             |
+//! | `svg`     | path data for edges, triangles and cells:              |
+//! |           | `edges_to_svg_path`, `triangles_to_svg_path`,          |
+//! |           | `voronoi_to_svg`                                       |
+//!
+//! `profiling`, off by default, adds `take_profile` with the time spent in
+//! each phase of the heavy exports; without it the timers compile away.
+//! `parallel`, also off by default, triangulates large point sets and
+//! sorts edge lists on a rayon thread pool, with the same results. On the
+//! web it runs on `wasm-bindgen-rayon` workers, which need a cross-origin
+//! isolated page, a threads-enabled build and `initThreadPool(n)` awaited
+//! first, so it is left to the builds that opt in.
+//! `simd`, off by default, runs `update_motes` and the batch noise exports
+//! four elements at a time, with bit-identical results. It uses SIMD128
+//! on wasm builds that enable it (`-C target-feature=+simd128`) and SSE2
+//! on x86_64; other targets keep the scalar loops.
 
 use wasm_bindgen::prelude::*;
 use std::f64::consts::PI;
 
+/// `$body`, timed as phase `$phase` for `take_profile` with the
+/// `profiling` feature and left alone without it
+macro_rules! profiled {
+    ($phase:expr, $body:expr) => {{
+        #[cfg(feature = "profiling")]
+        let start = crate::timing::now_ms();
+        let value = $body;
+        #[cfg(feature = "profiling")]
+        crate::profile::record($phase, crate::timing::now_ms() - start);
+        value
+    }};
+}
+
+mod adjacency;
+mod alpha;
+mod audio;
+#[cfg(feature = "motes")]
+mod bench;
+mod buffers;
+mod centers;
+mod clip;
+#[cfg(feature = "voronoi")]
+mod cell_index;
+#[cfg(feature = "sim")]
+mod clock;
+mod clusters;
+mod codec;
+mod color;
+#[cfg(feature = "sim")]
+mod config;
+mod constrained;
+mod contour;
+mod crossings;
+mod culling;
+mod dedup;
+mod diffusion;
+mod downsample;
+mod draw_order;
+#[cfg(feature = "sim")]
+mod drift;
+mod dynamic;
+mod edges;
+mod energy;
+#[cfg(feature = "sim")]
+mod elastic;
+mod error;
+mod fade;
+mod field;
+mod fingerprint;
+#[cfg(all(feature = "noise", feature = "motes"))]
+mod flow;
+mod gaps;
+mod geometry;
+#[cfg(feature = "mesh3d")]
+mod globe;
+mod graph;
+mod hull;
+mod laplace;
+mod layers;
+#[cfg(feature = "image")]
+mod mask;
+#[cfg(feature = "voronoi")]
+mod medial;
+mod mesh;
+mod morph;
+#[cfg(feature = "image")]
+mod mosaic;
+#[cfg(feature = "motes")]
+mod motes;
+#[cfg(feature = "voronoi")]
+mod natural;
+#[cfg(feature = "noise")]
+mod noise;
+mod options;
+#[cfg(feature = "voronoi")]
+mod outlines;
+#[cfg(feature = "motes")]
+mod path;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod periodic;
+mod picking;
+#[cfg(feature = "voronoi")]
+mod power;
+mod predicates;
+#[cfg(feature = "profiling")]
+mod profile;
+mod progressive;
+#[cfg(feature = "sim")]
+mod quality;
+#[cfg(feature = "voronoi")]
+mod raster;
+mod refine;
+#[cfg(feature = "voronoi")]
+mod relax;
+mod rescale;
+#[cfg(feature = "image")]
+mod reveal;
+mod ripples;
+mod rng;
+mod routes;
+mod sampling;
+#[cfg(feature = "sim")]
+mod scene;
+mod seeds;
+#[cfg(feature = "noise")]
+mod shading;
+#[cfg(all(feature = "simd", any(feature = "noise", feature = "motes")))]
+// Each kernel uses only some of the lane operations
+#[cfg_attr(
+    not(all(feature = "noise", feature = "motes")),
+    allow(dead_code, unused_imports, unused_macros)
+)]
+mod simd;
+mod snapshot;
+mod spatial;
+mod spectral;
+mod state;
+mod stats;
+mod stipple;
+mod strips;
+mod strokes;
+mod subdivide;
+#[cfg(feature = "mesh3d")]
+mod sphere;
+#[cfg(feature = "svg")]
+mod svg;
+mod tiles;
+mod timing;
+#[cfg(feature = "sim")]
+mod view;
+#[cfg(feature = "voronoi")]
+mod voronoi;
+#[cfg(feature = "noise")]
+mod worley;
+
+pub use adjacency::{
+    cell_adjacency, greedy_color_cells, triangle_neighbors, vertex_neighbors, CellColoring,
+    VertexNeighbors,
+};
+pub use alpha::{alpha_shape, filter_triangles_by_edge_length, AlphaShape};
+pub use audio::{apply_radial_spectrum, RadialSpectrum};
+#[cfg(feature = "motes")]
+pub use bench::{run_benchmark, BenchmarkReport};
+pub use buffers::{
+    build_mesh_buffers, deinterleave2, interleave2, pack_interleaved, split_mesh_for_u16, split_xyz,
+    to_f32, MeshBuffers, MeshChunks, VertexBufferBuilder, MAX_U16_POINTS,
+};
+pub use centers::{
+    compute_circumcircles, triangle_circumcenters, triangle_circumradii, triangle_incenters,
+    Circumcircles,
+};
+pub use clip::{clip_edges_to_polygon, filter_triangles_by_polygon, PolygonFilterMode};
+#[cfg(feature = "voronoi")]
+pub use cell_index::{build_cell_triangle_index, CellTriangleIndex};
+#[cfg(feature = "sim")]
+pub use clock::Clock;
+pub use clusters::{cell_clusters, label_cell_clusters, CellClusters};
+pub use color::{ColorRamp, ColorSpace, Interpolation};
+#[cfg(feature = "sim")]
+pub use config::SCENE_CONFIG_VERSION;
+pub use constrained::compute_constrained_delaunay;
+#[cfg(feature = "noise")]
+pub use contour::noise_contours;
+pub use contour::{marching_squares, ContourSet};
+pub use crossings::{find_edge_crossings, remove_crossings};
+pub use culling::{cull_edges, cull_triangles, CullIndex, CullKind};
+pub use dedup::{compute_delaunay_checked, CheckedDelaunay};
+pub use diffusion::{diffuse_values, diffuse_values_weighted};
+pub use downsample::{decimate_points, downsample_positions, lod_levels, LodLevels};
+pub use draw_order::{sort_triangles, SortedTriangles, TriangleOrder};
+#[cfg(feature = "sim")]
+pub use drift::{SeedSimulation, DRIFT_MAX_DT};
+pub use dynamic::DynamicTriangulation;
+pub use edges::{
+    bucket_edges_by_length, compute_edge_lod, edge_centrality_weights, edge_metrics,
+    EdgeWeightMode,
+};
+#[cfg(feature = "sim")]
+pub use elastic::{ElasticMesh, ELASTIC_MAX_DAMPING, ELASTIC_MAX_DT, ELASTIC_MAX_STIFFNESS};
+pub use energy::{update_edge_energy, EdgeEnergy};
+pub use error::{error_code, Error, ErrorCode};
+pub use fade::EdgeTracker;
+pub use field::edges_distance_field;
+pub use fingerprint::{geometry_hash, points_hash, GEOMETRY_HASH_DECIMALS};
+#[cfg(all(feature = "noise", feature = "motes"))]
+pub use flow::update_flow_motes;
+pub use gaps::{split_edges_with_gaps, GapSplit};
+pub use geometry::{Triangle, Triangulation};
+#[cfg(feature = "voronoi")]
+pub use geometry::VoronoiDiagram;
+#[cfg(feature = "mesh3d")]
+pub use globe::{compute_delaunay_sphere, compute_edges_sphere, generate_golden_seeds_sphere};
+pub use graph::{
+    decompose_tree_and_chords, gabriel_edges, mst_edges, rng_edges, TreeChordResult,
+};
+pub use hull::{compute_boundary_edges, compute_boundary_loops, compute_hull, BoundaryLoops};
+pub use laplace::{solve_laplace_field, LaplaceField};
+pub use layers::{generate_layers, LayerSet};
+#[cfg(feature = "image")]
+pub use mask::cell_mask_coverage;
+#[cfg(feature = "voronoi")]
+pub use medial::{approximate_medial_axis, ContourResult};
+pub use morph::{match_points, morph_mesh, morph_points, MorphKeyframes, MORPH_MAX_STEPS};
+#[cfg(feature = "image")]
+pub use mosaic::MosaicSampler;
+#[cfg(feature = "motes")]
+pub use motes::{MoteStyle, MoteSystem, MOTE_STRIDE};
+#[cfg(feature = "voronoi")]
+pub use natural::{natural_neighbor_at, natural_neighbor_batch, NaturalNeighborInterpolator};
+#[cfg(feature = "noise")]
+pub use noise::{
+    animate_points, animate_points_into, fbm_noise_2d, fbm_noise_2d_batch, ridged_noise_2d,
+    simplex_noise_2d, simplex_noise_2d_deriv, simplex_noise_2d_deriv_batch,
+    simplex_noise_2d_gradient, simplex_noise_3d, simplex_noise_3d_batch, turbulence_noise_2d,
+    warped_noise_2d, warped_noise_2d_batch, warped_noise_2d_grid, FractalMode, NoiseGenerator,
+    MAX_OCTAVES, MAX_WARP_LEVELS,
+};
+#[cfg(feature = "motes")]
+pub use options::{update_motes_with_config, MoteConfig};
+#[cfg(feature = "noise")]
+pub use options::{fbm_noise_2d_batch_with_config, NoiseConfig};
+pub use options::{generate_golden_seeds_with_config, SeedConfig};
+#[cfg(feature = "voronoi")]
+pub use outlines::{cluster_outlines, ClusterOutlines};
+#[cfg(feature = "motes")]
+pub use path::PathTable;
+#[cfg(all(feature = "parallel", target_arch = "wasm32"))]
+pub use parallel::init_thread_pool;
+pub use periodic::{compute_delaunay_periodic, PeriodicTriangulation};
+pub use picking::{locate_triangle, nearest_seed, SeedIndex, TriangleHit, TriangleLocator};
+#[cfg(feature = "voronoi")]
+pub use power::{compute_power_diagram, PowerDiagram};
+#[cfg(feature = "sim")]
+pub use quality::{QualityGovernor, QUALITY_TIERS};
+#[cfg(feature = "voronoi")]
+pub use raster::{rasterize_cell_distances, rasterize_cells, rasterize_voronoi, CellRaster};
+pub use predicates::{
+    degenerate_triangles, is_degenerate_triangle, orientation, signed_area, triangle_orientations,
+};
+pub use progressive::ProgressiveDelaunay;
+#[cfg(feature = "profiling")]
+pub use profile::take_profile;
+pub use refine::{refine_delaunay, refine_mesh, Bounds, RefineResult, REFINE_MAX_INSERTIONS};
+#[cfg(feature = "voronoi")]
+pub use relax::{lloyd_relax, lloyd_relax_pinned};
+pub use rescale::{is_delaunay_still_valid, rescale_points};
+#[cfg(feature = "image")]
+pub use reveal::compute_reveal_mask;
+pub use ripples::{apply_ripples, apply_ripples_into, prune_ripples};
+pub use routes::{path_to_polyline, shortest_path, shortest_path_tree, ShortestPathTree};
+pub use sampling::sample_edges;
+#[cfg(feature = "sim")]
+pub use scene::Scene;
+pub use seeds::{
+    generate_golden_seeds_at, generate_golden_seeds_cover, generate_golden_seeds_ex,
+    generate_golden_seeds_transformed, generate_halton_seeds, generate_jittered_grid,
+    generate_phyllotaxis, generate_poisson_seeds, generate_r2_seeds, CoverSeeds, FitMode,
+};
+#[cfg(feature = "noise")]
+pub use shading::{compute_vertex_colors, triangle_shading, VertexColorMode};
+pub use snapshot::{deserialize_triangulation, serialize_triangulation, TriangulationSnapshot};
+pub use spectral::spectral_layout;
+pub use state::StateBundle;
+pub use stats::{
+    ensure_ccw, mesh_centroid, triangle_areas, triangles_bbox, triangulation_stats,
+    validate_delaunay, TriangulationStats,
+};
+pub use stipple::{generate_weighted_seeds, weighted_lloyd_relax};
+pub use strips::{triangles_to_fan_groups, triangles_to_strips, FanGroups, TriangleStrips};
+pub use strokes::{edge_strokes, edge_strokes_into, MAX_STROKE_SEGMENTS};
+pub use subdivide::{subdivide_triangles, Subdivision, SubdivisionMode};
+#[cfg(feature = "mesh3d")]
+pub use sphere::{morph_planar_spherical, project_stereographic, unproject_stereographic};
+#[cfg(feature = "svg")]
+pub use svg::{edges_to_svg_path, triangles_to_svg_path, voronoi_to_svg, SVG_MAX_PRECISION};
+pub use tiles::{TileResult, TileStream};
+#[cfg(feature = "sim")]
+pub use view::FrameResult;
+#[cfg(feature = "voronoi")]
+pub use voronoi::{
+    compute_voronoi, inset_cells, round_cell_corners, voronoi_cell_metrics, InsetMode, VoronoiCells,
+};
+#[cfg(feature = "noise")]
+pub use worley::{worley_grid, worley_noise, WorleyMetric, WorleyMode, WorleyNoise};
+
 // Golden ratio constant
 const PHI: f64 = 1.618033988749895;
 // Golden angle in radians: 2π × (2 - φ) ≈ 2.39996
@@ -23,339 +356,655 @@ pub fn init() {
     console_error_panic_hook::set_once();
 }
 
-/// Point structure for internal calculations
-#[derive(Clone, Copy, Debug)]
-struct Point {
-    x: f64,
-    y: f64,
-}
-
-/// Triangle for Delaunay triangulation
-#[derive(Clone, Copy, Debug)]
-struct Triangle {
-    p0: usize,
-    p1: usize,
-    p2: usize,
-}
-
-impl Triangle {
-    /// Check if point is inside circumcircle of triangle
-    fn circumcircle_contains(&self, points: &[Point], p: Point) -> bool {
-        let a = &points[self.p0];
-        let b = &points[self.p1];
-        let c = &points[self.p2];
-
-        let ax = a.x - p.x;
-        let ay = a.y - p.y;
-        let bx = b.x - p.x;
-        let by = b.y - p.y;
-        let cx = c.x - p.x;
-        let cy = c.y - p.y;
-
-        let det = (ax * ax + ay * ay) * (bx * cy - cx * by)
-                - (bx * bx + by * by) * (ax * cy - cx * ay)
-                + (cx * cx + cy * cy) * (ax * by - bx * ay);
-
-        det > 0.0
-    }
-}
-
-/// Edge for polygon hole detection
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-struct Edge {
-    p0: usize,
-    p1: usize,
+/// Optional features compiled into this binary, comma-separated in the
+/// order of the crate docs' table
+#[wasm_bindgen]
+pub fn build_features() -> String {
+    let features = [
+        ("noise", cfg!(feature = "noise")),
+        ("motes", cfg!(feature = "motes")),
+        ("voronoi", cfg!(feature = "voronoi")),
+        ("image", cfg!(feature = "image")),
+        ("mesh3d", cfg!(feature = "mesh3d")),
+        ("sim", cfg!(feature = "sim")),
+        ("svg", cfg!(feature = "svg")),
+        ("profiling", cfg!(feature = "profiling")),
+        ("parallel", cfg!(feature = "parallel")),
+        ("simd", cfg!(feature = "simd")),
+    ];
+    let on: Vec<&str> = features.iter().filter(|(_, on)| *on).map(|(name, _)| *name).collect();
+    on.join(",")
 }
 
-impl Edge {
-    fn new(a: usize, b: usize) -> Self {
-        // Normalize edge direction for comparison
-        if a < b { Edge { p0: a, p1: b } } else { Edge { p0: b, p1: a } }
-    }
+/// A point in canvas space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
 }
 
 /// Generate seed points using golden ratio spiral (Vogel's model)
 ///
 /// θ = n × golden_angle
 /// r = c × √n (Fermat's spiral for uniform density)
+///
+/// Centred on the canvas with a radius of 0.85 × half the shorter side;
+/// `generate_golden_seeds_at` places and sizes the spiral freely.
 #[wasm_bindgen]
 pub fn generate_golden_seeds(width: f64, height: f64, count: usize) -> Vec<f64> {
-    let mut result = Vec::with_capacity(count * 2);
-    let center_x = width / 2.0;
-    let center_y = height / 2.0;
     let max_radius = (width.min(height) / 2.0) * 0.85;
+    profiled!("seeds", generate_golden_seeds_at(width / 2.0, height / 2.0, max_radius, count, 0))
+}
 
-    for n in 0..count {
-        let theta = (n as f64) * GOLDEN_ANGLE;
-        let r = max_radius * ((n as f64) / (count as f64)).sqrt();
-
-        let x = center_x + r * theta.cos();
-        let y = center_y + r * theta.sin();
+#[cfg(feature = "sim")]
+/// Spiral radius step giving `count` seeds the same extent as
+/// `generate_golden_seeds`
+pub(crate) fn golden_spacing(width: f64, height: f64, count: usize) -> f64 {
+    (width.min(height) / 2.0) * 0.85 / (count.max(1) as f64).sqrt()
+}
 
-        result.push(x);
-        result.push(y);
-    }
+#[cfg(feature = "sim")]
+/// Seeds `range` of the golden spiral with a fixed spacing
+///
+/// r = spacing × √n, so seed `n` sits at the same place whatever the total
+/// count; growing or shrinking the range only adds or drops seeds at the
+/// rim.
+pub(crate) fn golden_spiral(
+    width: f64,
+    height: f64,
+    spacing: f64,
+    range: std::ops::Range<usize>,
+) -> Vec<f64> {
+    let (center_x, center_y) = (width / 2.0, height / 2.0);
+    range
+        .flat_map(|n| {
+            let theta = (n as f64) * GOLDEN_ANGLE;
+            let r = spacing * (n as f64).sqrt();
+            [center_x + r * theta.cos(), center_y + r * theta.sin()]
+        })
+        .collect()
+}
 
-    result
+/// `generate_golden_seeds` narrowed to `f32`
+#[wasm_bindgen]
+pub fn generate_golden_seeds_f32(width: f64, height: f64, count: usize) -> Vec<f32> {
+    buffers::to_f32(&generate_golden_seeds(width, height, count))
 }
 
-/// Compute Delaunay triangulation using Bowyer-Watson algorithm
+/// Compute the Delaunay triangulation of `[x0, y0, x1, y1, ...]`
 /// Returns flat array of triangle vertex indices [t0p0, t0p1, t0p2, t1p0, ...]
+///
+/// Adapter over `Triangulation`; non-finite input gives no triangles.
+/// `compute_delaunay_checked` rejects bad input and welds duplicates.
+///
+/// `width` and `height` are ignored. Points may lie anywhere, negative or
+/// far off the canvas: the super triangle is sized from their own
+/// bounding box and the triangles cover their convex hull. The two
+/// parameters are only kept to match `compute_voronoi`, which clips to
+/// the canvas.
+///
+/// Every triangle is counter-clockwise in the input coordinates. A
+/// projection that flips y, as from canvas pixels to clip space, makes
+/// them all clockwise, so cull with `gl.frontFace(gl.CW)` there;
+/// `ensure_ccw` fixes the winding of index buffers from elsewhere.
 #[wasm_bindgen]
-pub fn compute_delaunay(points_flat: &[f64], width: f64, height: f64) -> Vec<u32> {
-    let point_count = points_flat.len() / 2;
-    if point_count < 3 {
-        return vec![];
+pub fn compute_delaunay(points_flat: &[f64], _width: f64, _height: f64) -> Vec<u32> {
+    let points: Vec<Point> = points_flat
+        .chunks_exact(2)
+        .map(|c| Point::new(c[0], c[1]))
+        .collect();
+    match Triangulation::new(&points) {
+        Ok(tri) => tri.triangles().flat_map(|t| t.vertices().map(|v| v as u32)).collect(),
+        Err(_) => Vec::new(),
     }
+}
 
-    // Convert flat array to points
-    let mut points: Vec<Point> = (0..point_count)
-        .map(|i| Point {
-            x: points_flat[i * 2],
-            y: points_flat[i * 2 + 1],
-        })
+/// `compute_delaunay` in a stretched metric: Delaunay after dividing x by
+/// `scale_x` and y by `scale_y`
+///
+/// The empty-circumcircle test runs on the scaled coordinates, so on an
+/// ultrawide canvas with seeds spread by `generate_golden_seeds_transformed`
+/// the triangles are well shaped in the stretched space instead of long
+/// slivers. The indices refer to the original, unscaled input points, and
+/// winding stays counter-clockwise since both scales are positive.
+#[wasm_bindgen]
+pub fn compute_delaunay_metric(
+    points_flat: &[f64],
+    width: f64,
+    height: f64,
+    scale_x: f64,
+    scale_y: f64,
+) -> error::Result<Vec<u32>> {
+    if !(scale_x.is_finite() && scale_y.is_finite() && scale_x > 0.0 && scale_y > 0.0) {
+        return Err(error::Error::InvalidArgument(format!(
+            "scales must be positive, got ({scale_x}, {scale_y})"
+        )));
+    }
+    let scaled: Vec<f64> = points_flat
+        .chunks(2)
+        .flat_map(|c| c.iter().zip([scale_x, scale_y]).map(|(v, s)| v / s))
         .collect();
+    Ok(compute_delaunay(&scaled, width / scale_x, height / scale_y))
+}
 
-    // Create super triangle that contains all points
-    let margin = (width.max(height)) * 3.0;
-    let st0 = points.len();
-    let st1 = points.len() + 1;
-    let st2 = points.len() + 2;
-
-    points.push(Point { x: -margin, y: -margin });
-    points.push(Point { x: width / 2.0, y: height + margin * 2.0 });
-    points.push(Point { x: width + margin, y: -margin });
-
-    let mut triangles = vec![Triangle { p0: st0, p1: st1, p2: st2 }];
-
-    // Bowyer-Watson algorithm
-    for i in 0..point_count {
-        let p = points[i];
-
-        // Find triangles whose circumcircle contains the point
-        let mut bad_triangles: Vec<usize> = Vec::new();
-        for (ti, tri) in triangles.iter().enumerate() {
-            if tri.circumcircle_contains(&points, p) {
-                bad_triangles.push(ti);
-            }
-        }
-
-        // Find polygon hole boundary
-        let mut polygon: Vec<Edge> = Vec::new();
-        for &ti in &bad_triangles {
-            let tri = &triangles[ti];
-            let edges = [
-                Edge::new(tri.p0, tri.p1),
-                Edge::new(tri.p1, tri.p2),
-                Edge::new(tri.p2, tri.p0),
-            ];
-
-            for edge in edges {
-                // Edge is on boundary if it's not shared with another bad triangle
-                let shared = bad_triangles.iter().any(|&other_ti| {
-                    if other_ti == ti { return false; }
-                    let other = &triangles[other_ti];
-                    let other_edges = [
-                        Edge::new(other.p0, other.p1),
-                        Edge::new(other.p1, other.p2),
-                        Edge::new(other.p2, other.p0),
-                    ];
-                    other_edges.contains(&edge)
-                });
-
-                if !shared {
-                    polygon.push(edge);
-                }
-            }
-        }
+/// Upper bound on the indices `compute_delaunay` returns for
+/// `point_count` points: a planar triangulation has fewer than `2n`
+/// triangles, so `6n` indices always suffice
+#[wasm_bindgen]
+pub fn delaunay_capacity(point_count: usize) -> usize {
+    point_count.saturating_mul(6)
+}
 
-        // Remove bad triangles (in reverse order to preserve indices)
-        bad_triangles.sort_by(|a, b| b.cmp(a));
-        for ti in bad_triangles {
-            triangles.swap_remove(ti);
-        }
+/// `compute_delaunay` into the front of `out`; returns the number of
+/// indices written
+///
+/// A buffer of `delaunay_capacity(n)` indices is always large enough.
+/// Errors, leaving `out` untouched, when it is too short for this call.
+#[wasm_bindgen]
+pub fn compute_delaunay_into(
+    points_flat: &[f64],
+    width: f64,
+    height: f64,
+    out: &mut [u32],
+) -> error::Result<usize> {
+    let triangles = compute_delaunay(points_flat, width, height);
+    buffers::check_out_len(triangles.len(), out.len())?;
+    out[..triangles.len()].copy_from_slice(&triangles);
+    Ok(triangles.len())
+}
 
-        // Re-triangulate polygon hole
-        for edge in polygon {
-            triangles.push(Triangle { p0: edge.p0, p1: edge.p1, p2: i });
-        }
+/// `compute_delaunay` as `u16` indices for WebGL 1 element buffers
+///
+/// Errors with `TooLarge` when there are more than `MAX_U16_POINTS`
+/// points, before triangulating; `split_mesh_for_u16` cuts bigger meshes
+/// into pieces that fit.
+#[wasm_bindgen]
+pub fn compute_delaunay_u16(
+    points_flat: &[f64],
+    width: f64,
+    height: f64,
+) -> error::Result<Vec<u16>> {
+    let n = points_flat.len() / 2;
+    if n > buffers::MAX_U16_POINTS {
+        return Err(error::Error::TooLarge(format!(
+            "{n} points need 32-bit indices, u16 holds at most {}; see split_mesh_for_u16",
+            buffers::MAX_U16_POINTS
+        )));
     }
+    buffers::narrow_u16(&compute_delaunay(points_flat, width, height))
+}
 
-    // Remove triangles that contain super triangle vertices
-    triangles.retain(|tri| {
-        tri.p0 < point_count && tri.p1 < point_count && tri.p2 < point_count
-    });
-
-    // Convert to flat array
-    let mut result = Vec::with_capacity(triangles.len() * 3);
-    for tri in triangles {
-        result.push(tri.p0 as u32);
-        result.push(tri.p1 as u32);
-        result.push(tri.p2 as u32);
-    }
+/// Unique edges of `triangles` as `[i0, j0, i1, j1, ...]` index pairs
+///
+/// Each pair has `i < j` and pairs are sorted, so the order depends only
+/// on the topology: edge `k` stays edge `k` while points move, and JS can
+/// read endpoints from its own live point buffer.
+#[wasm_bindgen]
+pub fn compute_edge_indices(triangles: &[u32]) -> Vec<u32> {
+    graph::unique_edges(triangles).into_iter().flat_map(|(a, b)| [a, b]).collect()
+}
 
-    result
+/// `compute_edge_indices` as `u16` pairs; errors with `TooLarge` on any
+/// index that does not fit below `MAX_U16_POINTS`
+#[wasm_bindgen]
+pub fn compute_edge_indices_u16(triangles: &[u32]) -> error::Result<Vec<u16>> {
+    buffers::narrow_u16(&compute_edge_indices(triangles))
 }
 
 /// Compute edges from Delaunay triangulation (for rendering)
 /// Returns flat array of edge endpoint coordinates [x0, y0, x1, y1, ...]
+///
+/// Edges come in `compute_edge_indices` order. Errors when `points_flat`
+/// has odd length or `triangles` is ragged or references a missing point.
 #[wasm_bindgen]
-pub fn compute_edges(points_flat: &[f64], triangles: &[u32]) -> Vec<f64> {
-    use std::collections::HashSet;
+pub fn compute_edges(points_flat: &[f64], triangles: &[u32]) -> error::Result<Vec<f64>> {
+    collect_edges(points_flat, triangles)
+}
 
-    let mut edges: HashSet<(u32, u32)> = HashSet::new();
+/// `compute_edges` for an `f32` point buffer, emitting `f32` coordinates
+#[wasm_bindgen]
+pub fn compute_edges_f32(points_flat: &[f32], triangles: &[u32]) -> error::Result<Vec<f32>> {
+    collect_edges(points_flat, triangles)
+}
 
-    // Extract unique edges from triangles
-    for i in (0..triangles.len()).step_by(3) {
-        let p0 = triangles[i];
-        let p1 = triangles[i + 1];
-        let p2 = triangles[i + 2];
+/// Upper bound on the coordinates `compute_edges` returns for a
+/// triangulation of `point_count` points: fewer than `3n` edges, at 4
+/// coordinates each
+#[wasm_bindgen]
+pub fn edges_capacity(point_count: usize) -> usize {
+    point_count.saturating_mul(12)
+}
 
-        // Normalize edge direction
-        edges.insert(if p0 < p1 { (p0, p1) } else { (p1, p0) });
-        edges.insert(if p1 < p2 { (p1, p2) } else { (p2, p1) });
-        edges.insert(if p2 < p0 { (p2, p0) } else { (p0, p2) });
-    }
+/// `compute_edges` into the front of `out`; returns the number of
+/// coordinates written
+///
+/// A buffer of `edges_capacity(n)` values is always large enough for a
+/// triangulation of `n` points. Errors, leaving `out` untouched, when it
+/// is too short or `triangles` references a missing point.
+#[wasm_bindgen]
+pub fn compute_edges_into(
+    points_flat: &[f64],
+    triangles: &[u32],
+    out: &mut [f64],
+) -> error::Result<usize> {
+    write_edges(points_flat, triangles, out)
+}
+
+/// `compute_edges_into` for `f32` points, as `compute_edges_f32`
+#[wasm_bindgen]
+pub fn compute_edges_f32_into(
+    points_flat: &[f32],
+    triangles: &[u32],
+    out: &mut [f32],
+) -> error::Result<usize> {
+    write_edges(points_flat, triangles, out)
+}
 
-    // Convert to coordinate pairs
-    let mut result = Vec::with_capacity(edges.len() * 4);
-    for (p0, p1) in edges {
-        let i0 = p0 as usize;
-        let i1 = p1 as usize;
-        result.push(points_flat[i0 * 2]);
-        result.push(points_flat[i0 * 2 + 1]);
-        result.push(points_flat[i1 * 2]);
-        result.push(points_flat[i1 * 2 + 1]);
+/// `compute_edge_indices` after checking `triangles` against `points_flat`
+fn checked_edge_indices<T>(points_flat: &[T], triangles: &[u32]) -> error::Result<Vec<u32>> {
+    if !points_flat.len().is_multiple_of(2) {
+        return Err(error::Error::InvalidLength(format!(
+            "point array length {} is odd",
+            points_flat.len()
+        )));
     }
+    graph::check_triangles(triangles, points_flat.len() / 2)?;
+    Ok(compute_edge_indices(triangles))
+}
 
-    result
+fn collect_edges<T: Copy>(points_flat: &[T], triangles: &[u32]) -> error::Result<Vec<T>> {
+    let indices = profiled!("edges", checked_edge_indices(points_flat, triangles))?;
+    Ok(indices
+        .into_iter()
+        .flat_map(|v| [points_flat[v as usize * 2], points_flat[v as usize * 2 + 1]])
+        .collect())
 }
 
-/// Simple 2D simplex noise for organic animation
-/// Returns value in range [-1, 1]
-#[wasm_bindgen]
-pub fn simplex_noise_2d(x: f64, y: f64) -> f64 {
-    // Skew and unskew factors for 2D
-    const F2: f64 = 0.5 * (1.732050808 - 1.0); // (sqrt(3) - 1) / 2
-    const G2: f64 = (3.0 - 1.732050808) / 6.0; // (3 - sqrt(3)) / 6
-
-    // Skew input space
-    let s = (x + y) * F2;
-    let i = (x + s).floor();
-    let j = (y + s).floor();
-
-    // Unskew to get (x, y) distances from cell origin
-    let t = (i + j) * G2;
-    let x0 = x - (i - t);
-    let y0 = y - (j - t);
-
-    // Determine which simplex we're in
-    let (i1, j1) = if x0 > y0 { (1.0, 0.0) } else { (0.0, 1.0) };
-
-    let x1 = x0 - i1 + G2;
-    let y1 = y0 - j1 + G2;
-    let x2 = x0 - 1.0 + 2.0 * G2;
-    let y2 = y0 - 1.0 + 2.0 * G2;
-
-    // Hash coordinates to get gradient indices
-    let ii = (i as i32) & 255;
-    let jj = (j as i32) & 255;
-
-    // Simple hash function
-    let hash = |x: i32, y: i32| -> usize {
-        let h = ((x.wrapping_mul(374761393) ^ y.wrapping_mul(668265263)) as u32) as usize;
-        h % 12
-    };
+fn write_edges<T: Copy>(
+    points_flat: &[T],
+    triangles: &[u32],
+    out: &mut [T],
+) -> error::Result<usize> {
+    let indices = profiled!("edges", checked_edge_indices(points_flat, triangles))?;
+    buffers::check_out_len(indices.len() * 2, out.len())?;
+    for (slot, v) in out.chunks_exact_mut(2).zip(indices.iter().map(|&v| v as usize * 2)) {
+        slot.copy_from_slice(&points_flat[v..v + 2]);
+    }
+    Ok(indices.len() * 2)
+}
 
-    // Gradient vectors for 2D
-    let grad = |h: usize, x: f64, y: f64| -> f64 {
-        let gradients: [(f64, f64); 12] = [
-            (1.0, 1.0), (-1.0, 1.0), (1.0, -1.0), (-1.0, -1.0),
-            (1.0, 0.0), (-1.0, 0.0), (1.0, 0.0), (-1.0, 0.0),
-            (0.0, 1.0), (0.0, -1.0), (0.0, 1.0), (0.0, -1.0),
-        ];
-        let (gx, gy) = gradients[h];
-        gx * x + gy * y
-    };
+/// Element type `update_motes` can run over; arithmetic is always `f64`
+#[cfg(feature = "motes")]
+trait MoteScalar: Copy {
+    fn get(self) -> f64;
+    fn put(v: f64) -> Self;
+}
 
-    // Calculate contribution from three corners
-    let mut n0 = 0.0;
-    let mut t0 = 0.5 - x0 * x0 - y0 * y0;
-    if t0 >= 0.0 {
-        t0 *= t0;
-        n0 = t0 * t0 * grad(hash(ii, jj), x0, y0);
+#[cfg(feature = "motes")]
+impl MoteScalar for f64 {
+    fn get(self) -> f64 {
+        self
     }
+    fn put(v: f64) -> f64 {
+        v
+    }
+}
 
-    let mut n1 = 0.0;
-    let mut t1 = 0.5 - x1 * x1 - y1 * y1;
-    if t1 >= 0.0 {
-        t1 *= t1;
-        n1 = t1 * t1 * grad(hash(ii + i1 as i32, jj + j1 as i32), x1, y1);
+#[cfg(feature = "motes")]
+impl MoteScalar for f32 {
+    fn get(self) -> f64 {
+        self as f64
+    }
+    fn put(v: f64) -> f32 {
+        v as f32
     }
+}
+
+#[cfg(feature = "motes")]
+thread_local! {
+    static MOTE_SEED: std::cell::Cell<u64> = const { std::cell::Cell::new(0) };
+}
+
+/// Seed the edge jumps of `update_motes` and `update_motes_f32`
+///
+/// A jump's target is a hash of this seed, the mote's index, its current
+/// edge and `time`, so the same seed and the same sequence of calls give
+/// the same animation in every tab. The default seed is 0.
+#[cfg(feature = "motes")]
+#[wasm_bindgen]
+pub fn set_mote_seed(seed: u64) {
+    MOTE_SEED.with(|s| s.set(seed));
+}
 
-    let mut n2 = 0.0;
-    let mut t2 = 0.5 - x2 * x2 - y2 * y2;
-    if t2 >= 0.0 {
-        t2 *= t2;
-        n2 = t2 * t2 * grad(hash(ii + 1, jj + 1), x2, y2);
+/// The seed last given to `set_mote_seed`
+#[cfg(feature = "motes")]
+pub(crate) fn mote_seed() -> u64 {
+    MOTE_SEED.with(|s| s.get())
+}
+
+/// Generator for the jump a mote makes after finishing `edge` at `time`
+#[cfg(feature = "motes")]
+fn jump_rng(mote: usize, edge: usize, time: f64) -> rng::Rng {
+    let mut key = mote_seed();
+    for v in [mote as u64, edge as u64, time.to_bits()] {
+        key = rng::Rng::new(key ^ v).next_u64();
     }
+    rng::Rng::new(key)
+}
 
-    // Scale to [-1, 1]
-    70.0 * (n0 + n1 + n2)
+/// Edge a mote jumps to after finishing `edge`; `edge_count > 0`
+#[cfg(feature = "motes")]
+fn jump_target(mote: usize, edge: usize, time: f64, edge_count: usize) -> usize {
+    jump_rng(mote, edge, time).below(edge_count as u32) as usize
 }
 
 /// Batch update mote positions along edges
 /// Input: motes [edgeIdx, progress, speed, ...], edges [x0, y0, x1, y1, ...]
 /// Output: positions [x, y, brightness, ...]
+///
+/// A mote that runs off the end of its edge jumps to a pseudo-random one
+/// picked by `set_mote_seed`'s hash, as does one whose edge index is past
+/// the end of `edges`. Every mote gets an output entry in input order;
+/// with no edges at all motes stay put, at a NaN position with zero
+/// brightness. Errors, touching nothing, when `motes` is not whole
+/// triples, `edges` not whole quads, or `dt` or `time` is not finite.
+#[cfg(feature = "motes")]
 #[wasm_bindgen]
 pub fn update_motes(
     motes: &mut [f64],
     edges: &[f64],
     dt: f64,
     time: f64,
-) -> Vec<f64> {
+) -> error::Result<Vec<f64>> {
+    check_motes(motes.len(), edges.len(), dt, time)?;
+    Ok(advance_motes(motes, edges, dt, time, &MoteStyle::default(), false))
+}
+
+/// `update_motes` over `f32` buffers, for an animation loop that stays in
+/// `Float32Array`s end to end
+///
+/// Positions are computed in `f64` and narrowed on output; edge indices
+/// are exact in `f32` up to 2^24 edges.
+#[cfg(feature = "motes")]
+#[wasm_bindgen]
+pub fn update_motes_f32(
+    motes: &mut [f32],
+    edges: &[f32],
+    dt: f64,
+    time: f64,
+) -> error::Result<Vec<f32>> {
+    check_motes(motes.len(), edges.len(), dt, time)?;
+    Ok(advance_motes(motes, edges, dt, time, &MoteStyle::default(), false))
+}
+
+/// `update_motes` writing `[x, y, brightness, ...]` into the front of
+/// `out` instead of a new array; returns the number of values written
+///
+/// `out` may be longer than the `3 × mote count` values it needs, so one
+/// buffer sized for the largest frame can be reused, typically as a
+/// persistent `Float32Array` view into wasm memory. Errors, touching
+/// neither buffer, when `out` is too short or `update_motes` would.
+#[cfg(feature = "motes")]
+#[wasm_bindgen]
+pub fn update_motes_into(
+    motes: &mut [f64],
+    edges: &[f64],
+    out: &mut [f32],
+    dt: f64,
+    time: f64,
+) -> error::Result<usize> {
+    check_motes(motes.len(), edges.len(), dt, time)?;
+    let needed = motes.len();
+    buffers::check_out_len(needed, out.len())?;
+    profiled!("motes", step_motes(motes, edges, dt, time, &MoteStyle::default(), false, out));
+    Ok(needed)
+}
+
+/// `update_motes_into` for `f32` mote state, as `update_motes_f32`
+#[cfg(feature = "motes")]
+#[wasm_bindgen]
+pub fn update_motes_f32_into(
+    motes: &mut [f32],
+    edges: &[f32],
+    out: &mut [f32],
+    dt: f64,
+    time: f64,
+) -> error::Result<usize> {
+    check_motes(motes.len(), edges.len(), dt, time)?;
+    let needed = motes.len();
+    buffers::check_out_len(needed, out.len())?;
+    profiled!("motes", step_motes(motes, edges, dt, time, &MoteStyle::default(), false, out));
+    Ok(needed)
+}
+
+/// `update_motes` with a configurable pulse and stride-5 output
+/// `[x, y, brightness, size, edgeIdx, ...]`
+///
+/// `edgeIdx` is the edge the mote is on after this step, so it reflects
+/// any jump. Errors as `update_motes` does, or if `style` has a
+/// non-finite field or a reversed range.
+#[cfg(feature = "motes")]
+#[wasm_bindgen]
+pub fn update_motes_ex(
+    motes: &mut [f64],
+    edges: &[f64],
+    dt: f64,
+    time: f64,
+    style: &MoteStyle,
+) -> error::Result<Vec<f64>> {
+    check_motes(motes.len(), edges.len(), dt, time)?;
+    style.validate()?;
+    Ok(advance_motes(motes, edges, dt, time, style, true))
+}
+
+/// The input checks shared by the `update_motes` family
+#[cfg(feature = "motes")]
+fn check_motes(mote_len: usize, edge_len: usize, dt: f64, time: f64) -> error::Result<()> {
+    if !mote_len.is_multiple_of(3) {
+        return Err(error::Error::InvalidLength(format!(
+            "mote array length {mote_len} is not a multiple of 3"
+        )));
+    }
+    if !edge_len.is_multiple_of(4) {
+        return Err(error::Error::InvalidLength(format!(
+            "edge array length {edge_len} is not a multiple of 4"
+        )));
+    }
+    if !dt.is_finite() || !time.is_finite() {
+        return Err(error::Error::NonFiniteInput(format!("dt {dt} and time {time} must be finite")));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "motes")]
+fn advance_motes<T: MoteScalar>(
+    motes: &mut [T],
+    edges: &[T],
+    dt: f64,
+    time: f64,
+    style: &MoteStyle,
+    extended: bool,
+) -> Vec<T> {
+    let stride = if extended { 5 } else { 3 };
+    let mut positions = vec![T::put(0.0); motes.len() / 3 * stride];
+    profiled!("motes", step_motes(motes, edges, dt, time, style, extended, &mut positions));
+    positions
+}
+
+/// The body of `advance_motes`, writing `stride` values per mote into the
+/// front of `out`, which must hold them all
+///
+/// With the `simd` feature, each whole group of four motes goes through
+/// `step_motes_x4` where `simd::USE_LANES`, with the same results.
+#[cfg(feature = "motes")]
+fn step_motes<T: MoteScalar, U: MoteScalar>(
+    motes: &mut [T],
+    edges: &[T],
+    dt: f64,
+    time: f64,
+    style: &MoteStyle,
+    extended: bool,
+    out: &mut [U],
+) {
     let mote_count = motes.len() / 3; // [edgeIdx, progress, speed] per mote
+    #[cfg(feature = "simd")]
+    let done = if simd::USE_LANES {
+        for first in (0..mote_count / 4 * 4).step_by(4) {
+            step_motes_x4(first, motes, edges, dt, time, style, extended, out);
+        }
+        mote_count / 4 * 4
+    } else {
+        0
+    };
+    #[cfg(not(feature = "simd"))]
+    let done = 0;
+    for i in done..mote_count {
+        step_mote(i, motes, edges, dt, time, style, extended, out);
+    }
+}
+
+/// Move mote `i` and write its `stride` output values
+#[cfg(feature = "motes")]
+#[allow(clippy::too_many_arguments)]
+fn step_mote<T: MoteScalar, U: MoteScalar>(
+    i: usize,
+    motes: &mut [T],
+    edges: &[T],
+    dt: f64,
+    time: f64,
+    style: &MoteStyle,
+    extended: bool,
+    out: &mut [U],
+) {
     let edge_count = edges.len() / 4; // [x0, y0, x1, y1] per edge
-    let mut positions = Vec::with_capacity(mote_count * 3);
-
-    for i in 0..mote_count {
-        let base = i * 3;
-        let edge_idx = motes[base] as usize;
-        let progress = motes[base + 1];
-        let speed = motes[base + 2];
-
-        // Update progress
-        let new_progress = progress + speed * dt;
-        motes[base + 1] = if new_progress > 1.0 {
-            // Jump to random edge
-            motes[base] = ((edge_idx + 7) % edge_count) as f64; // Simple deterministic "random"
-            0.0
-        } else {
-            new_progress
-        };
+    let stride = if extended { 5 } else { 3 };
 
-        // Calculate position on edge
-        if edge_idx < edge_count {
-            let edge_base = edge_idx * 4;
-            let x0 = edges[edge_base];
-            let y0 = edges[edge_base + 1];
-            let x1 = edges[edge_base + 2];
-            let y1 = edges[edge_base + 3];
+    let base = i * 3;
+    let mut edge_idx = motes[base].get() as usize;
+    let progress = motes[base + 1].get();
+    let speed = motes[base + 2].get();
 
-            let p = motes[base + 1];
-            let x = x0 + (x1 - x0) * p;
-            let y = y0 + (y1 - y0) * p;
+    // Update progress; a mote whose edge has gone away moves on now
+    // rather than dropping out of the output
+    let new_progress = progress + speed * dt;
+    motes[base + 1] = T::put(if new_progress > 1.0 || edge_idx >= edge_count {
+        if edge_count > 0 {
+            edge_idx = jump_target(i, edge_idx, time, edge_count);
+            motes[base] = T::put(edge_idx as f64);
+        }
+        0.0
+    } else {
+        new_progress
+    });
 
-            // Pulsing brightness
-            let brightness = 0.5 + 0.5 * (time * 2.0 + (i as f64) * 0.5).sin();
+    // Calculate position on edge
+    let (mut x, mut y, (mut brightness, size)) = (f64::NAN, f64::NAN, style.pulse(i, time));
+    if edge_idx < edge_count {
+        let edge_base = edge_idx * 4;
+        let x0 = edges[edge_base].get();
+        let y0 = edges[edge_base + 1].get();
+        let x1 = edges[edge_base + 2].get();
+        let y1 = edges[edge_base + 3].get();
 
-            positions.push(x);
-            positions.push(y);
-            positions.push(brightness);
-        }
+        let p = motes[base + 1].get();
+        x = x0 + (x1 - x0) * p;
+        y = y0 + (y1 - y0) * p;
+    } else {
+        brightness = 0.0;
     }
 
-    positions
+    let slot = &mut out[i * stride..(i + 1) * stride];
+    slot[0] = U::put(x);
+    slot[1] = U::put(y);
+    slot[2] = U::put(brightness);
+    if extended {
+        slot[3] = U::put(size);
+        slot[4] = U::put(motes[base].get());
+    }
+}
+
+/// `step_mote` for motes `first..first + 4` at once, bit for bit
+///
+/// Progress, the pulse and the position are lane arithmetic; jumps, `sin`
+/// and reading each mote's edge are done lane by lane.
+#[cfg(all(feature = "motes", feature = "simd"))]
+#[allow(clippy::too_many_arguments)]
+fn step_motes_x4<T: MoteScalar, U: MoteScalar>(
+    first: usize,
+    motes: &mut [T],
+    edges: &[T],
+    dt: f64,
+    time: f64,
+    style: &MoteStyle,
+    extended: bool,
+    out: &mut [U],
+) {
+    use simd::{per_lane, Lanes, Mask};
+
+    let stride = if extended { 5 } else { 3 };
+    let base = first * 3;
+
+    let progress = Lanes::from_array(per_lane!(l => motes[base + l * 3 + 1].get()));
+    let speed = Lanes::from_array(per_lane!(l => motes[base + l * 3 + 2].get()));
+    let new_progress = (progress + speed * dt).to_array();
+    let moved = per_lane!(l => move_mote(first + l, motes, new_progress[l], edges, time));
+
+    let on_edge = Mask::from_array(per_lane!(l => moved[l].1));
+    let x0 = Lanes::from_array(per_lane!(l => moved[l].2[0]));
+    let y0 = Lanes::from_array(per_lane!(l => moved[l].2[1]));
+    let x1 = Lanes::from_array(per_lane!(l => moved[l].2[2]));
+    let y1 = Lanes::from_array(per_lane!(l => moved[l].2[3]));
+    let p = Lanes::from_array(per_lane!(l => moved[l].0));
+    let nan = Lanes::splat(f64::NAN);
+    let x = on_edge.select(x0 + (x1 - x0) * p, nan).to_array();
+    let y = on_edge.select(y0 + (y1 - y0) * p, nan).to_array();
+    let (brightness, size) = style.pulse_x4(first, time);
+    let brightness = on_edge.select(brightness, Lanes::splat(0.0)).to_array();
+    let size = size.to_array();
+
+    per_lane!(l => {
+        let slot = (first + l) * stride;
+        out[slot] = U::put(x[l]);
+        out[slot + 1] = U::put(y[l]);
+        out[slot + 2] = U::put(brightness[l]);
+        if extended {
+            out[slot + 3] = U::put(size[l]);
+            out[slot + 4] = U::put(motes[base + l * 3].get());
+        }
+    });
+}
+
+/// The per-lane part of `step_motes_x4` for mote `i`: store its progress,
+/// jumping as `step_mote` does, and return that progress, whether it is
+/// on an edge, and the edge's endpoints
+#[cfg(all(feature = "motes", feature = "simd"))]
+#[inline(always)]
+fn move_mote<T: MoteScalar>(
+    i: usize,
+    motes: &mut [T],
+    new_progress: f64,
+    edges: &[T],
+    time: f64,
+) -> (f64, bool, [f64; 4]) {
+    let edge_count = edges.len() / 4;
+    let base = i * 3;
+    let mut edge_idx = motes[base].get() as usize;
+    motes[base + 1] = T::put(if new_progress > 1.0 || edge_idx >= edge_count {
+        if edge_count > 0 {
+            edge_idx = jump_target(i, edge_idx, time, edge_count);
+            motes[base] = T::put(edge_idx as f64);
+        }
+        0.0
+    } else {
+        new_progress
+    });
+    let p = motes[base + 1].get();
+    if edge_idx < edge_count {
+        let e = edge_idx * 4;
+        (p, true, [edges[e].get(), edges[e + 1].get(), edges[e + 2].get(), edges[e + 3].get()])
+    } else {
+        (p, false, [f64::NAN; 4])
+    }
 }
 
 #[cfg(test)]
@@ -380,6 +1029,366 @@ mod tests {
     }
 
     #[test]
+    fn test_delaunay_ignores_the_canvas_and_covers_the_hull() {
+        // Seeds scattered over a world 12x the canvas, mostly negative
+        let mut rng = rng::Rng::new(17);
+        let world: Vec<f64> = (0..4000).map(|_| rng.range(-5000.0, 5000.0)).collect();
+        let triangles = compute_delaunay(&world, 800.0, 600.0);
+        assert!(validate_delaunay(&world, &triangles).unwrap());
+        // A triangulation of n points with h on the hull has 2n - 2 - h triangles
+        let hull = compute_hull(&world).unwrap().len();
+        assert_eq!(triangles.len() / 3, 2 * 2000 - 2 - hull);
+        for (w, h) in [(1.0, 1.0), (-800.0, 600.0), (f64::NAN, 600.0), (1e300, f64::INFINITY)] {
+            assert_eq!(compute_delaunay(&world, w, h), triangles);
+        }
+        // Far off the canvas, shifted as a block: the same triangles
+        let shifted: Vec<f64> = world.iter().map(|v| v + 1e6).collect();
+        assert_eq!(compute_delaunay(&shifted, 800.0, 600.0), triangles);
+
+        // A shallow arc far wider than the super triangle keeps its hull
+        let arc: Vec<f64> = (0..200)
+            .flat_map(|i| {
+                let a = (i as f64 / 199.0 - 0.5) * 1e-3;
+                [1e6 * a.sin(), 1e6 * (1.0 - a.cos())]
+            })
+            .chain((0..200).flat_map(|_| [rng.range(-400.0, 400.0), rng.range(1.0, 300.0)]))
+            .collect();
+        let triangles = compute_delaunay(&arc, 800.0, 600.0);
+        assert!(validate_delaunay(&arc, &triangles).unwrap());
+        assert_eq!(compute_boundary_edges(&triangles).len() / 2, compute_hull(&arc).unwrap().len());
+    }
+
+    #[test]
+    fn test_delaunay_of_collinear_points_is_empty() {
+        let line: Vec<f64> = (0..100).flat_map(|i| [i as f64 * 3.0 - 50.0, i as f64 * -2.0]).collect();
+        assert!(compute_delaunay(&line, 800.0, 600.0).is_empty());
+        let checked = compute_delaunay_checked(&line, 800.0, 600.0, 0.0).unwrap();
+        assert_eq!(checked.triangle_count(), 0);
+    }
+
+    #[test]
+    fn test_metric_delaunay_triangulates_the_scaled_points() {
+        let transform = [1920.0, 0.0, 0.0, 300.0, 1920.0, 300.0];
+        let seeds = generate_golden_seeds_transformed(800, &transform).unwrap();
+        let metric = compute_delaunay_metric(&seeds, 3840.0, 600.0, 6.4, 1.0).unwrap();
+        let scaled: Vec<f64> = seeds.chunks(2).flat_map(|p| [p[0] / 6.4, p[1]]).collect();
+        assert_eq!(metric, compute_delaunay(&scaled, 600.0, 600.0));
+        assert!(validate_delaunay(&scaled, &metric).unwrap());
+        assert_eq!(ensure_ccw(&seeds, &mut metric.clone()).unwrap(), 0);
+        // The plain triangulation is all slivers in the stretched space
+        let plain = compute_delaunay(&seeds, 3840.0, 600.0);
+        assert!(!validate_delaunay(&scaled, &plain).unwrap());
+        let slivers = |triangles: &[u32]| {
+            let angle = |t: &[u32]| triangulation_stats(&scaled, t, 0.0).unwrap().min_angle_deg();
+            triangles.chunks(3).filter(|&t| angle(t) < 10.0).count()
+        };
+        assert!(slivers(&metric) * 10 < slivers(&plain));
+
+        let metric = |sx: f64| compute_delaunay_metric(&seeds, 1.0, 1.0, sx, 1.0);
+        assert_eq!(error::code_of(metric(0.0)), error::ErrorCode::InvalidArgument);
+        assert_eq!(error::code_of(metric(f64::NAN)), error::ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_edge_indices_are_sorted_and_match_coordinates() {
+        let points = generate_golden_seeds(400.0, 300.0, 60);
+        let triangles = compute_delaunay(&points, 400.0, 300.0);
+        let indices = compute_edge_indices(&triangles);
+        let pairs: Vec<(u32, u32)> = indices.chunks(2).map(|e| (e[0], e[1])).collect();
+        assert!(pairs.windows(2).all(|w| w[0] < w[1]));
+        assert!(pairs.iter().all(|&(a, b)| a < b));
+        let coords = compute_edges(&points, &triangles).unwrap();
+        assert_eq!(coords, compute_edges(&points, &triangles).unwrap());
+        for (e, xy) in indices.chunks(2).zip(coords.chunks(4)) {
+            let (a, b) = (e[0] as usize, e[1] as usize);
+            assert_eq!(xy, [points[2 * a], points[2 * a + 1], points[2 * b], points[2 * b + 1]]);
+        }
+    }
+
+    #[cfg(feature = "motes")]
+    #[test]
+    fn test_f32_variants_match_f64() {
+        let seeds = generate_golden_seeds(640.0, 480.0, 40);
+        let seeds32 = generate_golden_seeds_f32(640.0, 480.0, 40);
+        assert_eq!(seeds32, buffers::to_f32(&seeds));
+        let triangles = compute_delaunay(&seeds, 640.0, 480.0);
+        let edges = compute_edges(&seeds, &triangles).unwrap();
+        let edges32 = compute_edges_f32(&seeds32, &triangles).unwrap();
+        assert_eq!(edges32, buffers::to_f32(&edges));
+
+        let mut motes = vec![0.0, 0.95, 0.1, 3.0, 0.25, 0.5, 11.0, 0.0, 0.3];
+        let mut motes32 = buffers::to_f32(&motes);
+        for frame in 0..5 {
+            let t = frame as f64 / 60.0;
+            let out = update_motes(&mut motes, &edges, 0.5, t).unwrap();
+            let out32 = update_motes_f32(&mut motes32, &edges32, 0.5, t).unwrap();
+            assert_eq!(out.len(), out32.len());
+            for (a, b) in out.iter().zip(&out32) {
+                assert!((a - *b as f64).abs() < 1e-3, "{a} vs {b}");
+            }
+            let edge_of = |m: &[f32]| m.iter().step_by(3).copied().collect::<Vec<f32>>();
+            assert_eq!(edge_of(&motes32), edge_of(&buffers::to_f32(&motes)));
+        }
+    }
+
+    #[test]
+    fn test_into_variants_fill_caller_buffers() {
+        let seeds = generate_golden_seeds(400.0, 300.0, 80);
+        let triangles = compute_delaunay(&seeds, 400.0, 300.0);
+        let mut indices = vec![u32::MAX; delaunay_capacity(80)];
+        let written = compute_delaunay_into(&seeds, 400.0, 300.0, &mut indices).unwrap();
+        assert_eq!(&indices[..written], &triangles[..]);
+        assert!(indices[written..].iter().all(|&i| i == u32::MAX));
+        let mut short = vec![0; written - 1];
+        let result = compute_delaunay_into(&seeds, 400.0, 300.0, &mut short);
+        assert_eq!(error::code_of(result), error::ErrorCode::InvalidLength);
+
+        let edges = compute_edges(&seeds, &triangles).unwrap();
+        let mut out = vec![0.0; edges_capacity(80)];
+        let written = compute_edges_into(&seeds, &triangles, &mut out).unwrap();
+        assert_eq!(&out[..written], &edges[..]);
+        let seeds32 = buffers::to_f32(&seeds);
+        let mut out32 = vec![0.0; edges.len()];
+        assert_eq!(compute_edges_f32_into(&seeds32, &triangles, &mut out32).unwrap(), edges.len());
+        assert_eq!(out32, compute_edges_f32(&seeds32, &triangles).unwrap());
+        let result = compute_edges_into(&seeds, &triangles, &mut out[..written - 1]);
+        assert_eq!(error::code_of(result), error::ErrorCode::InvalidLength);
+        let result = compute_edges_into(&seeds, &[0, 1, 80], &mut out);
+        assert_eq!(error::code_of(result), error::ErrorCode::IndexOutOfRange);
+    }
+
+    #[test]
+    fn test_u16_variants_narrow_or_refuse() {
+        let seeds = generate_golden_seeds(400.0, 300.0, 80);
+        let triangles = compute_delaunay(&seeds, 400.0, 300.0);
+        let narrow = compute_delaunay_u16(&seeds, 400.0, 300.0).unwrap();
+        assert!(narrow.iter().map(|&v| v as u32).eq(triangles.iter().copied()));
+        let edges = compute_edge_indices(&triangles);
+        let narrow = compute_edge_indices_u16(&triangles).unwrap();
+        assert!(narrow.iter().map(|&v| v as u32).eq(edges.iter().copied()));
+
+        // Refused up front, without triangulating 65536 points
+        let many = vec![0.0; 2 * (buffers::MAX_U16_POINTS + 1)];
+        let result = compute_delaunay_u16(&many, 400.0, 300.0);
+        assert_eq!(error::code_of(result), error::ErrorCode::TooLarge);
+        // 65535 is the primitive restart index, so it is refused too
+        let result = compute_edge_indices_u16(&[0, 1, 65535]);
+        assert!(result.unwrap_err().to_string().contains("index 65535"));
+        assert_eq!(compute_edge_indices_u16(&[0, 1, 65534]).unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_compute_edges_rejects_bad_input() {
+        let message = |r: error::Result<Vec<f64>>| r.unwrap_err().to_string();
+        let points = [0.0, 0.0, 10.0, 0.0, 0.0, 10.0];
+        assert_eq!(
+            message(compute_edges(&points, &[0, 1, 3])),
+            "index out of range: triangle references vertex 3 but only 3 points were given"
+        );
+        assert_eq!(
+            message(compute_edges(&points, &[0, 1])),
+            "invalid length: triangle index count 2 is not a multiple of 3"
+        );
+        assert_eq!(
+            message(compute_edges(&points[..5], &[])),
+            "invalid length: point array length 5 is odd"
+        );
+        let result = compute_edges_f32(&[0.0; 4], &[0, 1, 2]);
+        assert_eq!(error::code_of(result), error::ErrorCode::IndexOutOfRange);
+        assert!(compute_edges(&[], &[]).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "motes")]
+    #[test]
+    fn test_update_motes_rejects_bad_input() {
+        let edges = [0.0, 0.0, 10.0, 0.0];
+        let start = vec![0.0, 0.5, 0.1];
+        let mut motes = start.clone();
+        let message = |r: error::Result<Vec<f64>>| r.unwrap_err().to_string();
+        assert_eq!(
+            message(update_motes(&mut motes[..2], &edges, 0.1, 0.0)),
+            "invalid length: mote array length 2 is not a multiple of 3"
+        );
+        assert_eq!(
+            message(update_motes(&mut motes, &edges[..3], 0.1, 0.0)),
+            "invalid length: edge array length 3 is not a multiple of 4"
+        );
+        assert_eq!(
+            message(update_motes(&mut motes, &edges, f64::NAN, 0.0)),
+            "non-finite input: dt NaN and time 0 must be finite"
+        );
+        let style = MoteStyle::default();
+        let result = update_motes_ex(&mut motes, &edges, 0.1, f64::INFINITY, &style);
+        assert_eq!(error::code_of(result), error::ErrorCode::NonFiniteInput);
+        let result = update_motes_f32(&mut [0.0; 4], &[0.0; 4], 0.1, 0.0);
+        assert_eq!(error::code_of(result), error::ErrorCode::InvalidLength);
+        let result = update_motes_into(&mut motes, &edges[..2], &mut [0.0; 3], 0.1, 0.0);
+        assert_eq!(error::code_of(result), error::ErrorCode::InvalidLength);
+        assert_eq!(motes, start);
+    }
+
+    #[cfg(feature = "motes")]
+    #[test]
+    fn test_update_motes_into_matches_update_motes() {
+        let seeds = generate_golden_seeds(640.0, 480.0, 40);
+        let edges = compute_edges(&seeds, &compute_delaunay(&seeds, 640.0, 480.0)).unwrap();
+        let edges32 = buffers::to_f32(&edges);
+        let mut motes = vec![0.0, 0.95, 0.1, 3.0, 0.25, 0.5, 11.0, 0.0, 0.3];
+        let (mut into, mut into32) = (motes.clone(), buffers::to_f32(&motes));
+        let mut out = vec![-1.0f32; 12];
+        let mut out32 = vec![0.0f32; 9];
+        for frame in 0..5 {
+            let t = frame as f64 / 60.0;
+            let expected = buffers::to_f32(&update_motes(&mut motes, &edges, 0.5, t).unwrap());
+            assert_eq!(update_motes_into(&mut into, &edges, &mut out, 0.5, t).unwrap(), 9);
+            assert_eq!(&out[..9], &expected[..]);
+            assert_eq!(out[9..], [-1.0; 3]);
+            assert_eq!(into, motes);
+            update_motes_f32_into(&mut into32, &edges32, &mut out32, 0.5, t).unwrap();
+            for (a, b) in out32.iter().zip(&expected) {
+                assert!((a - b).abs() < 1e-3, "{a} vs {b}");
+            }
+        }
+        // Too short a buffer changes nothing, not even the motes
+        let before = into.clone();
+        let result = update_motes_into(&mut into, &edges, &mut out[..8], 0.5, 1.0);
+        assert_eq!(error::code_of(result), error::ErrorCode::InvalidLength);
+        assert_eq!(into, before);
+    }
+
+    #[cfg(feature = "motes")]
+    #[test]
+    fn test_mote_jumps_are_seeded_and_spread() {
+        let edges: Vec<f64> = (0..100).flat_map(|i| [i as f64, 0.0, i as f64, 1.0]).collect();
+        let visits = |seed: u64| {
+            set_mote_seed(seed);
+            let mut motes = vec![0.0, 0.0, 2.0];
+            (0..500)
+                .map(|frame| {
+                    update_motes(&mut motes, &edges, 1.0, frame as f64 / 60.0).unwrap();
+                    motes[0] as u32
+                })
+                .collect::<Vec<u32>>()
+        };
+        let a = visits(42);
+        assert_eq!(a, visits(42));
+        assert_ne!(a, visits(43));
+        let mut distinct = a.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        // A fixed stride would cycle through at most 100 / gcd(7, 100)
+        // edges in a fixed order; the hash should reach nearly all of them
+        assert!(distinct.len() > 90, "{} edges visited", distinct.len());
+
+        // With no edges a mote stays put but keeps its output slot
+        let mut stranded = vec![3.0, 0.9, 1.0];
+        let out = update_motes(&mut stranded, &[], 1.0, 0.0).unwrap();
+        assert!(out[0].is_nan() && out[1].is_nan() && out[2] == 0.0);
+        assert_eq!(stranded, vec![3.0, 0.0, 1.0]);
+        set_mote_seed(0);
+    }
+
+    #[cfg(feature = "motes")]
+    #[test]
+    fn test_update_motes_ex_styles_and_keeps_stride() {
+        let edges: Vec<f64> = (0..10).flat_map(|i| [i as f64, 0.0, i as f64, 10.0]).collect();
+        let start = vec![0.0, 0.5, 0.1, 9.0, 0.95, 0.1, 4.0, 0.2, 0.0];
+        let (mut plain, mut ex) = (start.clone(), start.clone());
+        let old = update_motes(&mut plain, &edges, 1.0, 0.25).unwrap();
+        let new = update_motes_ex(&mut ex, &edges, 1.0, 0.25, &MoteStyle::default()).unwrap();
+        assert_eq!((plain, new.len()), (ex.clone(), 15));
+        for (o, n) in old.chunks(3).zip(new.chunks(5)) {
+            assert_eq!(o, &n[..3]);
+            assert_eq!(n[3], 1.0);
+        }
+        // The second mote finished its edge, and reports the one it jumped to
+        assert_eq!(new[9], ex[3]);
+        assert_eq!(new[5..7], [ex[3], 0.0]);
+
+        let style = MoteStyle::new(3.0, 1.0, 0.2, 0.6, 2.0, 6.0);
+        let styled = update_motes_ex(&mut start.clone(), &edges, 0.0, 1.0, &style).unwrap();
+        for (i, m) in styled.chunks(5).enumerate() {
+            let s = 0.5 + 0.5 * (3.0 + i as f64).sin();
+            assert!((m[2] - (0.2 + 0.4 * s)).abs() < 1e-12);
+            assert!((m[3] - (2.0 + 4.0 * s)).abs() < 1e-12);
+        }
+
+        // Shrinking `edges` under motes rehomes them instead of dropping
+        // their entries, so output stays aligned with input
+        let mut motes = start.clone();
+        let out = update_motes_ex(&mut motes, &edges[..8], 0.0, 0.0, &style).unwrap();
+        assert_eq!(out.len(), 15);
+        assert!(motes.iter().step_by(3).all(|&e| e < 2.0));
+        assert_eq!(out[5 + 4], motes[3]);
+        assert_eq!(out[5 + 1], 0.0);
+
+        let reversed = MoteStyle::new(2.0, 0.5, 1.0, 0.0, 1.0, 1.0);
+        let err = update_motes_ex(&mut motes, &edges, 0.0, 0.0, &reversed);
+        assert_eq!(error::code_of(err), ErrorCode::InvalidArgument);
+        let nan = MoteStyle { max_size: f64::NAN, ..style };
+        let err = update_motes_ex(&mut motes, &edges, 0.0, 0.0, &nan);
+        assert_eq!(error::code_of(err), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_build_features_match_cfg() {
+        let built = build_features();
+        let listed = |name: &str| built.split(',').any(|f| f == name);
+        assert_eq!(listed("noise"), cfg!(feature = "noise"));
+        assert_eq!(listed("voronoi"), cfg!(feature = "voronoi"));
+        assert_eq!(listed("sim"), cfg!(feature = "sim"));
+        // `sim` pulls in what the scene runs on
+        assert!(!listed("sim") || (listed("noise") && listed("motes")));
+    }
+
+    /// `step_motes` with the output of stepping each mote alone, for `f64`
+    /// and `f32` buffers and both strides, compared bit for bit
+    #[cfg(feature = "motes")]
+    fn assert_steps_match_single_motes<T: MoteScalar>(start: &[T], edges: &[T]) {
+        let bits = |v: &[T]| v.iter().map(|v| v.get().to_bits()).collect::<Vec<u64>>();
+        let style = MoteStyle::new(3.0, 0.7, 0.2, 0.6, 2.0, 6.0);
+        let mote_count = start.len() / 3;
+        for extended in [false, true] {
+            let stride = if extended { 5 } else { 3 };
+            let (mut grouped, mut single) = (start.to_vec(), start.to_vec());
+            for frame in 0..30 {
+                let t = frame as f64 / 60.0;
+                let mut out = vec![T::put(0.0); mote_count * stride];
+                let mut want = out.clone();
+                step_motes(&mut grouped, edges, 0.25, t, &style, extended, &mut out);
+                for i in 0..mote_count {
+                    step_mote(i, &mut single, edges, 0.25, t, &style, extended, &mut want);
+                }
+                assert_eq!(bits(&out), bits(&want));
+                assert_eq!(bits(&grouped), bits(&single));
+            }
+        }
+    }
+
+    /// `update_motes` takes four motes at a time with the `simd` feature;
+    /// each must still come out as if stepped alone
+    #[cfg(feature = "motes")]
+    #[test]
+    fn test_mote_steps_match_single_motes_bit_for_bit() {
+        let seeds = generate_golden_seeds(640.0, 480.0, 60);
+        let edges = compute_edges(&seeds, &compute_delaunay(&seeds, 640.0, 480.0)).unwrap();
+        let edge_count = edges.len() / 4;
+        // Some motes start past the last edge and some about to jump, and
+        // the count leaves a partial group
+        let start: Vec<f64> = (0..103)
+            .flat_map(|i| {
+                let edge = i * 7 % (edge_count + 9);
+                [edge as f64, (i % 10) as f64 * 0.11, 0.05 + (i % 7) as f64 * 0.1]
+            })
+            .collect();
+        assert_steps_match_single_motes(&start, &edges);
+        assert_steps_match_single_motes(&start, &[]);
+        assert_steps_match_single_motes(&buffers::to_f32(&start), &buffers::to_f32(&edges));
+    }
+
+    #[cfg(feature = "noise")]
+    #[test]
+    #[allow(clippy::manual_range_contains)]
     fn test_simplex_noise() {
         let n1 = simplex_noise_2d(0.0, 0.0);
         let n2 = simplex_noise_2d(0.5, 0.5);
@@ -0,0 +1,139 @@
+//! Voronoi cell coverage of raster masks
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::spatial::PointGrid;
+use crate::Point;
+
+/// Fraction of each seed's Voronoi cell covered by a mask, in [0, 1]
+///
+/// The mask is stretched over the `width × height` canvas, so its
+/// resolution need not match. It may be single-channel (`mask_w · mask_h`
+/// bytes) or RGBA as returned by `getImageData` (4 bytes per pixel, alpha
+/// is used). A pixel is covered when its value exceeds `threshold`.
+///
+/// Cells are rasterized at mask resolution: every pixel center is assigned
+/// to its nearest seed (ties to the lower index), making the result exact
+/// up to pixel quantization. Coverage is measured over the part of the
+/// cell inside the canvas; cells containing no pixel center, including
+/// those entirely outside the canvas, get 0.
+#[wasm_bindgen]
+pub fn cell_mask_coverage(
+    points: &[f64],
+    width: f64,
+    height: f64,
+    mask: &[u8],
+    mask_w: u32,
+    mask_h: u32,
+    threshold: u8,
+) -> Result<Vec<f32>> {
+    let n = check_points(points)?;
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if points.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    // Overflows usize on wasm32 for masks no buffer could hold anyway
+    let pixels = (mask_w as usize)
+        .checked_mul(mask_h as usize)
+        .filter(|p| p.checked_mul(4).is_some())
+        .ok_or_else(|| Error::InvalidData(format!("mask of {mask_w}x{mask_h} is too large")))?;
+    let stride = match mask.len() {
+        len if len == pixels => 1,
+        len if len == pixels * 4 => 4,
+        len => {
+            return Err(Error::InvalidLength(format!(
+                "mask of {mask_w}x{mask_h} needs {pixels} or {} bytes, got {len}",
+                pixels * 4
+            )))
+        }
+    };
+    let channel = stride - 1;
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+
+    let seeds: Vec<Point> = points.chunks_exact(2).map(|c| Point { x: c[0], y: c[1] }).collect();
+    let grid = PointGrid::new(&seeds, [0.0, 0.0, width, height]);
+    let mut total = vec![0u32; n];
+    let mut covered = vec![0u32; n];
+    let (sx, sy) = (width / mask_w as f64, height / mask_h as f64);
+    for row in 0..mask_h as usize {
+        let y = (row as f64 + 0.5) * sy;
+        for col in 0..mask_w as usize {
+            let p = Point { x: (col as f64 + 0.5) * sx, y };
+            let seed = grid.nearest(p).unwrap_or(0);
+            total[seed as usize] += 1;
+            if mask[(row * mask_w as usize + col) * stride + channel] > threshold {
+                covered[seed as usize] += 1;
+            }
+        }
+    }
+
+    Ok(covered
+        .iter()
+        .zip(&total)
+        .map(|(&c, &t)| if t == 0 { 0.0 } else { c as f32 / t as f32 })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_half_mask_splits_two_cells() {
+        // Seeds left and right of center; mask opaque on the left half only
+        let points = [25.0, 50.0, 75.0, 50.0];
+        let mask: Vec<u8> = (0..40 * 20).map(|i| if i % 40 < 20 { 255 } else { 0 }).collect();
+        let cov = cell_mask_coverage(&points, 100.0, 100.0, &mask, 40, 20, 127).unwrap();
+        assert_eq!(cov, vec![1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_rgba_mask_scaled_and_outside_cells() {
+        // 2x2 RGBA mask over a 200x200 canvas: only the top-left quadrant
+        let mut mask = vec![0u8; 16];
+        mask[3] = 200;
+        let points = [50.0, 50.0, 150.0, 150.0, 1000.0, 1000.0];
+        let cov = cell_mask_coverage(&points, 200.0, 200.0, &mask, 2, 2, 0).unwrap();
+        // The off-diagonal pixel centers are equidistant and go to seed 0
+        assert_eq!(cov[0], 1.0 / 3.0);
+        assert_eq!(cov[1], 0.0);
+        assert_eq!(cov[2], 0.0);
+        assert_eq!(
+            code_of(cell_mask_coverage(&points, 200.0, 200.0, &mask[..15], 2, 2, 0)),
+            ErrorCode::InvalidLength
+        );
+        assert_eq!(
+            code_of(cell_mask_coverage(&points, 200.0, 200.0, &mask, u32::MAX, u32::MAX, 0)),
+            ErrorCode::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_coverage_converges_with_mask_resolution() {
+        // Circle mask; the center seed's coverage should match analytic
+        // area ratio regardless of mask resolution
+        let points = [50.0, 50.0, 10.0, 10.0, 90.0, 10.0, 10.0, 90.0, 90.0, 90.0];
+        let circle = |w: u32| -> Vec<u8> {
+            (0..w * w)
+                .map(|i| {
+                    let x = ((i % w) as f64 + 0.5) * 100.0 / w as f64 - 50.0;
+                    let y = ((i / w) as f64 + 0.5) * 100.0 / w as f64 - 50.0;
+                    if x * x + y * y < 20.0 * 20.0 { 255 } else { 0 }
+                })
+                .collect()
+        };
+        let coarse = cell_mask_coverage(&points, 100.0, 100.0, &circle(50), 50, 50, 0).unwrap();
+        let fine = cell_mask_coverage(&points, 100.0, 100.0, &circle(400), 400, 400, 0).unwrap();
+        // Center cell is the diamond |x| + |y| < 40 with area 3200
+        let expected = (std::f64::consts::PI * 400.0 / 3200.0) as f32;
+        assert!((fine[0] - expected).abs() < 0.01, "{} vs {expected}", fine[0]);
+        assert!((coarse[0] - fine[0]).abs() < 0.05);
+    }
+}
@@ -0,0 +1,417 @@
+//! Medial axis of the gaps between inset Voronoi cells
+//!
+//! Every cell is shrunk by `inset` and a grid of samples records which
+//! inset cell is nearest and how far away it is. The medial axis of the
+//! gap region is where the nearest cell changes: the ridge of the distance
+//! field. Ridges are located to sub-sample precision by interpolating the
+//! distance difference along grid sides, and chained through grid squares
+//! into polylines that meet at junctions where three or more cells do.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, Csr};
+use crate::mesh::{Mesh, NONE, SUPER};
+use crate::predicates::orient2d;
+use crate::spatial::PointGrid;
+use crate::voronoi::{clip_half_plane, voronoi_cells};
+use crate::Point;
+
+/// Samples `approximate_medial_axis` may allocate
+const MAX_SAMPLES: usize = 1 << 24;
+
+/// Polylines sharing one vertex array
+///
+/// Polyline `i` is vertices `offsets[i]..offsets[i + 1]`; a closed loop
+/// repeats its first vertex at the end.
+#[wasm_bindgen]
+pub struct ContourResult {
+    vertices: Vec<f64>,
+    distances: Vec<f32>,
+    offsets: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl ContourResult {
+    /// All polyline vertices `[x0, y0, x1, y1, ...]`
+    pub fn vertices(&self) -> Vec<f64> {
+        self.vertices.clone()
+    }
+
+    /// Clearance per vertex: distance to the nearest inset cell
+    pub fn distances(&self) -> Vec<f32> {
+        self.distances.clone()
+    }
+
+    /// Start index of each polyline in vertex units, plus a final end entry
+    pub fn offsets(&self) -> Vec<u32> {
+        self.offsets.clone()
+    }
+
+    pub fn polyline_count(&self) -> usize {
+        self.offsets.len() - 1
+    }
+}
+
+/// Centerlines of the grout between Voronoi cells inset by `inset`
+///
+/// The distance field is sampled on a grid with `resolution` samples
+/// along the longer canvas side, so vertices are accurate to a fraction of
+/// `max(width, height) / resolution`. With `inset` 0 the result traces
+/// the interior Voronoi edges; only cells count as features, not the
+/// canvas border. Cells inset away entirely leave no ridge of their own.
+#[wasm_bindgen]
+pub fn approximate_medial_axis(
+    points: &[f64],
+    width: f64,
+    height: f64,
+    inset: f64,
+    resolution: u32,
+) -> Result<ContourResult> {
+    let n = check_points(points)?;
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if points.iter().any(|v| !v.is_finite()) || !inset.is_finite() {
+        return Err(Error::NonFiniteInput("points and inset must be finite".into()));
+    }
+    if inset < 0.0 {
+        return Err(Error::InvalidArgument(format!("inset must be non-negative, got {inset}")));
+    }
+    if resolution < 2 {
+        return Err(Error::InvalidArgument(format!(
+            "resolution must be at least 2, got {resolution}"
+        )));
+    }
+    let step = width.max(height) / resolution as f64;
+    let (cols, rows) = ((width / step).ceil() as usize, (height / step).ceil() as usize);
+    if cols * rows > MAX_SAMPLES {
+        return Err(Error::TooLarge(format!("a {cols}x{rows} sample grid is too many samples")));
+    }
+    let empty =
+        || ContourResult { vertices: Vec::new(), distances: Vec::new(), offsets: vec![0] };
+    if n < 2 {
+        return Ok(empty());
+    }
+
+    let seeds: Vec<Point> = points.chunks_exact(2).map(|c| Point { x: c[0], y: c[1] }).collect();
+    let cells = inset_cells(&seeds, width, height, inset);
+    let mesh = Mesh::build(&seeds, Some([0.0, 0.0, width, height]));
+    let edges: Vec<(u32, u32)> =
+        mesh.real_edges().into_iter().map(|(a, b)| (a - SUPER, b - SUPER)).collect();
+    let csr = Csr::from_edges(n, &edges);
+    let grid = PointGrid::new(&seeds, [0.0, 0.0, width, height]);
+
+    // Nearest inset cell per sample, searched over the containing cell
+    // and its Voronoi neighbors
+    let (sx, sy) = (width / cols as f64, height / rows as f64);
+    let sample =
+        |c: usize, r: usize| Point { x: (c as f64 + 0.5) * sx, y: (r as f64 + 0.5) * sy };
+    let mut label = vec![NONE; cols * rows];
+    for r in 0..rows {
+        for c in 0..cols {
+            let p = sample(c, r);
+            let Some(home) = grid.nearest(p) else { continue };
+            let candidates = std::iter::once(home).chain(csr.neighbors(home).iter().copied());
+            let mut best = (f64::INFINITY, NONE);
+            for j in candidates {
+                let d = poly_distance(&cells[j as usize], p);
+                if d < best.0 || (d == best.0 && j < best.1) {
+                    best = (d, j);
+                }
+            }
+            label[r * cols + c] = best.1;
+        }
+    }
+
+    // Ridge crossings on grid sides: horizontal sides first, then vertical
+    let mut vertices: Vec<Point> = Vec::new();
+    let mut distances: Vec<f32> = Vec::new();
+    let mut side_node = vec![NONE; 2 * cols * rows];
+    let mut cross = |p: Point, q: Point, a: u32, b: u32| {
+        let (pa, pb) = (&cells[a as usize], &cells[b as usize]);
+        let f1 = poly_distance(pa, p) - poly_distance(pb, p);
+        let f2 = poly_distance(pa, q) - poly_distance(pb, q);
+        let t = if f1 != f2 { (f1 / (f1 - f2)).clamp(0.0, 1.0) } else { 0.5 };
+        let x = Point { x: p.x + (q.x - p.x) * t, y: p.y + (q.y - p.y) * t };
+        vertices.push(x);
+        distances.push(poly_distance(pa, x).min(poly_distance(pb, x)) as f32);
+        (vertices.len() - 1) as u32
+    };
+    for r in 0..rows {
+        for c in 0..cols {
+            let a = label[r * cols + c];
+            if c + 1 < cols {
+                let b = label[r * cols + c + 1];
+                if a != b && a != NONE && b != NONE {
+                    side_node[r * cols + c] = cross(sample(c, r), sample(c + 1, r), a, b);
+                }
+            }
+            if r + 1 < rows {
+                let b = label[(r + 1) * cols + c];
+                if a != b && a != NONE && b != NONE {
+                    side_node[cols * rows + r * cols + c] =
+                        cross(sample(c, r), sample(c, r + 1), a, b);
+                }
+            }
+        }
+    }
+
+    // Two crossings in a grid square join directly; more meet at a
+    // junction placed at their mean
+    let mut links: Vec<(u32, u32)> = Vec::new();
+    for r in 0..rows.saturating_sub(1) {
+        for c in 0..cols.saturating_sub(1) {
+            let sides = [
+                side_node[r * cols + c],
+                side_node[(r + 1) * cols + c],
+                side_node[cols * rows + r * cols + c],
+                side_node[cols * rows + r * cols + c + 1],
+            ];
+            let hits: Vec<u32> = sides.into_iter().filter(|&s| s != NONE).collect();
+            match hits.len() {
+                0 | 1 => {}
+                2 => links.push((hits[0].min(hits[1]), hits[0].max(hits[1]))),
+                k => {
+                    let (mut x, mut y, mut d) = (0.0, 0.0, 0.0f32);
+                    for &h in &hits {
+                        x += vertices[h as usize].x / k as f64;
+                        y += vertices[h as usize].y / k as f64;
+                        d += distances[h as usize] / k as f32;
+                    }
+                    let j = vertices.len() as u32;
+                    vertices.push(Point { x, y });
+                    distances.push(d);
+                    links.extend(hits.iter().map(|&h| (h, j)));
+                }
+            }
+        }
+    }
+    if links.is_empty() {
+        return Ok(empty());
+    }
+
+    let chains = chain_links(vertices.len(), &links);
+    let mut out = empty();
+    for chain in chains {
+        for &v in &chain {
+            out.vertices.extend_from_slice(&[vertices[v as usize].x, vertices[v as usize].y]);
+            out.distances.push(distances[v as usize]);
+        }
+        out.offsets.push(out.distances.len() as u32);
+    }
+    Ok(out)
+}
+
+/// Voronoi cells with every edge moved `inset` inwards; cells that
+/// vanish come back empty
+fn inset_cells(seeds: &[Point], width: f64, height: f64, inset: f64) -> Vec<Vec<Point>> {
+    let (vertices, offsets) = voronoi_cells(seeds, width, height);
+    let mut scratch = Vec::new();
+    offsets
+        .windows(2)
+        .map(|w| {
+            let cell: Vec<Point> = vertices[w[0] as usize * 2..w[1] as usize * 2]
+                .chunks(2)
+                .map(|c| Point { x: c[0], y: c[1] })
+                .collect();
+            if inset == 0.0 {
+                return cell;
+            }
+            let mut poly = cell.clone();
+            for i in 0..cell.len() {
+                let (a, b) = (cell[i], cell[(i + 1) % cell.len()]);
+                let len = (b.x - a.x).hypot(b.y - a.y);
+                if len == 0.0 {
+                    continue;
+                }
+                // Inward normal of a counter-clockwise edge
+                let inward = Point { x: -(b.y - a.y) / len, y: (b.x - a.x) / len };
+                let origin = Point { x: a.x + inward.x * inset, y: a.y + inward.y * inset };
+                let outward = Point { x: -inward.x, y: -inward.y };
+                clip_half_plane(&poly, origin, outward, &mut scratch);
+                std::mem::swap(&mut poly, &mut scratch);
+                if poly.is_empty() {
+                    break;
+                }
+            }
+            poly
+        })
+        .collect()
+}
+
+/// Distance from `p` to a convex counter-clockwise polygon: zero inside,
+/// infinite for an empty one
+fn poly_distance(poly: &[Point], p: Point) -> f64 {
+    let n = poly.len();
+    if n == 0 {
+        return f64::INFINITY;
+    }
+    let mut inside = n >= 3;
+    let mut best = f64::INFINITY;
+    for i in 0..n {
+        let (a, b) = (poly[i], poly[(i + 1) % n]);
+        if orient2d(a, b, p) < 0.0 {
+            inside = false;
+        }
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let len2 = dx * dx + dy * dy;
+        let t = if len2 > 0.0 {
+            (((p.x - a.x) * dx + (p.y - a.y) * dy) / len2).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        best = best.min((a.x + dx * t - p.x).hypot(a.y + dy * t - p.y));
+    }
+    if inside { 0.0 } else { best }
+}
+
+/// Split a graph into maximal paths between nodes of degree other than
+/// two, then close whatever loops are left
+fn chain_links(node_count: usize, links: &[(u32, u32)]) -> Vec<Vec<u32>> {
+    let mut links = links.to_vec();
+    links.sort_unstable();
+    links.dedup();
+    let csr = Csr::from_edges(node_count, &links);
+    let degree = |v: u32| csr.neighbors(v).len();
+    let mut used = std::collections::HashSet::new();
+    let mut take = |a: u32, b: u32| used.insert((a.min(b), a.max(b)));
+
+    let mut chains = Vec::new();
+    let walk = |start: u32, first: u32, take: &mut dyn FnMut(u32, u32) -> bool| {
+        let mut chain = vec![start];
+        let (mut prev, mut cur) = (start, first);
+        loop {
+            chain.push(cur);
+            if degree(cur) != 2 || cur == start {
+                break;
+            }
+            let next = csr.neighbors(cur).iter().copied().find(|&w| w != prev);
+            match next {
+                Some(next) if take(cur, next) => (prev, cur) = (cur, next),
+                _ => break,
+            }
+        }
+        chain
+    };
+    for v in 0..node_count as u32 {
+        if degree(v) == 2 || degree(v) == 0 {
+            continue;
+        }
+        for &w in csr.neighbors(v) {
+            if take(v, w) {
+                chains.push(walk(v, w, &mut take));
+            }
+        }
+    }
+    for v in 0..node_count as u32 {
+        for &w in csr.neighbors(v) {
+            if take(v, w) {
+                chains.push(walk(v, w, &mut take));
+            }
+        }
+    }
+    chains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+
+    fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+        poly_distance(&[a, b], p)
+    }
+
+    /// Voronoi edges shared by two cells, i.e. not on the canvas border
+    fn interior_voronoi_edges(seeds: &[Point], w: f64, h: f64) -> Vec<[Point; 2]> {
+        let on_border = |p: Point| p.x == 0.0 || p.y == 0.0 || p.x == w || p.y == h;
+        inset_cells(seeds, w, h, 0.0)
+            .iter()
+            .flat_map(|cell| (0..cell.len()).map(move |i| [cell[i], cell[(i + 1) % cell.len()]]))
+            .filter(|[a, b]| !(on_border(*a) && on_border(*b)))
+            .collect()
+    }
+
+    fn random_seeds(seed: u64, n: usize, w: f64, h: f64) -> Vec<f64> {
+        let mut rng = Rng::new(seed);
+        (0..n).flat_map(|_| [rng.range(0.0, w), rng.range(0.0, h)]).collect()
+    }
+
+    #[test]
+    fn test_zero_inset_traces_voronoi_edges() {
+        let (w, h) = (400.0, 300.0);
+        let flat = random_seeds(3, 40, w, h);
+        let seeds: Vec<Point> = flat.chunks(2).map(|c| Point { x: c[0], y: c[1] }).collect();
+        let axis = approximate_medial_axis(&flat, w, h, 0.0, 400).unwrap();
+        let edges = interior_voronoi_edges(&seeds, w, h);
+        let step = 1.0;
+        let verts: Vec<Point> =
+            axis.vertices().chunks(2).map(|c| Point { x: c[0], y: c[1] }).collect();
+        assert!(axis.polyline_count() > 0);
+
+        // Every ridge vertex sits on a Voronoi edge...
+        let near_edge = |p: Point| {
+            edges.iter().map(|e| segment_distance(p, e[0], e[1])).fold(f64::INFINITY, f64::min)
+        };
+        for &p in &verts {
+            assert!(near_edge(p) < step, "ridge vertex {p:?} is {} off", near_edge(p));
+        }
+        // ...and every Voronoi edge is traced by ridge segments
+        let segments: Vec<[Point; 2]> = axis
+            .offsets()
+            .windows(2)
+            .flat_map(|o| (o[0] as usize..o[1] as usize - 1).map(|i| [verts[i], verts[i + 1]]))
+            .collect();
+        for e in &edges {
+            for k in 1..8 {
+                let t = k as f64 / 8.0;
+                let (dx, dy) = (e[1].x - e[0].x, e[1].y - e[0].y);
+                let p = Point { x: e[0].x + dx * t, y: e[0].y + dy * t };
+                let d = segments
+                    .iter()
+                    .map(|s| segment_distance(p, s[0], s[1]))
+                    .fold(f64::INFINITY, f64::min);
+                assert!(d < 2.0 * step, "voronoi edge point {p:?} is {d} from the axis");
+            }
+        }
+        assert!(axis.distances().iter().all(|&d| (d as f64) < step));
+    }
+
+    #[test]
+    fn test_inset_keeps_centerlines_with_clearance() {
+        let (w, h) = (400.0, 300.0);
+        let flat = random_seeds(8, 25, w, h);
+        let seeds: Vec<Point> = flat.chunks(2).map(|c| Point { x: c[0], y: c[1] }).collect();
+        let axis = approximate_medial_axis(&flat, w, h, 3.0, 300).unwrap();
+        let edges = interior_voronoi_edges(&seeds, w, h);
+        let offsets = axis.offsets();
+        assert_eq!(offsets.len(), axis.polyline_count() + 1);
+        assert_eq!(*offsets.last().unwrap() as usize * 2, axis.vertices().len());
+        for (p, &d) in axis.vertices().chunks(2).zip(&axis.distances()) {
+            let p = Point { x: p[0], y: p[1] };
+            assert!(d as f64 > 3.0 - 1.5, "clearance {d} below the inset");
+            // Cells are inset from the canvas border too, which bends the
+            // ridge ends within a few insets of it
+            if p.x.min(p.y).min(w - p.x).min(h - p.y) < 10.0 {
+                continue;
+            }
+            let off = edges
+                .iter()
+                .map(|e| segment_distance(p, e[0], e[1]))
+                .fold(f64::INFINITY, f64::min);
+            assert!(off < 1.5, "centerline vertex {p:?} is {off} off");
+        }
+
+        let code = |inset: f64, resolution: u32, points: &[f64]| {
+            code_of(approximate_medial_axis(points, w, h, inset, resolution))
+        };
+        assert_eq!(code(-1.0, 100, &flat), ErrorCode::InvalidArgument);
+        assert_eq!(code(f64::NAN, 100, &flat), ErrorCode::NonFiniteInput);
+        assert_eq!(code(1.0, 1, &flat), ErrorCode::InvalidArgument);
+        assert_eq!(code(1.0, 100, &flat[..3]), ErrorCode::InvalidLength);
+        let single = approximate_medial_axis(&flat[..2], w, h, 0.0, 100).unwrap();
+        assert_eq!(single.polyline_count(), 0);
+    }
+}
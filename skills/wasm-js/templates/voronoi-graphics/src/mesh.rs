@@ -0,0 +1,1268 @@
+//! Adjacency-based triangulation core shared by the stateful classes
+//!
+//! Triangles are stored in slots with explicit neighbor links, so a point
+//! can be located by walking and the mesh can be repaired locally with
+//! Bowyer-Watson cavities and Lawson edge flips instead of rebuilt.
+//!
+//! Conventions:
+//! - internal vertices `0..SUPER` are the super-triangle corners; input
+//!   point `i` is internal vertex `i + SUPER`
+//! - every live triangle is counter-clockwise
+//! - edge `k` of a triangle joins `v[(k + 1) % 3]` and `v[(k + 2) % 3]`,
+//!   i.e. it is the edge opposite vertex `k`, and `adj[t][k]` is the
+//!   triangle on the other side of it (`NONE` on the super-triangle rim)
+
+use crate::codec::{ByteReader, ByteWriter};
+use crate::error::{Error, Result};
+use crate::predicates::{incircle, orient2d, power_incircle};
+use crate::Point;
+
+pub(crate) const NONE: u32 = u32::MAX;
+/// Number of super-triangle vertices at the front of the vertex list
+pub(crate) const SUPER: u32 = 3;
+
+/// Topology change recorded while `Mesh::track_changes` is set
+///
+/// Edges are canonical internal vertex pairs (smaller index first).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum EdgeChange {
+    Added(u32, u32),
+    Removed(u32, u32),
+}
+
+/// Triangle created or destroyed while `Mesh::track_changes` is set, as
+/// its corners in the order the mesh held them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TriangleChange {
+    Added([u32; 3]),
+    Removed([u32; 3]),
+}
+
+fn canonical(a: u32, b: u32) -> (u32, u32) {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+/// Cells per side of the grid `hilbert_order` snaps points to
+const HILBERT_SIDE: u32 = 1 << 16;
+
+/// Distance of cell `(x, y)` along the Hilbert curve over the grid
+fn hilbert_distance(mut x: u32, mut y: u32) -> u64 {
+    let mut d = 0u64;
+    let mut s = HILBERT_SIDE / 2;
+    while s > 0 {
+        let rx = (x & s != 0) as u32;
+        let ry = (y & s != 0) as u32;
+        d += s as u64 * s as u64 * ((3 * rx) ^ ry) as u64;
+        // Rotate the quadrant so the curve stays continuous
+        if ry == 0 {
+            if rx == 1 {
+                x = HILBERT_SIDE - 1 - x;
+                y = HILBERT_SIDE - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    d
+}
+
+/// Indices of `points` sorted along a Hilbert curve over their bounding
+/// box; ties, coincident points included, stay in index order
+pub(crate) fn hilbert_order(points: &[Point]) -> Vec<u32> {
+    let mut keyed = Vec::new();
+    hilbert_keys(points, &mut keyed);
+    keyed.into_iter().map(|(_, i)| i).collect()
+}
+
+/// `hilbert_order` as sorted `(distance, index)` pairs in `keyed`
+fn hilbert_keys(points: &[Point], keyed: &mut Vec<(u64, u32)>) {
+    let mut min = Point::new(f64::INFINITY, f64::INFINITY);
+    let mut max = Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for p in points {
+        min = Point::new(min.x.min(p.x), min.y.min(p.y));
+        max = Point::new(max.x.max(p.x), max.y.max(p.y));
+    }
+    let span = (max.x - min.x).max(max.y - min.y);
+    let scale = if span > 0.0 { (HILBERT_SIDE - 1) as f64 / span } else { 0.0 };
+    // Non-finite coordinates land in cell 0 via the saturating cast
+    let cell = |c: f64, lo: f64| ((c - lo) * scale) as u32;
+    keyed.clear();
+    keyed.extend(
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (hilbert_distance(cell(p.x, min.x), cell(p.y, min.y)), i as u32)),
+    );
+    keyed.sort_unstable();
+}
+
+/// Work buffers kept from one insertion, or rebuild, to the next so a
+/// long-lived mesh stops allocating once they have grown to fit
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Scratch {
+    /// Cavity of the point being inserted
+    bad: Vec<u32>,
+    /// Rim of that cavity as `(a, b, triangle outside)`
+    boundary: Vec<(u32, u32, u32)>,
+    /// New triangles keyed by their first rim vertex
+    fan: Vec<(u32, u32)>,
+    /// Hilbert keys of the points a rebuild inserts
+    order: Vec<(u64, u32)>,
+    /// Copy of those points, since the rebuild clears `points`
+    points: Vec<Point>,
+}
+
+/// Heap bytes `v` holds, used or not
+pub(crate) fn capacity_bytes<T>(v: &Vec<T>) -> usize {
+    v.capacity() * std::mem::size_of::<T>()
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Mesh {
+    pub(crate) points: Vec<Point>,
+    pub(crate) tris: Vec<[u32; 3]>,
+    pub(crate) adj: Vec<[u32; 3]>,
+    pub(crate) alive: Vec<bool>,
+    pub(crate) free: Vec<u32>,
+    /// One live triangle incident to each vertex, `NONE` if unlinked
+    pub(crate) vert_tri: Vec<u32>,
+    pub(crate) last: u32,
+    pub(crate) track_changes: bool,
+    pub(crate) changes: Vec<EdgeChange>,
+    pub(crate) triangle_changes: Vec<TriangleChange>,
+    /// Incircle margin, relative to the shared edge length to the fourth
+    /// power, an edge between real points must exceed before it flips
+    pub(crate) flip_tolerance: f64,
+    /// Per-vertex weights of a regular triangulation, super corners
+    /// included; empty for a plain Delaunay mesh. Only insertion honours
+    /// them, flips and removal do not
+    pub(crate) weights: Vec<f64>,
+    /// Set by `close_hull`: the corners count as infinitely far, so no
+    /// edge touching them is ever illegal
+    hull_closed: bool,
+    mark: Vec<u32>,
+    stamp: u32,
+    scratch: Scratch,
+}
+
+impl Mesh {
+    /// Super triangle enclosing the bounding box with a generous margin
+    ///
+    /// The margin is proportional to the box, so the corners stay
+    /// resolvable however small or far from the origin it is; a box of
+    /// coincident points is widened relative to their magnitude. Corners
+    /// overflow to infinity only for spans near `f64::MAX`.
+    pub(crate) fn super_triangle(min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> [Point; 3] {
+        let cx = (min_x + max_x) / 2.0;
+        let cy = (min_y + max_y) / 2.0;
+        let d = (max_x - min_x)
+            .max(max_y - min_y)
+            .max(1e-9 * cx.abs().max(cy.abs()))
+            .max(f64::MIN_POSITIVE);
+        [
+            Point { x: cx - 20.0 * d, y: cy - 10.0 * d },
+            Point { x: cx + 20.0 * d, y: cy - 10.0 * d },
+            Point { x: cx, y: cy + 20.0 * d },
+        ]
+    }
+
+    /// Empty mesh consisting only of the given CCW super triangle
+    pub(crate) fn with_super(corners: [Point; 3]) -> Mesh {
+        Mesh {
+            points: corners.to_vec(),
+            tris: vec![[0, 1, 2]],
+            adj: vec![[NONE; 3]],
+            alive: vec![true],
+            free: Vec::new(),
+            vert_tri: vec![0; SUPER as usize],
+            last: 0,
+            track_changes: false,
+            changes: Vec::new(),
+            triangle_changes: Vec::new(),
+            flip_tolerance: 0.0,
+            weights: Vec::new(),
+            hull_closed: false,
+            mark: vec![0],
+            stamp: 0,
+            scratch: Scratch::default(),
+        }
+    }
+
+    /// Back to `with_super(corners)`, keeping every buffer's capacity
+    pub(crate) fn reset(&mut self, corners: [Point; 3]) {
+        self.points.clear();
+        self.points.extend_from_slice(&corners);
+        self.tris.clear();
+        self.tris.push([0, 1, 2]);
+        self.adj.clear();
+        self.adj.push([NONE; 3]);
+        self.alive.clear();
+        self.alive.push(true);
+        self.free.clear();
+        self.vert_tri.clear();
+        self.vert_tri.resize(SUPER as usize, 0);
+        self.last = 0;
+        self.track_changes = false;
+        self.clear_changes();
+        self.flip_tolerance = 0.0;
+        self.weights.clear();
+        self.hull_closed = false;
+        self.mark.clear();
+        self.mark.push(0);
+        self.stamp = 0;
+    }
+
+    /// Triangulate `points`; the super triangle covers them and `bounds`
+    ///
+    /// Points keep their indices but are inserted along a Hilbert curve,
+    /// so each walk starts next to its target instead of crossing the
+    /// mesh. Of coincident points the first one is linked, as before.
+    pub(crate) fn build(points: &[Point], bounds: Option<[f64; 4]>) -> Mesh {
+        let mut mesh = Mesh::with_super(Self::enclosing(points, bounds));
+        mesh.points.extend_from_slice(points);
+        mesh.vert_tri.resize(mesh.points.len(), NONE);
+        mesh.tris.reserve(points.len() * 2);
+        profiled!("insertion", {
+            for v in hilbert_order(points) {
+                mesh.insert_vertex(v + SUPER);
+            }
+        });
+        mesh
+    }
+
+    /// `build` sized from the points alone, then `close_hull`: the
+    /// Delaunay triangulation of their whole convex hull, for meshes that
+    /// are built once and read
+    pub(crate) fn build_closed(points: &[Point]) -> Mesh {
+        let mut mesh = Mesh::build(points, None);
+        mesh.close_hull();
+        mesh
+    }
+
+    /// `build` again over the mesh's current points, in place
+    ///
+    /// The result is the mesh `build` would return for them, but every
+    /// buffer, `Scratch` included, keeps its capacity, so rebuilding a mesh
+    /// of the same size again and again allocates nothing.
+    pub(crate) fn rebuild(&mut self, bounds: Option<[f64; 4]>) {
+        let mut points = std::mem::take(&mut self.scratch.points);
+        let mut order = std::mem::take(&mut self.scratch.order);
+        points.clear();
+        points.extend_from_slice(&self.points[SUPER as usize..]);
+        hilbert_keys(&points, &mut order);
+        self.reset(Self::enclosing(&points, bounds));
+        self.points.extend_from_slice(&points);
+        self.vert_tri.resize(self.points.len(), NONE);
+        self.tris.reserve(points.len() * 2);
+        profiled!("insertion", {
+            for &(_, v) in &order {
+                self.insert_vertex(v + SUPER);
+            }
+        });
+        self.scratch.points = points;
+        self.scratch.order = order;
+    }
+
+    /// Heap bytes held per group of buffers, for `memory_stats`
+    pub(crate) fn pool_bytes(&self) -> [(&'static str, usize); 5] {
+        let s = &self.scratch;
+        [
+            ("points", capacity_bytes(&self.points) + capacity_bytes(&self.weights)),
+            (
+                "triangles",
+                capacity_bytes(&self.tris)
+                    + capacity_bytes(&self.adj)
+                    + capacity_bytes(&self.alive)
+                    + capacity_bytes(&self.free)
+                    + capacity_bytes(&self.mark),
+            ),
+            ("vertex_links", capacity_bytes(&self.vert_tri)),
+            (
+                "change_log",
+                capacity_bytes(&self.changes) + capacity_bytes(&self.triangle_changes),
+            ),
+            (
+                "insertion_scratch",
+                capacity_bytes(&s.bad)
+                    + capacity_bytes(&s.boundary)
+                    + capacity_bytes(&s.fan)
+                    + capacity_bytes(&s.order)
+                    + capacity_bytes(&s.points),
+            ),
+        ]
+    }
+
+    /// Release spare capacity, dropping the scratch buffers entirely
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.points.shrink_to_fit();
+        self.weights.shrink_to_fit();
+        self.tris.shrink_to_fit();
+        self.adj.shrink_to_fit();
+        self.alive.shrink_to_fit();
+        self.free.shrink_to_fit();
+        self.mark.shrink_to_fit();
+        self.vert_tri.shrink_to_fit();
+        self.changes.shrink_to_fit();
+        self.triangle_changes.shrink_to_fit();
+        self.scratch = Scratch::default();
+    }
+
+    /// Regular triangulation of `points` with `weights`, whose dual is
+    /// the power diagram
+    ///
+    /// A point whose lifted position lies above the others' lower hull has
+    /// no power cell and is left unlinked, whether it comes before or after
+    /// the points that hide it. Coincident points are linked first come.
+    #[cfg(feature = "voronoi")]
+    pub(crate) fn build_weighted(
+        points: &[Point],
+        weights: &[f64],
+        bounds: Option<[f64; 4]>,
+    ) -> Mesh {
+        let mut mesh = Mesh::with_super(Self::enclosing(points, bounds));
+        mesh.points.extend_from_slice(points);
+        mesh.vert_tri.resize(mesh.points.len(), NONE);
+        mesh.weights = [0.0; SUPER as usize].into_iter().chain(weights.iter().copied()).collect();
+        mesh.tris.reserve(points.len() * 2);
+        for v in hilbert_order(points) {
+            mesh.insert_vertex(v + SUPER);
+        }
+        mesh
+    }
+
+    pub(crate) fn enclosing(points: &[Point], bounds: Option<[f64; 4]>) -> [Point; 3] {
+        let [mut min_x, mut min_y, mut max_x, mut max_y] =
+            bounds.unwrap_or([f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY]);
+        for p in points {
+            min_x = min_x.min(p.x);
+            min_y = min_y.min(p.y);
+            max_x = max_x.max(p.x);
+            max_y = max_y.max(p.y);
+        }
+        if min_x > max_x {
+            (min_x, min_y, max_x, max_y) = (0.0, 0.0, 1.0, 1.0);
+        }
+        Self::super_triangle(min_x, min_y, max_x, max_y)
+    }
+
+    #[inline]
+    pub(crate) fn real_count(&self) -> usize {
+        self.points.len() - SUPER as usize
+    }
+
+    #[inline]
+    pub(crate) fn is_real_triangle(&self, t: usize) -> bool {
+        self.alive[t] && self.tris[t].iter().all(|&v| v >= SUPER)
+    }
+
+    /// Live triangles not touching the super triangle, in slot order,
+    /// as input point indices
+    pub(crate) fn real_triangles(&self) -> impl Iterator<Item = [u32; 3]> + '_ {
+        (0..self.tris.len())
+            .filter(|&t| self.is_real_triangle(t))
+            .map(|t| self.tris[t].map(|v| v - SUPER))
+    }
+
+    /// Whether `p` lies strictly inside the super triangle
+    pub(crate) fn encloses(&self, p: Point) -> bool {
+        let [a, b, c] = [self.points[0], self.points[1], self.points[2]];
+        orient2d(a, b, p) > 0.0 && orient2d(b, c, p) > 0.0 && orient2d(c, a, p) > 0.0
+    }
+
+    /// True when every live triangle is strictly counter-clockwise
+    pub(crate) fn orientation_valid(&self) -> bool {
+        (0..self.tris.len()).filter(|&t| self.alive[t]).all(|t| {
+            let [a, b, c] = self.tris[t].map(|v| self.points[v as usize]);
+            orient2d(a, b, c) > 0.0
+        })
+    }
+
+    fn alloc(&mut self, v: [u32; 3], a: [u32; 3]) -> u32 {
+        self.record_triangle(TriangleChange::Added(v));
+        if let Some(t) = self.free.pop() {
+            self.tris[t as usize] = v;
+            self.adj[t as usize] = a;
+            self.alive[t as usize] = true;
+            t
+        } else {
+            self.tris.push(v);
+            self.adj.push(a);
+            self.alive.push(true);
+            self.mark.push(0);
+            (self.tris.len() - 1) as u32
+        }
+    }
+
+    fn kill(&mut self, t: u32) {
+        self.record_triangle(TriangleChange::Removed(self.tris[t as usize]));
+        self.alive[t as usize] = false;
+        self.free.push(t);
+    }
+
+    fn next_stamp(&mut self) -> u32 {
+        self.stamp = self.stamp.wrapping_add(1);
+        if self.stamp == 0 {
+            self.mark.iter_mut().for_each(|m| *m = 0);
+            self.stamp = 1;
+        }
+        self.stamp
+    }
+
+    fn record(&mut self, change: EdgeChange) {
+        if self.track_changes {
+            self.changes.push(change);
+        }
+    }
+
+    fn record_triangle(&mut self, change: TriangleChange) {
+        if self.track_changes {
+            self.triangle_changes.push(change);
+        }
+    }
+
+    /// Empty both change logs
+    pub(crate) fn clear_changes(&mut self) {
+        self.changes.clear();
+        self.triangle_changes.clear();
+    }
+
+    /// Index `j` such that `adj[u][j] == t`
+    #[inline]
+    pub(crate) fn neighbor_index(&self, u: u32, t: u32) -> usize {
+        let a = self.adj[u as usize];
+        if a[0] == t { 0 } else if a[1] == t { 1 } else { 2 }
+    }
+
+    /// Walk toward `p` from the last located triangle
+    ///
+    /// Returns a triangle containing `p` (possibly on its boundary), or
+    /// `None` when `p` is outside the super triangle.
+    pub(crate) fn locate(&mut self, p: Point) -> Option<u32> {
+        let mut t = self.last;
+        if t as usize >= self.tris.len() || !self.alive[t as usize] {
+            t = self.alive.iter().position(|&a| a)? as u32;
+        }
+
+        let limit = self.tris.len() + 64;
+        let mut steps = 0usize;
+        'walk: loop {
+            steps += 1;
+            if steps > limit {
+                // Precision trouble can make the walk cycle; scan instead
+                return self.locate_linear(p);
+            }
+            let v = self.tris[t as usize];
+            for i in 0..3 {
+                // Rotating the first edge tested breaks walk cycles
+                let k = (i + steps) % 3;
+                let a = self.points[v[(k + 1) % 3] as usize];
+                let b = self.points[v[(k + 2) % 3] as usize];
+                if orient2d(a, b, p) < 0.0 {
+                    let u = self.adj[t as usize][k];
+                    if u == NONE {
+                        return None;
+                    }
+                    t = u;
+                    continue 'walk;
+                }
+            }
+            self.last = t;
+            return Some(t);
+        }
+    }
+
+    fn locate_linear(&mut self, p: Point) -> Option<u32> {
+        let found = (0..self.tris.len()).find(|&t| {
+            self.alive[t] && {
+                let [a, b, c] = self.tris[t].map(|v| self.points[v as usize]);
+                orient2d(a, b, p) >= 0.0 && orient2d(b, c, p) >= 0.0 && orient2d(c, a, p) >= 0.0
+            }
+        })? as u32;
+        self.last = found;
+        Some(found)
+    }
+
+    /// Whether `p` with `weight` conflicts with live triangle `t`, i.e.
+    /// lies inside its circumcircle or, in a weighted mesh, has positive
+    /// power against its orthogonal circle
+    fn conflicts(&self, t: u32, p: Point, weight: f64) -> bool {
+        let tv = self.tris[t as usize];
+        let [a, b, c] = tv.map(|w| self.points[w as usize]);
+        if self.weights.is_empty() {
+            return incircle(a, b, c, p) > 0.0;
+        }
+        let [wa, wb, wc] = tv.map(|w| self.weights[w as usize]);
+        power_incircle(a, b, c, p, [wa, wb, wc, weight]) > 0.0
+    }
+
+    /// Triangles `p` with `weight` conflicts with, grown from `t0`
+    ///
+    /// `t0` must contain `p` and conflict with it. This is the
+    /// Bowyer-Watson cavity that inserting `p` would replace; the triangles
+    /// are only marked, so the mesh is unchanged.
+    #[cfg_attr(not(feature = "voronoi"), allow(dead_code))]
+    pub(crate) fn cavity(&mut self, p: Point, weight: f64, t0: u32) -> Vec<u32> {
+        let mut bad = Vec::new();
+        self.cavity_into(p, weight, t0, &mut bad);
+        bad
+    }
+
+    /// `cavity` into `bad`, replacing what it held
+    fn cavity_into(&mut self, p: Point, weight: f64, t0: u32, bad: &mut Vec<u32>) {
+        let stamp = self.next_stamp();
+        self.mark[t0 as usize] = stamp;
+        bad.clear();
+        bad.push(t0);
+        let mut i = 0;
+        while i < bad.len() {
+            let t = bad[i];
+            i += 1;
+            for k in 0..3 {
+                let u = self.adj[t as usize][k];
+                if u == NONE || self.mark[u as usize] == stamp {
+                    continue;
+                }
+                if self.conflicts(u, p, weight) {
+                    self.mark[u as usize] = stamp;
+                    bad.push(u);
+                }
+            }
+        }
+    }
+
+    /// Link vertex `v` (already in `points`) into the triangulation
+    ///
+    /// Returns false, leaving `v` unlinked, if it lies outside the super
+    /// triangle or coincides with an existing vertex, or in a weighted mesh
+    /// if it is hidden by the vertices around it.
+    pub(crate) fn insert_vertex(&mut self, v: u32) -> bool {
+        let p = self.points[v as usize];
+        let Some(t0) = self.locate(p) else {
+            return false;
+        };
+        if self.tris[t0 as usize].iter().any(|&w| self.points[w as usize] == p) {
+            return false;
+        }
+        let weight = self.weights.get(v as usize).copied().unwrap_or(0.0);
+        // Always true without weights: p is inside the triangle's circumcircle
+        if !self.conflicts(t0, p, weight) {
+            return false;
+        }
+
+        let mut bad = std::mem::take(&mut self.scratch.bad);
+        self.cavity_into(p, weight, t0, &mut bad);
+        // The cavity is still marked with the current stamp
+        let stamp = self.stamp;
+
+        // Boundary edges (a, b) with the outside neighbor beyond them
+        let mut boundary = std::mem::take(&mut self.scratch.boundary);
+        boundary.clear();
+        for &t in &bad {
+            let tv = self.tris[t as usize];
+            for k in 0..3 {
+                let u = self.adj[t as usize][k];
+                let a = tv[(k + 1) % 3];
+                let b = tv[(k + 2) % 3];
+                if u == NONE || self.mark[u as usize] != stamp {
+                    boundary.push((a, b, u));
+                } else if t < u {
+                    self.record(EdgeChange::Removed(a.min(b), a.max(b)));
+                }
+            }
+        }
+
+        for &t in &bad {
+            self.kill(t);
+            // Only a weighted insertion can leave a vertex inside the
+            // cavity, hidden; the fan below relinks the rim ones
+            for w in self.tris[t as usize] {
+                self.vert_tri[w as usize] = NONE;
+            }
+        }
+
+        let mut fan = std::mem::take(&mut self.scratch.fan);
+        fan.clear();
+        for &(a, b, outer) in &boundary {
+            let n = self.alloc([a, b, v], [NONE, NONE, outer]);
+            if outer != NONE {
+                let ov = self.tris[outer as usize];
+                let j = (0..3).find(|&j| ov[j] != a && ov[j] != b).unwrap_or(0);
+                self.adj[outer as usize][j] = n;
+            }
+            self.vert_tri[a as usize] = n;
+            fan.push((a, n));
+            self.record(EdgeChange::Added(a.min(v), a.max(v)));
+        }
+        self.vert_tri[v as usize] = fan.last().map_or(NONE, |f| f.1);
+
+        // Successive fan triangles share the spoke from b to v
+        fan.sort_unstable();
+        for &(a, b, _) in &boundary {
+            let n = fan[fan.binary_search_by_key(&a, |f| f.0).unwrap()].1;
+            if let Ok(si) = fan.binary_search_by_key(&b, |f| f.0) {
+                let s = fan[si].1;
+                self.adj[n as usize][0] = s;
+                self.adj[s as usize][1] = n;
+            }
+        }
+
+        if let Some(&(_, n)) = fan.first() {
+            self.last = n;
+        }
+        self.scratch.bad = bad;
+        self.scratch.boundary = boundary;
+        self.scratch.fan = fan;
+        true
+    }
+
+    /// Unlink and drop the last vertex, retriangulating the hole it leaves
+    ///
+    /// The star polygon is ear-clipped and then legalized with Lawson
+    /// flips, so the result is again Delaunay. Returns the flip count, or
+    /// `None` if the hole could not be closed robustly; the vertex is gone
+    /// either way but the mesh must then be rebuilt.
+    pub(crate) fn remove_last_vertex(&mut self) -> Option<usize> {
+        let v = (self.points.len() - 1) as u32;
+        debug_assert!(v >= SUPER);
+        let start = self.vert_tri[v as usize];
+        self.points.pop();
+        self.vert_tri.pop();
+        if start == NONE {
+            return Some(0);
+        }
+
+        // Ring of neighbors CCW around v, with the triangle beyond each
+        // ring edge (ring[j], ring[j + 1])
+        let star = self.star(v, start)?;
+        let mut ring: Vec<(u32, u32)> = Vec::with_capacity(star.len());
+        for &t in &star {
+            let tv = self.tris[t as usize];
+            let i = tv.iter().position(|&w| w == v)?;
+            ring.push((tv[(i + 1) % 3], self.adj[t as usize][i]));
+        }
+        for &t in &star {
+            self.kill(t);
+        }
+        for &(a, _) in &ring {
+            self.record(EdgeChange::Removed(a.min(v), a.max(v)));
+        }
+
+        let mut created = Vec::with_capacity(ring.len() - 2);
+        while ring.len() >= 3 {
+            let n = ring.len();
+            let ear = (0..n).find(|&j| {
+                let [a, b, c] = [0, 1, 2].map(|k| self.points[ring[(j + k) % n].0 as usize]);
+                orient2d(a, b, c) > 0.0
+                    && (3..n).all(|k| {
+                        let p = self.points[ring[(j + k) % n].0 as usize];
+                        let sides = [(a, b), (b, c), (c, a)];
+                        sides.iter().any(|&(x, y)| orient2d(x, y, p) < 0.0)
+                    })
+            })?;
+            let (a, outer_ab) = ring[ear];
+            let (b, outer_bc) = ring[(ear + 1) % n];
+            let c = ring[(ear + 2) % n].0;
+            // Edge (c, a) is opposite b; it is linked once its other side exists
+            let last = n == 3;
+            let outer_ca = if last { ring[(ear + 2) % n].1 } else { NONE };
+            let e = self.alloc([a, b, c], [outer_bc, outer_ca, outer_ab]);
+            for (x, y, o) in [(a, b, outer_ab), (b, c, outer_bc), (c, a, outer_ca)] {
+                if o != NONE {
+                    let ov = self.tris[o as usize];
+                    let j = (0..3).find(|&j| ov[j] != x && ov[j] != y).unwrap_or(0);
+                    self.adj[o as usize][j] = e;
+                }
+            }
+            for w in [a, b, c] {
+                self.vert_tri[w as usize] = e;
+            }
+            created.push(e);
+            if last {
+                break;
+            }
+            self.record(EdgeChange::Added(a.min(c), a.max(c)));
+            ring[ear].1 = e;
+            ring.remove((ear + 1) % n);
+        }
+        self.last = *created.last()?;
+
+        let mut stack = Vec::new();
+        for &t in &created {
+            self.push_edges(t, &mut stack);
+        }
+        let cap = 16 * created.len() + 64;
+        self.legalize(&mut stack, cap)
+    }
+
+    /// Triangles around interior vertex `v` in CCW order, from `start`
+    ///
+    /// `None` if the fan does not close, which only a corrupt mesh causes.
+    pub(crate) fn star(&self, v: u32, start: u32) -> Option<Vec<u32>> {
+        let mut star = Vec::new();
+        let mut t = start;
+        loop {
+            let tv = self.tris[t as usize];
+            let i = tv.iter().position(|&w| w == v)?;
+            star.push(t);
+            t = self.adj[t as usize][(i + 1) % 3];
+            if t == start {
+                return Some(star);
+            }
+            if t == NONE || star.len() > self.tris.len() {
+                return None;
+            }
+        }
+    }
+
+    /// Exchange the labels of real vertices `v` and `w`
+    ///
+    /// Positions move with the labels, so the geometry is unchanged; only
+    /// the triangles around the two vertices are renamed, and their edges
+    /// are logged as removed and re-added under the new names. Returns
+    /// false, touching nothing, if either star cannot be walked.
+    pub(crate) fn swap_vertices(&mut self, v: u32, w: u32) -> bool {
+        debug_assert!(v >= SUPER && w >= SUPER);
+        if v == w {
+            return true;
+        }
+        let mut touched = Vec::new();
+        for u in [v, w] {
+            let start = self.vert_tri[u as usize];
+            if start != NONE {
+                match self.star(u, start) {
+                    Some(star) => touched.extend(star),
+                    None => return false,
+                }
+            }
+        }
+        touched.sort_unstable();
+        touched.dedup();
+
+        let rename = |x: u32| if x == v { w } else if x == w { v } else { x };
+        let mut edges = Vec::new();
+        for &t in &touched {
+            let tv = self.tris[t as usize];
+            for k in 0..3 {
+                let (a, b) = (tv[(k + 1) % 3], tv[(k + 2) % 3]);
+                if [a, b].iter().any(|&x| x == v || x == w) {
+                    edges.push(canonical(a, b));
+                }
+            }
+        }
+        edges.sort_unstable();
+        edges.dedup();
+        for (a, b) in edges {
+            self.record(EdgeChange::Removed(a, b));
+            let (c, d) = canonical(rename(a), rename(b));
+            self.record(EdgeChange::Added(c, d));
+        }
+
+        for &t in &touched {
+            let tv = self.tris[t as usize];
+            self.record_triangle(TriangleChange::Removed(tv));
+            self.tris[t as usize] = tv.map(rename);
+            self.record_triangle(TriangleChange::Added(self.tris[t as usize]));
+        }
+        self.points.swap(v as usize, w as usize);
+        self.vert_tri.swap(v as usize, w as usize);
+        true
+    }
+
+    /// Flip edge `k` of triangle `t`; both triangles keep their slots
+    ///
+    /// The caller guarantees the surrounding quad is convex.
+    pub(crate) fn flip(&mut self, t: u32, k: usize) -> u32 {
+        let u = self.adj[t as usize][k];
+        let tv = self.tris[t as usize];
+        let c = tv[k];
+        let a = tv[(k + 1) % 3];
+        let b = tv[(k + 2) % 3];
+        let n_bc = self.adj[t as usize][(k + 1) % 3];
+        let n_ca = self.adj[t as usize][(k + 2) % 3];
+
+        let j = self.neighbor_index(u, t);
+        let d = self.tris[u as usize][j];
+        let n_ad = self.adj[u as usize][(j + 1) % 3];
+        let n_db = self.adj[u as usize][(j + 2) % 3];
+
+        self.record_triangle(TriangleChange::Removed(tv));
+        self.record_triangle(TriangleChange::Removed(self.tris[u as usize]));
+        self.tris[t as usize] = [c, a, d];
+        self.adj[t as usize] = [n_ad, u, n_ca];
+        self.tris[u as usize] = [d, b, c];
+        self.adj[u as usize] = [n_bc, t, n_db];
+
+        if n_ad != NONE {
+            let i = self.neighbor_index(n_ad, u);
+            self.adj[n_ad as usize][i] = t;
+        }
+        if n_bc != NONE {
+            let i = self.neighbor_index(n_bc, t);
+            self.adj[n_bc as usize][i] = u;
+        }
+
+        self.vert_tri[a as usize] = t;
+        self.vert_tri[c as usize] = t;
+        self.vert_tri[b as usize] = u;
+        self.vert_tri[d as usize] = u;
+
+        let (ra, rb) = canonical(a, b);
+        let (ac, ad) = canonical(c, d);
+        self.record(EdgeChange::Removed(ra, rb));
+        self.record(EdgeChange::Added(ac, ad));
+        self.record_triangle(TriangleChange::Added([c, a, d]));
+        self.record_triangle(TriangleChange::Added([d, b, c]));
+        u
+    }
+
+    /// Whether edge `k` of `t` violates the empty-circumcircle property
+    ///
+    /// Between four real points the violation must exceed `flip_tolerance`;
+    /// edges touching the super triangle use the exact test, or none at
+    /// all once `close_hull` has run.
+    pub(crate) fn edge_is_illegal(&self, t: u32, k: usize) -> bool {
+        let u = self.adj[t as usize][k];
+        if u == NONE {
+            return false;
+        }
+        let j = self.neighbor_index(u, t);
+        let dv = self.tris[u as usize][j];
+        let tv = self.tris[t as usize];
+        let d = self.points[dv as usize];
+        let [a, b, c] = tv.map(|w| self.points[w as usize]);
+        if self.hull_closed && (dv < SUPER || tv.iter().any(|&w| w < SUPER)) {
+            return false;
+        }
+        let det = incircle(a, b, c, d);
+        if self.flip_tolerance == 0.0 || dv < SUPER || tv.iter().any(|&w| w < SUPER) {
+            return det > 0.0;
+        }
+        let (p, q) = (self.points[tv[(k + 1) % 3] as usize], self.points[tv[(k + 2) % 3] as usize]);
+        let l2 = (q.x - p.x).powi(2) + (q.y - p.y).powi(2);
+        det > self.flip_tolerance * l2 * l2
+    }
+
+    /// Push every edge of triangle `t` onto a legalization stack
+    pub(crate) fn push_edges(&self, t: u32, stack: &mut Vec<(u32, u32, u32)>) {
+        let v = self.tris[t as usize];
+        for k in 0..3 {
+            stack.push((t, v[(k + 1) % 3], v[(k + 2) % 3]));
+        }
+    }
+
+    /// Lawson flips until every stacked edge is locally Delaunay
+    ///
+    /// Stack entries are `(triangle, a, b)`; entries whose edge no longer
+    /// belongs to that triangle are skipped. Returns the flip count, or
+    /// `None` if `max_flips` was exceeded (floating-point cycling).
+    pub(crate) fn legalize(
+        &mut self,
+        stack: &mut Vec<(u32, u32, u32)>,
+        max_flips: usize,
+    ) -> Option<usize> {
+        let mut flips = 0;
+        while let Some((t, a, b)) = stack.pop() {
+            if !self.alive[t as usize] {
+                continue;
+            }
+            let tv = self.tris[t as usize];
+            let Some(k) = (0..3).find(|&k| {
+                let (x, y) = (tv[(k + 1) % 3], tv[(k + 2) % 3]);
+                (x == a && y == b) || (x == b && y == a)
+            }) else {
+                continue;
+            };
+            if !self.edge_is_illegal(t, k) {
+                continue;
+            }
+            flips += 1;
+            if flips > max_flips {
+                stack.clear();
+                return None;
+            }
+            let u = self.flip(t, k);
+            // After the flip t = [c, a, d] and u = [d, b, c]; recheck the outer edges
+            let t_v = self.tris[t as usize];
+            let u_v = self.tris[u as usize];
+            stack.push((t, t_v[1], t_v[2]));
+            stack.push((t, t_v[0], t_v[1]));
+            stack.push((u, u_v[0], u_v[1]));
+            stack.push((u, u_v[1], u_v[2]));
+        }
+        Some(flips)
+    }
+
+    /// Fill the dents a finite super triangle leaves in the convex hull
+    ///
+    /// Near-collinear hull points can have a circumcircle wider than the
+    /// super triangle, so Bowyer-Watson links them to a corner instead of
+    /// to each other and the real triangles miss slivers of the hull, some
+    /// of them large. Corner edges are flipped out of every dent as if the
+    /// corners were at infinity, then the new real triangles are
+    /// Lawson-flipped back to Delaunay. For meshes that only get read:
+    /// edges touching the corners are never flipped afterwards, so moving
+    /// points would leave the hull stale.
+    pub(crate) fn close_hull(&mut self) {
+        self.hull_closed = true;
+        let mut filled = false;
+        // Each flip either turns a corner triangle real or, in a star
+        // holding two corners, moves one of them off the dented vertex
+        let mut budget = 4 * self.tris.len() + 64;
+        loop {
+            let mut dented: Vec<u32> = (0..self.tris.len())
+                .filter(|&t| self.alive[t] && self.tris[t].iter().any(|&w| w < SUPER))
+                .flat_map(|t| self.tris[t])
+                .filter(|&w| w >= SUPER)
+                .collect();
+            dented.sort_unstable();
+            dented.dedup();
+            let before = budget;
+            for v in dented {
+                while budget > 0 && self.fill_dent(v) {
+                    budget -= 1;
+                }
+            }
+            if budget == before {
+                break;
+            }
+            filled = true;
+            if budget == 0 {
+                break;
+            }
+        }
+        if filled {
+            let mut stack = Vec::new();
+            for t in 0..self.tris.len() as u32 {
+                if self.is_real_triangle(t as usize) {
+                    self.push_edges(t, &mut stack);
+                }
+            }
+            let cap = 16 * stack.len() + 64;
+            self.legalize(&mut stack, cap);
+        }
+    }
+
+    /// One flip towards closing the dent at real vertex `b`, if it has one
+    ///
+    /// `b` dents the hull when its real neighbours `a` and `c` on either
+    /// side of a run of corner triangles turn left about it, so the
+    /// missing triangle `(b, a, c)` would be counter-clockwise. Returns
+    /// false when there is no dent or no corner edge of `b` can be flipped.
+    fn fill_dent(&mut self, b: u32) -> bool {
+        let start = self.vert_tri[b as usize];
+        if start == NONE {
+            return false;
+        }
+        let Some(star) = self.star(b, start) else {
+            return false;
+        };
+        // Triangle `star[i]` is `(b, ring[i], ring[i + 1])`, CCW about `b`
+        let ring: Vec<u32> = star
+            .iter()
+            .map(|&t| {
+                let tv = self.tris[t as usize];
+                tv[(tv.iter().position(|&w| w == b).unwrap_or(0) + 1) % 3]
+            })
+            .collect();
+        let n = ring.len();
+        for i in 0..n {
+            if ring[i] < SUPER || ring[(i + 1) % n] >= SUPER {
+                continue;
+            }
+            let Some(run) = (2..n).find(|&j| ring[(i + j) % n] >= SUPER) else {
+                continue;
+            };
+            let (a, c) = (ring[i], ring[(i + run) % n]);
+            let [pb, pa, pc] = [b, a, c].map(|w| self.points[w as usize]);
+            if a == c || orient2d(pb, pa, pc) <= 0.0 {
+                continue;
+            }
+            // Flip the first corner spoke `b`-`ring[i + j]` whose quad is
+            // convex; flipping it removes that corner from the star
+            for j in 1..run {
+                let t = star[(i + j - 1) % n];
+                let tv = self.tris[t as usize];
+                let k = tv.iter().position(|&w| w == ring[(i + j - 1) % n]).unwrap_or(0);
+                if self.flip_is_convex(t, k) {
+                    self.flip(t, k);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Whether both triangles `flip(t, k)` would create are CCW
+    fn flip_is_convex(&self, t: u32, k: usize) -> bool {
+        let u = self.adj[t as usize][k];
+        if u == NONE {
+            return false;
+        }
+        let tv = self.tris[t as usize];
+        let d = self.tris[u as usize][self.neighbor_index(u, t)];
+        let [c, a, b] = [tv[k], tv[(k + 1) % 3], tv[(k + 2) % 3]].map(|w| self.points[w as usize]);
+        let d = self.points[d as usize];
+        orient2d(c, a, d) > 0.0 && orient2d(d, b, c) > 0.0
+    }
+
+    /// Canonical internal edges between two input points, sorted
+    pub(crate) fn real_edges(&self) -> Vec<(u32, u32)> {
+        let mut edges = Vec::with_capacity(self.tris.len() * 2);
+        for t in 0..self.tris.len() {
+            if !self.alive[t] {
+                continue;
+            }
+            let v = self.tris[t];
+            for k in 0..3 {
+                let (a, b) = (v[(k + 1) % 3], v[(k + 2) % 3]);
+                // Each interior edge is seen once in each direction
+                if a < b && a >= SUPER {
+                    edges.push((a, b));
+                }
+            }
+        }
+        edges.sort_unstable();
+        edges
+    }
+
+    /// Serialize the full topology so a decoded mesh replays identically
+    pub(crate) fn encode(&self, w: &mut ByteWriter) {
+        let coords: Vec<f64> = self.points.iter().flat_map(|p| [p.x, p.y]).collect();
+        w.f64s(&coords);
+        w.u32s(&self.tris.iter().flatten().copied().collect::<Vec<u32>>());
+        w.u32s(&self.adj.iter().flatten().copied().collect::<Vec<u32>>());
+        w.u32s(&self.alive.iter().map(|&a| a as u32).collect::<Vec<u32>>());
+        w.u32s(&self.free);
+        w.u32s(&self.vert_tri);
+        w.u32(self.last);
+        w.u8(self.track_changes as u8);
+    }
+
+    /// Inverse of `encode`, rejecting links that index out of range or do
+    /// not form a counter-clockwise mesh
+    pub(crate) fn decode(r: &mut ByteReader) -> Result<Mesh> {
+        let bad = |what: &str| Error::InvalidData(format!("mesh: {what}"));
+        let coords = r.f64s()?;
+        let tris_flat = r.u32s()?;
+        let adj_flat = r.u32s()?;
+        let alive = r.u32s()?;
+        let free = r.u32s()?;
+        let vert_tri = r.u32s()?;
+        let last = r.u32()?;
+        let track_changes = r.u8()? != 0;
+
+        if coords.len() % 2 != 0 || coords.len() < 2 * SUPER as usize {
+            return Err(bad("point array"));
+        }
+        let n_points = coords.len() / 2;
+        let n_tris = alive.len();
+        if tris_flat.len() != 3 * n_tris || adj_flat.len() != 3 * n_tris || n_tris == 0 {
+            return Err(bad("triangle arrays disagree in length"));
+        }
+        if vert_tri.len() != n_points {
+            return Err(bad("vertex links disagree with point count"));
+        }
+        let tri_ok = |t: u32| (t as usize) < n_tris;
+        // Dead slots may still name vertices removed since they died
+        let live_vertices = tris_flat.chunks_exact(3).zip(&alive).filter(|(_, &a)| a != 0);
+        if live_vertices.flat_map(|(t, _)| t).any(|&v| v as usize >= n_points)
+            || adj_flat.iter().any(|&t| t != NONE && !tri_ok(t))
+            || vert_tri.iter().any(|&t| t != NONE && !tri_ok(t))
+            || free.iter().any(|&t| !tri_ok(t) || alive[t as usize] != 0)
+            || alive.iter().any(|&a| a > 1)
+            || !tri_ok(last)
+        {
+            return Err(bad("index out of range"));
+        }
+
+        let mesh = Mesh {
+            points: coords.chunks_exact(2).map(|c| Point { x: c[0], y: c[1] }).collect(),
+            tris: tris_flat.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect(),
+            adj: adj_flat.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect(),
+            alive: alive.iter().map(|&a| a != 0).collect(),
+            free,
+            vert_tri,
+            last,
+            track_changes,
+            changes: Vec::new(),
+            triangle_changes: Vec::new(),
+            flip_tolerance: 0.0,
+            weights: Vec::new(),
+            hull_closed: false,
+            mark: vec![0; n_tris],
+            stamp: 0,
+            scratch: Scratch::default(),
+        };
+        match mesh.topology_error() {
+            Some(what) => Err(bad(what)),
+            None => Ok(mesh),
+        }
+    }
+
+    /// First way the links fail to describe a valid mesh, for `decode`:
+    /// the ranges are known good, but a single wrong index would still
+    /// send later walks and flips astray
+    fn topology_error(&self) -> Option<&'static str> {
+        let mut freed = vec![false; self.tris.len()];
+        let dead = self.alive.iter().filter(|&&a| !a).count();
+        if self.free.len() != dead
+            || self.free.iter().any(|&t| std::mem::replace(&mut freed[t as usize], true))
+        {
+            return Some("free list does not match dead triangles");
+        }
+        for t in (0..self.tris.len()).filter(|&t| self.alive[t]) {
+            let v = self.tris[t];
+            let [a, b, c] = v.map(|w| self.points[w as usize]);
+            let finite = [a, b, c].iter().all(|p| p.x.is_finite() && p.y.is_finite());
+            if !finite || orient2d(a, b, c) <= 0.0 {
+                return Some("triangle not counter-clockwise");
+            }
+            for k in 0..3 {
+                let u = self.adj[t][k];
+                // `u` must hold the edge opposite `k`, reversed, and link back
+                let links_back = u == NONE || {
+                    let (w, j) = (self.tris[u as usize], self.neighbor_index(u, t as u32));
+                    self.alive[u as usize]
+                        && self.adj[u as usize][j] == t as u32
+                        && w[(j + 1) % 3] == v[(k + 2) % 3]
+                        && w[(j + 2) % 3] == v[(k + 1) % 3]
+                };
+                if !links_back {
+                    return Some("neighbours do not link back");
+                }
+                if self.vert_tri[v[k] as usize] == NONE {
+                    return Some("vertex of a live triangle unlinked");
+                }
+            }
+        }
+        let linked_wrong = self.vert_tri.iter().enumerate().any(|(w, &t)| {
+            t != NONE && !(self.alive[t as usize] && self.tris[t as usize].contains(&(w as u32)))
+        });
+        linked_wrong.then_some("vertex linked to a triangle without it")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    fn random_points(n: usize, w: f64, h: f64, seed: u64) -> Vec<Point> {
+        let mut rng = Rng::new(seed);
+        (0..n).map(|_| Point { x: rng.next_f64() * w, y: rng.next_f64() * h }).collect()
+    }
+
+    /// Brute-force empty-circumcircle check over the real triangles
+    pub(crate) fn assert_delaunay(mesh: &Mesh) {
+        let pts = &mesh.points[SUPER as usize..];
+        for tri in mesh.real_triangles() {
+            let [a, b, c] = tri.map(|v| pts[v as usize]);
+            assert!(orient2d(a, b, c) > 0.0);
+            let scale = [a, b, c]
+                .iter()
+                .map(|p| p.x.abs().max(p.y.abs()))
+                .fold(1.0, f64::max);
+            for (i, &d) in pts.iter().enumerate() {
+                if tri.contains(&(i as u32)) {
+                    continue;
+                }
+                assert!(incircle(a, b, c, d) <= 1e-9 * scale.powi(4), "{tri:?} contains {i}");
+            }
+        }
+    }
+
+    fn assert_links_consistent(mesh: &Mesh) {
+        for t in 0..mesh.tris.len() {
+            if !mesh.alive[t] {
+                continue;
+            }
+            for k in 0..3 {
+                let u = mesh.adj[t][k];
+                if u != NONE {
+                    assert!(mesh.alive[u as usize]);
+                    assert_eq!(mesh.adj[u as usize][mesh.neighbor_index(u, t as u32)], t as u32);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_is_delaunay() {
+        let pts = random_points(300, 800.0, 600.0, 7);
+        let mesh = Mesh::build(&pts, None);
+        assert_links_consistent(&mesh);
+        assert_delaunay(&mesh);
+        // Euler: a full triangulation of n points plus 3 corners has 2(n+3)-5 triangles
+        assert_eq!(mesh.alive.iter().filter(|&&a| a).count(), 2 * 303 - 5);
+    }
+
+    #[test]
+    fn test_hilbert_order_walks_the_grid_in_unit_steps() {
+        let grid: Vec<Point> =
+            (0..64).map(|i| Point::new((i % 8) as f64, (i / 8) as f64)).collect();
+        let order = hilbert_order(&grid);
+        let mut seen = order.clone();
+        seen.sort_unstable();
+        assert_eq!(seen, (0..64).collect::<Vec<u32>>());
+        for pair in order.windows(2) {
+            let (p, q) = (grid[pair[0] as usize], grid[pair[1] as usize]);
+            assert_eq!((p.x - q.x).abs() + (p.y - q.y).abs(), 1.0, "{p:?} -> {q:?}");
+        }
+    }
+
+    #[test]
+    fn test_duplicate_point_left_unlinked() {
+        let pts = [
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 10.0, y: 0.0 },
+            Point { x: 0.0, y: 10.0 },
+            Point { x: 10.0, y: 0.0 },
+        ];
+        let mesh = Mesh::build(&pts, None);
+        assert_eq!(mesh.vert_tri[SUPER as usize + 3], NONE);
+        assert_eq!(mesh.real_triangles().count(), 1);
+    }
+
+    #[test]
+    fn test_legalize_restores_delaunay_after_motion() {
+        let mut pts = random_points(200, 500.0, 500.0, 11);
+        let mut mesh = Mesh::build(&pts, None);
+        // Small enough that no triangle turns over, large enough to flip
+        let shifted = random_points(200, 4.0, 4.0, 13);
+        for (p, s) in pts.iter_mut().zip(&shifted) {
+            p.x += s.x - 2.0;
+            p.y += s.y - 2.0;
+        }
+        mesh.points[SUPER as usize..].copy_from_slice(&pts);
+        assert!(mesh.orientation_valid());
+
+        let mut stack = Vec::new();
+        for t in 0..mesh.tris.len() as u32 {
+            if mesh.alive[t as usize] {
+                mesh.push_edges(t, &mut stack);
+            }
+        }
+        assert!(mesh.legalize(&mut stack, 100_000).is_some_and(|flips| flips > 0));
+        assert_links_consistent(&mesh);
+        assert_delaunay(&mesh);
+    }
+
+    #[test]
+    fn test_remove_last_vertex_matches_fresh_build() {
+        let pts = random_points(300, 800.0, 600.0, 21);
+        let mut mesh = Mesh::build(&pts, None);
+        let mut remaining = pts.len();
+        while remaining > 40 {
+            assert!(mesh.remove_last_vertex().is_some());
+            remaining -= 1;
+            assert_eq!(mesh.real_count(), remaining);
+            if remaining.is_multiple_of(37) {
+                assert_links_consistent(&mesh);
+                assert_delaunay(&mesh);
+            }
+        }
+        assert!(mesh.orientation_valid());
+        let fresh = Mesh::build(&pts[..remaining], Some([0.0, 0.0, 800.0, 600.0]));
+        assert_eq!(mesh.real_edges(), fresh.real_edges());
+    }
+}
@@ -0,0 +1,329 @@
+//! Transitions between two point sets of the same size
+//!
+//! Point `i` of `from` travels in a straight line to point `i` of `to`,
+//! or to the point `match_points` pairs it with when the two orderings are
+//! unrelated. Re-triangulating every frame of such a tween pops whenever
+//! an edge flips, so `morph_mesh` finds where along the way the topology
+//! changes and hands back each stable topology as a keyframe, leaving JS
+//! to cross-fade between neighbours instead.
+//!
+//! Flips are found by sampling `steps + 1` evenly spaced times and, where
+//! two consecutive samples differ, bisecting for the first time the
+//! earlier topology breaks. Several flips between two samples are
+//! separated one after another; flips closer together than the bisection
+//! resolution, or a topology that changes and changes back between two
+//! samples, are merged or missed, so more steps catch more of them.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::mesh::Mesh;
+use crate::spatial::PointGrid;
+use crate::Point;
+
+/// Most samples `morph_mesh` will triangulate along the way
+pub const MORPH_MAX_STEPS: u32 = 1024;
+/// Halvings of the sample spacing when locating a flip
+const BISECTIONS: u32 = 16;
+/// Nearest candidates per point considered by `match_points`
+const CANDIDATES: usize = 8;
+
+fn parse_pair(from: &[f64], to: &[f64]) -> Result<(Vec<Point>, Vec<Point>)> {
+    check_points(from)?;
+    check_points(to)?;
+    if from.len() != to.len() {
+        return Err(Error::InvalidLength(format!(
+            "point sets must be the same size, got {} and {} points",
+            from.len() / 2,
+            to.len() / 2
+        )));
+    }
+    if from.iter().chain(to).any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let parse = |flat: &[f64]| flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+    Ok((parse(from), parse(to)))
+}
+
+fn lerp(from: &[Point], to: &[Point], t: f64) -> Vec<Point> {
+    from.iter()
+        .zip(to)
+        .map(|(a, b)| Point::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t))
+        .collect()
+}
+
+/// `from + (to - from) · t` for each coordinate
+///
+/// `t` outside [0, 1] extrapolates.
+#[wasm_bindgen]
+pub fn morph_points(from: &[f64], to: &[f64], t: f64) -> Result<Vec<f64>> {
+    let (from, to) = parse_pair(from, to)?;
+    if !t.is_finite() {
+        return Err(Error::NonFiniteInput(format!("t must be finite, got {t}")));
+    }
+    Ok(lerp(&from, &to, t).into_iter().flat_map(|p| [p.x, p.y]).collect())
+}
+
+/// For each point of `from`, the index of the point of `to` it should
+/// travel to, every index used once
+///
+/// Pairs are taken greedily, closest first, among each point's few
+/// nearest candidates; the rest then take the nearest point still free.
+/// This keeps most paths short without the cost of an optimal
+/// assignment. Ties go to lower indices, so the result is deterministic.
+#[wasm_bindgen]
+pub fn match_points(from: &[f64], to: &[f64]) -> Result<Vec<u32>> {
+    let (from, to) = parse_pair(from, to)?;
+    Ok(assign(&from, &to))
+}
+
+fn assign(from: &[Point], to: &[Point]) -> Vec<u32> {
+    let Some(&first) = to.first() else { return Vec::new() };
+    let d2 = |a: Point, b: Point| (a.x - b.x).powi(2) + (a.y - b.y).powi(2);
+    let grid = PointGrid::new(to, [first.x, first.y, first.x, first.y]);
+    let mut pairs: Vec<(f64, u32, u32)> = Vec::with_capacity(from.len() * CANDIDATES);
+    for (i, &p) in from.iter().enumerate() {
+        for j in grid.k_nearest(p, CANDIDATES) {
+            pairs.push((d2(p, to[j as usize]), i as u32, j));
+        }
+    }
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+
+    const FREE: u32 = u32::MAX;
+    let mut target = vec![FREE; from.len()];
+    let mut taken = vec![false; to.len()];
+    for (_, i, j) in pairs {
+        if target[i as usize] == FREE && !taken[j as usize] {
+            target[i as usize] = j;
+            taken[j as usize] = true;
+        }
+    }
+    for (i, &p) in from.iter().enumerate() {
+        if target[i] != FREE {
+            continue;
+        }
+        let free = (0..to.len()).filter(|&j| !taken[j]);
+        // At least one is free, since fewer sources than targets are set
+        let j = free.min_by(|&a, &b| d2(p, to[a]).total_cmp(&d2(p, to[b]))).unwrap_or(0);
+        target[i] = j as u32;
+        taken[j] = true;
+    }
+    target
+}
+
+/// Box around both point sets, so the super triangle fits every frame
+fn morph_bounds(from: &[Point], to: &[Point]) -> Option<[f64; 4]> {
+    let bounds = from.iter().chain(to).fold(
+        [f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY],
+        |b, p| [b[0].min(p.x), b[1].min(p.y), b[2].max(p.x), b[3].max(p.y)],
+    );
+    (!from.is_empty()).then_some(bounds)
+}
+
+/// Triangles at `t`, each rotated to start at its lowest vertex with the
+/// winding kept, and sorted, so equal topologies compare equal
+fn topology(from: &[Point], to: &[Point], bounds: Option<[f64; 4]>, t: f64) -> Vec<[u32; 3]> {
+    let mut tris: Vec<[u32; 3]> = Mesh::build(&lerp(from, to, t), bounds)
+        .real_triangles()
+        .map(|t| {
+            let k = (0..3).min_by_key(|&k| t[k]).unwrap_or(0);
+            [t[k], t[(k + 1) % 3], t[(k + 2) % 3]]
+        })
+        .collect();
+    tris.sort_unstable();
+    tris
+}
+
+/// Stable topologies along a morph, from `morph_mesh`
+#[wasm_bindgen]
+pub struct MorphKeyframes {
+    from: Vec<Point>,
+    to: Vec<Point>,
+    matching: Vec<u32>,
+    times: Vec<f64>,
+    triangles: Vec<u32>,
+    offsets: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl MorphKeyframes {
+    /// Time at which each keyframe's topology takes over, ascending and
+    /// starting at 0; keyframe `k` holds until `times[k + 1]`, the last
+    /// until 1
+    pub fn times(&self) -> Vec<f64> {
+        self.times.clone()
+    }
+
+    /// Every keyframe's triangle indices back to back
+    pub fn triangles(&self) -> Vec<u32> {
+        self.triangles.clone()
+    }
+
+    /// One entry per keyframe plus one, in index units, so keyframe `k` is
+    /// `triangles[offsets[k]..offsets[k + 1]]`
+    pub fn offsets(&self) -> Vec<u32> {
+        self.offsets.clone()
+    }
+
+    /// Index into `to` of each `from` point's destination; the identity
+    /// unless matching was asked for
+    pub fn matching(&self) -> Vec<u32> {
+        self.matching.clone()
+    }
+
+    /// Point positions at `t`, in `from` order
+    pub fn positions_at(&self, t: f64) -> Result<Vec<f64>> {
+        if !t.is_finite() {
+            return Err(Error::NonFiniteInput(format!("t must be finite, got {t}")));
+        }
+        Ok(lerp(&self.from, &self.to, t).into_iter().flat_map(|p| [p.x, p.y]).collect())
+    }
+
+    pub fn keyframe_count(&self) -> usize {
+        self.times.len()
+    }
+}
+
+/// Triangulations along the straight-line morph from `from` to `to`
+///
+/// Samples `steps + 1` times evenly over [0, 1] and returns one keyframe
+/// per distinct topology, each with the time it starts; see the module
+/// notes for how flips between samples are located. With `match_nearest`
+/// the destinations are paired up by `match_points` first, and indices in
+/// the keyframes refer to `from`. The super triangle is sized once for
+/// both sets together rather than for each frame's points.
+#[wasm_bindgen]
+pub fn morph_mesh(
+    from: &[f64],
+    to: &[f64],
+    steps: u32,
+    match_nearest: bool,
+) -> Result<MorphKeyframes> {
+    let (from, to) = parse_pair(from, to)?;
+    if steps == 0 || steps > MORPH_MAX_STEPS {
+        return Err(Error::InvalidArgument(format!(
+            "steps must be in 1..={MORPH_MAX_STEPS}, got {steps}"
+        )));
+    }
+    let matching: Vec<u32> =
+        if match_nearest { assign(&from, &to) } else { (0..from.len() as u32).collect() };
+    let to: Vec<Point> = matching.iter().map(|&j| to[j as usize]).collect();
+
+    let bounds = morph_bounds(&from, &to);
+    let at = |t: f64| topology(&from, &to, bounds, t);
+    let mut current = at(0.0);
+    let (mut times, mut frames) = (vec![0.0], vec![current.clone()]);
+    let mut start = 0.0;
+    for k in 1..=steps {
+        let end = k as f64 / steps as f64;
+        let sample = at(end);
+        while current != sample {
+            // `current` holds at `lo` and has broken by `hi`
+            let (mut lo, mut hi) = (start, end);
+            let mut broken = None;
+            for _ in 0..BISECTIONS {
+                let mid = 0.5 * (lo + hi);
+                let middle = at(mid);
+                if middle == current {
+                    lo = mid;
+                } else {
+                    (hi, broken) = (mid, Some(middle));
+                }
+            }
+            current = broken.unwrap_or_else(|| sample.clone());
+            times.push(hi);
+            frames.push(current.clone());
+            start = hi;
+        }
+        start = end;
+    }
+
+    let mut offsets = vec![0];
+    let mut triangles = Vec::new();
+    for frame in frames {
+        triangles.extend(frame.into_iter().flatten());
+        offsets.push(triangles.len() as u32);
+    }
+    Ok(MorphKeyframes { from, to, matching, times, triangles, offsets })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+
+    #[test]
+    fn test_flip_is_located_between_samples() {
+        // The top right corner of a square slides outwards along the
+        // diagonal. It crosses the circle through the other three corners,
+        // centred at (1, 1), at (2, 2), so at t = 1/3, and the diagonal 0-2
+        // flips to 1-3
+        let from = [0.0, 0.0, 2.0, 0.0, 1.5, 1.5, 0.0, 2.0];
+        let to = [0.0, 0.0, 2.0, 0.0, 3.0, 3.0, 0.0, 2.0];
+        let frames = morph_mesh(&from, &to, 4, false).unwrap();
+        assert_eq!(frames.keyframe_count(), 2);
+        let t = frames.times()[1];
+        assert!((t - 1.0 / 3.0).abs() < 1e-4, "flip at {t}");
+        assert_eq!(frames.offsets(), vec![0, 6, 12]);
+        let triangles = frames.triangles();
+        let uses = |f: usize, v| triangles[f * 6..f * 6 + 6].iter().filter(|&&i| i == v).count();
+        assert_eq!((uses(0, 0), uses(0, 2), uses(1, 1), uses(1, 3)), (2, 2, 2, 2));
+        assert_eq!(frames.positions_at(1.0).unwrap(), to);
+        assert_eq!(frames.matching(), vec![0, 1, 2, 3]);
+
+        let still = morph_mesh(&from, &from, 8, false).unwrap();
+        assert_eq!((still.keyframe_count(), still.times()), (1, vec![0.0]));
+    }
+
+    #[test]
+    fn test_keyframes_match_direct_triangulation() {
+        let mut rng = Rng::new(8);
+        let from: Vec<f64> = (0..80).map(|_| rng.range(0.0, 400.0)).collect();
+        let to: Vec<f64> = (0..80).map(|_| rng.range(0.0, 400.0)).collect();
+        let frames = morph_mesh(&from, &to, 64, true).unwrap();
+        let (times, offsets, triangles) = (frames.times(), frames.offsets(), frames.triangles());
+        assert!(frames.keyframe_count() > 2);
+        assert!(times[0] == 0.0 && times.windows(2).all(|w| w[0] < w[1]));
+        let bounds = morph_bounds(&frames.from, &frames.to);
+        // Each keyframe is the triangulation just after it starts
+        for k in 0..times.len() {
+            let end = times.get(k + 1).copied().unwrap_or(1.0);
+            let t = times[k] + 1e-3 * (end - times[k]);
+            let direct: Vec<u32> =
+                topology(&frames.from, &frames.to, bounds, t).into_iter().flatten().collect();
+            assert_eq!(triangles[offsets[k] as usize..offsets[k + 1] as usize], direct);
+        }
+    }
+
+    #[test]
+    fn test_matching_pairs_nearby_points() {
+        // `to` is `from` shuffled and nudged, so nearest matching recovers
+        // the shuffle
+        let mut rng = Rng::new(4);
+        let from: Vec<f64> =
+            (0..400).flat_map(|i| [(i % 20) as f64 * 10.0, (i / 20) as f64 * 10.0]).collect();
+        let mut order: Vec<usize> = (0..400).collect();
+        for i in (1..400).rev() {
+            order.swap(i, rng.below(i as u32 + 1) as usize);
+        }
+        let mut to = vec![0.0; 800];
+        for (i, &j) in order.iter().enumerate() {
+            to[j * 2] = from[i * 2] + rng.range(-1.0, 1.0);
+            to[j * 2 + 1] = from[i * 2 + 1] + rng.range(-1.0, 1.0);
+        }
+        let matching = match_points(&from, &to).unwrap();
+        assert_eq!(matching, order.iter().map(|&j| j as u32).collect::<Vec<u32>>());
+        let midway = morph_points(&from, &to, 0.5).unwrap();
+        assert!((midway[0] - (from[0] + to[0]) / 2.0).abs() < 1e-12);
+
+        assert_eq!(code_of(morph_points(&from, &to[..798], 0.5)), ErrorCode::InvalidLength);
+        assert_eq!(code_of(morph_points(&from, &to, f64::NAN)), ErrorCode::NonFiniteInput);
+        let nan = match_points(&[f64::NAN, 0.0], &[0.0, 0.0]);
+        assert_eq!(code_of(nan), ErrorCode::NonFiniteInput);
+        assert_eq!(code_of(morph_mesh(&from, &to, 0, false)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(morph_mesh(&from, &to, 2000, false)), ErrorCode::InvalidArgument);
+        assert!(match_points(&[], &[]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+//! Per-cell average colors from video frames, smoothed over time
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::spatial::PointGrid;
+use crate::Point;
+
+/// Seed layout plus the smoothed RGBA color of every cell
+///
+/// Frames are stretched over the `width × height` canvas. The
+/// pixel-to-cell ownership map is built once per layout and frame size
+/// with nearest-seed grid lookups, so each `sample_frame` afterwards is
+/// a single pass over the pixels.
+#[wasm_bindgen]
+pub struct MosaicSampler {
+    seeds: Vec<Point>,
+    width: f64,
+    height: f64,
+    /// Owning seed per frame pixel, for the frame size in `owner_size`
+    owner: Vec<u32>,
+    owner_size: (u32, u32),
+    /// Smoothed RGBA per cell, `None` until the first frame after a reset
+    colors: Option<Vec<[f32; 4]>>,
+}
+
+#[wasm_bindgen]
+impl MosaicSampler {
+    #[wasm_bindgen(constructor)]
+    pub fn new(points_flat: &[f64], width: f64, height: f64) -> Result<MosaicSampler> {
+        if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+            return Err(Error::InvalidArgument("width and height must be positive".into()));
+        }
+        let mut sampler = MosaicSampler {
+            seeds: Vec::new(),
+            width,
+            height,
+            owner: Vec::new(),
+            owner_size: (0, 0),
+            colors: None,
+        };
+        sampler.set_points(points_flat)?;
+        Ok(sampler)
+    }
+
+    /// Replace the seed layout; this discards the smoothed colors
+    pub fn set_points(&mut self, points_flat: &[f64]) -> Result<()> {
+        check_points(points_flat)?;
+        if points_flat.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+        }
+        self.seeds = points_flat.chunks_exact(2).map(|c| Point { x: c[0], y: c[1] }).collect();
+        self.owner.clear();
+        self.owner_size = (0, 0);
+        self.colors = None;
+        Ok(())
+    }
+
+    /// Average each cell's pixels and blend into the running colors
+    ///
+    /// `smoothing` in `[0, 1]` is the weight kept from the previous frame:
+    /// 0 follows the video exactly, values near 1 change slowly. The first
+    /// frame after a reset is taken as-is. Cells that own no pixel keep
+    /// their previous color.
+    pub fn sample_frame(&mut self, pixels_rgba: &[u8], w: u32, h: u32, smoothing: f64) -> Result<()> {
+        let count = w as usize * h as usize;
+        if pixels_rgba.len() != count * 4 {
+            return Err(Error::InvalidLength(format!(
+                "{w}x{h} RGBA frame needs {} bytes, got {}",
+                count * 4,
+                pixels_rgba.len()
+            )));
+        }
+        if !(0.0..=1.0).contains(&smoothing) {
+            return Err(Error::InvalidArgument(format!(
+                "smoothing must be in [0, 1], got {smoothing}"
+            )));
+        }
+        if self.seeds.is_empty() {
+            return Ok(());
+        }
+        if self.owner_size != (w, h) {
+            self.build_owner(w, h);
+        }
+
+        let mut sums = vec![[0u64; 4]; self.seeds.len()];
+        let mut counts = vec![0u32; self.seeds.len()];
+        for (px, &cell) in pixels_rgba.chunks_exact(4).zip(&self.owner) {
+            let s = &mut sums[cell as usize];
+            for k in 0..4 {
+                s[k] += px[k] as u64;
+            }
+            counts[cell as usize] += 1;
+        }
+
+        let keep = smoothing as f32;
+        let first = self.colors.is_none();
+        let colors = self.colors.get_or_insert_with(|| vec![[0.0; 4]; sums.len()]);
+        for ((c, s), &n) in colors.iter_mut().zip(&sums).zip(&counts) {
+            if n == 0 {
+                continue;
+            }
+            for k in 0..4 {
+                let avg = s[k] as f32 / n as f32;
+                c[k] = if first { avg } else { c[k] * keep + avg * (1.0 - keep) };
+            }
+        }
+        Ok(())
+    }
+
+    /// Smoothed `[r, g, b, a, ...]` per cell; zeros before the first frame
+    pub fn colors(&self) -> Vec<u8> {
+        match &self.colors {
+            Some(colors) => colors
+                .iter()
+                .flat_map(|c| c.map(|v| v.round().clamp(0.0, 255.0) as u8))
+                .collect(),
+            None => vec![0; self.seeds.len() * 4],
+        }
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.seeds.len()
+    }
+}
+
+impl MosaicSampler {
+    fn build_owner(&mut self, w: u32, h: u32) {
+        let grid = PointGrid::new(&self.seeds, [0.0, 0.0, self.width, self.height]);
+        let (sx, sy) = (self.width / w as f64, self.height / h as f64);
+        self.owner.clear();
+        self.owner.reserve(w as usize * h as usize);
+        for row in 0..h {
+            let y = (row as f64 + 0.5) * sy;
+            for col in 0..w {
+                let p = Point { x: (col as f64 + 0.5) * sx, y };
+                self.owner.push(grid.nearest(p).unwrap_or(0));
+            }
+        }
+        self.owner_size = (w, h);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    fn halves(w: u32, h: u32, left: [u8; 4], right: [u8; 4]) -> Vec<u8> {
+        (0..w * h)
+            .flat_map(|i| if i % w < w / 2 { left } else { right })
+            .collect()
+    }
+
+    #[test]
+    fn test_cells_average_their_pixels() {
+        let mut m = MosaicSampler::new(&[25.0, 50.0, 75.0, 50.0], 100.0, 100.0).unwrap();
+        assert_eq!(m.colors(), vec![0; 8]);
+        // Frame at a different resolution from the canvas
+        m.sample_frame(&halves(64, 32, [255, 0, 0, 255], [0, 0, 200, 255]), 64, 32, 0.9)
+            .unwrap();
+        assert_eq!(m.colors(), vec![255, 0, 0, 255, 0, 0, 200, 255]);
+    }
+
+    #[test]
+    fn test_smoothing_blends_and_reset_clears() {
+        let points = [25.0, 50.0, 75.0, 50.0];
+        let mut m = MosaicSampler::new(&points, 100.0, 100.0).unwrap();
+        let black = halves(10, 10, [0, 0, 0, 255], [0, 0, 0, 255]);
+        let white = halves(10, 10, [255, 255, 255, 255], [255, 255, 255, 255]);
+        m.sample_frame(&black, 10, 10, 0.75).unwrap();
+        m.sample_frame(&white, 10, 10, 0.75).unwrap();
+        assert_eq!(&m.colors()[..4], &[64, 64, 64, 255]);
+        m.sample_frame(&white, 10, 10, 0.0).unwrap();
+        assert_eq!(&m.colors()[..4], &[255, 255, 255, 255]);
+
+        m.set_points(&points).unwrap();
+        assert_eq!(m.colors(), vec![0; 8]);
+        m.sample_frame(&black, 10, 10, 0.75).unwrap();
+        assert_eq!(&m.colors()[..4], &[0, 0, 0, 255]);
+
+        assert_eq!(code_of(m.sample_frame(&black[..399], 10, 10, 0.5)), ErrorCode::InvalidLength);
+        assert_eq!(code_of(m.sample_frame(&black, 10, 10, 1.5)), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_empty_cells_keep_previous_color() {
+        // Seed 1 is far off canvas and owns no pixel
+        let mut m = MosaicSampler::new(&[50.0, 50.0, 5000.0, 5000.0], 100.0, 100.0).unwrap();
+        m.sample_frame(&[9; 4 * 16], 4, 4, 0.5).unwrap();
+        assert_eq!(m.colors(), vec![9, 9, 9, 9, 0, 0, 0, 0]);
+    }
+}
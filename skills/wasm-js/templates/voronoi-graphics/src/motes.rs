@@ -0,0 +1,1115 @@
+//! Mote pool with stable ids, per-mote attributes and automatic expiry
+//!
+//! `update_motes` works on a caller-owned stride-3 array, which cannot
+//! grow or carry extra attributes without a reallocation on the JS side.
+//! `MoteSystem` owns a fixed-capacity slot table instead: despawned slots
+//! go on a free list and are handed out again by `spawn`, and the output
+//! buffer is rebuilt in place every `update`.
+//!
+//! By default a mote that finishes its edge jumps to an unrelated one,
+//! every edge equally likely. `set_edge_weights` and
+//! `weight_edges_by_length` bias those jumps through a prefix-sum table,
+//! rebuilt on the next `update` after the weights or edges change.
+//! Once `set_graph` supplies the edges' vertex indices, motes flow
+//! instead: each continues onto another edge at the vertex it reached
+//! and only turns back at a dead end.
+
+use wasm_bindgen::prelude::*;
+
+use crate::codec::{ByteReader, ByteWriter};
+use crate::error::{Error, Result};
+#[cfg(feature = "simd")]
+use crate::simd::{per_lane, Lanes};
+
+/// Floats per mote in `positions()`: `[x, y, brightness, size]`
+pub const MOTE_STRIDE: u32 = 4;
+
+const STATE_MAGIC: &[u8; 4] = b"VGMS";
+const STATE_VERSION: u8 = 1;
+
+/// Pulse curve for `update_motes_ex`
+///
+/// Mote `i` pulses with `s = 0.5 + 0.5 * sin(time * pulse_frequency + i *
+/// phase_spread)` and gets brightness and size at fraction `s` of their
+/// ranges, so brighter motes are also bigger. The default reproduces
+/// `update_motes`: frequency 2, spread 0.5, brightness 0 to 1, size 1.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MoteStyle {
+    /// Radians per unit of `time`
+    pub pulse_frequency: f64,
+    /// Radians between consecutive motes
+    pub phase_spread: f64,
+    pub min_brightness: f64,
+    pub max_brightness: f64,
+    pub min_size: f64,
+    pub max_size: f64,
+}
+
+#[wasm_bindgen]
+impl MoteStyle {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        pulse_frequency: f64,
+        phase_spread: f64,
+        min_brightness: f64,
+        max_brightness: f64,
+        min_size: f64,
+        max_size: f64,
+    ) -> MoteStyle {
+        MoteStyle {
+            pulse_frequency,
+            phase_spread,
+            min_brightness,
+            max_brightness,
+            min_size,
+            max_size,
+        }
+    }
+}
+
+impl Default for MoteStyle {
+    fn default() -> MoteStyle {
+        MoteStyle::new(2.0, 0.5, 0.0, 1.0, 1.0, 1.0)
+    }
+}
+
+impl MoteStyle {
+    /// Error unless every field is finite and both ranges are ordered
+    pub(crate) fn validate(&self) -> Result<()> {
+        let fields = [
+            self.pulse_frequency,
+            self.phase_spread,
+            self.min_brightness,
+            self.max_brightness,
+            self.min_size,
+            self.max_size,
+        ];
+        if fields.iter().any(|v| !v.is_finite()) {
+            return Err(Error::InvalidArgument("mote style fields must be finite".into()));
+        }
+        if self.min_brightness > self.max_brightness || self.min_size > self.max_size {
+            return Err(Error::InvalidArgument(format!("mote style ranges are reversed: {self:?}")));
+        }
+        Ok(())
+    }
+
+    /// Brightness and size of mote `i` at `time`
+    pub(crate) fn pulse(&self, i: usize, time: f64) -> (f64, f64) {
+        let s = 0.5 + 0.5 * (time * self.pulse_frequency + i as f64 * self.phase_spread).sin();
+        (
+            self.min_brightness + (self.max_brightness - self.min_brightness) * s,
+            self.min_size + (self.max_size - self.min_size) * s,
+        )
+    }
+
+    /// `pulse` for motes `first..first + 4`, bit for bit
+    #[cfg(feature = "simd")]
+    pub(crate) fn pulse_x4(&self, first: usize, time: f64) -> (Lanes, Lanes) {
+        let index = Lanes::from_array(per_lane!(l => (first + l) as f64));
+        let phase = (time * self.pulse_frequency + index * self.phase_spread).to_array();
+        let s = 0.5 + 0.5 * Lanes::from_array(per_lane!(l => phase[l].sin()));
+        (
+            self.min_brightness + (self.max_brightness - self.min_brightness) * s,
+            self.min_size + (self.max_size - self.min_size) * s,
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Mote {
+    edge: u32,
+    progress: f64,
+    speed: f64,
+    size: f32,
+    age: f64,
+    /// Seconds to live; infinite until `set_lifetime`
+    lifetime: f64,
+    /// Travelling from the edge's first vertex to its second
+    forward: bool,
+    alive: bool,
+}
+
+/// Edge endpoints and vertex → incident edge lists, CSR-style
+#[derive(Clone, Debug)]
+struct EdgeGraph {
+    ends: Vec<(u32, u32)>,
+    offsets: Vec<u32>,
+    /// Edge ids, ascending within each vertex
+    incident: Vec<u32>,
+}
+
+impl EdgeGraph {
+    fn new(edge_indices: &[u32]) -> EdgeGraph {
+        let ends: Vec<(u32, u32)> = edge_indices.chunks_exact(2).map(|e| (e[0], e[1])).collect();
+        let n = edge_indices.iter().max().map_or(0, |&v| v as usize + 1);
+        let mut offsets = vec![0u32; n + 1];
+        for &v in edge_indices {
+            offsets[v as usize + 1] += 1;
+        }
+        for i in 0..n {
+            offsets[i + 1] += offsets[i];
+        }
+        // Filling in edge order leaves every list ascending
+        let mut fill = offsets.clone();
+        let mut incident = vec![0u32; edge_indices.len()];
+        for (e, &(a, b)) in ends.iter().enumerate() {
+            for v in [a, b] {
+                incident[fill[v as usize] as usize] = e as u32;
+                fill[v as usize] += 1;
+            }
+        }
+        EdgeGraph { ends, offsets, incident }
+    }
+
+    /// Edge and direction taken on reaching the end of `edge`; `pick(n)`
+    /// chooses among `n > 0` candidates
+    fn next(&self, edge: u32, forward: bool, pick: impl FnOnce(usize) -> usize) -> (u32, bool) {
+        let (a, b) = self.ends[edge as usize];
+        let at = if forward { b } else { a };
+        let around = &self.incident[self.offsets[at as usize] as usize..][..self.degree(at)];
+        // A self-loop is listed twice; skip both entries
+        let onward = around.iter().filter(|&&e| e != edge).count();
+        let next = if onward == 0 {
+            edge
+        } else {
+            let k = pick(onward);
+            *around.iter().filter(|&&e| e != edge).nth(k).unwrap_or(&edge)
+        };
+        (next, self.ends[next as usize].0 == at)
+    }
+
+    fn degree(&self, v: u32) -> usize {
+        (self.offsets[v as usize + 1] - self.offsets[v as usize]) as usize
+    }
+}
+
+/// How `MoteSystem` weights the edges a jumping mote can land on
+#[derive(Clone, Debug, PartialEq)]
+enum JumpWeights {
+    Uniform,
+    Length,
+    Custom(Vec<f32>),
+}
+
+/// Fixed-capacity set of motes travelling along edges
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct MoteSystem {
+    slots: Vec<Mote>,
+    /// Dead slot ids, reused last-in first-out
+    free: Vec<u32>,
+    capacity: u32,
+    edge_count: u32,
+    /// Flat `[x0, y0, x1, y1, ...]`, `edge_count` edges once set
+    edges: Vec<f32>,
+    positions: Vec<f32>,
+    ids: Vec<u32>,
+    graph: Option<EdgeGraph>,
+    weights: JumpWeights,
+    /// Running sums of the jump weights, empty for uniform jumps
+    cumulative: Vec<f64>,
+    /// `cumulative` is out of date
+    stale: bool,
+    /// Speeds are in canvas units per second rather than edges per second
+    per_length: bool,
+    /// Keep motes on the same edge `min_spacing` canvas units apart
+    spacing: bool,
+    min_spacing: f64,
+    /// Per-frame scratch for the spacing pass: `bucket_ids` holds live
+    /// mote ids grouped by edge and direction, bucket `k` starting at
+    /// `bucket_offsets[k]`
+    bucket_offsets: Vec<u32>,
+    bucket_fill: Vec<u32>,
+    bucket_ids: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl MoteSystem {
+    /// Empty system for `edge_count` edges holding at most `capacity` motes
+    ///
+    /// Positions stay empty until `set_edges` supplies the geometry.
+    #[wasm_bindgen(constructor)]
+    pub fn new(edge_count: u32, capacity: u32) -> MoteSystem {
+        MoteSystem {
+            slots: Vec::with_capacity(capacity as usize),
+            free: Vec::new(),
+            capacity,
+            edge_count,
+            edges: Vec::new(),
+            positions: Vec::with_capacity(capacity as usize * MOTE_STRIDE as usize),
+            ids: Vec::with_capacity(capacity as usize),
+            graph: None,
+            weights: JumpWeights::Uniform,
+            cumulative: Vec::new(),
+            stale: false,
+            per_length: false,
+            spacing: false,
+            min_spacing: 0.0,
+            bucket_offsets: Vec::new(),
+            bucket_fill: Vec::new(),
+            bucket_ids: Vec::new(),
+        }
+    }
+
+    /// Replace the edge geometry; the edge count may change
+    ///
+    /// Motes on an edge that no longer exists jump at the next `update`.
+    /// A graph from `set_graph` and weights from `set_edge_weights` are
+    /// dropped if the count changes.
+    pub fn set_edges(&mut self, edges: &[f32]) -> Result<()> {
+        if !edges.len().is_multiple_of(4) {
+            return Err(Error::InvalidLength(format!(
+                "edge array length {} is not a multiple of 4",
+                edges.len()
+            )));
+        }
+        if edges.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("edge coordinates must be finite".into()));
+        }
+        self.edges.clear();
+        self.edges.extend_from_slice(edges);
+        self.edge_count = (edges.len() / 4) as u32;
+        if self.graph.as_ref().is_some_and(|g| g.ends.len() != self.edge_count as usize) {
+            self.graph = None;
+        }
+        if matches!(&self.weights, JumpWeights::Custom(w) if w.len() != self.edge_count as usize) {
+            self.weights = JumpWeights::Uniform;
+        }
+        self.stale = true;
+        Ok(())
+    }
+
+    /// Make a jump land on edge `k` with probability proportional to
+    /// `weights[k]`, one non-negative weight per edge
+    ///
+    /// Edges of weight 0 are never jumped to, though motes can still
+    /// reach them by flowing along a graph. An empty slice goes back to
+    /// uniform jumps.
+    pub fn set_edge_weights(&mut self, weights: &[f32]) -> Result<()> {
+        if weights.is_empty() {
+            self.weights = JumpWeights::Uniform;
+            self.stale = true;
+            return Ok(());
+        }
+        if weights.len() != self.edge_count as usize {
+            return Err(Error::InvalidLength(format!(
+                "{} weights given for a system of {} edges",
+                weights.len(),
+                self.edge_count
+            )));
+        }
+        if weights.iter().any(|w| !w.is_finite()) {
+            return Err(Error::NonFiniteInput("edge weights must be finite".into()));
+        }
+        if weights.iter().any(|&w| w < 0.0) || !weights.iter().any(|&w| w > 0.0) {
+            return Err(Error::InvalidArgument(
+                "edge weights must be non-negative with at least one positive".into(),
+            ));
+        }
+        self.weights = JumpWeights::Custom(weights.to_vec());
+        self.stale = true;
+        Ok(())
+    }
+
+    /// Weight jumps by edge length, so long edges carry more motes
+    ///
+    /// Follows later `set_edges` calls; jumps stay uniform while every
+    /// edge has zero length.
+    pub fn weight_edges_by_length(&mut self) {
+        self.weights = JumpWeights::Length;
+        self.stale = true;
+    }
+
+    /// Read `speed` as canvas units per second instead of edge lengths
+    /// per second, so on-screen velocity does not depend on the edge
+    pub fn set_speed_per_length(&mut self, on: bool) {
+        self.per_length = on;
+    }
+
+    /// Hold back any mote that comes within `min_spacing` canvas units of
+    /// the one ahead of it on the same edge, when spacing is on
+    ///
+    /// Without edge geometry the spacing is in edge lengths instead. Motes
+    /// travelling in opposite directions pass through each other.
+    pub fn set_min_spacing(&mut self, min_spacing: f64) -> Result<()> {
+        if !min_spacing.is_finite() || min_spacing < 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "minimum spacing must be finite and non-negative, got {min_spacing}"
+            )));
+        }
+        self.min_spacing = min_spacing;
+        Ok(())
+    }
+
+    /// Turn `set_min_spacing` on or off; off by default
+    pub fn set_spacing_enabled(&mut self, on: bool) {
+        self.spacing = on;
+    }
+
+    /// Make motes flow along the graph of `edge_indices`
+    ///
+    /// `edge_indices` is `[i0, j0, i1, j1, ...]` with one pair per edge in
+    /// `set_edges` order, as `compute_edge_indices` returns. A mote that
+    /// reaches vertex `v` continues from `v` along another edge incident
+    /// to it, chosen by the `set_mote_seed` hash, and turns back only when
+    /// its edge is the only one at `v`.
+    pub fn set_graph(&mut self, edge_indices: &[u32]) -> Result<()> {
+        if !edge_indices.len().is_multiple_of(2) {
+            return Err(Error::InvalidLength(format!(
+                "edge index array length {} is odd",
+                edge_indices.len()
+            )));
+        }
+        let count = edge_indices.len() / 2;
+        if count != self.edge_count as usize {
+            return Err(Error::InvalidLength(format!(
+                "{count} edges given for a system of {} edges",
+                self.edge_count
+            )));
+        }
+        self.graph = Some(EdgeGraph::new(edge_indices));
+        Ok(())
+    }
+
+    /// Go back to jumping between unrelated edges
+    pub fn clear_graph(&mut self) {
+        self.graph = None;
+    }
+
+    pub fn has_graph(&self) -> bool {
+        self.graph.is_some()
+    }
+
+    /// Start a mote at the beginning of `edge_idx`; returns its id
+    ///
+    /// Ids are slot numbers below `capacity`, stable until the mote is
+    /// despawned, after which the id may be reused.
+    pub fn spawn(&mut self, edge_idx: u32, speed: f64) -> Result<u32> {
+        if edge_idx >= self.edge_count {
+            return Err(Error::IndexOutOfRange(format!(
+                "edge {edge_idx} out of range for {} edges",
+                self.edge_count
+            )));
+        }
+        if !speed.is_finite() {
+            return Err(Error::NonFiniteInput(format!("mote speed must be finite, got {speed}")));
+        }
+        let mote = Mote {
+            edge: edge_idx,
+            progress: 0.0,
+            speed,
+            size: 1.0,
+            age: 0.0,
+            lifetime: f64::INFINITY,
+            forward: true,
+            alive: true,
+        };
+        if let Some(id) = self.free.pop() {
+            self.slots[id as usize] = mote;
+            return Ok(id);
+        }
+        if self.slots.len() as u32 >= self.capacity {
+            return Err(Error::TooLarge(format!("all {} mote slots are in use", self.capacity)));
+        }
+        self.slots.push(mote);
+        Ok(self.slots.len() as u32 - 1)
+    }
+
+    pub fn despawn(&mut self, id: u32) -> Result<()> {
+        self.live_slot(id)?.alive = false;
+        self.free.push(id);
+        Ok(())
+    }
+
+    /// Despawn `id` once it has lived `seconds` from its spawn
+    pub fn set_lifetime(&mut self, id: u32, seconds: f64) -> Result<()> {
+        if seconds.is_nan() || seconds < 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "lifetime must be non-negative, got {seconds}"
+            )));
+        }
+        self.live_slot(id)?.lifetime = seconds;
+        Ok(())
+    }
+
+    /// Size written to the fourth channel of `positions()`; 1 by default
+    pub fn set_size(&mut self, id: u32, size: f32) -> Result<()> {
+        if !size.is_finite() {
+            return Err(Error::NonFiniteInput(format!("mote size must be finite, got {size}")));
+        }
+        self.live_slot(id)?.size = size;
+        Ok(())
+    }
+
+    pub fn is_alive(&self, id: u32) -> bool {
+        self.slots.get(id as usize).is_some_and(|m| m.alive)
+    }
+
+    pub fn live_count(&self) -> u32 {
+        self.slots.len() as u32 - self.free.len() as u32
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Advance every mote by `dt` seconds and rebuild the outputs
+    ///
+    /// Motes past their lifetime are despawned first. Jumps between edges
+    /// follow `set_mote_seed`, as in `update_motes`. With spacing on, a
+    /// mote that would close in on the one ahead is held `min_spacing`
+    /// behind it instead, so a bunch queues up at the leader's pace.
+    pub fn update(&mut self, dt: f64, time: f64) {
+        self.positions.clear();
+        self.ids.clear();
+        let edge_count = self.edge_count as usize;
+        let have_geometry = self.edges.len() == edge_count * 4;
+        if self.stale {
+            self.rebuild_weights(have_geometry);
+        }
+        for id in 0..self.slots.len() {
+            let mote = &mut self.slots[id];
+            if !mote.alive {
+                continue;
+            }
+            mote.age += dt;
+            if mote.age >= mote.lifetime {
+                mote.alive = false;
+                self.free.push(id as u32);
+                continue;
+            }
+            let stranded = mote.edge as usize >= edge_count;
+            let length = if self.per_length && have_geometry && !stranded {
+                edge_length(&self.edges, mote.edge as usize)
+            } else {
+                1.0
+            };
+            // A zero-length edge is done in one step unless the mote is
+            // standing still
+            mote.progress += mote.speed * dt / length.max(f64::MIN_POSITIVE);
+            if (mote.progress > 1.0 || stranded) && edge_count > 0 {
+                let edge = mote.edge as usize;
+                let pick = |n: usize| crate::jump_target(id, edge, time, n);
+                (mote.edge, mote.forward) = match &self.graph {
+                    Some(graph) if !stranded => graph.next(mote.edge, mote.forward, pick),
+                    _ => (weighted_jump(&self.cumulative, id, edge, time, edge_count), true),
+                };
+                mote.progress = 0.0;
+            }
+        }
+        if self.spacing && self.min_spacing > 0.0 {
+            self.keep_spacing(have_geometry);
+        }
+        for id in 0..self.slots.len() {
+            let mote = &self.slots[id];
+            if !mote.alive || !have_geometry || mote.edge as usize >= edge_count {
+                continue;
+            }
+            let e = &self.edges[mote.edge as usize * 4..mote.edge as usize * 4 + 4];
+            let along = mote.progress.clamp(0.0, 1.0);
+            let p = if mote.forward { along } else { 1.0 - along };
+            let (x0, y0, x1, y1) = (e[0] as f64, e[1] as f64, e[2] as f64, e[3] as f64);
+            let brightness = 0.5 + 0.5 * (time * 2.0 + id as f64 * 0.5).sin();
+            self.positions.extend([
+                (x0 + (x1 - x0) * p) as f32,
+                (y0 + (y1 - y0) * p) as f32,
+                brightness as f32,
+                mote.size,
+            ]);
+            self.ids.push(id as u32);
+        }
+    }
+
+    /// `[x, y, brightness, size]` per live mote from the last `update`,
+    /// dead slots skipped
+    pub fn positions(&self) -> Vec<f32> {
+        self.positions.clone()
+    }
+
+    /// Id of each mote in `positions()` order
+    pub fn ids(&self) -> Vec<u32> {
+        self.ids.clone()
+    }
+
+    /// Serialize every slot, the edges, graph and jump weights, the last
+    /// `update`'s outputs and the `set_mote_seed` seed
+    ///
+    /// Given the same `dt`s and times, a system restored from the bytes
+    /// updates exactly as this one does.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = ByteWriter::new();
+        w.bytes(STATE_MAGIC);
+        w.u8(STATE_VERSION);
+        w.u64(crate::mote_seed());
+        w.u32(self.capacity);
+        w.u32(self.edge_count);
+        w.f32s(&self.edges);
+        let field = |f: fn(&Mote) -> f64| self.slots.iter().map(f).collect::<Vec<f64>>();
+        w.u32s(&self.slots.iter().map(|m| m.edge).collect::<Vec<u32>>());
+        w.f64s(&field(|m| m.progress));
+        w.f64s(&field(|m| m.speed));
+        w.f32s(&self.slots.iter().map(|m| m.size).collect::<Vec<f32>>());
+        w.f64s(&field(|m| m.age));
+        w.f64s(&field(|m| m.lifetime));
+        let flags = self.slots.iter().map(|m| m.forward as u32 | (m.alive as u32) << 1);
+        w.u32s(&flags.collect::<Vec<u32>>());
+        w.u32s(&self.free);
+        w.f32s(&self.positions);
+        w.u32s(&self.ids);
+        w.u8(self.graph.is_some() as u8);
+        if let Some(graph) = &self.graph {
+            w.u32s(&graph.ends.iter().flat_map(|&(a, b)| [a, b]).collect::<Vec<u32>>());
+            w.u32s(&graph.offsets);
+            w.u32s(&graph.incident);
+        }
+        match &self.weights {
+            JumpWeights::Uniform => w.u8(0),
+            JumpWeights::Length => w.u8(1),
+            JumpWeights::Custom(weights) => {
+                w.u8(2);
+                w.f32s(weights);
+            }
+        }
+        w.u8(self.per_length as u8);
+        w.u8(self.spacing as u8);
+        w.f64(self.min_spacing);
+        w.buf
+    }
+
+    /// Replace this system with one saved by `save_state`, and set the
+    /// mote seed it was saved with
+    ///
+    /// Errors on truncated, corrupt or version-mismatched data and then
+    /// leaves both `self` and the seed unchanged.
+    pub fn restore_state(&mut self, bytes: &[u8]) -> Result<()> {
+        let mut r = ByteReader::new(bytes);
+        let version = r.header(STATE_MAGIC)?;
+        if version != STATE_VERSION {
+            return Err(Error::VersionMismatch(format!(
+                "mote state version {version}, expected {STATE_VERSION}"
+            )));
+        }
+        let (seed, restored) = MoteSystem::decode(&mut r)?;
+        r.finish()?;
+        crate::set_mote_seed(seed);
+        *self = restored;
+        Ok(())
+    }
+}
+
+impl MoteSystem {
+    fn rebuild_weights(&mut self, have_geometry: bool) {
+        self.stale = false;
+        self.cumulative.clear();
+        let edge_count = self.edge_count as usize;
+        let mut total = 0.0;
+        let mut push = |w: f64| {
+            total += w;
+            total
+        };
+        match &self.weights {
+            JumpWeights::Uniform => return,
+            JumpWeights::Length if !have_geometry => return,
+            JumpWeights::Length => self
+                .cumulative
+                .extend((0..edge_count).map(|e| push(edge_length(&self.edges, e)))),
+            JumpWeights::Custom(w) => self.cumulative.extend(w.iter().map(|&w| push(w as f64))),
+        }
+        if self.cumulative.last().is_none_or(|&t| t <= 0.0) {
+            self.cumulative.clear();
+        }
+    }
+
+    /// Pull each mote back to at least `min_spacing` behind the one ahead
+    /// of it on its edge, going in the same direction
+    ///
+    /// Motes are bucketed by edge and direction with a counting sort and
+    /// each bucket is walked from its leader back. Only trailing motes
+    /// move, and only backwards, so a settled queue stays put: there is
+    /// nothing for a bunch of three to oscillate between.
+    fn keep_spacing(&mut self, have_geometry: bool) {
+        let edge_count = self.edge_count as usize;
+        let bucket = |m: &Mote| m.edge as usize * 2 + m.forward as usize;
+        let on_edge = |m: &&Mote| m.alive && (m.edge as usize) < edge_count;
+        self.bucket_offsets.clear();
+        self.bucket_offsets.resize(edge_count * 2 + 1, 0);
+        for m in self.slots.iter().filter(on_edge) {
+            self.bucket_offsets[bucket(m) + 1] += 1;
+        }
+        for k in 0..edge_count * 2 {
+            self.bucket_offsets[k + 1] += self.bucket_offsets[k];
+        }
+        let live = self.bucket_offsets[edge_count * 2] as usize;
+        self.bucket_ids.clear();
+        self.bucket_ids.resize(live, 0);
+        self.bucket_fill.clone_from(&self.bucket_offsets);
+        for (id, m) in self.slots.iter().enumerate() {
+            if on_edge(&m) {
+                self.bucket_ids[self.bucket_fill[bucket(m)] as usize] = id as u32;
+                self.bucket_fill[bucket(m)] += 1;
+            }
+        }
+        for k in 0..edge_count * 2 {
+            let range = self.bucket_offsets[k] as usize..self.bucket_offsets[k + 1] as usize;
+            if range.len() < 2 {
+                continue;
+            }
+            let length = if have_geometry { edge_length(&self.edges, k / 2) } else { 1.0 };
+            let gap = self.min_spacing / length.max(f64::MIN_POSITIVE);
+            let ids = &mut self.bucket_ids[range];
+            // Leader first; ids break ties so equal progress sorts the
+            // same way every frame
+            let slots = &self.slots;
+            ids.sort_unstable_by(|&a, &b| {
+                let (pa, pb) = (slots[a as usize].progress, slots[b as usize].progress);
+                pb.total_cmp(&pa).then(a.cmp(&b))
+            });
+            let mut limit = f64::INFINITY;
+            for &id in ids.iter() {
+                let mote = &mut self.slots[id as usize];
+                mote.progress = mote.progress.min(limit).max(0.0);
+                limit = mote.progress - gap;
+            }
+        }
+    }
+
+    /// Inverse of `save_state` after the header, with the seed it saved
+    fn decode(r: &mut ByteReader) -> Result<(u64, MoteSystem)> {
+        let bad = |what: &str| Error::InvalidData(format!("mote state: {what}"));
+        let seed = r.u64()?;
+        let capacity = r.u32()?;
+        let edge_count = r.u32()?;
+        let edges = r.f32s()?;
+        let edge_ids = r.u32s()?;
+        let progress = r.f64s()?;
+        let speed = r.f64s()?;
+        let size = r.f32s()?;
+        let age = r.f64s()?;
+        let lifetime = r.f64s()?;
+        let flags = r.u32s()?;
+        let free = r.u32s()?;
+        let positions = r.f32s()?;
+        let ids = r.u32s()?;
+        let graph = match r.u8()? {
+            0 => None,
+            1 => Some((r.u32s()?, r.u32s()?, r.u32s()?)),
+            _ => return Err(bad("bad graph flag")),
+        };
+        let weights = match r.u8()? {
+            0 => JumpWeights::Uniform,
+            1 => JumpWeights::Length,
+            2 => JumpWeights::Custom(r.f32s()?),
+            _ => return Err(bad("bad weights tag")),
+        };
+        let per_length = r.u8()?;
+        let spacing = r.u8()?;
+        let min_spacing = r.f64()?;
+
+        let n = edge_ids.len();
+        if [progress.len(), speed.len(), size.len(), age.len(), lifetime.len(), flags.len()]
+            .iter()
+            .any(|&len| len != n)
+        {
+            return Err(bad("mote arrays disagree in length"));
+        }
+        if n > capacity as usize {
+            return Err(bad("more slots than capacity"));
+        }
+        if edges.len() % 4 != 0
+            || !(edges.is_empty() || edges.len() == 4 * edge_count as usize)
+            || edges.iter().any(|v| !v.is_finite())
+        {
+            return Err(bad("edge geometry disagrees with the edge count"));
+        }
+        let slots: Vec<Mote> = (0..n)
+            .map(|i| Mote {
+                edge: edge_ids[i],
+                progress: progress[i],
+                speed: speed[i],
+                size: size[i],
+                age: age[i],
+                lifetime: lifetime[i],
+                forward: flags[i] & 1 != 0,
+                alive: flags[i] & 2 != 0,
+            })
+            .collect();
+        let valid = |m: &Mote| {
+            m.speed.is_finite() && m.size.is_finite() && m.lifetime >= 0.0 && !m.age.is_nan()
+        };
+        if flags.iter().any(|&f| f > 3) || !slots.iter().all(valid) {
+            return Err(bad("mote fields out of range"));
+        }
+        let mut listed = vec![false; n];
+        for &id in &free {
+            match slots.get(id as usize) {
+                Some(m) if !m.alive && !listed[id as usize] => listed[id as usize] = true,
+                _ => return Err(bad("free list does not match slots")),
+            }
+        }
+        if slots.iter().filter(|m| !m.alive).count() != free.len() {
+            return Err(bad("free list does not match slots"));
+        }
+        if positions.len() != ids.len() * MOTE_STRIDE as usize
+            || ids.iter().any(|&id| id as usize >= n)
+        {
+            return Err(bad("outputs disagree with the slots"));
+        }
+        let graph = match graph {
+            None => None,
+            Some((ends, offsets, incident)) => {
+                let vertices = offsets.len().saturating_sub(1) as u32;
+                if ends.len() != 2 * edge_count as usize
+                    || incident.len() != ends.len()
+                    || offsets.first() != Some(&0)
+                    || offsets.last() != Some(&(incident.len() as u32))
+                    || offsets.windows(2).any(|o| o[0] > o[1])
+                    || ends.iter().any(|&v| v >= vertices)
+                    || incident.iter().any(|&e| e >= edge_count)
+                {
+                    return Err(bad("edge graph is inconsistent"));
+                }
+                let ends = ends.chunks_exact(2).map(|e| (e[0], e[1])).collect();
+                Some(EdgeGraph { ends, offsets, incident })
+            }
+        };
+        if let JumpWeights::Custom(w) = &weights {
+            if w.len() != edge_count as usize
+                || w.iter().any(|&w| !(w.is_finite() && w >= 0.0))
+                || !w.iter().any(|&w| w > 0.0)
+            {
+                return Err(bad("edge weights out of range"));
+            }
+        }
+        if per_length > 1 || spacing > 1 || !(min_spacing.is_finite() && min_spacing >= 0.0) {
+            return Err(bad("spacing settings out of range"));
+        }
+        let system = MoteSystem {
+            slots,
+            free,
+            capacity,
+            edge_count,
+            edges,
+            positions,
+            ids,
+            graph,
+            weights,
+            // The jump table is derived, so rebuild it on the next update
+            cumulative: Vec::new(),
+            stale: true,
+            per_length: per_length == 1,
+            spacing: spacing == 1,
+            min_spacing,
+            bucket_offsets: Vec::new(),
+            bucket_fill: Vec::new(),
+            bucket_ids: Vec::new(),
+        };
+        Ok((seed, system))
+    }
+
+    fn live_slot(&mut self, id: u32) -> Result<&mut Mote> {
+        match self.slots.get_mut(id as usize) {
+            Some(mote) if mote.alive => Ok(mote),
+            _ => Err(Error::InvalidArgument(format!("mote {id} is not alive"))),
+        }
+    }
+}
+
+/// Length of edge `e` of a flat `[x0, y0, x1, y1, ...]` array
+fn edge_length(edges: &[f32], e: usize) -> f64 {
+    let c = &edges[e * 4..e * 4 + 4];
+    (c[2] as f64 - c[0] as f64).hypot(c[3] as f64 - c[1] as f64)
+}
+
+/// Free jump target: uniform, or drawn from the running weight sums
+fn weighted_jump(cumulative: &[f64], mote: usize, edge: usize, time: f64, n: usize) -> u32 {
+    let Some(&total) = cumulative.last() else {
+        return crate::jump_target(mote, edge, time, n) as u32;
+    };
+    let u = crate::jump_rng(mote, edge, time).next_f64() * total;
+    // The first edge whose running sum passes `u`; zero-weight edges
+    // never do
+    cumulative.partition_point(|&c| c <= u).min(n - 1) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    fn ladder(n: usize) -> Vec<f32> {
+        (0..n).flat_map(|i| [i as f32, 0.0, i as f32, 10.0]).collect()
+    }
+
+    #[test]
+    fn test_ids_are_stable_and_reused() {
+        let mut motes = MoteSystem::new(4, 3);
+        motes.set_edges(&ladder(4)).unwrap();
+        let a = motes.spawn(0, 0.1).unwrap();
+        let b = motes.spawn(1, 0.1).unwrap();
+        let c = motes.spawn(2, 0.1).unwrap();
+        assert_eq!((a, b, c), (0, 1, 2));
+        assert_eq!(code_of(motes.spawn(3, 0.1)), ErrorCode::TooLarge);
+        motes.despawn(b).unwrap();
+        assert_eq!(code_of(motes.despawn(b)), ErrorCode::InvalidArgument);
+        assert_eq!(motes.spawn(3, 0.1).unwrap(), b);
+
+        motes.set_size(c, 2.5).unwrap();
+        motes.update(1.0, 0.0);
+        assert_eq!(motes.ids(), vec![0, 1, 2]);
+        let out = motes.positions();
+        assert_eq!(out.len(), 3 * MOTE_STRIDE as usize);
+        // Mote 1 now runs up edge 3, a tenth of the way along
+        assert_eq!(&out[4..6], &[3.0, 1.0]);
+        assert_eq!(out[11], 2.5);
+
+        assert_eq!(code_of(motes.spawn(4, 0.1)), ErrorCode::IndexOutOfRange);
+        assert_eq!(code_of(motes.spawn(0, f64::NAN)), ErrorCode::NonFiniteInput);
+        assert_eq!(code_of(motes.set_edges(&[0.0; 3])), ErrorCode::InvalidLength);
+    }
+
+    #[test]
+    fn test_graph_motes_flow_through_vertices() {
+        // A path 0-1-2-3 with a branch 1-4; edge k joins the listed pair
+        let indices = [0, 1, 2, 1, 2, 3, 1, 4];
+        let coords = [0.0, 5.0, 3.0, 9.0, 0.0];
+        let edges: Vec<f32> = indices
+            .chunks(2)
+            .flat_map(|e| [coords[e[0] as usize], 0.0, coords[e[1] as usize], 0.0])
+            .collect();
+        let mut motes = MoteSystem::new(4, 4);
+        motes.set_edges(&edges).unwrap();
+        motes.set_graph(&indices).unwrap();
+        let id = motes.spawn(0, 4.0).unwrap();
+        let mut last_x = 0.0;
+        for frame in 0..400 {
+            motes.update(0.1, frame as f64 * 0.1);
+            let x = motes.positions()[0];
+            // No teleports: a step never exceeds speed x dt x longest edge
+            assert!((x - last_x).abs() <= 0.4 * 6.0 + 1e-4, "frame {frame}: {last_x} -> {x}");
+            last_x = x;
+        }
+        assert!(motes.is_alive(id));
+
+        assert_eq!(code_of(motes.set_graph(&indices[..6])), ErrorCode::InvalidLength);
+        assert_eq!(code_of(motes.set_graph(&indices[..7])), ErrorCode::InvalidLength);
+        motes.set_edges(&edges[..12]).unwrap();
+        assert!(!motes.has_graph());
+    }
+
+    #[test]
+    fn test_dead_ends_turn_back_and_junctions_avoid_the_inbound_edge() {
+        let graph = EdgeGraph::new(&[0, 1, 1, 2, 1, 3]);
+        // Arriving at 1 along edge 0 never picks edge 0 again
+        for k in 0..2 {
+            let (next, forward) = graph.next(0, true, |n| {
+                assert_eq!(n, 2);
+                k
+            });
+            assert_eq!((next, forward), ([1, 2][k], true));
+        }
+        // At the leaf 2 the only way on is back along edge 1
+        assert_eq!(graph.next(1, true, |_| unreachable!()), (1, false));
+        assert_eq!(graph.next(1, false, |_| 0), (0, false));
+    }
+
+    #[test]
+    fn test_expired_motes_leave_the_output() {
+        let mut motes = MoteSystem::new(2, 8);
+        motes.set_edges(&ladder(2)).unwrap();
+        let short = motes.spawn(0, 0.01).unwrap();
+        let long = motes.spawn(1, 0.01).unwrap();
+        motes.set_lifetime(short, 0.25).unwrap();
+        for frame in 0..10 {
+            motes.update(0.1, frame as f64 * 0.1);
+        }
+        assert!(!motes.is_alive(short) && motes.is_alive(long));
+        assert_eq!(motes.ids(), vec![long]);
+        assert_eq!(motes.live_count(), 1);
+        assert_eq!(motes.positions().len(), MOTE_STRIDE as usize);
+        assert_eq!(code_of(motes.set_lifetime(short, 1.0)), ErrorCode::InvalidArgument);
+
+        // Shrinking the edge set moves stranded motes onto surviving edges
+        motes.set_edges(&ladder(1)).unwrap();
+        motes.update(0.1, 1.0);
+        assert_eq!(motes.positions()[0], 0.0);
+    }
+
+    #[test]
+    fn test_weighted_jumps_follow_long_edges() {
+        // Edge 1 is nine times as long as edge 0, edge 2 twice edge 1
+        let edges = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 9.0, 1.0, 0.0, 2.0, 18.0, 2.0];
+        let landings = |motes: &mut MoteSystem| {
+            let mut counts = [0u32; 3];
+            for frame in 0..200 {
+                motes.update(1.0, frame as f64);
+                for id in 0..50 {
+                    counts[motes.slots[id].edge as usize] += 1;
+                }
+            }
+            counts
+        };
+        let mut motes = MoteSystem::new(3, 50);
+        motes.set_edges(&edges).unwrap();
+        for _ in 0..50 {
+            motes.spawn(0, 2.0).unwrap();
+        }
+        let uniform = landings(&mut motes);
+        assert!(uniform.iter().all(|&c| (2800..3900).contains(&c)), "{uniform:?}");
+
+        motes.weight_edges_by_length();
+        let by_length = landings(&mut motes);
+        // Expected shares 1/28, 9/28 and 18/28 of 10000 jumps
+        let expected = [357.0, 3214.0, 6429.0];
+        for (&got, want) in by_length.iter().zip(expected) {
+            assert!((got as f64 - want).abs() < 0.15 * want + 60.0, "{by_length:?}");
+        }
+
+        motes.set_edge_weights(&[1.0, 0.0, 1.0]).unwrap();
+        assert_eq!(landings(&mut motes)[1], 0);
+        // A new edge count drops the weights instead of misreading them
+        motes.set_edges(&edges[..8]).unwrap();
+        assert_eq!(motes.weights, JumpWeights::Uniform);
+        motes.set_edge_weights(&[]).unwrap();
+
+        assert_eq!(code_of(motes.set_edge_weights(&[1.0])), ErrorCode::InvalidLength);
+        assert_eq!(code_of(motes.set_edge_weights(&[1.0, f32::NAN])), ErrorCode::NonFiniteInput);
+        assert_eq!(code_of(motes.set_edge_weights(&[1.0, -1.0])), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(motes.set_edge_weights(&[0.0, 0.0])), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_speed_per_length_moves_at_constant_velocity() {
+        let mut motes = MoteSystem::new(2, 2);
+        motes.set_edges(&[0.0, 0.0, 10.0, 0.0, 0.0, 5.0, 40.0, 5.0]).unwrap();
+        motes.set_speed_per_length(true);
+        motes.spawn(0, 4.0).unwrap();
+        motes.spawn(1, 4.0).unwrap();
+        motes.update(1.0, 0.0);
+        let out = motes.positions();
+        // Both moved 4 canvas units, whatever their edge's length
+        assert_eq!((out[0], out[4]), (4.0, 4.0));
+        // Back to edges per second: 0.4 of the 40-unit edge more
+        motes.set_speed_per_length(false);
+        motes.update(0.1, 0.1);
+        assert_eq!(motes.positions()[4], 20.0);
+    }
+
+    #[test]
+    fn test_spacing_queues_motes_on_an_edge() {
+        // One 100-unit edge; without a graph motes jump back onto it
+        let mut motes = MoteSystem::new(1, 8);
+        motes.set_edges(&[0.0, 0.0, 100.0, 0.0]).unwrap();
+        motes.set_speed_per_length(true);
+        let xs = |motes: &MoteSystem| -> Vec<f32> {
+            motes.positions().chunks(4).map(|p| p[0]).collect()
+        };
+        // Three bunched motes, the fastest at the back
+        for speed in [10.0, 20.0, 30.0] {
+            motes.spawn(0, speed).unwrap();
+        }
+        motes.update(1.0, 0.0);
+        assert_eq!(xs(&motes), [10.0, 20.0, 30.0], "spacing is off by default");
+
+        let mut spaced = MoteSystem::new(1, 8);
+        spaced.set_edges(&[0.0, 0.0, 100.0, 0.0]).unwrap();
+        spaced.set_speed_per_length(true);
+        spaced.set_min_spacing(15.0).unwrap();
+        spaced.set_spacing_enabled(true);
+        for speed in [30.0, 10.0, 20.0] {
+            spaced.spawn(0, speed).unwrap();
+        }
+        spaced.update(1.0, 0.0);
+        // The leader at 30 keeps its place, the others queue behind it
+        assert_eq!(xs(&spaced), [30.0, 0.0, 15.0]);
+
+        // Faster motes packed behind a slow leader drop back to 15 apart at
+        // once, then move in step with it, frame after frame
+        for (slot, progress) in spaced.slots.iter_mut().zip([0.5, 0.45, 0.4]) {
+            slot.progress = progress;
+        }
+        spaced.slots[0].speed = 10.0;
+        for frame in 0..20 {
+            spaced.update(0.1, frame as f64 * 0.1);
+            let now = xs(&spaced);
+            let lead = 51.0 + frame as f32;
+            for (a, b) in now.iter().zip([lead, lead - 15.0, lead - 30.0]) {
+                assert!((a - b).abs() < 1e-4, "frame {frame}: {now:?}");
+            }
+        }
+
+        // Motes going opposite ways pass through each other
+        let mut passing = MoteSystem::new(1, 2);
+        passing.set_edges(&[0.0, 0.0, 100.0, 0.0]).unwrap();
+        passing.set_min_spacing(50.0).unwrap();
+        passing.set_spacing_enabled(true);
+        passing.spawn(0, 0.1).unwrap();
+        passing.spawn(0, 0.1).unwrap();
+        passing.slots[0].progress = 0.4;
+        (passing.slots[1].progress, passing.slots[1].forward) = (0.4, false);
+        passing.update(1.0, 0.0);
+        assert_eq!(xs(&passing), [50.0, 50.0]);
+        passing.update(1.0, 1.0);
+        assert_eq!(xs(&passing), [60.0, 40.0]);
+        assert_eq!(code_of(passing.set_min_spacing(-1.0)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(passing.set_min_spacing(f64::NAN)), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_restored_state_replays_exactly() {
+        crate::set_mote_seed(17);
+        let mut motes = MoteSystem::new(6, 12);
+        motes.set_edges(&ladder(6)).unwrap();
+        motes.set_graph(&[0, 1, 1, 2, 2, 3, 3, 0, 1, 3, 2, 4]).unwrap();
+        motes.weight_edges_by_length();
+        motes.set_min_spacing(0.5).unwrap();
+        motes.set_spacing_enabled(true);
+        for k in 0..10 {
+            let id = motes.spawn(k % 6, 0.3 + k as f64 * 0.11).unwrap();
+            motes.set_lifetime(id, 1.0 + k as f64).unwrap();
+        }
+        let frames = |m: &mut MoteSystem, from: usize| -> Vec<(Vec<f32>, Vec<u32>)> {
+            (from..from + 60)
+                .map(|f| {
+                    m.update(0.1, f as f64 * 0.1);
+                    (m.positions(), m.ids())
+                })
+                .collect()
+        };
+        frames(&mut motes, 0);
+        let bytes = motes.save_state();
+        let expected = frames(&mut motes, 60);
+
+        crate::set_mote_seed(0);
+        let mut replay = MoteSystem::new(1, 1);
+        replay.restore_state(&bytes).unwrap();
+        assert_eq!(crate::mote_seed(), 17);
+        assert_eq!(frames(&mut replay, 60), expected);
+        assert_eq!(replay.save_state(), motes.save_state());
+    }
+
+    #[test]
+    fn test_restore_state_rejects_corrupt_input() {
+        let mut motes = MoteSystem::new(3, 4);
+        motes.set_edges(&ladder(3)).unwrap();
+        motes.set_graph(&[0, 1, 1, 2, 2, 0]).unwrap();
+        motes.set_edge_weights(&[1.0, 0.0, 2.0]).unwrap();
+        motes.spawn(1, 0.5).unwrap();
+        motes.update(0.5, 0.0);
+        let bytes = motes.save_state();
+
+        let mut target = MoteSystem::new(2, 2);
+        let mut restore = |b: &[u8]| code_of(target.restore_state(b));
+        assert_eq!(restore(&bytes[..bytes.len() - 1]), ErrorCode::InvalidData);
+        let mut version = bytes.clone();
+        version[4] = STATE_VERSION + 1;
+        assert_eq!(restore(&version), ErrorCode::VersionMismatch);
+        assert_eq!(restore(b"VGDT\x01"), ErrorCode::InvalidData);
+        // Flipping any single byte must error or decode, never panic
+        for i in 0..bytes.len() {
+            let mut flipped = bytes.clone();
+            flipped[i] ^= 0xA5;
+            let _ = target.restore_state(&flipped);
+        }
+    }
+}
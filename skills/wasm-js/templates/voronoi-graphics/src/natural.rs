@@ -0,0 +1,296 @@
+//! Natural-neighbour (Sibson) interpolation of per-seed values
+//!
+//! The value at `q` is a weighted mean over the seeds whose Voronoi cells
+//! would shrink if `q` were added as a seed, each weighted by the area its
+//! cell would lose. Unlike barycentric interpolation over the Delaunay
+//! triangles, the field has no creases along triangle edges: it is
+//! smooth everywhere except at the seeds themselves, and it reproduces
+//! linear fields exactly.
+//!
+//! Nothing is actually inserted. The seeds that would lose area are the
+//! corners of the Bowyer-Watson cavity of `q`, found with the same walk and
+//! incircle test `Mesh::insert_vertex` uses, but the mesh is left untouched,
+//! so there is nothing to roll back between queries. The cell `q` would
+//! get is the polygon of circumcentres of the fan that would fill the
+//! cavity, and the area a corner loses is the part of that polygon still
+//! nearest to it.
+
+use wasm_bindgen::prelude::*;
+
+use crate::centers::circumcircle;
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::mesh::{Mesh, SUPER};
+use crate::predicates::signed_area2;
+use crate::refine::Bounds;
+use crate::spatial::PointGrid;
+use crate::voronoi::clip_half_plane;
+use crate::Point;
+
+/// Samples `NaturalNeighborInterpolator::sample_grid` produces at most
+const MAX_GRID_SAMPLES: u64 = 1 << 24;
+
+fn check_query(x: f64, y: f64) -> Result<Point> {
+    if !(x.is_finite() && y.is_finite()) {
+        return Err(Error::NonFiniteInput(format!("query point ({x}, {y}) is not finite")));
+    }
+    Ok(Point::new(x, y))
+}
+
+fn parse_queries(coords: &[f64]) -> Result<Vec<Point>> {
+    if !coords.len().is_multiple_of(2) {
+        return Err(Error::InvalidLength(format!(
+            "query coordinate count {} is odd",
+            coords.len()
+        )));
+    }
+    coords.chunks_exact(2).map(|c| check_query(c[0], c[1])).collect()
+}
+
+/// Seeds triangulated once for repeated natural-neighbour queries
+///
+/// Queries walk from the previous one, so sampling in scan order, as
+/// `sample_grid` does, keeps each walk to a step or two. Queries outside
+/// the seeds' convex hull, where no natural neighbours enclose them, take
+/// the value of the nearest seed; so do all queries when the seeds are
+/// collinear. Of several seeds at the same coordinates, the first one's
+/// value is used.
+#[wasm_bindgen]
+pub struct NaturalNeighborInterpolator {
+    mesh: Mesh,
+    values: Vec<f64>,
+    grid: PointGrid,
+}
+
+#[wasm_bindgen]
+impl NaturalNeighborInterpolator {
+    /// `values[i]` is the field at seed `i` of `[x0, y0, x1, y1, ...]`
+    #[wasm_bindgen(constructor)]
+    pub fn new(points_flat: &[f64], values: &[f64]) -> Result<NaturalNeighborInterpolator> {
+        let n = check_points(points_flat)?;
+        if n == 0 {
+            return Err(Error::InvalidArgument("no seeds to interpolate between".into()));
+        }
+        if values.len() != n {
+            return Err(Error::InvalidLength(format!(
+                "{n} seeds but {} values",
+                values.len()
+            )));
+        }
+        if points_flat.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("seed coordinates must be finite".into()));
+        }
+        if values.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("seed values must be finite".into()));
+        }
+        let seeds: Vec<Point> =
+            points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+        let first = seeds[0];
+        let grid = PointGrid::new(&seeds, [first.x, first.y, first.x, first.y]);
+        let mesh = Mesh::build(&seeds, None);
+        Ok(NaturalNeighborInterpolator { mesh, values: values.to_vec(), grid })
+    }
+
+    pub fn seed_count(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Interpolated value at `(x, y)`
+    pub fn at(&mut self, x: f64, y: f64) -> Result<f64> {
+        let q = check_query(x, y)?;
+        Ok(self.value(q))
+    }
+
+    /// `at` for every point of `[x0, y0, x1, y1, ...]`
+    pub fn at_batch(&mut self, coords: &[f64]) -> Result<Vec<f64>> {
+        let queries = parse_queries(coords)?;
+        Ok(queries.into_iter().map(|q| self.value(q)).collect())
+    }
+
+    /// `cols × rows` samples at the cell centres of a grid over `bounds`,
+    /// row by row from `min_y`
+    pub fn sample_grid(&mut self, bounds: Bounds, cols: u32, rows: u32) -> Result<Vec<f64>> {
+        let Bounds { min_x, min_y, max_x, max_y } = bounds;
+        if ![min_x, min_y, max_x, max_y].iter().all(|v| v.is_finite()) {
+            return Err(Error::NonFiniteInput("grid bounds must be finite".into()));
+        }
+        if cols == 0 || rows == 0 {
+            return Err(Error::InvalidArgument("cols and rows must be positive".into()));
+        }
+        if cols as u64 * rows as u64 > MAX_GRID_SAMPLES {
+            return Err(Error::TooLarge(format!("a {cols}x{rows} grid is too large")));
+        }
+        let (dx, dy) = ((max_x - min_x) / cols as f64, (max_y - min_y) / rows as f64);
+        let mut out = Vec::with_capacity(cols as usize * rows as usize);
+        for r in 0..rows {
+            let y = min_y + (r as f64 + 0.5) * dy;
+            for c in 0..cols {
+                out.push(self.value(Point::new(min_x + (c as f64 + 0.5) * dx, y)));
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl NaturalNeighborInterpolator {
+    fn nearest(&self, q: Point) -> f64 {
+        // Never empty, so never the fallback
+        self.values[self.grid.nearest(q).unwrap_or(0) as usize]
+    }
+
+    fn value(&mut self, q: Point) -> f64 {
+        let t0 = match self.mesh.locate(q) {
+            Some(t) if self.mesh.is_real_triangle(t as usize) => t,
+            _ => return self.nearest(q),
+        };
+        let mesh = &self.mesh;
+        if let Some(&v) = mesh.tris[t0 as usize].iter().find(|&&v| mesh.points[v as usize] == q) {
+            return self.values[(v - SUPER) as usize];
+        }
+
+        let cavity = self.mesh.cavity(q, 0.0, t0);
+        let mesh = &self.mesh;
+        let mut ring: Vec<u32> = cavity.iter().flat_map(|&t| mesh.tris[t as usize]).collect();
+        ring.sort_unstable();
+        ring.dedup();
+        // The cavity is star-shaped from q, so going round q is going
+        // round its rim
+        let angle = |v: u32| {
+            let p = mesh.points[v as usize];
+            (p.y - q.y).atan2(p.x - q.x)
+        };
+        ring.sort_by(|&a, &b| angle(a).total_cmp(&angle(b)));
+        let sites: Vec<Point> = ring.iter().map(|&v| mesh.points[v as usize]).collect();
+        let m = sites.len();
+        let cell: Vec<Point> =
+            (0..m).map(|k| circumcircle(q, sites[k], sites[(k + 1) % m]).0).collect();
+
+        let (mut total, mut sum) = (0.0, 0.0);
+        let (mut poly, mut scratch) = (Vec::with_capacity(m + 4), Vec::with_capacity(m + 4));
+        for (k, &s) in sites.iter().enumerate() {
+            // Super-triangle corners only steal area next to the hull and
+            // carry no value; leaving them out renormalizes the rest
+            if ring[k] < SUPER {
+                continue;
+            }
+            poly.clone_from(&cell);
+            for (j, &o) in sites.iter().enumerate() {
+                if j == k || poly.is_empty() {
+                    continue;
+                }
+                let mid = Point::new((s.x + o.x) * 0.5, (s.y + o.y) * 0.5);
+                clip_half_plane(&poly, mid, Point::new(o.x - s.x, o.y - s.y), &mut scratch);
+                std::mem::swap(&mut poly, &mut scratch);
+            }
+            let area = signed_area2(&poly);
+            total += area;
+            sum += area * self.values[(ring[k] - SUPER) as usize];
+        }
+        // Also false for NaN, which a degenerate circumcentre would give
+        if total > 0.0 {
+            sum / total
+        } else {
+            self.nearest(q)
+        }
+    }
+}
+
+/// Natural-neighbour interpolation of `values` at `(x, y)`
+///
+/// One-off form of `NaturalNeighborInterpolator::at`; it triangulates the
+/// seeds on every call, so build the interpolator for repeated queries.
+#[wasm_bindgen]
+pub fn natural_neighbor_at(points_flat: &[f64], values: &[f64], x: f64, y: f64) -> Result<f64> {
+    NaturalNeighborInterpolator::new(points_flat, values)?.at(x, y)
+}
+
+/// `natural_neighbor_at` for every point of `[x0, y0, x1, y1, ...]`,
+/// triangulating the seeds once
+#[wasm_bindgen]
+pub fn natural_neighbor_batch(
+    points_flat: &[f64],
+    values: &[f64],
+    coords: &[f64],
+) -> Result<Vec<f64>> {
+    NaturalNeighborInterpolator::new(points_flat, values)?.at_batch(coords)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+
+    fn scattered(count: usize, seed: u64) -> Vec<f64> {
+        let mut rng = Rng::new(seed);
+        (0..count).flat_map(|_| [rng.range(0.0, 400.0), rng.range(0.0, 300.0)]).collect()
+    }
+
+    #[test]
+    fn test_reproduces_linear_fields_and_seed_values() {
+        let points = scattered(300, 5);
+        let linear = |x: f64, y: f64| 3.0 * x - 2.0 * y + 7.0;
+        let values: Vec<f64> = points.chunks(2).map(|p| linear(p[0], p[1])).collect();
+        let mut field = NaturalNeighborInterpolator::new(&points, &values).unwrap();
+        assert_eq!(field.seed_count(), 300);
+
+        // Well inside the hull the weights are exact Sibson coordinates
+        let mut rng = Rng::new(9);
+        for _ in 0..500 {
+            let (x, y) = (rng.range(60.0, 340.0), rng.range(60.0, 240.0));
+            let v = field.at(x, y).unwrap();
+            assert!((v - linear(x, y)).abs() < 1e-6, "{v} vs {} at ({x}, {y})", linear(x, y));
+        }
+        for (i, p) in points.chunks(2).enumerate().step_by(37) {
+            assert_eq!(field.at(p[0], p[1]).unwrap(), values[i]);
+        }
+
+        // Far outside the hull the nearest seed decides
+        let corner = crate::nearest_seed(&points, -500.0, -500.0).unwrap();
+        assert_eq!(field.at(-500.0, -500.0).unwrap(), values[corner as usize]);
+
+        let coords = [100.0, 100.0, 250.5, 80.25, -500.0, -500.0];
+        let batch = natural_neighbor_batch(&points, &values, &coords).unwrap();
+        for (q, &v) in coords.chunks(2).zip(&batch) {
+            assert_eq!(v, natural_neighbor_at(&points, &values, q[0], q[1]).unwrap());
+        }
+        let grid = field.sample_grid(Bounds::new(100.0, 50.0, 300.0, 250.0), 4, 2).unwrap();
+        assert_eq!(grid.len(), 8);
+        assert_eq!(grid[5], field.at(175.0, 200.0).unwrap());
+
+        // The centre of a square takes a quarter from each corner
+        let square = [0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0];
+        let centre = natural_neighbor_at(&square, &[1.0, 2.0, 3.0, 6.0], 5.0, 5.0).unwrap();
+        assert!((centre - 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_field_is_bounded_and_continuous() {
+        let points = scattered(120, 13);
+        let mut rng = Rng::new(2);
+        let values: Vec<f64> = (0..120).map(|_| rng.range(-1.0, 1.0)).collect();
+        let mut field = NaturalNeighborInterpolator::new(&points, &values).unwrap();
+        // A fine scan line across the whole canvas, hull crossings included
+        let line: Vec<f64> = (0..=4000)
+            .flat_map(|i| [i as f64 * 0.1, 150.0 + i as f64 * 0.01])
+            .collect();
+        let samples = field.at_batch(&line).unwrap();
+        assert!(samples.iter().all(|v| (-1.0..=1.0).contains(v)));
+        let inside = &samples[400..3600];
+        let jump = inside.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0.0, f64::max);
+        assert!(jump < 0.05, "largest step {jump}");
+
+        let collinear = [0.0, 0.0, 1.0, 1.0, 2.0, 2.0];
+        assert_eq!(natural_neighbor_at(&collinear, &[1.0, 2.0, 3.0], 1.9, 2.2).unwrap(), 3.0);
+
+        let at = |p: &[f64], v: &[f64], x| code_of(natural_neighbor_at(p, v, x, 0.0));
+        assert_eq!(at(&[], &[], 0.0), ErrorCode::InvalidArgument);
+        assert_eq!(at(&[0.0, 0.0], &[1.0, 2.0], 0.0), ErrorCode::InvalidLength);
+        assert_eq!(at(&[0.0, 0.0], &[f64::NAN], 0.0), ErrorCode::NonFiniteInput);
+        assert_eq!(at(&[0.0, 0.0], &[1.0], f64::INFINITY), ErrorCode::NonFiniteInput);
+        assert_eq!(code_of(field.at_batch(&[1.0])), ErrorCode::InvalidLength);
+        let bounds = Bounds::new(0.0, 0.0, 1.0, 1.0);
+        assert_eq!(code_of(field.sample_grid(bounds, 0, 4)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(field.sample_grid(bounds, 1 << 13, 1 << 12)), ErrorCode::TooLarge);
+    }
+}
@@ -0,0 +1,1408 @@
+//! Simplex noise, seeded lattices and fractal sums of octaves
+//!
+//! `simplex_noise_2d` hashes lattice corners with a fixed integer mix, so
+//! it is one field shared by every caller. `NoiseGenerator` swaps that for
+//! a classic 256-entry permutation table shuffled from a seed; seed 0
+//! keeps the fixed mix and matches `simplex_noise_2d` exactly. The shuffle
+//! runs on the crate's PCG32, so a seed gives the same field in every
+//! release.
+//!
+//! Octave `i` of a fractal sum samples the base noise at `lacunarity^i`
+//! times the input frequency with amplitude `gain^i`, and the sum is
+//! divided by the total amplitude, so the range is [-1, 1] for any octave
+//! count and adding octaves adds detail without changing the overall
+//! scale. Each octave is also offset by a fixed shift so the lattices do
+//! not line up at the origin.
+//!
+//! Domain warping samples such a sum at `p + k·w(p)`, where `w` is itself
+//! a pair of fBm sums, for marbled, folded fields; with two levels `w` is
+//! warped the same way first. The warp sums read the same seeded lattice
+//! at their own fixed domain shifts, far enough apart to be uncorrelated
+//! with each other and with the field they displace.
+
+use wasm_bindgen::prelude::*;
+
+use crate::buffers::check_out_len;
+use crate::codec::{ByteReader, ByteWriter};
+use crate::error::{Error, Result};
+use crate::refine::Bounds;
+use crate::rng::Rng;
+#[cfg(feature = "simd")]
+use crate::simd::Lanes;
+
+/// Octave counts above this add nothing visible at f64 precision
+pub const MAX_OCTAVES: u32 = 24;
+
+/// Per-octave domain shift
+const OCTAVE_SHIFT: (f64, f64) = (17.31, -9.77);
+
+/// Levels of warping `warped_noise_2d` nests at most
+pub const MAX_WARP_LEVELS: u32 = 2;
+
+/// Domain shifts of the x and y warp sums, per level
+const WARP_SHIFT: [[(f64, f64); 2]; MAX_WARP_LEVELS as usize] =
+    [[(41.3, 7.9), (-23.6, 58.1)], [(67.4, -31.2), (-52.7, -44.9)]];
+
+/// Samples a warped noise grid computes in one call
+const MAX_GRID_SAMPLES: u64 = 1 << 26;
+
+const STATE_MAGIC: &[u8; 4] = b"VGNG";
+const STATE_VERSION: u8 = 1;
+
+/// How each octave is shaped before summing
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FractalMode {
+    /// Plain fractal Brownian motion
+    Fbm = 0,
+    /// `1 - |n|` per octave: sharp crests where the noise crosses zero
+    Ridged = 1,
+    /// `|n|` per octave: billowy, with creases at the zero crossings
+    Turbulence = 2,
+}
+
+/// Gradient hash for the simplex lattice corners
+#[derive(Clone, Debug)]
+enum Lattice {
+    /// The fixed integer mix behind `simplex_noise_2d`
+    Mixed,
+    /// Shuffled permutation, doubled so `p[i + p[j]]` needs no wrap
+    Permuted(Box<[u8; 512]>),
+}
+
+impl Lattice {
+    fn from_seed(seed: u32) -> Lattice {
+        if seed == 0 {
+            return Lattice::Mixed;
+        }
+        let mut perm: [u8; 256] = std::array::from_fn(|i| i as u8);
+        let mut rng = Rng::new(seed as u64);
+        for i in (1..256).rev() {
+            perm.swap(i, rng.below(i as u32 + 1) as usize);
+        }
+        Lattice::Permuted(Box::new(std::array::from_fn(|i| perm[i & 255])))
+    }
+
+    /// Index into the 12 gradients for lattice corner `(i, j)`
+    fn gradient_2d(&self, i: i32, j: i32) -> usize {
+        match self {
+            Lattice::Mixed => {
+                ((i.wrapping_mul(374761393) ^ j.wrapping_mul(668265263)) as u32) as usize % 12
+            }
+            Lattice::Permuted(p) => {
+                p[(i & 255) as usize + p[(j & 255) as usize] as usize] as usize % 12
+            }
+        }
+    }
+
+    /// Index into the 12 edge gradients for lattice corner `(i, j, k)`
+    fn gradient_3d(&self, i: i32, j: i32, k: i32) -> usize {
+        match self {
+            Lattice::Mixed => {
+                let mut h = (i.wrapping_mul(374761393)
+                    ^ j.wrapping_mul(668265263)
+                    ^ k.wrapping_mul(1440662683)) as u32;
+                h = (h ^ (h >> 13)).wrapping_mul(1274126177);
+                (h ^ (h >> 16)) as usize % 12
+            }
+            Lattice::Permuted(p) => {
+                let pk = p[(k & 255) as usize] as usize;
+                let pj = p[(j & 255) as usize + pk] as usize;
+                p[(i & 255) as usize + pj] as usize % 12
+            }
+        }
+    }
+}
+
+/// `$one(x, y)` for each `[x, y]` pair of `$coords`, in order; with the
+/// `simd` feature, `simd::map_pairs` may take whole groups of four pairs
+/// through `$four` instead
+macro_rules! map_pairs {
+    ($coords:expr, $one:expr, $four:expr) => {{
+        #[cfg(feature = "simd")]
+        let out = crate::simd::map_pairs($coords, $one, $four);
+        #[cfg(not(feature = "simd"))]
+        let out: Vec<_> = $coords.chunks_exact(2).map(|c| ($one)(c[0], c[1])).collect();
+        out
+    }};
+}
+
+/// Simple 2D simplex noise for organic animation
+/// Returns value in range [-1, 1]
+#[wasm_bindgen]
+pub fn simplex_noise_2d(x: f64, y: f64) -> f64 {
+    simplex_2d(x, y, &Lattice::Mixed)
+}
+
+/// Exact gradient `[d/dx, d/dy]` of `simplex_noise_2d` at `(x, y)`
+///
+/// Continuous everywhere, since each corner's falloff reaches zero with
+/// zero slope, which makes it usable for curl noise and normal maps.
+#[wasm_bindgen]
+pub fn simplex_noise_2d_gradient(x: f64, y: f64) -> Vec<f64> {
+    let (dx, dy) = simplex_noise_2d_gradient_xy(x, y);
+    vec![dx, dy]
+}
+
+/// `simplex_noise_2d_gradient` without the allocation
+pub(crate) fn simplex_noise_2d_gradient_xy(x: f64, y: f64) -> (f64, f64) {
+    let (_, dx, dy) = simplex_2d_deriv(x, y, &Lattice::Mixed);
+    (dx, dy)
+}
+
+/// `[value, d/dx, d/dy]` of `simplex_noise_2d` at `(x, y)` in one pass
+///
+/// The value is exactly `simplex_noise_2d(x, y)`.
+#[wasm_bindgen]
+pub fn simplex_noise_2d_deriv(x: f64, y: f64) -> Vec<f64> {
+    let (n, dx, dy) = simplex_2d_deriv(x, y, &Lattice::Mixed);
+    vec![n, dx, dy]
+}
+
+/// `simplex_noise_2d_deriv` for each `[x, y]` pair of `coords`, as
+/// `[value0, dx0, dy0, value1, ...]`
+#[wasm_bindgen]
+pub fn simplex_noise_2d_deriv_batch(coords: &[f64]) -> Result<Vec<f64>> {
+    simplex_2d_deriv_batch(&Lattice::Mixed, coords)
+}
+
+fn simplex_2d_deriv_batch(lattice: &Lattice, coords: &[f64]) -> Result<Vec<f64>> {
+    check_coords(coords)?;
+    let samples = map_pairs!(
+        coords,
+        |x, y| <[f64; 3]>::from(simplex_2d_deriv(x, y, lattice)),
+        |x, y| crate::simd::transpose(simplex_2d_deriv_x4(x, y, lattice))
+    );
+    Ok(samples.into_flattened())
+}
+
+/// Gradients of the 2D lattice, picked by `Lattice::gradient_2d`
+const GRADIENTS_2D: [(f64, f64); 12] = [
+    (1.0, 1.0), (-1.0, 1.0), (1.0, -1.0), (-1.0, -1.0),
+    (1.0, 0.0), (-1.0, 0.0), (1.0, 0.0), (-1.0, 0.0),
+    (0.0, 1.0), (0.0, -1.0), (0.0, 1.0), (0.0, -1.0),
+];
+
+// Skew and unskew factors for 2D
+const F2: f64 = 0.5 * (1.732050808 - 1.0); // (sqrt(3) - 1) / 2
+const G2: f64 = (3.0 - 1.732050808) / 6.0; // (3 - sqrt(3)) / 6
+
+/// Offset of `(x, y)` from each corner of its simplex, with the corner's
+/// gradient
+fn simplex_corners_2d(x: f64, y: f64, lattice: &Lattice) -> [((f64, f64), (f64, f64)); 3] {
+    // Skew input space
+    let s = (x + y) * F2;
+    let i = (x + s).floor();
+    let j = (y + s).floor();
+
+    // Unskew to get (x, y) distances from cell origin
+    let t = (i + j) * G2;
+    let x0 = x - (i - t);
+    let y0 = y - (j - t);
+
+    // Determine which simplex we're in
+    let (i1, j1) = if x0 > y0 { (1.0, 0.0) } else { (0.0, 1.0) };
+
+    let x1 = x0 - i1 + G2;
+    let y1 = y0 - j1 + G2;
+    let x2 = x0 - 1.0 + 2.0 * G2;
+    let y2 = y0 - 1.0 + 2.0 * G2;
+
+    // Hash coordinates to get gradient indices
+    let ii = (i as i32) & 255;
+    let jj = (j as i32) & 255;
+    let grad = |x: i32, y: i32| GRADIENTS_2D[lattice.gradient_2d(x, y)];
+
+    [
+        ((x0, y0), grad(ii, jj)),
+        ((x1, y1), grad(ii + i1 as i32, jj + j1 as i32)),
+        ((x2, y2), grad(ii + 1, jj + 1)),
+    ]
+}
+
+fn simplex_2d(x: f64, y: f64, lattice: &Lattice) -> f64 {
+    // Sum the contributions from the three corners
+    let mut n = 0.0;
+    for ((x, y), (gx, gy)) in simplex_corners_2d(x, y, lattice) {
+        let mut t = 0.5 - x * x - y * y;
+        if t >= 0.0 {
+            t *= t;
+            n += t * t * (gx * x + gy * y);
+        }
+    }
+
+    // Scale to [-1, 1]
+    70.0 * n
+}
+
+/// `simplex_2d` with its analytic `d/dx` and `d/dy`
+fn simplex_2d_deriv(x: f64, y: f64, lattice: &Lattice) -> (f64, f64, f64) {
+    // Each corner adds t⁴ (g · d) with t = 0.5 - |d|², and d moves one for
+    // one with (x, y) inside a simplex, so its gradient is
+    // t⁴ g - 8 t³ (g · d) d
+    let (mut n, mut dx, mut dy) = (0.0, 0.0, 0.0);
+    for ((x, y), (gx, gy)) in simplex_corners_2d(x, y, lattice) {
+        let t = 0.5 - x * x - y * y;
+        if t >= 0.0 {
+            let (t2, dot) = (t * t, gx * x + gy * y);
+            let t3 = t2 * t;
+            // Same operation order as `simplex_2d`, so the values match
+            n += t2 * t2 * dot;
+            dx += t3 * (t * gx - 8.0 * dot * x);
+            dy += t3 * (t * gy - 8.0 * dot * y);
+        }
+    }
+    (70.0 * n, 70.0 * dx, 70.0 * dy)
+}
+
+/// Offsets and gradients of one corner for four points
+#[cfg(feature = "simd")]
+type Corner4 = ((Lanes, Lanes), (Lanes, Lanes));
+
+/// `simplex_corners_2d` for four points at once
+#[cfg(feature = "simd")]
+fn simplex_corners_2d_x4(x: Lanes, y: Lanes, lattice: &Lattice) -> [Corner4; 3] {
+    let s = (x + y) * F2;
+    let i = (x + s).floor();
+    let j = (y + s).floor();
+
+    let t = (i + j) * G2;
+    let x0 = x - (i - t);
+    let y0 = y - (j - t);
+
+    let lower = x0.gt(y0);
+    let (one, zero) = (Lanes::splat(1.0), Lanes::splat(0.0));
+    let x1 = x0 - lower.select(one, zero) + G2;
+    let y1 = y0 - lower.select(zero, one) + G2;
+    let x2 = x0 - 1.0 + 2.0 * G2;
+    let y2 = y0 - 1.0 + 2.0 * G2;
+
+    // The gradient hash is integer work on each lane
+    let (i, j, x0a, y0a) = (i.to_array(), j.to_array(), x0.to_array(), y0.to_array());
+    let mut g = [[[0.0; 4]; 2]; 3];
+    for l in 0..4 {
+        let (ii, jj) = ((i[l] as i32) & 255, (j[l] as i32) & 255);
+        let (i1, j1) = if x0a[l] > y0a[l] { (1, 0) } else { (0, 1) };
+        (g[0][0][l], g[0][1][l]) = GRADIENTS_2D[lattice.gradient_2d(ii, jj)];
+        (g[1][0][l], g[1][1][l]) = GRADIENTS_2D[lattice.gradient_2d(ii + i1, jj + j1)];
+        (g[2][0][l], g[2][1][l]) = GRADIENTS_2D[lattice.gradient_2d(ii + 1, jj + 1)];
+    }
+    [
+        ((x0, y0), (Lanes::from_array(g[0][0]), Lanes::from_array(g[0][1]))),
+        ((x1, y1), (Lanes::from_array(g[1][0]), Lanes::from_array(g[1][1]))),
+        ((x2, y2), (Lanes::from_array(g[2][0]), Lanes::from_array(g[2][1]))),
+    ]
+}
+
+/// `simplex_2d` for four points at once, bit for bit
+#[cfg(feature = "simd")]
+fn simplex_2d_x4(x: Lanes, y: Lanes, lattice: &Lattice) -> Lanes {
+    let mut n = Lanes::splat(0.0);
+    for &((x, y), (gx, gy)) in &simplex_corners_2d_x4(x, y, lattice) {
+        let t = 0.5 - x * x - y * y;
+        let t2 = t * t;
+        n = t.ge(Lanes::splat(0.0)).select(n + t2 * t2 * (gx * x + gy * y), n);
+    }
+    70.0 * n
+}
+
+/// `simplex_2d_deriv` for four points at once, bit for bit
+#[cfg(feature = "simd")]
+fn simplex_2d_deriv_x4(x: Lanes, y: Lanes, lattice: &Lattice) -> [Lanes; 3] {
+    let zero = Lanes::splat(0.0);
+    let (mut n, mut dx, mut dy) = (zero, zero, zero);
+    for &((x, y), (gx, gy)) in &simplex_corners_2d_x4(x, y, lattice) {
+        let t = 0.5 - x * x - y * y;
+        let inside = t.ge(zero);
+        let (t2, dot) = (t * t, gx * x + gy * y);
+        let t3 = t2 * t;
+        n = inside.select(n + t2 * t2 * dot, n);
+        dx = inside.select(dx + t3 * (t * gx - 8.0 * dot * x), dx);
+        dy = inside.select(dy + t3 * (t * gy - 8.0 * dot * y), dy);
+    }
+    [70.0 * n, 70.0 * dx, 70.0 * dy]
+}
+
+/// 3D simplex noise in [-1, 1]
+///
+/// Sampling with time as `z` gives a field that morphs in place, where
+/// offsetting a 2D sample by time only scrolls it. Shares the lattice hash
+/// with `simplex_noise_2d`, so `NoiseGenerator` seeds it the same way.
+#[wasm_bindgen]
+pub fn simplex_noise_3d(x: f64, y: f64, z: f64) -> f64 {
+    simplex_3d(x, y, z, &Lattice::Mixed)
+}
+
+/// One `simplex_noise_3d` sample per `[x, y]` pair of `coords`, all at `z`
+#[wasm_bindgen]
+pub fn simplex_noise_3d_batch(coords: &[f64], z: f64) -> Result<Vec<f64>> {
+    simplex_3d_batch(&Lattice::Mixed, coords, z)
+}
+
+fn simplex_3d_batch(lattice: &Lattice, coords: &[f64], z: f64) -> Result<Vec<f64>> {
+    check_coords(coords)?;
+    Ok(map_pairs!(
+        coords,
+        |x, y| simplex_3d(x, y, z, lattice),
+        |x, y| simplex_3d_x4(x, y, Lanes::splat(z), lattice).to_array()
+    ))
+}
+
+/// Copy of `points_flat` with each point displaced by 3D noise
+///
+/// Both offsets sample `simplex_noise_3d` at `(x * frequency, y * frequency,
+/// time)`, the y channel shifted in the domain so the two are
+/// uncorrelated, and scale it by `amplitude`. The input is left alone, so
+/// animating always starts from the same base positions and never drifts.
+/// With `bounds`, displaced points are clamped into it.
+#[wasm_bindgen]
+pub fn animate_points(
+    points_flat: &[f64],
+    time: f64,
+    amplitude: f64,
+    frequency: f64,
+    bounds: Option<Bounds>,
+) -> Result<Vec<f64>> {
+    let mut out = vec![0.0; points_flat.len()];
+    displace(&Lattice::Mixed, points_flat, time, amplitude, frequency, bounds, &mut out)?;
+    Ok(out)
+}
+
+/// `animate_points` into the front of `out`, which must hold at least
+/// `points_flat.len()` values; returns the number written
+///
+/// Reuse one buffer across frames to avoid a new array per call. Errors,
+/// leaving `out` untouched, on the same input as `animate_points` or
+/// when `out` is too short.
+#[wasm_bindgen]
+pub fn animate_points_into(
+    points_flat: &[f64],
+    time: f64,
+    amplitude: f64,
+    frequency: f64,
+    bounds: Option<Bounds>,
+    out: &mut [f64],
+) -> Result<usize> {
+    displace(&Lattice::Mixed, points_flat, time, amplitude, frequency, bounds, out)
+}
+
+/// Domain shift between the x and y displacement channels
+const CHANNEL_SHIFT: (f64, f64) = (31.7, -47.3);
+
+#[allow(clippy::too_many_arguments)]
+fn displace(
+    lattice: &Lattice,
+    points_flat: &[f64],
+    time: f64,
+    amplitude: f64,
+    frequency: f64,
+    bounds: Option<Bounds>,
+    out: &mut [f64],
+) -> Result<usize> {
+    check_coords(points_flat)?;
+    check_out_len(points_flat.len(), out.len())?;
+    if ![time, amplitude, frequency].iter().all(|v| v.is_finite()) {
+        return Err(Error::InvalidArgument(
+            "time, amplitude and frequency must be finite".into(),
+        ));
+    }
+    if let Some(b) = bounds {
+        if !(b.min_x <= b.max_x && b.min_y <= b.max_y) {
+            return Err(Error::InvalidArgument(format!("empty clamp bounds {b:?}")));
+        }
+    }
+    for (c, slot) in points_flat.chunks_exact(2).zip(out.chunks_exact_mut(2)) {
+        let (nx, ny) = (c[0] * frequency, c[1] * frequency);
+        let dx = simplex_3d(nx, ny, time, lattice);
+        let dy = simplex_3d(nx + CHANNEL_SHIFT.0, ny + CHANNEL_SHIFT.1, time, lattice);
+        let (mut x, mut y) = (c[0] + amplitude * dx, c[1] + amplitude * dy);
+        if let Some(b) = bounds {
+            x = x.clamp(b.min_x, b.max_x);
+            y = y.clamp(b.min_y, b.max_y);
+        }
+        slot.copy_from_slice(&[x, y]);
+    }
+    Ok(points_flat.len())
+}
+
+// Skew and unskew factors for 3D
+const F3: f64 = 1.0 / 3.0;
+const G3: f64 = 1.0 / 6.0;
+
+/// Gradients of the 3D lattice, the midpoints of the cube's edges
+const GRADIENTS_3D: [[f64; 3]; 12] = [
+    [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0], [1.0, -1.0, 0.0], [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0], [-1.0, 0.0, 1.0], [1.0, 0.0, -1.0], [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0], [0.0, -1.0, 1.0], [0.0, 1.0, -1.0], [0.0, -1.0, -1.0],
+];
+
+/// Lattice steps to the second and third corners of the tetrahedron that
+/// holds a point at offset `(x0, y0, z0)` from its cell origin
+fn simplex_3d_steps(x0: f64, y0: f64, z0: f64) -> ([i32; 3], [i32; 3]) {
+    // Rank the offsets to pick which of the six tetrahedra holds the point
+    if x0 >= y0 {
+        if y0 >= z0 {
+            ([1, 0, 0], [1, 1, 0])
+        } else if x0 >= z0 {
+            ([1, 0, 0], [1, 0, 1])
+        } else {
+            ([0, 0, 1], [1, 0, 1])
+        }
+    } else if y0 < z0 {
+        ([0, 0, 1], [0, 1, 1])
+    } else if x0 < z0 {
+        ([0, 1, 0], [0, 1, 1])
+    } else {
+        ([0, 1, 0], [1, 1, 0])
+    }
+}
+
+fn simplex_3d(x: f64, y: f64, z: f64, lattice: &Lattice) -> f64 {
+    let s = (x + y + z) * F3;
+    let (i, j, k) = ((x + s).floor(), (y + s).floor(), (z + s).floor());
+    let t = (i + j + k) * G3;
+    let d0 = [x - (i - t), y - (j - t), z - (k - t)];
+    let (o1, o2) = simplex_3d_steps(d0[0], d0[1], d0[2]);
+
+    let (ii, jj, kk) = ((i as i32) & 255, (j as i32) & 255, (k as i32) & 255);
+    let mut n = 0.0;
+    for (step, o) in [[0, 0, 0], o1, o2, [1, 1, 1]].into_iter().enumerate() {
+        let back = step as f64 * G3;
+        let d: [f64; 3] = std::array::from_fn(|a| d0[a] - o[a] as f64 + back);
+        let falloff = 0.6 - d[0] * d[0] - d[1] * d[1] - d[2] * d[2];
+        if falloff > 0.0 {
+            let g = GRADIENTS_3D[lattice.gradient_3d(ii + o[0], jj + o[1], kk + o[2])];
+            n += falloff.powi(4) * (g[0] * d[0] + g[1] * d[1] + g[2] * d[2]);
+        }
+    }
+    // The scale puts the extremes at about ±1; clamp the last rounding
+    (32.0 * n).clamp(-1.0, 1.0)
+}
+
+/// `simplex_3d` for four points at once, bit for bit
+#[cfg(feature = "simd")]
+fn simplex_3d_x4(x: Lanes, y: Lanes, z: Lanes, lattice: &Lattice) -> Lanes {
+    let s = (x + y + z) * F3;
+    let (i, j, k) = ((x + s).floor(), (y + s).floor(), (z + s).floor());
+    let t = (i + j + k) * G3;
+    let d0 = [x - (i - t), y - (j - t), z - (k - t)];
+
+    // Ranking and hashing are integer work on each lane; gather the
+    // corner steps and gradients they pick into lanes
+    let (i, j, k) = (i.to_array(), j.to_array(), k.to_array());
+    let (x0, y0, z0) = (d0[0].to_array(), d0[1].to_array(), d0[2].to_array());
+    let (mut steps, mut grads) = ([[[0.0; 4]; 3]; 4], [[[0.0; 4]; 3]; 4]);
+    for l in 0..4 {
+        let (o1, o2) = simplex_3d_steps(x0[l], y0[l], z0[l]);
+        let (ii, jj, kk) = ((i[l] as i32) & 255, (j[l] as i32) & 255, (k[l] as i32) & 255);
+        let corners = [[0, 0, 0], o1, o2, [1, 1, 1]];
+        for c in 0..4 {
+            let o = corners[c];
+            let g = GRADIENTS_3D[lattice.gradient_3d(ii + o[0], jj + o[1], kk + o[2])];
+            for a in 0..3 {
+                (steps[c][a][l], grads[c][a][l]) = (o[a] as f64, g[a]);
+            }
+        }
+    }
+
+    let mut n = Lanes::splat(0.0);
+    for c in 0..4 {
+        let back = c as f64 * G3;
+        let (o, g) = (&steps[c], &grads[c]);
+        let dx = d0[0] - Lanes::from_array(o[0]) + back;
+        let dy = d0[1] - Lanes::from_array(o[1]) + back;
+        let dz = d0[2] - Lanes::from_array(o[2]) + back;
+        let falloff = 0.6 - dx * dx - dy * dy - dz * dz;
+        let (gx, gy, gz) = (Lanes::from_array(g[0]), Lanes::from_array(g[1]), Lanes::from_array(g[2]));
+        let f2 = falloff * falloff;
+        let contribution = f2 * f2 * (gx * dx + gy * dy + gz * dz);
+        n = falloff.gt(Lanes::splat(0.0)).select(n + contribution, n);
+    }
+    (32.0 * n).clamp(-1.0, 1.0)
+}
+
+fn check_coords(coords: &[f64]) -> Result<()> {
+    if !coords.len().is_multiple_of(2) {
+        return Err(Error::InvalidLength(format!(
+            "coordinate array length {} is odd",
+            coords.len()
+        )));
+    }
+    Ok(())
+}
+
+fn check_params(octaves: u32, lacunarity: f64, gain: f64) -> Result<()> {
+    if octaves == 0 || octaves > MAX_OCTAVES {
+        return Err(Error::InvalidArgument(format!(
+            "octaves must be in 1..={MAX_OCTAVES}, got {octaves}"
+        )));
+    }
+    if !(lacunarity.is_finite() && lacunarity > 0.0) {
+        return Err(Error::InvalidArgument(format!(
+            "lacunarity must be positive, got {lacunarity}"
+        )));
+    }
+    if !(gain.is_finite() && gain > 0.0) {
+        return Err(Error::InvalidArgument(format!("gain must be positive, got {gain}")));
+    }
+    Ok(())
+}
+
+/// Normalized octave sum; parameters already checked
+fn fractal(
+    lattice: &Lattice,
+    x: f64,
+    y: f64,
+    octaves: u32,
+    lacunarity: f64,
+    gain: f64,
+    mode: FractalMode,
+) -> f64 {
+    let (mut frequency, mut amplitude) = (1.0, 1.0);
+    let (mut sum, mut total) = (0.0, 0.0);
+    for i in 0..octaves {
+        let shift = i as f64;
+        let n = simplex_2d(
+            x * frequency + shift * OCTAVE_SHIFT.0,
+            y * frequency + shift * OCTAVE_SHIFT.1,
+            lattice,
+        )
+        .clamp(-1.0, 1.0);
+        // The shaped variants live in [0, 1]; stretch them back to [-1, 1]
+        let shaped = match mode {
+            FractalMode::Fbm => n,
+            FractalMode::Ridged => 1.0 - 2.0 * n.abs(),
+            FractalMode::Turbulence => 2.0 * n.abs() - 1.0,
+        };
+        sum += amplitude * shaped;
+        total += amplitude;
+        frequency *= lacunarity;
+        amplitude *= gain;
+    }
+    sum / total
+}
+
+/// `fractal` for four points at once, bit for bit
+#[cfg(feature = "simd")]
+fn fractal_x4(
+    lattice: &Lattice,
+    x: Lanes,
+    y: Lanes,
+    octaves: u32,
+    lacunarity: f64,
+    gain: f64,
+    mode: FractalMode,
+) -> Lanes {
+    let (mut frequency, mut amplitude) = (1.0, 1.0);
+    let (mut sum, mut total) = (Lanes::splat(0.0), 0.0);
+    for i in 0..octaves {
+        let shift = i as f64;
+        let n = simplex_2d_x4(
+            x * frequency + shift * OCTAVE_SHIFT.0,
+            y * frequency + shift * OCTAVE_SHIFT.1,
+            lattice,
+        )
+        .clamp(-1.0, 1.0);
+        let shaped = match mode {
+            FractalMode::Fbm => n,
+            FractalMode::Ridged => 1.0 - 2.0 * n.abs(),
+            FractalMode::Turbulence => 2.0 * n.abs() - 1.0,
+        };
+        sum += amplitude * shaped;
+        total += amplitude;
+        frequency *= lacunarity;
+        amplitude *= gain;
+    }
+    sum / total
+}
+
+/// `fractal` in `Fbm` mode with its gradient, as `(n, dn/dx, dn/dy)`
+///
+/// Octave `i` drifts by `time` noise units along its own direction, a
+/// golden angle from the last, so the sum morphs over time instead of
+/// scrolling. At `time = 0` the value is `fbm_noise_2d`'s, bar that
+/// function's clamp of each octave. Parameters already checked.
+pub(crate) fn fbm_deriv_2d(
+    x: f64,
+    y: f64,
+    octaves: u32,
+    lacunarity: f64,
+    gain: f64,
+    time: f64,
+) -> (f64, f64, f64) {
+    let (mut frequency, mut amplitude) = (1.0, 1.0);
+    let (mut sum, mut dx, mut dy, mut total) = (0.0, 0.0, 0.0, 0.0);
+    for i in 0..octaves {
+        let (shift, heading) = (i as f64, i as f64 * crate::GOLDEN_ANGLE);
+        let (n, nx, ny) = simplex_2d_deriv(
+            x * frequency + shift * OCTAVE_SHIFT.0 + time * heading.cos(),
+            y * frequency + shift * OCTAVE_SHIFT.1 + time * heading.sin(),
+            &Lattice::Mixed,
+        );
+        sum += amplitude * n;
+        dx += amplitude * frequency * nx;
+        dy += amplitude * frequency * ny;
+        total += amplitude;
+        frequency *= lacunarity;
+        amplitude *= gain;
+    }
+    (sum / total, dx / total, dy / total)
+}
+
+fn check_warp(warp_strength: f64, warp_scale: f64, octaves: u32, levels: u32) -> Result<()> {
+    check_params(octaves, 2.0, 0.5)?;
+    if !warp_strength.is_finite() {
+        return Err(Error::InvalidArgument(format!(
+            "warp strength must be finite, got {warp_strength}"
+        )));
+    }
+    if !(warp_scale.is_finite() && warp_scale > 0.0) {
+        return Err(Error::InvalidArgument(format!(
+            "warp scale must be positive, got {warp_scale}"
+        )));
+    }
+    if levels == 0 || levels > MAX_WARP_LEVELS {
+        return Err(Error::InvalidArgument(format!(
+            "warp levels must be in 1..={MAX_WARP_LEVELS}, got {levels}"
+        )));
+    }
+    Ok(())
+}
+
+/// Warped fBm at `(x, y)` with the offset it was sampled at, as
+/// `(n, dx, dy)`; parameters already checked
+///
+/// Each level reads the warp sums at `(p + offset) × warp_scale` and sets
+/// the offset to `warp_strength` times them, so level one alone is
+/// `n(p + k·w(p))` and level two is `n(p + k·w(p + k·w'(p)))`.
+fn warped(
+    lattice: &Lattice,
+    x: f64,
+    y: f64,
+    warp_strength: f64,
+    warp_scale: f64,
+    octaves: u32,
+    levels: u32,
+) -> (f64, f64, f64) {
+    let fbm = |x: f64, y: f64| fractal(lattice, x, y, octaves, 2.0, 0.5, FractalMode::Fbm);
+    let (mut dx, mut dy) = (0.0, 0.0);
+    for [sx, sy] in &WARP_SHIFT[..levels as usize] {
+        let (wx, wy) = ((x + dx) * warp_scale, (y + dy) * warp_scale);
+        dx = warp_strength * fbm(wx + sx.0, wy + sx.1);
+        dy = warp_strength * fbm(wx + sy.0, wy + sy.1);
+    }
+    (fbm(x + dx, y + dy), dx, dy)
+}
+
+/// `warped` for four points at once, bit for bit, as `[n, dx, dy]`
+#[cfg(feature = "simd")]
+fn warped_x4(
+    lattice: &Lattice,
+    x: Lanes,
+    y: Lanes,
+    warp_strength: f64,
+    warp_scale: f64,
+    octaves: u32,
+    levels: u32,
+) -> [Lanes; 3] {
+    let fbm = |x: Lanes, y: Lanes| fractal_x4(lattice, x, y, octaves, 2.0, 0.5, FractalMode::Fbm);
+    let (mut dx, mut dy) = (Lanes::splat(0.0), Lanes::splat(0.0));
+    for [sx, sy] in &WARP_SHIFT[..levels as usize] {
+        let (wx, wy) = ((x + dx) * warp_scale, (y + dy) * warp_scale);
+        dx = warp_strength * fbm(wx + sx.0, wy + sx.1);
+        dy = warp_strength * fbm(wx + sy.0, wy + sy.1);
+    }
+    [fbm(x + dx, y + dy), dx, dy]
+}
+
+#[allow(clippy::too_many_arguments)]
+fn warped_batch(
+    lattice: &Lattice,
+    coords: &[f64],
+    warp_strength: f64,
+    warp_scale: f64,
+    octaves: u32,
+    levels: u32,
+    offsets_out: &mut [f64],
+) -> Result<Vec<f64>> {
+    check_coords(coords)?;
+    check_warp(warp_strength, warp_scale, octaves, levels)?;
+    let with_offsets = !offsets_out.is_empty();
+    if with_offsets {
+        check_out_len(coords.len(), offsets_out.len())?;
+    }
+    let samples = map_pairs!(
+        coords,
+        |x, y| <[f64; 3]>::from(warped(lattice, x, y, warp_strength, warp_scale, octaves, levels)),
+        |x, y| {
+            let lanes = warped_x4(lattice, x, y, warp_strength, warp_scale, octaves, levels);
+            crate::simd::transpose(lanes)
+        }
+    );
+    if with_offsets {
+        for (slot, [_, dx, dy]) in offsets_out.chunks_exact_mut(2).zip(&samples) {
+            slot.copy_from_slice(&[*dx, *dy]);
+        }
+    }
+    Ok(samples.into_iter().map(|[n, _, _]| n).collect())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn warped_grid(
+    lattice: &Lattice,
+    grid_w: u32,
+    grid_h: u32,
+    width: f64,
+    height: f64,
+    frequency: f64,
+    warp_strength: f64,
+    warp_scale: f64,
+    octaves: u32,
+    levels: u32,
+) -> Result<Vec<f32>> {
+    if grid_w == 0 || grid_h == 0 {
+        return Err(Error::InvalidArgument("grid size must be positive".into()));
+    }
+    if grid_w as u64 * grid_h as u64 > MAX_GRID_SAMPLES {
+        return Err(Error::TooLarge(format!("a {grid_w}x{grid_h} grid is too large")));
+    }
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if !frequency.is_finite() {
+        return Err(Error::InvalidArgument(format!("frequency must be finite, got {frequency}")));
+    }
+    check_warp(warp_strength, warp_scale, octaves, levels)?;
+    let (sx, sy) = (width / grid_w as f64, height / grid_h as f64);
+    let mut out = Vec::with_capacity(grid_w as usize * grid_h as usize);
+    for row in 0..grid_h {
+        for col in 0..grid_w {
+            let (x, y) = ((col as f64 + 0.5) * sx * frequency, (row as f64 + 0.5) * sy * frequency);
+            let (n, _, _) = warped(lattice, x, y, warp_strength, warp_scale, octaves, levels);
+            out.push(n as f32);
+        }
+    }
+    Ok(out)
+}
+
+/// Fractal Brownian motion over `octaves` layers of `simplex_noise_2d`
+///
+/// Typical values are `lacunarity = 2` and `gain = 0.5`. The result is in
+/// [-1, 1].
+#[wasm_bindgen]
+pub fn fbm_noise_2d(x: f64, y: f64, octaves: u32, lacunarity: f64, gain: f64) -> Result<f64> {
+    check_params(octaves, lacunarity, gain)?;
+    Ok(fractal(&Lattice::Mixed, x, y, octaves, lacunarity, gain, FractalMode::Fbm))
+}
+
+/// `fbm_noise_2d` with `1 - |n|` octaves, rescaled to [-1, 1]
+#[wasm_bindgen]
+pub fn ridged_noise_2d(x: f64, y: f64, octaves: u32, lacunarity: f64, gain: f64) -> Result<f64> {
+    check_params(octaves, lacunarity, gain)?;
+    Ok(fractal(&Lattice::Mixed, x, y, octaves, lacunarity, gain, FractalMode::Ridged))
+}
+
+/// `fbm_noise_2d` with `|n|` octaves, rescaled to [-1, 1]
+#[wasm_bindgen]
+pub fn turbulence_noise_2d(
+    x: f64,
+    y: f64,
+    octaves: u32,
+    lacunarity: f64,
+    gain: f64,
+) -> Result<f64> {
+    check_params(octaves, lacunarity, gain)?;
+    let mode = FractalMode::Turbulence;
+    Ok(fractal(&Lattice::Mixed, x, y, octaves, lacunarity, gain, mode))
+}
+
+/// One fractal noise sample per `[x, y]` pair of `coords`
+#[wasm_bindgen]
+pub fn fbm_noise_2d_batch(
+    coords: &[f64],
+    octaves: u32,
+    lacunarity: f64,
+    gain: f64,
+    mode: FractalMode,
+) -> Result<Vec<f64>> {
+    fractal_batch(&Lattice::Mixed, coords, octaves, lacunarity, gain, mode)
+}
+
+fn fractal_batch(
+    lattice: &Lattice,
+    coords: &[f64],
+    octaves: u32,
+    lacunarity: f64,
+    gain: f64,
+    mode: FractalMode,
+) -> Result<Vec<f64>> {
+    check_coords(coords)?;
+    check_params(octaves, lacunarity, gain)?;
+    Ok(map_pairs!(
+        coords,
+        |x, y| fractal(lattice, x, y, octaves, lacunarity, gain, mode),
+        |x, y| fractal_x4(lattice, x, y, octaves, lacunarity, gain, mode).to_array()
+    ))
+}
+
+/// `fbm_noise_2d` sampled through `levels` (1 or 2) layers of domain
+/// warping, in [-1, 1]
+///
+/// The fBm sums use `lacunarity = 2` and `gain = 0.5`. `warp_scale` is the
+/// frequency of the warp field relative to the noise, so below 1 the
+/// folds are broader than the detail, and `warp_strength` is how far it
+/// displaces a sample in noise units; about 1 to 4 gives marble, 0 gives
+/// plain `fbm_noise_2d`.
+#[wasm_bindgen]
+pub fn warped_noise_2d(
+    x: f64,
+    y: f64,
+    warp_strength: f64,
+    warp_scale: f64,
+    octaves: u32,
+    levels: u32,
+) -> Result<f64> {
+    check_warp(warp_strength, warp_scale, octaves, levels)?;
+    Ok(warped(&Lattice::Mixed, x, y, warp_strength, warp_scale, octaves, levels).0)
+}
+
+/// One `warped_noise_2d` sample per `[x, y]` pair of `coords`
+///
+/// A non-empty `offsets_out` must hold `coords.len()` values and receives
+/// the `[dx, dy]` offset each sample was read at, in noise units; adding
+/// them, divided by the frequency, to seed points displaces the geometry
+/// to match the texture. Pass an empty array to skip them.
+#[wasm_bindgen]
+pub fn warped_noise_2d_batch(
+    coords: &[f64],
+    warp_strength: f64,
+    warp_scale: f64,
+    octaves: u32,
+    levels: u32,
+    offsets_out: &mut [f64],
+) -> Result<Vec<f64>> {
+    let lattice = &Lattice::Mixed;
+    warped_batch(lattice, coords, warp_strength, warp_scale, octaves, levels, offsets_out)
+}
+
+/// Row-major `grid_w × grid_h` `warped_noise_2d` samples over a
+/// `width × height` canvas, for upload as a texture
+///
+/// Each is taken at the centre of its texel, scaled by `frequency` into
+/// noise units.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn warped_noise_2d_grid(
+    grid_w: u32,
+    grid_h: u32,
+    width: f64,
+    height: f64,
+    frequency: f64,
+    warp_strength: f64,
+    warp_scale: f64,
+    octaves: u32,
+    levels: u32,
+) -> Result<Vec<f32>> {
+    warped_grid(
+        &Lattice::Mixed,
+        grid_w,
+        grid_h,
+        width,
+        height,
+        frequency,
+        warp_strength,
+        warp_scale,
+        octaves,
+        levels,
+    )
+}
+
+/// Simplex noise over a lattice shuffled from `seed`
+///
+/// Each seed gives its own field, uncorrelated with the others, and the
+/// same field in every release so saved artwork reproduces. Seed 0 is
+/// `simplex_noise_2d` and the free fractal functions.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct NoiseGenerator {
+    seed: u32,
+    lattice: Lattice,
+}
+
+#[wasm_bindgen]
+impl NoiseGenerator {
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: u32) -> NoiseGenerator {
+        NoiseGenerator { seed, lattice: Lattice::from_seed(seed) }
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Serialize the generator; the seed fixes the whole field
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = ByteWriter::new();
+        w.bytes(STATE_MAGIC);
+        w.u8(STATE_VERSION);
+        w.u32(self.seed);
+        w.buf
+    }
+
+    /// Switch to the field saved by `save_state`; errors on truncated,
+    /// corrupt or version-mismatched data and leaves `self` unchanged
+    pub fn restore_state(&mut self, bytes: &[u8]) -> Result<()> {
+        let mut r = ByteReader::new(bytes);
+        let version = r.header(STATE_MAGIC)?;
+        if version != STATE_VERSION {
+            return Err(Error::VersionMismatch(format!(
+                "noise state version {version}, expected {STATE_VERSION}"
+            )));
+        }
+        let seed = r.u32()?;
+        r.finish()?;
+        *self = NoiseGenerator::new(seed);
+        Ok(())
+    }
+
+    /// Single-octave noise in [-1, 1]
+    pub fn noise_2d(&self, x: f64, y: f64) -> f64 {
+        simplex_2d(x, y, &self.lattice)
+    }
+
+    /// `simplex_noise_2d_deriv` over this generator's lattice
+    pub fn noise_2d_deriv(&self, x: f64, y: f64) -> Vec<f64> {
+        let (n, dx, dy) = simplex_2d_deriv(x, y, &self.lattice);
+        vec![n, dx, dy]
+    }
+
+    /// `simplex_noise_2d_deriv_batch` over this generator's lattice
+    pub fn noise_2d_deriv_batch(&self, coords: &[f64]) -> Result<Vec<f64>> {
+        simplex_2d_deriv_batch(&self.lattice, coords)
+    }
+
+    /// `simplex_noise_3d` over this generator's lattice
+    pub fn noise_3d(&self, x: f64, y: f64, z: f64) -> f64 {
+        simplex_3d(x, y, z, &self.lattice)
+    }
+
+    /// `simplex_noise_3d_batch` over this generator's lattice
+    pub fn noise_3d_batch(&self, coords: &[f64], z: f64) -> Result<Vec<f64>> {
+        simplex_3d_batch(&self.lattice, coords, z)
+    }
+
+    /// `animate_points` over this generator's lattice
+    pub fn animate_points(
+        &self,
+        points_flat: &[f64],
+        time: f64,
+        amplitude: f64,
+        frequency: f64,
+        bounds: Option<Bounds>,
+    ) -> Result<Vec<f64>> {
+        let mut out = vec![0.0; points_flat.len()];
+        displace(&self.lattice, points_flat, time, amplitude, frequency, bounds, &mut out)?;
+        Ok(out)
+    }
+
+    /// `animate_points_into` over this generator's lattice
+    pub fn animate_points_into(
+        &self,
+        points_flat: &[f64],
+        time: f64,
+        amplitude: f64,
+        frequency: f64,
+        bounds: Option<Bounds>,
+        out: &mut [f64],
+    ) -> Result<usize> {
+        displace(&self.lattice, points_flat, time, amplitude, frequency, bounds, out)
+    }
+
+    /// `fbm_noise_2d` and its variants over this generator's lattice
+    pub fn fractal_2d(
+        &self,
+        x: f64,
+        y: f64,
+        octaves: u32,
+        lacunarity: f64,
+        gain: f64,
+        mode: FractalMode,
+    ) -> Result<f64> {
+        check_params(octaves, lacunarity, gain)?;
+        Ok(fractal(&self.lattice, x, y, octaves, lacunarity, gain, mode))
+    }
+
+    /// `fbm_noise_2d_batch` over this generator's lattice
+    pub fn fractal_2d_batch(
+        &self,
+        coords: &[f64],
+        octaves: u32,
+        lacunarity: f64,
+        gain: f64,
+        mode: FractalMode,
+    ) -> Result<Vec<f64>> {
+        fractal_batch(&self.lattice, coords, octaves, lacunarity, gain, mode)
+    }
+
+    /// `warped_noise_2d` over this generator's lattice
+    pub fn warped_2d(
+        &self,
+        x: f64,
+        y: f64,
+        warp_strength: f64,
+        warp_scale: f64,
+        octaves: u32,
+        levels: u32,
+    ) -> Result<f64> {
+        check_warp(warp_strength, warp_scale, octaves, levels)?;
+        Ok(warped(&self.lattice, x, y, warp_strength, warp_scale, octaves, levels).0)
+    }
+
+    /// `warped_noise_2d_batch` over this generator's lattice
+    pub fn warped_2d_batch(
+        &self,
+        coords: &[f64],
+        warp_strength: f64,
+        warp_scale: f64,
+        octaves: u32,
+        levels: u32,
+        offsets_out: &mut [f64],
+    ) -> Result<Vec<f64>> {
+        let lattice = &self.lattice;
+        warped_batch(lattice, coords, warp_strength, warp_scale, octaves, levels, offsets_out)
+    }
+
+    /// `warped_noise_2d_grid` over this generator's lattice
+    #[allow(clippy::too_many_arguments)]
+    pub fn warped_2d_grid(
+        &self,
+        grid_w: u32,
+        grid_h: u32,
+        width: f64,
+        height: f64,
+        frequency: f64,
+        warp_strength: f64,
+        warp_scale: f64,
+        octaves: u32,
+        levels: u32,
+    ) -> Result<Vec<f32>> {
+        warped_grid(
+            &self.lattice,
+            grid_w,
+            grid_h,
+            width,
+            height,
+            frequency,
+            warp_strength,
+            warp_scale,
+            octaves,
+            levels,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    fn grid() -> Vec<f64> {
+        (0..4096).flat_map(|i| [(i % 64) as f64 * 0.173, (i / 64) as f64 * 0.191]).collect()
+    }
+
+    #[test]
+    fn test_octaves_stay_normalized_and_add_detail() {
+        let coords = grid();
+        let one = fbm_noise_2d_batch(&coords, 1, 2.0, 0.5, FractalMode::Fbm).unwrap();
+        let single: Vec<f64> = coords.chunks(2).map(|c| simplex_noise_2d(c[0], c[1])).collect();
+        assert_eq!(one, single);
+        for octaves in [2, 4, 8, MAX_OCTAVES] {
+            for mode in [FractalMode::Fbm, FractalMode::Ridged, FractalMode::Turbulence] {
+                let v = fbm_noise_2d_batch(&coords, octaves, 2.0, 0.8, mode).unwrap();
+                assert!(v.iter().all(|n| (-1.0..=1.0).contains(n)), "{mode:?} x{octaves}");
+            }
+        }
+        // Extra octaves add detail: neighbouring samples differ more
+        let roughness = |v: &[f64]| v.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f64>();
+        let line: Vec<f64> = (0..2000).flat_map(|i| [i as f64 * 0.005, 0.37]).collect();
+        let smooth = fbm_noise_2d_batch(&line, 1, 2.0, 0.5, FractalMode::Fbm).unwrap();
+        let detailed = fbm_noise_2d_batch(&line, 4, 2.0, 0.5, FractalMode::Fbm).unwrap();
+        assert!(roughness(&detailed) > 1.2 * roughness(&smooth));
+        let (x, y) = (coords[10], coords[11]);
+        let ridged = fbm_noise_2d_batch(&coords[10..12], 3, 2.0, 0.5, FractalMode::Ridged);
+        assert_eq!(ridged.unwrap()[0], ridged_noise_2d(x, y, 3, 2.0, 0.5).unwrap());
+        let turbulent = turbulence_noise_2d(x, y, 1, 2.0, 0.5).unwrap();
+        assert_eq!(turbulent, 2.0 * simplex_noise_2d(x, y).abs() - 1.0);
+    }
+
+    #[test]
+    fn test_bad_parameters_are_rejected() {
+        assert_eq!(code_of(fbm_noise_2d(0.0, 0.0, 0, 2.0, 0.5)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(fbm_noise_2d(0.0, 0.0, 25, 2.0, 0.5)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(fbm_noise_2d(0.0, 0.0, 4, 0.0, 0.5)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(fbm_noise_2d(0.0, 0.0, 4, 2.0, f64::NAN)), ErrorCode::InvalidArgument);
+        let odd = fbm_noise_2d_batch(&[0.0; 3], 4, 2.0, 0.5, FractalMode::Fbm);
+        assert_eq!(code_of(odd), ErrorCode::InvalidLength);
+    }
+
+    #[test]
+    fn test_seeds_give_distinct_stable_fields() {
+        // Spread out so most samples fall in different lattice cells
+        let coords: Vec<f64> = grid().iter().map(|v| v * 4.0).collect();
+        let field = |seed| -> Vec<f64> {
+            let g = NoiseGenerator::new(seed);
+            coords.chunks(2).map(|c| g.noise_2d(c[0], c[1])).collect()
+        };
+        let single: Vec<f64> = coords.chunks(2).map(|c| simplex_noise_2d(c[0], c[1])).collect();
+        assert_eq!(field(0), single);
+        let g = NoiseGenerator::new(0);
+        let fbm = g.fractal_2d_batch(&coords, 3, 2.0, 0.5, FractalMode::Fbm).unwrap();
+        assert_eq!(fbm, fbm_noise_2d_batch(&coords, 3, 2.0, 0.5, FractalMode::Fbm).unwrap());
+
+        let correlation = |a: &[f64], b: &[f64]| {
+            let dot = |u: &[f64], v: &[f64]| u.iter().zip(v).map(|(p, q)| p * q).sum::<f64>();
+            dot(a, b) / (dot(a, a) * dot(b, b)).sqrt()
+        };
+        let fields: Vec<Vec<f64>> = (0..4).map(field).collect();
+        for a in 0..4 {
+            assert!(fields[a].iter().all(|n| (-1.0..=1.0).contains(n)));
+            for b in a + 1..4 {
+                let r = correlation(&fields[a], &fields[b]);
+                assert!(r.abs() < 0.1, "seeds {a} and {b}: {r}");
+            }
+        }
+        assert_eq!(field(2), fields[2]);
+        // Pinned: these change only if the shuffle or the PRNG does
+        let g = NoiseGenerator::new(42);
+        let pinned = [
+            (0.3, 0.7, 0.4367672850457515),
+            (-5.1, 12.4, 0.8560353973180497),
+            (100.25, 3.5, -0.4359628143300858),
+        ];
+        for (x, y, n) in pinned {
+            assert!((g.noise_2d(x, y) - n).abs() < 1e-12, "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn test_2d_derivative_matches_finite_differences() {
+        let h = 1e-6;
+        let coords: Vec<f64> = grid().iter().map(|v| v * 1.7 + 0.123).collect();
+        let batch = simplex_noise_2d_deriv_batch(&coords).unwrap();
+        assert_eq!(batch.len(), coords.len() / 2 * 3);
+        for seed in [0, 5] {
+            let g = NoiseGenerator::new(seed);
+            let seeded = g.noise_2d_deriv_batch(&coords).unwrap();
+            for (c, d) in coords.chunks(2).zip(seeded.chunks(3)) {
+                let (x, y) = (c[0], c[1]);
+                assert_eq!(d, g.noise_2d_deriv(x, y));
+                assert_eq!(d[0], g.noise_2d(x, y));
+                let fx = (g.noise_2d(x + h, y) - g.noise_2d(x - h, y)) / (2.0 * h);
+                let fy = (g.noise_2d(x, y + h) - g.noise_2d(x, y - h)) / (2.0 * h);
+                assert!((d[1] - fx).abs() < 1e-4 && (d[2] - fy).abs() < 1e-4, "({x}, {y})");
+            }
+            if seed == 0 {
+                assert_eq!(seeded, batch);
+            }
+        }
+        let (x, y) = (coords[6], coords[7]);
+        assert_eq!(simplex_noise_2d_deriv(x, y), batch[9..12]);
+        assert_eq!(simplex_noise_2d_gradient(x, y), batch[10..12]);
+        assert_eq!(code_of(simplex_noise_2d_deriv_batch(&[0.0])), ErrorCode::InvalidLength);
+    }
+
+    #[test]
+    fn test_3d_noise_is_bounded_smooth_and_batched() {
+        let coords: Vec<f64> = grid().iter().map(|v| v * 2.0).collect();
+        let mut extreme: f64 = 0.0;
+        for z in [0.0, 0.37, -4.2, 19.9] {
+            let batch = simplex_noise_3d_batch(&coords, z).unwrap();
+            for (c, &n) in coords.chunks(2).zip(&batch) {
+                assert_eq!(n, simplex_noise_3d(c[0], c[1], z));
+                // A small step in time moves the field only a little
+                assert!((simplex_noise_3d(c[0], c[1], z + 1e-3) - n).abs() < 0.02);
+                extreme = extreme.max(n.abs());
+            }
+        }
+        assert!(extreme > 0.5 && extreme <= 1.0, "{extreme}");
+        let g = NoiseGenerator::new(0);
+        let unseeded = simplex_noise_3d_batch(&coords, 0.5).unwrap();
+        assert_eq!(g.noise_3d_batch(&coords, 0.5).unwrap(), unseeded);
+        let seeded = NoiseGenerator::new(7).noise_3d_batch(&coords, 0.5).unwrap();
+        assert_ne!(seeded, unseeded);
+        assert!(seeded.iter().all(|n| (-1.0..=1.0).contains(n)));
+        assert_eq!(code_of(simplex_noise_3d_batch(&[0.0], 0.0)), ErrorCode::InvalidLength);
+    }
+
+    /// The batches take four samples at a time with the `simd` feature;
+    /// each must still match its single sample bit for bit
+    #[test]
+    fn test_batches_match_single_samples_bit_for_bit() {
+        let mut coords = grid();
+        // Lattice points, simplex edges, negative and huge inputs, and an
+        // odd pair count so the last group is partial
+        coords.extend([
+            0.0, 0.0, 1.0, 1.0, -3.0, 2.0, 0.3, 0.3, -0.0, 7.25, 1e9, -3e7, 3e10, 0.5, -12.6,
+            -0.4, 2.0, -2.0,
+        ]);
+        let bits = |v: &[f64]| v.iter().map(|v| v.to_bits()).collect::<Vec<u64>>();
+        for lattice in [Lattice::Mixed, Lattice::from_seed(7)] {
+            let one: Vec<f64> = coords
+                .chunks(2)
+                .flat_map(|c| <[f64; 3]>::from(simplex_2d_deriv(c[0], c[1], &lattice)))
+                .collect();
+            assert_eq!(bits(&simplex_2d_deriv_batch(&lattice, &coords).unwrap()), bits(&one));
+            for z in [0.0, 0.3, -4.2, 19.9] {
+                let one: Vec<f64> =
+                    coords.chunks(2).map(|c| simplex_3d(c[0], c[1], z, &lattice)).collect();
+                assert_eq!(bits(&simplex_3d_batch(&lattice, &coords, z).unwrap()), bits(&one));
+            }
+            for mode in [FractalMode::Fbm, FractalMode::Ridged, FractalMode::Turbulence] {
+                let batch = fractal_batch(&lattice, &coords, 5, 2.1, 0.45, mode).unwrap();
+                let one: Vec<f64> = coords
+                    .chunks(2)
+                    .map(|c| fractal(&lattice, c[0], c[1], 5, 2.1, 0.45, mode))
+                    .collect();
+                assert_eq!(bits(&batch), bits(&one));
+            }
+            let mut offsets = vec![0.0; coords.len()];
+            let values = warped_batch(&lattice, &coords, 2.5, 0.7, 3, 2, &mut offsets).unwrap();
+            for ((c, &n), o) in coords.chunks(2).zip(&values).zip(offsets.chunks(2)) {
+                let (wn, dx, dy) = warped(&lattice, c[0], c[1], 2.5, 0.7, 3, 2);
+                assert_eq!(bits(&[n, o[0], o[1]]), bits(&[wn, dx, dy]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_animate_points_displaces_a_copy() {
+        let base = [10.0, 20.0, 300.0, 150.0, 0.0, 0.0, 640.0, 480.0];
+        let moved = animate_points(&base, 1.5, 8.0, 0.01, None).unwrap();
+        for (p, q) in base.chunks(2).zip(moved.chunks(2)) {
+            let (nx, ny) = (p[0] * 0.01, p[1] * 0.01);
+            assert_eq!(q[0], p[0] + 8.0 * simplex_noise_3d(nx, ny, 1.5));
+            assert!((q[1] - p[1]).abs() <= 8.0);
+        }
+        assert_ne!(moved, base);
+        assert_eq!(animate_points(&base, 1.5, 0.0, 0.01, None).unwrap(), base);
+
+        // Corner points pushed outward are held on the canvas
+        let canvas = Some(Bounds::new(0.0, 0.0, 640.0, 480.0));
+        for t in 0..50 {
+            let clamped = animate_points(&base, t as f64 * 0.3, 40.0, 0.01, canvas).unwrap();
+            for q in clamped.chunks(2) {
+                assert!((0.0..=640.0).contains(&q[0]) && (0.0..=480.0).contains(&q[1]));
+            }
+        }
+        let seeded = NoiseGenerator::new(3).animate_points(&base, 1.5, 8.0, 0.01, None);
+        assert_ne!(seeded.unwrap(), moved);
+
+        let code = |p: &[f64], t: f64, b| code_of(animate_points(p, t, 1.0, 0.01, b));
+        assert_eq!(code(&base[..3], 0.0, None), ErrorCode::InvalidLength);
+        assert_eq!(code(&base, f64::NAN, None), ErrorCode::InvalidArgument);
+        let inverted = Some(Bounds::new(10.0, 0.0, 0.0, 10.0));
+        assert_eq!(code(&base, 0.0, inverted), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_animate_points_into_reuses_the_buffer() {
+        let base = [10.0, 20.0, 300.0, 150.0, 0.0, 0.0];
+        let mut out = [f64::NAN; 8];
+        assert_eq!(animate_points_into(&base, 0.7, 5.0, 0.02, None, &mut out).unwrap(), 6);
+        assert_eq!(&out[..6], &animate_points(&base, 0.7, 5.0, 0.02, None).unwrap()[..]);
+        assert!(out[6].is_nan() && out[7].is_nan());
+        let generator = NoiseGenerator::new(9);
+        generator.animate_points_into(&base, 0.7, 5.0, 0.02, None, &mut out).unwrap();
+        let expected = generator.animate_points(&base, 0.7, 5.0, 0.02, None).unwrap();
+        assert_eq!(&out[..6], &expected[..]);
+
+        let mut short = [0.0; 5];
+        let result = animate_points_into(&base, 0.7, 5.0, 0.02, None, &mut short);
+        assert_eq!(code_of(result), ErrorCode::InvalidLength);
+        assert_eq!(short, [0.0; 5]);
+        let result = animate_points_into(&base, f64::NAN, 5.0, 0.02, None, &mut out);
+        assert_eq!(code_of(result), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_warped_noise_reads_fbm_at_its_offsets() {
+        let coords: Vec<f64> = grid().iter().map(|v| v * 0.5).collect();
+        let mut offsets = vec![0.0; coords.len()];
+        for levels in [1, MAX_WARP_LEVELS] {
+            let warped = warped_noise_2d_batch(&coords, 2.5, 0.6, 4, levels, &mut offsets).unwrap();
+            assert!(warped.iter().all(|n| (-1.0..=1.0).contains(n)));
+            for ((c, d), &n) in coords.chunks(2).zip(offsets.chunks(2)).zip(&warped) {
+                assert_eq!(n, warped_noise_2d(c[0], c[1], 2.5, 0.6, 4, levels).unwrap());
+                assert_eq!(n, fbm_noise_2d(c[0] + d[0], c[1] + d[1], 4, 2.0, 0.5).unwrap());
+                assert!(d[0].abs() <= 2.5 && d[1].abs() <= 2.5);
+            }
+            let plain = fbm_noise_2d_batch(&coords, 4, 2.0, 0.5, FractalMode::Fbm).unwrap();
+            assert_ne!(warped, plain);
+            let unwarped = warped_noise_2d_batch(&coords, 0.0, 0.6, 4, levels, &mut []).unwrap();
+            assert_eq!(unwarped, plain);
+        }
+        // The second level warps the first level's warp field
+        let one = warped_noise_2d_batch(&coords, 2.5, 0.6, 4, 1, &mut []).unwrap();
+        assert_ne!(one, warped_noise_2d_batch(&coords, 2.5, 0.6, 4, 2, &mut []).unwrap());
+        let (x, y) = (coords[20], coords[21]);
+        let first = warped_noise_2d(x, y, 2.5, 0.6, 4, 1).unwrap();
+        let warp = |(sx, sy): (f64, f64)| {
+            2.5 * fbm_noise_2d(x * 0.6 + sx, y * 0.6 + sy, 4, 2.0, 0.5).unwrap()
+        };
+        let (dx, dy) = (warp(WARP_SHIFT[0][0]), warp(WARP_SHIFT[0][1]));
+        assert_eq!(first, fbm_noise_2d(x + dx, y + dy, 4, 2.0, 0.5).unwrap());
+    }
+
+    #[test]
+    fn test_warped_grid_and_seeded_variants() {
+        let grid = warped_noise_2d_grid(8, 6, 400.0, 300.0, 0.01, 2.0, 0.5, 3, 2).unwrap();
+        assert_eq!(grid.len(), 48);
+        let centres: Vec<f64> = (0..48)
+            .flat_map(|i| [(i % 8) as f64 + 0.5, (i / 8) as f64 + 0.5].map(|t| t * 50.0 * 0.01))
+            .collect();
+        let batch = warped_noise_2d_batch(&centres, 2.0, 0.5, 3, 2, &mut []).unwrap();
+        assert_eq!(grid, crate::buffers::to_f32(&batch));
+
+        let g = NoiseGenerator::new(0);
+        assert_eq!(g.warped_2d_grid(8, 6, 400.0, 300.0, 0.01, 2.0, 0.5, 3, 2).unwrap(), grid);
+        assert_eq!(g.warped_2d_batch(&centres, 2.0, 0.5, 3, 2, &mut []).unwrap(), batch);
+        let seeded = NoiseGenerator::new(11);
+        let other = seeded.warped_2d_batch(&centres, 2.0, 0.5, 3, 2, &mut []).unwrap();
+        assert_ne!(other, batch);
+        let (x, y) = (centres[8], centres[9]);
+        assert_eq!(seeded.warped_2d(x, y, 2.0, 0.5, 3, 2).unwrap(), other[4]);
+
+        let code = |strength, scale, octaves, levels| {
+            code_of(warped_noise_2d(0.0, 0.0, strength, scale, octaves, levels))
+        };
+        assert_eq!(code(f64::NAN, 1.0, 4, 1), ErrorCode::InvalidArgument);
+        assert_eq!(code(1.0, 0.0, 4, 1), ErrorCode::InvalidArgument);
+        assert_eq!(code(1.0, 1.0, 0, 1), ErrorCode::InvalidArgument);
+        assert_eq!(code(1.0, 1.0, 4, 0), ErrorCode::InvalidArgument);
+        assert_eq!(code(1.0, 1.0, 4, MAX_WARP_LEVELS + 1), ErrorCode::InvalidArgument);
+        let short = warped_noise_2d_batch(&centres, 2.0, 0.5, 3, 2, &mut [0.0; 3]);
+        assert_eq!(code_of(short), ErrorCode::InvalidLength);
+        let empty = warped_noise_2d_grid(0, 6, 400.0, 300.0, 0.01, 2.0, 0.5, 3, 2);
+        assert_eq!(code_of(empty), ErrorCode::InvalidArgument);
+        let huge = warped_noise_2d_grid(1 << 14, 1 << 14, 400.0, 300.0, 0.01, 2.0, 0.5, 3, 2);
+        assert_eq!(code_of(huge), ErrorCode::TooLarge);
+    }
+
+    #[test]
+    fn test_generator_state_round_trips() {
+        let g = NoiseGenerator::new(4242);
+        let mut restored = NoiseGenerator::new(0);
+        restored.restore_state(&g.save_state()).unwrap();
+        assert_eq!(restored.seed(), 4242);
+        assert_eq!(restored.noise_3d(1.3, -0.7, 2.1), g.noise_3d(1.3, -0.7, 2.1));
+
+        let bytes = g.save_state();
+        let mut version = bytes.clone();
+        version[4] = STATE_VERSION + 1;
+        assert_eq!(code_of(restored.restore_state(&version)), ErrorCode::VersionMismatch);
+        assert_eq!(code_of(restored.restore_state(&bytes[..6])), ErrorCode::InvalidData);
+        let trailing = [bytes, vec![0]].concat();
+        assert_eq!(code_of(restored.restore_state(&trailing)), ErrorCode::InvalidData);
+        assert_eq!(code_of(restored.restore_state(b"VGMS\x01")), ErrorCode::InvalidData);
+    }
+}
@@ -0,0 +1,567 @@
+//! Option objects for the exports with long positional argument lists
+//!
+//! `SeedConfig`, `NoiseConfig` and `MoteConfig` start from defaults and are
+//! adjusted through `with_*` builders that check their value on the spot,
+//! so a bad option fails where it is set rather than at first use, and JS
+//! names each one instead of counting positions:
+//!
+//! ```js
+//! const config = new SeedConfig().with_width(1920).with_height(1080).with_count(500);
+//! const seeds = generate_golden_seeds_with_config(config);
+//! ```
+//!
+//! Each builder consumes the object it is called on and returns the
+//! updated one. With the `sim` feature, `from_json` takes the same options
+//! as a JSON object, `JSON.stringify({ count: 500, jitter: 0.2 })`, with
+//! omitted fields left at their defaults and unknown ones rejected.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+#[cfg(feature = "motes")]
+use crate::motes::MoteStyle;
+#[cfg(feature = "noise")]
+use crate::noise::{FractalMode, NoiseGenerator, MAX_OCTAVES};
+use crate::rng::Rng;
+use crate::seeds::{generate_golden_seeds_ex, FitMode};
+
+/// Seeds `SeedConfig` will lay out; a negative count passed from JS wraps
+/// to a huge one and is caught here
+const MAX_CONFIG_SEEDS: usize = 1 << 24;
+
+#[cfg(feature = "sim")]
+fn from_json<T: serde::de::DeserializeOwned>(what: &str, json: &str) -> Result<T> {
+    serde_json::from_str(json).map_err(|e| Error::InvalidData(format!("{what} config: {e}")))
+}
+
+fn check_positive(name: &str, v: f64) -> Result<()> {
+    if !(v.is_finite() && v > 0.0) {
+        return Err(Error::InvalidArgument(format!("{name} must be positive, got {v}")));
+    }
+    Ok(())
+}
+
+/// Options for `generate_golden_seeds_with_config`
+///
+/// Defaults to 500 seeds on an 800 × 600 canvas, fitted with
+/// `FitMode::Contain`, without jitter: `generate_golden_seeds(800, 600,
+/// 500)`.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeedConfig {
+    width: f64,
+    height: f64,
+    count: usize,
+    fit_mode: FitMode,
+    jitter: f64,
+    rng_seed: u32,
+}
+
+impl Default for SeedConfig {
+    fn default() -> SeedConfig {
+        SeedConfig {
+            width: 800.0,
+            height: 600.0,
+            count: 500,
+            fit_mode: FitMode::Contain,
+            jitter: 0.0,
+            rng_seed: 0,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl SeedConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SeedConfig {
+        SeedConfig::default()
+    }
+
+    /// Options from a JSON object with any of `width`, `height`, `count`,
+    /// `fit_mode` (`"contain"`, `"cover"` or `"stretch"`), `jitter` and
+    /// `rng_seed`
+    #[cfg(feature = "sim")]
+    pub fn from_json(json: &str) -> Result<SeedConfig> {
+        #[derive(serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Fields {
+            width: Option<f64>,
+            height: Option<f64>,
+            count: Option<usize>,
+            fit_mode: Option<String>,
+            jitter: Option<f64>,
+            rng_seed: Option<u32>,
+        }
+        let f: Fields = from_json("seed", json)?;
+        let mut config = SeedConfig::default();
+        if let Some(v) = f.width {
+            config = config.with_width(v)?;
+        }
+        if let Some(v) = f.height {
+            config = config.with_height(v)?;
+        }
+        if let Some(v) = f.count {
+            config = config.with_count(v)?;
+        }
+        if let Some(v) = f.fit_mode {
+            let mode = match v.as_str() {
+                "contain" => FitMode::Contain,
+                "cover" => FitMode::Cover,
+                "stretch" => FitMode::Stretch,
+                _ => {
+                    return Err(Error::InvalidData(format!(
+                        "seed config: fit_mode {v:?} is not contain, cover or stretch"
+                    )))
+                }
+            };
+            config = config.with_fit_mode(mode);
+        }
+        if let Some(v) = f.jitter {
+            config = config.with_jitter(v)?;
+        }
+        if let Some(v) = f.rng_seed {
+            config = config.with_rng_seed(v);
+        }
+        Ok(config)
+    }
+
+    pub fn with_width(mut self, width: f64) -> Result<SeedConfig> {
+        check_positive("width", width)?;
+        self.width = width;
+        Ok(self)
+    }
+
+    pub fn with_height(mut self, height: f64) -> Result<SeedConfig> {
+        check_positive("height", height)?;
+        self.height = height;
+        Ok(self)
+    }
+
+    pub fn with_count(mut self, count: usize) -> Result<SeedConfig> {
+        if count > MAX_CONFIG_SEEDS {
+            return Err(Error::TooLarge(format!(
+                "seed count {count} is over {MAX_CONFIG_SEEDS}; was it negative?"
+            )));
+        }
+        self.count = count;
+        Ok(self)
+    }
+
+    pub fn with_fit_mode(mut self, fit_mode: FitMode) -> SeedConfig {
+        self.fit_mode = fit_mode;
+        self
+    }
+
+    /// Random offset of each seed along each axis, up to `jitter / 2` of
+    /// the mean spacing; in [0, 1], where 1 breaks up the spiral's rings
+    /// and can carry a seed halfway to its neighbours
+    pub fn with_jitter(mut self, jitter: f64) -> Result<SeedConfig> {
+        if !(0.0..=1.0).contains(&jitter) {
+            return Err(Error::InvalidArgument(format!("jitter must be in [0, 1], got {jitter}")));
+        }
+        self.jitter = jitter;
+        Ok(self)
+    }
+
+    /// Seed for the jitter's random stream
+    pub fn with_rng_seed(mut self, rng_seed: u32) -> SeedConfig {
+        self.rng_seed = rng_seed;
+        self
+    }
+
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    pub fn height(&self) -> f64 {
+        self.height
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn fit_mode(&self) -> FitMode {
+        self.fit_mode
+    }
+
+    pub fn jitter(&self) -> f64 {
+        self.jitter
+    }
+
+    pub fn rng_seed(&self) -> u32 {
+        self.rng_seed
+    }
+}
+
+/// `generate_golden_seeds_ex` with its options named, and optional jitter
+///
+/// Jittered seeds are clamped to the canvas. Errors only as
+/// `generate_golden_seeds_ex` does for a `Cover` fit needing too many
+/// candidates.
+#[wasm_bindgen]
+pub fn generate_golden_seeds_with_config(config: &SeedConfig) -> Result<Vec<f64>> {
+    let SeedConfig { width, height, count, fit_mode, jitter, rng_seed } = *config;
+    let mut seeds = generate_golden_seeds_ex(width, height, count, fit_mode)?;
+    if jitter > 0.0 && count > 0 {
+        let radius = width.min(height) / 2.0 * 0.85;
+        let area = match fit_mode {
+            FitMode::Contain => std::f64::consts::PI * radius * radius,
+            FitMode::Stretch => std::f64::consts::PI * width * height / 4.0 * 0.85 * 0.85,
+            FitMode::Cover => width * height,
+        };
+        let reach = jitter * (area / count as f64).sqrt();
+        let mut rng = Rng::new(rng_seed as u64);
+        for p in seeds.chunks_exact_mut(2) {
+            let (dx, dy) = (rng.range(-0.5, 0.5), rng.range(-0.5, 0.5));
+            p[0] = (p[0] + reach * dx).clamp(0.0, width);
+            p[1] = (p[1] + reach * dy).clamp(0.0, height);
+        }
+    }
+    Ok(seeds)
+}
+
+/// Options for `fbm_noise_2d_batch_with_config`
+///
+/// Defaults to 4 octaves of `FractalMode::Fbm` with lacunarity 2 and gain
+/// 0.5 over lattice seed 0, the field of `fbm_noise_2d`.
+#[cfg(feature = "noise")]
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseConfig {
+    seed: u32,
+    octaves: u32,
+    lacunarity: f64,
+    gain: f64,
+    mode: FractalMode,
+}
+
+#[cfg(feature = "noise")]
+impl Default for NoiseConfig {
+    fn default() -> NoiseConfig {
+        NoiseConfig { seed: 0, octaves: 4, lacunarity: 2.0, gain: 0.5, mode: FractalMode::Fbm }
+    }
+}
+
+#[cfg(feature = "noise")]
+#[wasm_bindgen]
+impl NoiseConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> NoiseConfig {
+        NoiseConfig::default()
+    }
+
+    /// Options from a JSON object with any of `seed`, `octaves`,
+    /// `lacunarity`, `gain` and `mode` (`"fbm"`, `"ridged"` or
+    /// `"turbulence"`)
+    #[cfg(feature = "sim")]
+    pub fn from_json(json: &str) -> Result<NoiseConfig> {
+        #[derive(serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Fields {
+            seed: Option<u32>,
+            octaves: Option<u32>,
+            lacunarity: Option<f64>,
+            gain: Option<f64>,
+            mode: Option<String>,
+        }
+        let f: Fields = from_json("noise", json)?;
+        let mut config = NoiseConfig::default();
+        if let Some(v) = f.seed {
+            config = config.with_seed(v);
+        }
+        if let Some(v) = f.octaves {
+            config = config.with_octaves(v)?;
+        }
+        if let Some(v) = f.lacunarity {
+            config = config.with_lacunarity(v)?;
+        }
+        if let Some(v) = f.gain {
+            config = config.with_gain(v)?;
+        }
+        if let Some(v) = f.mode {
+            let mode = match v.as_str() {
+                "fbm" => FractalMode::Fbm,
+                "ridged" => FractalMode::Ridged,
+                "turbulence" => FractalMode::Turbulence,
+                _ => {
+                    return Err(Error::InvalidData(format!(
+                        "noise config: mode {v:?} is not fbm, ridged or turbulence"
+                    )))
+                }
+            };
+            config = config.with_mode(mode);
+        }
+        Ok(config)
+    }
+
+    /// Lattice seed, as for `NoiseGenerator::new`
+    pub fn with_seed(mut self, seed: u32) -> NoiseConfig {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_octaves(mut self, octaves: u32) -> Result<NoiseConfig> {
+        if octaves == 0 || octaves > MAX_OCTAVES {
+            return Err(Error::InvalidArgument(format!(
+                "octaves must be in 1..={MAX_OCTAVES}, got {octaves}"
+            )));
+        }
+        self.octaves = octaves;
+        Ok(self)
+    }
+
+    pub fn with_lacunarity(mut self, lacunarity: f64) -> Result<NoiseConfig> {
+        check_positive("lacunarity", lacunarity)?;
+        self.lacunarity = lacunarity;
+        Ok(self)
+    }
+
+    pub fn with_gain(mut self, gain: f64) -> Result<NoiseConfig> {
+        check_positive("gain", gain)?;
+        self.gain = gain;
+        Ok(self)
+    }
+
+    pub fn with_mode(mut self, mode: FractalMode) -> NoiseConfig {
+        self.mode = mode;
+        self
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn octaves(&self) -> u32 {
+        self.octaves
+    }
+
+    pub fn lacunarity(&self) -> f64 {
+        self.lacunarity
+    }
+
+    pub fn gain(&self) -> f64 {
+        self.gain
+    }
+
+    pub fn mode(&self) -> FractalMode {
+        self.mode
+    }
+}
+
+/// `fbm_noise_2d_batch`, or `NoiseGenerator::fractal_2d_batch` for a
+/// nonzero seed, with its options named
+#[cfg(feature = "noise")]
+#[wasm_bindgen]
+pub fn fbm_noise_2d_batch_with_config(coords: &[f64], config: &NoiseConfig) -> Result<Vec<f64>> {
+    let NoiseConfig { seed, octaves, lacunarity, gain, mode } = *config;
+    NoiseGenerator::new(seed).fractal_2d_batch(coords, octaves, lacunarity, gain, mode)
+}
+
+/// Options for `update_motes_with_config`
+///
+/// Defaults to `MoteStyle`'s default pulse and the stride-3 output of
+/// `update_motes`.
+#[cfg(feature = "motes")]
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MoteConfig {
+    style: MoteStyle,
+    extended: bool,
+}
+
+#[cfg(feature = "motes")]
+#[wasm_bindgen]
+impl MoteConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> MoteConfig {
+        MoteConfig::default()
+    }
+
+    /// Options from a JSON object with any of `pulse_frequency`,
+    /// `phase_spread`, `min_brightness`, `max_brightness`, `min_size`,
+    /// `max_size` and `extended`; ranges are checked once all are read
+    #[cfg(feature = "sim")]
+    pub fn from_json(json: &str) -> Result<MoteConfig> {
+        #[derive(serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Fields {
+            pulse_frequency: Option<f64>,
+            phase_spread: Option<f64>,
+            min_brightness: Option<f64>,
+            max_brightness: Option<f64>,
+            min_size: Option<f64>,
+            max_size: Option<f64>,
+            extended: Option<bool>,
+        }
+        let f: Fields = from_json("mote", json)?;
+        let s = MoteStyle::default();
+        let style = MoteStyle {
+            pulse_frequency: f.pulse_frequency.unwrap_or(s.pulse_frequency),
+            phase_spread: f.phase_spread.unwrap_or(s.phase_spread),
+            min_brightness: f.min_brightness.unwrap_or(s.min_brightness),
+            max_brightness: f.max_brightness.unwrap_or(s.max_brightness),
+            min_size: f.min_size.unwrap_or(s.min_size),
+            max_size: f.max_size.unwrap_or(s.max_size),
+        };
+        style.validate()?;
+        Ok(MoteConfig { style, extended: f.extended.unwrap_or(false) })
+    }
+
+    /// Pulse rate in radians per unit of `time`, and the phase step from
+    /// one mote to the next
+    pub fn with_pulse(mut self, frequency: f64, phase_spread: f64) -> Result<MoteConfig> {
+        let style = MoteStyle { pulse_frequency: frequency, phase_spread, ..self.style };
+        style.validate()?;
+        self.style = style;
+        Ok(self)
+    }
+
+    pub fn with_brightness(mut self, min: f64, max: f64) -> Result<MoteConfig> {
+        let style = MoteStyle { min_brightness: min, max_brightness: max, ..self.style };
+        style.validate()?;
+        self.style = style;
+        Ok(self)
+    }
+
+    pub fn with_size(mut self, min: f64, max: f64) -> Result<MoteConfig> {
+        let style = MoteStyle { min_size: min, max_size: max, ..self.style };
+        style.validate()?;
+        self.style = style;
+        Ok(self)
+    }
+
+    /// Whether to output `update_motes_ex`'s stride-5 `[x, y, brightness,
+    /// size, edgeIdx]` instead of stride-3 `[x, y, brightness]`
+    pub fn with_extended(mut self, extended: bool) -> MoteConfig {
+        self.extended = extended;
+        self
+    }
+
+    pub fn style(&self) -> MoteStyle {
+        self.style
+    }
+
+    pub fn extended(&self) -> bool {
+        self.extended
+    }
+}
+
+/// `update_motes`, or `update_motes_ex` when `config` is extended, with
+/// the pulse taken from `config`
+#[cfg(feature = "motes")]
+#[wasm_bindgen]
+pub fn update_motes_with_config(
+    motes: &mut [f64],
+    edges: &[f64],
+    dt: f64,
+    time: f64,
+    config: &MoteConfig,
+) -> Result<Vec<f64>> {
+    crate::check_motes(motes.len(), edges.len(), dt, time)?;
+    Ok(crate::advance_motes(motes, edges, dt, time, &config.style, config.extended))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::generate_golden_seeds;
+
+    #[test]
+    fn test_seed_config() {
+        let config = SeedConfig::new();
+        let plain = generate_golden_seeds_with_config(&config).unwrap();
+        assert_eq!(plain, generate_golden_seeds(800.0, 600.0, 500));
+
+        let config = config.with_width(1920.0).unwrap().with_height(1080.0).unwrap();
+        let config = config.with_count(300).unwrap().with_fit_mode(FitMode::Cover);
+        let (w, h) = (config.width(), config.height());
+        assert_eq!((w, h, config.count()), (1920.0, 1080.0, 300));
+        let cover = generate_golden_seeds_with_config(&config).unwrap();
+        assert_eq!(cover, generate_golden_seeds_ex(w, h, 300, FitMode::Cover).unwrap());
+        let jittered = config.clone().with_jitter(1.0).unwrap().with_rng_seed(3);
+        let moved = generate_golden_seeds_with_config(&jittered).unwrap();
+        assert_eq!(moved.len(), cover.len());
+        assert_ne!(moved, cover);
+        // Each seed stays within half the mean spacing of its spiral spot
+        let reach = (w * h / 300.0).sqrt() / 2.0;
+        for (p, q) in cover.chunks(2).zip(moved.chunks(2)) {
+            assert!((p[0] - q[0]).abs() <= reach && (p[1] - q[1]).abs() <= reach);
+            assert!((0.0..=w).contains(&q[0]) && (0.0..=h).contains(&q[1]));
+        }
+        assert_eq!(generate_golden_seeds_with_config(&jittered).unwrap(), moved);
+
+        let bad = |r: Result<SeedConfig>| code_of(r);
+        assert_eq!(bad(SeedConfig::new().with_width(-1.0)), ErrorCode::InvalidArgument);
+        assert_eq!(bad(SeedConfig::new().with_height(f64::NAN)), ErrorCode::InvalidArgument);
+        assert_eq!(bad(SeedConfig::new().with_jitter(1.5)), ErrorCode::InvalidArgument);
+        assert_eq!(bad(SeedConfig::new().with_count(usize::MAX)), ErrorCode::TooLarge);
+    }
+
+    #[cfg(all(feature = "noise", feature = "motes"))]
+    #[test]
+    fn test_noise_and_mote_configs() {
+        let coords: Vec<f64> = (0..50).map(|k| k as f64 * 0.37).collect();
+        let config = NoiseConfig::new();
+        let fbm = fbm_noise_2d_batch_with_config(&coords, &config).unwrap();
+        assert_eq!(fbm, crate::fbm_noise_2d_batch(&coords, 4, 2.0, 0.5, FractalMode::Fbm).unwrap());
+        let config = config.with_seed(9).with_octaves(6).unwrap().with_mode(FractalMode::Ridged);
+        let seeded = fbm_noise_2d_batch_with_config(&coords, &config).unwrap();
+        let generator = NoiseGenerator::new(9);
+        let want = generator.fractal_2d_batch(&coords, 6, 2.0, 0.5, FractalMode::Ridged);
+        assert_eq!(seeded, want.unwrap());
+        assert_eq!(code_of(NoiseConfig::new().with_octaves(0)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(NoiseConfig::new().with_gain(-0.5)), ErrorCode::InvalidArgument);
+        let odd = fbm_noise_2d_batch_with_config(&coords[..3], &NoiseConfig::new());
+        assert_eq!(code_of(odd), ErrorCode::InvalidLength);
+
+        let edges = [0.0, 0.0, 10.0, 0.0, 10.0, 0.0, 10.0, 10.0];
+        let start = [0.0, 0.25, 0.1, 1.0, 0.5, 0.2];
+        let (mut a, mut b) = (start, start);
+        let plain = update_motes_with_config(&mut a, &edges, 1.0, 0.5, &MoteConfig::new());
+        assert_eq!(plain.unwrap(), crate::update_motes(&mut b, &edges, 1.0, 0.5).unwrap());
+        assert_eq!(a, b);
+        let config = MoteConfig::new().with_size(2.0, 6.0).unwrap().with_extended(true);
+        let config = config.with_pulse(3.0, 1.0).unwrap().with_brightness(0.2, 0.8).unwrap();
+        let style = MoteStyle::new(3.0, 1.0, 0.2, 0.8, 2.0, 6.0);
+        assert_eq!(config.style(), style);
+        let (mut a, mut b) = (start, start);
+        let extended = update_motes_with_config(&mut a, &edges, 1.0, 0.5, &config).unwrap();
+        assert_eq!(extended, crate::update_motes_ex(&mut b, &edges, 1.0, 0.5, &style).unwrap());
+        let reversed = MoteConfig::new().with_brightness(1.0, 0.0);
+        assert_eq!(code_of(reversed), ErrorCode::InvalidArgument);
+        let nan = MoteConfig::new().with_pulse(f64::NAN, 0.0);
+        assert_eq!(code_of(nan), ErrorCode::InvalidArgument);
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn test_configs_from_json() {
+        let seeds = SeedConfig::from_json(r#"{ "count": 200, "jitter": 0.2 }"#).unwrap();
+        let want = SeedConfig::new().with_count(200).unwrap().with_jitter(0.2).unwrap();
+        assert_eq!(seeds, want);
+        let json = r#"{ "width": 400, "height": 300, "fit_mode": "stretch", "rng_seed": 5 }"#;
+        let seeds = SeedConfig::from_json(json).unwrap();
+        let fields = (seeds.width(), seeds.fit_mode(), seeds.rng_seed());
+        assert_eq!(fields, (400.0, FitMode::Stretch, 5));
+        let noise = NoiseConfig::from_json(r#"{ "octaves": 3, "mode": "turbulence" }"#).unwrap();
+        let want = NoiseConfig::new().with_octaves(3).unwrap();
+        assert_eq!(noise, want.with_mode(FractalMode::Turbulence));
+        let motes = MoteConfig::from_json(r#"{ "max_size": 4, "extended": true }"#).unwrap();
+        assert_eq!((motes.style().max_size, motes.extended()), (4.0, true));
+        assert_eq!(MoteConfig::from_json("{}").unwrap(), MoteConfig::new());
+
+        // A negative count, a typo, an unknown mode and broken JSON
+        let bad = [r#"{ "count": -5 }"#, r#"{ "jiter": 0.2 }"#, r#"{ "fit_mode": "fill" }"#, "["];
+        for json in bad {
+            assert_eq!(code_of(SeedConfig::from_json(json)), ErrorCode::InvalidData, "{json}");
+        }
+        assert_eq!(code_of(SeedConfig::from_json(r#"{ "width": 0 }"#)), ErrorCode::InvalidArgument);
+        let deep = NoiseConfig::from_json(r#"{ "octaves": 99 }"#);
+        assert_eq!(code_of(deep), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(NoiseConfig::from_json(r#"{ "mode": "fbn" }"#)), ErrorCode::InvalidData);
+        let reversed = MoteConfig::from_json(r#"{ "min_size": 3 }"#);
+        assert_eq!(code_of(reversed), ErrorCode::InvalidArgument);
+    }
+}
@@ -0,0 +1,366 @@
+//! Outlines of labelled regions of Voronoi cells
+//!
+//! Cells are clipped to the canvas as in `compute_voronoi`, but each cell
+//! edge remembers what cut it: the Delaunay neighbour across it, or one of
+//! the canvas sides. An edge is part of an outline when the cell across it
+//! has another label, or when it lies on the canvas, so every region
+//! closes along the canvas rectangle. Outlines are traced by turning
+//! around each cell vertex through the cells of the same label until the
+//! next outline edge, which follows the topology rather than comparing
+//! coordinates; a region therefore keeps the winding of its cells, its
+//! outer boundary counter-clockwise and any holes clockwise.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles, unique_edges, Csr};
+use crate::predicates::signed_area2;
+use crate::Point;
+
+/// Edge tags from here up are the canvas sides, in the order bottom
+/// (`y = 0`), right, top, left; below it they are seed indices
+const SIDE: u32 = u32::MAX - 3;
+
+/// Region outlines from `cluster_outlines`
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ClusterOutlines {
+    vertices: Vec<f64>,
+    offsets: Vec<u32>,
+    labels: Vec<u32>,
+    closed: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl ClusterOutlines {
+    /// Polyline vertices `[x0, y0, x1, y1, ...]`, path after path
+    pub fn vertices(&self) -> Vec<f64> {
+        self.vertices.clone()
+    }
+
+    /// One entry per path plus one, in vertex units
+    pub fn offsets(&self) -> Vec<u32> {
+        self.offsets.clone()
+    }
+
+    /// Label of the region each path bounds
+    pub fn labels(&self) -> Vec<u32> {
+        self.labels.clone()
+    }
+
+    /// 1 for each path that is a closed loop, 0 for one the trace could
+    /// not close, which only degenerate cells produce
+    pub fn closed(&self) -> Vec<u8> {
+        self.closed.clone()
+    }
+
+    pub fn path_count(&self) -> usize {
+        self.labels.len()
+    }
+}
+
+/// Clip a convex tagged polygon to `(p - origin) · normal <= 0`, tagging
+/// the new edge along the cut with `tag`
+///
+/// Each vertex carries the tag of the edge that starts at it.
+fn clip_tagged(
+    poly: &[(Point, u32)],
+    origin: Point,
+    normal: Point,
+    tag: u32,
+    out: &mut Vec<(Point, u32)>,
+) {
+    out.clear();
+    let side = |p: Point| (p.x - origin.x) * normal.x + (p.y - origin.y) * normal.y;
+    for (i, &(cur, cur_tag)) in poly.iter().enumerate() {
+        let (prev, prev_tag) = poly[(i + poly.len() - 1) % poly.len()];
+        let (sp, sc) = (side(prev), side(cur));
+        if (sp <= 0.0) != (sc <= 0.0) {
+            let t = sp / (sp - sc);
+            let at = Point {
+                x: prev.x + (cur.x - prev.x) * t,
+                y: prev.y + (cur.y - prev.y) * t,
+            };
+            // Leaving, the polygon continues along the cut; entering, along
+            // the rest of the edge it came in by
+            out.push((at, if sp <= 0.0 { tag } else { prev_tag }));
+        }
+        if sc <= 0.0 {
+            out.push((cur, cur_tag));
+        }
+    }
+}
+
+/// Tagged cells of every seed, flat, with the cell of each vertex
+struct Cells {
+    ring: Vec<(Point, u32)>,
+    offsets: Vec<u32>,
+    owner: Vec<u32>,
+}
+
+impl Cells {
+    fn build(points: &[Point], csr: &Csr, width: f64, height: f64) -> Cells {
+        let rect = [
+            (Point { x: 0.0, y: 0.0 }, SIDE),
+            (Point { x: width, y: 0.0 }, SIDE + 1),
+            (Point { x: width, y: height }, SIDE + 2),
+            (Point { x: 0.0, y: height }, SIDE + 3),
+        ];
+        let mut cells = Cells { ring: Vec::new(), offsets: vec![0], owner: Vec::new() };
+        let (mut poly, mut scratch) = (Vec::new(), Vec::new());
+        for (i, &s) in points.iter().enumerate() {
+            poly.clear();
+            // A seed no triangle uses, such as a duplicate, has no cell
+            if !csr.neighbors(i as u32).is_empty() {
+                poly.extend_from_slice(&rect);
+                for &j in csr.neighbors(i as u32) {
+                    let q = points[j as usize];
+                    let mid = Point { x: (s.x + q.x) * 0.5, y: (s.y + q.y) * 0.5 };
+                    let normal = Point { x: q.x - s.x, y: q.y - s.y };
+                    clip_tagged(&poly, mid, normal, j, &mut scratch);
+                    std::mem::swap(&mut poly, &mut scratch);
+                    if poly.is_empty() {
+                        break;
+                    }
+                }
+                // A repeated point starts a zero-length edge; drop it
+                let mut k = 0;
+                while poly.len() > 1 && k < poly.len() {
+                    if poly[k].0 == poly[(k + 1) % poly.len()].0 {
+                        poly.remove(k);
+                    } else {
+                        k += 1;
+                    }
+                }
+            }
+            let shape: Vec<Point> = poly.iter().map(|&(p, _)| p).collect();
+            if shape.len() >= 3 && signed_area2(&shape) > 0.0 {
+                cells.ring.extend_from_slice(&poly);
+                cells.owner.extend(std::iter::repeat_n(i as u32, poly.len()));
+            }
+            cells.offsets.push(cells.ring.len() as u32);
+        }
+        cells
+    }
+
+    fn range(&self, cell: u32) -> std::ops::Range<usize> {
+        self.offsets[cell as usize] as usize..self.offsets[cell as usize + 1] as usize
+    }
+
+    /// The edge after `e` around its cell
+    fn next(&self, e: usize) -> usize {
+        let range = self.range(self.owner[e]);
+        if e + 1 == range.end { range.start } else { e + 1 }
+    }
+
+    /// The edge of `cell` that starts where `e`, an edge of the cell's
+    /// neighbour, ends going round the vertex: the one after `cell`'s
+    /// copy of `e`, or failing that the one starting nearest `e`'s start
+    fn across(&self, e: usize, cell: u32) -> Option<usize> {
+        let range = self.range(cell);
+        if let Some(twin) = range.clone().find(|&k| self.ring[k].1 == self.owner[e]) {
+            return Some(self.next(twin));
+        }
+        let v = self.ring[e].0;
+        let gap = |k: usize| (self.ring[k].0.x - v.x).hypot(self.ring[k].0.y - v.y);
+        range.min_by(|&a, &b| gap(a).total_cmp(&gap(b)))
+    }
+}
+
+/// Outlines of the regions of equal label among the Voronoi cells of
+/// `points_flat` on a `width × height` canvas, with the Delaunay
+/// `triangles` as neighbours
+///
+/// `labels` has one entry per seed, such as `label_cell_clusters`
+/// returns; seeds labelled 0 are background and get no outline, though
+/// they still separate the regions around them. Paths come in order of
+/// the smallest seed they run along. Closed paths do not repeat their
+/// first vertex, and vertices where two cells of a region meet on the
+/// same canvas side are left out.
+#[wasm_bindgen]
+pub fn cluster_outlines(
+    points_flat: &[f64],
+    triangles: &[u32],
+    labels: &[u32],
+    width: f64,
+    height: f64,
+) -> Result<ClusterOutlines> {
+    let n = check_points(points_flat)?;
+    check_triangles(triangles, n)?;
+    if labels.len() != n {
+        return Err(Error::InvalidLength(format!(
+            "labels need one entry per seed ({n}), got {}",
+            labels.len()
+        )));
+    }
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    let points: Vec<Point> = points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+    let csr = Csr::from_edges(n, &unique_edges(triangles));
+    let cells = Cells::build(&points, &csr, width, height);
+    let tag = |e: usize| cells.ring[e].1;
+    let label_of = |e: usize| labels[cells.owner[e] as usize];
+    let outline = |e: usize| tag(e) >= SIDE || labels[tag(e) as usize] != label_of(e);
+
+    let mut out = ClusterOutlines {
+        vertices: Vec::new(),
+        offsets: vec![0],
+        labels: Vec::new(),
+        closed: Vec::new(),
+    };
+    let mut used = vec![false; cells.ring.len()];
+    for start in 0..cells.ring.len() {
+        let label = label_of(start);
+        if label == 0 || used[start] || !outline(start) {
+            continue;
+        }
+        let (mut chain, mut closed) = (vec![start], false);
+        used[start] = true;
+        let mut e = start;
+        'trace: loop {
+            // Turn round the end of `e` through cells of this label; a
+            // vertex touches fewer cells than there are seeds
+            let mut next = cells.next(e);
+            for _ in 0..=n {
+                if outline(next) {
+                    if next == start {
+                        closed = true;
+                    } else if !used[next] {
+                        used[next] = true;
+                        chain.push(next);
+                        e = next;
+                        continue 'trace;
+                    }
+                    break 'trace;
+                }
+                match cells.across(next, tag(next)) {
+                    Some(k) => next = k,
+                    None => break 'trace,
+                }
+            }
+            break;
+        }
+        for (k, &e) in chain.iter().enumerate() {
+            let prev = match k {
+                0 if closed => chain.last().copied(),
+                0 => None,
+                _ => Some(chain[k - 1]),
+            };
+            if prev.is_some_and(|p| tag(p) == tag(e) && tag(e) >= SIDE) {
+                continue;
+            }
+            out.vertices.extend([cells.ring[e].0.x, cells.ring[e].0.y]);
+        }
+        if !closed {
+            let end = cells.ring[cells.next(e)].0;
+            out.vertices.extend([end.x, end.y]);
+        }
+        out.offsets.push((out.vertices.len() / 2) as u32);
+        out.labels.push(label);
+        out.closed.push(closed as u8);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::{compute_delaunay, compute_voronoi, generate_golden_seeds};
+
+    /// Signed area of each path, counter-clockwise positive
+    fn path_areas(outlines: &ClusterOutlines) -> Vec<f64> {
+        let (vertices, offsets) = (outlines.vertices(), outlines.offsets());
+        offsets
+            .windows(2)
+            .map(|w| {
+                let ring = &vertices[w[0] as usize * 2..w[1] as usize * 2];
+                let shape: Vec<Point> = ring.chunks(2).map(|c| Point::new(c[0], c[1])).collect();
+                signed_area2(&shape) / 2.0
+            })
+            .collect()
+    }
+
+    /// Total area of the cells labelled `label`
+    fn cell_area(points: &[f64], labels: &[u32], label: u32) -> f64 {
+        let cells = compute_voronoi(points, 400.0, 300.0).unwrap();
+        (0..labels.len())
+            .filter(|&i| labels[i] == label)
+            .map(|i| {
+                let cell = cells.cell(i).unwrap();
+                let shape: Vec<Point> = cell.chunks(2).map(|c| Point::new(c[0], c[1])).collect();
+                signed_area2(&shape) / 2.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_outlines_enclose_their_cells() {
+        let points = generate_golden_seeds(400.0, 300.0, 300);
+        let triangles = compute_delaunay(&points, 400.0, 300.0);
+        // A disc of label 2 inside label 1, and seeds on the right as 0
+        let labels: Vec<u32> = points
+            .chunks(2)
+            .map(|p| match p[0] {
+                x if x > 330.0 => 0,
+                x if (x - 180.0).hypot(p[1] - 150.0) < 60.0 => 2,
+                _ => 1,
+            })
+            .collect();
+        let outlines = cluster_outlines(&points, &triangles, &labels, 400.0, 300.0).unwrap();
+        assert!(outlines.closed().iter().all(|&c| c == 1));
+        assert!(!outlines.labels().contains(&0));
+        let areas = path_areas(&outlines);
+        for label in [1, 2] {
+            let traced: f64 = (0..outlines.path_count())
+                .filter(|&k| outlines.labels()[k] == label)
+                .map(|k| areas[k])
+                .sum();
+            let want = cell_area(&points, &labels, label);
+            assert!((traced - want).abs() < 1e-6 * want, "{label}: {traced} vs {want}");
+        }
+        // The disc is one loop and a hole in label 1's
+        let of = |label: u32| -> Vec<f64> {
+            (0..outlines.path_count())
+                .filter(|&k| outlines.labels()[k] == label)
+                .map(|k| areas[k])
+                .collect()
+        };
+        assert_eq!(of(2).len(), 1);
+        assert!(of(1).iter().any(|&a| a < 0.0));
+        assert!(of(1).iter().any(|&a| a > 0.0));
+
+        // One label over everything is the canvas rectangle
+        let whole = cluster_outlines(&points, &triangles, &[7; 300], 400.0, 300.0).unwrap();
+        assert_eq!(whole.labels(), [7]);
+        let mut corners: Vec<[u64; 2]> = whole
+            .vertices()
+            .chunks(2)
+            .map(|c| [c[0].to_bits(), c[1].to_bits()])
+            .collect();
+        corners.sort();
+        let want = [[0.0, 0.0], [0.0, 300.0], [400.0, 0.0], [400.0, 300.0]];
+        assert_eq!(corners, want.map(|c: [f64; 2]| c.map(f64::to_bits)));
+    }
+
+    #[test]
+    fn test_outline_errors() {
+        let points = [10.0, 10.0, 50.0, 10.0, 30.0, 40.0];
+        let triangles = [0, 1, 2];
+        let run = |p: &[f64], t: &[u32], l: &[u32], w: f64| cluster_outlines(p, t, l, w, 50.0);
+        let two = run(&points, &triangles, &[1, 1, 3], 60.0).unwrap();
+        assert_eq!(two.labels(), [1, 3]);
+        assert_eq!(two.offsets().len(), 3);
+        let odd = run(&points[..5], &triangles, &[1, 1, 1], 60.0);
+        assert_eq!(code_of(odd), ErrorCode::InvalidLength);
+        assert_eq!(code_of(run(&points, &[0, 1, 3], &[1, 1, 1], 60.0)), ErrorCode::IndexOutOfRange);
+        assert_eq!(code_of(run(&points, &triangles, &[1, 1], 60.0)), ErrorCode::InvalidLength);
+        let nan = [f64::NAN, 10.0, 50.0, 10.0, 30.0, 40.0];
+        assert_eq!(code_of(run(&nan, &triangles, &[1, 1, 1], 60.0)), ErrorCode::NonFiniteInput);
+        assert_eq!(code_of(run(&points, &triangles, &[1, 1, 1], 0.0)), ErrorCode::InvalidArgument);
+    }
+}
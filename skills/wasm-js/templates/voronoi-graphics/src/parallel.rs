@@ -0,0 +1,385 @@
+//! Striped Delaunay construction on a rayon thread pool
+//!
+//! With the `parallel` feature, `Triangulation::new` splits a large point
+//! set into vertical stripes of equal count and triangulates each stripe
+//! on its own thread. A stripe triangle whose circumcircle stays inside
+//! the stripe's x range holds no other stripe's points, so it is already
+//! a triangle of the whole set. What is left, the seams between stripes,
+//! is redone on the vertices there alone, keeping the triangles whose
+//! circumcircle is empty of every point; a uniform grid over the points
+//! answers those scans in parallel.
+//!
+//! The assembled mesh is then checked to be a strictly Delaunay
+//! triangulation of the convex hull. That triangulation is unique, so it
+//! is the one the serial build makes, with the triangles in another
+//! order. Anything that fails the check, such as cocircular or duplicate
+//! points, is built serially instead.
+//!
+//! On the web, rayon runs on `wasm-bindgen-rayon` workers: the page must
+//! be cross-origin isolated and await `initThreadPool(n)` before the first
+//! triangulation.
+
+use std::collections::HashSet;
+
+use rayon::prelude::*;
+
+use crate::graph::boundary_edges;
+use crate::mesh::{Mesh, NONE};
+use crate::predicates::{incircle, orient2d};
+use crate::Point;
+
+#[cfg(target_arch = "wasm32")]
+pub use wasm_bindgen_rayon::init_thread_pool;
+
+/// Point sets smaller than this are triangulated serially
+const PARALLEL_MIN_POINTS: usize = 20_000;
+/// Fewest points a stripe is given
+const MIN_STRIPE_POINTS: usize = 5_000;
+
+/// Triangles of the Delaunay triangulation of `points`, CCW and in no
+/// particular order, or `None` when the serial build should be used
+pub(crate) fn triangulate(points: &[Point]) -> Option<Vec<[u32; 3]>> {
+    if points.len() < PARALLEL_MIN_POINTS {
+        return None;
+    }
+    let stripes = rayon::current_num_threads().min(points.len() / MIN_STRIPE_POINTS);
+    if stripes < 2 {
+        return None;
+    }
+    triangulate_striped(points, stripes)
+}
+
+/// `triangulate` with an explicit stripe count, at least 2
+fn triangulate_striped(points: &[Point], stripes: usize) -> Option<Vec<[u32; 3]>> {
+    let n = points.len();
+    let mut order: Vec<u32> = (0..n as u32).collect();
+    order.par_sort_unstable_by(|&a, &b| {
+        let (p, q) = (points[a as usize], points[b as usize]);
+        p.x.total_cmp(&q.x).then(p.y.total_cmp(&q.y)).then(a.cmp(&b))
+    });
+    let bounds: Vec<usize> = (0..=stripes).map(|k| k * n / stripes).collect();
+    let built = (0..stripes)
+        .into_par_iter()
+        .map(|k| {
+            let (lo, hi) = (bounds[k], bounds[k + 1]);
+            // Nearest x of the neighbouring stripes' points
+            let left = if k == 0 { f64::NEG_INFINITY } else { points[order[lo - 1] as usize].x };
+            let right = if hi == n { f64::INFINITY } else { points[order[hi] as usize].x };
+            stripe(points, &order[lo..hi], left, right)
+        })
+        .collect::<Option<Vec<Stripe>>>()?;
+
+    let mut in_seam = vec![false; n];
+    let mut finished = Vec::new();
+    for s in built {
+        s.seam.iter().for_each(|&v| in_seam[v as usize] = true);
+        finished.extend(s.finished);
+    }
+    let seam: Vec<u32> = (0..n as u32).filter(|&v| in_seam[v as usize]).collect();
+    let seam_points: Vec<Point> = seam.iter().map(|&v| points[v as usize]).collect();
+    let mesh = Mesh::build_closed(&seam_points);
+    if !seam_points.iter().all(|&p| mesh.encloses(p)) {
+        return None;
+    }
+    let known: HashSet<[u32; 3]> = finished.par_iter().map(|&t| key(t)).collect();
+    let grid = PointGrid::new(points);
+    let seam_triangles: Vec<[u32; 3]> =
+        mesh.real_triangles().map(|t| t.map(|v| seam[v as usize])).collect();
+    let kept: Vec<[u32; 3]> = seam_triangles
+        .into_par_iter()
+        .filter(|&t| !known.contains(&key(t)) && grid.circle_is_empty(points, t))
+        .collect();
+    finished.extend(kept);
+    is_delaunay_of_hull(points, &finished).then_some(finished)
+}
+
+/// One stripe's finished triangles and the vertices left to the seams
+struct Stripe {
+    finished: Vec<[u32; 3]>,
+    seam: Vec<u32>,
+}
+
+/// Triangulate the points `ids`, all with `left <= x <= right`
+///
+/// Triangles whose circumcircle reaches past either bound go to the
+/// seams, as do the stripe's hull vertices, which border triangles only
+/// the whole set can settle.
+fn stripe(points: &[Point], ids: &[u32], left: f64, right: f64) -> Option<Stripe> {
+    let local: Vec<Point> = ids.iter().map(|&i| points[i as usize]).collect();
+    let mesh = Mesh::build_closed(&local);
+    if !local.iter().all(|&p| mesh.encloses(p)) {
+        return None;
+    }
+    let triangles: Vec<u32> = mesh.real_triangles().flatten().collect();
+    let mut finished = Vec::with_capacity(triangles.len() / 3);
+    let mut seam: Vec<u32> = boundary_edges(&triangles)
+        .into_iter()
+        .flat_map(|(a, b)| [ids[a as usize], ids[b as usize]])
+        .collect();
+    for t in triangles.chunks_exact(3) {
+        let global = [t[0], t[1], t[2]].map(|v| ids[v as usize]);
+        let [a, b, c] = global.map(|v| points[v as usize]);
+        match circumcircle(a, b, c) {
+            Some((center, r)) if center.x - r > left && center.x + r < right => {
+                finished.push(global)
+            }
+            _ => seam.extend(global),
+        }
+    }
+    Some(Stripe { finished, seam })
+}
+
+/// Circumcentre and radius of `abc`, grown to cover rounding in the
+/// centre; `None` for a degenerate triangle
+///
+/// A circle misjudged even so only costs the parallel path, which the
+/// final check then rejects.
+fn circumcircle(a: Point, b: Point, c: Point) -> Option<(Point, f64)> {
+    let (bx, by) = (b.x - a.x, b.y - a.y);
+    let (cx, cy) = (c.x - a.x, c.y - a.y);
+    let d = 2.0 * (bx * cy - by * cx);
+    let (b2, c2) = (bx * bx + by * by, cx * cx + cy * cy);
+    let (ux, uy) = ((cy * b2 - by * c2) / d, (bx * c2 - cx * b2) / d);
+    let center = Point::new(a.x + ux, a.y + uy);
+    let r = ux.hypot(uy);
+    let slack = 1e-6 * r + 1e-9 * (center.x.abs() + center.y.abs());
+    (r.is_finite() && center.x.is_finite() && center.y.is_finite()).then_some((center, r + slack))
+}
+
+/// Corners of `t` in ascending order
+fn key(t: [u32; 3]) -> [u32; 3] {
+    let mut k = t;
+    k.sort_unstable();
+    k
+}
+
+/// Points bucketed into square cells, about two per cell
+struct PointGrid {
+    min: Point,
+    inv_cell: f64,
+    cols: usize,
+    rows: usize,
+    /// Where each cell's points start in `ids`, with a final entry for
+    /// the end
+    start: Vec<u32>,
+    ids: Vec<u32>,
+}
+
+impl PointGrid {
+    fn new(points: &[Point]) -> PointGrid {
+        let (mut min, mut max) = (points[0], points[0]);
+        for p in points {
+            (min.x, min.y) = (min.x.min(p.x), min.y.min(p.y));
+            (max.x, max.y) = (max.x.max(p.x), max.y.max(p.y));
+        }
+        let w = (max.x - min.x).max(f64::MIN_POSITIVE);
+        let h = (max.y - min.y).max(f64::MIN_POSITIVE);
+        let cell = (2.0 * w * h / points.len() as f64).sqrt().max(w.max(h) / 4096.0);
+        let inv_cell = 1.0 / cell;
+        let cols = ((w * inv_cell) as usize + 1).min(4096);
+        let rows = ((h * inv_cell) as usize + 1).min(4096);
+        let mut grid = PointGrid { min, inv_cell, cols, rows, start: Vec::new(), ids: Vec::new() };
+        let cells: Vec<usize> = points.iter().map(|&p| grid.cell(p)).collect();
+        grid.start = vec![0; cols * rows + 1];
+        for &c in &cells {
+            grid.start[c + 1] += 1;
+        }
+        for k in 1..grid.start.len() {
+            grid.start[k] += grid.start[k - 1];
+        }
+        let mut fill = grid.start.clone();
+        grid.ids = vec![0; points.len()];
+        for (i, &c) in cells.iter().enumerate() {
+            grid.ids[fill[c] as usize] = i as u32;
+            fill[c] += 1;
+        }
+        grid
+    }
+
+    fn column(&self, x: f64) -> usize {
+        (((x - self.min.x) * self.inv_cell).max(0.0) as usize).min(self.cols - 1)
+    }
+
+    fn row(&self, y: f64) -> usize {
+        (((y - self.min.y) * self.inv_cell).max(0.0) as usize).min(self.rows - 1)
+    }
+
+    fn cell(&self, p: Point) -> usize {
+        self.row(p.y) * self.cols + self.column(p.x)
+    }
+
+    fn bucket(&self, cell: usize) -> &[u32] {
+        &self.ids[self.start[cell] as usize..self.start[cell + 1] as usize]
+    }
+
+    /// Whether no point lies strictly inside the circumcircle of CCW `t`
+    ///
+    /// The cell holding the centre goes first: a circle that is not
+    /// empty nearly always has a point there.
+    fn circle_is_empty(&self, points: &[Point], t: [u32; 3]) -> bool {
+        let [a, b, c] = t.map(|v| points[v as usize]);
+        let Some((center, r)) = circumcircle(a, b, c) else { return false };
+        let inside = |cell: usize| {
+            self.bucket(cell).iter().any(|&q| incircle(a, b, c, points[q as usize]) > 0.0)
+        };
+        let first = self.cell(center);
+        if inside(first) {
+            return false;
+        }
+        let (c0, c1) = (self.column(center.x - r), self.column(center.x + r));
+        let (r0, r1) = (self.row(center.y - r), self.row(center.y + r));
+        let cells = (r0..=r1).flat_map(|row| (c0..=c1).map(move |col| row * self.cols + col));
+        !cells.filter(|&cell| cell != first).any(inside)
+    }
+}
+
+/// Whether `triangles` form a strictly Delaunay triangulation of the
+/// convex hull of `points` with every point a vertex
+///
+/// All triangles CCW, each edge shared by at most two with opposite
+/// directions, Euler characteristic 1 and a single convex boundary loop
+/// make a triangulated convex disk; no interior edge with a cocircular
+/// or inside opposite point makes it the unique Delaunay one.
+fn is_delaunay_of_hull(points: &[Point], triangles: &[[u32; 3]]) -> bool {
+    let n = points.len();
+    let p = |v: u32| points[v as usize];
+    if !triangles.par_iter().all(|&[a, b, c]| orient2d(p(a), p(b), p(c)) > 0.0) {
+        return false;
+    }
+    let mut used = vec![false; n];
+    triangles.iter().flatten().for_each(|&v| used[v as usize] = true);
+    if !used.iter().all(|&u| u) {
+        return false;
+    }
+
+    // Directed edges with the opposite corner, grouped by end points
+    let mut edges: Vec<(u32, u32, u32)> = triangles
+        .par_iter()
+        .flat_map_iter(|&[a, b, c]| [(a, b, c), (b, c, a), (c, a, b)])
+        .collect();
+    edges.par_sort_unstable_by_key(|&(a, b, _)| (a.min(b), a.max(b)));
+    let same = |i: usize, j: usize| {
+        let ((a, b, _), (c, d, _)) = (edges[i], edges[j]);
+        (a.min(b), a.max(b)) == (c.min(d), c.max(d))
+    };
+    let mut interior = Vec::new();
+    let mut next = vec![NONE; n];
+    let mut boundary = 0;
+    let mut i = 0;
+    while i < edges.len() {
+        if i + 1 < edges.len() && same(i, i + 1) {
+            let ((a, b, c), (d, e, f)) = (edges[i], edges[i + 1]);
+            if (a, b) != (e, d) || (i + 2 < edges.len() && same(i, i + 2)) {
+                return false;
+            }
+            interior.push((a, b, c, f));
+            i += 2;
+        } else {
+            let (a, b, _) = edges[i];
+            if next[a as usize] != NONE {
+                return false;
+            }
+            next[a as usize] = b;
+            boundary += 1;
+            i += 1;
+        }
+    }
+    let edge_count = interior.len() + boundary;
+    if n as i64 - edge_count as i64 + triangles.len() as i64 != 1 {
+        return false;
+    }
+
+    // The boundary is one loop, turning left or going straight throughout
+    let Some(start) = next.iter().position(|&v| v != NONE) else { return false };
+    let (mut prev, mut at, mut steps) = (start as u32, next[start], 1);
+    while at as usize != start {
+        let ahead = next[at as usize];
+        if ahead == NONE || steps > boundary || orient2d(p(prev), p(at), p(ahead)) < 0.0 {
+            return false;
+        }
+        (prev, at, steps) = (at, ahead, steps + 1);
+    }
+    let closing = orient2d(p(prev), p(at), p(next[at as usize])) >= 0.0;
+    closing
+        && steps == boundary
+        && interior.par_iter().all(|&(a, b, c, d)| incircle(p(a), p(b), p(c), p(d)) < 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::SUPER;
+    use crate::rng::Rng;
+
+    /// Triangles rotated to start at their lowest corner, sorted
+    fn canonical(mut triangles: Vec<[u32; 3]>) -> Vec<[u32; 3]> {
+        for t in &mut triangles {
+            let k = (0..3).min_by_key(|&k| t[k]).unwrap();
+            t.rotate_left(k);
+        }
+        triangles.sort_unstable();
+        triangles
+    }
+
+    fn serial(points: &[Point]) -> Vec<[u32; 3]> {
+        canonical(Mesh::build_closed(points).real_triangles().collect())
+    }
+
+    #[test]
+    fn test_striped_build_matches_serial() {
+        let mut rng = Rng::new(5);
+        let mut uniform = Vec::new();
+        for _ in 0..30_000 {
+            uniform.push(Point::new(rng.range(0.0, 1920.0), rng.range(0.0, 1080.0)));
+        }
+        // Dense clusters in a sparse field, on a tilted, shifted canvas
+        let clustered: Vec<Point> = (0..25_000)
+            .map(|i| {
+                let (cx, cy) = [(200.0, 300.0), (900.0, 50.0), (1500.0, 800.0)][i % 3];
+                let spread = if i % 5 == 0 { 900.0 } else { 60.0 };
+                let (x, y) = (cx + rng.range(-spread, spread), cy + rng.range(-spread, spread));
+                Point::new(x * 0.8 - y * 0.6 + 1e4, x * 0.6 + y * 0.8 - 3e3)
+            })
+            .collect();
+        for points in [&uniform, &clustered] {
+            let expected = serial(points);
+            for stripes in [2, 3, 8] {
+                let striped = triangulate_striped(points, stripes).expect("general position");
+                assert_eq!(canonical(striped), expected, "{stripes} stripes");
+            }
+        }
+    }
+
+    #[test]
+    fn test_triangulation_on_a_pool_matches_serial() {
+        let seeds = crate::generate_r2_seeds(1600.0, 900.0, 40_000, 3);
+        let points: Vec<Point> = seeds.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        assert!(pool.install(|| triangulate(&points)).is_some());
+        let tri = pool.install(|| crate::Triangulation::new(&points)).unwrap();
+        let triangles = tri.triangles().map(|t| [t.a, t.b, t.c].map(|v| v as u32)).collect();
+        assert_eq!(canonical(triangles), serial(&points));
+        let edges: Vec<(u32, u32)> = tri.edges().map(|(a, b)| (a as u32, b as u32)).collect();
+        let mesh = Mesh::build_closed(&points);
+        let expected: Vec<(u32, u32)> =
+            mesh.real_edges().into_iter().map(|(a, b)| (a - SUPER, b - SUPER)).collect();
+        assert_eq!(edges, expected);
+    }
+
+    #[test]
+    fn test_degenerate_input_falls_back() {
+        // A lattice is full of cocircular quads, so its triangulation is
+        // not unique and only the serial build settles it
+        let lattice: Vec<Point> =
+            (0..40_000).map(|i| Point::new((i % 200) as f64, (i / 200) as f64)).collect();
+        assert!(triangulate_striped(&lattice, 4).is_none());
+        let mut doubled: Vec<Point> = (0..10_000)
+            .map(|i| Point::new((i as f64 * 0.618).fract() * 500.0, i as f64 * 0.05))
+            .collect();
+        doubled.push(doubled[17]);
+        assert!(triangulate_striped(&doubled, 4).is_none());
+        let line: Vec<Point> = (0..30_000).map(|i| Point::new(i as f64, 2.0 * i as f64)).collect();
+        assert!(triangulate_striped(&line, 3).is_none());
+        assert!(triangulate(&line[..100]).is_none());
+    }
+}
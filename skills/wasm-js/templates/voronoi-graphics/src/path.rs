@@ -0,0 +1,184 @@
+//! Arc-length parameterized polylines
+//!
+//! Anything that moves along a curve (motes on smoothed boundaries, labels
+//! on spiral arms) samples a `PathTable` by distance travelled, which keeps
+//! speed constant no matter how unevenly the vertices are spaced.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+
+/// Polyline with a cumulative-length table for constant-speed sampling
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct PathTable {
+    /// Flat `[x, y, ...]`; closed paths repeat the first vertex at the end
+    vertices: Vec<f64>,
+    /// Arc length at each vertex, starting at 0
+    cumulative: Vec<f64>,
+    closed: bool,
+}
+
+#[wasm_bindgen]
+impl PathTable {
+    /// Build from a flat `[x0, y0, x1, y1, ...]` polyline
+    ///
+    /// Closed paths get an implicit segment from the last vertex back to
+    /// the first.
+    #[wasm_bindgen(constructor)]
+    pub fn new(vertices: &[f64], closed: bool) -> Result<PathTable> {
+        if vertices.is_empty() || !vertices.len().is_multiple_of(2) {
+            return Err(Error::InvalidLength(format!(
+                "path needs a non-empty, even-length vertex array, got {}",
+                vertices.len()
+            )));
+        }
+        if vertices.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("path vertices must be finite".into()));
+        }
+
+        let mut verts = vertices.to_vec();
+        if closed && verts.len() > 2 {
+            verts.extend_from_slice(&vertices[..2]);
+        }
+
+        let n = verts.len() / 2;
+        let mut cumulative = Vec::with_capacity(n);
+        cumulative.push(0.0);
+        for i in 1..n {
+            let dx = verts[i * 2] - verts[i * 2 - 2];
+            let dy = verts[i * 2 + 1] - verts[i * 2 - 1];
+            cumulative.push(cumulative[i - 1] + (dx * dx + dy * dy).sqrt());
+        }
+
+        Ok(PathTable {
+            vertices: verts,
+            cumulative,
+            closed,
+        })
+    }
+
+    /// Total arc length
+    pub fn length(&self) -> f64 {
+        *self.cumulative.last().unwrap_or(&0.0)
+    }
+
+    /// Whether `s` wraps around the path instead of clamping
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Position and unit tangent at arc length `s` as `[x, y, tx, ty]`
+    ///
+    /// Open paths clamp `s` to `[0, length]`; closed paths wrap it. A
+    /// zero-length path returns its first point with a zero tangent.
+    pub fn point_at(&self, s: f64) -> Vec<f64> {
+        self.sample(s).to_vec()
+    }
+
+    /// Batched `point_at`, stride 4 `[x, y, tx, ty]` per distance
+    pub fn points_at(&self, distances: &[f64]) -> Vec<f32> {
+        let mut out = Vec::with_capacity(distances.len() * 4);
+        for &s in distances {
+            out.extend(self.sample(s).map(|v| v as f32));
+        }
+        out
+    }
+}
+
+impl PathTable {
+    pub(crate) fn sample(&self, s: f64) -> [f64; 4] {
+        let total = self.length();
+        if total <= 0.0 {
+            return [self.vertices[0], self.vertices[1], 0.0, 0.0];
+        }
+
+        let s = if s.is_nan() {
+            0.0
+        } else if self.closed {
+            s.rem_euclid(total)
+        } else {
+            s.clamp(0.0, total)
+        };
+
+        // Segment i spans cumulative[i]..cumulative[i + 1]; zero-length
+        // segments are never selected because their span is empty
+        let last_seg = self.cumulative.len() - 2;
+        let seg = self.cumulative.partition_point(|&c| c <= s).saturating_sub(1);
+        let seg = if seg > last_seg { self.last_moving_segment() } else { seg };
+
+        let (x0, y0) = (self.vertices[seg * 2], self.vertices[seg * 2 + 1]);
+        let (x1, y1) = (self.vertices[seg * 2 + 2], self.vertices[seg * 2 + 3]);
+        let len = self.cumulative[seg + 1] - self.cumulative[seg];
+        let f = ((s - self.cumulative[seg]) / len).clamp(0.0, 1.0);
+        let (tx, ty) = ((x1 - x0) / len, (y1 - y0) / len);
+
+        [x0 + (x1 - x0) * f, y0 + (y1 - y0) * f, tx, ty]
+    }
+
+    fn last_moving_segment(&self) -> usize {
+        (0..self.cumulative.len() - 1)
+            .rev()
+            .find(|&i| self.cumulative[i + 1] > self.cumulative[i])
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    const SQUARE: [f64; 8] = [0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0];
+
+    #[test]
+    fn test_path_length() {
+        assert_eq!(PathTable::new(&SQUARE, false).unwrap().length(), 30.0);
+        assert_eq!(PathTable::new(&SQUARE, true).unwrap().length(), 40.0);
+    }
+
+    #[test]
+    fn test_path_point_at_with_tangent() {
+        let path = PathTable::new(&SQUARE, false).unwrap();
+        assert_eq!(path.point_at(5.0), vec![5.0, 0.0, 1.0, 0.0]);
+        assert_eq!(path.point_at(15.0), vec![10.0, 5.0, 0.0, 1.0]);
+        // Open paths clamp at both ends
+        assert_eq!(path.point_at(-3.0), vec![0.0, 0.0, 1.0, 0.0]);
+        assert_eq!(path.point_at(99.0), vec![0.0, 10.0, -1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_closed_path_wraps() {
+        let path = PathTable::new(&SQUARE, true).unwrap();
+        assert_eq!(path.point_at(35.0), vec![0.0, 5.0, 0.0, -1.0]);
+        assert_eq!(path.point_at(45.0), path.point_at(5.0));
+        assert_eq!(path.point_at(-5.0), path.point_at(35.0));
+    }
+
+    #[test]
+    fn test_constant_speed_over_uneven_vertices() {
+        // Densely sampled start, one long final segment
+        let verts = [0.0, 0.0, 1.0, 0.0, 2.0, 0.0, 3.0, 0.0, 100.0, 0.0];
+        let path = PathTable::new(&verts, false).unwrap();
+        let ss: Vec<f64> = (0..=10).map(|i| i as f64 * 10.0).collect();
+        let out = path.points_at(&ss);
+        for (i, p) in out.chunks(4).enumerate() {
+            assert!((p[0] - i as f32 * 10.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_degenerate_paths() {
+        let single = PathTable::new(&[3.0, 4.0], true).unwrap();
+        assert_eq!(single.length(), 0.0);
+        assert_eq!(single.point_at(12.0), vec![3.0, 4.0, 0.0, 0.0]);
+
+        // Repeated vertices contribute nothing and never produce NaN
+        let stutter = PathTable::new(&[0.0, 0.0, 0.0, 0.0, 4.0, 0.0, 4.0, 0.0], false).unwrap();
+        assert_eq!(stutter.point_at(2.0), vec![2.0, 0.0, 1.0, 0.0]);
+        assert_eq!(stutter.point_at(4.0), vec![4.0, 0.0, 1.0, 0.0]);
+
+        assert_eq!(code_of(PathTable::new(&[], false)), ErrorCode::InvalidLength);
+        assert_eq!(code_of(PathTable::new(&[1.0, 2.0, 3.0], false)), ErrorCode::InvalidLength);
+    }
+}
@@ -0,0 +1,266 @@
+//! Delaunay triangulation on a torus, for backgrounds that tile seamlessly
+//!
+//! The points are triangulated together with their eight translates by
+//! the canvas size, and of the nine copies of each torus triangle the one
+//! whose lowest-indexed corner sits in the central tile is kept. Near
+//! the outer rim of the 3 × 3 block the replicated triangulation is
+//! wrong, but no kept triangle reaches it while the points are spread
+//! densely enough; a torus triangulation has exactly twice as many
+//! triangles as vertices, which is checked to catch the cases where they
+//! are not.
+
+use std::collections::BTreeSet;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::mesh::Mesh;
+use crate::Point;
+
+/// Result of `compute_delaunay_periodic`
+///
+/// Corner `k` of a triangle is drawn at `p[k] + wrap[k] · (width, height)`,
+/// with `p` the point wrapped into the canvas and each wrap component
+/// -1, 0 or 1. The first corner of every triangle is its lowest index
+/// and never wraps, and triangles are counter-clockwise in that
+/// unwrapped position, sorted by their corner indices.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct PeriodicTriangulation {
+    triangles: Vec<u32>,
+    wraps: Vec<i8>,
+    edges: Vec<u32>,
+    edge_wraps: Vec<i8>,
+}
+
+#[wasm_bindgen]
+impl PeriodicTriangulation {
+    /// `[a0, b0, c0, a1, ...]` indices into the input points
+    pub fn triangles(&self) -> Vec<u32> {
+        self.triangles.clone()
+    }
+
+    /// `[dx, dy]` per triangle corner, in `triangles` order
+    pub fn wraps(&self) -> Vec<i8> {
+        self.wraps.clone()
+    }
+
+    /// Unique `[i0, j0, i1, j1, ...]` edges with `i <= j`, sorted
+    ///
+    /// Each torus edge appears once, so a pair can repeat with different
+    /// `edge_wraps` when the points are sparse.
+    pub fn edges(&self) -> Vec<u32> {
+        self.edges.clone()
+    }
+
+    /// `[dx, dy]` per edge: the wrap of `j` when `i` is drawn unwrapped
+    pub fn edge_wraps(&self) -> Vec<i8> {
+        self.edge_wraps.clone()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len() / 3
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len() / 2
+    }
+}
+
+/// Delaunay triangulation of `points_flat` on the torus made by gluing
+/// opposite sides of the `width × height` canvas
+///
+/// Points outside the canvas are wrapped into it first. Drawing every
+/// triangle at its wrapped position, and again shifted by the canvas size
+/// where it sticks out, tiles the plane without seams. Fails with
+/// `DegenerateGeometry` when the points are too few or too clustered to
+/// triangulate the torus from one ring of copies, or coincide once
+/// wrapped.
+#[wasm_bindgen]
+pub fn compute_delaunay_periodic(
+    points_flat: &[f64],
+    width: f64,
+    height: f64,
+) -> Result<PeriodicTriangulation> {
+    let n = check_points(points_flat)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if n > u32::MAX as usize / 9 {
+        return Err(Error::TooLarge(format!("{n} points are too many to replicate")));
+    }
+    let wrapped: Vec<Point> = points_flat
+        .chunks_exact(2)
+        .map(|c| {
+            // `rem_euclid` can round up to the modulus itself
+            let wrap = |v: f64, size: f64| {
+                let r = v.rem_euclid(size);
+                if r < size { r } else { 0.0 }
+            };
+            Point::new(wrap(c[0], width), wrap(c[1], height))
+        })
+        .collect();
+    // Copy `t` of point `i` is vertex `t · n + i`, shifted by `TILES[t]`
+    const TILES: [(i8, i8); 9] =
+        [(0, 0), (-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+    let copies: Vec<Point> = TILES
+        .iter()
+        .flat_map(|&(dx, dy)| {
+            let shift = Point::new(dx as f64 * width, dy as f64 * height);
+            wrapped.iter().map(move |p| Point::new(p.x + shift.x, p.y + shift.y))
+        })
+        .collect();
+    let mesh = Mesh::build(&copies, None);
+
+    let too_sparse = || {
+        Error::DegenerateGeometry(format!(
+            "{n} points do not triangulate a {width}x{height} torus"
+        ))
+    };
+    let mut kept: Vec<[(u32, (i8, i8)); 3]> = Vec::with_capacity(n * 2);
+    for t in mesh.real_triangles() {
+        let corners = t.map(|v| (v % n as u32, TILES[(v / n as u32) as usize]));
+        let k = (0..3).min_by_key(|&k| corners[k].0).unwrap_or(0);
+        let anchor = corners[k];
+        if anchor.1 != (0, 0) {
+            continue;
+        }
+        // A point meeting its own copy means the copies are too close
+        if corners[(k + 1) % 3].0 == anchor.0 || corners[(k + 2) % 3].0 == anchor.0 {
+            return Err(too_sparse());
+        }
+        kept.push([anchor, corners[(k + 1) % 3], corners[(k + 2) % 3]]);
+    }
+    if n == 0 || kept.len() != n * 2 {
+        return Err(too_sparse());
+    }
+    kept.sort_unstable_by_key(|t| t.map(|(i, _)| i));
+
+    let mut edge_set = BTreeSet::new();
+    for t in &kept {
+        for k in 0..3 {
+            let ((a, wa), (b, wb)) = (t[k], t[(k + 1) % 3]);
+            let (dx, dy) = (wb.0 - wa.0, wb.1 - wa.1);
+            edge_set.insert(if a <= b { (a, b, dx, dy) } else { (b, a, -dx, -dy) });
+        }
+    }
+    if edge_set.len() != n * 3 {
+        return Err(too_sparse());
+    }
+    Ok(PeriodicTriangulation {
+        triangles: kept.iter().flat_map(|t| t.map(|(i, _)| i)).collect(),
+        wraps: kept.iter().flat_map(|t| t.iter().flat_map(|&(_, (dx, dy))| [dx, dy])).collect(),
+        edges: edge_set.iter().flat_map(|&(i, j, _, _)| [i, j]).collect(),
+        edge_wraps: edge_set.iter().flat_map(|&(_, _, dx, dy)| [dx, dy]).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::predicates::orient2d;
+    use crate::rng::Rng;
+
+    fn random_points(count: usize, width: f64, height: f64, seed: u64) -> Vec<f64> {
+        let mut rng = Rng::new(seed);
+        (0..count).flat_map(|_| [rng.range(0.0, width), rng.range(0.0, height)]).collect()
+    }
+
+    /// Edges as `(i, j, displacement from i to j)`, independent of where
+    /// the canvas boundary falls
+    fn displacements(
+        tri: &PeriodicTriangulation,
+        points: &[f64],
+        w: f64,
+        h: f64,
+    ) -> Vec<(u32, u32, f64, f64)> {
+        let (edges, wraps) = (tri.edges(), tri.edge_wraps());
+        edges
+            .chunks(2)
+            .zip(wraps.chunks(2))
+            .map(|(e, d)| {
+                let (i, j) = (e[0] as usize, e[1] as usize);
+                let dx = points[j * 2] + d[0] as f64 * w - points[i * 2];
+                let dy = points[j * 2 + 1] + d[1] as f64 * h - points[i * 2 + 1];
+                (e[0], e[1], dx, dy)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_periodic_triangulation_tiles_the_torus() {
+        let (w, h) = (800.0, 600.0);
+        let points = random_points(300, w, h, 11);
+        let tri = compute_delaunay_periodic(&points, w, h).unwrap();
+        assert_eq!((tri.triangle_count(), tri.edge_count()), (600, 900));
+        let (triangles, wraps) = (tri.triangles(), tri.wraps());
+        assert_eq!(wraps.len(), triangles.len() * 2);
+        assert!(wraps.iter().all(|d| (-1..=1).contains(d)));
+        // Unwrapped triangles are counter-clockwise and cover one canvas
+        let mut area = 0.0;
+        for (t, d) in triangles.chunks(3).zip(wraps.chunks(6)) {
+            assert!(t[0] < t[1] && t[0] < t[2]);
+            assert_eq!((d[0], d[1]), (0, 0));
+            let [a, b, c] = [0, 1, 2].map(|k| {
+                let i = t[k] as usize * 2;
+                let (dx, dy) = (d[k * 2] as f64, d[k * 2 + 1] as f64);
+                Point::new(points[i] + dx * w, points[i + 1] + dy * h)
+            });
+            let doubled = orient2d(a, b, c);
+            assert!(doubled > 0.0);
+            area += doubled / 2.0;
+        }
+        assert!((area - w * h).abs() < 1e-6 * w * h);
+        // Every vertex has at least three neighbours, so degree sums to 2E
+        let mut degree = vec![0; 300];
+        for &i in &tri.edges() {
+            degree[i as usize] += 1;
+        }
+        assert!(degree.iter().all(|&d| d >= 3));
+    }
+
+    #[test]
+    fn test_shifted_points_give_an_isomorphic_triangulation() {
+        let (w, h) = (640.0, 480.0);
+        let points = random_points(200, w, h, 5);
+        let shifted: Vec<f64> = points
+            .chunks(2)
+            .flat_map(|c| [(c[0] + w / 2.0) % w, (c[1] + h / 2.0) % h])
+            .collect();
+        let a = displacements(&compute_delaunay_periodic(&points, w, h).unwrap(), &points, w, h);
+        let b =
+            displacements(&compute_delaunay_periodic(&shifted, w, h).unwrap(), &shifted, w, h);
+        assert_eq!(a.len(), b.len());
+        for &(i, j, dx, dy) in &a {
+            let matched = b.iter().any(|&(bi, bj, bx, by)| {
+                (bi, bj) == (i, j) && (bx - dx).abs() < 1e-9 && (by - dy).abs() < 1e-9
+            });
+            assert!(matched, "edge {i}-{j} ({dx}, {dy}) missing after the shift");
+        }
+        // Points outside the canvas wrap into it
+        let outside: Vec<f64> =
+            points.chunks(2).flat_map(|c| [c[0] - w, c[1] + 2.0 * h]).collect();
+        let wrapped = compute_delaunay_periodic(&outside, w, h).unwrap();
+        assert_eq!(wrapped.edge_count(), 600);
+    }
+
+    #[test]
+    fn test_periodic_rejects_bad_input() {
+        let periodic = |p: &[f64], w| code_of(compute_delaunay_periodic(p, w, 100.0));
+        assert_eq!(periodic(&[1.0, 2.0, 3.0], 100.0), ErrorCode::InvalidLength);
+        assert_eq!(periodic(&[f64::NAN, 0.0], 100.0), ErrorCode::NonFiniteInput);
+        assert_eq!(periodic(&[1.0, 2.0], 0.0), ErrorCode::InvalidArgument);
+        assert_eq!(periodic(&[], 100.0), ErrorCode::DegenerateGeometry);
+        assert_eq!(periodic(&[50.0, 50.0], 100.0), ErrorCode::DegenerateGeometry);
+        let pair = [10.0, 10.0, 60.0, 20.0];
+        assert_eq!(periodic(&pair, 100.0), ErrorCode::DegenerateGeometry);
+        let mut doubled = random_points(50, 100.0, 100.0, 2);
+        doubled.extend_from_slice(&[doubled[0] + 100.0, doubled[1]]);
+        assert_eq!(periodic(&doubled, 100.0), ErrorCode::DegenerateGeometry);
+    }
+}
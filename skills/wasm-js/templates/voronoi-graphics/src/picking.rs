@@ -0,0 +1,389 @@
+//! Which seed's Voronoi cell holds a point, for mouse picking
+//!
+//! The cell containing `(x, y)` belongs to the seed nearest to it, so
+//! picking is a nearest-neighbour query. `nearest_seed` scans every seed,
+//! which is fine for small sets or one-off queries; `SeedIndex` buckets
+//! the seeds once so each query only looks at the few cells around it.
+//! Both resolve exact ties to the lower index.
+//!
+//! `TriangleLocator` answers the same question for the triangles of a
+//! mesh, walking across neighbours from the previous hit so a cursor that
+//! moves a little costs a few steps.
+
+use wasm_bindgen::prelude::*;
+
+use crate::adjacency::triangle_neighbors;
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles};
+use crate::predicates::orient2d;
+use crate::spatial::{Aabb, Grid, PointGrid};
+use crate::Point;
+
+fn parse_seeds(points_flat: &[f64]) -> Result<Vec<Point>> {
+    check_points(points_flat)?;
+    if points_flat.is_empty() {
+        return Err(Error::InvalidArgument("no seeds to pick from".into()));
+    }
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("seed coordinates must be finite".into()));
+    }
+    Ok(points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect())
+}
+
+fn check_query(x: f64, y: f64) -> Result<Point> {
+    if !(x.is_finite() && y.is_finite()) {
+        return Err(Error::NonFiniteInput(format!("query point ({x}, {y}) is not finite")));
+    }
+    Ok(Point::new(x, y))
+}
+
+/// Index of the seed nearest `(x, y)` by a linear scan
+#[wasm_bindgen]
+pub fn nearest_seed(points_flat: &[f64], x: f64, y: f64) -> Result<u32> {
+    let seeds = parse_seeds(points_flat)?;
+    let p = check_query(x, y)?;
+    let d2 = |q: &Point| (q.x - p.x).powi(2) + (q.y - p.y).powi(2);
+    // `min_by` keeps the first of equal elements, so ties go low
+    let best = seeds.iter().enumerate().min_by(|(_, a), (_, b)| d2(a).total_cmp(&d2(b)));
+    Ok(best.map_or(0, |(i, _)| i as u32))
+}
+
+/// Seeds bucketed for repeated nearest-seed queries
+///
+/// Build once per seed layout; a query costs about as much as scanning
+/// the handful of seeds in the grid cells around it, whatever the total
+/// count. Queries anywhere, including off the seeds' bounding box, are
+/// exact.
+#[wasm_bindgen]
+pub struct SeedIndex {
+    grid: PointGrid,
+    count: usize,
+}
+
+#[wasm_bindgen]
+impl SeedIndex {
+    #[wasm_bindgen(constructor)]
+    pub fn new(points_flat: &[f64]) -> Result<SeedIndex> {
+        let seeds = parse_seeds(points_flat)?;
+        let first = seeds[0];
+        let grid = PointGrid::new(&seeds, [first.x, first.y, first.x, first.y]);
+        Ok(SeedIndex { grid, count: seeds.len() })
+    }
+
+    pub fn seed_count(&self) -> usize {
+        self.count
+    }
+
+    /// Index of the seed nearest `(x, y)`, i.e. the cell under it
+    pub fn query(&self, x: f64, y: f64) -> Result<u32> {
+        let p = check_query(x, y)?;
+        // Never empty, so never the fallback
+        Ok(self.grid.nearest(p).unwrap_or(0))
+    }
+
+    /// The `k` seeds nearest `(x, y)`, closest first; every seed when
+    /// there are fewer than `k`
+    pub fn query_k(&self, x: f64, y: f64, k: usize) -> Result<Vec<u32>> {
+        let p = check_query(x, y)?;
+        Ok(self.grid.k_nearest(p, k))
+    }
+}
+
+/// Triangles over their points, with the containment tests
+struct TriangleSet {
+    points: Vec<Point>,
+    triangles: Vec<u32>,
+}
+
+impl TriangleSet {
+    fn new(points_flat: &[f64], triangles: &[u32]) -> Result<TriangleSet> {
+        let n = check_points(points_flat)?;
+        check_triangles(triangles, n)?;
+        if points_flat.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+        }
+        let points = points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+        Ok(TriangleSet { points, triangles: triangles.to_vec() })
+    }
+
+    fn count(&self) -> usize {
+        self.triangles.len() / 3
+    }
+
+    fn corners(&self, t: usize) -> [Point; 3] {
+        let v = &self.triangles[t * 3..t * 3 + 3];
+        [v[0], v[1], v[2]].map(|i| self.points[i as usize])
+    }
+
+    /// Twice the area `p` spans with each side `(v_k, v_{k+1})` of `t`,
+    /// signed so the inside is positive whatever the winding; `None` for
+    /// a flat triangle, which contains nothing
+    fn sides(&self, t: usize, p: Point) -> Option<[f64; 3]> {
+        let [a, b, c] = self.corners(t);
+        let winding = orient2d(a, b, c);
+        if winding == 0.0 {
+            return None;
+        }
+        let sides = [orient2d(a, b, p), orient2d(b, c, p), orient2d(c, a, p)];
+        Some(sides.map(|s| if winding > 0.0 { s } else { -s }))
+    }
+
+    fn contains(&self, t: usize, p: Point) -> bool {
+        self.sides(t, p).is_some_and(|s| s.iter().all(|&v| v >= 0.0))
+    }
+
+    /// First triangle containing `p`, by a linear scan
+    fn scan(&self, p: Point) -> Option<usize> {
+        (0..self.count()).find(|&t| self.contains(t, p))
+    }
+
+    fn hit(&self, t: Option<usize>, p: Point) -> TriangleHit {
+        let Some(t) = t else { return TriangleHit::MISS };
+        let s = self.sides(t, p).unwrap_or([1.0; 3]);
+        let total = s[0] + s[1] + s[2];
+        // Side k is opposite corner k + 2
+        TriangleHit { triangle: t as i32, barycentric: [s[1], s[2], s[0]].map(|v| v / total) }
+    }
+}
+
+/// Index of the first triangle containing `(x, y)`, or -1 if none does,
+/// by a linear scan
+///
+/// Points on a shared edge or vertex go to the lowest-indexed incident
+/// triangle, as they do with `TriangleLocator`. Triangles of either
+/// winding are fine; flat ones contain nothing.
+#[wasm_bindgen]
+pub fn locate_triangle(points_flat: &[f64], triangles: &[u32], x: f64, y: f64) -> Result<i32> {
+    let set = TriangleSet::new(points_flat, triangles)?;
+    let p = check_query(x, y)?;
+    Ok(set.scan(p).map_or(-1, |t| t as i32))
+}
+
+/// Result of `TriangleLocator::locate`
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TriangleHit {
+    triangle: i32,
+    barycentric: [f64; 3],
+}
+
+impl TriangleHit {
+    const MISS: TriangleHit = TriangleHit { triangle: -1, barycentric: [0.0; 3] };
+}
+
+#[wasm_bindgen]
+impl TriangleHit {
+    /// Index of the containing triangle, or -1 outside the mesh
+    pub fn triangle(&self) -> i32 {
+        self.triangle
+    }
+
+    /// Weights of the triangle's corners, in `triangles` order, summing to
+    /// 1: interpolate a per-vertex value as `w0 · f0 + w1 · f1 + w2 · f2`.
+    /// All 0 for a miss.
+    pub fn barycentric(&self) -> Vec<f64> {
+        self.barycentric.to_vec()
+    }
+}
+
+/// Point location over a fixed mesh, remembering where the last query
+/// ended
+///
+/// Each query walks from the previous triangle towards the point, leaving
+/// through a side the point is beyond, so coherent queries such as mouse
+/// moves take a handful of steps. When the walk runs off the mesh, or
+/// wanders too long in a mesh that is not Delaunay, the triangles bucketed
+/// by bounding box settle it. Answers are the ones `locate_triangle`
+/// gives, whatever the previous query was.
+#[wasm_bindgen]
+pub struct TriangleLocator {
+    set: TriangleSet,
+    neighbors: Vec<i32>,
+    grid: Grid,
+    last: usize,
+    last_steps: usize,
+    scratch: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl TriangleLocator {
+    #[wasm_bindgen(constructor)]
+    pub fn new(points_flat: &[f64], triangles: &[u32]) -> Result<TriangleLocator> {
+        let set = TriangleSet::new(points_flat, triangles)?;
+        let neighbors = triangle_neighbors(triangles)?;
+        let boxes: Vec<Aabb> = (0..set.count())
+            .map(|t| {
+                let [a, b, c] = set.corners(t);
+                let (xs, ys) = ([a.x, b.x, c.x], [a.y, b.y, c.y]);
+                let min = |v: [f64; 3]| v[0].min(v[1]).min(v[2]);
+                let max = |v: [f64; 3]| v[0].max(v[1]).max(v[2]);
+                [min(xs), min(ys), max(xs), max(ys)]
+            })
+            .collect();
+        let mut grid = Grid::fitted(&boxes);
+        for (t, b) in boxes.iter().enumerate() {
+            grid.insert(t as u32, b);
+        }
+        Ok(TriangleLocator { set, neighbors, grid, last: 0, last_steps: 0, scratch: Vec::new() })
+    }
+
+    /// The triangle containing `(x, y)` and the barycentric coordinates of
+    /// the point in it
+    pub fn locate(&mut self, x: f64, y: f64) -> Result<TriangleHit> {
+        let p = check_query(x, y)?;
+        self.last_steps = 0;
+        if self.set.count() == 0 {
+            return Ok(TriangleHit::MISS);
+        }
+        let mut t = self.last;
+        let found = loop {
+            self.last_steps += 1;
+            if self.last_steps > self.set.count() {
+                break self.search_grid(p);
+            }
+            let Some(sides) = self.set.sides(t, p) else { break self.search_grid(p) };
+            // Rotating the side tried first keeps the walk from cycling
+            let exit = (0..3).map(|i| (i + self.last_steps) % 3).find(|&k| sides[k] < 0.0);
+            match exit {
+                None if sides.contains(&0.0) => break self.search_grid(p),
+                None => break Some(t),
+                Some(k) => match self.neighbors[t * 3 + k] {
+                    -1 => break self.search_grid(p),
+                    next => t = next as usize,
+                },
+            }
+        };
+        self.last = found.unwrap_or(t);
+        Ok(self.set.hit(found, p))
+    }
+
+    /// Steps the last `locate` walked, counting the triangle it ended on
+    pub fn last_walk_steps(&self) -> usize {
+        self.last_steps
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.set.count()
+    }
+}
+
+impl TriangleLocator {
+    /// Lowest-indexed triangle containing `p` among those whose box
+    /// covers it, which settles boundary ties the way a scan would
+    fn search_grid(&mut self, p: Point) -> Option<usize> {
+        self.grid.query(&[p.x, p.y, p.x, p.y], &mut self.scratch);
+        self.scratch.iter().map(|&t| t as usize).find(|&t| self.set.contains(t, p))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+
+    #[test]
+    fn test_index_matches_the_linear_scan() {
+        let mut rng = Rng::new(11);
+        let seeds: Vec<f64> =
+            (0..2000).flat_map(|_| [rng.range(0.0, 1600.0), rng.range(0.0, 900.0)]).collect();
+        let index = SeedIndex::new(&seeds).unwrap();
+        assert_eq!(index.seed_count(), 2000);
+        let d2 = |i: u32, x: f64, y: f64| {
+            (seeds[i as usize * 2] - x).powi(2) + (seeds[i as usize * 2 + 1] - y).powi(2)
+        };
+        for _ in 0..500 {
+            // Some queries land well off the seeds' bounding box
+            let (x, y) = (rng.range(-400.0, 2000.0), rng.range(-300.0, 1200.0));
+            let nearest = nearest_seed(&seeds, x, y).unwrap();
+            assert_eq!(index.query(x, y).unwrap(), nearest);
+            let mut brute: Vec<u32> = (0..2000).collect();
+            brute.sort_by(|&a, &b| d2(a, x, y).total_cmp(&d2(b, x, y)).then(a.cmp(&b)));
+            assert_eq!(index.query_k(x, y, 7).unwrap(), &brute[..7]);
+        }
+        assert_eq!(index.query_k(0.0, 0.0, 5000).unwrap().len(), 2000);
+        assert!(index.query_k(0.0, 0.0, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_ties_go_to_the_lower_index() {
+        // Four seeds around the centre, then a duplicate of the first
+        let seeds = [10.0, 0.0, 0.0, 10.0, -10.0, 0.0, 0.0, -10.0, 10.0, 0.0];
+        let index = SeedIndex::new(&seeds).unwrap();
+        assert_eq!(nearest_seed(&seeds, 0.0, 0.0).unwrap(), 0);
+        assert_eq!(index.query(0.0, 0.0).unwrap(), 0);
+        assert_eq!(index.query_k(0.0, 0.0, 5).unwrap(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(index.query_k(9.0, 1.0, 2).unwrap(), vec![0, 4]);
+        assert_eq!(index.query(-1.0, -1.0).unwrap(), 2);
+
+        assert_eq!(code_of(nearest_seed(&[], 0.0, 0.0)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(SeedIndex::new(&[0.0, f64::NAN])), ErrorCode::NonFiniteInput);
+        assert_eq!(code_of(index.query(f64::NAN, 0.0)), ErrorCode::NonFiniteInput);
+        assert_eq!(code_of(nearest_seed(&[1.0], 0.0, 0.0)), ErrorCode::InvalidLength);
+    }
+
+    #[test]
+    fn test_locator_walks_to_the_scan_answer() {
+        let mut rng = Rng::new(5);
+        let points: Vec<f64> =
+            (0..2000).flat_map(|_| [rng.range(0.0, 1000.0), rng.range(0.0, 800.0)]).collect();
+        let triangles = crate::compute_delaunay(&points, 1000.0, 800.0);
+        let mut locator = TriangleLocator::new(&points, &triangles).unwrap();
+        assert_eq!(locator.triangle_count(), triangles.len() / 3);
+        // A cursor drifting across the canvas and off its edge
+        let (mut x, mut y) = (500.0, 400.0);
+        let mut steps = 0;
+        for _ in 0..600 {
+            x += rng.range(-6.0, 8.0);
+            y += rng.range(-5.0, 5.0);
+            let hit = locator.locate(x, y).unwrap();
+            steps += locator.last_walk_steps();
+            assert_eq!(hit.triangle(), locate_triangle(&points, &triangles, x, y).unwrap());
+            let w = hit.barycentric();
+            if hit.triangle() < 0 {
+                assert_eq!(w, vec![0.0; 3]);
+                continue;
+            }
+            let t = &triangles[hit.triangle() as usize * 3..][..3];
+            let along = |axis: usize| {
+                (0..3).map(|k| w[k] * points[t[k] as usize * 2 + axis]).sum::<f64>()
+            };
+            assert!(w.iter().all(|&v| v >= 0.0) && (w.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+            assert!((along(0) - x).abs() < 1e-9 && (along(1) - y).abs() < 1e-9);
+        }
+        assert!(x > 1000.0, "the cursor never left the canvas");
+        assert!(steps < 600 * 4, "{steps} steps for 600 queries");
+    }
+
+    #[test]
+    fn test_boundary_points_go_to_the_lowest_triangle() {
+        // A square split along its diagonal, listed in both orders, and a
+        // second square beside it with a gap between them
+        let points = [0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0, 20.0, 0.0, 30.0, 0.0, 20.0, 10.0];
+        for triangles in [[0, 1, 2, 0, 2, 3, 4, 5, 6], [0, 3, 2, 2, 1, 0, 4, 6, 5]] {
+            let mut locator = TriangleLocator::new(&points, &triangles).unwrap();
+            for (x, y) in [(2.0, 8.0), (8.0, 2.0), (2.0, 8.0)] {
+                assert!(locator.locate(x, y).unwrap().triangle() >= 0);
+                assert_eq!(locator.locate(5.0, 5.0).unwrap().triangle(), 0);
+                assert_eq!(locator.locate(0.0, 0.0).unwrap().triangle(), 0);
+            }
+            // Walking off the first square still finds the second
+            assert_eq!(locator.locate(22.0, 2.0).unwrap().triangle(), 2);
+            assert_eq!(locator.locate(15.0, 5.0).unwrap().triangle(), -1);
+            let corner = locator.locate(10.0, 10.0).unwrap();
+            let at = triangles[corner.triangle() as usize * 3..][..3].iter().position(|&v| v == 2);
+            let mut unit = vec![0.0; 3];
+            unit[at.unwrap()] = 1.0;
+            assert_eq!(corner.barycentric(), unit);
+        }
+        assert_eq!(locate_triangle(&points, &[0, 1, 2, 0, 2, 3], 5.0, 5.0).unwrap(), 0);
+        assert_eq!(locate_triangle(&points, &[0, 2, 3, 0, 1, 2], 5.0, 5.0).unwrap(), 0);
+
+        let mut empty = TriangleLocator::new(&[], &[]).unwrap();
+        assert_eq!(empty.locate(0.0, 0.0).unwrap().triangle(), -1);
+        assert_eq!(code_of(empty.locate(f64::NAN, 0.0)), ErrorCode::NonFiniteInput);
+        let result = TriangleLocator::new(&points, &[0, 1, 7]);
+        assert_eq!(code_of(result), ErrorCode::IndexOutOfRange);
+        assert_eq!(code_of(locate_triangle(&points, &[0, 1], 0.0, 0.0)), ErrorCode::InvalidLength);
+    }
+}
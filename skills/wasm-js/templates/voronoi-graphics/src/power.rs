@@ -0,0 +1,209 @@
+//! Power diagrams: Voronoi cells with a weight per seed
+//!
+//! Seed `s` with weight `w` owns the points `p` where its power distance
+//! `|p - s|² - w` is smallest, so heavier seeds get bigger cells and the
+//! border between two seeds is a straight radical axis rather than their
+//! bisector. The dual is the regular triangulation, which the mesh builds
+//! with the power test in place of the incircle test; cells are then
+//! clipped to the canvas exactly as `compute_voronoi` clips them, in the
+//! same output layout.
+//!
+//! Only weight differences matter: adding a constant to every weight
+//! leaves the diagram unchanged, and equal weights reproduce
+//! `compute_voronoi` exactly.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::mesh::{Mesh, NONE, SUPER};
+use crate::voronoi::mesh_cells;
+use crate::Point;
+
+/// Power cells from `compute_power_diagram`, one (possibly empty) range
+/// per seed
+#[wasm_bindgen]
+pub struct PowerDiagram {
+    vertices: Vec<f64>,
+    offsets: Vec<u32>,
+    dominated: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl PowerDiagram {
+    /// All cell vertices `[x0, y0, x1, y1, ...]`, counter-clockwise per cell
+    pub fn vertices(&self) -> Vec<f64> {
+        self.vertices.clone()
+    }
+
+    /// Per-seed start index into `vertices()` in vertex units, plus a final
+    /// end entry; an empty range marks a seed without a cell on the canvas
+    pub fn offsets(&self) -> Vec<u32> {
+        self.offsets.clone()
+    }
+
+    /// 1 for each seed that has no power cell anywhere, because heavier
+    /// neighbours cover its whole surroundings, else 0
+    ///
+    /// Of coincident seeds all but the heaviest (lowest index among equals)
+    /// are dominated. A seed that is not dominated can still get an empty
+    /// range in `offsets()` when its cell lies off the canvas.
+    pub fn dominated(&self) -> Vec<u8> {
+        self.dominated.clone()
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.offsets.len() - 1
+    }
+}
+
+/// Power diagram of `points_flat` with `weights`, clipped to
+/// `[0, width] × [0, height]`
+///
+/// Weights are in squared canvas units: a seed with weight `r²` claims
+/// roughly a disc of radius `r` more than an unweighted one. Every
+/// non-empty cell is convex and counter-clockwise, and the cells tile the
+/// canvas. Seeds need not lie inside their own cells.
+#[wasm_bindgen]
+pub fn compute_power_diagram(
+    points_flat: &[f64],
+    weights: &[f64],
+    width: f64,
+    height: f64,
+) -> Result<PowerDiagram> {
+    let n = check_points(points_flat)?;
+    if weights.len() != n {
+        return Err(Error::InvalidLength(format!("{n} seeds but {} weights", weights.len())));
+    }
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if points_flat.iter().chain(weights).any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("seeds and weights must be finite".into()));
+    }
+    let points: Vec<Point> = points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+
+    // Of coincident seeds only the heaviest can own a cell, so the others
+    // are dropped before triangulating, and `kept` maps back
+    let mut order: Vec<u32> = (0..n as u32).collect();
+    let key = |v: u32| (points[v as usize].x + 0.0, points[v as usize].y + 0.0);
+    order.sort_by(|&a, &b| {
+        let ((ax, ay), (bx, by)) = (key(a), key(b));
+        let heavier = weights[b as usize].total_cmp(&weights[a as usize]);
+        ax.total_cmp(&bx).then(ay.total_cmp(&by)).then(heavier).then(a.cmp(&b))
+    });
+    order.dedup_by(|b, a| points[*a as usize] == points[*b as usize]);
+    let mut kept = order;
+    kept.sort_unstable();
+    let seeds: Vec<Point> = kept.iter().map(|&v| points[v as usize]).collect();
+    let seed_weights: Vec<f64> = kept.iter().map(|&v| weights[v as usize]).collect();
+
+    // The super triangle must be far enough out that its corners, at
+    // weight 0, hide no real seed; pad the canvas by the weight spread
+    let (lo, hi) = weights.iter().fold((0.0f64, 0.0f64), |(l, h), &w| (l.min(w), h.max(w)));
+    let pad = (hi - lo).sqrt();
+    let bounds = [-pad, -pad, width + pad, height + pad];
+    let mesh = Mesh::build_weighted(&seeds, &seed_weights, Some(bounds));
+    let (cells, cell_offsets) = mesh_cells(&mesh, width, height);
+
+    let mut dominated = vec![1u8; n];
+    let mut vertices = Vec::with_capacity(cells.len());
+    let mut offsets = Vec::with_capacity(n + 1);
+    offsets.push(0u32);
+    let mut next = kept.iter().enumerate().peekable();
+    for (i, flag) in dominated.iter_mut().enumerate() {
+        if let Some((k, _)) = next.next_if(|&(_, &v)| v as usize == i) {
+            *flag = (mesh.vert_tri[k + SUPER as usize] == NONE) as u8;
+            let (a, b) = (cell_offsets[k] as usize, cell_offsets[k + 1] as usize);
+            vertices.extend_from_slice(&cells[a * 2..b * 2]);
+        }
+        offsets.push((vertices.len() / 2) as u32);
+    }
+    Ok(PowerDiagram { vertices, offsets, dominated })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::predicates::signed_area2;
+    use crate::rng::Rng;
+    use crate::voronoi::assert_simple_ccw;
+    use crate::{compute_voronoi, generate_golden_seeds};
+
+    fn cell(d: &PowerDiagram, i: usize) -> Vec<Point> {
+        let (a, b) = (d.offsets[i] as usize, d.offsets[i + 1] as usize);
+        d.vertices[a * 2..b * 2].chunks(2).map(|c| Point::new(c[0], c[1])).collect()
+    }
+
+    #[test]
+    fn test_equal_weights_reproduce_the_voronoi_cells() {
+        let mut points = generate_golden_seeds(640.0, 480.0, 200);
+        // A duplicate and a seed off the canvas
+        points.extend([points[10], points[11], -900.0, 100.0]);
+        let plain = compute_voronoi(&points, 640.0, 480.0).unwrap();
+        for weight in [0.0, 350.0] {
+            let power = compute_power_diagram(&points, &[weight; 202], 640.0, 480.0).unwrap();
+            assert_eq!(power.vertices(), plain.vertices());
+            assert_eq!(power.offsets(), plain.offsets());
+            let dominated = power.dominated();
+            assert_eq!(dominated.iter().map(|&d| d as usize).sum::<usize>(), 1);
+            assert_eq!(dominated[200], 1);
+        }
+    }
+
+    #[test]
+    fn test_weights_move_borders_and_hide_seeds() {
+        // The radical axis of two seeds 200 apart moves w / 400 from the
+        // midpoint towards the lighter one
+        let pair = [100.0, 150.0, 300.0, 150.0];
+        let power = compute_power_diagram(&pair, &[4000.0, 0.0], 400.0, 300.0).unwrap();
+        let area = signed_area2(&cell(&power, 0)) / 2.0;
+        assert!((area - 210.0 * 300.0).abs() < 1e-9);
+
+        // Three heavy seeds around a light one at their centroid hide it
+        let hidden = [100.0, 100.0, 300.0, 100.0, 200.0, 150.0, 200.0, 250.0];
+        let weights = [20000.0, 20000.0, 0.0, 20000.0];
+        let power = compute_power_diagram(&hidden, &weights, 400.0, 300.0).unwrap();
+        assert_eq!(power.dominated(), vec![0, 0, 1, 0]);
+        assert!(cell(&power, 2).is_empty());
+        let first = [200.0, 150.0, 100.0, 100.0, 300.0, 100.0, 200.0, 250.0];
+        let power = compute_power_diagram(&first, &[0.0, 2e4, 2e4, 2e4], 400.0, 300.0).unwrap();
+        assert_eq!(power.dominated(), vec![1, 0, 0, 0]);
+
+        // Random weights still tile the canvas with convex cells
+        let mut rng = Rng::new(4);
+        let points: Vec<f64> =
+            (0..300).flat_map(|_| [rng.range(0.0, 800.0), rng.range(0.0, 600.0)]).collect();
+        let weights: Vec<f64> = (0..300).map(|_| rng.range(0.0, 2500.0)).collect();
+        let power = compute_power_diagram(&points, &weights, 800.0, 600.0).unwrap();
+        let mut total = 0.0;
+        for i in 0..300 {
+            let poly = cell(&power, i);
+            if poly.is_empty() {
+                continue;
+            }
+            assert_simple_ccw(&poly);
+            total += signed_area2(&poly) / 2.0;
+            // The vertex average of each cell is at least as close in
+            // power to its own seed as to any other
+            let own = |p: Point, j: usize| {
+                (p.x - points[2 * j]).powi(2) + (p.y - points[2 * j + 1]).powi(2) - weights[j]
+            };
+            let c = Point::new(
+                poly.iter().map(|p| p.x).sum::<f64>() / poly.len() as f64,
+                poly.iter().map(|p| p.y).sum::<f64>() / poly.len() as f64,
+            );
+            assert!((0..300).all(|j| own(c, i) <= own(c, j) + 1e-6), "cell {i}");
+        }
+        assert!((total - 800.0 * 600.0).abs() < 1e-6 * 800.0 * 600.0);
+        let dominated = power.dominated().iter().filter(|&&d| d == 1).count();
+        assert!(dominated > 0 && dominated < 60, "{dominated} hidden");
+
+        let diagram = |p: &[f64], w: &[f64]| code_of(compute_power_diagram(p, w, 10.0, 10.0));
+        assert_eq!(diagram(&[1.0, 1.0], &[]), ErrorCode::InvalidLength);
+        assert_eq!(diagram(&[1.0, 1.0], &[f64::NAN]), ErrorCode::NonFiniteInput);
+        let flat = compute_power_diagram(&[1.0, 1.0], &[0.0], 0.0, 10.0);
+        assert_eq!(code_of(flat), ErrorCode::InvalidArgument);
+    }
+}
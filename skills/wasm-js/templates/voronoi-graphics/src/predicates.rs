@@ -0,0 +1,471 @@
+//! Geometric predicates shared by the triangulation code
+//!
+//! Every orientation and incircle decision in the crate goes through
+//! these functions so the answers never disagree between modules.
+//! The exports below expose the same arithmetic to JS: `orientation` is
+//! exactly the sign the triangulator branches on, with no epsilon.
+//!
+//! Both predicates are adaptive in the manner of Shewchuk ("Adaptive
+//! Precision Floating-Point Arithmetic and Fast Robust Geometric
+//! Predicates", 1997): the plain f64 determinant is returned whenever its
+//! error bound proves the sign, and otherwise the determinant is redone in
+//! exact expansion arithmetic. The sign is therefore always that of the
+//! exact determinant of the given doubles, so cocircular and collinear
+//! inputs give exactly 0; the magnitude is only approximate.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles};
+use crate::Point;
+
+/// Half an ulp of 1
+const EPSILON: f64 = f64::EPSILON / 2.0;
+/// Relative error bounds of the fast determinants, from the paper
+const ORIENT_BOUND: f64 = (3.0 + 16.0 * EPSILON) * EPSILON;
+const INCIRCLE_BOUND: f64 = (10.0 + 96.0 * EPSILON) * EPSILON;
+#[cfg(feature = "mesh3d")]
+const ORIENT3D_BOUND: f64 = (7.0 + 56.0 * EPSILON) * EPSILON;
+/// 2^27 + 1, for splitting a double into two 26-bit halves
+const SPLITTER: f64 = 134_217_729.0;
+
+/// Twice the signed area of `abc`: positive when counter-clockwise
+#[inline]
+pub(crate) fn orient2d(a: Point, b: Point, c: Point) -> f64 {
+    let left = (a.x - c.x) * (b.y - c.y);
+    let right = (a.y - c.y) * (b.x - c.x);
+    let det = left - right;
+    let bound = ORIENT_BOUND * (left.abs() + right.abs());
+    if det > bound || -det > bound {
+        return det;
+    }
+    orient2d_exact(a, b, c)
+}
+
+/// Positive when `d` lies strictly inside the circumcircle of CCW `abc`
+#[inline]
+pub(crate) fn incircle(a: Point, b: Point, c: Point, d: Point) -> f64 {
+    let adx = a.x - d.x;
+    let ady = a.y - d.y;
+    let bdx = b.x - d.x;
+    let bdy = b.y - d.y;
+    let cdx = c.x - d.x;
+    let cdy = c.y - d.y;
+
+    let (bdxcdy, cdxbdy) = (bdx * cdy, cdx * bdy);
+    let (cdxady, adxcdy) = (cdx * ady, adx * cdy);
+    let (adxbdy, bdxady) = (adx * bdy, bdx * ady);
+    let alift = adx * adx + ady * ady;
+    let blift = bdx * bdx + bdy * bdy;
+    let clift = cdx * cdx + cdy * cdy;
+
+    let det = alift * (bdxcdy - cdxbdy) + blift * (cdxady - adxcdy) + clift * (adxbdy - bdxady);
+    let permanent = (bdxcdy.abs() + cdxbdy.abs()) * alift
+        + (cdxady.abs() + adxcdy.abs()) * blift
+        + (adxbdy.abs() + bdxady.abs()) * clift;
+    let bound = INCIRCLE_BOUND * permanent;
+    if det > bound || -det > bound {
+        return det;
+    }
+    incircle_exact(a, b, c, d)
+}
+
+/// Positive when `d` lies below the plane through `abc`, seen
+/// counter-clockwise from above; the magnitude is six times the volume
+/// of the tetrahedron
+#[cfg(feature = "mesh3d")]
+#[inline]
+pub(crate) fn orient3d(a: [f64; 3], b: [f64; 3], c: [f64; 3], d: [f64; 3]) -> f64 {
+    let [adx, ady, adz] = [0, 1, 2].map(|k| a[k] - d[k]);
+    let [bdx, bdy, bdz] = [0, 1, 2].map(|k| b[k] - d[k]);
+    let [cdx, cdy, cdz] = [0, 1, 2].map(|k| c[k] - d[k]);
+
+    let (bdxcdy, cdxbdy) = (bdx * cdy, cdx * bdy);
+    let (cdxady, adxcdy) = (cdx * ady, adx * cdy);
+    let (adxbdy, bdxady) = (adx * bdy, bdx * ady);
+
+    let det = adz * (bdxcdy - cdxbdy) + bdz * (cdxady - adxcdy) + cdz * (adxbdy - bdxady);
+    let permanent = (bdxcdy.abs() + cdxbdy.abs()) * adz.abs()
+        + (cdxady.abs() + adxcdy.abs()) * bdz.abs()
+        + (adxbdy.abs() + bdxady.abs()) * cdz.abs();
+    let bound = ORIENT3D_BOUND * permanent;
+    if det > bound || -det > bound {
+        return det;
+    }
+    orient3d_exact(a, b, c, d)
+}
+
+/// Power test of a regular triangulation: positive when `d` with weight
+/// `w[3]` lies below the plane through the lifted CCW `abc`, i.e. would
+/// shrink their power cells
+///
+/// `incircle` minus the weight terms, which are plain f64; only weight
+/// differences enter, so equal weights give exactly `incircle`.
+pub(crate) fn power_incircle(a: Point, b: Point, c: Point, d: Point, w: [f64; 4]) -> f64 {
+    let (adx, ady, bdx, bdy) = (a.x - d.x, a.y - d.y, b.x - d.x, b.y - d.y);
+    let (cdx, cdy) = (c.x - d.x, c.y - d.y);
+    let [aw, bw, cw] = [w[0] - w[3], w[1] - w[3], w[2] - w[3]];
+    let lifted = aw * (bdx * cdy - cdx * bdy)
+        + bw * (cdx * ady - adx * cdy)
+        + cw * (adx * bdy - bdx * ady);
+    incircle(a, b, c, d) - lifted
+}
+
+/// Exact sum of nonoverlapping doubles in increasing magnitude, zeros
+/// dropped; empty is 0
+type Expansion = Vec<f64>;
+
+#[inline]
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let bv = x - a;
+    let av = x - bv;
+    (x, (a - av) + (b - bv))
+}
+
+#[inline]
+fn split(a: f64) -> (f64, f64) {
+    let c = SPLITTER * a;
+    let hi = c - (c - a);
+    (hi, a - hi)
+}
+
+#[inline]
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let x = a * b;
+    let (ahi, alo) = split(a);
+    let (bhi, blo) = split(b);
+    let err = x - ahi * bhi - alo * bhi - ahi * blo;
+    (x, alo * blo - err)
+}
+
+/// Add `b` to `e` exactly (Grow-Expansion with zero elimination)
+fn grow(e: &mut Expansion, b: f64) {
+    let mut q = b;
+    let mut k = 0;
+    for i in 0..e.len() {
+        let (sum, tail) = two_sum(q, e[i]);
+        q = sum;
+        if tail != 0.0 {
+            e[k] = tail;
+            k += 1;
+        }
+    }
+    e.truncate(k);
+    if q != 0.0 {
+        e.push(q);
+    }
+}
+
+fn difference(a: f64, b: f64) -> Expansion {
+    let mut e = Vec::with_capacity(2);
+    grow(&mut e, a);
+    grow(&mut e, -b);
+    e
+}
+
+fn product(e: &[f64], f: &[f64]) -> Expansion {
+    let mut out = Vec::new();
+    for &x in e {
+        for &y in f {
+            let (p, err) = two_product(x, y);
+            grow(&mut out, err);
+            grow(&mut out, p);
+        }
+    }
+    out
+}
+
+/// `e * f - g * h`
+fn cross(e: &[f64], f: &[f64], g: &[f64], h: &[f64]) -> Expansion {
+    let mut out = product(e, f);
+    for v in product(g, h) {
+        grow(&mut out, -v);
+    }
+    out
+}
+
+/// Approximate value of `e`, with its exact sign
+fn estimate(e: &[f64]) -> f64 {
+    e.iter().sum()
+}
+
+#[cold]
+fn orient2d_exact(a: Point, b: Point, c: Point) -> f64 {
+    let (acx, acy) = (difference(a.x, c.x), difference(a.y, c.y));
+    let (bcx, bcy) = (difference(b.x, c.x), difference(b.y, c.y));
+    estimate(&cross(&acx, &bcy, &acy, &bcx))
+}
+
+#[cold]
+fn incircle_exact(a: Point, b: Point, c: Point, d: Point) -> f64 {
+    let (adx, ady) = (difference(a.x, d.x), difference(a.y, d.y));
+    let (bdx, bdy) = (difference(b.x, d.x), difference(b.y, d.y));
+    let (cdx, cdy) = (difference(c.x, d.x), difference(c.y, d.y));
+    let lift = |x: &[f64], y: &[f64]| {
+        let mut l = product(x, x);
+        for v in product(y, y) {
+            grow(&mut l, v);
+        }
+        l
+    };
+    let mut det = Vec::new();
+    let terms = [
+        (lift(&adx, &ady), cross(&bdx, &cdy, &cdx, &bdy)),
+        (lift(&bdx, &bdy), cross(&cdx, &ady, &adx, &cdy)),
+        (lift(&cdx, &cdy), cross(&adx, &bdy, &bdx, &ady)),
+    ];
+    for (l, minor) in terms {
+        for v in product(&l, &minor) {
+            grow(&mut det, v);
+        }
+    }
+    estimate(&det)
+}
+
+#[cfg(feature = "mesh3d")]
+#[cold]
+fn orient3d_exact(a: [f64; 3], b: [f64; 3], c: [f64; 3], d: [f64; 3]) -> f64 {
+    let [adx, ady, adz] = [0, 1, 2].map(|k| difference(a[k], d[k]));
+    let [bdx, bdy, bdz] = [0, 1, 2].map(|k| difference(b[k], d[k]));
+    let [cdx, cdy, cdz] = [0, 1, 2].map(|k| difference(c[k], d[k]));
+    let mut det = Vec::new();
+    let terms = [
+        (adz, cross(&bdx, &cdy, &cdx, &bdy)),
+        (bdz, cross(&cdx, &ady, &adx, &cdy)),
+        (cdz, cross(&adx, &bdy, &bdx, &ady)),
+    ];
+    for (z, minor) in terms {
+        for v in product(&z, &minor) {
+            grow(&mut det, v);
+        }
+    }
+    estimate(&det)
+}
+
+/// Twice the signed area of a polygon: positive when counter-clockwise
+pub(crate) fn signed_area2(poly: &[Point]) -> f64 {
+    (0..poly.len())
+        .map(|i| {
+            let (a, b) = (poly[i], poly[(i + 1) % poly.len()]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum()
+}
+
+fn to_points(points_flat: &[f64]) -> Result<Vec<Point>> {
+    check_points(points_flat)?;
+    Ok(points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect())
+}
+
+fn sign(v: f64) -> i32 {
+    if v > 0.0 {
+        1
+    } else if v < 0.0 {
+        -1
+    } else {
+        0
+    }
+}
+
+/// Whether `abc`, of either winding, is degenerate at relative tolerance
+/// `epsilon`: twice its area at most `epsilon` times its longest side
+/// squared
+pub(crate) fn degenerate(a: Point, b: Point, c: Point, epsilon: f64) -> bool {
+    let side2 = |p: Point, q: Point| (p.x - q.x).powi(2) + (p.y - q.y).powi(2);
+    let longest = side2(a, b).max(side2(b, c)).max(side2(c, a));
+    let area2 = orient2d(a, b, c).abs();
+    area2.is_nan() || area2 <= epsilon * longest
+}
+
+fn corners(points: &[Point], t: &[u32]) -> [Point; 3] {
+    [t[0], t[1], t[2]].map(|i| points[i as usize])
+}
+
+fn check_epsilon(epsilon: f64) -> Result<()> {
+    if !(epsilon.is_finite() && epsilon >= 0.0) {
+        return Err(Error::InvalidArgument(format!(
+            "epsilon must be finite and non-negative, got {epsilon}"
+        )));
+    }
+    Ok(())
+}
+
+/// Signed area of the polygon `[x0, y0, x1, y1, ...]`, closed implicitly;
+/// positive when counter-clockwise in a y-up frame
+#[wasm_bindgen]
+pub fn signed_area(polygon: &[f64]) -> Result<f64> {
+    Ok(signed_area2(&to_points(polygon)?) / 2.0)
+}
+
+/// 1 when `abc` turns counter-clockwise, -1 when clockwise and 0 when
+/// collinear (or any input is NaN)
+#[wasm_bindgen]
+pub fn orientation(ax: f64, ay: f64, bx: f64, by: f64, cx: f64, cy: f64) -> i32 {
+    sign(orient2d(Point::new(ax, ay), Point::new(bx, by), Point::new(cx, cy)))
+}
+
+/// Whether triangle `(i0, i1, i2)` of `points` is degenerate
+///
+/// `epsilon` is relative: the triangle is degenerate when twice its area
+/// is at most `epsilon` times its longest side squared, so the answer does
+/// not change with the canvas scale. `epsilon = 0` flags exactly the
+/// triangles `orientation` calls collinear.
+#[wasm_bindgen]
+pub fn is_degenerate_triangle(
+    points: &[f64],
+    i0: u32,
+    i1: u32,
+    i2: u32,
+    epsilon: f64,
+) -> Result<bool> {
+    let points = to_points(points)?;
+    check_triangles(&[i0, i1, i2], points.len())?;
+    check_epsilon(epsilon)?;
+    let [a, b, c] = corners(&points, &[i0, i1, i2]);
+    Ok(degenerate(a, b, c, epsilon))
+}
+
+/// `orientation` of every triangle in a flat index list
+#[wasm_bindgen]
+pub fn triangle_orientations(points: &[f64], triangles: &[u32]) -> Result<Vec<i32>> {
+    let points = to_points(points)?;
+    check_triangles(triangles, points.len())?;
+    Ok(triangles
+        .chunks_exact(3)
+        .map(|t| {
+            let [a, b, c] = corners(&points, t);
+            sign(orient2d(a, b, c))
+        })
+        .collect())
+}
+
+/// Indices of the triangles `is_degenerate_triangle` would flag
+#[wasm_bindgen]
+pub fn degenerate_triangles(points: &[f64], triangles: &[u32], epsilon: f64) -> Result<Vec<u32>> {
+    let points = to_points(points)?;
+    check_triangles(triangles, points.len())?;
+    check_epsilon(epsilon)?;
+    Ok(triangles
+        .chunks_exact(3)
+        .enumerate()
+        .filter(|(_, t)| {
+            let [a, b, c] = corners(&points, t);
+            degenerate(a, b, c, epsilon)
+        })
+        .map(|(i, _)| i as u32)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_delaunay;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_exports_agree_with_the_triangulator() {
+        let points = [0.0, 0.0, 4.0, 0.0, 4.0, 3.0, 0.0, 3.0, 2.0, 1.5];
+        let triangles = compute_delaunay(&points, 4.0, 3.0);
+        let orientations = triangle_orientations(&points, &triangles).unwrap();
+        assert_eq!(orientations, vec![1; triangles.len() / 3]);
+        assert!(degenerate_triangles(&points, &triangles, 0.0).unwrap().is_empty());
+
+        assert_eq!(orientation(0.0, 0.0, 1.0, 0.0, 0.0, 1.0), 1);
+        assert_eq!(orientation(0.0, 0.0, 0.0, 1.0, 1.0, 0.0), -1);
+        assert_eq!(orientation(0.0, 0.0, 1.0, 1.0, 2.0, 2.0), 0);
+        assert_eq!(orientation(0.0, 0.0, 1.0, f64::NAN, 2.0, 2.0), 0);
+        assert_eq!(signed_area(&points[..8]).unwrap(), 12.0);
+        let clockwise = [0.0, 0.0, 0.0, 3.0, 4.0, 3.0, 4.0, 0.0];
+        assert_eq!(signed_area(&clockwise).unwrap(), -12.0);
+    }
+
+    #[test]
+    fn test_cocircular_and_collinear_inputs_are_exactly_zero() {
+        let p = Point::new;
+        // Square corners, shifted so the plain determinant loses every bit
+        let o = 1e9 + 0.5;
+        let [a, b, c, d] = [p(o, o), p(o + 1.0, o), p(o + 1.0, o + 1.0), p(o, o + 1.0)];
+        assert_eq!(incircle(a, b, c, d), 0.0);
+        assert_eq!(incircle(b, c, d, a), 0.0);
+        assert_eq!(orient2d(p(0.1, 0.3), p(0.1, 0.7), p(0.1, 1e6)), 0.0);
+        assert_eq!(orient2d(a, c, p(o + 2.0, o + 2.0)), 0.0);
+        // One ulp towards the center is inside, one ulp away is outside
+        let inside = p(o, o + 1.0 - 2f64.powi(-23));
+        let outside = p(o, o + 1.0 + 2f64.powi(-23));
+        assert!(incircle(a, b, c, inside) > 0.0);
+        assert!(incircle(a, b, c, outside) < 0.0);
+    }
+
+    #[cfg(feature = "mesh3d")]
+    #[test]
+    fn test_orient3d_signs_and_coplanar_points() {
+        let (a, b, c) = ([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+        assert!(orient3d(a, b, c, [0.3, 0.3, -1.0]) > 0.0);
+        assert!(orient3d(a, b, c, [0.3, 0.3, 1.0]) < 0.0);
+        assert!(orient3d(b, a, c, [0.3, 0.3, 1.0]) > 0.0);
+        // Far from the origin the fast determinant cannot decide
+        let o = 1e9 + 0.5;
+        let shift = |p: [f64; 3]| p.map(|v| v + o);
+        let (a, b, c) = (shift(a), shift(b), shift([0.0, 0.0, 1.0]));
+        assert_eq!(orient3d(a, b, c, shift([0.5, 0.0, 0.5])), 0.0);
+        assert!(orient3d(a, b, c, shift([0.5, 2f64.powi(-20), 0.5])) > 0.0);
+        assert!(orient3d(a, b, c, shift([0.5, -(2f64.powi(-20)), 0.5])) < 0.0);
+    }
+
+    #[test]
+    fn test_orientation_near_a_line_follows_the_ulps() {
+        // Shewchuk's example: a within a few ulps of the line through b
+        // and c, on whichever side its coordinates put it
+        let ulp = f64::EPSILON / 2.0;
+        let (b, c) = (Point::new(12.0, 12.0), Point::new(24.0, 24.0));
+        for i in 0..32 {
+            for j in 0..32 {
+                let a = Point::new(0.5 + i as f64 * ulp, 0.5 + j as f64 * ulp);
+                let expected = sign((j - i) as f64);
+                assert_eq!(sign(orient2d(a, b, c)), expected, "{i}, {j}");
+                assert_eq!(sign(orient2d(b, c, a)), expected, "{i}, {j}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_grid_triangulates_without_slivers() {
+        // Every quad of a lattice is cocircular
+        let n = 12;
+        let points: Vec<f64> =
+            (0..n * n).flat_map(|i| [(i % n) as f64 * 0.1, (i / n) as f64 * 0.1]).collect();
+        let triangles = compute_delaunay(&points, 1.1, 1.1);
+        assert!(degenerate_triangles(&points, &triangles, 0.0).unwrap().is_empty());
+        let corners_of = to_points(&points).unwrap();
+        let area: f64 = triangles
+            .chunks(3)
+            .map(|t| {
+                let [a, b, c] = corners(&corners_of, t);
+                orient2d(a, b, c) / 2.0
+            })
+            .sum();
+        assert!((area - 1.21).abs() < 1e-9, "{area}");
+        assert_eq!(triangles.len() / 3, 2 * (n - 1) * (n - 1));
+    }
+
+    #[test]
+    fn test_degenerate_tolerance_is_scale_free() {
+        // A sliver of height 0.01 over a base of 10, and the same at 1000x
+        for s in [1.0, 1000.0] {
+            let pts = [0.0, 0.0, 10.0 * s, 0.0, 5.0 * s, 0.01 * s];
+            assert!(!is_degenerate_triangle(&pts, 0, 1, 2, 0.0).unwrap());
+            assert!(!is_degenerate_triangle(&pts, 0, 1, 2, 1e-4).unwrap());
+            assert!(is_degenerate_triangle(&pts, 0, 1, 2, 1e-2).unwrap());
+        }
+        let pts = [0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 0.0, 1.0];
+        assert!(is_degenerate_triangle(&pts, 0, 1, 2, 0.0).unwrap());
+        assert_eq!(degenerate_triangles(&pts, &[0, 1, 3, 0, 1, 2], 0.0).unwrap(), vec![1]);
+
+        let code = |i2: u32, epsilon: f64| code_of(is_degenerate_triangle(&pts, 0, 1, i2, epsilon));
+        assert_eq!(code(4, 0.0), ErrorCode::IndexOutOfRange);
+        assert_eq!(code(2, -1.0), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(triangle_orientations(&pts, &[0, 1])), ErrorCode::InvalidLength);
+        assert_eq!(code_of(signed_area(&pts[..3])), ErrorCode::InvalidLength);
+    }
+}
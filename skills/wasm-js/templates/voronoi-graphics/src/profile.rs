@@ -0,0 +1,79 @@
+//! Per-phase wall time inside the heavy exports, for `take_profile`
+//!
+//! Built only with the `profiling` feature. The `profiled!` macro in the
+//! crate root brackets each phase with `now_ms` readings and adds the
+//! difference here under the phase's name; without the feature it expands
+//! to the bare expression, so release builds pay nothing. Phases:
+//!
+//! | phase       | where                                                   |
+//! |-------------|---------------------------------------------------------|
+//! | `seeds`     | `generate_golden_seeds`                                 |
+//! | `insertion` | the point insertion loop of every Delaunay build        |
+//! | `compact`   | dropping the super triangle's triangles after insertion |
+//! | `adjacency` | unique edges and the neighbour table of a triangulation |
+//! | `boundary`  | `compute_boundary_edges`                                |
+//! | `edges`     | edge extraction in `compute_edges` and its variants     |
+//! | `motes`     | the `update_motes` family                               |
+
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+    /// Milliseconds per phase since the last `take_profile`, in the order
+    /// the phases first ran
+    static PHASES: RefCell<Vec<(&'static str, f64)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Add `ms` to `phase`'s total
+pub(crate) fn record(phase: &'static str, ms: f64) {
+    PHASES.with(|p| {
+        let mut phases = p.borrow_mut();
+        match phases.iter_mut().find(|(name, _)| *name == phase) {
+            Some((_, total)) => *total += ms,
+            None => phases.push((phase, ms)),
+        }
+    });
+}
+
+/// Totals so far, emptying the accumulator
+pub(crate) fn take_phases() -> Vec<(&'static str, f64)> {
+    PHASES.with(|p| std::mem::take(&mut *p.borrow_mut()))
+}
+
+/// Milliseconds spent in each phase since the last call, as an object
+/// from phase name to total, e.g. `{ insertion: 4.1, edges: 0.6 }`;
+/// phases that have not run are absent. Resets every total.
+#[wasm_bindgen]
+pub fn take_profile() -> JsValue {
+    let profile = js_sys::Object::new();
+    for (phase, ms) in take_phases() {
+        // Setting a plain property on a fresh object cannot fail
+        let _ = js_sys::Reflect::set(&profile, &phase.into(), &ms.into());
+    }
+    profile.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_delaunay, compute_edges, generate_golden_seeds};
+
+    #[test]
+    fn test_phases_accumulate_and_reset() {
+        take_phases();
+        let seeds = generate_golden_seeds(400.0, 300.0, 2000);
+        let triangles = compute_delaunay(&seeds, 400.0, 300.0);
+        compute_edges(&seeds, &triangles).unwrap();
+        compute_edges(&seeds, &triangles).unwrap();
+        let phases = take_phases();
+        let names: Vec<&str> = phases.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["seeds", "insertion", "compact", "adjacency", "edges"]);
+        assert!(phases.iter().all(|(_, ms)| *ms >= 0.0));
+        assert!(take_phases().is_empty());
+
+        record("seeds", 1.5);
+        record("seeds", 2.0);
+        assert_eq!(take_phases(), [("seeds", 3.5)]);
+    }
+}
@@ -0,0 +1,194 @@
+//! Delaunay triangulation spread across calls under a time budget
+//!
+//! `ProgressiveDelaunay` inserts seeds in the Hilbert order
+//! `compute_delaunay` uses, as many per `step` as fit in the budget, so a
+//! large set can build over a few animation frames instead of stalling
+//! one. Between steps `triangles` is the Delaunay triangulation of the
+//! seeds inserted so far; once all are in it is `compute_delaunay`'s
+//! output, triangle for triangle.
+
+use wasm_bindgen::prelude::*;
+
+use crate::dynamic::flat_to_points;
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::mesh::{hilbert_order, Mesh, NONE, SUPER};
+use crate::timing::now_ms;
+
+/// Insertions between clock readings in `step`
+const CLOCK_STRIDE: usize = 256;
+
+/// Incremental Delaunay triangulation that can stop and go on later
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ProgressiveDelaunay {
+    mesh: Mesh,
+    /// Input indices in insertion order
+    order: Vec<u32>,
+    /// How much of `order` has been inserted
+    next: usize,
+}
+
+#[wasm_bindgen]
+impl ProgressiveDelaunay {
+    /// Nothing inserted yet; as in `compute_delaunay`, the super triangle
+    /// is sized from the seeds and the canvas size is unused
+    #[wasm_bindgen(constructor)]
+    pub fn new(points_flat: &[f64], width: f64, height: f64) -> Result<ProgressiveDelaunay> {
+        let mut progressive = ProgressiveDelaunay {
+            mesh: Mesh::with_super(Mesh::enclosing(&[], None)),
+            order: Vec::new(),
+            next: 0,
+        };
+        progressive.restart(points_flat, width, height)?;
+        Ok(progressive)
+    }
+
+    /// Start over on new seeds, reusing the mesh buffers
+    ///
+    /// On error the current triangulation is left as it was.
+    pub fn restart(&mut self, points_flat: &[f64], _width: f64, _height: f64) -> Result<()> {
+        check_points(points_flat)?;
+        if points_flat.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+        }
+        let points = flat_to_points(points_flat);
+        let mesh = &mut self.mesh;
+        mesh.reset(Mesh::enclosing(&points, None));
+        mesh.points.extend_from_slice(&points);
+        mesh.vert_tri.resize(mesh.points.len(), NONE);
+        mesh.tris.reserve(points.len() * 2);
+        self.order = hilbert_order(&points);
+        self.next = 0;
+        Ok(())
+    }
+
+    /// Insert seeds for about `budget_ms` milliseconds; true once every
+    /// seed is in
+    ///
+    /// The clock is read every 256 insertions and each call makes at least
+    /// that many, so a call always makes progress and can overrun the
+    /// budget by one batch. An infinite budget finishes in one call.
+    pub fn step(&mut self, budget_ms: f64) -> Result<bool> {
+        if budget_ms.is_nan() || budget_ms < 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "budget must be non-negative, got {budget_ms}"
+            )));
+        }
+        let start = now_ms();
+        while !self.step_points(CLOCK_STRIDE) && now_ms() - start < budget_ms {}
+        Ok(self.is_finished())
+    }
+
+    /// Insert up to `count` more seeds; true once every seed is in
+    ///
+    /// A budget in insertions rather than time, for callers that want
+    /// the same split on every device.
+    pub fn step_points(&mut self, count: usize) -> bool {
+        let end = self.order.len().min(self.next.saturating_add(count));
+        for &v in &self.order[self.next..end] {
+            self.mesh.insert_vertex(v + SUPER);
+        }
+        if self.next < end && end == self.order.len() {
+            // Only the finished mesh has to match `compute_delaunay`
+            self.mesh.close_hull();
+        }
+        self.next = end;
+        self.is_finished()
+    }
+
+    /// `[a0, b0, c0, a1, ...]` triangles of the seeds inserted so far,
+    /// counter-clockwise and indexing the full input
+    pub fn triangles(&self) -> Vec<u32> {
+        self.mesh.real_triangles().flatten().collect()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next == self.order.len()
+    }
+
+    pub fn inserted_count(&self) -> usize {
+        self.next
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.order.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+    use crate::{compute_delaunay, generate_golden_seeds, validate_delaunay};
+
+    fn scattered(n: usize, seed: u64) -> Vec<f64> {
+        let mut rng = Rng::new(seed);
+        (0..n).flat_map(|_| [rng.range(0.0, 640.0), rng.range(0.0, 480.0)]).collect()
+    }
+
+    #[test]
+    fn test_partial_triangulations_are_delaunay() {
+        let seeds = scattered(1500, 3);
+        let mut progressive = ProgressiveDelaunay::new(&seeds, 640.0, 480.0).unwrap();
+        assert_eq!((progressive.point_count(), progressive.inserted_count()), (1500, 0));
+        assert!(progressive.triangles().is_empty());
+        let mut steps = 0;
+        while !progressive.step_points(100) {
+            steps += 1;
+            let triangles = progressive.triangles();
+            let mut inserted = vec![false; 1500];
+            for &v in &progressive.order[..progressive.inserted_count()] {
+                inserted[v as usize] = true;
+            }
+            assert!(triangles.iter().all(|&v| inserted[v as usize]));
+            assert!(triangles.len() / 3 >= progressive.inserted_count() - 2);
+            assert!(validate_delaunay(&seeds, &triangles).unwrap(), "step {steps}");
+        }
+        assert_eq!(steps, 14);
+        assert_eq!(progressive.triangles(), compute_delaunay(&seeds, 640.0, 480.0));
+        assert!(progressive.step_points(100) && progressive.step(0.0).unwrap());
+    }
+
+    #[test]
+    fn test_step_budget_and_restart() {
+        let seeds = generate_golden_seeds(800.0, 600.0, 3000);
+        let mut progressive = ProgressiveDelaunay::new(&seeds, 800.0, 600.0).unwrap();
+        // A zero budget still inserts one batch
+        assert!(!progressive.step(0.0).unwrap());
+        assert_eq!(progressive.inserted_count(), CLOCK_STRIDE);
+        assert!(progressive.step(f64::INFINITY).unwrap());
+        assert_eq!(progressive.triangles(), compute_delaunay(&seeds, 800.0, 600.0));
+
+        // Restarting midway matches a fresh triangulation of the new seeds
+        let other = scattered(700, 9);
+        progressive.restart(&other, 640.0, 480.0).unwrap();
+        assert_eq!((progressive.point_count(), progressive.inserted_count()), (700, 0));
+        progressive.step_points(300);
+        progressive.restart(&other, 640.0, 480.0).unwrap();
+        while !progressive.step(1.0).unwrap() {}
+        assert_eq!(progressive.triangles(), compute_delaunay(&other, 640.0, 480.0));
+
+        // Empty input is finished from the start
+        let mut empty = ProgressiveDelaunay::new(&[], 10.0, 10.0).unwrap();
+        assert!(empty.is_finished() && empty.step(5.0).unwrap());
+        assert!(empty.triangles().is_empty());
+    }
+
+    #[test]
+    fn test_progressive_errors() {
+        let seeds = [1.0, 2.0, 5.0, 3.0, 2.0, 6.0];
+        let odd = ProgressiveDelaunay::new(&seeds[..5], 8.0, 8.0);
+        assert_eq!(code_of(odd), ErrorCode::InvalidLength);
+        let mut progressive = ProgressiveDelaunay::new(&seeds, 8.0, 8.0).unwrap();
+        assert_eq!(code_of(progressive.step(-1.0)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(progressive.step(f64::NAN)), ErrorCode::InvalidArgument);
+        progressive.step_points(3);
+        // A rejected restart keeps the finished triangle
+        let bad = progressive.restart(&[0.0, f64::INFINITY], 8.0, 8.0);
+        assert_eq!(code_of(bad), ErrorCode::NonFiniteInput);
+        assert_eq!(progressive.triangles().len(), 3);
+        assert!(progressive.is_finished());
+    }
+}
@@ -0,0 +1,205 @@
+//! Frame-budget governor that trades scene fidelity for speed
+//!
+//! Tiers, each adding to the previous one's savings:
+//!
+//! | tier | degradation                                                   |
+//! |------|---------------------------------------------------------------|
+//! | 0    | none                                                          |
+//! | 1    | motes step every other frame, by the time of both frames      |
+//! | 2    | seeds are displaced but the triangulation is not repaired, so |
+//! |      | edges keep their endpoints until the tier drops back          |
+//! | 3    | the drift field is sampled every other frame                  |
+//!
+//! The governor keeps an exponential moving average of the measured frame
+//! cost. It degrades one tier while the average exceeds the budget and
+//! recovers one tier once it falls below `RECOVER_FRACTION` of it; after
+//! each change it holds the tier for `HOLD_FRAMES` frames so the average
+//! can settle on the new cost. The gap between the two thresholds and the
+//! hold keep it from flipping between neighbouring tiers every frame.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+
+/// Number of quality tiers; valid tiers are `0..QUALITY_TIERS`
+pub const QUALITY_TIERS: u32 = 4;
+
+/// Weight of the newest sample in the moving average
+const SMOOTHING: f64 = 0.2;
+/// Fraction of the budget the average must drop below to recover a tier
+const RECOVER_FRACTION: f64 = 0.6;
+/// Frames a tier is held after each change
+const HOLD_FRAMES: u32 = 30;
+
+/// Picks a quality tier from measured frame times
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QualityGovernor {
+    /// Target cost per frame in milliseconds, 0 while automation is off
+    pub(crate) budget_ms: f64,
+    /// Smoothed frame cost, NaN before the first sample
+    pub(crate) average_ms: f64,
+    pub(crate) tier: u32,
+    pub(crate) locked: bool,
+    /// Frames left before the tier may change again
+    pub(crate) hold: u32,
+    /// Pending `[from, to, ...]` tier changes
+    pub(crate) events: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl QualityGovernor {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> QualityGovernor {
+        QualityGovernor { average_ms: f64::NAN, ..QualityGovernor::default() }
+    }
+
+    /// Target cost per frame in milliseconds; 0 turns automation off and
+    /// returns to tier 0 unless a tier is locked
+    pub fn set_budget_ms(&mut self, ms: f64) -> Result<()> {
+        if !ms.is_finite() {
+            return Err(Error::NonFiniteInput(format!("budget must be finite, got {ms}")));
+        }
+        if ms < 0.0 {
+            return Err(Error::InvalidArgument(format!("budget must be non-negative, got {ms}")));
+        }
+        self.budget_ms = ms;
+        self.average_ms = f64::NAN;
+        self.hold = 0;
+        if ms == 0.0 && !self.locked {
+            self.switch(0);
+        }
+        Ok(())
+    }
+
+    /// Feed the cost of one frame; returns whether the tier changed
+    pub fn record(&mut self, frame_ms: f64) -> bool {
+        if self.locked || self.budget_ms == 0.0 || !frame_ms.is_finite() {
+            return false;
+        }
+        self.average_ms = if self.average_ms.is_nan() {
+            frame_ms
+        } else {
+            self.average_ms + SMOOTHING * (frame_ms - self.average_ms)
+        };
+        if self.hold > 0 {
+            self.hold -= 1;
+            return false;
+        }
+        let tier = if self.average_ms > self.budget_ms {
+            (self.tier + 1).min(QUALITY_TIERS - 1)
+        } else if self.average_ms < self.budget_ms * RECOVER_FRACTION {
+            self.tier.saturating_sub(1)
+        } else {
+            self.tier
+        };
+        if tier == self.tier {
+            return false;
+        }
+        self.switch(tier);
+        self.hold = HOLD_FRAMES;
+        true
+    }
+
+    /// Pin `tier`, bypassing the budget until `unlock_tier`
+    pub fn lock_tier(&mut self, tier: u32) -> Result<()> {
+        if tier >= QUALITY_TIERS {
+            return Err(Error::IndexOutOfRange(format!(
+                "tier {tier} out of range, expected below {QUALITY_TIERS}"
+            )));
+        }
+        self.locked = true;
+        self.switch(tier);
+        Ok(())
+    }
+
+    /// Hand the tier back to the automation, starting from the locked one
+    pub fn unlock_tier(&mut self) {
+        self.locked = false;
+        self.average_ms = f64::NAN;
+        self.hold = HOLD_FRAMES;
+        if self.budget_ms == 0.0 {
+            self.switch(0);
+        }
+    }
+
+    pub fn current_tier(&self) -> u32 {
+        self.tier
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn budget_ms(&self) -> f64 {
+        self.budget_ms
+    }
+
+    /// Smoothed frame cost in milliseconds, NaN before any sample
+    pub fn average_ms(&self) -> f64 {
+        self.average_ms
+    }
+
+    /// Drain `[from, to]` pairs of tier changes since the previous call,
+    /// automatic and manual alike
+    pub fn tier_events(&mut self) -> Vec<u32> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl QualityGovernor {
+    fn switch(&mut self, tier: u32) {
+        if tier != self.tier {
+            self.events.extend_from_slice(&[self.tier, tier]);
+            self.tier = tier;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_degrades_and_recovers_with_hysteresis() {
+        let mut g = QualityGovernor::new();
+        assert!(!g.record(100.0));
+        g.set_budget_ms(4.0).unwrap();
+
+        // Over budget: one tier at a time, with a hold in between
+        let changes = (0..200).filter(|_| g.record(6.0)).count();
+        assert_eq!((changes, g.current_tier()), (3, 3));
+        assert_eq!(g.tier_events(), vec![0, 1, 1, 2, 2, 3]);
+
+        // Inside the band between the thresholds nothing moves
+        assert_eq!((0..200).filter(|_| g.record(3.0)).count(), 0);
+        // Alternating costs around the budget average out without flapping
+        let flips = (0..200).filter(|i| g.record(if i % 2 == 0 { 5.0 } else { 1.0 })).count();
+        assert_eq!(flips, 0);
+
+        assert_eq!((0..200).filter(|_| g.record(1.0)).count(), 3);
+        assert_eq!(g.current_tier(), 0);
+        assert_eq!(g.tier_events(), vec![3, 2, 2, 1, 1, 0]);
+    }
+
+    #[test]
+    fn test_lock_bypasses_automation() {
+        let mut g = QualityGovernor::new();
+        g.set_budget_ms(4.0).unwrap();
+        g.lock_tier(2).unwrap();
+        assert!((0..100).all(|_| !g.record(50.0)));
+        assert_eq!(g.current_tier(), 2);
+        g.unlock_tier();
+        assert_eq!(g.current_tier(), 2);
+        assert_eq!(g.tier_events(), vec![0, 2]);
+        assert!((0..100).any(|_| g.record(50.0)));
+        assert_eq!(g.current_tier(), 3);
+
+        assert_eq!(code_of(g.lock_tier(QUALITY_TIERS)), ErrorCode::IndexOutOfRange);
+        assert_eq!(code_of(g.set_budget_ms(f64::NAN)), ErrorCode::NonFiniteInput);
+        assert_eq!(code_of(g.set_budget_ms(-1.0)), ErrorCode::InvalidArgument);
+        g.set_budget_ms(0.0).unwrap();
+        assert_eq!(g.current_tier(), 0);
+    }
+}
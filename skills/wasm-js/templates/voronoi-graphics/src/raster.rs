@@ -0,0 +1,291 @@
+//! Nearest-seed rasterization of Voronoi cells into RGBA pixels, or into
+//! a grid of owning seed indices for effects that do their own shading
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, Csr};
+use crate::mesh::{Mesh, SUPER};
+use crate::spatial::PointGrid;
+use crate::Point;
+
+/// Pixels `rasterize_voronoi` will fill in one call
+const MAX_PIXELS: u64 = 1 << 26;
+
+/// Paint each pixel of a `width × height` canvas in its cell's color
+///
+/// `colors` holds one RGBA quadruple per seed; the result is row-major
+/// RGBA, ready for `ImageData`. A pixel belongs to the seed nearest its
+/// centre (ties to the lower index). With `antialias` set, a pixel within
+/// half a pixel of a cell border is blended with the neighbouring cell
+/// by the border's analytic coverage of the pixel: `0.5 + d` for the
+/// pixel's own cell, where `d` is the centre's distance to the bisector.
+/// The neighbour is the one whose bisector is closest, which is the
+/// second-nearest seed, so in corners where three cells meet the two
+/// nearest cells are blended.
+#[wasm_bindgen]
+pub fn rasterize_voronoi(
+    points: &[f64],
+    colors: &[u8],
+    width: u32,
+    height: u32,
+    antialias: bool,
+) -> Result<Vec<u8>> {
+    let n = check_points(points)?;
+    if colors.len() != n * 4 {
+        return Err(Error::InvalidLength(format!(
+            "{n} seeds need {} color bytes, got {}",
+            n * 4,
+            colors.len()
+        )));
+    }
+    if width == 0 || height == 0 {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return Err(Error::TooLarge(format!("a {width}x{height} raster is too large")));
+    }
+    if points.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let mut out = vec![0u8; width as usize * height as usize * 4];
+    if n == 0 {
+        return Ok(out);
+    }
+
+    let seeds: Vec<Point> = points.chunks_exact(2).map(|c| Point { x: c[0], y: c[1] }).collect();
+    let bounds = [0.0, 0.0, width as f64, height as f64];
+    let grid = PointGrid::new(&seeds, bounds);
+    // The second-nearest seed of any point is a Delaunay neighbour of the
+    // nearest one
+    let csr = antialias.then(|| {
+        let edges: Vec<(u32, u32)> = Mesh::build(&seeds, Some(bounds))
+            .real_edges()
+            .into_iter()
+            .map(|(a, b)| (a - SUPER, b - SUPER))
+            .collect();
+        Csr::from_edges(n, &edges)
+    });
+    let color = |s: u32| &colors[s as usize * 4..s as usize * 4 + 4];
+
+    for (i, px) in out.chunks_exact_mut(4).enumerate() {
+        let q = Point {
+            x: (i % width as usize) as f64 + 0.5,
+            y: (i / width as usize) as f64 + 0.5,
+        };
+        let Some(a) = grid.nearest(q) else { continue };
+        px.copy_from_slice(color(a));
+        let Some(csr) = &csr else { continue };
+
+        let pa = seeds[a as usize];
+        let da = (q.x - pa.x).powi(2) + (q.y - pa.y).powi(2);
+        let mut border: Option<(f64, u32)> = None;
+        for &b in csr.neighbors(a) {
+            let pb = seeds[b as usize];
+            let span = ((pb.x - pa.x).powi(2) + (pb.y - pa.y).powi(2)).sqrt();
+            if span == 0.0 {
+                continue;
+            }
+            let db = (q.x - pb.x).powi(2) + (q.y - pb.y).powi(2);
+            let d = (db - da) / (2.0 * span);
+            if border.is_none_or(|(best, _)| d < best) {
+                border = Some((d, b));
+            }
+        }
+        if let Some((d, b)) = border.filter(|&(d, _)| d < 0.5) {
+            let own = 0.5 + d.max(0.0);
+            for (c, (&ca, &cb)) in px.iter_mut().zip(color(a).iter().zip(color(b))) {
+                *c = (ca as f64 * own + cb as f64 * (1.0 - own)).round() as u8;
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Seed index and distance for every cell of a `rasterize_cell_distances`
+/// grid
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct CellRaster {
+    owners: Vec<u32>,
+    distances: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl CellRaster {
+    /// Row-major index of the seed owning each grid cell
+    pub fn owners(&self) -> Vec<u32> {
+        self.owners.clone()
+    }
+
+    /// Row-major canvas distance from each grid cell's centre to its owner
+    pub fn distances(&self) -> Vec<f32> {
+        self.distances.clone()
+    }
+}
+
+/// Nearest seed to each grid cell's centre, in canvas coordinates, with
+/// the distance when `distances` is given
+fn cell_owners(
+    points_flat: &[f64],
+    grid_w: u32,
+    grid_h: u32,
+    width: f64,
+    height: f64,
+    mut distances: Option<&mut Vec<f32>>,
+) -> Result<Vec<u32>> {
+    check_points(points_flat)?;
+    if points_flat.is_empty() {
+        return Err(Error::InvalidArgument("no seeds to rasterize".into()));
+    }
+    if grid_w == 0 || grid_h == 0 {
+        return Err(Error::InvalidArgument("grid width and height must be positive".into()));
+    }
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if grid_w as u64 * grid_h as u64 > MAX_PIXELS {
+        return Err(Error::TooLarge(format!("a {grid_w}x{grid_h} grid is too large")));
+    }
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let seeds: Vec<Point> = points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+    let grid = PointGrid::new(&seeds, [0.0, 0.0, width, height]);
+    let (sx, sy) = (width / grid_w as f64, height / grid_h as f64);
+    let mut owners = Vec::with_capacity(grid_w as usize * grid_h as usize);
+    for r in 0..grid_h {
+        let y = (r as f64 + 0.5) * sy;
+        for c in 0..grid_w {
+            let q = Point::new((c as f64 + 0.5) * sx, y);
+            // Never empty, so never the fallback
+            let owner = grid.nearest(q).unwrap_or(0);
+            owners.push(owner);
+            if let Some(d) = distances.as_deref_mut() {
+                let s = seeds[owner as usize];
+                d.push((q.x - s.x).hypot(q.y - s.y) as f32);
+            }
+        }
+    }
+    Ok(owners)
+}
+
+/// Row-major index of the seed whose cell holds each cell of a
+/// `grid_w × grid_h` grid stretched over the `width × height` canvas
+///
+/// A grid cell belongs to the seed nearest its centre, ties to the lower
+/// index; seeds off the canvas still claim the cells nearest them. Seeds
+/// are bucketed, so a call costs about one small neighbourhood scan per
+/// grid cell rather than one per seed.
+#[wasm_bindgen]
+pub fn rasterize_cells(
+    points_flat: &[f64],
+    grid_w: u32,
+    grid_h: u32,
+    width: f64,
+    height: f64,
+) -> Result<Vec<u32>> {
+    cell_owners(points_flat, grid_w, grid_h, width, height, None)
+}
+
+/// `rasterize_cells` along with each grid cell's distance to its seed,
+/// for darkening towards cell edges
+#[wasm_bindgen]
+pub fn rasterize_cell_distances(
+    points_flat: &[f64],
+    grid_w: u32,
+    grid_h: u32,
+    width: f64,
+    height: f64,
+) -> Result<CellRaster> {
+    let mut distances = Vec::with_capacity(grid_w as usize * grid_h as usize);
+    let owners = cell_owners(points_flat, grid_w, grid_h, width, height, Some(&mut distances))?;
+    Ok(CellRaster { owners, distances })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    const BLACK_WHITE: [u8; 8] = [0, 0, 0, 255, 255, 255, 255, 255];
+
+    #[test]
+    fn test_antialiased_border_has_intermediate_pixels() {
+        // Tilted straight border between two cells across a 64x32 canvas
+        let seeds = [10.0, 12.0, 50.0, 20.0];
+        let (w, h) = (64u32, 32u32);
+        let jumps = |antialias: bool| {
+            let img = rasterize_voronoi(&seeds, &BLACK_WHITE, w, h, antialias).unwrap();
+            let mut count = 0;
+            for row in img.chunks_exact(w as usize * 4) {
+                for pair in row.chunks_exact(4).collect::<Vec<_>>().windows(2) {
+                    count += (pair[0][0] as i32 - pair[1][0] as i32).abs().eq(&255) as usize;
+                }
+            }
+            count
+        };
+        assert_eq!(jumps(true), 0);
+        assert_eq!(jumps(false), h as usize);
+
+        // Vertical border at x = 30.25: pixel 30 is three quarters white
+        let img = rasterize_voronoi(&[20.0, 5.0, 40.5, 5.0], &BLACK_WHITE, w, h, true).unwrap();
+        let px = |x: usize| img[x * 4];
+        assert_eq!((px(29), px(30), px(31)), (0, 191, 255));
+    }
+
+    #[test]
+    fn test_three_cell_corner_and_errors() {
+        let seeds = [10.0, 10.0, 30.0, 10.0, 20.0, 27.3];
+        let colors = [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255];
+        let img = rasterize_voronoi(&seeds, &colors, 40, 40, true).unwrap();
+        assert!(img.chunks(4).all(|p| p[3] == 255));
+        // Every pixel is a blend of at most two cells
+        assert!(img.chunks(4).all(|p| p[..3].iter().filter(|&&c| c > 0).count() <= 2));
+
+        let raster = |p: &[f64], c: &[u8], w: u32| code_of(rasterize_voronoi(p, c, w, 4, true));
+        assert_eq!(raster(&seeds, &colors[..8], 4), ErrorCode::InvalidLength);
+        assert_eq!(raster(&seeds, &colors, 0), ErrorCode::InvalidArgument);
+        assert_eq!(raster(&seeds, &colors, u32::MAX), ErrorCode::TooLarge);
+        assert_eq!(raster(&[f64::NAN, 0.0], &colors[..4], 4), ErrorCode::NonFiniteInput);
+    }
+
+    #[test]
+    fn test_cell_grid_matches_nearest_seed() {
+        let mut rng = crate::rng::Rng::new(5);
+        // Some seeds well off the 300x200 canvas, and one repeated
+        let mut seeds: Vec<f64> =
+            (0..120).flat_map(|_| [rng.range(-60.0, 360.0), rng.range(-40.0, 240.0)]).collect();
+        seeds.extend_from_within(10..12);
+        let (gw, gh) = (48u32, 30u32);
+        let owners = rasterize_cells(&seeds, gw, gh, 300.0, 200.0).unwrap();
+        let raster = rasterize_cell_distances(&seeds, gw, gh, 300.0, 200.0).unwrap();
+        assert_eq!(raster.owners(), owners);
+        assert_eq!(owners.len(), (gw * gh) as usize);
+        for (i, (&owner, &d)) in owners.iter().zip(&raster.distances()).enumerate() {
+            let x = (i % gw as usize) as f64 * 6.25 + 3.125;
+            let y = (i / gw as usize) as f64 * 200.0 / 30.0 + 100.0 / 30.0;
+            assert_eq!(owner, crate::nearest_seed(&seeds, x, y).unwrap());
+            let s = &seeds[owner as usize * 2..];
+            assert_eq!(d, (x - s[0]).hypot(y - s[1]) as f32);
+        }
+        // Ties go to the lower index: all three seeds are as near the
+        // middle centre, and the last two as near the left one
+        let tied = rasterize_cells(&[2.5, 1.5, 0.5, 1.5, 0.5, -0.5], 3, 1, 3.0, 1.0).unwrap();
+        assert_eq!(tied, vec![1, 0, 0]);
+        assert_eq!(rasterize_cells(&[9.0, 9.0, 9.0, 9.0], 2, 2, 1.0, 1.0).unwrap(), [0; 4]);
+    }
+
+    #[test]
+    fn test_cell_grid_errors() {
+        let cells = |p: &[f64], gw: u32, w: f64| code_of(rasterize_cells(p, gw, 4, w, 10.0));
+        assert_eq!(cells(&[1.0, 2.0, 3.0], 4, 10.0), ErrorCode::InvalidLength);
+        assert_eq!(cells(&[], 4, 10.0), ErrorCode::InvalidArgument);
+        assert_eq!(cells(&[1.0, 2.0], 0, 10.0), ErrorCode::InvalidArgument);
+        assert_eq!(cells(&[1.0, 2.0], 4, f64::NAN), ErrorCode::InvalidArgument);
+        assert_eq!(cells(&[1.0, 2.0], u32::MAX, 10.0), ErrorCode::TooLarge);
+        assert_eq!(cells(&[f64::INFINITY, 2.0], 4, 10.0), ErrorCode::NonFiniteInput);
+        let raster = rasterize_cell_distances(&[1.0], 4, 4, 10.0, 10.0);
+        assert_eq!(code_of(raster), ErrorCode::InvalidLength);
+    }
+}
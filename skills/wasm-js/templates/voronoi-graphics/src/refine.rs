@@ -0,0 +1,442 @@
+//! Quality mesh refinement
+//!
+//! Ruppert-style Delaunay refinement with the bounds rectangle as the only
+//! input segments: triangles whose smallest angle or area violates the
+//! bound get their circumcenter inserted, which removes them from the
+//! (still Delaunay) mesh. A circumcenter that would encroach on the rim
+//! (lie inside the diametral circle of a piece of it, or beyond it) splits
+//! that piece at its midpoint instead, so new points never leave the
+//! bounds. This repeats until no violation is left or the insertion cap
+//! is hit.
+
+use wasm_bindgen::prelude::*;
+
+use crate::dynamic::flat_to_points;
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles};
+use crate::mesh::{Mesh, NONE, SUPER};
+use crate::predicates::orient2d;
+use crate::Point;
+
+/// Steiner points `refine_mesh` inserts before it stops unconverged
+pub const REFINE_MAX_INSERTIONS: usize = 1 << 20;
+
+/// Axis-aligned rectangle new points must stay inside
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+#[wasm_bindgen]
+impl Bounds {
+    #[wasm_bindgen(constructor)]
+    pub fn new(min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Bounds {
+        Bounds { min_x, min_y, max_x, max_y }
+    }
+}
+
+impl Bounds {
+    fn contains(&self, p: Point) -> bool {
+        (self.min_x..=self.max_x).contains(&p.x) && (self.min_y..=self.max_y).contains(&p.y)
+    }
+
+    fn corners(&self) -> [Point; 4] {
+        [
+            Point { x: self.min_x, y: self.min_y },
+            Point { x: self.max_x, y: self.min_y },
+            Point { x: self.max_x, y: self.max_y },
+            Point { x: self.min_x, y: self.max_y },
+        ]
+    }
+}
+
+/// Output of `refine_mesh`
+#[wasm_bindgen]
+pub struct RefineResult {
+    points: Vec<f64>,
+    triangles: Vec<u32>,
+    inserted: usize,
+    converged: bool,
+}
+
+#[wasm_bindgen]
+impl RefineResult {
+    /// Input points followed by the inserted ones, `[x, y, ...]`
+    pub fn points(&self) -> Vec<f64> {
+        self.points.clone()
+    }
+
+    /// Triangles as indices into `points()`
+    pub fn triangles(&self) -> Vec<u32> {
+        self.triangles.clone()
+    }
+
+    /// Number of Steiner points added
+    pub fn inserted_count(&self) -> usize {
+        self.inserted
+    }
+
+    /// True when every triangle meets both bounds; false when the
+    /// insertion cap was hit or a violation could not be removed
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
+}
+
+/// Refine a triangulation until no angle is below `min_angle_deg` and no
+/// triangle is larger than `max_area`
+///
+/// `triangles` must triangulate `points` (any triangulation from this
+/// crate does); it is validated and the refinement then works on the
+/// Delaunay triangulation of the points. The mesh covers all of `bounds`:
+/// its corners are added as Steiner points unless already present, and
+/// every point must lie inside it.
+///
+/// Termination is only guaranteed for angles up to about 20°; in
+/// practice bounds up to ~33° finish, and beyond that refinement may
+/// cascade forever. `REFINE_MAX_INSERTIONS` caps it, and `converged()`
+/// reports which way it ended. Pass `f64::INFINITY` as `max_area` for an
+/// angle bound alone.
+#[wasm_bindgen]
+pub fn refine_mesh(
+    points: &[f64],
+    triangles: &[u32],
+    min_angle_deg: f64,
+    max_area: f64,
+    bounds: Bounds,
+) -> Result<RefineResult> {
+    let n = check_points(points)?;
+    check_triangles(triangles, n)?;
+    refine(points, min_angle_deg, max_area, bounds)
+}
+
+/// `refine_mesh` for bare points, with the `width × height` canvas as
+/// bounds
+///
+/// The result is the same as triangulating with `compute_delaunay` and
+/// refining that: input points first, Steiner points appended.
+#[wasm_bindgen]
+pub fn refine_delaunay(
+    points: &[f64],
+    width: f64,
+    height: f64,
+    min_angle_deg: f64,
+    max_area: f64,
+) -> Result<RefineResult> {
+    check_points(points)?;
+    refine(points, min_angle_deg, max_area, Bounds::new(0.0, 0.0, width, height))
+}
+
+/// `refine_mesh` once the triangles have been checked, which it ignores
+fn refine(
+    points: &[f64],
+    min_angle_deg: f64,
+    max_area: f64,
+    bounds: Bounds,
+) -> Result<RefineResult> {
+    if points.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    if !(0.0..60.0).contains(&min_angle_deg) {
+        return Err(Error::InvalidArgument(format!(
+            "minimum angle must be in [0, 60) degrees, got {min_angle_deg}"
+        )));
+    }
+    if max_area.is_nan() || max_area <= 0.0 {
+        return Err(Error::InvalidArgument(format!("max area must be positive, got {max_area}")));
+    }
+    let Bounds { min_x, min_y, max_x, max_y } = bounds;
+    let finite = [min_x, min_y, max_x, max_y].iter().all(|v| v.is_finite());
+    if !finite || min_x > max_x || min_y > max_y {
+        return Err(Error::InvalidArgument("bounds must be finite and ordered".into()));
+    }
+    let input = flat_to_points(points);
+    if let Some(i) = input.iter().position(|&p| !bounds.contains(p)) {
+        return Err(Error::InvalidArgument(format!("point {i} lies outside the bounds")));
+    }
+
+    let mut mesh = Mesh::build(&input, Some([min_x, min_y, max_x, max_y]));
+    let mut rim = Rim::new(bounds);
+    let mut inserted = 0;
+    for corner in bounds.corners() {
+        inserted += insert(&mut mesh, corner).is_some() as usize;
+    }
+    for &p in &input {
+        rim.add_if_on(p);
+    }
+    let mut stuck = false;
+    // Input points too close to the rim split it until they stop
+    // encroaching; halves of an unencroached piece never are encroached
+    for &p in &input {
+        while let Some(piece) = rim.encroached(p) {
+            if inserted == REFINE_MAX_INSERTIONS {
+                stuck = true;
+                break;
+            }
+            match rim.split(piece).and_then(|m| insert(&mut mesh, m)) {
+                Some(_) => inserted += 1,
+                None => {
+                    stuck = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    let sin_min = min_angle_deg.to_radians().sin();
+    let mut queue: Vec<u32> = (0..mesh.tris.len() as u32)
+        .filter(|&t| mesh.is_real_triangle(t as usize))
+        .collect();
+    let mut head = 0;
+    while head < queue.len() {
+        let t = queue[head];
+        head += 1;
+        if !mesh.is_real_triangle(t as usize) {
+            continue;
+        }
+        let Some(center) = violation(&mesh, t, sin_min, max_area) else {
+            continue;
+        };
+        if inserted == REFINE_MAX_INSERTIONS {
+            stuck = true;
+            break;
+        }
+        // A circumcenter near or beyond the rim splits the rim instead
+        let piece = rim.encroached(center);
+        let target = match piece {
+            Some(piece) => rim.split(piece),
+            None => Some(center),
+        };
+        let Some(v) = target.and_then(|p| insert(&mut mesh, p)) else {
+            // Precision has run out around this triangle
+            stuck = true;
+            continue;
+        };
+        inserted += 1;
+        queue.extend(star(&mesh, v));
+        if piece.is_some() {
+            queue.push(t);
+        }
+    }
+
+    let triangles = mesh.real_triangles().flatten().collect();
+    let points = mesh.points[SUPER as usize..].iter().flat_map(|p| [p.x, p.y]).collect();
+    Ok(RefineResult { points, triangles, inserted, converged: !stuck })
+}
+
+/// The bounds outline as four sides, each cut into pieces at the vertices
+/// lying on it
+///
+/// Sides run bottom, right, top, left; each holds the sorted coordinates
+/// along it (x for bottom and top, y for left and right).
+struct Rim {
+    bounds: Bounds,
+    sides: [Vec<f64>; 4],
+}
+
+impl Rim {
+    fn new(b: Bounds) -> Rim {
+        let (xs, ys) = (vec![b.min_x, b.max_x], vec![b.min_y, b.max_y]);
+        Rim { bounds: b, sides: [xs.clone(), ys.clone(), xs, ys] }
+    }
+
+    /// Position of `p` along `side` and its inward distance from it
+    fn local(&self, side: usize, p: Point) -> (f64, f64) {
+        let b = self.bounds;
+        match side {
+            0 => (p.x, p.y - b.min_y),
+            1 => (p.y, b.max_x - p.x),
+            2 => (p.x, b.max_y - p.y),
+            _ => (p.y, p.x - b.min_x),
+        }
+    }
+
+    fn point(&self, side: usize, along: f64) -> Point {
+        let b = self.bounds;
+        match side {
+            0 => Point { x: along, y: b.min_y },
+            1 => Point { x: b.max_x, y: along },
+            2 => Point { x: along, y: b.max_y },
+            _ => Point { x: b.min_x, y: along },
+        }
+    }
+
+    /// Index of the piece of `side` spanning `along`
+    fn piece(&self, side: usize, along: f64) -> usize {
+        let cuts = &self.sides[side];
+        cuts.partition_point(|&c| c <= along).clamp(1, cuts.len() - 1) - 1
+    }
+
+    fn add_if_on(&mut self, p: Point) {
+        for side in 0..4 {
+            let (along, depth) = self.local(side, p);
+            if depth == 0.0 {
+                let cuts = &mut self.sides[side];
+                if let Err(i) = cuts.binary_search_by(|c| c.total_cmp(&along)) {
+                    cuts.insert(i, along);
+                }
+            }
+        }
+    }
+
+    /// A piece whose diametral circle holds `p`, or the piece nearest `p`
+    /// if it lies outside the bounds
+    fn encroached(&self, p: Point) -> Option<(usize, usize)> {
+        let outside = (0..4)
+            .map(|side| (side, self.local(side, p).1))
+            .filter(|&(_, depth)| depth < 0.0)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((side, _)) = outside {
+            return Some((side, self.piece(side, self.local(side, p).0)));
+        }
+        (0..4).find_map(|side| {
+            let (along, depth) = self.local(side, p);
+            let i = self.piece(side, along);
+            let (lo, hi) = (self.sides[side][i], self.sides[side][i + 1]);
+            let (mid, half) = ((lo + hi) / 2.0, (hi - lo) / 2.0);
+            ((along - mid).powi(2) + depth * depth < half * half).then_some((side, i))
+        })
+    }
+
+    /// Cut a piece at its midpoint, returning the new rim point or `None`
+    /// once it is too short to halve
+    fn split(&mut self, (side, i): (usize, usize)) -> Option<Point> {
+        let cuts = &mut self.sides[side];
+        let mid = (cuts[i] + cuts[i + 1]) / 2.0;
+        if mid <= cuts[i] || mid >= cuts[i + 1] {
+            return None;
+        }
+        cuts.insert(i + 1, mid);
+        Some(self.point(side, mid))
+    }
+}
+
+/// Circumcenter of `t` if it breaks the angle or area bound
+fn violation(mesh: &Mesh, t: u32, sin_min: f64, max_area: f64) -> Option<Point> {
+    let [a, b, c] = mesh.tris[t as usize].map(|v| mesh.points[v as usize]);
+    let cross = orient2d(a, b, c);
+    let (bx, by, cx, cy) = (b.x - a.x, b.y - a.y, c.x - a.x, c.y - a.y);
+    let (b2, c2) = (bx * bx + by * by, cx * cx + cy * cy);
+    let (ux, uy) = ((cy * b2 - by * c2) / (2.0 * cross), (bx * c2 - cx * b2) / (2.0 * cross));
+    // Smallest angle θ faces the shortest edge l: sin θ = l / 2R
+    let shortest2 = b2.min(c2).min((c.x - b.x).powi(2) + (c.y - b.y).powi(2));
+    let r2 = ux * ux + uy * uy;
+    let skinny = shortest2 < 4.0 * r2 * sin_min * sin_min;
+    (skinny || cross / 2.0 > max_area).then_some(Point { x: a.x + ux, y: a.y + uy })
+}
+
+/// Add `p` to the mesh, returning its vertex or `None` if it duplicates
+/// an existing one
+fn insert(mesh: &mut Mesh, p: Point) -> Option<u32> {
+    let v = mesh.points.len() as u32;
+    mesh.points.push(p);
+    mesh.vert_tri.push(NONE);
+    if mesh.insert_vertex(v) {
+        Some(v)
+    } else {
+        mesh.points.pop();
+        mesh.vert_tri.pop();
+        None
+    }
+}
+
+/// Live triangles around linked vertex `v`
+fn star(mesh: &Mesh, v: u32) -> Vec<u32> {
+    let start = mesh.vert_tri[v as usize];
+    let mut out = Vec::new();
+    let mut t = start;
+    while t != NONE && out.len() < mesh.tris.len() {
+        out.push(t);
+        let k = mesh.tris[t as usize].iter().position(|&w| w == v).unwrap_or(0);
+        t = mesh.adj[t as usize][(k + 2) % 3];
+        if t == start {
+            break;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_delaunay;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+
+    fn min_angle_and_max_area(points: &[f64], triangles: &[u32]) -> (f64, f64) {
+        let p = |i: u32| Point { x: points[i as usize * 2], y: points[i as usize * 2 + 1] };
+        let mut worst = (f64::INFINITY, 0.0f64);
+        for t in triangles.chunks(3) {
+            let [a, b, c] = [p(t[0]), p(t[1]), p(t[2])];
+            for (o, u, w) in [(a, b, c), (b, c, a), (c, a, b)] {
+                let (ux, uy, wx, wy) = (u.x - o.x, u.y - o.y, w.x - o.x, w.y - o.y);
+                let angle = (ux * wy - uy * wx).atan2(ux * wx + uy * wy).abs().to_degrees();
+                worst.0 = worst.0.min(angle);
+            }
+            worst.1 = worst.1.max(orient2d(a, b, c) / 2.0);
+        }
+        worst
+    }
+
+    #[test]
+    fn test_refine_meets_angle_and_area_bounds() {
+        let mut rng = Rng::new(17);
+        let points: Vec<f64> = (0..400).map(|_| rng.range(0.0, 500.0)).collect();
+        let triangles = compute_delaunay(&points, 500.0, 500.0);
+        let bounds = Bounds::new(0.0, 0.0, 500.0, 500.0);
+        let (before, _) = min_angle_and_max_area(&points, &triangles);
+        assert!(before < 5.0);
+
+        let result = refine_mesh(&points, &triangles, 28.0, 400.0, bounds).unwrap();
+        assert!(result.converged());
+        let (out_points, out_tris) = (result.points(), result.triangles());
+        assert_eq!(out_points.len(), points.len() + 2 * result.inserted_count());
+        assert_eq!(&out_points[..points.len()], &points[..]);
+        for p in out_points.chunks(2) {
+            assert!(bounds.contains(Point { x: p[0], y: p[1] }));
+        }
+        let (angle, area) = min_angle_and_max_area(&out_points, &out_tris);
+        assert!(angle >= 28.0 - 1e-9, "smallest angle {angle}");
+        assert!(area <= 400.0, "largest area {area}");
+
+        let direct = refine_delaunay(&points, 500.0, 500.0, 28.0, 400.0).unwrap();
+        assert_eq!((direct.points(), direct.triangles()), (out_points, out_tris));
+        let outside = refine_delaunay(&points, 400.0, 500.0, 28.0, 400.0);
+        assert_eq!(code_of(outside), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_refine_caps_and_validates() {
+        let points = [0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0, 5.0, 5.0];
+        let triangles = compute_delaunay(&points, 10.0, 10.0);
+        let bounds = Bounds::new(0.0, 0.0, 10.0, 10.0);
+
+        // Already satisfied: nothing inserted
+        let loose = refine_mesh(&points, &triangles, 30.0, f64::INFINITY, bounds).unwrap();
+        assert!(loose.converged() && loose.inserted_count() == 0);
+        assert_eq!(loose.triangles().len(), 12);
+
+        // Splitting a tall sliver's base keeps new points on the rim
+        let sliver = [0.0, 0.0, 10.0, 0.0, 5.0, 0.1];
+        let fan = refine_mesh(&sliver, &[0, 1, 2], 25.0, f64::INFINITY, bounds).unwrap();
+        assert!(fan.converged() && fan.inserted_count() > 4);
+        let (angle, _) = min_angle_and_max_area(&fan.points(), &fan.triangles());
+        assert!(angle >= 25.0 - 1e-9, "smallest angle {angle}");
+
+        let check = |angle: f64, area: f64, b: Bounds| {
+            code_of(refine_mesh(&points, &triangles, angle, area, b))
+        };
+        assert_eq!(check(60.0, 1.0, bounds), ErrorCode::InvalidArgument);
+        assert_eq!(check(20.0, 0.0, bounds), ErrorCode::InvalidArgument);
+        let small = Bounds::new(0.0, 0.0, 1.0, 1.0);
+        assert_eq!(check(20.0, 1.0, small), ErrorCode::InvalidArgument);
+        let bad_index = refine_mesh(&points, &[0, 1, 9], 20.0, 1.0, bounds);
+        assert_eq!(code_of(bad_index), ErrorCode::IndexOutOfRange);
+        let odd = refine_mesh(&points[..3], &[], 20.0, 1.0, bounds);
+        assert_eq!(code_of(odd), ErrorCode::InvalidLength);
+    }
+}
@@ -0,0 +1,179 @@
+//! Lloyd relaxation of seed sets
+
+use wasm_bindgen::prelude::*;
+
+use crate::dynamic::DynamicTriangulation;
+use crate::error::{Error, Result};
+use crate::voronoi::{centroid_and_area, mesh_cells};
+
+/// Move every seed to the centroid of its canvas-clipped Voronoi cell,
+/// `iterations` times
+///
+/// Each pass reads the cells off the previous pass's triangulation and
+/// repairs it with flips rather than triangulating from scratch, the way
+/// `DynamicTriangulation` follows moving points. A seed with no cell of
+/// its own (a duplicate, or one off the canvas) stays where it is for
+/// that pass; once its twin has moved away it gets a cell and relaxes
+/// like the rest.
+#[wasm_bindgen]
+pub fn lloyd_relax(
+    points_flat: &[f64],
+    width: f64,
+    height: f64,
+    iterations: u32,
+) -> Result<Vec<f64>> {
+    lloyd_relax_pinned(points_flat, width, height, iterations, &[], f64::INFINITY)
+}
+
+/// `lloyd_relax` with some seeds held in place and a cap on each step
+///
+/// Seeds listed in `pinned` never move, but their cells still shape
+/// their neighbours'. Every other seed moves towards its centroid by at
+/// most `max_displacement` per iteration, so a few iterations a frame
+/// animate the relaxation instead of snapping to it; `Infinity` lifts the
+/// cap. Seeds keep their order.
+#[wasm_bindgen]
+pub fn lloyd_relax_pinned(
+    points_flat: &[f64],
+    width: f64,
+    height: f64,
+    iterations: u32,
+    pinned: &[u32],
+    max_displacement: f64,
+) -> Result<Vec<f64>> {
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if max_displacement.is_nan() || max_displacement < 0.0 {
+        return Err(Error::InvalidArgument(format!(
+            "max displacement must be non-negative, got {max_displacement}"
+        )));
+    }
+    let mut tri = DynamicTriangulation::new(points_flat, width, height)?;
+    let n = points_flat.len() / 2;
+    let mut held = vec![false; n];
+    for &i in pinned {
+        if i as usize >= n {
+            return Err(Error::IndexOutOfRange(format!(
+                "pinned seed {i} out of range for {n} seeds"
+            )));
+        }
+        held[i as usize] = true;
+    }
+    let mut points = points_flat.to_vec();
+    for _ in 0..iterations {
+        let (vertices, offsets) = mesh_cells(&tri.mesh, width, height);
+        for (i, w) in offsets.windows(2).enumerate().filter(|&(i, _)| !held[i]) {
+            let cell = &vertices[w[0] as usize * 2..w[1] as usize * 2];
+            if let Some((c, _)) = (cell.len() >= 6).then(|| centroid_and_area(cell)).flatten() {
+                let (x, y) = (c.x.clamp(0.0, width), c.y.clamp(0.0, height));
+                let (dx, dy) = (x - points[2 * i], y - points[2 * i + 1]);
+                let d = dx.hypot(dy);
+                if d > max_displacement {
+                    let t = max_displacement / d;
+                    points[2 * i] += dx * t;
+                    points[2 * i + 1] += dy * t;
+                } else {
+                    points[2 * i] = x;
+                    points[2 * i + 1] = y;
+                }
+            }
+        }
+        tri.update_points(&points)?;
+    }
+    Ok(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_voronoi;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+
+    /// Variance of the cell areas, relative to the squared mean
+    fn area_spread(points: &[f64], w: f64, h: f64) -> f64 {
+        let cells = compute_voronoi(points, w, h).unwrap();
+        let (vertices, offsets) = (cells.vertices(), cells.offsets());
+        let areas: Vec<f64> = offsets
+            .windows(2)
+            .map(|o| {
+                let cell = &vertices[o[0] as usize * 2..o[1] as usize * 2];
+                let n = cell.len() / 2;
+                let cross = |i: usize| {
+                    let j = (i + 1) % n;
+                    cell[2 * i] * cell[2 * j + 1] - cell[2 * j] * cell[2 * i + 1]
+                };
+                (0..n).map(cross).sum::<f64>() / 2.0
+            })
+            .collect();
+        let mean = areas.iter().sum::<f64>() / areas.len() as f64;
+        areas.iter().map(|a| (a - mean).powi(2)).sum::<f64>() / areas.len() as f64 / (mean * mean)
+    }
+
+    #[test]
+    fn test_relaxation_evens_out_cells() {
+        let (w, h) = (800.0, 600.0);
+        let mut rng = Rng::new(3);
+        let points: Vec<f64> =
+            (0..300).flat_map(|_| [rng.range(0.0, w), rng.range(0.0, h)]).collect();
+        let relaxed = lloyd_relax(&points, w, h, 5).unwrap();
+        assert_eq!(relaxed.len(), points.len());
+        let (before, after) = (area_spread(&points, w, h), area_spread(&relaxed, w, h));
+        assert!(after < before / 4.0, "area spread {before} -> {after}");
+        for p in relaxed.chunks(2) {
+            assert!((0.0..=w).contains(&p[0]) && (0.0..=h).contains(&p[1]));
+        }
+        assert_eq!(lloyd_relax(&points, w, h, 0).unwrap(), points);
+    }
+
+    #[test]
+    fn test_coincident_seeds_separate() {
+        let points = [10.0, 10.0, 10.0, 10.0, 10.0, 10.0, 90.0, 50.0, 50.0, 90.0];
+        let relaxed = lloyd_relax(&points, 100.0, 100.0, 8).unwrap();
+        let distinct = |i: usize, j: usize| relaxed[2 * i..2 * i + 2] != relaxed[2 * j..2 * j + 2];
+        assert!(distinct(0, 1) && distinct(0, 2) && distinct(1, 2), "{relaxed:?}");
+
+        let code = |pts: &[f64], w: f64| code_of(lloyd_relax(pts, w, 100.0, 1));
+        assert_eq!(code(&points[..3], 100.0), ErrorCode::InvalidLength);
+        assert_eq!(code(&points, 0.0), ErrorCode::InvalidArgument);
+        assert_eq!(code(&[f64::NAN, 0.0], 100.0), ErrorCode::NonFiniteInput);
+    }
+
+    #[test]
+    fn test_pinned_seeds_hold_and_steps_are_capped() {
+        let (w, h) = (600.0, 400.0);
+        let mut rng = Rng::new(11);
+        let points: Vec<f64> =
+            (0..200).flat_map(|_| [rng.range(0.0, w), rng.range(0.0, h)]).collect();
+        let pinned = [0, 7, 199];
+        let relaxed = lloyd_relax_pinned(&points, w, h, 6, &pinned, f64::INFINITY).unwrap();
+        for &i in &pinned {
+            assert_eq!(relaxed[i as usize * 2..][..2], points[i as usize * 2..][..2]);
+        }
+        // The pins still bound their neighbours' cells, so the rest differ
+        // from an unpinned run
+        let free = lloyd_relax(&points, w, h, 6).unwrap();
+        assert_ne!(relaxed[2..14], free[2..14]);
+        assert_eq!(lloyd_relax_pinned(&points, w, h, 6, &[], f64::INFINITY).unwrap(), free);
+
+        // A capped step covers the uncapped one or the cap, whichever is
+        // shorter
+        let once = lloyd_relax(&points, w, h, 1).unwrap();
+        let slow = lloyd_relax_pinned(&points, w, h, 1, &[], 2.0).unwrap();
+        let step = |a: &[f64], i: usize| {
+            (a[i * 2] - points[i * 2]).hypot(a[i * 2 + 1] - points[i * 2 + 1])
+        };
+        for i in 0..200 {
+            assert!((step(&slow, i) - step(&once, i).min(2.0)).abs() < 1e-9);
+        }
+        assert_eq!(lloyd_relax_pinned(&points, w, h, 3, &[], 0.0).unwrap(), points);
+
+        let code = |pinned: &[u32], cap: f64| {
+            code_of(lloyd_relax_pinned(&points, w, h, 1, pinned, cap))
+        };
+        assert_eq!(code(&[200], 1.0), ErrorCode::IndexOutOfRange);
+        assert_eq!(code(&[], -1.0), ErrorCode::InvalidArgument);
+        assert_eq!(code(&[], f64::NAN), ErrorCode::InvalidArgument);
+    }
+}
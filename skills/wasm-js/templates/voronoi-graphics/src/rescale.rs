@@ -0,0 +1,111 @@
+//! Carrying seeds over a canvas resize without re-triangulating
+//!
+//! A uniform scale maps circles to circles, so the Delaunay triangulation
+//! of the rescaled seeds has the same triangles: the index buffers can be
+//! kept as they are. Stretching to a new aspect ratio can break the empty
+//! circle test wherever the triangles were nearly cocircular, so
+//! `is_delaunay_still_valid` confirms before the old buffers are reused.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::seeds::FitMode;
+use crate::validate_delaunay;
+
+/// `[sx, sy, tx, ty]` taking the old canvas onto the new one as `mode`
+/// fits a generator's domain: `Contain` scales uniformly and letterboxes,
+/// `Cover` scales uniformly and crops, `Stretch` scales each axis
+pub(crate) fn rescale_transform(
+    old: (f64, f64),
+    new: (f64, f64),
+    mode: FitMode,
+) -> Result<[f64; 4]> {
+    if [old.0, old.1, new.0, new.1].iter().any(|v| !v.is_finite() || *v <= 0.0) {
+        return Err(Error::InvalidArgument("canvas sizes must be positive".into()));
+    }
+    let (sx, sy) = (new.0 / old.0, new.1 / old.1);
+    let uniform = |s: f64| [s, s, (new.0 - old.0 * s) / 2.0, (new.1 - old.1 * s) / 2.0];
+    Ok(match mode {
+        FitMode::Contain => uniform(sx.min(sy)),
+        FitMode::Cover => uniform(sx.max(sy)),
+        FitMode::Stretch => [sx, sy, 0.0, 0.0],
+    })
+}
+
+/// Seeds on an `old_w × old_h` canvas moved onto `new_w × new_h`, in the
+/// same order
+///
+/// `Contain` keeps the whole old canvas in view, centred, and `Cover`
+/// fills the new one, so seeds can land off its edges; both keep the
+/// triangles Delaunay. `Stretch` fills it exactly with a change of aspect
+/// ratio, after which check `is_delaunay_still_valid`.
+#[wasm_bindgen]
+pub fn rescale_points(
+    points_flat: &[f64],
+    old_w: f64,
+    old_h: f64,
+    new_w: f64,
+    new_h: f64,
+    mode: FitMode,
+) -> Result<Vec<f64>> {
+    check_points(points_flat)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let [sx, sy, tx, ty] = rescale_transform((old_w, old_h), (new_w, new_h), mode)?;
+    Ok(points_flat.chunks_exact(2).flat_map(|c| [c[0] * sx + tx, c[1] * sy + ty]).collect())
+}
+
+/// Whether `triangles`, built before a `rescale_points`, is still the
+/// Delaunay triangulation of the rescaled points; `validate_delaunay`
+/// under the name of the question it answers after a resize
+#[wasm_bindgen]
+pub fn is_delaunay_still_valid(points_flat: &[f64], triangles: &[u32]) -> Result<bool> {
+    validate_delaunay(points_flat, triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::{compute_delaunay, generate_golden_seeds};
+
+    #[test]
+    fn test_modes_place_the_old_canvas() {
+        let corners = [0.0, 0.0, 400.0, 300.0];
+        let fit = |mode| rescale_points(&corners, 400.0, 300.0, 800.0, 800.0, mode).unwrap();
+        assert_eq!(fit(FitMode::Stretch), vec![0.0, 0.0, 800.0, 800.0]);
+        // Letterboxed top and bottom, or cropped left and right
+        assert_eq!(fit(FitMode::Contain), vec![0.0, 100.0, 800.0, 700.0]);
+        let cover = fit(FitMode::Cover);
+        let third = 800.0 / 3.0;
+        for (got, want) in cover.iter().zip([-third / 2.0, 0.0, 800.0 + third / 2.0, 800.0]) {
+            assert!((got - want).abs() < 1e-9, "{cover:?}");
+        }
+
+        let code = |w: f64, pts: &[f64]| {
+            code_of(rescale_points(pts, 400.0, 300.0, w, 800.0, FitMode::Contain))
+        };
+        assert_eq!(code(0.0, &corners), ErrorCode::InvalidArgument);
+        assert_eq!(code(f64::NAN, &corners), ErrorCode::InvalidArgument);
+        assert_eq!(code(800.0, &corners[..3]), ErrorCode::InvalidLength);
+        assert_eq!(code(800.0, &[f64::INFINITY, 0.0]), ErrorCode::NonFiniteInput);
+    }
+
+    #[test]
+    fn test_uniform_scaling_keeps_the_triangles() {
+        let seeds = generate_golden_seeds(400.0, 300.0, 600);
+        let triangles = compute_delaunay(&seeds, 400.0, 300.0);
+        for mode in [FitMode::Contain, FitMode::Cover] {
+            let moved = rescale_points(&seeds, 400.0, 300.0, 1000.0, 600.0, mode).unwrap();
+            assert!(is_delaunay_still_valid(&moved, &triangles).unwrap());
+        }
+        // The spiral's near-circular rings do not survive a squash
+        let squashed = rescale_points(&seeds, 400.0, 300.0, 1600.0, 300.0, FitMode::Stretch);
+        let squashed = squashed.unwrap();
+        assert!(!is_delaunay_still_valid(&squashed, &triangles).unwrap());
+        let rebuilt = compute_delaunay(&squashed, 1600.0, 300.0);
+        assert!(is_delaunay_still_valid(&squashed, &rebuilt).unwrap());
+    }
+}
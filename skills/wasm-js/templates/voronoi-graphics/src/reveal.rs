@@ -0,0 +1,113 @@
+//! Ordered-dither reveal thresholds for fading triangles in one by one
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+
+/// Reveal threshold per triangle from a tiled dither texture
+///
+/// `centroids` holds `[x, y, ...]`, one pair per triangle, in canvas
+/// pixels. The `noise_size × noise_size` texture is tiled over the canvas
+/// at one texel per pixel, and each triangle takes the texel under its
+/// centroid (clamped into the `width × height` canvas). Its threshold is
+/// `(value + 0.5) / 256`. At reveal progress `p`, draw the triangles whose
+/// threshold is below `p`: none at 0, all at 1. With a blue-noise texture
+/// every intermediate subset is spread evenly over the canvas. Thresholds
+/// depend only on the centroid, so they stay fixed from frame to frame.
+///
+/// The texture may be single-channel or RGBA as returned by
+/// `getImageData`. RGBA uses the red channel.
+#[wasm_bindgen]
+pub fn compute_reveal_mask(
+    centroids: &[f64],
+    blue_noise: &[u8],
+    noise_size: u32,
+    width: f64,
+    height: f64,
+) -> Result<Vec<f32>> {
+    if !centroids.len().is_multiple_of(2) {
+        return Err(Error::InvalidLength(format!(
+            "centroid array length {} is odd",
+            centroids.len()
+        )));
+    }
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if noise_size == 0 {
+        return Err(Error::InvalidArgument("noise size must be positive".into()));
+    }
+    let texels = noise_size as usize * noise_size as usize;
+    let stride = match blue_noise.len() {
+        len if len == texels => 1,
+        len if len == texels * 4 => 4,
+        len => {
+            return Err(Error::InvalidLength(format!(
+                "noise texture of {noise_size}x{noise_size} needs {texels} or {} bytes, got {len}",
+                texels * 4
+            )))
+        }
+    };
+    if centroids.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("centroid coordinates must be finite".into()));
+    }
+
+    let size = noise_size as f64;
+    let tile = |v: f64, extent: f64| (v.clamp(0.0, extent).floor() % size) as usize;
+    Ok(centroids
+        .chunks_exact(2)
+        .map(|c| {
+            let (tx, ty) = (tile(c[0], width), tile(c[1], height));
+            let value = blue_noise[(ty * noise_size as usize + tx) * stride];
+            (value as f32 + 0.5) / 256.0
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    /// 4×4 Bayer matrix scaled to bytes, the classic ordered-dither mask
+    const BAYER: [u8; 16] =
+        [0, 128, 32, 160, 192, 64, 224, 96, 48, 176, 16, 144, 240, 112, 208, 80];
+
+    #[test]
+    fn test_thresholds_follow_the_tiled_texture() {
+        // One centroid per pixel centre on a 16x16 canvas
+        let centroids: Vec<f64> =
+            (0..256).flat_map(|i| [(i % 16) as f64 + 0.5, (i / 16) as f64 + 0.5]).collect();
+        let mask = compute_reveal_mask(&centroids, &BAYER, 4, 16.0, 16.0).unwrap();
+        assert!(mask.iter().all(|t| *t > 0.0 && *t < 1.0));
+        assert_eq!(mask, compute_reveal_mask(&centroids, &BAYER, 4, 16.0, 16.0).unwrap());
+
+        // At every quarter of the reveal each 2x2 block of each tile shows
+        // exactly as many triangles as the others
+        for p in [0.25, 0.5, 0.75] {
+            let mut blocks = [0u32; 64];
+            for (i, t) in mask.iter().enumerate() {
+                if *t < p {
+                    blocks[(i / 16 / 2) * 8 + (i % 16) / 2] += 1;
+                }
+            }
+            assert!(blocks.iter().all(|&b| b == (p * 4.0) as u32), "p = {p}");
+        }
+
+        // RGBA reads the red channel; off-canvas centroids clamp to the edge
+        let rgba: Vec<u8> = BAYER.iter().flat_map(|&v| [v, 0, 0, 255]).collect();
+        let edge = compute_reveal_mask(&[-5.0, 2.0, 100.0, 2.0], &rgba, 4, 16.0, 16.0).unwrap();
+        assert_eq!(edge, vec![(48.0 + 0.5) / 256.0; 2]);
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        let reveal = |c: &[f64], noise: &[u8], size: u32| {
+            code_of(compute_reveal_mask(c, noise, size, 16.0, 16.0))
+        };
+        assert_eq!(reveal(&[1.0], &BAYER, 4), ErrorCode::InvalidLength);
+        assert_eq!(reveal(&[1.0, 1.0], &BAYER[..15], 4), ErrorCode::InvalidLength);
+        assert_eq!(reveal(&[1.0, 1.0], &[], 0), ErrorCode::InvalidArgument);
+        assert_eq!(reveal(&[f64::NAN, 1.0], &BAYER, 4), ErrorCode::NonFiniteInput);
+    }
+}
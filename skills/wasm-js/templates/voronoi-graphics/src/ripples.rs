@@ -0,0 +1,198 @@
+//! Expanding click ripples that push seeds outward as they pass
+//!
+//! Each ripple is `[cx, cy, start_time, strength]` in a stride-4 array. At
+//! `time` its ring has radius `speed × (time - start_time)`, and a seed at
+//! distance `d` from the centre is pushed away from it by
+//! `strength × exp(-((d - radius) / ring_width)² / 2)`. Beyond three ring
+//! widths from the ring the push is taken as zero, so each ripple only
+//! touches the seeds in its band, and once the band has passed the whole
+//! canvas `prune_ripples` can drop it.
+
+use wasm_bindgen::prelude::*;
+
+use crate::buffers::check_out_len;
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+
+/// Half-width of a ring's band, in ring widths
+const RIPPLE_BAND: f64 = 3.0;
+
+fn check_ripples(ripples: &[f64], time: f64, speed: f64, ring_width: f64) -> Result<()> {
+    if !ripples.len().is_multiple_of(4) {
+        return Err(Error::InvalidLength(format!(
+            "ripple array length {} is not a multiple of 4",
+            ripples.len()
+        )));
+    }
+    if ripples.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("ripples must be finite".into()));
+    }
+    if !time.is_finite() {
+        return Err(Error::NonFiniteInput(format!("time must be finite, got {time}")));
+    }
+    if !speed.is_finite() || speed < 0.0 {
+        return Err(Error::InvalidArgument(format!(
+            "speed must be finite and non-negative, got {speed}"
+        )));
+    }
+    if !ring_width.is_finite() || ring_width <= 0.0 {
+        return Err(Error::InvalidArgument(format!(
+            "ring width must be finite and positive, got {ring_width}"
+        )));
+    }
+    Ok(())
+}
+
+/// `base_points` displaced by every started ripple at `time`
+///
+/// A ripple whose `start_time` is still ahead does nothing yet, and a
+/// seed exactly at a centre has no outward direction and stays put. The
+/// pushes of overlapping ripples add up.
+#[wasm_bindgen]
+pub fn apply_ripples(
+    base_points: &[f64],
+    ripples: &[f64],
+    time: f64,
+    speed: f64,
+    ring_width: f64,
+) -> Result<Vec<f64>> {
+    let mut out = vec![0.0; base_points.len()];
+    apply_ripples_into(base_points, ripples, time, speed, ring_width, &mut out)?;
+    Ok(out)
+}
+
+/// `apply_ripples` writing into the front of `out`; returns the number of
+/// values written
+#[wasm_bindgen]
+pub fn apply_ripples_into(
+    base_points: &[f64],
+    ripples: &[f64],
+    time: f64,
+    speed: f64,
+    ring_width: f64,
+    out: &mut [f64],
+) -> Result<usize> {
+    check_points(base_points)?;
+    if base_points.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    check_ripples(ripples, time, speed, ring_width)?;
+    check_out_len(base_points.len(), out.len())?;
+    let out = &mut out[..base_points.len()];
+    out.copy_from_slice(base_points);
+    let band = RIPPLE_BAND * ring_width;
+    let scale = -0.5 / (ring_width * ring_width);
+    for r in ripples.chunks_exact(4) {
+        let [cx, cy, start, strength] = [r[0], r[1], r[2], r[3]];
+        if time < start {
+            continue;
+        }
+        let radius = speed * (time - start);
+        let (inner, outer) = ((radius - band).max(0.0), radius + band);
+        let (inner2, outer2) = (inner * inner, outer * outer);
+        for (p, slot) in base_points.chunks_exact(2).zip(out.chunks_exact_mut(2)) {
+            let (dx, dy) = (p[0] - cx, p[1] - cy);
+            let d2 = dx * dx + dy * dy;
+            if d2 < inner2 || d2 > outer2 || d2 == 0.0 {
+                continue;
+            }
+            let d = d2.sqrt();
+            let push = strength * (scale * (d - radius).powi(2)).exp() / d;
+            slot[0] += dx * push;
+            slot[1] += dy * push;
+        }
+    }
+    Ok(base_points.len())
+}
+
+/// `ripples` without the ones whose band has passed every point of the
+/// `width × height` canvas, in their original order
+///
+/// A ripple is dropped once its band's inner edge is beyond the canvas
+/// corner farthest from its centre, after which `apply_ripples` would
+/// never use it again.
+#[wasm_bindgen]
+pub fn prune_ripples(
+    ripples: &[f64],
+    time: f64,
+    width: f64,
+    height: f64,
+    speed: f64,
+    ring_width: f64,
+) -> Result<Vec<f64>> {
+    check_ripples(ripples, time, speed, ring_width)?;
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    let live = |r: &&[f64]| {
+        let reach = r[0].max(width - r[0]).hypot(r[1].max(height - r[1]));
+        speed * (time - r[2]) - RIPPLE_BAND * ring_width <= reach
+    };
+    Ok(ripples.chunks_exact(4).filter(live).flatten().copied().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_ring_pushes_seeds_in_its_band() {
+        // Seeds along the x axis from a ripple at the origin
+        let base: Vec<f64> = (0..=40).flat_map(|k| [k as f64 * 5.0, 0.0]).collect();
+        let ripple = [0.0, 0.0, 1.0, 4.0];
+        // At time 2 the ring is at radius 100
+        let moved = apply_ripples(&base, &ripple, 2.0, 100.0, 10.0).unwrap();
+        for (k, (p, q)) in base.chunks(2).zip(moved.chunks(2)).enumerate() {
+            let push = q[0] - p[0];
+            assert_eq!(q[1], 0.0);
+            let off = (p[0] - 100.0).abs();
+            if off > 30.0 || k == 0 {
+                assert_eq!(push, 0.0, "seed {k}");
+            } else {
+                assert!((push - 4.0 * (-0.5 * (off / 10.0).powi(2)).exp()).abs() < 1e-12);
+            }
+        }
+        assert_eq!(moved[41], 0.0);
+        // Not started yet
+        assert_eq!(apply_ripples(&base, &ripple, 0.5, 100.0, 10.0).unwrap(), base);
+        let mut out = vec![-1.0; base.len() + 2];
+        assert_eq!(apply_ripples_into(&base, &ripple, 2.0, 100.0, 10.0, &mut out), Ok(82));
+        assert_eq!((&out[..82], out[82]), (&moved[..], -1.0));
+
+        // Two ripples add
+        let both = [0.0, 0.0, 1.0, 4.0, 200.0, 0.0, 1.0, 2.0];
+        let summed = apply_ripples(&base, &both, 2.0, 100.0, 10.0).unwrap();
+        let other = apply_ripples(&base, &both[4..], 2.0, 100.0, 10.0).unwrap();
+        for k in 0..base.len() {
+            assert!((summed[k] - (moved[k] + other[k] - base[k])).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_prune_and_errors() {
+        // Centres in a 400 × 300 canvas; the farthest corner of the first
+        // is 500 away
+        let ripples = [0.0, 0.0, 0.0, 1.0, 200.0, 150.0, 4.0, 1.0, 100.0, 100.0, 9.0, 1.0];
+        // At time 5.29 the first band runs 499 to 559, the second is at 129
+        let kept = prune_ripples(&ripples, 5.29, 400.0, 300.0, 100.0, 10.0).unwrap();
+        assert_eq!(kept, ripples);
+        let kept = prune_ripples(&ripples, 5.31, 400.0, 300.0, 100.0, 10.0).unwrap();
+        assert_eq!(kept, ripples[4..]);
+        assert!(prune_ripples(&[], 1.0, 400.0, 300.0, 100.0, 10.0).unwrap().is_empty());
+
+        let base = [1.0, 2.0];
+        let apply = |ripples: &[f64], time: f64, speed: f64, width: f64| {
+            code_of(apply_ripples(&base, ripples, time, speed, width))
+        };
+        assert_eq!(apply(&ripples[..3], 1.0, 1.0, 1.0), ErrorCode::InvalidLength);
+        assert_eq!(apply(&[0.0, 0.0, f64::NAN, 1.0], 1.0, 1.0, 1.0), ErrorCode::NonFiniteInput);
+        assert_eq!(apply(&ripples, f64::INFINITY, 1.0, 1.0), ErrorCode::NonFiniteInput);
+        assert_eq!(apply(&ripples, 1.0, -1.0, 1.0), ErrorCode::InvalidArgument);
+        assert_eq!(apply(&ripples, 1.0, 1.0, 0.0), ErrorCode::InvalidArgument);
+        let short = apply_ripples_into(&base, &ripples, 1.0, 1.0, 1.0, &mut [0.0]);
+        assert_eq!(code_of(short), ErrorCode::InvalidLength);
+        let flat = prune_ripples(&ripples, 1.0, 0.0, 300.0, 1.0, 1.0);
+        assert_eq!(code_of(flat), ErrorCode::InvalidArgument);
+    }
+}
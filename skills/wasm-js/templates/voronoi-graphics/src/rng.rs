@@ -0,0 +1,96 @@
+//! Small deterministic PRNG
+//!
+//! PCG32 (XSH-RR) with a SplitMix64 seed expander: fast, tiny, and
+//! stable across platforms, so a given seed reproduces the same
+//! animation everywhere. Not for anything security-related.
+
+/// PCG32 generator; the raw state is exposed for snapshots
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Rng {
+    pub(crate) state: u64,
+    pub(crate) inc: u64,
+}
+
+fn splitmix64(x: &mut u64) -> u64 {
+    *x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *x;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Rng {
+        let mut s = seed;
+        let state = splitmix64(&mut s);
+        let inc = splitmix64(&mut s) | 1;
+        let mut rng = Rng { state: 0, inc };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(state);
+        rng.next_u32();
+        rng
+    }
+
+    pub(crate) fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    #[cfg_attr(not(feature = "motes"), allow(dead_code))]
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        ((self.next_u32() as u64) << 32) | self.next_u32() as u64
+    }
+
+    /// Uniform in [0, 1) with 53 bits of precision
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        let hi = (self.next_u32() as u64) << 21;
+        let lo = (self.next_u32() >> 11) as u64;
+        (hi | lo) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform integer in `0..n` (Lemire's multiply-shift, `n > 0`)
+    pub(crate) fn below(&mut self, n: u32) -> u32 {
+        ((self.next_u32() as u64 * n as u64) >> 32) as u32
+    }
+
+    /// Uniform in `[lo, hi)`
+    #[cfg_attr(not(feature = "motes"), allow(dead_code))]
+    pub(crate) fn range(&mut self, lo: f64, hi: f64) -> f64 {
+        lo + (hi - lo) * self.next_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng_deterministic_and_seed_sensitive() {
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+        let mut c = Rng::new(8);
+        let xs: Vec<u32> = (0..8).map(|_| a.next_u32()).collect();
+        let ys: Vec<u32> = (0..8).map(|_| b.next_u32()).collect();
+        let zs: Vec<u32> = (0..8).map(|_| c.next_u32()).collect();
+        assert_eq!(xs, ys);
+        assert_ne!(xs, zs);
+    }
+
+    #[test]
+    fn test_rng_ranges() {
+        let mut rng = Rng::new(1);
+        let mut buckets = [0u32; 10];
+        for _ in 0..10_000 {
+            let f = rng.next_f64();
+            assert!((0.0..1.0).contains(&f));
+            buckets[rng.below(10) as usize] += 1;
+        }
+        // Roughly uniform: every bucket within 20% of the expected 1000
+        assert!(buckets.iter().all(|&b| (800..1200).contains(&b)), "{buckets:?}");
+    }
+}
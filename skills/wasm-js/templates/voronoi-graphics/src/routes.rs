@@ -0,0 +1,229 @@
+//! Shortest routes between seeds along the edge graph
+//!
+//! Edges weigh their Euclidean length, so a route is the shortest way
+//! from one seed to another without leaving the mesh. A single query runs
+//! A* with the straight-line distance as its heuristic and stops at the
+//! target; `ShortestPathTree` runs the full Dijkstra once so every route
+//! from one source is a walk up its predecessor array. Seeds in another
+//! component, e.g. after alpha filtering, have no route.
+
+use std::collections::BinaryHeap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_edge_indices, check_points, edge_length, Csr, HeapEntry};
+use crate::mesh::NONE;
+
+fn parse(points_flat: &[f64], edge_indices: &[u32]) -> Result<Csr> {
+    let n = check_points(points_flat)?;
+    check_edge_indices(edge_indices, n)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let edges: Vec<(u32, u32)> = edge_indices.chunks_exact(2).map(|e| (e[0], e[1])).collect();
+    Ok(Csr::from_edges(n, &edges))
+}
+
+fn check_vertex(v: u32, point_count: usize) -> Result<()> {
+    if v as usize >= point_count {
+        return Err(Error::IndexOutOfRange(format!(
+            "vertex {v} out of range for {point_count} points"
+        )));
+    }
+    Ok(())
+}
+
+/// Distances and predecessors from `from`, settling vertices until `to`
+/// if given, guided by the straight line to it
+fn search(points_flat: &[f64], csr: &Csr, from: u32, to: Option<u32>) -> (Vec<f64>, Vec<u32>) {
+    let n = points_flat.len() / 2;
+    let heuristic = |v: u32| to.map_or(0.0, |t| edge_length(points_flat, v, t));
+    let mut dist = vec![f64::INFINITY; n];
+    let mut prev = vec![NONE; n];
+    let mut heap = BinaryHeap::new();
+    dist[from as usize] = 0.0;
+    heap.push(HeapEntry(heuristic(from), from));
+    while let Some(HeapEntry(f, v)) = heap.pop() {
+        if Some(v) == to {
+            break;
+        }
+        let d = dist[v as usize];
+        if f > d + heuristic(v) {
+            continue;
+        }
+        for &u in csr.neighbors(v) {
+            let nd = d + edge_length(points_flat, v, u);
+            if nd < dist[u as usize] {
+                dist[u as usize] = nd;
+                prev[u as usize] = v;
+                heap.push(HeapEntry(nd + heuristic(u), u));
+            }
+        }
+    }
+    (dist, prev)
+}
+
+/// Vertices from `from` to `to` following `prev`; empty if `to` was never
+/// reached
+fn walk_back(prev: &[u32], from: u32, to: u32) -> Vec<u32> {
+    if to != from && prev[to as usize] == NONE {
+        return Vec::new();
+    }
+    let mut path = vec![to];
+    let mut v = to;
+    while v != from {
+        v = prev[v as usize];
+        path.push(v);
+    }
+    path.reverse();
+    path
+}
+
+/// Vertices of the shortest route from `from` to `to` over
+/// `edge_indices`, both ends included
+///
+/// Just `[from]` when they are the same vertex, and empty when no route
+/// connects them.
+#[wasm_bindgen]
+pub fn shortest_path(
+    points_flat: &[f64],
+    edge_indices: &[u32],
+    from: u32,
+    to: u32,
+) -> Result<Vec<u32>> {
+    let csr = parse(points_flat, edge_indices)?;
+    check_vertex(from, points_flat.len() / 2)?;
+    check_vertex(to, points_flat.len() / 2)?;
+    let (_, prev) = search(points_flat, &csr, from, Some(to));
+    Ok(walk_back(&prev, from, to))
+}
+
+/// `[x0, y0, x1, y1, ...]` along a `shortest_path` route, for a line strip
+/// or, widened to f64, a `PathTable` a mote can travel at constant speed
+#[wasm_bindgen]
+pub fn path_to_polyline(points_flat: &[f64], path: &[u32]) -> Result<Vec<f32>> {
+    let n = check_points(points_flat)?;
+    if let Some(&v) = path.iter().find(|&&v| v as usize >= n) {
+        return Err(Error::IndexOutOfRange(format!("vertex {v} out of range for {n} points")));
+    }
+    let at = |v: u32| [points_flat[v as usize * 2] as f32, points_flat[v as usize * 2 + 1] as f32];
+    Ok(path.iter().flat_map(|&v| at(v)).collect())
+}
+
+/// Every shortest route from one source, from `shortest_path_tree`
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ShortestPathTree {
+    source: u32,
+    distances: Vec<f64>,
+    predecessors: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl ShortestPathTree {
+    pub fn source(&self) -> u32 {
+        self.source
+    }
+
+    /// Route length from the source to each vertex, infinite where there
+    /// is no route
+    pub fn distances(&self) -> Vec<f64> {
+        self.distances.clone()
+    }
+
+    /// The vertex before each one on its route from the source;
+    /// `0xFFFFFFFF` for the source itself and for unreachable vertices
+    pub fn predecessors(&self) -> Vec<u32> {
+        self.predecessors.clone()
+    }
+
+    /// `shortest_path` from the source to `to`, read off the tree
+    pub fn path_to(&self, to: u32) -> Result<Vec<u32>> {
+        check_vertex(to, self.predecessors.len())?;
+        Ok(walk_back(&self.predecessors, self.source, to))
+    }
+}
+
+/// Shortest routes from `from` to every vertex over `edge_indices`
+#[wasm_bindgen]
+pub fn shortest_path_tree(
+    points_flat: &[f64],
+    edge_indices: &[u32],
+    from: u32,
+) -> Result<ShortestPathTree> {
+    let csr = parse(points_flat, edge_indices)?;
+    check_vertex(from, points_flat.len() / 2)?;
+    let (distances, predecessors) = search(points_flat, &csr, from, None);
+    Ok(ShortestPathTree { source: from, distances, predecessors })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+    use crate::{compute_delaunay, compute_edge_indices};
+
+    fn route_length(points: &[f64], path: &[u32]) -> f64 {
+        path.windows(2).map(|w| edge_length(points, w[0], w[1])).sum()
+    }
+
+    #[test]
+    fn test_routes_are_shortest() {
+        let mut rng = Rng::new(21);
+        let points: Vec<f64> =
+            (0..500).flat_map(|_| [rng.range(0.0, 500.0), rng.range(0.0, 400.0)]).collect();
+        let edges = compute_edge_indices(&compute_delaunay(&points, 500.0, 400.0));
+        let tree = shortest_path_tree(&points, &edges, 17).unwrap();
+        let distances = tree.distances();
+        assert_eq!((tree.source(), distances[17], tree.predecessors()[17]), (17, 0.0, NONE));
+
+        let linked: std::collections::HashSet<(u32, u32)> =
+            edges.chunks(2).flat_map(|e| [(e[0], e[1]), (e[1], e[0])]).collect();
+        for to in (0..500).step_by(7) {
+            // A* and the full tree agree on the length, if not always the
+            // route between equally short ones
+            let path = shortest_path(&points, &edges, 17, to).unwrap();
+            assert_eq!((path[0], *path.last().unwrap()), (17, to));
+            assert!(path.windows(2).all(|w| linked.contains(&(w[0], w[1]))));
+            let length = route_length(&points, &path);
+            assert!((length - distances[to as usize]).abs() < 1e-9);
+            let from_tree = tree.path_to(to).unwrap();
+            assert!((route_length(&points, &from_tree) - length).abs() < 1e-9);
+            // Never shorter than the straight line
+            assert!(length >= edge_length(&points, 17, to) - 1e-9);
+        }
+
+        let polyline = path_to_polyline(&points, &shortest_path(&points, &edges, 3, 4).unwrap());
+        let polyline = polyline.unwrap();
+        assert_eq!(polyline[..2], [points[6] as f32, points[7] as f32]);
+        assert_eq!(polyline[polyline.len() - 2..], [points[8] as f32, points[9] as f32]);
+    }
+
+    #[test]
+    fn test_detour_and_disconnected_graphs() {
+        // A square with one diagonal missing, and a separate pair
+        let points = [0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0, 5.0, 5.0, 6.0, 5.0];
+        let edges = [0, 1, 1, 2, 2, 3, 3, 0, 0, 2, 4, 5];
+        assert_eq!(shortest_path(&points, &edges, 1, 3).unwrap().len(), 3);
+        assert_eq!(shortest_path(&points, &edges, 0, 2).unwrap(), vec![0, 2]);
+        assert_eq!(shortest_path(&points, &edges, 2, 2).unwrap(), vec![2]);
+        assert!(shortest_path(&points, &edges, 0, 5).unwrap().is_empty());
+        let tree = shortest_path_tree(&points, &edges, 4).unwrap();
+        assert_eq!(tree.predecessors(), vec![NONE, NONE, NONE, NONE, NONE, 4]);
+        assert!(tree.distances()[0].is_infinite());
+        assert!(tree.path_to(0).unwrap().is_empty());
+        assert_eq!(tree.path_to(5).unwrap(), vec![4, 5]);
+        assert!(path_to_polyline(&points, &[]).unwrap().is_empty());
+
+        assert_eq!(code_of(shortest_path(&points, &edges, 0, 6)), ErrorCode::IndexOutOfRange);
+        assert_eq!(code_of(shortest_path(&points, &[0, 9], 0, 1)), ErrorCode::IndexOutOfRange);
+        assert_eq!(code_of(shortest_path(&points, &[0], 0, 1)), ErrorCode::InvalidLength);
+        assert_eq!(code_of(shortest_path_tree(&points, &edges, 6)), ErrorCode::IndexOutOfRange);
+        assert_eq!(code_of(tree.path_to(6)), ErrorCode::IndexOutOfRange);
+        assert_eq!(code_of(path_to_polyline(&points, &[6])), ErrorCode::IndexOutOfRange);
+        let bad = [f64::NAN, 0.0, 1.0, 0.0];
+        assert_eq!(code_of(shortest_path(&bad, &[0, 1], 0, 1)), ErrorCode::NonFiniteInput);
+    }
+}
@@ -0,0 +1,149 @@
+//! Weighted random selection over caller-provided weights
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::rng::Rng;
+
+/// Walker/Vose alias table for O(1) draws with replacement
+pub(crate) struct AliasTable {
+    prob: Vec<f64>,
+    alias: Vec<u32>,
+}
+
+impl AliasTable {
+    /// `weights` must be non-negative with a positive sum
+    pub(crate) fn new(weights: &[f64]) -> AliasTable {
+        let n = weights.len();
+        let total: f64 = weights.iter().sum();
+        let mut prob: Vec<f64> = weights.iter().map(|w| w * n as f64 / total).collect();
+        let mut alias = vec![0u32; n];
+        let (mut small, mut large): (Vec<u32>, Vec<u32>) =
+            (0..n as u32).partition(|&i| prob[i as usize] < 1.0);
+        while let (Some(&s), Some(&l)) = (small.last(), large.last()) {
+            small.pop();
+            alias[s as usize] = l;
+            prob[l as usize] -= 1.0 - prob[s as usize];
+            if prob[l as usize] < 1.0 {
+                large.pop();
+                small.push(l);
+            }
+        }
+        // Leftovers are 1 up to rounding
+        for i in small.into_iter().chain(large) {
+            prob[i as usize] = 1.0;
+        }
+        AliasTable { prob, alias }
+    }
+
+    pub(crate) fn draw(&self, rng: &mut Rng) -> u32 {
+        let i = rng.below(self.prob.len() as u32);
+        if rng.next_f64() < self.prob[i as usize] {
+            i
+        } else {
+            self.alias[i as usize]
+        }
+    }
+}
+
+/// Draw `count` indices with probability proportional to `weights`
+///
+/// With replacement this uses an alias table; without replacement it is
+/// the Efraimidis–Spirakis exponential-key method, which draws the same
+/// distribution as repeatedly sampling and removing. Indices come back in
+/// draw order. Zero-weight entries are never drawn; when `count` reaches
+/// the number of positive-weight entries in the without-replacement case,
+/// all of them are returned in ascending order. Output is fully
+/// determined by `seed`.
+#[wasm_bindgen]
+pub fn sample_edges(
+    weights: &[f32],
+    count: u32,
+    seed: u64,
+    without_replacement: bool,
+) -> Result<Vec<u32>> {
+    if weights.iter().any(|w| !w.is_finite()) {
+        return Err(Error::NonFiniteInput("weights must be finite".into()));
+    }
+    if let Some(bad) = weights.iter().find(|&&w| w < 0.0) {
+        return Err(Error::InvalidArgument(format!("weights must be non-negative, got {bad}")));
+    }
+    let w: Vec<f64> = weights.iter().map(|&w| w as f64).collect();
+    if w.iter().sum::<f64>() <= 0.0 {
+        return Err(Error::InvalidArgument("weights sum to zero".into()));
+    }
+    let mut rng = Rng::new(seed);
+
+    if !without_replacement {
+        let table = AliasTable::new(&w);
+        return Ok((0..count).map(|_| table.draw(&mut rng)).collect());
+    }
+
+    let eligible: Vec<u32> = (0..w.len() as u32).filter(|&i| w[i as usize] > 0.0).collect();
+    if count as usize >= eligible.len() {
+        return Ok(eligible);
+    }
+    // Smallest Exp(1)/w keys; u is kept away from 0 so ln is finite
+    let mut keyed: Vec<(f64, u32)> = eligible
+        .iter()
+        .map(|&i| {
+            let u = 1.0 - rng.next_f64();
+            (-u.ln() / w[i as usize], i)
+        })
+        .collect();
+    let k = count as usize;
+    if k > 0 {
+        keyed.select_nth_unstable_by(k - 1, |a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+    }
+    keyed.truncate(k);
+    keyed.sort_unstable_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+    Ok(keyed.into_iter().map(|(_, i)| i).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_with_replacement_follows_weights() {
+        let weights = [1.0, 0.0, 3.0, 6.0];
+        let draws = sample_edges(&weights, 100_000, 42, false).unwrap();
+        let mut counts = [0u32; 4];
+        draws.iter().for_each(|&i| counts[i as usize] += 1);
+        assert_eq!(counts[1], 0);
+        for (c, w) in counts.iter().zip(&weights) {
+            let expected = 100_000.0 * w / 10.0;
+            assert!((*c as f32 - expected).abs() < 0.02 * 100_000.0, "{counts:?}");
+        }
+        assert_eq!(draws, sample_edges(&weights, 100_000, 42, false).unwrap());
+        assert_ne!(draws, sample_edges(&weights, 100_000, 43, false).unwrap());
+    }
+
+    #[test]
+    fn test_without_replacement_is_distinct_and_biased() {
+        let mut weights = vec![1.0f32; 100];
+        weights[7] = 1000.0;
+        let mut heavy_first = 0;
+        for seed in 0..200 {
+            let pick = sample_edges(&weights, 10, seed, true).unwrap();
+            assert_eq!(pick.len(), 10);
+            let mut sorted = pick.clone();
+            sorted.sort_unstable();
+            sorted.dedup();
+            assert_eq!(sorted.len(), 10);
+            heavy_first += (pick[0] == 7) as u32;
+        }
+        assert!(heavy_first > 180);
+    }
+
+    #[test]
+    fn test_sampling_edge_cases() {
+        assert_eq!(sample_edges(&[0.5, 0.0, 2.0], 10, 1, true).unwrap(), vec![0, 2]);
+        assert_eq!(code_of(sample_edges(&[0.0, 0.0], 1, 1, false)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(sample_edges(&[], 1, 1, true)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(sample_edges(&[1.0, -1.0], 1, 1, true)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(sample_edges(&[1.0, f32::NAN], 1, 1, false)), ErrorCode::NonFiniteInput);
+        assert!(sample_edges(&[1.0, 2.0], 0, 1, true).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,1057 @@
+//! Self-contained animated scene
+//!
+//! `Scene` owns everything a frame depends on: golden-spiral seeds drifting
+//! on simplex noise, the incremental triangulation, motes travelling along
+//! edge slots, per-slot heat counters and a seeded RNG. Stepping is fully
+//! deterministic, so `snapshot()` captures the live state as bytes and
+//! `restore()` replays it bit-for-bit elsewhere. A `Clock` turns the real
+//! frame time into scene time, so pausing or slowing it affects drift,
+//! brightness, heat and motes alike.
+
+use wasm_bindgen::prelude::*;
+
+use crate::clock::Clock;
+use crate::codec::{ByteReader, ByteWriter};
+use crate::config::Palette;
+use crate::dynamic::DynamicTriangulation;
+use crate::error::{Error, Result};
+use crate::mesh::NONE;
+use crate::quality::QualityGovernor;
+use crate::rng::Rng;
+use crate::timing::now_ms;
+use crate::{golden_spacing, golden_spiral, simplex_noise_2d};
+
+const SNAPSHOT_MAGIC: &[u8; 4] = b"VGSC";
+const SNAPSHOT_VERSION: u8 = 7;
+/// Default `set_kinematic_smoothing` time constant, in seconds
+const DEFAULT_SMOOTHING: f64 = 0.25;
+
+/// Tunables that shape the animation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct SceneParams {
+    /// Maximum seed displacement from its spiral position, in pixels
+    pub(crate) drift_amplitude: f64,
+    /// Spatial frequency of the drift noise, per pixel
+    pub(crate) drift_frequency: f64,
+    /// How fast the drift field evolves, noise units per second
+    pub(crate) drift_speed: f64,
+    /// Mote speed range in edge lengths per second
+    pub(crate) mote_speed_min: f64,
+    pub(crate) mote_speed_max: f64,
+    /// Exponential decay rate of edge heat, per second
+    pub(crate) heat_decay: f64,
+}
+
+impl Default for SceneParams {
+    fn default() -> Self {
+        SceneParams {
+            drift_amplitude: 12.0,
+            drift_frequency: 0.004,
+            drift_speed: 0.15,
+            mote_speed_min: 0.2,
+            mote_speed_max: 0.6,
+            heat_decay: 0.5,
+        }
+    }
+}
+
+impl SceneParams {
+    fn to_array(self) -> [f64; 6] {
+        [
+            self.drift_amplitude,
+            self.drift_frequency,
+            self.drift_speed,
+            self.mote_speed_min,
+            self.mote_speed_max,
+            self.heat_decay,
+        ]
+    }
+
+    fn from_array(a: [f64; 6]) -> SceneParams {
+        SceneParams {
+            drift_amplitude: a[0],
+            drift_frequency: a[1],
+            drift_speed: a[2],
+            mote_speed_min: a[3],
+            mote_speed_max: a[4],
+            heat_decay: a[5],
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.to_array().iter().any(|v| !v.is_finite() || *v < 0.0) {
+            return Err(Error::InvalidArgument(
+                "scene parameters must be finite and non-negative".into(),
+            ));
+        }
+        if self.mote_speed_min > self.mote_speed_max {
+            return Err(Error::InvalidArgument("mote speed min exceeds max".into()));
+        }
+        Ok(())
+    }
+}
+
+/// A mote travelling along one edge slot
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Mote {
+    /// Edge slot, `NONE` while the scene has no edges
+    pub(crate) slot: u32,
+    /// Position along the edge in `[0, 1]`
+    pub(crate) progress: f64,
+    /// Edge lengths per second
+    pub(crate) speed: f64,
+}
+
+/// Seed state streamed in from outside, replacing the drift field
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Kinematic {
+    /// Latest ingested positions, extrapolated along `velocities` since
+    pub(crate) target: Vec<f64>,
+    /// Pixels per second of scene time, per seed
+    pub(crate) velocities: Vec<f64>,
+    /// Timestamp of the latest accepted packet
+    pub(crate) timestamp: f64,
+}
+
+/// Seeds, triangulation and motes advanced together one frame at a time
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Scene {
+    pub(crate) width: f64,
+    pub(crate) height: f64,
+    /// Spiral radius step, fixed at construction so that changing the seed
+    /// count never moves existing seeds
+    pub(crate) spacing: f64,
+    pub(crate) params: SceneParams,
+    /// Seed the RNG was created from, kept for `current_config`
+    pub(crate) seed: u32,
+    pub(crate) rng: Rng,
+    pub(crate) clock: Clock,
+    pub(crate) frame: u32,
+    /// Undisplaced seed positions `[x, y, ...]`
+    pub(crate) base: Vec<f64>,
+    /// Current seed positions after drift
+    pub(crate) positions: Vec<f64>,
+    pub(crate) tri: DynamicTriangulation,
+    pub(crate) motes: Vec<Mote>,
+    /// Heat per edge slot, bumped each time a mote arrives on the edge
+    pub(crate) heat: Vec<f32>,
+    /// Ramp `edge_colors` maps heat through, if one was configured
+    pub(crate) palette: Option<Palette>,
+    /// Picks the tier `update` runs at; device-specific, so not part of
+    /// snapshots
+    pub(crate) governor: QualityGovernor,
+    /// Scene time motes have yet to travel while tiers skip their steps
+    pub(crate) mote_dt: f64,
+    /// Streamed seed motion, once `ingest_kinematic` has been called
+    pub(crate) kinematic: Option<Kinematic>,
+    /// Time constant of the blend from shown to ingested positions, in
+    /// seconds
+    pub(crate) smoothing: f64,
+}
+
+#[wasm_bindgen]
+impl Scene {
+    /// Build a scene of `seed_count` spiral seeds and `mote_count` motes;
+    /// `seed` drives every random choice the scene makes
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        width: f64,
+        height: f64,
+        seed_count: usize,
+        mote_count: usize,
+        seed: u32,
+    ) -> Result<Scene> {
+        Scene::with_params(width, height, seed_count, mote_count, seed, SceneParams::default())
+    }
+
+    /// Advance the scene by `real_dt` seconds of wall time, scaled by the
+    /// clock's speed
+    ///
+    /// Running backwards replays drift and brightness exactly, since they
+    /// depend only on the clock; motes travel back along their edges and
+    /// jump to the far end of a new one, and heat keeps decaying. Above
+    /// tier 0 some of the work is skipped; see `QualityGovernor`.
+    pub fn update(&mut self, real_dt: f64) -> Result<()> {
+        let start = now_ms();
+        let dt = self.clock.advance(real_dt)?;
+        self.frame = self.frame.wrapping_add(1);
+        let tier = self.governor.tier;
+        let odd = self.frame % 2 == 1;
+        if self.kinematic.is_some() {
+            self.follow(dt);
+        } else if tier < 3 || !odd {
+            self.displace();
+        }
+        if tier < 2 {
+            self.tri.update_points(&self.positions)?;
+        }
+        self.sync_heat((-self.params.heat_decay * dt.abs()).exp() as f32);
+
+        self.mote_dt += dt;
+        if tier < 1 || !odd {
+            let mote_dt = std::mem::take(&mut self.mote_dt);
+            self.step_motes(mote_dt);
+        } else if tier < 2 {
+            // The triangulation moved on without the motes; any left on a
+            // freed edge slot must still land on a live one
+            self.reseat_orphans();
+        }
+        self.governor.record(now_ms() - start);
+        Ok(())
+    }
+
+    /// Feed one packet of streamed seed state, one entry per seed
+    ///
+    /// From the first packet on, seeds stop drifting: each `update` moves
+    /// them along the latest velocities and blends them toward the
+    /// latest positions, extrapolated by the same velocities, with time
+    /// constant `set_kinematic_smoothing`. A packet thus never makes seeds
+    /// jump; the remaining error shrinks by `1/e` per time constant.
+    /// Packets not newer than the last accepted one are ignored and
+    /// reported by returning `false`.
+    pub fn ingest_kinematic(
+        &mut self,
+        points: &[f64],
+        velocities: &[f64],
+        timestamp: f64,
+    ) -> Result<bool> {
+        let n = self.base.len();
+        if points.len() != n || velocities.len() != n {
+            return Err(Error::InvalidLength(format!(
+                "expected {n} values for {} seeds, got {} points and {} velocities",
+                n / 2,
+                points.len(),
+                velocities.len()
+            )));
+        }
+        if !timestamp.is_finite() || points.iter().chain(velocities).any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("kinematic data must be finite".into()));
+        }
+        if self.kinematic.as_ref().is_some_and(|k| timestamp <= k.timestamp) {
+            return Ok(false);
+        }
+        self.kinematic = Some(Kinematic {
+            target: points.to_vec(),
+            velocities: velocities.to_vec(),
+            timestamp,
+        });
+        Ok(true)
+    }
+
+    /// Time constant in seconds for converging on ingested positions; 0
+    /// snaps to them on the next update
+    pub fn set_kinematic_smoothing(&mut self, seconds: f64) -> Result<()> {
+        if !seconds.is_finite() || seconds < 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "smoothing must be finite and non-negative, got {seconds}"
+            )));
+        }
+        self.smoothing = seconds;
+        Ok(())
+    }
+
+    /// Flip hysteresis of the triangulation; see
+    /// `DynamicTriangulation::set_flip_hysteresis`
+    pub fn set_flip_hysteresis(&mut self, threshold: f64) -> Result<()> {
+        self.tri.set_flip_hysteresis(threshold)
+    }
+
+    /// Per-frame cost target in milliseconds for automatic tiering; 0
+    /// turns it off
+    pub fn set_budget_ms(&mut self, ms: f64) -> Result<()> {
+        self.governor.set_budget_ms(ms)
+    }
+
+    /// Quality tier the next `update` runs at, 0 being full quality
+    pub fn current_tier(&self) -> u32 {
+        self.governor.tier
+    }
+
+    /// Pin a tier regardless of the budget
+    pub fn lock_tier(&mut self, tier: u32) -> Result<()> {
+        self.governor.lock_tier(tier)
+    }
+
+    pub fn unlock_tier(&mut self) {
+        self.governor.unlock_tier();
+    }
+
+    /// Drain `[from, to]` pairs of tier changes since the previous call
+    pub fn tier_events(&mut self) -> Vec<u32> {
+        self.governor.tier_events()
+    }
+
+    /// Playback rate: 1 is real time, 0.5 half speed, negative reverses
+    pub fn set_speed(&mut self, speed: f64) -> Result<()> {
+        self.clock.set_speed(speed)
+    }
+
+    /// Freeze scene time; `update` still re-renders the frame
+    pub fn pause(&mut self) {
+        self.clock.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.clock.resume();
+    }
+
+    /// Jump to scene time `t`
+    ///
+    /// Seed positions, the triangulation and mote brightness are functions
+    /// of time and match a scene that played continuously to `t`. Mote
+    /// travel and edge heat depend on the path taken and are not
+    /// reconstructed: they carry on from their current state, with motes
+    /// whose edge vanished moved to a random live one.
+    pub fn scrub_to(&mut self, t: f64) -> Result<()> {
+        self.clock.scrub_to(t)?;
+        self.redrift()?;
+        self.sync_heat(1.0);
+        self.reseat_orphans();
+        Ok(())
+    }
+
+    /// Copy of the scene's clock state
+    pub fn clock(&self) -> Clock {
+        self.clock
+    }
+
+    /// Current seed positions `[x, y, ...]`
+    pub fn positions(&self) -> Vec<f64> {
+        self.positions.clone()
+    }
+
+    /// Current triangles as seed indices
+    pub fn triangles(&self) -> Vec<u32> {
+        self.tri.triangles()
+    }
+
+    /// Edge slots as `[i, j, ...]`; see `DynamicTriangulation::edges_indexed`
+    pub fn edges_indexed(&self) -> Vec<u32> {
+        self.tri.edges_indexed()
+    }
+
+    /// Drain `[kind, slot, i, j]` edge events since the previous call
+    pub fn edge_events(&mut self) -> Vec<u32> {
+        self.tri.edge_events()
+    }
+
+    /// Mote render data `[x, y, brightness, ...]`; motes without an edge
+    /// are omitted
+    pub fn mote_positions(&self) -> Vec<f32> {
+        self.mote_render().flat_map(|m| m.map(|v| v as f32)).collect()
+    }
+
+    /// Heat per edge slot, aligned with `edges_indexed()`
+    pub fn edge_heat(&self) -> Vec<f32> {
+        self.heat.clone()
+    }
+
+    /// Current scene time in seconds
+    pub fn time(&self) -> f64 {
+        self.clock.time
+    }
+
+    /// Updates applied so far (wrapping)
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn seed_count(&self) -> usize {
+        self.base.len() / 2
+    }
+
+    pub fn mote_count(&self) -> usize {
+        self.motes.len()
+    }
+
+    /// Grow or shrink the spiral to `count` seeds without a rebuild
+    ///
+    /// Seeds keep their spiral positions: new ones are appended at the rim
+    /// and inserted into the triangulation, shrinking removes the
+    /// highest-index seeds. Edge slots change through the usual events,
+    /// and motes whose edge disappeared move to a random live one.
+    ///
+    /// With streamed seeds, new ones hold still at their spiral position
+    /// until the next packet.
+    pub fn set_seed_count(&mut self, count: usize) -> Result<()> {
+        if count < 3 {
+            return Err(Error::InvalidArgument(format!("need at least 3 seeds, got {count}")));
+        }
+        let current = self.seed_count();
+        if count > current {
+            let added = golden_spiral(self.width, self.height, self.spacing, current..count);
+            let moved: Vec<f64> = added.chunks_exact(2).flat_map(|b| self.drifted(b)).collect();
+            self.tri.append_points(&moved)?;
+            self.base.extend_from_slice(&added);
+            self.positions.extend_from_slice(&moved);
+            if let Some(k) = &mut self.kinematic {
+                k.target.extend_from_slice(&moved);
+                k.velocities.resize(count * 2, 0.0);
+            }
+        } else if count < current {
+            self.tri.truncate_points(count)?;
+            self.base.truncate(count * 2);
+            self.positions.truncate(count * 2);
+            if let Some(k) = &mut self.kinematic {
+                k.target.truncate(count * 2);
+                k.velocities.truncate(count * 2);
+            }
+        }
+
+        self.sync_heat(1.0);
+        self.reseat_orphans();
+        Ok(())
+    }
+
+    /// Grow or shrink the mote pool to `count`
+    ///
+    /// Motes beyond `count` are dropped; new ones start on a random live
+    /// edge like those of a fresh scene.
+    pub fn set_mote_count(&mut self, count: usize) {
+        self.motes.truncate(count);
+        while self.motes.len() < count {
+            let mote = spawn_mote(&mut self.rng, &self.tri, &self.params);
+            self.motes.push(mote);
+        }
+    }
+
+    /// Edge colors `[r, g, b, a, ...]` per slot, aligned with
+    /// `edges_indexed()`: each slot's heat looked up in the configured
+    /// palette; empty when no palette is set
+    pub fn edge_colors(&self) -> Vec<u8> {
+        self.palette.as_ref().map_or_else(Vec::new, |p| p.ramp.map(&self.heat))
+    }
+
+    /// Seed drift: amplitude in pixels, noise frequency per pixel, and
+    /// evolution speed per second
+    pub fn set_drift(&mut self, amplitude: f64, frequency: f64, speed: f64) -> Result<()> {
+        let params = SceneParams {
+            drift_amplitude: amplitude,
+            drift_frequency: frequency,
+            drift_speed: speed,
+            ..self.params
+        };
+        params.validate()?;
+        self.params = params;
+        Ok(())
+    }
+
+    /// Speed range for motes picking a new edge, in edge lengths per second
+    pub fn set_mote_speed(&mut self, min: f64, max: f64) -> Result<()> {
+        let params = SceneParams { mote_speed_min: min, mote_speed_max: max, ..self.params };
+        params.validate()?;
+        self.params = params;
+        Ok(())
+    }
+
+    /// Exponential decay rate of edge heat, per second
+    pub fn set_heat_decay(&mut self, rate: f64) -> Result<()> {
+        let params = SceneParams { heat_decay: rate, ..self.params };
+        params.validate()?;
+        self.params = params;
+        Ok(())
+    }
+
+    /// Serialize the complete live state
+    ///
+    /// The bytes are versioned; `Scene.restore` on them yields a scene whose
+    /// subsequent updates match this one bit for bit at tier 0. The quality
+    /// governor is device-specific and not captured: a restored scene has
+    /// no budget and runs at tier 0.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut w = ByteWriter::new();
+        w.bytes(SNAPSHOT_MAGIC);
+        w.u8(SNAPSHOT_VERSION);
+        w.f64(self.width);
+        w.f64(self.height);
+        w.f64(self.spacing);
+        self.params.to_array().iter().for_each(|&v| w.f64(v));
+        w.u32(self.seed);
+        w.u64(self.rng.state);
+        w.u64(self.rng.inc);
+        w.f64(self.clock.time);
+        w.f64(self.clock.speed);
+        w.u8(self.clock.paused as u8);
+        w.u32(self.frame);
+        w.f64s(&self.base);
+        w.f64s(&self.positions);
+        self.tri.encode(&mut w);
+        w.u32s(&self.motes.iter().map(|m| m.slot).collect::<Vec<u32>>());
+        w.f64s(&self.motes.iter().map(|m| m.progress).collect::<Vec<f64>>());
+        w.f64s(&self.motes.iter().map(|m| m.speed).collect::<Vec<f64>>());
+        w.f32s(&self.heat);
+        w.u8(self.palette.is_some() as u8);
+        if let Some(palette) = &self.palette {
+            palette.encode(&mut w);
+        }
+        w.f64(self.smoothing);
+        w.u8(self.kinematic.is_some() as u8);
+        if let Some(k) = &self.kinematic {
+            w.f64(k.timestamp);
+            w.f64s(&k.target);
+            w.f64s(&k.velocities);
+        }
+        w.buf
+    }
+
+    /// Rebuild a scene from `snapshot()` bytes; errors on truncated,
+    /// corrupt or version-mismatched data
+    pub fn restore(bytes: &[u8]) -> Result<Scene> {
+        let mut r = ByteReader::new(bytes);
+        let version = r.header(SNAPSHOT_MAGIC)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(Error::VersionMismatch(format!(
+                "snapshot version {version}, expected {SNAPSHOT_VERSION}"
+            )));
+        }
+        let width = r.f64()?;
+        let height = r.f64()?;
+        let spacing = r.f64()?;
+        let mut params = [0.0; 6];
+        for v in &mut params {
+            *v = r.f64()?;
+        }
+        let params = SceneParams::from_array(params);
+        let seed = r.u32()?;
+        let rng = Rng { state: r.u64()?, inc: r.u64()? };
+        let time = r.f64()?;
+        let clock_speed = r.f64()?;
+        let paused = r.u8()?;
+        let frame = r.u32()?;
+        let base = r.f64s()?;
+        let positions = r.f64s()?;
+        let tri = DynamicTriangulation::decode(&mut r)?;
+        let slots = r.u32s()?;
+        let progress = r.f64s()?;
+        let speed = r.f64s()?;
+        let heat = r.f32s()?;
+        let palette = match r.u8()? {
+            0 => None,
+            1 => Some(Palette::decode(&mut r)?),
+            _ => return Err(Error::InvalidData("snapshot: bad palette flag".into())),
+        };
+        let smoothing = r.f64()?;
+        let kinematic = match r.u8()? {
+            0 => None,
+            1 => Some(Kinematic { timestamp: r.f64()?, target: r.f64s()?, velocities: r.f64s()? }),
+            _ => return Err(Error::InvalidData("snapshot: bad kinematic flag".into())),
+        };
+        r.finish()?;
+
+        let bad = |what: &str| Error::InvalidData(format!("snapshot: {what}"));
+        params.validate().map_err(|_| bad("parameters out of range"))?;
+        if !(spacing.is_finite() && spacing > 0.0) {
+            return Err(bad("spiral spacing must be positive"));
+        }
+        if base.len() != positions.len() || base.len() != 2 * tri.point_count() {
+            return Err(bad("seed arrays disagree with the triangulation"));
+        }
+        if slots.len() != progress.len() || slots.len() != speed.len() {
+            return Err(bad("mote arrays disagree in length"));
+        }
+        if heat.len() != tri.edge_slot_count()
+            || slots.iter().any(|&s| s != NONE && s as usize >= heat.len())
+        {
+            return Err(bad("edge slot references out of range"));
+        }
+        if slots.iter().any(|&s| s != NONE && tri.edges.slots[s as usize].0 == NONE) {
+            return Err(bad("mote on a free edge slot"));
+        }
+        if !(time.is_finite() && clock_speed.is_finite()) || paused > 1 {
+            return Err(bad("clock state out of range"));
+        }
+        if rng.inc & 1 == 0 {
+            return Err(bad("rng increment must be odd"));
+        }
+        if !(smoothing.is_finite() && smoothing >= 0.0) {
+            return Err(bad("smoothing out of range"));
+        }
+        if let Some(k) = &kinematic {
+            let finite = k.target.iter().chain(&k.velocities).all(|v| v.is_finite());
+            if k.target.len() != base.len() || k.velocities.len() != base.len() || !finite {
+                return Err(bad("kinematic state disagrees with the seeds"));
+            }
+        }
+        let motes = slots
+            .iter()
+            .zip(&progress)
+            .zip(&speed)
+            .map(|((&slot, &progress), &speed)| Mote { slot, progress, speed })
+            .collect();
+        Ok(Scene {
+            width,
+            height,
+            spacing,
+            params,
+            seed,
+            rng,
+            clock: Clock { time, speed: clock_speed, paused: paused == 1 },
+            frame,
+            base,
+            positions,
+            tri,
+            motes,
+            heat,
+            palette,
+            governor: QualityGovernor::new(),
+            mote_dt: 0.0,
+            kinematic,
+            smoothing,
+        })
+    }
+}
+
+impl Scene {
+    /// `Scene::new` with explicit tunables, so the first motes already use
+    /// the configured speed range
+    pub(crate) fn with_params(
+        width: f64,
+        height: f64,
+        seed_count: usize,
+        mote_count: usize,
+        seed: u32,
+        params: SceneParams,
+    ) -> Result<Scene> {
+        params.validate()?;
+        if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+            return Err(Error::InvalidArgument("width and height must be positive".into()));
+        }
+        if seed_count < 3 {
+            return Err(Error::InvalidArgument(format!(
+                "need at least 3 seeds, got {seed_count}"
+            )));
+        }
+        let spacing = golden_spacing(width, height, seed_count);
+        let base = golden_spiral(width, height, spacing, 0..seed_count);
+        let tri = DynamicTriangulation::new(&base, width, height)?;
+        let mut rng = Rng::new(seed as u64);
+        let motes = (0..mote_count).map(|_| spawn_mote(&mut rng, &tri, &params)).collect();
+        let heat = vec![0.0; tri.edge_slot_count()];
+        Ok(Scene {
+            width,
+            height,
+            spacing,
+            params,
+            seed,
+            rng,
+            clock: Clock::new(),
+            frame: 0,
+            positions: base.clone(),
+            base,
+            tri,
+            motes,
+            heat,
+            palette: None,
+            governor: QualityGovernor::new(),
+            mote_dt: 0.0,
+            kinematic: None,
+            smoothing: DEFAULT_SMOOTHING,
+        })
+    }
+
+    /// Base position `b` displaced by the drift field at the current time,
+    /// on two decorrelated noise channels
+    fn drifted(&self, b: &[f64]) -> [f64; 2] {
+        let p = self.params;
+        let t = self.clock.time * p.drift_speed;
+        let (nx, ny) = (b[0] * p.drift_frequency, b[1] * p.drift_frequency);
+        [
+            b[0] + p.drift_amplitude * simplex_noise_2d(nx + t, ny),
+            b[1] + p.drift_amplitude * simplex_noise_2d(nx + 31.7, ny + t),
+        ]
+    }
+
+    /// `[x, y, brightness]` of every mote on an edge
+    pub(crate) fn mote_render(&self) -> impl Iterator<Item = [f64; 3]> + '_ {
+        self.motes.iter().enumerate().filter(|(_, m)| m.slot != NONE).map(|(i, m)| {
+            let (a, b) = self.tri.edges.slots[m.slot as usize];
+            let (a, b) = (a as usize * 2, b as usize * 2);
+            let p = &self.positions;
+            let x = p[a] + (p[b] - p[a]) * m.progress;
+            let y = p[a + 1] + (p[b + 1] - p[a + 1]) * m.progress;
+            let brightness = 0.5 + 0.5 * (self.clock.time * 2.0 + (i as f64) * 0.5).sin();
+            [x, y, brightness]
+        })
+    }
+
+    /// Move every seed to its drifted position and patch the triangulation;
+    /// streamed seeds stay where they are
+    fn redrift(&mut self) -> Result<()> {
+        if self.kinematic.is_none() {
+            self.displace();
+        }
+        self.tri.update_points(&self.positions)
+    }
+
+    /// Carry streamed seeds and their targets `dt` along the velocities,
+    /// then blend the seeds toward the targets
+    fn follow(&mut self, dt: f64) {
+        let Some(k) = &mut self.kinematic else { return };
+        let blend = if self.smoothing == 0.0 {
+            1.0
+        } else {
+            1.0 - (-dt.abs() / self.smoothing).exp()
+        };
+        let state = self.positions.iter_mut().zip(&mut k.target).zip(&k.velocities);
+        for ((p, t), &v) in state {
+            *t += v * dt;
+            *p += v * dt;
+            *p += (*t - *p) * blend;
+        }
+    }
+
+    /// Move every seed to its drifted position, leaving the triangulation
+    fn displace(&mut self) {
+        for i in 0..self.base.len() / 2 {
+            let [x, y] = self.drifted(&self.base[i * 2..i * 2 + 2]);
+            self.positions[i * 2] = x;
+            self.positions[i * 2 + 1] = y;
+        }
+    }
+
+    /// Advance every mote by `dt` of scene time, moving those that run off
+    /// their edge, or whose edge died, to a random live one
+    fn step_motes(&mut self, dt: f64) {
+        let slots = self.tri.edge_slot_count();
+        for i in 0..self.motes.len() {
+            let mote = self.motes[i];
+            let live = (mote.slot as usize) < slots
+                && self.tri.edges.slots[mote.slot as usize].0 != NONE;
+            let progress = mote.progress + mote.speed * dt;
+            if !live || !(0.0..=1.0).contains(&progress) {
+                let slot = pick_slot(&mut self.rng, &self.tri);
+                if slot != NONE {
+                    self.heat[slot as usize] += 1.0;
+                }
+                self.motes[i].slot = slot;
+                self.motes[i].progress = if dt < 0.0 { 1.0 } else { 0.0 };
+            } else {
+                self.motes[i].progress = progress;
+            }
+        }
+    }
+
+    /// Fit heat to the slot table and scale live slots by `decay`
+    ///
+    /// Heat follows its slot: freed slots go cold, new slots start cold.
+    fn sync_heat(&mut self, decay: f32) {
+        let slots = &self.tri.edges.slots;
+        self.heat.resize(slots.len(), 0.0);
+        for (h, &(a, _)) in self.heat.iter_mut().zip(slots) {
+            *h = if a == NONE { 0.0 } else { *h * decay };
+        }
+    }
+
+    /// Move motes whose edge slot died onto a random live one
+    fn reseat_orphans(&mut self) {
+        for i in 0..self.motes.len() {
+            let slot = self.motes[i].slot;
+            if slot == NONE || self.tri.edges.slots[slot as usize].0 == NONE {
+                self.motes[i].slot = pick_slot(&mut self.rng, &self.tri);
+                self.motes[i].progress = 0.0;
+            }
+        }
+    }
+}
+
+fn spawn_mote(rng: &mut Rng, tri: &DynamicTriangulation, params: &SceneParams) -> Mote {
+    Mote {
+        slot: pick_slot(rng, tri),
+        progress: rng.next_f64(),
+        speed: rng.range(params.mote_speed_min, params.mote_speed_max),
+    }
+}
+
+/// Uniformly random live edge slot, `NONE` if there are none
+///
+/// A random start is probed forward to the next live slot, so the choice
+/// depends only on the slot table and the RNG state.
+fn pick_slot(rng: &mut Rng, tri: &DynamicTriangulation) -> u32 {
+    let slots = &tri.edges.slots;
+    if tri.edge_count() == 0 {
+        return NONE;
+    }
+    let start = rng.below(slots.len() as u32) as usize;
+    (0..slots.len())
+        .map(|k| (start + k) % slots.len())
+        .find(|&s| slots[s].0 != NONE)
+        .map_or(NONE, |s| s as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    /// Per-frame (positions, motes, edge slots, heat)
+    type Frame = (Vec<f64>, Vec<f32>, Vec<u32>, Vec<f32>);
+
+    fn run(scene: &mut Scene, steps: usize) -> Vec<Frame> {
+        (0..steps)
+            .map(|_| {
+                scene.update(1.0 / 60.0).unwrap();
+                (
+                    scene.positions(),
+                    scene.mote_positions(),
+                    scene.edges_indexed(),
+                    scene.edge_heat(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_restore_then_simulate_is_bit_identical() {
+        let mut scene = Scene::new(800.0, 600.0, 150, 60, 9).unwrap();
+        scene.set_drift(25.0, 0.01, 0.8).unwrap();
+        run(&mut scene, 90);
+        scene.edge_events();
+
+        let bytes = scene.snapshot();
+        let mut replay = Scene::restore(&bytes).unwrap();
+        assert_eq!(replay.snapshot(), bytes);
+
+        let a = run(&mut scene, 240);
+        let b = run(&mut replay, 240);
+        for (frame, (x, y)) in a.iter().zip(&b).enumerate() {
+            let same_bits = x.0.iter().zip(&y.0).all(|(p, q)| p.to_bits() == q.to_bits())
+                && x.1.iter().zip(&y.1).all(|(p, q)| p.to_bits() == q.to_bits())
+                && x.3.iter().zip(&y.3).all(|(p, q)| p.to_bits() == q.to_bits());
+            assert!(same_bits && x.1.len() == y.1.len(), "diverged on frame {frame}");
+            assert_eq!(x.2, y.2, "edge slots diverged on frame {frame}");
+        }
+        assert_eq!(scene.edge_events(), replay.edge_events());
+        assert_eq!(scene.snapshot(), replay.snapshot());
+    }
+
+    #[test]
+    fn test_restore_rejects_corrupt_input() {
+        let mut scene = Scene::new(400.0, 400.0, 40, 10, 1).unwrap();
+        run(&mut scene, 10);
+        let bytes = scene.snapshot();
+
+        assert_eq!(code_of(Scene::restore(&[])), ErrorCode::InvalidData);
+        assert_eq!(code_of(Scene::restore(&bytes[..bytes.len() - 1])), ErrorCode::InvalidData);
+        assert_eq!(code_of(Scene::restore(&bytes[..bytes.len() / 2])), ErrorCode::InvalidData);
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(code_of(Scene::restore(&trailing)), ErrorCode::InvalidData);
+
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        assert_eq!(code_of(Scene::restore(&magic)), ErrorCode::InvalidData);
+
+        let mut version = bytes.clone();
+        version[4] = SNAPSHOT_VERSION + 1;
+        assert_eq!(code_of(Scene::restore(&version)), ErrorCode::VersionMismatch);
+
+        // A mote riding an edge slot the triangulation has freed
+        let mut drifted = Scene::new(400.0, 400.0, 40, 10, 1).unwrap();
+        drifted.set_drift(60.0, 0.03, 3.0).unwrap();
+        run(&mut drifted, 120);
+        let free = *drifted.tri.edges.free.first().expect("drift frees edge slots");
+        drifted.motes[0].slot = free;
+        assert_eq!(code_of(Scene::restore(&drifted.snapshot())), ErrorCode::InvalidData);
+
+        // Flipping any single byte must error or decode, never panic
+        for i in 0..bytes.len() {
+            let mut flipped = bytes.clone();
+            flipped[i] ^= 0xFF;
+            let _ = Scene::restore(&flipped);
+        }
+    }
+
+    #[test]
+    fn test_motes_stay_on_live_edges() {
+        let mut scene = Scene::new(600.0, 400.0, 80, 40, 3).unwrap();
+        scene.set_drift(40.0, 0.02, 2.0).unwrap();
+        for _ in 0..200 {
+            scene.update(1.0 / 30.0).unwrap();
+            for m in &scene.motes {
+                assert_ne!(scene.tri.edges.slots[m.slot as usize].0, NONE);
+                assert!((0.0..=1.0).contains(&m.progress));
+            }
+        }
+        assert_eq!(scene.mote_positions().len(), 40 * 3);
+        assert!(scene.edge_heat().iter().any(|&h| h > 0.0));
+    }
+
+    #[test]
+    fn test_seed_slider_round_trip_matches_fresh_build() {
+        let mut scene = Scene::new(800.0, 600.0, 100, 50, 4).unwrap();
+        let steps = (150..=2000).step_by(50).chain((100..=1950).rev().step_by(50));
+        for n in steps {
+            scene.set_seed_count(n).unwrap();
+            assert!(!scene.tri.last_update_rebuilt(), "rebuilt at {n} seeds");
+            assert_eq!(scene.seed_count(), n);
+            assert_eq!(scene.edge_heat().len(), scene.tri.edge_slot_count());
+            for m in &scene.motes {
+                assert_ne!(scene.tri.edges.slots[m.slot as usize].0, NONE);
+            }
+        }
+        let fresh = Scene::new(800.0, 600.0, 100, 50, 4).unwrap();
+        assert_eq!(scene.base, fresh.base);
+        assert_eq!(scene.positions, fresh.positions);
+        assert_eq!(scene.tri.live_edges(), fresh.tri.live_edges());
+        let canonical = |t: Vec<u32>| {
+            let mut tris: Vec<[u32; 3]> = t
+                .chunks(3)
+                .map(|c| {
+                    let r = (0..3).min_by_key(|&k| c[k]).unwrap();
+                    [c[r], c[(r + 1) % 3], c[(r + 2) % 3]]
+                })
+                .collect();
+            tris.sort_unstable();
+            tris
+        };
+        assert_eq!(canonical(scene.triangles()), canonical(fresh.triangles()));
+
+        // Still animates and snapshots after resizing
+        scene.set_seed_count(180).unwrap();
+        run(&mut scene, 5);
+        let replay = Scene::restore(&scene.snapshot()).unwrap();
+        assert_eq!(replay.seed_count(), 180);
+        assert_eq!(code_of(scene.set_seed_count(2)), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_scrub_matches_continuous_play() {
+        let mut played = Scene::new(800.0, 600.0, 200, 30, 5).unwrap();
+        played.set_drift(30.0, 0.01, 1.5).unwrap();
+        let mut scrubbed = played.clone();
+        run(&mut played, 150);
+
+        scrubbed.scrub_to(played.time()).unwrap();
+        assert_eq!(scrubbed.positions, played.positions);
+        for _ in 0..30 {
+            played.update(1.0 / 60.0).unwrap();
+            scrubbed.update(1.0 / 60.0).unwrap();
+            assert_eq!(scrubbed.time().to_bits(), played.time().to_bits());
+            assert_eq!(scrubbed.positions, played.positions);
+            assert_eq!(scrubbed.tri.live_edges(), played.tri.live_edges());
+        }
+        for m in &scrubbed.motes {
+            assert_ne!(scrubbed.tri.edges.slots[m.slot as usize].0, NONE);
+        }
+        assert_eq!(code_of(scrubbed.scrub_to(f64::NAN)), ErrorCode::NonFiniteInput);
+    }
+
+    #[test]
+    fn test_pause_slow_motion_and_reverse() {
+        let mut scene = Scene::new(600.0, 400.0, 80, 40, 6).unwrap();
+        scene.set_drift(40.0, 0.02, 2.0).unwrap();
+        run(&mut scene, 20);
+        let t = scene.time();
+
+        // Paused frames change nothing the clock drives
+        scene.pause();
+        let (positions, motes) = (scene.positions(), scene.mote_positions());
+        run(&mut scene, 10);
+        assert_eq!((scene.time(), scene.positions()), (t, positions));
+        assert_eq!(scene.mote_positions(), motes);
+
+        scene.resume();
+        scene.set_speed(0.5).unwrap();
+        scene.update(0.2).unwrap();
+        assert!((scene.time() - (t + 0.1)).abs() < 1e-12);
+
+        // Reverse back past zero: state stays valid and drift retraces
+        scene.set_speed(-2.0).unwrap();
+        let mut forward = Scene::new(600.0, 400.0, 80, 40, 6).unwrap();
+        forward.set_drift(40.0, 0.02, 2.0).unwrap();
+        for _ in 0..60 {
+            scene.update(1.0 / 30.0).unwrap();
+            for m in &scene.motes {
+                assert_ne!(scene.tri.edges.slots[m.slot as usize].0, NONE);
+                assert!((0.0..=1.0).contains(&m.progress));
+            }
+        }
+        assert!(scene.time() < 0.0);
+        forward.scrub_to(scene.time()).unwrap();
+        assert_eq!(forward.positions, scene.positions);
+        let replay = Scene::restore(&scene.snapshot()).unwrap();
+        assert_eq!(replay.clock(), scene.clock());
+    }
+
+    #[test]
+    fn test_locked_tiers_skip_their_work() {
+        let mut scene = Scene::new(400.0, 300.0, 200, 50, 3).unwrap();
+        scene.set_drift(40.0, 0.01, 2.0).unwrap();
+        run(&mut scene, 2);
+
+        scene.lock_tier(1).unwrap();
+        let motes = scene.motes.clone();
+        scene.update(1.0 / 60.0).unwrap();
+        assert_eq!(scene.motes, motes, "odd frames leave motes alone");
+        scene.update(1.0 / 60.0).unwrap();
+        assert_ne!(scene.motes, motes);
+        assert_eq!(scene.mote_dt, 0.0);
+
+        scene.lock_tier(2).unwrap();
+        let (edges, positions) = (scene.edges_indexed(), scene.positions());
+        run(&mut scene, 60);
+        assert_eq!(scene.edges_indexed(), edges);
+        assert_ne!(scene.positions(), positions);
+
+        scene.lock_tier(0).unwrap();
+        run(&mut scene, 1);
+        let fresh = DynamicTriangulation::new(&scene.positions(), 400.0, 300.0).unwrap();
+        assert_eq!(scene.tri.edge_count(), fresh.edge_count());
+        assert_eq!(scene.tier_events(), vec![0, 1, 1, 2, 2, 0]);
+        assert_eq!(code_of(scene.lock_tier(4)), ErrorCode::IndexOutOfRange);
+    }
+
+    #[test]
+    fn test_skipped_mote_frames_keep_motes_on_live_edges() {
+        let mut scene = Scene::new(400.0, 300.0, 120, 60, 5).unwrap();
+        scene.set_drift(60.0, 0.03, 3.0).unwrap();
+        scene.lock_tier(1).unwrap();
+        for _ in 0..400 {
+            scene.update(1.0 / 30.0).unwrap();
+            assert_eq!(scene.mote_positions().len(), 60 * 3);
+            for m in &scene.motes {
+                assert_ne!(scene.tri.edges.slots[m.slot as usize].0, NONE);
+            }
+        }
+    }
+
+    #[test]
+    fn test_kinematic_seeds_converge_without_popping() {
+        let mut scene = Scene::new(400.0, 300.0, 30, 10, 2).unwrap();
+        scene.set_kinematic_smoothing(0.2).unwrap();
+        let start = scene.positions();
+        let truth: Vec<f64> = start.iter().map(|v| v + 20.0).collect();
+        let velocity: Vec<f64> = (0..60).map(|i| if i % 2 == 0 { 30.0 } else { -10.0 }).collect();
+        assert!(scene.ingest_kinematic(&truth, &velocity, 1.0).unwrap());
+        // Stale and repeated packets are dropped
+        assert!(!scene.ingest_kinematic(&start, &velocity, 1.0).unwrap());
+        assert!(!scene.ingest_kinematic(&start, &velocity, 0.5).unwrap());
+
+        let dt = 1.0 / 60.0;
+        let mut prev = scene.positions();
+        for frame in 1..=60 {
+            scene.update(dt).unwrap();
+            let now = scene.positions();
+            let step = now.iter().zip(&prev).map(|(a, b)| (a - b).abs()).fold(0.0, f64::max);
+            assert!(step < 2.5, "seeds jumped {step}px on frame {frame}");
+            prev = now;
+        }
+        // One second is five time constants: under 1% of the 20px error
+        for i in 0..60 {
+            let expected = truth[i] + velocity[i] * 60.0 * dt;
+            assert!((prev[i] - expected).abs() < 0.2, "seed value {i}");
+        }
+        let replay = Scene::restore(&scene.snapshot()).unwrap();
+        assert_eq!(replay.kinematic, scene.kinematic);
+
+        let mut bad = truth.clone();
+        bad[0] = f64::NAN;
+        let ingest =
+            |scene: &mut Scene, p: &[f64]| code_of(scene.ingest_kinematic(p, &velocity, 2.0));
+        assert_eq!(ingest(&mut scene, &truth[2..]), ErrorCode::InvalidLength);
+        assert_eq!(ingest(&mut scene, &bad), ErrorCode::NonFiniteInput);
+        assert_eq!(code_of(scene.set_kinematic_smoothing(-1.0)), ErrorCode::InvalidArgument);
+    }
+}
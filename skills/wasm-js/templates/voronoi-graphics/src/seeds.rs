@@ -0,0 +1,858 @@
+//! Seed generators: the golden spiral with explicit canvas fitting, and
+//! R2, jittered-grid, Halton and Poisson-disk layouts
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::rng::Rng;
+use crate::GOLDEN_ANGLE;
+
+/// Candidates generated for `FitMode::Cover` are capped at this many
+const MAX_COVER_CANDIDATES: usize = 1 << 24;
+
+/// Background grid cells `generate_poisson_seeds` may allocate
+const MAX_POISSON_CELLS: f64 = (1u64 << 22) as f64;
+
+/// Candidates tried around an active point before it is retired
+const POISSON_ATTEMPTS: u32 = 30;
+
+/// Cells `generate_jittered_grid` will lay out
+const MAX_GRID_SEEDS: usize = 1 << 24;
+
+/// Seeds `generate_phyllotaxis` will lay out
+const MAX_PHYLLOTAXIS_SEEDS: usize = 1 << 24;
+
+/// How a generator's natural domain (a disc for the spiral) is mapped onto
+/// the canvas, or `rescale_points` maps an old canvas onto a new one
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FitMode {
+    /// Disc inscribed in the shorter side with a 15% margin; the
+    /// `generate_golden_seeds` layout
+    Contain = 0,
+    /// Disc through the canvas corners, keeping only seeds on the canvas
+    Cover = 1,
+    /// Disc scaled independently per axis into an ellipse with the same
+    /// 15% margin
+    Stretch = 2,
+}
+
+/// Golden-spiral seeds fitted to the canvas according to `fit_mode`
+///
+/// `Contain` and `Stretch` return exactly `count` seeds. `Cover` lays out
+/// enough spiral candidates that about `count` land on the canvas (the
+/// expected number, exact to within rounding of the clipped rim) and
+/// drops the rest, so every corner is reached at the same density as the
+/// middle.
+#[wasm_bindgen]
+pub fn generate_golden_seeds_ex(
+    width: f64,
+    height: f64,
+    count: usize,
+    fit_mode: FitMode,
+) -> Result<Vec<f64>> {
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    let (cx, cy) = (width / 2.0, height / 2.0);
+    let spiral = |total: usize, rx: f64, ry: f64| {
+        (0..total).map(move |n| {
+            let theta = (n as f64) * GOLDEN_ANGLE;
+            let r = ((n as f64) / (total as f64)).sqrt();
+            (cx + rx * r * theta.cos(), cy + ry * r * theta.sin())
+        })
+    };
+
+    let out = match fit_mode {
+        FitMode::Contain => {
+            let radius = (width.min(height) / 2.0) * 0.85;
+            spiral(count, radius, radius).flat_map(|(x, y)| [x, y]).collect()
+        }
+        FitMode::Stretch => {
+            spiral(count, cx * 0.85, cy * 0.85).flat_map(|(x, y)| [x, y]).collect()
+        }
+        FitMode::Cover => {
+            let radius = cx.hypot(cy);
+            let ratio = std::f64::consts::PI * radius * radius / (width * height);
+            let total = (count as f64 * ratio).round();
+            if total > MAX_COVER_CANDIDATES as f64 {
+                return Err(Error::TooLarge(format!(
+                    "covering {width}x{height} with {count} seeds needs {total} candidates"
+                )));
+            }
+            spiral(total as usize, radius, radius)
+                .filter(|&(x, y)| (0.0..=width).contains(&x) && (0.0..=height).contains(&y))
+                .flat_map(|(x, y)| [x, y])
+                .collect()
+        }
+    };
+    Ok(out)
+}
+
+/// `count` golden-spiral seeds in the disc of `max_radius` about
+/// `(center_x, center_y)`, plus `boundary_points` on its rim
+///
+/// A bare spiral ends in a ragged rim whose hull triangles are long
+/// slivers. With `boundary_points > 0` the interior is pulled in by half a
+/// ring's worth of area and that many evenly spaced points are added on
+/// the circle itself, after the `count` interior ones (Vogel's boundary
+/// correction); about `2 * sqrt(PI * count)` of them match the interior
+/// spacing. With none, this is the plain spiral of `generate_golden_seeds`.
+#[wasm_bindgen]
+pub fn generate_golden_seeds_at(
+    center_x: f64,
+    center_y: f64,
+    max_radius: f64,
+    count: usize,
+    boundary_points: usize,
+) -> Vec<f64> {
+    let norm = count as f64 + boundary_points as f64 / 2.0;
+    let interior = (0..count).map(|n| {
+        let theta = (n as f64) * GOLDEN_ANGLE;
+        let r = max_radius * ((n as f64) / norm).sqrt();
+        (theta, r)
+    });
+    let step = std::f64::consts::TAU / boundary_points.max(1) as f64;
+    let rim = (0..boundary_points).map(|k| (k as f64 * step, max_radius));
+    interior
+        .chain(rim)
+        .flat_map(|(theta, r)| [center_x + r * theta.cos(), center_y + r * theta.sin()])
+        .collect()
+}
+
+/// Golden-angle phyllotaxis with the knobs of `generate_golden_seeds`
+/// exposed: several arms, another radial law and seeded jitter
+///
+/// Seed `n` goes to arm `n % arms` as that arm's seed `m = n / arms`, at
+/// angle `m × golden_angle + arm × 2π / arms + angle_offset` and radius
+/// `R × (m / per_arm)^radial_exponent`, where `per_arm` is `count / arms`
+/// rounded up and `R` the `generate_golden_seeds` radius; the arms are
+/// rotated copies of one spiral. An exponent of 0.5 is Vogel's even
+/// density, and larger ones crowd the middle, 1 into rings. `jitter` in
+/// [0, 1] moves each seed by up to half that fraction of the even
+/// spacing `R × √(π / count)`, radially and along its circle, from the
+/// sequence of `seed`, without leaving the disc.
+///
+/// One arm, no offset, exponent 0.5 and no jitter is `generate_golden_seeds`
+/// bit for bit.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn generate_phyllotaxis(
+    width: f64,
+    height: f64,
+    count: usize,
+    arms: u32,
+    angle_offset: f64,
+    radial_exponent: f64,
+    jitter: f64,
+    seed: u32,
+) -> Result<Vec<f64>> {
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if arms == 0 {
+        return Err(Error::InvalidArgument("arms must be at least 1".into()));
+    }
+    if !(radial_exponent.is_finite() && radial_exponent > 0.0) {
+        return Err(Error::InvalidArgument(format!(
+            "radial exponent must be positive, got {radial_exponent}"
+        )));
+    }
+    if !angle_offset.is_finite() {
+        return Err(Error::NonFiniteInput("angle offset must be finite".into()));
+    }
+    if !(0.0..=1.0).contains(&jitter) {
+        return Err(Error::InvalidArgument(format!("jitter must be in [0, 1], got {jitter}")));
+    }
+    if count > MAX_PHYLLOTAXIS_SEEDS {
+        return Err(Error::TooLarge(format!(
+            "{count} seeds is more than the {MAX_PHYLLOTAXIS_SEEDS} allowed"
+        )));
+    }
+    let (center_x, center_y) = (width / 2.0, height / 2.0);
+    let max_radius = (width.min(height) / 2.0) * 0.85;
+    let per_arm = count.div_ceil(arms as usize) as f64;
+    let arm_turn = std::f64::consts::TAU / arms as f64;
+    let reach = jitter * max_radius * (std::f64::consts::PI / count.max(1) as f64).sqrt();
+    let mut rng = Rng::new(seed as u64);
+    let mut out = Vec::with_capacity(count * 2);
+    for n in 0..count {
+        let (arm, m) = ((n % arms as usize) as f64, (n / arms as usize) as f64);
+        let mut theta = m * GOLDEN_ANGLE + arm * arm_turn + angle_offset;
+        let t = m / per_arm;
+        // `powf(0.5)` may round differently from the spiral's `sqrt`
+        let scaled = if radial_exponent == 0.5 { t.sqrt() } else { t.powf(radial_exponent) };
+        let mut r = max_radius * scaled;
+        if reach > 0.0 {
+            let (dr, ds) = (rng.range(-0.5, 0.5), rng.range(-0.5, 0.5));
+            if r > 0.0 {
+                theta += reach * ds / r;
+            }
+            r = (r + reach * dr).clamp(0.0, max_radius);
+        }
+        out.extend([center_x + r * theta.cos(), center_y + r * theta.sin()]);
+    }
+    Ok(out)
+}
+
+/// `count` golden-spiral seeds in the unit disc, mapped through the affine
+/// `transform`
+///
+/// `transform` is `[a, b, c, d, e, f]` as in the canvas `setTransform`, so
+/// a seed at `(x, y)` lands at `(a x + c y + e, b x + d y + f)`. The map
+/// keeps the spiral's even density, so `[w/2, 0, 0, h/2, w/2, h/2]` fills
+/// the ellipse inscribed in a `w × h` canvas, however wide. Errors on a
+/// singular or non-finite matrix.
+#[wasm_bindgen]
+pub fn generate_golden_seeds_transformed(count: usize, transform: &[f64]) -> Result<Vec<f64>> {
+    let &[a, b, c, d, e, f] = transform else {
+        return Err(Error::InvalidLength(format!(
+            "transform needs 6 values, got {}",
+            transform.len()
+        )));
+    };
+    if transform.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("transform must be finite".into()));
+    }
+    if a * d - b * c == 0.0 {
+        return Err(Error::InvalidArgument("transform is singular".into()));
+    }
+    let unit = generate_golden_seeds_at(0.0, 0.0, 1.0, count, 0);
+    let map = |p: &[f64]| [a * p[0] + c * p[1] + e, b * p[0] + d * p[1] + f];
+    Ok(unit.chunks_exact(2).flat_map(map).collect())
+}
+
+/// Border points `generate_golden_seeds_cover` will lay out
+const MAX_BORDER_SEEDS: f64 = (1u64 << 22) as f64;
+
+/// Seeds covering a whole canvas, from `generate_golden_seeds_cover`
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct CoverSeeds {
+    points: Vec<f64>,
+    border_count: usize,
+}
+
+#[wasm_bindgen]
+impl CoverSeeds {
+    /// `[x0, y0, x1, y1, ...]`, the interior seeds followed by the border
+    pub fn points(&self) -> Vec<f64> {
+        self.points.clone()
+    }
+
+    /// Points on the canvas edges, the last ones in `points`
+    pub fn border_count(&self) -> usize {
+        self.border_count
+    }
+
+    pub fn interior_count(&self) -> usize {
+        self.points.len() / 2 - self.border_count
+    }
+}
+
+/// Golden-spiral seeds over the whole canvas plus a ring of points on its
+/// edges, so the triangulation reaches every corner
+///
+/// The interior is the `FitMode::Cover` spiral without the seeds within
+/// half a `border_spacing` of an edge, which would make slivers against
+/// the border. The border starts at the corner `(0, 0)` and runs along
+/// `y = 0`, then `x = width`, `y = height` and `x = 0`, the winding of the
+/// counter-clockwise triangles, with the corners exact and each side cut
+/// into equal steps no longer than `border_spacing`. Pick a spacing near the interior's,
+/// `sqrt(width × height / count)`, for even triangles up to the edge.
+#[wasm_bindgen]
+pub fn generate_golden_seeds_cover(
+    width: f64,
+    height: f64,
+    count: usize,
+    border_spacing: f64,
+) -> Result<CoverSeeds> {
+    if !(border_spacing.is_finite() && border_spacing > 0.0) {
+        return Err(Error::InvalidArgument(format!(
+            "border spacing must be positive, got {border_spacing}"
+        )));
+    }
+    let spiral = generate_golden_seeds_ex(width, height, count, FitMode::Cover)?;
+    let (cols, rows) = ((width / border_spacing).ceil(), (height / border_spacing).ceil());
+    if 2.0 * (cols + rows) > MAX_BORDER_SEEDS {
+        return Err(Error::TooLarge(format!(
+            "a border every {border_spacing} around {width}x{height} is too many seeds"
+        )));
+    }
+    let margin = border_spacing / 2.0;
+    let inside = |x: f64, y: f64| {
+        x > margin && x < width - margin && y > margin && y < height - margin
+    };
+    let mut points: Vec<f64> =
+        spiral.chunks_exact(2).filter(|p| inside(p[0], p[1])).flatten().copied().collect();
+    let interior = points.len();
+    let (cols, rows) = (cols as usize, rows as usize);
+    let sides = [
+        ((0.0, 0.0), (width, 0.0), cols),
+        ((width, 0.0), (width, height), rows),
+        ((width, height), (0.0, height), cols),
+        ((0.0, height), (0.0, 0.0), rows),
+    ];
+    for ((x0, y0), (x1, y1), steps) in sides {
+        for k in 0..steps {
+            let t = k as f64 / steps as f64;
+            points.extend([x0 + (x1 - x0) * t, y0 + (y1 - y0) * t]);
+        }
+    }
+    Ok(CoverSeeds { border_count: (points.len() - interior) / 2, points })
+}
+
+/// R2 steps 1/g and 1/g² as 0.64 fixed point, g the plastic number
+/// (the real root of g³ = g + 1)
+const R2_ALPHA: [u64; 2] = [0xc13f_a9a9_02a6_328f, 0x91e1_0da5_c79e_7b1c];
+
+/// R2 low-discrepancy seeds, the two-dimensional golden ratio sequence
+///
+/// Point `n` is the fractional part of `0.5 + n (1/g, 1/g²)` scaled to the
+/// canvas, for `n` from `seed_offset` on; different offsets give
+/// different, equally even layouts. The sequence is evaluated in
+/// wrapping fixed point, so it is exact and platform independent for
+/// any offset.
+#[wasm_bindgen]
+pub fn generate_r2_seeds(width: f64, height: f64, count: usize, seed_offset: u64) -> Vec<f64> {
+    const HALF: u64 = 1 << 63;
+    let unit = |n: u64, alpha: u64| {
+        let fixed = HALF.wrapping_add(n.wrapping_mul(alpha));
+        // The top 53 bits, exactly representable
+        (fixed >> 11) as f64 / (1u64 << 53) as f64
+    };
+    (0..count as u64)
+        .flat_map(|i| {
+            let n = seed_offset.wrapping_add(i);
+            [unit(n, R2_ALPHA[0]) * width, unit(n, R2_ALPHA[1]) * height]
+        })
+        .collect()
+}
+
+/// One seed per cell of a `cols` x `rows` grid, offset at random
+///
+/// Each seed starts at its cell's centre and moves by up to `jitter / 2`
+/// of the cell size along each axis, so `jitter = 0` is an exact grid
+/// (collinear and cocircular throughout, handy for exercising the
+/// triangulator's degenerate cases) and `jitter = 1` can reach, but never
+/// leave, its cell. Seeds are in row-major order.
+#[wasm_bindgen]
+pub fn generate_jittered_grid(
+    width: f64,
+    height: f64,
+    cols: usize,
+    rows: usize,
+    jitter: f64,
+    seed: u32,
+) -> Result<Vec<f64>> {
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if !(0.0..=1.0).contains(&jitter) {
+        return Err(Error::InvalidArgument(format!("jitter must be in [0, 1], got {jitter}")));
+    }
+    if cols.checked_mul(rows).is_none_or(|n| n > MAX_GRID_SEEDS) {
+        return Err(Error::TooLarge(format!("a {cols}x{rows} grid is too many seeds")));
+    }
+    let (cw, ch) = (width / cols as f64, height / rows as f64);
+    let mut rng = Rng::new(seed as u64);
+    let mut out = Vec::with_capacity(cols * rows * 2);
+    for r in 0..rows {
+        for c in 0..cols {
+            let (dx, dy) = (rng.range(-0.5, 0.5), rng.range(-0.5, 0.5));
+            let x = (c as f64 + 0.5 + jitter * dx) * cw;
+            let y = (r as f64 + 0.5 + jitter * dy) * ch;
+            // Rounding at the far edge could land a hair outside
+            out.extend([x.clamp(0.0, width), y.clamp(0.0, height)]);
+        }
+    }
+    Ok(out)
+}
+
+/// Halton (2, 3) low-discrepancy seeds
+///
+/// Point `n` (from 1, since point 0 is the corner) is the base-2 and
+/// base-3 radical inverses of `n` scaled to the canvas. Coverage is as
+/// even as `generate_r2_seeds` at most counts, with visible diagonal
+/// structure at small ones.
+#[wasm_bindgen]
+pub fn generate_halton_seeds(width: f64, height: f64, count: usize) -> Vec<f64> {
+    (1..=count as u64)
+        .flat_map(|n| [radical_inverse(n, 2) * width, radical_inverse(n, 3) * height])
+        .collect()
+}
+
+/// Digits of `n` in `base` mirrored about the radix point
+fn radical_inverse(mut n: u64, base: u64) -> f64 {
+    let (mut value, mut scale) = (0.0, 1.0);
+    while n > 0 {
+        scale /= base as f64;
+        value += (n % base) as f64 * scale;
+        n /= base;
+    }
+    value
+}
+
+/// Poisson-disk seeds by Bridson's algorithm
+///
+/// No two seeds are closer than `min_distance`, and the canvas is filled
+/// until no gap could take another seed, so the layout is even edge to
+/// edge with none of the spiral's radial structure. The same `seed`
+/// always gives the same layout. Growth starts at a random point and
+/// spreads outward, so stopping at `max_count` leaves a patch around it
+/// rather than a sparser canvas; to cover the canvas with about `n`
+/// seeds, use a `min_distance` of about `0.8 * sqrt(width * height / n)`.
+///
+/// Errors when the canvas or `min_distance` is not positive, or when
+/// `min_distance` is so small against the canvas that the background grid
+/// would exceed 4M cells.
+#[wasm_bindgen]
+pub fn generate_poisson_seeds(
+    width: f64,
+    height: f64,
+    min_distance: f64,
+    seed: u32,
+    max_count: Option<usize>,
+) -> Result<Vec<f64>> {
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if !(min_distance.is_finite() && min_distance > 0.0) {
+        return Err(Error::InvalidArgument(format!(
+            "min_distance must be positive, got {min_distance}"
+        )));
+    }
+    // Cells of side r / sqrt(2) hold at most one seed each
+    let cell = min_distance / std::f64::consts::SQRT_2;
+    let (cols, rows) = ((width / cell).ceil().max(1.0), (height / cell).ceil().max(1.0));
+    if cols * rows > MAX_POISSON_CELLS {
+        return Err(Error::TooLarge(format!(
+            "min_distance {min_distance} on {width}x{height} needs a {cols}x{rows} grid"
+        )));
+    }
+    let limit = max_count.unwrap_or(usize::MAX);
+    let mut disk = PoissonGrid {
+        cell,
+        cols: cols as usize,
+        rows: rows as usize,
+        grid: vec![u32::MAX; (cols * rows) as usize],
+        points: Vec::new(),
+        active: Vec::new(),
+    };
+    let mut rng = Rng::new(seed as u64);
+    if limit > 0 {
+        disk.place(rng.range(0.0, width), rng.range(0.0, height));
+    }
+    let r2 = min_distance * min_distance;
+    while !disk.active.is_empty() && disk.points.len() < limit {
+        let slot = rng.below(disk.active.len() as u32) as usize;
+        let (px, py) = disk.points[disk.active[slot] as usize];
+        let mut placed = false;
+        for _ in 0..POISSON_ATTEMPTS {
+            // Uniform over the annulus [r, 2r] by area
+            let radius = (r2 * (1.0 + 3.0 * rng.next_f64())).sqrt();
+            let theta = rng.range(0.0, 2.0 * std::f64::consts::PI);
+            let (x, y) = (px + radius * theta.cos(), py + radius * theta.sin());
+            if (0.0..width).contains(&x) && (0.0..height).contains(&y) && disk.clear(x, y, r2) {
+                disk.place(x, y);
+                placed = true;
+                break;
+            }
+        }
+        if !placed {
+            disk.active.swap_remove(slot);
+        }
+    }
+    Ok(disk.points.into_iter().flat_map(|(x, y)| [x, y]).collect())
+}
+
+/// Bridson's background grid plus the active list
+struct PoissonGrid {
+    cell: f64,
+    cols: usize,
+    rows: usize,
+    /// Index of the seed in each cell, `u32::MAX` when empty
+    grid: Vec<u32>,
+    points: Vec<(f64, f64)>,
+    active: Vec<u32>,
+}
+
+impl PoissonGrid {
+    fn cell_of(&self, x: f64, y: f64) -> (usize, usize) {
+        let cx = ((x / self.cell) as usize).min(self.cols - 1);
+        (cx, ((y / self.cell) as usize).min(self.rows - 1))
+    }
+
+    fn place(&mut self, x: f64, y: f64) {
+        let (cx, cy) = self.cell_of(x, y);
+        let index = self.points.len() as u32;
+        self.grid[cy * self.cols + cx] = index;
+        self.active.push(index);
+        self.points.push((x, y));
+    }
+
+    /// No seed within `sqrt(r2)` of `(x, y)`; two cells each way cover it
+    fn clear(&self, x: f64, y: f64, r2: f64) -> bool {
+        let (cx, cy) = self.cell_of(x, y);
+        (cy.saturating_sub(2)..(cy + 3).min(self.rows)).all(|gy| {
+            (cx.saturating_sub(2)..(cx + 3).min(self.cols)).all(|gx| {
+                let near = self.grid[gy * self.cols + gx];
+                near == u32::MAX || {
+                    let (qx, qy) = self.points[near as usize];
+                    (qx - x).powi(2) + (qy - y).powi(2) >= r2
+                }
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+    use crate::{generate_golden_seeds, Point, Triangulation};
+
+    fn nearest_to(seeds: &[f64], x: f64, y: f64) -> f64 {
+        seeds
+            .chunks(2)
+            .map(|p| (p[0] - x).hypot(p[1] - y))
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    #[test]
+    fn test_contain_matches_golden_seeds() {
+        let seeds = generate_golden_seeds_ex(800.0, 600.0, 300, FitMode::Contain).unwrap();
+        assert_eq!(seeds, generate_golden_seeds(800.0, 600.0, 300));
+    }
+
+    #[test]
+    fn test_cover_seeds_mesh_the_whole_canvas() {
+        for (w, h, count) in [(800.0, 600.0, 1500), (1920.0, 1080.0, 4000), (300.0, 2000.0, 700)] {
+            let spacing = (w * h / count as f64).sqrt();
+            let cover = generate_golden_seeds_cover(w, h, count, spacing).unwrap();
+            let points = cover.points();
+            let (cols, rows) = ((w / spacing).ceil() as usize, (h / spacing).ceil() as usize);
+            assert_eq!(cover.border_count(), 2 * (cols + rows));
+            assert_eq!(cover.interior_count() + cover.border_count(), points.len() / 2);
+            let (interior, border) = points.split_at(cover.interior_count() * 2);
+            assert_eq!(border[..2], [0.0, 0.0]);
+            for corner in [[w, 0.0], [w, h], [0.0, h]] {
+                assert!(border.chunks(2).any(|p| p == corner));
+            }
+            assert!(border.chunks(2).all(|p| p[0] == 0.0 || p[0] == w || p[1] == 0.0 || p[1] == h));
+            let clear = |p: &[f64]| p[0].min(w - p[0]).min(p[1]).min(h - p[1]) > spacing / 2.0;
+            assert!(interior.chunks(2).all(clear));
+
+            // No flat or missing triangles: the mesh tiles the canvas
+            let triangles = crate::compute_delaunay(&points, w, h);
+            let at = |v: u32| Point::new(points[v as usize * 2], points[v as usize * 2 + 1]);
+            let mut area = 0.0;
+            for t in triangles.chunks(3) {
+                let twice = crate::predicates::orient2d(at(t[0]), at(t[1]), at(t[2]));
+                assert!(twice > 0.0);
+                area += twice / 2.0;
+            }
+            assert!((area - w * h).abs() < 1e-9 * w * h, "{w}x{h}: area {area}");
+        }
+        let bad = |spacing: f64| code_of(generate_golden_seeds_cover(800.0, 600.0, 100, spacing));
+        assert_eq!(bad(0.0), ErrorCode::InvalidArgument);
+        assert_eq!(bad(f64::NAN), ErrorCode::InvalidArgument);
+        assert_eq!(bad(1e-6), ErrorCode::TooLarge);
+        let flat = generate_golden_seeds_cover(0.0, 600.0, 100, 10.0);
+        assert_eq!(code_of(flat), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_cover_reaches_every_corner() {
+        let count = 2000;
+        for (w, h) in [(800.0, 800.0), (1920.0, 1080.0), (1080.0, 1920.0), (3000.0, 600.0)] {
+            let seeds = generate_golden_seeds_ex(w, h, count, FitMode::Cover).unwrap();
+            let n = seeds.len() / 2;
+            assert!(n.abs_diff(count) <= count / 50, "{w}x{h}: {n} seeds");
+            for p in seeds.chunks(2) {
+                assert!((0.0..=w).contains(&p[0]) && (0.0..=h).contains(&p[1]));
+            }
+            // The seed owning each corner is about one spacing away
+            let spacing = (w * h / count as f64).sqrt();
+            for (x, y) in [(0.0, 0.0), (w, 0.0), (0.0, h), (w, h)] {
+                let d = nearest_to(&seeds, x, y);
+                assert!(d < 1.5 * spacing, "{w}x{h}: corner ({x}, {y}) is {d} from a seed");
+            }
+            // Contain leaves the corners of a non-square canvas empty
+            let contain = generate_golden_seeds_ex(w, h, count, FitMode::Contain).unwrap();
+            assert!(nearest_to(&contain, 0.0, 0.0) > 3.0 * spacing);
+        }
+    }
+
+    #[test]
+    fn test_stretch_fills_an_ellipse() {
+        let (w, h) = (1000.0, 200.0);
+        let seeds = generate_golden_seeds_ex(w, h, 500, FitMode::Stretch).unwrap();
+        assert_eq!(seeds.len(), 1000);
+        let (mut max_x, mut max_y) = (0.0f64, 0.0f64);
+        for p in seeds.chunks(2) {
+            let (u, v) = ((p[0] - 500.0) / 425.0, (p[1] - 100.0) / 85.0);
+            assert!(u * u + v * v <= 1.0 + 1e-12);
+            max_x = max_x.max(u.abs());
+            max_y = max_y.max(v.abs());
+        }
+        assert!(max_x > 0.95 && max_y > 0.95);
+        assert_eq!(
+            code_of(generate_golden_seeds_ex(0.0, 1.0, 10, FitMode::Stretch)),
+            ErrorCode::InvalidArgument
+        );
+        assert_eq!(
+            code_of(generate_golden_seeds_ex(1e9, 1.0, 10_000, FitMode::Cover)),
+            ErrorCode::TooLarge
+        );
+    }
+
+    /// Coefficient of variation of the Delaunay edge lengths, away from
+    /// the hull slivers along the `w × h` border
+    fn edge_length_cv(seeds: &[f64], w: f64, h: f64) -> f64 {
+        let points: Vec<Point> = seeds.chunks(2).map(|p| Point::new(p[0], p[1])).collect();
+        let tri = Triangulation::new(&points).unwrap();
+        let inner = |p: Point| p.x > 0.1 * w && p.x < 0.9 * w && p.y > 0.1 * h && p.y < 0.9 * h;
+        let lengths: Vec<f64> = tri
+            .edges()
+            .map(|(a, b)| (points[a], points[b]))
+            .filter(|&(p, q)| inner(p) && inner(q))
+            .map(|(p, q)| (p.x - q.x).hypot(p.y - q.y))
+            .collect();
+        let mean = lengths.iter().sum::<f64>() / lengths.len() as f64;
+        let var = lengths.iter().map(|l| (l - mean).powi(2)).sum::<f64>() / lengths.len() as f64;
+        var.sqrt() / mean
+    }
+
+    #[test]
+    fn test_r2_is_deterministic_and_more_even_than_random() {
+        let (w, h) = (1600.0, 900.0);
+        let seeds = generate_r2_seeds(w, h, 1000, 0);
+        assert_eq!(seeds, generate_r2_seeds(w, h, 1000, 0));
+        assert_eq!(seeds.len(), 2000);
+        assert_eq!(&seeds[..2], &[0.5 * w, 0.5 * h]);
+        // An offset is the same sequence started later
+        assert_eq!(&generate_r2_seeds(w, h, 10, 990)[..], &seeds[1980..]);
+        // ... and far offsets stay exact
+        let far = generate_r2_seeds(w, h, 2, u64::MAX);
+        assert_eq!(&far[2..], &seeds[..2]);
+        for p in seeds.chunks(2) {
+            assert!((0.0..w).contains(&p[0]) && (0.0..h).contains(&p[1]));
+        }
+
+        let mut rng = Rng::new(5);
+        let random: Vec<f64> =
+            (0..1000).flat_map(|_| [rng.range(0.0, w), rng.range(0.0, h)]).collect();
+        let (r2, white) = (edge_length_cv(&seeds, w, h), edge_length_cv(&random, w, h));
+        assert!(r2 < 0.7 * white, "R2 CV {r2} vs random {white}");
+    }
+
+    #[test]
+    fn test_poisson_keeps_its_distance_and_fills_the_canvas() {
+        let (w, h, r) = (800.0, 450.0, 20.0);
+        let seeds = generate_poisson_seeds(w, h, r, 9, None).unwrap();
+        assert_eq!(seeds, generate_poisson_seeds(w, h, r, 9, None).unwrap());
+        assert_ne!(seeds, generate_poisson_seeds(w, h, r, 10, None).unwrap());
+        let points: Vec<&[f64]> = seeds.chunks(2).collect();
+        for (i, p) in points.iter().enumerate() {
+            assert!((0.0..w).contains(&p[0]) && (0.0..h).contains(&p[1]));
+            for q in &points[i + 1..] {
+                assert!((p[0] - q[0]).hypot(p[1] - q[1]) >= r);
+            }
+        }
+        // Maximal up to the attempt limit: no spot is far from a seed
+        for gy in 0..=45 {
+            for gx in 0..=80 {
+                let d = nearest_to(&seeds, gx as f64 * 10.0, gy as f64 * 10.0);
+                assert!(d < 2.0 * r, "({gx}, {gy}) is {d} from a seed");
+            }
+        }
+        // The documented spacing for a target count lands near it
+        let n = seeds.len() / 2;
+        let target = (0.8 * (w * h / n as f64).sqrt() - r).abs();
+        assert!(target < 0.1 * r, "{n} seeds");
+        let triangles = crate::compute_delaunay(&seeds, w, h);
+        assert!(triangles.len() / 3 > n);
+    }
+
+    #[test]
+    fn test_poisson_caps_and_rejects() {
+        let capped = generate_poisson_seeds(1000.0, 1000.0, 5.0, 1, Some(100)).unwrap();
+        assert_eq!(capped.len(), 200);
+        assert!(generate_poisson_seeds(10.0, 10.0, 5.0, 1, Some(0)).unwrap().is_empty());
+        // Larger than the canvas: just the starting seed
+        assert_eq!(generate_poisson_seeds(10.0, 10.0, 50.0, 1, None).unwrap().len(), 2);
+        let code = |w: f64, r: f64| code_of(generate_poisson_seeds(w, 1000.0, r, 1, Some(10)));
+        assert_eq!(code(1000.0, 1e-3), ErrorCode::TooLarge);
+        assert_eq!(code(1000.0, 0.0), ErrorCode::InvalidArgument);
+        assert_eq!(code(-1.0, 5.0), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_jittered_grid_bounds_and_exact_zero() {
+        let (w, h) = (300.0, 200.0);
+        let exact = generate_jittered_grid(w, h, 3, 2, 0.0, 4).unwrap();
+        let centres = [50.0, 150.0].map(|y| [50.0, 150.0, 250.0].map(|x| [x, y]));
+        assert_eq!(exact, centres.as_flattened().as_flattened());
+        let full = generate_jittered_grid(w, h, 30, 20, 1.0, 4).unwrap();
+        assert_eq!(full, generate_jittered_grid(w, h, 30, 20, 1.0, 4).unwrap());
+        assert_ne!(full, generate_jittered_grid(w, h, 30, 20, 1.0, 5).unwrap());
+        for (i, p) in full.chunks(2).enumerate() {
+            // Every seed stays in its own 10x10 cell
+            let (c, r) = ((i % 30) as f64, (i / 30) as f64);
+            assert!((c * 10.0..=(c + 1.0) * 10.0).contains(&p[0]));
+            assert!((r * 10.0..=(r + 1.0) * 10.0).contains(&p[1]));
+        }
+        assert!(generate_jittered_grid(w, h, 0, 5, 0.5, 1).unwrap().is_empty());
+        let code = |jitter, cols| code_of(generate_jittered_grid(w, h, cols, 2, jitter, 1));
+        assert_eq!(code(1.5, 2), ErrorCode::InvalidArgument);
+        assert_eq!(code(f64::NAN, 2), ErrorCode::InvalidArgument);
+        assert_eq!(code(0.5, usize::MAX), ErrorCode::TooLarge);
+    }
+
+    #[test]
+    fn test_halton_sequence_and_evenness() {
+        let seeds = generate_halton_seeds(1.0, 1.0, 4);
+        let expected = [0.5, 1.0 / 3.0, 0.25, 2.0 / 3.0, 0.75, 1.0 / 9.0, 0.125, 4.0 / 9.0];
+        for (a, b) in seeds.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-15);
+        }
+        let (w, h) = (1600.0, 900.0);
+        let halton = generate_halton_seeds(w, h, 1000);
+        assert!(halton.chunks(2).all(|p| (0.0..w).contains(&p[0]) && (0.0..h).contains(&p[1])));
+        let mut rng = Rng::new(5);
+        let random: Vec<f64> =
+            (0..1000).flat_map(|_| [rng.range(0.0, w), rng.range(0.0, h)]).collect();
+        assert!(edge_length_cv(&halton, w, h) < 0.8 * edge_length_cv(&random, w, h));
+    }
+
+    /// Smallest interior angle over a triangulation of `seeds`, in degrees
+    fn min_angle(seeds: &[f64]) -> f64 {
+        let points: Vec<Point> = seeds.chunks(2).map(|p| Point::new(p[0], p[1])).collect();
+        let tri = Triangulation::new(&points).unwrap();
+        let mut smallest = f64::INFINITY;
+        for t in tri.triangles() {
+            let [a, b, c] = t.vertices().map(|v| points[v]);
+            for (p, q, r) in [(a, b, c), (b, c, a), (c, a, b)] {
+                let (u, v) = ((q.x - p.x, q.y - p.y), (r.x - p.x, r.y - p.y));
+                let cos = (u.0 * v.0 + u.1 * v.1) / (u.0.hypot(u.1) * v.0.hypot(v.1));
+                smallest = smallest.min(cos.clamp(-1.0, 1.0).acos().to_degrees());
+            }
+        }
+        smallest
+    }
+
+    #[test]
+    fn test_transformed_spiral_fills_a_wide_ellipse() {
+        let unit = generate_golden_seeds_at(0.0, 0.0, 1.0, 500, 0);
+        let identity = generate_golden_seeds_transformed(500, &[1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+        assert_eq!(identity.unwrap(), unit);
+
+        // A 3840 × 600 banner: the ellipse matches `FitMode::Stretch`
+        // without its margin
+        let transform = [1920.0, 0.0, 0.0, 300.0, 1920.0, 300.0];
+        let wide = generate_golden_seeds_transformed(500, &transform).unwrap();
+        let stretch = generate_golden_seeds_ex(3840.0 / 0.85, 600.0 / 0.85, 500, FitMode::Stretch);
+        let offset = (3840.0 / 0.85 - 3840.0) / 2.0;
+        for (p, q) in wide.chunks(2).zip(stretch.unwrap().chunks(2)) {
+            assert!((p[0] - (q[0] - offset)).abs() < 1e-6, "{p:?} {q:?}");
+        }
+        assert!(wide.iter().step_by(2).fold(0.0f64, |m, &x| m.max(x)) > 3700.0);
+
+        // A shear and rotation keep every seed inside the mapped disc
+        let (cos, sin) = (0.6, 0.8);
+        let sheared = [2.0 * cos, 2.0 * sin, -sin + 0.5 * cos, cos + 0.5 * sin, 10.0, -4.0];
+        let seeds = generate_golden_seeds_transformed(500, &sheared).unwrap();
+        for (p, u) in seeds.chunks(2).zip(unit.chunks(2)) {
+            let [a, b, c, d, e, f] = sheared;
+            assert!((p[0] - (a * u[0] + c * u[1] + e)).abs() < 1e-12);
+            assert!((p[1] - (b * u[0] + d * u[1] + f)).abs() < 1e-12);
+        }
+
+        let transformed = |t: &[f64]| code_of(generate_golden_seeds_transformed(10, t));
+        assert_eq!(transformed(&[1.0, 0.0, 0.0, 1.0]), ErrorCode::InvalidLength);
+        assert_eq!(transformed(&[1.0, 0.0, 0.0, f64::NAN, 0.0, 0.0]), ErrorCode::NonFiniteInput);
+        assert_eq!(transformed(&[1.0, 2.0, 2.0, 4.0, 0.0, 0.0]), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_phyllotaxis_defaults_to_the_golden_spiral() {
+        let plain = generate_golden_seeds(800.0, 600.0, 500);
+        let same = generate_phyllotaxis(800.0, 600.0, 500, 1, 0.0, 0.5, 0.0, 9).unwrap();
+        let bits = |v: &[f64]| v.iter().map(|x| x.to_bits()).collect::<Vec<u64>>();
+        assert_eq!(bits(&same), bits(&plain));
+
+        // Three arms are rotated copies of each other
+        let arms = generate_phyllotaxis(800.0, 600.0, 300, 3, 0.25, 0.5, 0.0, 0).unwrap();
+        let (sin, cos) = (std::f64::consts::TAU / 3.0).sin_cos();
+        for m in 0..100 {
+            let (a, b) = (&arms[m * 6..m * 6 + 2], &arms[m * 6 + 2..m * 6 + 4]);
+            let (x, y) = (a[0] - 400.0, a[1] - 300.0);
+            let turned = (400.0 + x * cos - y * sin, 300.0 + x * sin + y * cos);
+            assert!((turned.0 - b[0]).abs() < 1e-9 && (turned.1 - b[1]).abs() < 1e-9);
+        }
+        // Exponent 1 spaces the radii linearly
+        let rings = generate_phyllotaxis(800.0, 600.0, 100, 1, 0.0, 1.0, 0.0, 0).unwrap();
+        for (n, p) in rings.chunks(2).enumerate() {
+            let r = (p[0] - 400.0).hypot(p[1] - 300.0);
+            assert!((r - 255.0 * n as f64 / 100.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_phyllotaxis_jitter_and_errors() {
+        let run = |jitter: f64, seed: u32| {
+            generate_phyllotaxis(800.0, 600.0, 400, 2, 0.0, 0.5, jitter, seed).unwrap()
+        };
+        let (calm, shaken) = (run(0.0, 1), run(0.8, 1));
+        assert_eq!(shaken, run(0.8, 1));
+        assert_ne!(shaken, run(0.8, 2));
+        let spacing = 255.0 * (std::f64::consts::PI / 400.0).sqrt();
+        for (a, b) in calm.chunks(2).zip(shaken.chunks(2)) {
+            let moved = (a[0] - b[0]).hypot(a[1] - b[1]);
+            assert!(moved <= 0.8 * spacing, "{moved}");
+            assert!((b[0] - 400.0).hypot(b[1] - 300.0) <= 255.0 + 1e-9);
+        }
+        assert!(calm.iter().zip(&shaken).any(|(a, b)| a != b));
+        assert!(generate_phyllotaxis(800.0, 600.0, 0, 4, 0.0, 0.5, 1.0, 0).unwrap().is_empty());
+
+        let code = |arms: u32, exponent: f64, offset: f64, jitter: f64| {
+            code_of(generate_phyllotaxis(800.0, 600.0, 10, arms, offset, exponent, jitter, 0))
+        };
+        assert_eq!(code(0, 0.5, 0.0, 0.0), ErrorCode::InvalidArgument);
+        assert_eq!(code(1, 0.0, 0.0, 0.0), ErrorCode::InvalidArgument);
+        assert_eq!(code(1, f64::NAN, 0.0, 0.0), ErrorCode::InvalidArgument);
+        assert_eq!(code(1, 0.5, f64::INFINITY, 0.0), ErrorCode::NonFiniteInput);
+        assert_eq!(code(1, 0.5, 0.0, 1.5), ErrorCode::InvalidArgument);
+        let huge = generate_phyllotaxis(800.0, 600.0, usize::MAX, 1, 0.0, 0.5, 0.0, 0);
+        assert_eq!(code_of(huge), ErrorCode::TooLarge);
+        let flat = generate_phyllotaxis(800.0, 0.0, 10, 1, 0.0, 0.5, 0.0, 0);
+        assert_eq!(code_of(flat), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_golden_seeds_at_places_the_spiral_and_its_rim() {
+        let plain = generate_golden_seeds_at(400.0, 300.0, 255.0, 300, 0);
+        assert_eq!(plain, generate_golden_seeds(800.0, 600.0, 300));
+
+        let (cx, cy, radius) = (1500.0, 120.0, 100.0);
+        let seeds = generate_golden_seeds_at(cx, cy, radius, 400, 71);
+        assert_eq!(seeds.len(), 2 * 471);
+        let (interior, rim) = seeds.split_at(800);
+        for p in rim.chunks(2) {
+            assert!(((p[0] - cx).hypot(p[1] - cy) - radius).abs() < 1e-9);
+        }
+        // Evenly spaced around the circle
+        let gap = |a: &[f64], b: &[f64]| (a[0] - b[0]).hypot(a[1] - b[1]);
+        let chord = gap(&rim[..2], &rim[2..4]);
+        for w in rim.chunks(2).collect::<Vec<_>>().windows(2) {
+            assert!((gap(w[0], w[1]) - chord).abs() < 1e-9);
+        }
+        // The interior keeps clear of the rim
+        let outermost = interior.chunks(2).map(|p| (p[0] - cx).hypot(p[1] - cy));
+        let outermost = outermost.fold(0.0, f64::max);
+        assert!(outermost < radius - 0.4 * chord, "{outermost}");
+        assert!(min_angle(&seeds) > 2.0 * min_angle(&plain));
+    }
+}
@@ -0,0 +1,299 @@
+//! Per-vertex gradient colors for WebGL attributes
+//!
+//! Each vertex gets a scalar in [0, 1], from its distance to the canvas
+//! centre, from animated noise or from both, and the scalar picks a color
+//! from a palette of evenly spaced RGBA stops. The noise is
+//! `simplex_noise_3d` with time as `z`, scaled so about
+//! `NOISE_FEATURES` blobs span the shorter canvas side.
+//!
+//! `triangle_shading` is the low-poly terrain look instead: a fractal
+//! height field sampled once per triangle, lit by a light on the horizon.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles};
+use crate::noise::fbm_deriv_2d;
+use crate::simplex_noise_3d;
+
+/// Noise features across the shorter canvas side
+pub(crate) const NOISE_FEATURES: f64 = 4.0;
+
+/// Octaves of the `triangle_shading` height field, with lacunarity 2 and
+/// gain 0.5
+const TERRAIN_OCTAVES: u32 = 4;
+
+/// Which scalar drives the gradient in `compute_vertex_colors`
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VertexColorMode {
+    /// Distance from the canvas centre, 0 there and 1 at the corners
+    Radial = 0,
+    /// Animated noise, remapped from [-1, 1] to [0, 1]
+    Noise = 1,
+    /// The mean of the radial and noise scalars
+    NoiseRadial = 2,
+    /// `NoiseRadial` at each triangle's centroid, one color per triangle
+    FlatTriangles = 3,
+}
+
+/// Gradient scalar in [0, 1] at `(x, y)`
+fn scalar(mode: VertexColorMode, x: f64, y: f64, width: f64, height: f64, time: f64) -> f64 {
+    let (cx, cy) = (width * 0.5, height * 0.5);
+    let radial = || ((x - cx).powi(2) + (y - cy).powi(2)).sqrt() / cx.hypot(cy);
+    let noise = || {
+        let frequency = NOISE_FEATURES / width.min(height);
+        (simplex_noise_3d(x * frequency, y * frequency, time) + 1.0) * 0.5
+    };
+    let t = match mode {
+        VertexColorMode::Radial => radial(),
+        VertexColorMode::Noise => noise(),
+        VertexColorMode::NoiseRadial | VertexColorMode::FlatTriangles => {
+            (radial() + noise()) * 0.5
+        }
+    };
+    t.clamp(0.0, 1.0)
+}
+
+/// Palette color at `t`, blending the two surrounding stops linearly
+fn sample(palette: &[f32], t: f64) -> [f32; 4] {
+    let stops = palette.len() / 4;
+    let stop = |i: usize| {
+        let c = &palette[i * 4..i * 4 + 4];
+        [c[0], c[1], c[2], c[3]]
+    };
+    if stops == 1 {
+        return stop(0);
+    }
+    let at = t * (stops - 1) as f64;
+    let lo = (at.floor() as usize).min(stops - 2);
+    let f = (at - lo as f64) as f32;
+    let (a, b) = (stop(lo), stop(lo + 1));
+    [0, 1, 2, 3].map(|k| a[k] + (b[k] - a[k]) * f)
+}
+
+/// RGBA colors for a WebGL vertex attribute, 4 floats per vertex
+///
+/// `palette` holds RGBA stops (4 floats each, usually in [0, 1]) spread
+/// evenly over the gradient, first stop at scalar 0 and last at 1. For
+/// `FlatTriangles` the output instead has one color per corner of each
+/// triangle of `triangles`, 12 floats per triangle, for non-indexed
+/// `drawArrays`; the other modes ignore `triangles`.
+#[wasm_bindgen]
+pub fn compute_vertex_colors(
+    points_flat: &[f64],
+    width: f64,
+    height: f64,
+    time: f64,
+    palette: &[f32],
+    mode: VertexColorMode,
+    triangles: &[u32],
+) -> Result<Vec<f32>> {
+    let n = check_points(points_flat)?;
+    if palette.is_empty() || !palette.len().is_multiple_of(4) {
+        return Err(Error::InvalidLength(format!(
+            "palette needs 4 floats per stop and at least one stop, got {}",
+            palette.len()
+        )));
+    }
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(Error::InvalidArgument("width and height must be positive".into()));
+    }
+    if !time.is_finite() || points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("points and time must be finite".into()));
+    }
+    if palette.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("palette values must be finite".into()));
+    }
+    let color = |x: f64, y: f64| sample(palette, scalar(mode, x, y, width, height, time));
+
+    if mode != VertexColorMode::FlatTriangles {
+        let mut out = Vec::with_capacity(n * 4);
+        for c in points_flat.chunks_exact(2) {
+            out.extend(color(c[0], c[1]));
+        }
+        return Ok(out);
+    }
+    check_triangles(triangles, n)?;
+    let mut out = Vec::with_capacity(triangles.len() * 4);
+    for t in triangles.chunks_exact(3) {
+        let (x, y) = t.iter().fold((0.0, 0.0), |(x, y), &v| {
+            (x + points_flat[v as usize * 2], y + points_flat[v as usize * 2 + 1])
+        });
+        let rgba = color(x / 3.0, y / 3.0);
+        for _ in 0..3 {
+            out.extend(rgba);
+        }
+    }
+    Ok(out)
+}
+
+/// Flat height and shade per triangle for a low-poly terrain look, as
+/// `[height, shade, centroid_x, centroid_y, ...]` in triangle order
+///
+/// `height` is 4-octave fBm in [-1, 1] at the centroid, sampled at
+/// `noise_scale` noise units per canvas unit, and `time` morphs the field.
+/// `shade` is Lambert lighting of that surface by a light on the horizon
+/// in direction `(light_dir_x, light_dir_y)`, which need not be unit
+/// length: 1 for a slope facing the light head-on, 0 facing away and 0.5
+/// on the flat, so turning the light round gives `1 - shade`. The slope
+/// is the noise gradient per noise unit, so the relief looks the same at
+/// any `noise_scale`.
+#[wasm_bindgen]
+pub fn triangle_shading(
+    points_flat: &[f64],
+    triangles: &[u32],
+    light_dir_x: f64,
+    light_dir_y: f64,
+    noise_scale: f64,
+    time: f64,
+) -> Result<Vec<f32>> {
+    let n = check_points(points_flat)?;
+    check_triangles(triangles, n)?;
+    if !time.is_finite() || points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("points and time must be finite".into()));
+    }
+    let length = light_dir_x.hypot(light_dir_y);
+    if !(length.is_finite() && length > 0.0) {
+        return Err(Error::InvalidArgument(format!(
+            "light direction must be finite and non-zero, got ({light_dir_x}, {light_dir_y})"
+        )));
+    }
+    if !(noise_scale.is_finite() && noise_scale > 0.0) {
+        return Err(Error::InvalidArgument(format!(
+            "noise scale must be positive, got {noise_scale}"
+        )));
+    }
+    let (lx, ly) = (light_dir_x / length, light_dir_y / length);
+    let mut out = Vec::with_capacity(triangles.len() / 3 * 4);
+    for t in triangles.chunks_exact(3) {
+        let (x, y) = t.iter().fold((0.0, 0.0), |(x, y), &v| {
+            (x + points_flat[v as usize * 2], y + points_flat[v as usize * 2 + 1])
+        });
+        let (x, y) = (x / 3.0, y / 3.0);
+        let (height, gx, gy) =
+            fbm_deriv_2d(x * noise_scale, y * noise_scale, TERRAIN_OCTAVES, 2.0, 0.5, time);
+        // The normal of z = height is (-gx, -gy, 1), unnormalized
+        let lambert = -(gx * lx + gy * ly) / (1.0 + gx * gx + gy * gy).sqrt();
+        let shade = 0.5 + 0.5 * lambert;
+        out.extend([height.clamp(-1.0, 1.0) as f32, shade as f32, x as f32, y as f32]);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::{compute_delaunay, generate_golden_seeds};
+
+    const BLACK_TO_WHITE: [f32; 8] = [0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+
+    /// Colors on a 400 × 300 canvas
+    fn colors(
+        points: &[f64],
+        palette: &[f32],
+        mode: VertexColorMode,
+        time: f64,
+        triangles: &[u32],
+    ) -> Result<Vec<f32>> {
+        compute_vertex_colors(points, 400.0, 300.0, time, palette, mode, triangles)
+    }
+
+    #[test]
+    fn test_vertex_colors_follow_the_modes() {
+        // Centre, mid-way to a corner, and the corner itself
+        let points = [200.0, 150.0, 100.0, 75.0, 0.0, 0.0];
+        let shade = |mode, time| colors(&points, &BLACK_TO_WHITE, mode, time, &[]).unwrap();
+        let radial = shade(VertexColorMode::Radial, 0.0);
+        assert_eq!(radial.len(), 12);
+        assert_eq!(&radial[..4], &[0.0, 0.0, 0.0, 1.0]);
+        assert!((radial[4] - 0.5).abs() < 1e-6 && radial[7] == 1.0);
+        assert_eq!(&radial[8..], &[1.0, 1.0, 1.0, 1.0]);
+
+        // Three stops: the middle one is hit exactly at scalar 0.5
+        let palette = [1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0];
+        let mid = colors(&points, &palette, VertexColorMode::Radial, 0.0, &[]).unwrap();
+        assert!(mid[4].abs() < 1e-6 && (mid[5] - 1.0).abs() < 1e-6);
+        assert_eq!(&mid[8..], &[0.0, 0.0, 1.0, 0.0]);
+
+        let noise = shade(VertexColorMode::Noise, 0.0);
+        assert_ne!(noise, shade(VertexColorMode::Noise, 1.5), "time animates the noise");
+        let both = shade(VertexColorMode::NoiseRadial, 0.0);
+        for i in 0..3 {
+            let expected = (radial[i * 4] + noise[i * 4]) * 0.5;
+            assert!((both[i * 4] - expected).abs() < 1e-6);
+        }
+        let single = colors(&points, &[0.2, 0.4, 0.6, 0.8], VertexColorMode::Noise, 0.0, &[]);
+        assert_eq!(single.unwrap(), [0.2, 0.4, 0.6, 0.8].repeat(3));
+    }
+
+    #[test]
+    fn test_flat_triangle_colors() {
+        let points = generate_golden_seeds(400.0, 300.0, 100);
+        let triangles = compute_delaunay(&points, 400.0, 300.0);
+        let flat = VertexColorMode::FlatTriangles;
+        let out = colors(&points, &BLACK_TO_WHITE, flat, 2.0, &triangles).unwrap();
+        assert_eq!(out.len(), triangles.len() * 4);
+        for (corners, t) in out.chunks(12).zip(triangles.chunks(3)) {
+            assert_eq!(&corners[..4], &corners[4..8]);
+            assert_eq!(&corners[..4], &corners[8..]);
+            let (x, y) = t.iter().fold((0.0, 0.0), |(x, y), &v| {
+                (x + points[v as usize * 2], y + points[v as usize * 2 + 1])
+            });
+            let at_centroid =
+                scalar(VertexColorMode::NoiseRadial, x / 3.0, y / 3.0, 400.0, 300.0, 2.0);
+            assert!((corners[0] as f64 - at_centroid).abs() < 1e-5);
+        }
+
+        let error = |p: &[f64], palette: &[f32], t: &[u32]| {
+            code_of(colors(p, palette, flat, 0.0, t))
+        };
+        assert_eq!(error(&points, &[], &[]), ErrorCode::InvalidLength);
+        assert_eq!(error(&points, &[0.0; 6], &[]), ErrorCode::InvalidLength);
+        assert_eq!(error(&points, &[f32::NAN; 4], &[]), ErrorCode::NonFiniteInput);
+        assert_eq!(error(&[f64::NAN, 0.0], &BLACK_TO_WHITE, &[]), ErrorCode::NonFiniteInput);
+        assert_eq!(error(&points, &BLACK_TO_WHITE, &[0, 1, 100]), ErrorCode::IndexOutOfRange);
+        let empty = compute_vertex_colors(&points, 0.0, 300.0, 0.0, &BLACK_TO_WHITE, flat, &[]);
+        assert_eq!(code_of(empty), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_triangle_shading() {
+        let points = generate_golden_seeds(400.0, 300.0, 400);
+        let triangles = compute_delaunay(&points, 400.0, 300.0);
+        let shading = |lx: f64, ly: f64, time: f64| {
+            triangle_shading(&points, &triangles, lx, ly, 0.01, time).unwrap()
+        };
+        let lit = shading(1.0, -1.0, 0.0);
+        assert_eq!(lit.len(), triangles.len() / 3 * 4);
+        for (s, t) in lit.chunks(4).zip(triangles.chunks(3)) {
+            assert!((0.0..=1.0).contains(&s[1]));
+            let (x, y) = t.iter().fold((0.0, 0.0), |(x, y), &v| {
+                (x + points[v as usize * 2] / 3.0, y + points[v as usize * 2 + 1] / 3.0)
+            });
+            assert!((s[2] as f64 - x).abs() < 1e-3 && (s[3] as f64 - y).abs() < 1e-3);
+            let fbm = crate::fbm_noise_2d(x * 0.01, y * 0.01, 4, 2.0, 0.5).unwrap();
+            assert!((s[0] as f64 - fbm).abs() < 1e-6);
+        }
+        // The shades actually vary, and the opposite light inverts them
+        let shades = lit.chunks(4).map(|s| s[1]);
+        let (lo, hi) = shades.fold((1.0f32, 0.0f32), |(lo, hi), s| (lo.min(s), hi.max(s)));
+        assert!(lo < 0.4 && hi > 0.6, "{lo} {hi}");
+        let opposite = shading(-2.0, 2.0, 0.0);
+        for (a, b) in lit.chunks(4).zip(opposite.chunks(4)) {
+            assert_eq!((a[0], a[2], a[3]), (b[0], b[2], b[3]));
+            assert!((a[1] + b[1] - 1.0).abs() < 1e-6);
+        }
+        assert_ne!(shading(1.0, -1.0, 0.7), lit, "time morphs the field");
+
+        let error = |lx: f64, scale: f64, time: f64, t: &[u32]| {
+            code_of(triangle_shading(&points, t, lx, 0.0, scale, time))
+        };
+        assert_eq!(error(0.0, 0.01, 0.0, &triangles), ErrorCode::InvalidArgument);
+        assert_eq!(error(1.0, 0.0, 0.0, &triangles), ErrorCode::InvalidArgument);
+        assert_eq!(error(1.0, 0.01, f64::NAN, &triangles), ErrorCode::NonFiniteInput);
+        assert_eq!(error(1.0, 0.01, 0.0, &triangles[..4]), ErrorCode::InvalidLength);
+        assert!(triangle_shading(&points, &[], 1.0, 0.0, 0.01, 0.0).unwrap().is_empty());
+    }
+}
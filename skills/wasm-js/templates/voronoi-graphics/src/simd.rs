@@ -0,0 +1,477 @@
+//! Four `f64` lanes at a time for the batch kernels
+//!
+//! With the `simd` feature, `update_motes` and the batch noise exports run
+//! groups of four elements through kernels written on `Lanes`, a pair of
+//! two-lane registers. Those are `f64x2` on wasm32 built with `simd128`
+//! (`RUSTFLAGS="-C target-feature=+simd128"`) and `__m128d` on x86_64,
+//! where SSE2 is always there. Anywhere else a pair is a `[f64; 2]`, which
+//! size-optimized builds run slower than the scalar loops, so `USE_LANES`
+//! keeps those loops there outside of tests. The choice is made at compile
+//! time.
+//!
+//! Every operation rounds exactly as the scalar one it stands for, and the
+//! kernels keep the scalar code's operation order, so the results are
+//! bit-identical to the scalar path. Branches become a `Mask` and a
+//! `select`; hashing, `sin` and gathers from edge lists stay per lane.
+
+use std::ops::{Add, AddAssign, Div, Mul, Sub};
+
+/// Whether the target has two-lane `f64` registers for `pair`
+const VECTOR: bool = cfg!(any(
+    all(target_arch = "wasm32", target_feature = "simd128"),
+    all(target_arch = "x86_64", target_feature = "sse2"),
+));
+
+/// Whether the batch exports take the four-lane kernels: where `VECTOR`,
+/// and in tests, which check them against the scalar path on any target
+pub(crate) const USE_LANES: bool = cfg!(test) || VECTOR;
+
+/// `[e; 4]` with `$l` bound to each lane index in turn, spelled out so
+/// that size-optimized builds need no loop or closure call per lane
+macro_rules! per_lane {
+    ($l:ident => $e:expr) => {
+        [{ let $l = 0; $e }, { let $l = 1; $e }, { let $l = 2; $e }, { let $l = 3; $e }]
+    };
+}
+pub(crate) use per_lane;
+
+/// Two lanes in SIMD128 registers
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod pair {
+    use core::arch::wasm32::*;
+
+    pub(super) type Pair = v128;
+    pub(super) type PairMask = v128;
+
+    #[inline(always)]
+    pub(super) fn new(a: f64, b: f64) -> Pair {
+        f64x2(a, b)
+    }
+
+    #[inline(always)]
+    pub(super) fn splat(v: f64) -> Pair {
+        f64x2_splat(v)
+    }
+
+    #[inline(always)]
+    pub(super) fn get(p: Pair) -> [f64; 2] {
+        [f64x2_extract_lane::<0>(p), f64x2_extract_lane::<1>(p)]
+    }
+
+    #[inline(always)]
+    pub(super) fn add(a: Pair, b: Pair) -> Pair {
+        f64x2_add(a, b)
+    }
+
+    #[inline(always)]
+    pub(super) fn sub(a: Pair, b: Pair) -> Pair {
+        f64x2_sub(a, b)
+    }
+
+    #[inline(always)]
+    pub(super) fn mul(a: Pair, b: Pair) -> Pair {
+        f64x2_mul(a, b)
+    }
+
+    #[inline(always)]
+    pub(super) fn div(a: Pair, b: Pair) -> Pair {
+        f64x2_div(a, b)
+    }
+
+    #[inline(always)]
+    pub(super) fn floor(p: Pair) -> Pair {
+        f64x2_floor(p)
+    }
+
+    #[inline(always)]
+    pub(super) fn abs(p: Pair) -> Pair {
+        f64x2_abs(p)
+    }
+
+    #[inline(always)]
+    pub(super) fn lt(a: Pair, b: Pair) -> PairMask {
+        f64x2_lt(a, b)
+    }
+
+    #[inline(always)]
+    pub(super) fn gt(a: Pair, b: Pair) -> PairMask {
+        f64x2_gt(a, b)
+    }
+
+    #[inline(always)]
+    pub(super) fn ge(a: Pair, b: Pair) -> PairMask {
+        f64x2_ge(a, b)
+    }
+
+    #[inline(always)]
+    pub(super) fn mask(a: bool, b: bool) -> PairMask {
+        i64x2(-(a as i64), -(b as i64))
+    }
+
+    #[inline(always)]
+    pub(super) fn select(m: PairMask, on: Pair, off: Pair) -> Pair {
+        v128_bitselect(on, off, m)
+    }
+}
+
+/// Two lanes in SSE2 registers
+///
+/// The intrinsics are `unsafe` only because they need SSE2, which every
+/// x86_64 target has and this module is only built with.
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+mod pair {
+    use core::arch::x86_64::*;
+
+    pub(super) type Pair = __m128d;
+    pub(super) type PairMask = __m128d;
+
+    #[inline(always)]
+    pub(super) fn new(a: f64, b: f64) -> Pair {
+        unsafe { _mm_set_pd(b, a) }
+    }
+
+    #[inline(always)]
+    pub(super) fn splat(v: f64) -> Pair {
+        unsafe { _mm_set1_pd(v) }
+    }
+
+    #[inline(always)]
+    pub(super) fn get(p: Pair) -> [f64; 2] {
+        unsafe { [_mm_cvtsd_f64(p), _mm_cvtsd_f64(_mm_unpackhi_pd(p, p))] }
+    }
+
+    #[inline(always)]
+    pub(super) fn add(a: Pair, b: Pair) -> Pair {
+        unsafe { _mm_add_pd(a, b) }
+    }
+
+    #[inline(always)]
+    pub(super) fn sub(a: Pair, b: Pair) -> Pair {
+        unsafe { _mm_sub_pd(a, b) }
+    }
+
+    #[inline(always)]
+    pub(super) fn mul(a: Pair, b: Pair) -> Pair {
+        unsafe { _mm_mul_pd(a, b) }
+    }
+
+    #[inline(always)]
+    pub(super) fn div(a: Pair, b: Pair) -> Pair {
+        unsafe { _mm_div_pd(a, b) }
+    }
+
+    /// SSE2 has no rounding instruction, so this goes through `f64::floor`
+    #[inline(always)]
+    pub(super) fn floor(p: Pair) -> Pair {
+        let [a, b] = get(p);
+        new(a.floor(), b.floor())
+    }
+
+    #[inline(always)]
+    pub(super) fn abs(p: Pair) -> Pair {
+        unsafe { _mm_andnot_pd(_mm_set1_pd(-0.0), p) }
+    }
+
+    #[inline(always)]
+    pub(super) fn lt(a: Pair, b: Pair) -> PairMask {
+        unsafe { _mm_cmplt_pd(a, b) }
+    }
+
+    #[inline(always)]
+    pub(super) fn gt(a: Pair, b: Pair) -> PairMask {
+        unsafe { _mm_cmpgt_pd(a, b) }
+    }
+
+    #[inline(always)]
+    pub(super) fn ge(a: Pair, b: Pair) -> PairMask {
+        unsafe { _mm_cmpge_pd(a, b) }
+    }
+
+    #[inline(always)]
+    pub(super) fn mask(a: bool, b: bool) -> PairMask {
+        unsafe { _mm_castsi128_pd(_mm_set_epi64x(-(b as i64), -(a as i64))) }
+    }
+
+    #[inline(always)]
+    pub(super) fn select(m: PairMask, on: Pair, off: Pair) -> Pair {
+        unsafe { _mm_or_pd(_mm_and_pd(m, on), _mm_andnot_pd(m, off)) }
+    }
+}
+
+/// Two lanes as a plain array, for targets without `f64` vector registers
+#[cfg(not(any(
+    all(target_arch = "wasm32", target_feature = "simd128"),
+    all(target_arch = "x86_64", target_feature = "sse2"),
+)))]
+mod pair {
+    pub(super) type Pair = [f64; 2];
+    pub(super) type PairMask = [bool; 2];
+
+    #[inline(always)]
+    pub(super) fn new(a: f64, b: f64) -> Pair {
+        [a, b]
+    }
+
+    #[inline(always)]
+    pub(super) fn splat(v: f64) -> Pair {
+        [v, v]
+    }
+
+    #[inline(always)]
+    pub(super) fn get(p: Pair) -> [f64; 2] {
+        p
+    }
+
+    #[inline(always)]
+    pub(super) fn add(a: Pair, b: Pair) -> Pair {
+        [a[0] + b[0], a[1] + b[1]]
+    }
+
+    #[inline(always)]
+    pub(super) fn sub(a: Pair, b: Pair) -> Pair {
+        [a[0] - b[0], a[1] - b[1]]
+    }
+
+    #[inline(always)]
+    pub(super) fn mul(a: Pair, b: Pair) -> Pair {
+        [a[0] * b[0], a[1] * b[1]]
+    }
+
+    #[inline(always)]
+    pub(super) fn div(a: Pair, b: Pair) -> Pair {
+        [a[0] / b[0], a[1] / b[1]]
+    }
+
+    #[inline(always)]
+    pub(super) fn floor(p: Pair) -> Pair {
+        [p[0].floor(), p[1].floor()]
+    }
+
+    #[inline(always)]
+    pub(super) fn abs(p: Pair) -> Pair {
+        [p[0].abs(), p[1].abs()]
+    }
+
+    #[inline(always)]
+    pub(super) fn lt(a: Pair, b: Pair) -> PairMask {
+        [a[0] < b[0], a[1] < b[1]]
+    }
+
+    #[inline(always)]
+    pub(super) fn gt(a: Pair, b: Pair) -> PairMask {
+        [a[0] > b[0], a[1] > b[1]]
+    }
+
+    #[inline(always)]
+    pub(super) fn ge(a: Pair, b: Pair) -> PairMask {
+        [a[0] >= b[0], a[1] >= b[1]]
+    }
+
+    #[inline(always)]
+    pub(super) fn mask(a: bool, b: bool) -> PairMask {
+        [a, b]
+    }
+
+    #[inline(always)]
+    pub(super) fn select(m: PairMask, on: Pair, off: Pair) -> Pair {
+        [if m[0] { on[0] } else { off[0] }, if m[1] { on[1] } else { off[1] }]
+    }
+}
+
+/// Four `f64` values operated on together
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Lanes([pair::Pair; 2]);
+
+/// Per-lane outcome of a comparison of `Lanes`
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Mask([pair::PairMask; 2]);
+
+impl Lanes {
+    #[inline(always)]
+    pub(crate) fn splat(v: f64) -> Lanes {
+        let p = pair::splat(v);
+        Lanes([p, p])
+    }
+
+    #[inline(always)]
+    pub(crate) fn from_array(a: [f64; 4]) -> Lanes {
+        Lanes([pair::new(a[0], a[1]), pair::new(a[2], a[3])])
+    }
+
+    #[inline(always)]
+    pub(crate) fn to_array(self) -> [f64; 4] {
+        let ([a, b], [c, d]) = (pair::get(self.0[0]), pair::get(self.0[1]));
+        [a, b, c, d]
+    }
+
+    #[inline(always)]
+    pub(crate) fn floor(self) -> Lanes {
+        Lanes([pair::floor(self.0[0]), pair::floor(self.0[1])])
+    }
+
+    #[inline(always)]
+    pub(crate) fn abs(self) -> Lanes {
+        Lanes([pair::abs(self.0[0]), pair::abs(self.0[1])])
+    }
+
+    #[inline(always)]
+    pub(crate) fn lt(self, other: Lanes) -> Mask {
+        Mask([pair::lt(self.0[0], other.0[0]), pair::lt(self.0[1], other.0[1])])
+    }
+
+    #[inline(always)]
+    pub(crate) fn gt(self, other: Lanes) -> Mask {
+        Mask([pair::gt(self.0[0], other.0[0]), pair::gt(self.0[1], other.0[1])])
+    }
+
+    #[inline(always)]
+    pub(crate) fn ge(self, other: Lanes) -> Mask {
+        Mask([pair::ge(self.0[0], other.0[0]), pair::ge(self.0[1], other.0[1])])
+    }
+
+    /// `f64::clamp` in each lane: NaN stays NaN
+    #[inline(always)]
+    pub(crate) fn clamp(self, min: f64, max: f64) -> Lanes {
+        let (min, max) = (Lanes::splat(min), Lanes::splat(max));
+        let v = self.lt(min).select(min, self);
+        v.gt(max).select(max, v)
+    }
+}
+
+impl Mask {
+    #[inline(always)]
+    pub(crate) fn from_array(a: [bool; 4]) -> Mask {
+        Mask([pair::mask(a[0], a[1]), pair::mask(a[2], a[3])])
+    }
+
+    /// `on` in the lanes where the mask is set, `off` elsewhere
+    #[inline(always)]
+    pub(crate) fn select(self, on: Lanes, off: Lanes) -> Lanes {
+        Lanes([
+            pair::select(self.0[0], on.0[0], off.0[0]),
+            pair::select(self.0[1], on.0[1], off.0[1]),
+        ])
+    }
+}
+
+/// `Lanes op Lanes`, `Lanes op f64` and `f64 op Lanes`, lane by lane
+macro_rules! lane_op {
+    ($trait:ident, $method:ident, $op:tt) => {
+        impl $trait for Lanes {
+            type Output = Lanes;
+            #[inline(always)]
+            fn $method(self, other: Lanes) -> Lanes {
+                Lanes([pair::$method(self.0[0], other.0[0]), pair::$method(self.0[1], other.0[1])])
+            }
+        }
+
+        impl $trait<f64> for Lanes {
+            type Output = Lanes;
+            #[inline(always)]
+            fn $method(self, other: f64) -> Lanes {
+                self $op Lanes::splat(other)
+            }
+        }
+
+        impl $trait<Lanes> for f64 {
+            type Output = Lanes;
+            #[inline(always)]
+            fn $method(self, other: Lanes) -> Lanes {
+                Lanes::splat(self) $op other
+            }
+        }
+    };
+}
+
+lane_op!(Add, add, +);
+lane_op!(Sub, sub, -);
+lane_op!(Mul, mul, *);
+lane_op!(Div, div, /);
+
+impl AddAssign for Lanes {
+    #[inline(always)]
+    fn add_assign(&mut self, other: Lanes) {
+        *self = *self + other;
+    }
+}
+
+/// `one(x, y)` for each `[x, y]` pair of `coords`, in order; where
+/// `USE_LANES`, each whole group of four pairs goes through `four` instead
+pub(crate) fn map_pairs<T: Copy>(
+    coords: &[f64],
+    one: impl Fn(f64, f64) -> T,
+    four: impl Fn(Lanes, Lanes) -> [T; 4],
+) -> Vec<T> {
+    if !USE_LANES {
+        return coords.chunks_exact(2).map(|c| one(c[0], c[1])).collect();
+    }
+    let mut out = Vec::with_capacity(coords.len() / 2);
+    let mut groups = coords.chunks_exact(8);
+    for g in &mut groups {
+        let (x, y) = ([g[0], g[2], g[4], g[6]], [g[1], g[3], g[5], g[7]]);
+        out.extend_from_slice(&four(Lanes::from_array(x), Lanes::from_array(y)));
+    }
+    out.extend(groups.remainder().chunks_exact(2).map(|c| one(c[0], c[1])));
+    out
+}
+
+/// Lane `l` of each of `lanes`, for each `l`
+pub(crate) fn transpose<const N: usize>(lanes: [Lanes; N]) -> [[f64; N]; 4] {
+    let lanes = lanes.map(Lanes::to_array);
+    std::array::from_fn(|l| std::array::from_fn(|v| lanes[v][l]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Values on both sides of every rounding and comparison edge case
+    const AWKWARD: [f64; 12] =
+        [0.0, -0.0, 0.5, -0.5, 1.0, -1.0, 2.5, -2.5, 1e-300, -7.25e12, f64::INFINITY, f64::NAN];
+
+    fn bits(l: Lanes) -> [u64; 4] {
+        l.to_array().map(f64::to_bits)
+    }
+
+    #[test]
+    fn test_lane_ops_round_as_scalar_ops() {
+        for a in AWKWARD.chunks_exact(4) {
+            for b in AWKWARD.chunks_exact(4) {
+                let (a, b) = ([a[0], a[1], a[2], a[3]], [b[0], b[1], b[2], b[3]]);
+                let (la, lb) = (Lanes::from_array(a), Lanes::from_array(b));
+                let each = |f: fn(f64, f64) -> f64| std::array::from_fn(|l| f(a[l], b[l]));
+                // NaN results may differ in payload between targets, so
+                // compare those by kind and everything else by bits
+                let same = |lanes: Lanes, scalar: [f64; 4]| {
+                    for (v, s) in lanes.to_array().into_iter().zip(scalar) {
+                        assert!(v.to_bits() == s.to_bits() || (v.is_nan() && s.is_nan()));
+                    }
+                };
+                same(la + lb, each(|x, y| x + y));
+                same(la - lb, each(|x, y| x - y));
+                same(la * lb, each(|x, y| x * y));
+                same(la / lb, each(|x, y| x / y));
+                same(la.floor(), a.map(f64::floor));
+                same(la.abs(), a.map(f64::abs));
+                same(la.clamp(-1.0, 1.0), a.map(|v| v.clamp(-1.0, 1.0)));
+                let pick = |m: Mask| bits(m.select(Lanes::splat(1.0), Lanes::splat(0.0)));
+                let want = |f: fn(&f64, &f64) -> bool| {
+                    std::array::from_fn(|l| if f(&a[l], &b[l]) { 1.0 } else { 0.0 })
+                };
+                assert_eq!(pick(la.lt(lb)), bits(Lanes::from_array(want(f64::lt))));
+                assert_eq!(pick(la.gt(lb)), bits(Lanes::from_array(want(f64::gt))));
+                assert_eq!(pick(la.ge(lb)), bits(Lanes::from_array(want(f64::ge))));
+            }
+        }
+    }
+
+    #[test]
+    fn test_map_pairs_keeps_order_and_the_remainder() {
+        for pairs in 0..11 {
+            let coords: Vec<f64> = (0..pairs * 2).map(|v| v as f64).collect();
+            let sums = map_pairs(&coords, |x, y| x + y, |x, y| (x + y).to_array());
+            let want: Vec<f64> = coords.chunks_exact(2).map(|c| c[0] + c[1]).collect();
+            assert_eq!(sums, want);
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Compact binary snapshots of a finished triangulation
+//!
+//! Meant for shipping a precomputed mesh as a static asset. The layout is
+//! little-endian throughout:
+//!
+//! | bytes    | field                                      |
+//! |----------|--------------------------------------------|
+//! | 4        | magic `VGTR`                               |
+//! | 1        | format version, currently 1                |
+//! | 4        | point count `n`                            |
+//! | 4        | triangle count `t`                         |
+//! | 8 `n`    | coordinates `x0, y0, x1, y1, ...` as `f32` |
+//! | 12 `t`   | vertex indices, 3 per triangle, as `u32`   |
+//!
+//! Coordinates are stored at `f32` precision, which is exact to well
+//! under a pixel on any practical canvas. Loading checks every length and
+//! index, so truncated or corrupt bytes give an error rather than a
+//! panic; a version other than the one this build writes is refused, so
+//! a newer asset fails loudly instead of being misread.
+
+use wasm_bindgen::prelude::*;
+
+use crate::codec::{ByteReader, ByteWriter};
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles};
+
+const TRIANGULATION_MAGIC: &[u8; 4] = b"VGTR";
+const TRIANGULATION_VERSION: u8 = 1;
+// Magic, version and the two counts
+const HEADER_LEN: u64 = 4 + 1 + 4 + 4;
+
+/// Points and triangles read back by `deserialize_triangulation`
+#[wasm_bindgen]
+pub struct TriangulationSnapshot {
+    points: Vec<f64>,
+    triangles: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl TriangulationSnapshot {
+    /// `[x0, y0, x1, y1, ...]`, widened from the stored `f32` values
+    pub fn points(&self) -> Vec<f64> {
+        self.points.clone()
+    }
+
+    /// Vertex indices, 3 per triangle, as they were written
+    pub fn triangles(&self) -> Vec<u32> {
+        self.triangles.clone()
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.points.len() / 2
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len() / 3
+    }
+}
+
+/// Encode `points_flat` and `triangles` in the snapshot layout above
+///
+/// Coordinates must be finite and within `f32` range.
+#[wasm_bindgen]
+pub fn serialize_triangulation(points_flat: &[f64], triangles: &[u32]) -> Result<Vec<u8>> {
+    let n = check_points(points_flat)?;
+    check_triangles(triangles, n)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    if let Some(bad) = points_flat.iter().find(|v| v.abs() > f32::MAX as f64) {
+        return Err(Error::InvalidArgument(format!("coordinate {bad} is out of f32 range")));
+    }
+    if n > u32::MAX as usize || triangles.len() / 3 > u32::MAX as usize {
+        return Err(Error::TooLarge("too many points or triangles for a snapshot".into()));
+    }
+    let mut w = ByteWriter::new();
+    w.buf.reserve(HEADER_LEN as usize + points_flat.len() * 4 + triangles.len() * 4);
+    w.bytes(TRIANGULATION_MAGIC);
+    w.u8(TRIANGULATION_VERSION);
+    w.u32(n as u32);
+    w.u32((triangles.len() / 3) as u32);
+    points_flat.iter().for_each(|&v| w.f32(v as f32));
+    triangles.iter().for_each(|&v| w.u32(v));
+    Ok(w.buf)
+}
+
+/// Decode bytes from `serialize_triangulation`
+///
+/// Errors with `VersionMismatch` for another format version and with
+/// `InvalidData` for a bad magic, a length that does not match the
+/// counts, a non-finite coordinate or an index past the point count.
+#[wasm_bindgen]
+pub fn deserialize_triangulation(bytes: &[u8]) -> Result<TriangulationSnapshot> {
+    let mut r = ByteReader::new(bytes);
+    let version = r.header(TRIANGULATION_MAGIC)?;
+    if version != TRIANGULATION_VERSION {
+        return Err(Error::VersionMismatch(format!(
+            "triangulation snapshot version {version}, expected {TRIANGULATION_VERSION}"
+        )));
+    }
+    let n = r.u32()?;
+    let t = r.u32()?;
+    // Check the total up front so corrupt counts never drive a huge
+    // allocation
+    let expected = HEADER_LEN + 8 * n as u64 + 12 * t as u64;
+    if bytes.len() as u64 != expected {
+        return Err(Error::InvalidData(format!(
+            "{n} points and {t} triangles need {expected} bytes, got {}",
+            bytes.len()
+        )));
+    }
+    let points = (0..2 * n).map(|_| r.f32().map(f64::from)).collect::<Result<Vec<f64>>>()?;
+    if points.iter().any(|v| !v.is_finite()) {
+        return Err(Error::InvalidData("non-finite coordinate in snapshot".into()));
+    }
+    let triangles = (0..3 * t).map(|_| r.u32()).collect::<Result<Vec<u32>>>()?;
+    if let Some(&bad) = triangles.iter().find(|&&v| v >= n) {
+        return Err(Error::InvalidData(format!(
+            "snapshot triangle references vertex {bad} of {n}"
+        )));
+    }
+    r.finish()?;
+    Ok(TriangulationSnapshot { points, triangles })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::compute_delaunay;
+    use crate::rng::Rng;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut rng = Rng::new(21);
+        let points: Vec<f64> =
+            (0..3000).flat_map(|_| [rng.range(0.0, 1920.0), rng.range(0.0, 1080.0)]).collect();
+        let triangles = compute_delaunay(&points, 1920.0, 1080.0);
+        let bytes = serialize_triangulation(&points, &triangles).unwrap();
+        assert_eq!(bytes.len(), 13 + 8 * 3000 + 4 * triangles.len());
+        assert_eq!(&bytes[..5], b"VGTR\x01");
+
+        let snapshot = deserialize_triangulation(&bytes).unwrap();
+        assert_eq!(snapshot.point_count(), 3000);
+        assert_eq!(snapshot.triangle_count(), triangles.len() / 3);
+        assert_eq!(snapshot.triangles(), triangles);
+        let rounded: Vec<f64> = points.iter().map(|&v| v as f32 as f64).collect();
+        assert_eq!(snapshot.points(), rounded);
+        // Stored values survive a second trip exactly
+        let again = serialize_triangulation(&snapshot.points(), &snapshot.triangles()).unwrap();
+        assert_eq!(again, bytes);
+
+        let empty = deserialize_triangulation(&serialize_triangulation(&[], &[]).unwrap());
+        assert_eq!(empty.unwrap().point_count(), 0);
+
+        let save = |p: &[f64], t: &[u32]| code_of(serialize_triangulation(p, t));
+        assert_eq!(save(&[0.0, 1.0, 2.0], &[]), ErrorCode::InvalidLength);
+        assert_eq!(save(&[0.0, 0.0, 1.0, 0.0], &[0, 1, 2]), ErrorCode::IndexOutOfRange);
+        assert_eq!(save(&[f64::NAN, 0.0], &[]), ErrorCode::NonFiniteInput);
+        assert_eq!(save(&[1e39, 0.0], &[]), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_snapshot_rejects_corrupt_input() {
+        let points = [0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0];
+        let bytes = serialize_triangulation(&points, &[0, 1, 2, 0, 2, 3]).unwrap();
+        let load = |b: &[u8]| code_of(deserialize_triangulation(b));
+
+        for len in 0..bytes.len() {
+            assert_eq!(load(&bytes[..len]), ErrorCode::InvalidData, "truncated to {len}");
+        }
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(load(&trailing), ErrorCode::InvalidData);
+
+        let mut version = bytes.clone();
+        version[4] = 2;
+        assert_eq!(load(&version), ErrorCode::VersionMismatch);
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        assert_eq!(load(&magic), ErrorCode::InvalidData);
+        // A huge point count is caught by the length check, not allocated
+        let mut count = bytes.clone();
+        count[5..9].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(load(&count), ErrorCode::InvalidData);
+        let mut index = bytes.clone();
+        let last = index.len() - 4;
+        index[last..].copy_from_slice(&4u32.to_le_bytes());
+        assert_eq!(load(&index), ErrorCode::InvalidData);
+        let mut coordinate = bytes.clone();
+        coordinate[13..17].copy_from_slice(&f32::NAN.to_le_bytes());
+        assert_eq!(load(&coordinate), ErrorCode::InvalidData);
+
+        // Flipping any single byte must error or decode, never panic
+        for i in 0..bytes.len() {
+            let mut flipped = bytes.clone();
+            flipped[i] ^= 0xFF;
+            let _ = deserialize_triangulation(&flipped);
+        }
+    }
+}
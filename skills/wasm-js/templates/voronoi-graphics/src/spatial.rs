@@ -0,0 +1,283 @@
+//! Uniform bucket grid for broad-phase spatial queries
+//!
+//! Items are registered by axis-aligned bounding box into every cell the
+//! box overlaps; a query returns each candidate id once, in ascending
+//! order, so callers iterating the result stay deterministic.
+
+use crate::Point;
+
+/// Axis-aligned box `[min_x, min_y, max_x, max_y]`
+pub(crate) type Aabb = [f64; 4];
+
+/// Cells per axis are capped so a tiny cell size cannot blow up memory
+const MAX_CELLS_PER_AXIS: usize = 1024;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Grid {
+    min_x: f64,
+    min_y: f64,
+    inv_cell: f64,
+    cols: usize,
+    rows: usize,
+    cells: Vec<Vec<u32>>,
+}
+
+impl Grid {
+    /// Grid over `bounds` with roughly `cell_size` square cells
+    pub(crate) fn new(bounds: Aabb, cell_size: f64) -> Grid {
+        let [min_x, min_y, max_x, max_y] = bounds;
+        let w = (max_x - min_x).max(0.0);
+        let h = (max_y - min_y).max(0.0);
+        let cell = if cell_size.is_finite() && cell_size > 0.0 {
+            cell_size.max(w.max(h) / MAX_CELLS_PER_AXIS as f64)
+        } else {
+            w.max(h).max(1.0)
+        };
+        let cols = ((w / cell).floor() as usize + 1).min(MAX_CELLS_PER_AXIS);
+        let rows = ((h / cell).floor() as usize + 1).min(MAX_CELLS_PER_AXIS);
+        Grid {
+            min_x,
+            min_y,
+            inv_cell: 1.0 / cell,
+            cols,
+            rows,
+            cells: vec![Vec::new(); cols * rows],
+        }
+    }
+
+    /// Grid fitted to `boxes`, with about one item per cell on average
+    pub(crate) fn fitted(boxes: &[Aabb]) -> Grid {
+        let mut bounds = [f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY];
+        let mut size_sum = 0.0;
+        for b in boxes {
+            bounds[0] = bounds[0].min(b[0]);
+            bounds[1] = bounds[1].min(b[1]);
+            bounds[2] = bounds[2].max(b[2]);
+            bounds[3] = bounds[3].max(b[3]);
+            size_sum += (b[2] - b[0]).max(b[3] - b[1]);
+        }
+        if boxes.is_empty() {
+            bounds = [0.0, 0.0, 1.0, 1.0];
+        }
+        let mean_size = size_sum / boxes.len().max(1) as f64;
+        let area = (bounds[2] - bounds[0]) * (bounds[3] - bounds[1]);
+        let density_cell = (area / boxes.len().max(1) as f64).sqrt();
+        Grid::new(bounds, mean_size.max(density_cell))
+    }
+
+    /// Inclusive cell range covered by `b`, clamped to the grid
+    fn span(&self, b: &Aabb) -> (usize, usize, usize, usize) {
+        let col = |x: f64| {
+            let c = ((x - self.min_x) * self.inv_cell).floor();
+            (c.max(0.0) as usize).min(self.cols - 1)
+        };
+        let row = |y: f64| {
+            let r = ((y - self.min_y) * self.inv_cell).floor();
+            (r.max(0.0) as usize).min(self.rows - 1)
+        };
+        (col(b[0]), row(b[1]), col(b[2]), row(b[3]))
+    }
+
+    pub(crate) fn insert(&mut self, id: u32, b: &Aabb) {
+        let (c0, r0, c1, r1) = self.span(b);
+        for r in r0..=r1 {
+            for c in c0..=c1 {
+                self.cells[r * self.cols + c].push(id);
+            }
+        }
+    }
+
+    /// Ids whose cells overlap `b`, sorted and deduplicated into `out`
+    pub(crate) fn query(&self, b: &Aabb, out: &mut Vec<u32>) {
+        out.clear();
+        let (c0, r0, c1, r1) = self.span(b);
+        for r in r0..=r1 {
+            for c in c0..=c1 {
+                out.extend_from_slice(&self.cells[r * self.cols + c]);
+            }
+        }
+        out.sort_unstable();
+        out.dedup();
+    }
+
+    /// Call `f` with every id in the cells overlapping `b`, an id once
+    /// per cell it was inserted into
+    pub(crate) fn for_each_candidate(&self, b: &Aabb, mut f: impl FnMut(u32)) {
+        let (c0, r0, c1, r1) = self.span(b);
+        for r in r0..=r1 {
+            for &id in self.cells[r * self.cols + c0..=r * self.cols + c1].iter().flatten() {
+                f(id);
+            }
+        }
+    }
+}
+
+/// Bucketed point set answering exact nearest-point queries
+#[derive(Clone, Debug)]
+pub(crate) struct PointGrid {
+    grid: Grid,
+    points: Vec<Point>,
+    cell: f64,
+}
+
+impl PointGrid {
+    /// Index `points`; queries are exact for any location inside `bounds`
+    /// (which is widened to cover the points)
+    pub(crate) fn new(points: &[Point], bounds: Aabb) -> PointGrid {
+        let mut b = bounds;
+        for p in points {
+            b = [b[0].min(p.x), b[1].min(p.y), b[2].max(p.x), b[3].max(p.y)];
+        }
+        let area = ((b[2] - b[0]) * (b[3] - b[1])).max(f64::MIN_POSITIVE);
+        let mut grid = Grid::new(b, (area / points.len().max(1) as f64).sqrt());
+        for (i, p) in points.iter().enumerate() {
+            grid.insert(i as u32, &[p.x, p.y, p.x, p.y]);
+        }
+        let cell = 1.0 / grid.inv_cell;
+        PointGrid { grid, points: points.to_vec(), cell }
+    }
+
+    /// Index of the point nearest `p`, ties to the lowest index; `None`
+    /// only when the set is empty
+    pub(crate) fn nearest(&self, p: Point) -> Option<u32> {
+        if self.points.is_empty() {
+            return None;
+        }
+        let g = &self.grid;
+        let (c, r, _, _) = g.span(&[p.x, p.y, p.x, p.y]);
+        let mut best: Option<(f64, u32)> = None;
+        for ring in 0..g.cols.max(g.rows) {
+            let (c0, c1) = (c.saturating_sub(ring), (c + ring).min(g.cols - 1));
+            let (r0, r1) = (r.saturating_sub(ring), (r + ring).min(g.rows - 1));
+            for row in r0..=r1 {
+                for col in c0..=c1 {
+                    let on_ring = row == r0 || row == r1 || col == c0 || col == c1;
+                    if !on_ring || row.abs_diff(r).max(col.abs_diff(c)) != ring {
+                        continue;
+                    }
+                    for &i in &g.cells[row * g.cols + col] {
+                        let q = self.points[i as usize];
+                        let d = (q.x - p.x).powi(2) + (q.y - p.y).powi(2);
+                        if best.is_none_or(|(bd, bi)| d < bd || (d == bd && i < bi)) {
+                            best = Some((d, i));
+                        }
+                    }
+                }
+            }
+            // Everything beyond this ring is at least `ring · cell` away
+            if let Some((d, _)) = best {
+                let reach = ring as f64 * self.cell;
+                if d < reach * reach {
+                    break;
+                }
+            }
+        }
+        best.map(|(_, i)| i)
+    }
+
+    /// Indices of the `k` points nearest `p`, closest first with ties to
+    /// the lowest index; all points when there are fewer than `k`
+    pub(crate) fn k_nearest(&self, p: Point, k: usize) -> Vec<u32> {
+        let d2 = |q: Point| (q.x - p.x).powi(2) + (q.y - p.y).powi(2);
+        let best = self.k_nearest_by(p, k, d2, |reach| reach * reach);
+        best.into_iter().map(|(_, i)| i).collect()
+    }
+
+    /// `k_nearest` under any metric as `(key, index)` pairs, where `key`
+    /// orders points by distance and `bound` turns a distance into the
+    /// key of a point that far away. Exact when the metric never undercuts
+    /// the Chebyshev distance, as Euclidean and Manhattan do not.
+    pub(crate) fn k_nearest_by(
+        &self,
+        p: Point,
+        k: usize,
+        key: impl Fn(Point) -> f64,
+        bound: impl Fn(f64) -> f64,
+    ) -> Vec<(f64, u32)> {
+        let k = k.min(self.points.len());
+        if k == 0 {
+            return Vec::new();
+        }
+        let g = &self.grid;
+        let (c, r, _, _) = g.span(&[p.x, p.y, p.x, p.y]);
+        // Sorted by (key, index), at most `k` long
+        let mut best: Vec<(f64, u32)> = Vec::with_capacity(k + 1);
+        for ring in 0..g.cols.max(g.rows) {
+            let (c0, c1) = (c.saturating_sub(ring), (c + ring).min(g.cols - 1));
+            let (r0, r1) = (r.saturating_sub(ring), (r + ring).min(g.rows - 1));
+            for row in r0..=r1 {
+                for col in c0..=c1 {
+                    if row.abs_diff(r).max(col.abs_diff(c)) != ring {
+                        continue;
+                    }
+                    for &i in &g.cells[row * g.cols + col] {
+                        let entry = (key(self.points[i as usize]), i);
+                        let at = best.partition_point(|&(d, j)| (d, j) < entry);
+                        if at < k {
+                            best.insert(at, entry);
+                            best.truncate(k);
+                        }
+                    }
+                }
+            }
+            if best.len() == k && best[k - 1].0 < bound(ring as f64 * self.cell) {
+                break;
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    #[test]
+    fn test_grid_query_finds_overlapping_boxes_once() {
+        let boxes: Vec<Aabb> = (0..100)
+            .map(|i| {
+                let x = (i % 10) as f64 * 10.0;
+                let y = (i / 10) as f64 * 10.0;
+                [x, y, x + 15.0, y + 15.0]
+            })
+            .collect();
+        let mut grid = Grid::fitted(&boxes);
+        for (i, b) in boxes.iter().enumerate() {
+            grid.insert(i as u32, b);
+        }
+        let mut out = Vec::new();
+        let probe = [42.0, 42.0, 43.0, 43.0];
+        grid.query(&probe, &mut out);
+        let overlapping: Vec<u32> = (0..100u32)
+            .filter(|&i| {
+                let b = boxes[i as usize];
+                b[0] <= probe[2] && probe[0] <= b[2] && b[1] <= probe[3] && probe[1] <= b[3]
+            })
+            .collect();
+        assert!(overlapping.iter().all(|i| out.contains(i)));
+        assert!(out.windows(2).all(|w| w[0] < w[1]));
+        // Outside the bounds clamps to the border cells instead of panicking
+        grid.query(&[-1e9, -1e9, -1e9, -1e9], &mut out);
+    }
+
+    #[test]
+    fn test_point_grid_nearest_matches_brute_force() {
+        let mut rng = Rng::new(17);
+        let mut next = || Point { x: rng.next_f64() * 400.0, y: rng.next_f64() * 100.0 };
+        let points: Vec<Point> = (0..300).map(|_| next()).collect();
+        let grid = PointGrid::new(&points, [0.0, 0.0, 400.0, 100.0]);
+        for _ in 0..2000 {
+            let p = next();
+            let brute = (0..points.len())
+                .min_by(|&a, &b| {
+                    let da = (points[a].x - p.x).powi(2) + (points[a].y - p.y).powi(2);
+                    let db = (points[b].x - p.x).powi(2) + (points[b].y - p.y).powi(2);
+                    da.total_cmp(&db).then(a.cmp(&b))
+                })
+                .unwrap();
+            assert_eq!(grid.nearest(p), Some(brute as u32));
+        }
+        assert_eq!(PointGrid::new(&[], [0.0, 0.0, 1.0, 1.0]).nearest(points[0]), None);
+    }
+}
@@ -0,0 +1,288 @@
+//! Spectral embedding of the Delaunay graph
+//!
+//! Coordinates come from the two smallest non-trivial eigenvectors of the
+//! normalized Laplacian `I - D^-1/2 A D^-1/2`. They are found by power
+//! iteration on the shifted matrix `(I + D^-1/2 A D^-1/2) / 2`, whose
+//! spectrum lies in `[0, 1]` with the smallest Laplacian modes on top,
+//! deflating the trivial `√d` vector and each found vector in turn.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_triangles, unique_edges, Csr, UnionFind};
+use crate::rng::Rng;
+
+/// Gap left between packed components, relative to the unit square
+const PACK_GAP: f64 = 0.1;
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn normalize(v: &mut [f64]) -> bool {
+    let n = dot(v, v).sqrt();
+    if n <= f64::MIN_POSITIVE {
+        return false;
+    }
+    v.iter_mut().for_each(|x| *x /= n);
+    true
+}
+
+/// Project out each (unit) basis vector
+fn deflate(v: &mut [f64], basis: &[Vec<f64>]) {
+    for b in basis {
+        let d = dot(v, b);
+        v.iter_mut().zip(b).for_each(|(x, y)| *x -= d * y);
+    }
+}
+
+/// Shifted operator restricted to one connected component
+struct Component<'a> {
+    csr: &'a Csr,
+    /// Local → global vertex ids
+    local: &'a [u32],
+    /// Global → local ids (valid for this component's vertices)
+    index: &'a [u32],
+    inv_sqrt_deg: &'a [f64],
+}
+
+/// Next eigenvector of the shifted operator orthogonal to `basis`
+fn power_iterate(
+    op: &Component,
+    basis: &[Vec<f64>],
+    rng: &mut Rng,
+    iterations: u32,
+    tolerance: f64,
+) -> Vec<f64> {
+    let Component { csr, local, index, inv_sqrt_deg } = *op;
+    let n = local.len();
+    let mut v: Vec<f64> = (0..n).map(|_| rng.next_f64() - 0.5).collect();
+    deflate(&mut v, basis);
+    if !normalize(&mut v) {
+        return vec![0.0; n];
+    }
+    let mut next = vec![0.0; n];
+    for _ in 0..iterations {
+        for (li, &g) in local.iter().enumerate() {
+            let mut acc = 0.0;
+            for &u in csr.neighbors(g) {
+                acc += inv_sqrt_deg[u as usize] * v[index[u as usize] as usize];
+            }
+            next[li] = 0.5 * (v[li] + inv_sqrt_deg[g as usize] * acc);
+        }
+        deflate(&mut next, basis);
+        if !normalize(&mut next) {
+            return vec![0.0; n];
+        }
+        let delta = next.iter().zip(&v).map(|(a, b)| (a - b).abs()).fold(0.0, f64::max);
+        std::mem::swap(&mut v, &mut next);
+        if delta < tolerance {
+            break;
+        }
+    }
+    // Fix the sign: the largest-magnitude entry is positive
+    let mut pivot = 0;
+    for i in 1..n {
+        if v[i].abs() > v[pivot].abs() {
+            pivot = i;
+        }
+    }
+    if v[pivot] < 0.0 {
+        v.iter_mut().for_each(|x| *x = -*x);
+    }
+    v
+}
+
+/// 2D layout of the triangulation's graph structure, in `[0, 1]²`
+///
+/// Each connected component (vertices not referenced by any triangle are
+/// components of their own) is embedded separately, scaled uniformly to a
+/// square proportional to the square root of its size, and the squares are
+/// shelf-packed side by side; the packing is then scaled uniformly into
+/// the unit square. Power iteration runs for at most `iterations` steps per
+/// eigenvector, stopping early once no entry changes by more than
+/// `tolerance`. The starting vectors come from `seed`, so the output is
+/// deterministic.
+#[wasm_bindgen]
+pub fn spectral_layout(
+    triangles: &[u32],
+    point_count: u32,
+    iterations: u32,
+    tolerance: f64,
+    seed: u64,
+) -> Result<Vec<f64>> {
+    let n = point_count as usize;
+    check_triangles(triangles, n)?;
+    if tolerance.is_nan() || tolerance < 0.0 {
+        return Err(Error::InvalidArgument(format!(
+            "tolerance must be non-negative, got {tolerance}"
+        )));
+    }
+    let edges = unique_edges(triangles);
+    let csr = Csr::from_edges(n, &edges);
+    let inv_sqrt_deg: Vec<f64> = (0..n as u32)
+        .map(|v| {
+            let d = csr.neighbors(v).len();
+            if d == 0 { 0.0 } else { 1.0 / (d as f64).sqrt() }
+        })
+        .collect();
+
+    let mut sets = UnionFind::new(n);
+    for &(a, b) in &edges {
+        sets.union(a, b);
+    }
+    let mut components: Vec<Vec<u32>> = Vec::new();
+    let mut comp_of_root = vec![u32::MAX; n];
+    for v in 0..n as u32 {
+        let r = sets.find(v) as usize;
+        if comp_of_root[r] == u32::MAX {
+            comp_of_root[r] = components.len() as u32;
+            components.push(Vec::new());
+        }
+        components[comp_of_root[r] as usize].push(v);
+    }
+
+    let mut rng = Rng::new(seed);
+    let mut index = vec![0u32; n];
+    let mut unit = vec![(0.5, 0.5); n];
+    for comp in &components {
+        for (li, &g) in comp.iter().enumerate() {
+            index[g as usize] = li as u32;
+        }
+        if comp.len() < 2 {
+            continue;
+        }
+        let mut trivial: Vec<f64> = comp
+            .iter()
+            .map(|&g| (csr.neighbors(g).len() as f64).sqrt())
+            .collect();
+        normalize(&mut trivial);
+        let mut basis = vec![trivial];
+        let op = Component { csr: &csr, local: comp, index: &index, inv_sqrt_deg: &inv_sqrt_deg };
+        for _ in 0..2 {
+            let v = power_iterate(&op, &basis, &mut rng, iterations, tolerance);
+            basis.push(v);
+        }
+        // Random-walk eigenvectors D^-1/2 v, fitted uniformly into [0, 1]²
+        let walk = |k: usize| -> Vec<f64> {
+            comp.iter()
+                .enumerate()
+                .map(|(i, &g)| basis[k][i] * inv_sqrt_deg[g as usize])
+                .collect()
+        };
+        let (xs, ys) = (walk(1), walk(2));
+        let range = |v: &[f64]| {
+            v.iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &x| (lo.min(x), hi.max(x)))
+        };
+        let ((x0, x1), (y0, y1)) = (range(&xs), range(&ys));
+        let span = (x1 - x0).max(y1 - y0);
+        let s = if span > 0.0 { 1.0 / span } else { 0.0 };
+        let (ox, oy) = (0.5 - (x0 + x1) * 0.5 * s, 0.5 - (y0 + y1) * 0.5 * s);
+        // Clamped against rounding just outside the unit square
+        let fit = |o: f64, v: f64| (o + v * s).clamp(0.0, 1.0);
+        for (i, &g) in comp.iter().enumerate() {
+            unit[g as usize] = (fit(ox, xs[i]), fit(oy, ys[i]));
+        }
+    }
+
+    // Shelf-pack squares of side √size, largest first, into rows of
+    // roughly square total extent
+    let sides: Vec<f64> = components.iter().map(|c| (c.len() as f64).sqrt()).collect();
+    let mut order: Vec<usize> = (0..components.len()).collect();
+    order.sort_by(|&a, &b| components[b].len().cmp(&components[a].len()).then(a.cmp(&b)));
+    let target = sides.iter().map(|s| (s + PACK_GAP) * (s + PACK_GAP)).sum::<f64>().sqrt();
+    let mut origin = vec![(0.0, 0.0); components.len()];
+    let (mut x, mut y, mut row_h, mut width) = (0.0f64, 0.0f64, 0.0f64, 0.0f64);
+    for &c in &order {
+        if x > 0.0 && x + sides[c] > target {
+            y += row_h + PACK_GAP;
+            x = 0.0;
+            row_h = 0.0;
+        }
+        origin[c] = (x, y);
+        x += sides[c] + PACK_GAP;
+        row_h = row_h.max(sides[c]);
+        width = width.max(x - PACK_GAP);
+    }
+    let height = y + row_h;
+    let scale = 1.0 / width.max(height);
+
+    let mut out = vec![0.0; n * 2];
+    for (c, comp) in components.iter().enumerate() {
+        for &g in comp {
+            let (ux, uy) = unit[g as usize];
+            out[g as usize * 2] = (origin[c].0 + ux * sides[c]) * scale;
+            out[g as usize * 2 + 1] = (origin[c].1 + uy * sides[c]) * scale;
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    /// Triangulated `cols × rows` lattice; vertex `r * cols + c`
+    fn lattice(cols: u32, rows: u32) -> Vec<u32> {
+        let mut t = Vec::new();
+        for r in 0..rows - 1 {
+            for c in 0..cols - 1 {
+                let v = r * cols + c;
+                t.extend_from_slice(&[v, v + 1, v + cols, v + 1, v + cols + 1, v + cols]);
+            }
+        }
+        t
+    }
+
+    fn correlation(a: &[f64], b: &[f64]) -> f64 {
+        let n = a.len() as f64;
+        let (ma, mb) = (a.iter().sum::<f64>() / n, b.iter().sum::<f64>() / n);
+        let cov: f64 = a.iter().zip(b).map(|(x, y)| (x - ma) * (y - mb)).sum();
+        let va: f64 = a.iter().map(|x| (x - ma).powi(2)).sum();
+        let vb: f64 = b.iter().map(|y| (y - mb).powi(2)).sum();
+        cov / (va * vb).sqrt()
+    }
+
+    #[test]
+    fn test_lattice_layout_recovers_long_axis() {
+        let (cols, rows) = (20, 5);
+        let layout = spectral_layout(&lattice(cols, rows), cols * rows, 20_000, 1e-12, 1).unwrap();
+        assert!(layout.iter().all(|v| (0.0..=1.0).contains(v)));
+        let xs: Vec<f64> = layout.chunks(2).map(|p| p[0]).collect();
+        let columns: Vec<f64> = (0..cols * rows).map(|v| (v % cols) as f64).collect();
+        // The Fiedler vector is a monotone function of the column
+        assert!(correlation(&xs, &columns).abs() > 0.95);
+        let again = spectral_layout(&lattice(cols, rows), cols * rows, 20_000, 1e-12, 1).unwrap();
+        assert_eq!(layout, again);
+    }
+
+    #[test]
+    fn test_components_are_packed_apart() {
+        let mut triangles = lattice(6, 6);
+        // Second component: a 4x4 lattice on vertices 36..52, plus an
+        // isolated vertex 52
+        triangles.extend(lattice(4, 4).iter().map(|v| v + 36));
+        let layout = spectral_layout(&triangles, 53, 5_000, 1e-10, 3).unwrap();
+        assert!(layout.iter().all(|v| (0.0..=1.0).contains(v)));
+        let bbox = |r: std::ops::Range<usize>| {
+            r.fold([f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY], |b, v| {
+                let (x, y) = (layout[v * 2], layout[v * 2 + 1]);
+                [b[0].min(x), b[1].min(y), b[2].max(x), b[3].max(y)]
+            })
+        };
+        let (a, b, c) = (bbox(0..36), bbox(36..52), bbox(52..53));
+        let disjoint =
+            |p: [f64; 4], q: [f64; 4]| p[2] < q[0] || q[2] < p[0] || p[3] < q[1] || q[3] < p[1];
+        assert!(disjoint(a, b) && disjoint(a, c) && disjoint(b, c));
+        assert_eq!(
+            code_of(spectral_layout(&[0, 1, 9], 3, 10, 1e-6, 0)),
+            ErrorCode::IndexOutOfRange
+        );
+        assert_eq!(
+            code_of(spectral_layout(&[0, 1, 2], 3, 10, -1.0, 0)),
+            ErrorCode::InvalidArgument
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! Planar ↔ spherical mapping for the flat-to-globe morph
+//!
+//! Canvas point `(x, y)` becomes plane coordinates
+//! `(u, v) = ((x - width / 2) / scale, (y - height / 2) / scale)`, which are
+//! then inverse-stereographically projected from the north pole `(0, 0, 1)`
+//! onto the unit sphere, so the canvas center lands on the south pole and
+//! far-away points approach the north pole. Both directions avoid the
+//! cancellation in `1 - z` near the pole.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+
+fn check_frame(width: f64, height: f64, scale: f64) -> Result<()> {
+    if ![width, height, scale].iter().all(|v| v.is_finite()) || scale <= 0.0 {
+        return Err(Error::InvalidArgument(format!(
+            "width/height must be finite and scale positive, got {width}, {height}, {scale}"
+        )));
+    }
+    Ok(())
+}
+
+fn plane_coords(points_xy: &[f64], width: f64, height: f64, scale: f64) -> Result<Vec<(f64, f64)>> {
+    check_points(points_xy)?;
+    check_frame(width, height, scale)?;
+    if points_xy.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let (cx, cy) = (width / 2.0, height / 2.0);
+    Ok(points_xy
+        .chunks_exact(2)
+        .map(|c| ((c[0] - cx) / scale, (c[1] - cy) / scale))
+        .collect())
+}
+
+#[inline]
+fn lift(u: f64, v: f64) -> [f64; 3] {
+    let r2 = u * u + v * v;
+    let d = 1.0 + r2;
+    [2.0 * u / d, 2.0 * v / d, (r2 - 1.0) / d]
+}
+
+/// Planar points `[x, y, ...]` to unit-sphere points `[x, y, z, ...]`
+#[wasm_bindgen]
+pub fn project_stereographic(points_xy: &[f64], width: f64, height: f64, scale: f64) -> Result<Vec<f64>> {
+    let plane = plane_coords(points_xy, width, height, scale)?;
+    Ok(plane.into_iter().flat_map(|(u, v)| lift(u, v)).collect())
+}
+
+/// Sphere points `[x, y, z, ...]` back to canvas `[x, y, ...]`
+///
+/// Inputs are normalized first, so they need not be exactly unit length.
+/// The north pole itself has no planar image and is rejected.
+#[wasm_bindgen]
+pub fn unproject_stereographic(points_xyz: &[f64], width: f64, height: f64, scale: f64) -> Result<Vec<f64>> {
+    if !points_xyz.len().is_multiple_of(3) {
+        return Err(Error::InvalidLength(format!(
+            "xyz array length {} is not a multiple of 3",
+            points_xyz.len()
+        )));
+    }
+    check_frame(width, height, scale)?;
+    let (cx, cy) = (width / 2.0, height / 2.0);
+    let mut out = Vec::with_capacity(points_xyz.len() / 3 * 2);
+    for (i, p) in points_xyz.chunks_exact(3).enumerate() {
+        if p.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput(format!("point {i} is not finite")));
+        }
+        let len = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+        if !len.is_finite() || len == 0.0 {
+            return Err(Error::DegenerateGeometry(format!("point {i} has no direction")));
+        }
+        let (x, y, z) = (p[0] / len, p[1] / len, p[2] / len);
+        // 1 - z, computed as (x² + y²) / (1 + z) in the northern half
+        let h2 = x * x + y * y;
+        let one_minus_z = if z > 0.0 { h2 / (1.0 + z) } else { 1.0 - z };
+        if one_minus_z == 0.0 {
+            return Err(Error::DegenerateGeometry(format!("point {i} is the projection pole")));
+        }
+        out.push(cx + scale * x / one_minus_z);
+        out.push(cy + scale * y / one_minus_z);
+    }
+    Ok(out)
+}
+
+/// Blend from the flat embedding (`t = 0`) to the sphere (`t = 1`)
+///
+/// The flat embedding is `(u, v, 0)` in plane coordinates. Each point
+/// travels along the great circle from the direction of its flat position
+/// to the direction of its sphere position, with its distance from the
+/// origin interpolated linearly, so intermediate frames bulge smoothly
+/// instead of cutting through the sphere. The canvas center, which has no
+/// flat direction, moves straight to the south pole. `t` is clamped to
+/// `[0, 1]`. Topology is untouched: reuse the planar triangles.
+#[wasm_bindgen]
+pub fn morph_planar_spherical(points_xy: &[f64], width: f64, height: f64, scale: f64, t: f64) -> Result<Vec<f64>> {
+    let plane = plane_coords(points_xy, width, height, scale)?;
+    if t.is_nan() {
+        return Err(Error::NonFiniteInput("t must not be NaN".into()));
+    }
+    let t = t.clamp(0.0, 1.0);
+    let mut out = Vec::with_capacity(plane.len() * 3);
+    for (u, v) in plane {
+        let s = lift(u, v);
+        let r_flat = u.hypot(v);
+        let radius = r_flat + (1.0 - r_flat) * t;
+        if r_flat == 0.0 {
+            out.extend_from_slice(&s.map(|c| c * radius));
+            continue;
+        }
+        let f = [u / r_flat, v / r_flat, 0.0];
+        // Angle between the directions; the xy part of s is a positive
+        // multiple of (u, v), so it is strictly below π
+        let cos = (f[0] * s[0] + f[1] * s[1]).clamp(-1.0, 1.0);
+        let omega = cos.acos();
+        let dir = if omega < 1e-12 {
+            f
+        } else {
+            let (a, b) = (((1.0 - t) * omega).sin(), (t * omega).sin());
+            let sin = omega.sin();
+            [0, 1, 2].map(|k| (a * f[k] + b * s[k]) / sin)
+        };
+        out.extend_from_slice(&dir.map(|c| c * radius));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_project_round_trip_including_near_pole() {
+        let (w, h, scale) = (800.0, 600.0, 300.0);
+        let mut points = vec![400.0, 300.0, 0.0, 0.0, 800.0, 600.0, 123.4, 567.8];
+        // Far from the center the image crowds the north pole
+        points.extend_from_slice(&[400.0 + 3e5, 300.0, 400.0, 300.0 - 2e5, 400.0 + 1e6, 300.0 + 1e6]);
+        let xyz = project_stereographic(&points, w, h, scale).unwrap();
+        for p in xyz.chunks(3) {
+            let len = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+            assert!((len - 1.0).abs() < 1e-12);
+        }
+        assert_eq!(&xyz[..3], &[0.0, 0.0, -1.0]);
+        let back = unproject_stereographic(&xyz, w, h, scale).unwrap();
+        for (a, b) in back.iter().zip(&points) {
+            assert!((a - b).abs() <= 1e-9 * b.abs().max(1.0), "{a} vs {b}");
+        }
+    }
+
+    #[test]
+    fn test_unproject_rejects_pole_and_bad_input() {
+        let code = |xyz: &[f64]| code_of(unproject_stereographic(xyz, 10.0, 10.0, 1.0));
+        assert_eq!(code(&[0.0, 0.0, 1.0]), ErrorCode::DegenerateGeometry);
+        assert_eq!(code(&[0.0, 0.0, 0.0]), ErrorCode::DegenerateGeometry);
+        assert_eq!(code(&[0.0, f64::NAN, 1.0]), ErrorCode::NonFiniteInput);
+        assert_eq!(code(&[0.0, 0.0]), ErrorCode::InvalidLength);
+        let code = |xy: &[f64], scale| code_of(project_stereographic(xy, 10.0, 10.0, scale));
+        assert_eq!(code(&[1.0, 2.0], 0.0), ErrorCode::InvalidArgument);
+        assert_eq!(code(&[1.0, f64::INFINITY], 1.0), ErrorCode::NonFiniteInput);
+        // Not unit length is fine
+        let p = unproject_stereographic(&[0.0, 0.0, -5.0], 10.0, 10.0, 1.0).unwrap();
+        assert_eq!(p, vec![5.0, 5.0]);
+    }
+
+    #[test]
+    fn test_morph_endpoints_and_radius() {
+        let (w, h, scale) = (400.0, 400.0, 100.0);
+        let points = [200.0, 200.0, 300.0, 200.0, 50.0, 390.0, 399.0, 1.0];
+        let flat = morph_planar_spherical(&points, w, h, scale, 0.0).unwrap();
+        let sphere = morph_planar_spherical(&points, w, h, scale, 1.0).unwrap();
+        let projected = project_stereographic(&points, w, h, scale).unwrap();
+        for (i, c) in points.chunks(2).enumerate() {
+            let (u, v) = ((c[0] - 200.0) / scale, (c[1] - 200.0) / scale);
+            assert!((flat[i * 3] - u).abs() < 1e-12 && (flat[i * 3 + 1] - v).abs() < 1e-12);
+            assert!(flat[i * 3 + 2].abs() < 1e-12);
+        }
+        for (a, b) in sphere.iter().zip(&projected) {
+            assert!((a - b).abs() < 1e-12);
+        }
+        // Halfway: radius is the average of the endpoint radii
+        let mid = morph_planar_spherical(&points, w, h, scale, 0.5).unwrap();
+        for (i, c) in points.chunks(2).enumerate() {
+            let r_flat = ((c[0] - 200.0) / scale).hypot((c[1] - 200.0) / scale);
+            let m = &mid[i * 3..i * 3 + 3];
+            let r = (m[0] * m[0] + m[1] * m[1] + m[2] * m[2]).sqrt();
+            assert!((r - (r_flat + 1.0) / 2.0).abs() < 1e-12);
+        }
+    }
+}
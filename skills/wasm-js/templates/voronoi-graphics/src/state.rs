@@ -0,0 +1,148 @@
+//! Saved states of several animation objects in one buffer
+//!
+//! `MoteSystem`, `SeedSimulation`, `DynamicTriangulation` and
+//! `NoiseGenerator` each serialize themselves with `save_state` and come
+//! back with `restore_state`. A pause or a scrubbing timeline needs all of
+//! them at the same frame, so `StateBundle` keeps their bytes together,
+//! in the order pushed, under one versioned header. Every entry carries
+//! its own tag and version, so restoring one into the wrong kind of
+//! object is an error rather than a silently wrong state.
+
+use wasm_bindgen::prelude::*;
+
+use crate::codec::{ByteReader, ByteWriter};
+use crate::error::{Error, Result};
+
+const BUNDLE_MAGIC: &[u8; 4] = b"VGSB";
+const BUNDLE_VERSION: u8 = 1;
+
+/// Ordered list of `save_state` outputs, serialized as one buffer
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct StateBundle {
+    states: Vec<Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl StateBundle {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> StateBundle {
+        StateBundle::default()
+    }
+
+    /// Append the bytes of one object's `save_state`; returns their index
+    pub fn push(&mut self, state: &[u8]) -> u32 {
+        self.states.push(state.to_vec());
+        self.states.len() as u32 - 1
+    }
+
+    /// Bytes pushed at `index`, to pass to that object's `restore_state`
+    pub fn get(&self, index: u32) -> Result<Vec<u8>> {
+        match self.states.get(index as usize) {
+            Some(state) => Ok(state.clone()),
+            None => Err(Error::IndexOutOfRange(format!(
+                "state {index} out of range for a bundle of {}",
+                self.states.len()
+            ))),
+        }
+    }
+
+    /// Number of states in the bundle
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// The whole bundle as one versioned buffer
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = ByteWriter::new();
+        w.bytes(BUNDLE_MAGIC);
+        w.u8(BUNDLE_VERSION);
+        w.u32(self.states.len() as u32);
+        self.states.iter().for_each(|s| w.u8s(s));
+        w.buf
+    }
+
+    /// Read a bundle written by `to_bytes`; errors on truncated, corrupt
+    /// or version-mismatched data
+    ///
+    /// The entries themselves are checked when they are restored.
+    pub fn from_bytes(bytes: &[u8]) -> Result<StateBundle> {
+        let mut r = ByteReader::new(bytes);
+        let version = r.header(BUNDLE_MAGIC)?;
+        if version != BUNDLE_VERSION {
+            return Err(Error::VersionMismatch(format!(
+                "state bundle version {version}, expected {BUNDLE_VERSION}"
+            )));
+        }
+        let count = r.u32()?;
+        // Each entry takes at least its length prefix, so a corrupt count
+        // runs out of input long before it can exhaust memory
+        let states = (0..count).map(|_| r.u8s()).collect::<Result<Vec<_>>>()?;
+        r.finish()?;
+        Ok(StateBundle { states })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dynamic::DynamicTriangulation;
+    use crate::error::{code_of, ErrorCode};
+    use crate::generate_golden_seeds;
+
+    #[test]
+    fn test_bundle_round_trips_and_restores_exactly() {
+        let base = generate_golden_seeds(400.0, 300.0, 120);
+        let mut tri = DynamicTriangulation::new(&base, 400.0, 300.0).unwrap();
+        let frame = |t: f64| -> Vec<f64> {
+            base.iter().enumerate().map(|(i, &v)| v + 6.0 * (t + i as f64 * 0.37).sin()).collect()
+        };
+        tri.update_points(&frame(0.5)).unwrap();
+
+        let mut bundle = StateBundle::new();
+        assert_eq!(bundle.push(b"opaque"), 0);
+        assert_eq!(bundle.push(&tri.save_state()), 1);
+        let bundle = StateBundle::from_bytes(&bundle.to_bytes()).unwrap();
+        assert_eq!(bundle.len(), 2);
+        assert_eq!(bundle.get(0).unwrap(), b"opaque");
+        assert_eq!(code_of(bundle.get(2)), ErrorCode::IndexOutOfRange);
+
+        let mut replay = DynamicTriangulation::new(&[], 1.0, 1.0).unwrap();
+        replay.restore_state(&bundle.get(1).unwrap()).unwrap();
+        // The wrong entry is refused and leaves the triangulation alone
+        assert_eq!(code_of(replay.restore_state(b"opaque")), ErrorCode::InvalidData);
+        for step in 1..20 {
+            let points = frame(0.5 + step as f64 * 0.1);
+            tri.update_points(&points).unwrap();
+            replay.update_points(&points).unwrap();
+            assert_eq!(replay.triangles_indexed(), tri.triangles_indexed());
+            assert_eq!(replay.added_triangles(), tri.added_triangles());
+            assert_eq!(replay.edge_events(), tri.edge_events());
+            assert_eq!(replay.changed_triangles(), tri.changed_triangles());
+            assert_eq!(replay.generation(), tri.generation());
+        }
+        assert_eq!(replay.save_state(), tri.save_state());
+    }
+
+    #[test]
+    fn test_bundle_rejects_corrupt_input() {
+        let mut bundle = StateBundle::new();
+        bundle.push(&[1, 2, 3]);
+        let bytes = bundle.to_bytes();
+        assert_eq!(code_of(StateBundle::from_bytes(&[])), ErrorCode::InvalidData);
+        assert_eq!(
+            code_of(StateBundle::from_bytes(&bytes[..bytes.len() - 1])),
+            ErrorCode::InvalidData
+        );
+        let mut huge = bytes.clone();
+        huge[5..9].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(code_of(StateBundle::from_bytes(&huge)), ErrorCode::InvalidData);
+        let mut version = bytes.clone();
+        version[4] = BUNDLE_VERSION + 1;
+        assert_eq!(code_of(StateBundle::from_bytes(&version)), ErrorCode::VersionMismatch);
+    }
+}
@@ -0,0 +1,439 @@
+//! Quality report, Delaunay check, winding fix and area, centroid and
+//! bounds for a finished triangulation
+//!
+//! All are single passes over the triangles (plus a convex hull and a
+//! side map), cheap enough to run on every frame of a debug build to
+//! catch the slivers and flat triangles some uploaded point sets produce.
+
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles};
+use crate::predicates::{degenerate, incircle, orient2d, signed_area2};
+use crate::{compute_hull, Point};
+
+/// Summary of a triangle list from `triangulation_stats`
+///
+/// Every triangle counts, degenerate ones included, so a single flat
+/// triangle shows up as a zero minimum angle and an infinite aspect ratio.
+/// With no triangles the per-triangle figures are NaN.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct TriangulationStats {
+    triangle_count: usize,
+    min_area: f64,
+    max_area: f64,
+    mean_area: f64,
+    min_angle_deg: f64,
+    max_aspect_ratio: f64,
+    degenerate_count: usize,
+    covered_area: f64,
+    hull_area: f64,
+}
+
+#[wasm_bindgen]
+impl TriangulationStats {
+    pub fn triangle_count(&self) -> usize {
+        self.triangle_count
+    }
+
+    pub fn min_area(&self) -> f64 {
+        self.min_area
+    }
+
+    pub fn max_area(&self) -> f64 {
+        self.max_area
+    }
+
+    pub fn mean_area(&self) -> f64 {
+        self.mean_area
+    }
+
+    /// Smallest interior angle of any triangle, in degrees
+    pub fn min_angle_deg(&self) -> f64 {
+        self.min_angle_deg
+    }
+
+    /// Largest `longest side / (2√3 · inradius)`, which is 1 for an
+    /// equilateral triangle and grows without bound for slivers
+    pub fn max_aspect_ratio(&self) -> f64 {
+        self.max_aspect_ratio
+    }
+
+    /// Triangles `degenerate_triangles` flags at the same `epsilon`
+    pub fn degenerate_count(&self) -> usize {
+        self.degenerate_count
+    }
+
+    /// Sum of the triangle areas
+    pub fn covered_area(&self) -> f64 {
+        self.covered_area
+    }
+
+    /// Area of the convex hull of all the points
+    pub fn hull_area(&self) -> f64 {
+        self.hull_area
+    }
+
+    /// `covered_area / hull_area`: 1 for a full triangulation, less when
+    /// triangles are missing and more when they overlap; NaN for a flat
+    /// hull
+    pub fn coverage(&self) -> f64 {
+        if self.hull_area > 0.0 {
+            self.covered_area / self.hull_area
+        } else {
+            f64::NAN
+        }
+    }
+}
+
+fn parse(points_flat: &[f64], triangles: &[u32]) -> Result<Vec<Point>> {
+    let n = check_points(points_flat)?;
+    check_triangles(triangles, n)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    Ok(points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect())
+}
+
+/// Area, angle and shape statistics of `triangles` over `points_flat`
+///
+/// `epsilon` is the relative tolerance of `is_degenerate_triangle`;
+/// around `1e-6` flags triangles too thin to see.
+#[wasm_bindgen]
+pub fn triangulation_stats(
+    points_flat: &[f64],
+    triangles: &[u32],
+    epsilon: f64,
+) -> Result<TriangulationStats> {
+    let points = parse(points_flat, triangles)?;
+    if !(epsilon.is_finite() && epsilon >= 0.0) {
+        return Err(Error::InvalidArgument(format!(
+            "epsilon must be finite and non-negative, got {epsilon}"
+        )));
+    }
+    let count = triangles.len() / 3;
+    // Running extremes start from their identities, or NaN when no
+    // triangle will update them
+    let start = |v: f64| if count == 0 { f64::NAN } else { v };
+    let mut stats = TriangulationStats {
+        triangle_count: count,
+        min_area: start(f64::INFINITY),
+        max_area: start(0.0),
+        mean_area: start(0.0),
+        min_angle_deg: start(180.0),
+        max_aspect_ratio: start(0.0),
+        degenerate_count: 0,
+        covered_area: 0.0,
+        hull_area: 0.0,
+    };
+    for t in triangles.chunks_exact(3) {
+        let [a, b, c] = [t[0], t[1], t[2]].map(|i| points[i as usize]);
+        let area = orient2d(a, b, c).abs() / 2.0;
+        let side = |p: Point, q: Point| (p.x - q.x).hypot(p.y - q.y);
+        let (ab, bc, ca) = (side(a, b), side(b, c), side(c, a));
+        // Law of cosines, clamped against rounding just outside [-1, 1]
+        // (a zero side gives NaN, and a zero angle)
+        let angle = |opposite: f64, p: f64, q: f64| {
+            let cos = (p * p + q * q - opposite * opposite) / (2.0 * p * q);
+            if cos.is_nan() {
+                0.0
+            } else {
+                cos.clamp(-1.0, 1.0).acos().to_degrees()
+            }
+        };
+        let smallest = angle(ab, bc, ca).min(angle(bc, ca, ab)).min(angle(ca, ab, bc));
+        let aspect = if area > 0.0 {
+            ab.max(bc).max(ca) * (ab + bc + ca) / (4.0 * 3f64.sqrt() * area)
+        } else {
+            f64::INFINITY
+        };
+        stats.min_area = stats.min_area.min(area);
+        stats.max_area = stats.max_area.max(area);
+        stats.covered_area += area;
+        stats.min_angle_deg = stats.min_angle_deg.min(smallest);
+        stats.max_aspect_ratio = stats.max_aspect_ratio.max(aspect);
+        stats.degenerate_count += degenerate(a, b, c, epsilon) as usize;
+    }
+    if count > 0 {
+        stats.mean_area = stats.covered_area / count as f64;
+    }
+    let hull: Vec<Point> =
+        compute_hull(points_flat)?.iter().map(|&i| points[i as usize]).collect();
+    stats.hull_area = signed_area2(&hull).abs() / 2.0;
+    Ok(stats)
+}
+
+/// Whether `triangles` satisfies the empty-circumcircle property
+///
+/// Checks every side shared by two triangles: the vertex across it must
+/// not lie strictly inside the other triangle's circumcircle, using the
+/// exact `incircle` predicate, so cocircular points pass. For a valid
+/// triangulation this local test is equivalent to the global one. Flat
+/// triangles, and sides shared by more than two triangles, fail.
+#[wasm_bindgen]
+pub fn validate_delaunay(points_flat: &[f64], triangles: &[u32]) -> Result<bool> {
+    let points = parse(points_flat, triangles)?;
+    let corners = |t: usize| {
+        let t = &triangles[t * 3..t * 3 + 3];
+        [t[0], t[1], t[2]].map(|i| points[i as usize])
+    };
+    // Side -> the vertices across it
+    let mut across: HashMap<(u32, u32), Vec<(usize, u32)>> = HashMap::new();
+    for (i, t) in triangles.chunks_exact(3).enumerate() {
+        let [a, b, c] = corners(i);
+        if orient2d(a, b, c) == 0.0 {
+            return Ok(false);
+        }
+        for k in 0..3 {
+            let (u, v) = (t[k], t[(k + 1) % 3]);
+            across.entry((u.min(v), u.max(v))).or_default().push((i, t[(k + 2) % 3]));
+        }
+    }
+    for users in across.values() {
+        match users[..] {
+            [_] => {}
+            [(t, _), (_, d)] => {
+                let [a, b, c] = corners(t);
+                // `incircle` wants counter-clockwise corners
+                let inside = if orient2d(a, b, c) > 0.0 {
+                    incircle(a, b, c, points[d as usize])
+                } else {
+                    incircle(a, c, b, points[d as usize])
+                };
+                if inside > 0.0 {
+                    return Ok(false);
+                }
+            }
+            _ => return Ok(false),
+        }
+    }
+    Ok(true)
+}
+
+/// Swap the last two corners of every clockwise triangle in place, so
+/// the whole list winds counter-clockwise; returns how many were flipped
+///
+/// For index buffers from elsewhere: `compute_delaunay` and the other
+/// triangulations here already wind counter-clockwise. Flat triangles
+/// have no winding and are left as they are.
+#[wasm_bindgen]
+pub fn ensure_ccw(points_flat: &[f64], triangles: &mut [u32]) -> Result<usize> {
+    let points = parse(points_flat, triangles)?;
+    let mut flipped = 0;
+    for t in triangles.chunks_exact_mut(3) {
+        let [a, b, c] = [t[0], t[1], t[2]].map(|i| points[i as usize]);
+        if orient2d(a, b, c) < 0.0 {
+            t.swap(1, 2);
+            flipped += 1;
+        }
+    }
+    Ok(flipped)
+}
+
+/// Signed area of every triangle, positive for counter-clockwise ones
+#[wasm_bindgen]
+pub fn triangle_areas(points_flat: &[f64], triangles: &[u32]) -> Result<Vec<f32>> {
+    let points = parse(points_flat, triangles)?;
+    let area = |t: &[u32]| signed_area2(&[t[0], t[1], t[2]].map(|i| points[i as usize])) / 2.0;
+    Ok(triangles.chunks_exact(3).map(|t| area(t) as f32).collect())
+}
+
+/// `[cx, cy, area]`: the centroid of the region `triangles` cover and its
+/// total area
+///
+/// Each triangle weighs by its unsigned area, so winding does not
+/// matter. With no area to weigh the centroid is NaN.
+#[wasm_bindgen]
+pub fn mesh_centroid(points_flat: &[f64], triangles: &[u32]) -> Result<Vec<f64>> {
+    let points = parse(points_flat, triangles)?;
+    let (mut area, mut cx, mut cy) = (0.0, 0.0, 0.0);
+    for t in triangles.chunks_exact(3) {
+        let corners = [t[0], t[1], t[2]].map(|i| points[i as usize]);
+        let a = signed_area2(&corners).abs() / 2.0;
+        area += a;
+        cx += a * corners.iter().map(|p| p.x).sum::<f64>() / 3.0;
+        cy += a * corners.iter().map(|p| p.y).sum::<f64>() / 3.0;
+    }
+    let (cx, cy) = if area > 0.0 { (cx / area, cy / area) } else { (f64::NAN, f64::NAN) };
+    Ok(vec![cx, cy, area])
+}
+
+/// `[min_x, min_y, max_x, max_y]` around the triangles listed in
+/// `subset`, or around all of them when it is empty
+///
+/// `subset` holds triangle indices, 0 for the first triple, as
+/// `DynamicTriangulation::changed_triangles` reports them, so a local
+/// update repaints only this rectangle. Empty when there is nothing to
+/// bound.
+#[wasm_bindgen]
+pub fn triangles_bbox(points_flat: &[f64], triangles: &[u32], subset: &[u32]) -> Result<Vec<f64>> {
+    let points = parse(points_flat, triangles)?;
+    let count = triangles.len() / 3;
+    if let Some(&t) = subset.iter().find(|&&t| t as usize >= count) {
+        return Err(Error::IndexOutOfRange(format!(
+            "triangle {t} out of range for {count} triangles"
+        )));
+    }
+    let mut bounds = [f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY];
+    let mut grow = |t: usize| {
+        for &v in &triangles[t * 3..t * 3 + 3] {
+            let p = points[v as usize];
+            let [min_x, min_y, max_x, max_y] = bounds;
+            bounds = [min_x.min(p.x), min_y.min(p.y), max_x.max(p.x), max_y.max(p.y)];
+        }
+    };
+    if subset.is_empty() {
+        (0..count).for_each(&mut grow);
+    } else {
+        subset.iter().for_each(|&t| grow(t as usize));
+    }
+    Ok(if count == 0 { Vec::new() } else { bounds.to_vec() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_delaunay, generate_golden_seeds};
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+
+    #[test]
+    fn test_stats_of_known_triangles() {
+        // A 4 × 3 rectangle split along a diagonal, plus a flat triangle
+        let points = [0.0, 0.0, 4.0, 0.0, 4.0, 3.0, 0.0, 3.0, 2.0, 0.0];
+        let stats = triangulation_stats(&points, &[0, 1, 2, 0, 2, 3], 1e-6).unwrap();
+        assert_eq!(stats.triangle_count(), 2);
+        assert_eq!((stats.min_area(), stats.max_area(), stats.mean_area()), (6.0, 6.0, 6.0));
+        assert!((stats.min_angle_deg() - 0.75f64.atan().to_degrees()).abs() < 1e-9);
+        let aspect = 5.0 * 12.0 / (4.0 * 3f64.sqrt() * 6.0);
+        assert!((stats.max_aspect_ratio() - aspect).abs() < 1e-12);
+        assert_eq!((stats.covered_area(), stats.hull_area(), stats.coverage()), (12.0, 12.0, 1.0));
+        assert_eq!(stats.degenerate_count(), 0);
+
+        let flat = triangulation_stats(&points, &[0, 1, 2, 0, 4, 1], 1e-6).unwrap();
+        assert_eq!(flat.degenerate_count(), 1);
+        assert_eq!((flat.min_area(), flat.min_angle_deg()), (0.0, 0.0));
+        assert_eq!(flat.max_aspect_ratio(), f64::INFINITY);
+        assert_eq!(flat.coverage(), 0.5);
+
+        let equilateral = [0.0, 0.0, 2.0, 0.0, 1.0, 3f64.sqrt()];
+        let stats = triangulation_stats(&equilateral, &[0, 1, 2], 0.0).unwrap();
+        assert!((stats.max_aspect_ratio() - 1.0).abs() < 1e-12);
+        assert!((stats.min_angle_deg() - 60.0).abs() < 1e-9);
+
+        let none = triangulation_stats(&points, &[], 0.0).unwrap();
+        assert!(none.min_area().is_nan() && none.min_angle_deg().is_nan());
+        assert_eq!(none.covered_area(), 0.0);
+        let stats = |t: &[u32], epsilon| code_of(triangulation_stats(&points, t, epsilon));
+        assert_eq!(stats(&[0, 1, 5], 0.0), ErrorCode::IndexOutOfRange);
+        assert_eq!(stats(&[], -1.0), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_validate_delaunay() {
+        let mut rng = Rng::new(3);
+        let points: Vec<f64> =
+            (0..500).flat_map(|_| [rng.range(0.0, 800.0), rng.range(0.0, 600.0)]).collect();
+        let triangles = compute_delaunay(&points, 800.0, 600.0);
+        assert!(validate_delaunay(&points, &triangles).unwrap());
+        let seeds = generate_golden_seeds(800.0, 600.0, 300);
+        let triangles = compute_delaunay(&seeds, 800.0, 600.0);
+        assert!(validate_delaunay(&seeds, &triangles).unwrap());
+        let stats = triangulation_stats(&seeds, &triangles, 1e-9).unwrap();
+        assert!((stats.coverage() - 1.0).abs() < 1e-9);
+        assert_eq!(stats.degenerate_count(), 0);
+
+        // A quad split along the wrong diagonal fails; cocircular passes
+        let quad = [0.0, 0.0, 10.0, 0.0, 11.0, 8.0, 0.0, 10.0];
+        assert!(validate_delaunay(&quad, &[0, 1, 2, 0, 2, 3]).unwrap());
+        assert!(!validate_delaunay(&quad, &[0, 1, 3, 1, 2, 3]).unwrap());
+        let square = [0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0];
+        assert!(validate_delaunay(&square, &[0, 1, 2, 0, 2, 3]).unwrap());
+        assert!(validate_delaunay(&square, &[2, 1, 0, 3, 2, 0]).unwrap(), "winding is free");
+        // Flat triangles and a side used three times
+        assert!(!validate_delaunay(&[0.0, 0.0, 1.0, 0.0, 2.0, 0.0], &[0, 1, 2]).unwrap());
+        let fan = [0.0, 0.0, 2.0, 0.0, 1.0, 1.0, 1.0, -1.0, 1.0, 3.0];
+        assert!(!validate_delaunay(&fan, &[0, 1, 2, 1, 0, 3, 0, 1, 4]).unwrap());
+        assert_eq!(code_of(validate_delaunay(&[f64::NAN, 0.0], &[])), ErrorCode::NonFiniteInput);
+    }
+
+    #[test]
+    fn test_delaunay_winds_counter_clockwise() {
+        let mut rng = Rng::new(11);
+        for round in 0..8 {
+            let n = 50 + round * 150;
+            // Uniform, clustered on a grid with duplicates, and on a circle
+            let mut points: Vec<f64> = match round % 3 {
+                0 => (0..n).flat_map(|_| [rng.range(0.0, 800.0), rng.range(0.0, 600.0)]).collect(),
+                1 => (0..n)
+                    .flat_map(|_| [rng.below(20) as f64 * 40.0, rng.range(0.0, 600.0)])
+                    .collect(),
+                _ => (0..n)
+                    .flat_map(|k| {
+                        let phi = k as f64 * std::f64::consts::TAU / n as f64;
+                        [400.0 + 250.0 * phi.cos(), 300.0 + 250.0 * phi.sin()]
+                    })
+                    .collect(),
+            };
+            points.extend([400.0, 300.0]);
+            let mut triangles = compute_delaunay(&points, 800.0, 600.0);
+            assert!(!triangles.is_empty());
+            let corners = |t: &[u32]| [t[0], t[1], t[2]].map(|i| {
+                Point::new(points[2 * i as usize], points[2 * i as usize + 1])
+            });
+            for t in triangles.chunks(3) {
+                let [a, b, c] = corners(t);
+                assert!(orient2d(a, b, c) > 0.0, "round {round}: {t:?} is not counter-clockwise");
+            }
+            assert_eq!(ensure_ccw(&points, &mut triangles).unwrap(), 0);
+
+            // Flipping every other triangle is undone exactly
+            let original = triangles.clone();
+            for t in triangles.chunks_mut(3).step_by(2) {
+                t.swap(0, 1);
+            }
+            let flips = triangles.len().div_ceil(6);
+            assert_eq!(ensure_ccw(&points, &mut triangles).unwrap(), flips);
+            for (t, o) in triangles.chunks(3).zip(original.chunks(3)) {
+                let [a, b, c] = corners(t);
+                assert!(orient2d(a, b, c) > 0.0);
+                assert!((0..3).any(|r| [t[r], t[(r + 1) % 3], t[(r + 2) % 3]] == *o));
+            }
+        }
+
+        let line = [0.0, 0.0, 1.0, 0.0, 2.0, 0.0];
+        let mut flat = [0u32, 2, 1];
+        assert_eq!(ensure_ccw(&line, &mut flat).unwrap(), 0);
+        assert_eq!(flat, [0, 2, 1]);
+        assert_eq!(code_of(ensure_ccw(&line, &mut [0, 1, 3])), ErrorCode::IndexOutOfRange);
+        assert_eq!(code_of(ensure_ccw(&line, &mut [0, 1])), ErrorCode::InvalidLength);
+    }
+
+    #[test]
+    fn test_areas_centroid_and_bbox() {
+        // The 4 × 3 rectangle again, its second half wound clockwise, and a
+        // unit triangle off to the side
+        let points = [0.0, 0.0, 4.0, 0.0, 4.0, 3.0, 0.0, 3.0, 10.0, 10.0, 11.0, 10.0, 10.0, 11.0];
+        let triangles = [0, 1, 2, 0, 3, 2, 4, 5, 6];
+        assert_eq!(triangle_areas(&points, &triangles).unwrap(), vec![6.0, -6.0, 0.5]);
+        let [cx, cy, area] = mesh_centroid(&points, &triangles).unwrap()[..] else { panic!() };
+        assert_eq!(area, 12.5);
+        assert!((cx - (12.0 * 2.0 + 0.5 * 31.0 / 3.0) / 12.5).abs() < 1e-12);
+        assert!((cy - (12.0 * 1.5 + 0.5 * 31.0 / 3.0) / 12.5).abs() < 1e-12);
+        let rect = mesh_centroid(&points, &triangles[..6]).unwrap();
+        assert_eq!(rect, vec![2.0, 1.5, 12.0]);
+        assert!(mesh_centroid(&points, &[]).unwrap()[0].is_nan());
+
+        let bbox = |subset: &[u32]| triangles_bbox(&points, &triangles, subset);
+        assert_eq!(bbox(&[]).unwrap(), vec![0.0, 0.0, 11.0, 11.0]);
+        assert_eq!(bbox(&[1]).unwrap(), vec![0.0, 0.0, 4.0, 3.0]);
+        assert_eq!(bbox(&[2, 0]).unwrap(), vec![0.0, 0.0, 11.0, 11.0]);
+        assert!(triangles_bbox(&points, &[], &[]).unwrap().is_empty());
+        assert_eq!(code_of(bbox(&[3])), ErrorCode::IndexOutOfRange);
+        assert_eq!(code_of(triangle_areas(&points, &[0, 1])), ErrorCode::InvalidLength);
+        assert_eq!(code_of(mesh_centroid(&points, &[0, 1, 7])), ErrorCode::IndexOutOfRange);
+    }
+}
@@ -0,0 +1,269 @@
+//! Stippling: seeds distributed by a density map
+//!
+//! The map is a `grid_w × grid_h` row-major grid stretched over the canvas,
+//! each value sitting at the centre of its grid cell and interpolated
+//! bilinearly in between (held constant past the outer centres). For an
+//! image where dark should be dense, pass `1 - luminance`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::rng::Rng;
+use crate::sampling::AliasTable;
+use crate::spatial::PointGrid;
+use crate::Point;
+
+/// Density samples `weighted_lloyd_relax` refines the grid up to
+const MAX_SAMPLES: usize = 1 << 22;
+
+/// Samples per seed `weighted_lloyd_relax` aims for when the density grid
+/// alone is too coarse
+const SAMPLES_PER_SEED: usize = 64;
+
+/// A validated density grid over a canvas
+struct DensityMap<'a> {
+    values: &'a [f32],
+    cols: usize,
+    rows: usize,
+    width: f64,
+    height: f64,
+}
+
+impl<'a> DensityMap<'a> {
+    fn new(
+        values: &'a [f32],
+        grid_w: u32,
+        grid_h: u32,
+        width: f64,
+        height: f64,
+    ) -> Result<DensityMap<'a>> {
+        if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+            return Err(Error::InvalidArgument("width and height must be positive".into()));
+        }
+        let (cols, rows) = (grid_w as usize, grid_h as usize);
+        if cols == 0 || rows == 0 {
+            return Err(Error::InvalidArgument("density grid must not be empty".into()));
+        }
+        if cols.checked_mul(rows) != Some(values.len()) {
+            return Err(Error::InvalidLength(format!(
+                "a {grid_w}x{grid_h} density grid needs {} values, got {}",
+                cols as u64 * rows as u64,
+                values.len()
+            )));
+        }
+        if values.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("density values must be finite".into()));
+        }
+        if values.iter().any(|&v| v < 0.0) {
+            return Err(Error::InvalidArgument("density values must be non-negative".into()));
+        }
+        if values.iter().all(|&v| v == 0.0) {
+            return Err(Error::InvalidArgument("density map is zero everywhere".into()));
+        }
+        Ok(DensityMap { values, cols, rows, width, height })
+    }
+
+    fn value(&self, col: usize, row: usize) -> f64 {
+        self.values[row * self.cols + col] as f64
+    }
+
+    /// Bilinear density at canvas point `(x, y)`
+    fn at(&self, x: f64, y: f64) -> f64 {
+        // Grid coordinates with centres at integers
+        let fit = |v: f64, n: usize| (v * n as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+        let (gx, gy) = (fit(x / self.width, self.cols), fit(y / self.height, self.rows));
+        let (c0, r0) = (gx as usize, gy as usize);
+        let (c1, r1) = ((c0 + 1).min(self.cols - 1), (r0 + 1).min(self.rows - 1));
+        let (fx, fy) = (gx - c0 as f64, gy - r0 as f64);
+        let top = self.value(c0, r0) * (1.0 - fx) + self.value(c1, r0) * fx;
+        let bottom = self.value(c0, r1) * (1.0 - fx) + self.value(c1, r1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    /// Largest density anywhere in grid cell `(col, row)`: the cell only
+    /// blends its own value with its eight neighbours'
+    fn cell_bound(&self, col: usize, row: usize) -> f64 {
+        let mut bound = 0.0f64;
+        for r in row.saturating_sub(1)..=(row + 1).min(self.rows - 1) {
+            for c in col.saturating_sub(1)..=(col + 1).min(self.cols - 1) {
+                bound = bound.max(self.value(c, r));
+            }
+        }
+        bound
+    }
+}
+
+/// `count` seeds `[x0, y0, x1, y1, ...]` drawn independently with
+/// probability proportional to the bilinear density
+///
+/// A grid cell is picked in proportion to the largest density it can
+/// reach and a point in it accepted with probability density over that
+/// bound, which is exact sampling in a bounded number of expected tries.
+/// The result is fully determined by `seed`. Errors when the canvas or
+/// grid is empty, the grid length does not match, or the density is
+/// negative, non-finite or zero everywhere.
+#[wasm_bindgen]
+pub fn generate_weighted_seeds(
+    density: &[f32],
+    grid_w: u32,
+    grid_h: u32,
+    width: f64,
+    height: f64,
+    count: usize,
+    seed: u32,
+) -> Result<Vec<f64>> {
+    let map = DensityMap::new(density, grid_w, grid_h, width, height)?;
+    let bounds: Vec<f64> = (0..map.rows)
+        .flat_map(|r| (0..map.cols).map(move |c| (c, r)))
+        .map(|(c, r)| map.cell_bound(c, r))
+        .collect();
+    let table = AliasTable::new(&bounds);
+    let (cw, ch) = (width / map.cols as f64, height / map.rows as f64);
+    let mut rng = Rng::new(seed as u64);
+    let mut out = Vec::with_capacity(count * 2);
+    while out.len() < count * 2 {
+        let cell = table.draw(&mut rng) as usize;
+        let (col, row) = (cell % map.cols, cell / map.cols);
+        let x = ((col as f64 + rng.next_f64()) * cw).min(width);
+        let y = ((row as f64 + rng.next_f64()) * ch).min(height);
+        if rng.next_f64() * bounds[cell] < map.at(x, y) {
+            out.extend([x, y]);
+        }
+    }
+    Ok(out)
+}
+
+/// Lloyd relaxation towards density-weighted centroids, `iterations`
+/// times, so seeds from `generate_weighted_seeds` settle into even
+/// stippling
+///
+/// Each pass assigns a lattice of density samples over the canvas to
+/// their nearest seed (ties to the lower index) and moves every seed to
+/// the density-weighted mean of its samples. The lattice is the density
+/// grid, refined until there are about 64 samples per seed. A seed whose
+/// cell holds no density stays put for that pass. Deterministic; errors
+/// as `generate_weighted_seeds` does, and on bad points.
+#[wasm_bindgen]
+pub fn weighted_lloyd_relax(
+    points_flat: &[f64],
+    density: &[f32],
+    grid_w: u32,
+    grid_h: u32,
+    width: f64,
+    height: f64,
+    iterations: u32,
+) -> Result<Vec<f64>> {
+    let n = check_points(points_flat)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let map = DensityMap::new(density, grid_w, grid_h, width, height)?;
+    let cells = map.cols * map.rows;
+    let wanted = n.saturating_mul(SAMPLES_PER_SEED).min(MAX_SAMPLES);
+    let refine = ((wanted as f64 / cells as f64).sqrt().ceil() as usize).max(1);
+    let (cols, rows) = (map.cols * refine, map.rows * refine);
+    let (sw, sh) = (width / cols as f64, height / rows as f64);
+    let samples: Vec<(Point, f64)> = (0..rows)
+        .flat_map(|r| (0..cols).map(move |c| (c, r)))
+        .map(|(c, r)| {
+            let p = Point::new((c as f64 + 0.5) * sw, (r as f64 + 0.5) * sh);
+            (p, map.at(p.x, p.y))
+        })
+        .filter(|&(_, d)| d > 0.0)
+        .collect();
+
+    let mut points: Vec<Point> =
+        points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+    for _ in 0..iterations {
+        let grid = PointGrid::new(&points, [0.0, 0.0, width, height]);
+        // Weighted sums of x and y, and the total weight, per seed
+        let mut sums = vec![(0.0, 0.0, 0.0); n];
+        for &(p, d) in &samples {
+            let Some(i) = grid.nearest(p) else { break };
+            let s = &mut sums[i as usize];
+            *s = (s.0 + d * p.x, s.1 + d * p.y, s.2 + d);
+        }
+        for (p, &(sx, sy, total)) in points.iter_mut().zip(&sums) {
+            if total > 0.0 {
+                *p = Point::new((sx / total).clamp(0.0, width), (sy / total).clamp(0.0, height));
+            }
+        }
+    }
+    Ok(points.iter().flat_map(|p| [p.x, p.y]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_weighted_seeds_follow_the_bilinear_density() {
+        // Zero left of x = 100, rising linearly to 1 at x = 300, then flat
+        let (w, h) = (400.0, 100.0);
+        let seeds = generate_weighted_seeds(&[0.0, 1.0], 2, 1, w, h, 6000, 9).unwrap();
+        assert_eq!(seeds, generate_weighted_seeds(&[0.0, 1.0], 2, 1, w, h, 6000, 9).unwrap());
+        assert_ne!(seeds, generate_weighted_seeds(&[0.0, 1.0], 2, 1, w, h, 6000, 10).unwrap());
+        assert_eq!(seeds.len(), 12_000);
+        let xs: Vec<f64> = seeds.iter().step_by(2).copied().collect();
+        assert!(xs.iter().all(|&x| (100.0..=w).contains(&x)));
+        assert!(seeds.iter().skip(1).step_by(2).all(|&y| (0.0..=h).contains(&y)));
+        // The ramp and the flat part hold equal mass, and the first half
+        // of the ramp a quarter of the ramp's
+        let share = |limit: f64| xs.iter().filter(|&&x| x < limit).count() as f64 / 6000.0;
+        assert!((share(300.0) - 0.5).abs() < 0.03, "{}", share(300.0));
+        assert!((share(200.0) - 0.125).abs() < 0.02, "{}", share(200.0));
+    }
+
+    #[test]
+    fn test_weighted_relaxation_lowers_the_weighted_energy() {
+        let (w, h) = (400.0, 400.0);
+        let uniform = generate_weighted_seeds(&[1.0], 1, 1, w, h, 200, 4).unwrap();
+        let density = [0.05, 1.0];
+        let map = DensityMap::new(&density, 2, 1, w, h).unwrap();
+        // Density-weighted squared distance to the nearest seed, the
+        // quantity every Lloyd pass can only lower
+        let energy = |p: &[f64]| {
+            let seeds: Vec<Point> = p.chunks(2).map(|c| Point::new(c[0], c[1])).collect();
+            let mut total = 0.0;
+            for k in 0..200 * 200 {
+                let q = Point::new((k % 200) as f64 * 2.0 + 1.0, (k / 200) as f64 * 2.0 + 1.0);
+                let near = seeds.iter().map(|s| (s.x - q.x).powi(2) + (s.y - q.y).powi(2));
+                total += map.at(q.x, q.y) * near.fold(f64::INFINITY, f64::min);
+            }
+            total
+        };
+        let mut last = energy(&uniform);
+        for iterations in [1, 3, 10] {
+            let relaxed = weighted_lloyd_relax(&uniform, &density, 2, 1, w, h, iterations).unwrap();
+            let e = energy(&relaxed);
+            assert!(e < last, "{iterations} passes: energy {last} -> {e}");
+            last = e;
+        }
+        let relaxed = weighted_lloyd_relax(&uniform, &density, 2, 1, w, h, 10).unwrap();
+        assert_eq!(relaxed, weighted_lloyd_relax(&uniform, &density, 2, 1, w, h, 10).unwrap());
+        let right = |p: &[f64]| p.iter().step_by(2).filter(|&&x| x > w / 2.0).count();
+        assert!(right(&relaxed) > right(&uniform));
+        let inside = |p: &[f64]| (0.0..=w).contains(&p[0]) && (0.0..=h).contains(&p[1]);
+        assert!(relaxed.chunks(2).all(inside));
+        assert_eq!(weighted_lloyd_relax(&uniform, &density, 2, 1, w, h, 0).unwrap(), uniform);
+    }
+
+    #[test]
+    fn test_density_errors() {
+        let seeds = |d: &[f32], gw, w| code_of(generate_weighted_seeds(d, gw, 1, w, 10.0, 5, 0));
+        assert_eq!(seeds(&[0.0, 0.0], 2, 10.0), ErrorCode::InvalidArgument);
+        assert_eq!(seeds(&[1.0, -1.0], 2, 10.0), ErrorCode::InvalidArgument);
+        assert_eq!(seeds(&[1.0, f32::NAN], 2, 10.0), ErrorCode::NonFiniteInput);
+        assert_eq!(seeds(&[1.0, 1.0], 3, 10.0), ErrorCode::InvalidLength);
+        assert_eq!(seeds(&[], 0, 10.0), ErrorCode::InvalidArgument);
+        assert_eq!(seeds(&[1.0], 1, 0.0), ErrorCode::InvalidArgument);
+        let relax = |p: &[f64]| code_of(weighted_lloyd_relax(p, &[1.0], 1, 1, 10.0, 10.0, 1));
+        assert_eq!(relax(&[1.0]), ErrorCode::InvalidLength);
+        assert_eq!(relax(&[f64::NAN, 1.0]), ErrorCode::NonFiniteInput);
+        let zero = weighted_lloyd_relax(&[1.0, 1.0], &[0.0], 1, 1, 10.0, 10.0, 1);
+        let message = zero.unwrap_err().to_string();
+        assert_eq!(message, "invalid argument: density map is zero everywhere");
+    }
+}
@@ -0,0 +1,404 @@
+//! Index buffers for fewer draw calls: stitched strips and vertex fans
+//!
+//! `triangles_to_strips` walks across shared sides with
+//! `triangle_neighbors`, starting every strip from a triangle with the
+//! fewest free neighbours so the walks do not strand single triangles,
+//! and joins the strips with repeated indices that rasterize nothing.
+//! `triangles_to_fan_groups` gathers triangles around shared vertices
+//! instead, for `TRIANGLE_FAN` draws or one canvas `fill()` per fan.
+//! Both keep the winding of every triangle. Triangles repeating a vertex
+//! cover nothing and are left out.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::adjacency::triangle_neighbors;
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles};
+use crate::predicates::orient2d;
+use crate::Point;
+
+fn is_degenerate(t: &[u32]) -> bool {
+    t[0] == t[1] || t[1] == t[2] || t[2] == t[0]
+}
+
+/// Whether `[a, b, c]` is `t` up to rotation, i.e. the same winding
+fn same_winding(abc: [u32; 3], t: &[u32]) -> bool {
+    (0..3).any(|r| abc == [t[r], t[(r + 1) % 3], t[(r + 2) % 3]])
+}
+
+/// Stitched strips from `triangles_to_strips`
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct TriangleStrips {
+    indices: Vec<u32>,
+    strip_count: usize,
+    triangle_count: usize,
+}
+
+#[wasm_bindgen]
+impl TriangleStrips {
+    /// Every strip in one `TRIANGLE_STRIP` index buffer, joined by
+    /// degenerate triangles
+    pub fn indices(&self) -> Vec<u32> {
+        self.indices.clone()
+    }
+
+    /// Strips before stitching; the buffer replaces this many separate
+    /// strip draws, or a triangle count's worth of canvas fills
+    pub fn strip_count(&self) -> usize {
+        self.strip_count
+    }
+
+    /// Triangles the buffer draws, the non-degenerate input triangles
+    pub fn triangle_count(&self) -> usize {
+        self.triangle_count
+    }
+}
+
+/// One `TRIANGLE_STRIP` buffer drawing exactly `triangles`
+///
+/// Strips grow greedily from a triangle with the fewest free neighbours,
+/// trying each of its three sides as the start, and only across sides
+/// where the strip's alternating winding matches the next triangle's, so
+/// inconsistently wound meshes just give shorter strips. Consecutive
+/// strips are joined by repeating the last index of one and the first of
+/// the next, once more when needed to keep the next strip on an even
+/// position.
+#[wasm_bindgen]
+pub fn triangles_to_strips(triangles: &[u32]) -> Result<TriangleStrips> {
+    let neighbors = triangle_neighbors(triangles)?;
+    let n = triangles.len() / 3;
+    let tri = |t: usize| &triangles[3 * t..3 * t + 3];
+    let mut used: Vec<bool> = triangles.chunks_exact(3).map(is_degenerate).collect();
+    let free = |t: usize, used: &[bool]| {
+        let sides = &neighbors[3 * t..3 * t + 3];
+        sides.iter().filter(|&&m| m >= 0 && !used[m as usize]).count()
+    };
+    // Free-neighbour buckets with lazy deletion: an entry is live while
+    // its triangle is unused and still has that many free neighbours
+    let mut buckets: [Vec<usize>; 4] = Default::default();
+    for t in (0..n).rev().filter(|&t| !used[t]) {
+        buckets[free(t, &used)].push(t);
+    }
+
+    let mut seen = vec![0u32; n];
+    let mut stamp = 0;
+    let (mut walk, mut walk_tris) = (Vec::new(), Vec::new());
+    let (mut best, mut best_tris) = (Vec::new(), Vec::new());
+    let mut indices = Vec::with_capacity(triangles.len());
+    let (mut strip_count, mut triangle_count) = (0, 0);
+    loop {
+        let mut start = None;
+        for (b, bucket) in buckets.iter_mut().enumerate() {
+            while let Some(t) = bucket.pop() {
+                if !used[t] && free(t, &used) == b {
+                    start = Some(t);
+                    break;
+                }
+            }
+            if start.is_some() {
+                break;
+            }
+        }
+        let Some(start) = start else { break };
+
+        best.clear();
+        for r in 0..3 {
+            stamp += 1;
+            let s = tri(start);
+            walk.clear();
+            walk.extend([s[r], s[(r + 1) % 3], s[(r + 2) % 3]]);
+            walk_tris.clear();
+            walk_tris.push(start);
+            seen[start] = stamp;
+            let mut cur = start;
+            loop {
+                let (x, y) = (walk[walk.len() - 2], walk[walk.len() - 1]);
+                let c = tri(cur);
+                let k = (0..3).find(|&k| {
+                    let (p, q) = (c[k], c[(k + 1) % 3]);
+                    (p, q) == (x, y) || (p, q) == (y, x)
+                });
+                let Some(m) = k.map(|k| neighbors[3 * cur + k]).filter(|&m| m >= 0) else {
+                    break;
+                };
+                let m = m as usize;
+                if used[m] || seen[m] == stamp {
+                    break;
+                }
+                let z = *tri(m).iter().find(|&&v| v != x && v != y).unwrap();
+                // The strip draws position `i` as `(s_i, s_i+1, s_i+2)` when
+                // `i` is even and with the first two swapped when odd
+                let drawn = if walk.len() % 2 == 0 { [x, y, z] } else { [y, x, z] };
+                if !same_winding(drawn, tri(m)) {
+                    break;
+                }
+                walk.push(z);
+                walk_tris.push(m);
+                seen[m] = stamp;
+                cur = m;
+            }
+            if walk.len() > best.len() {
+                std::mem::swap(&mut walk, &mut best);
+                std::mem::swap(&mut walk_tris, &mut best_tris);
+            }
+        }
+
+        if let Some(&last) = indices.last() {
+            indices.extend([last, best[0]]);
+            if indices.len() % 2 == 1 {
+                indices.push(best[0]);
+            }
+        }
+        indices.extend_from_slice(&best);
+        strip_count += 1;
+        triangle_count += best_tris.len();
+        for &t in &best_tris {
+            used[t] = true;
+        }
+        for &t in &best_tris {
+            for &m in &neighbors[3 * t..3 * t + 3] {
+                if m >= 0 && !used[m as usize] {
+                    let m = m as usize;
+                    buckets[free(m, &used)].push(m);
+                }
+            }
+        }
+    }
+    Ok(TriangleStrips { indices, strip_count, triangle_count })
+}
+
+/// Vertex fans from `triangles_to_fan_groups`
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct FanGroups {
+    indices: Vec<u32>,
+    offsets: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl FanGroups {
+    /// Every fan as its centre then its rim, back to back; each one is a
+    /// `TRIANGLE_FAN` draw, or a polygon to fill in canvas
+    pub fn indices(&self) -> Vec<u32> {
+        self.indices.clone()
+    }
+
+    /// `fan_count + 1` entries; fan `i` is
+    /// `indices()[offsets[i]..offsets[i + 1]]`
+    pub fn offsets(&self) -> Vec<u32> {
+        self.offsets.clone()
+    }
+
+    pub fn fan_count(&self) -> usize {
+        self.offsets.len() - 1
+    }
+}
+
+/// `triangles` grouped into fans around shared vertices
+///
+/// The centre with the most ungrouped triangles goes first, and its
+/// triangles are chained side to side into as few fans as their
+/// adjacency allows; a closed ring repeats its first rim vertex at the
+/// end. `points_flat` keeps triangles of opposite orientation out of one
+/// fan, so on a folded mesh each filled fan polygon still covers exactly
+/// its triangles.
+#[wasm_bindgen]
+pub fn triangles_to_fan_groups(triangles: &[u32], points_flat: &[f64]) -> Result<FanGroups> {
+    let point_count = check_points(points_flat)?;
+    check_triangles(triangles, point_count)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let point = |v: u32| Point::new(points_flat[2 * v as usize], points_flat[2 * v as usize + 1]);
+    let sign: Vec<i8> = triangles
+        .chunks_exact(3)
+        .map(|t| {
+            let o = orient2d(point(t[0]), point(t[1]), point(t[2]));
+            (o > 0.0) as i8 - (o < 0.0) as i8
+        })
+        .collect();
+    let mut used: Vec<bool> = triangles.chunks_exact(3).map(is_degenerate).collect();
+
+    // Incident triangles per vertex, CSR style
+    let mut starts = vec![0u32; point_count + 1];
+    for &v in triangles {
+        starts[v as usize + 1] += 1;
+    }
+    for v in 0..point_count {
+        starts[v + 1] += starts[v];
+    }
+    let mut incident = vec![0u32; triangles.len()];
+    let mut fill = starts.clone();
+    for (i, &v) in triangles.iter().enumerate() {
+        incident[fill[v as usize] as usize] = (i / 3) as u32;
+        fill[v as usize] += 1;
+    }
+    let mut free: Vec<usize> = (0..point_count)
+        .map(|v| {
+            let around = &incident[starts[v] as usize..starts[v + 1] as usize];
+            around.iter().filter(|&&t| !used[t as usize]).count()
+        })
+        .collect();
+    let mut heap: BinaryHeap<(usize, Reverse<u32>)> =
+        (0..point_count as u32).map(|v| (free[v as usize], Reverse(v))).collect();
+
+    let mut indices = Vec::with_capacity(triangles.len());
+    let mut offsets = vec![0u32];
+    // `(a, b, t)` for each free triangle `(v, a, b)` around the centre
+    let mut around: Vec<(u32, u32, u32)> = Vec::new();
+    let mut ends: Vec<u32> = Vec::new();
+    while let Some((count, Reverse(v))) = heap.pop() {
+        if count == 0 || count != free[v as usize] {
+            continue;
+        }
+        around.clear();
+        for &t in &incident[starts[v as usize] as usize..starts[v as usize + 1] as usize] {
+            let s = &triangles[3 * t as usize..3 * t as usize + 3];
+            if !used[t as usize] {
+                let k = s.iter().position(|&u| u == v).unwrap();
+                around.push((s[(k + 1) % 3], s[(k + 2) % 3], t));
+            }
+        }
+        around.sort_unstable();
+        ends.clear();
+        ends.extend(around.iter().map(|e| e.1));
+        ends.sort_unstable();
+
+        let mut taken = vec![false; around.len()];
+        // Open chains first, from triangles nothing precedes; what is left
+        // is closed rings, which may start anywhere
+        for pass in 0..2 {
+            for i in 0..around.len() {
+                if taken[i] || (pass == 0 && ends.binary_search(&around[i].0).is_ok()) {
+                    continue;
+                }
+                taken[i] = true;
+                let (a, mut b, t) = around[i];
+                let mut orientation = sign[t as usize];
+                indices.extend([v, a, b]);
+                let mut chain = vec![t];
+                loop {
+                    let from = around.partition_point(|e| e.0 < b);
+                    let next = (from..around.len()).take_while(|&j| around[j].0 == b).find(|&j| {
+                        let s = sign[around[j].2 as usize];
+                        !taken[j] && (s == 0 || orientation == 0 || s == orientation)
+                    });
+                    let Some(j) = next else { break };
+                    taken[j] = true;
+                    if orientation == 0 {
+                        orientation = sign[around[j].2 as usize];
+                    }
+                    b = around[j].1;
+                    indices.push(b);
+                    chain.push(around[j].2);
+                }
+                offsets.push(indices.len() as u32);
+                for &t in &chain {
+                    used[t as usize] = true;
+                    for &u in &triangles[3 * t as usize..3 * t as usize + 3] {
+                        free[u as usize] -= 1;
+                        if u != v {
+                            heap.push((free[u as usize], Reverse(u)));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(FanGroups { indices, offsets })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::{compute_delaunay, generate_golden_seeds};
+
+    /// Triangles rotated to start at their least index, then sorted
+    fn canonical(triangles: impl IntoIterator<Item = [u32; 3]>) -> Vec<[u32; 3]> {
+        let mut out: Vec<[u32; 3]> = triangles
+            .into_iter()
+            .map(|t| {
+                let r = (0..3).min_by_key(|&r| t[r]).unwrap();
+                [t[r], t[(r + 1) % 3], t[(r + 2) % 3]]
+            })
+            .collect();
+        out.sort_unstable();
+        out
+    }
+
+    /// What a GPU draws for a strip, degenerate triangles dropped
+    fn expand_strip(strip: &[u32]) -> Vec<[u32; 3]> {
+        let drawn = strip.windows(3).enumerate().map(|(i, w)| match i % 2 {
+            0 => [w[0], w[1], w[2]],
+            _ => [w[1], w[0], w[2]],
+        });
+        drawn.filter(|t| !is_degenerate(t)).collect()
+    }
+
+    fn fixture() -> (Vec<f64>, Vec<u32>) {
+        let points = generate_golden_seeds(800.0, 600.0, 600);
+        let mut triangles = compute_delaunay(&points, 800.0, 600.0);
+        // A flipped triangle and a degenerate one
+        triangles.swap(0, 1);
+        triangles.extend([5, 5, 9]);
+        (points, triangles)
+    }
+
+    #[test]
+    fn test_strips_round_trip() {
+        let (_, triangles) = fixture();
+        let strips = triangles_to_strips(&triangles).unwrap();
+        let input: Vec<[u32; 3]> = triangles
+            .chunks(3)
+            .filter(|t| !is_degenerate(t))
+            .map(|t| [t[0], t[1], t[2]])
+            .collect();
+        assert_eq!(strips.triangle_count(), input.len());
+        assert_eq!(canonical(expand_strip(&strips.indices())), canonical(input.clone()));
+        // Strips average several triangles each on a Delaunay mesh
+        assert!(strips.strip_count() * 4 < input.len(), "{} strips", strips.strip_count());
+
+        let empty = triangles_to_strips(&[]).unwrap();
+        assert!(empty.indices().is_empty() && empty.strip_count() == 0);
+        let single = triangles_to_strips(&[0, 1, 2]).unwrap();
+        assert_eq!((single.indices(), single.strip_count()), (vec![0, 1, 2], 1));
+        assert_eq!(code_of(triangles_to_strips(&[0, 1])), ErrorCode::InvalidLength);
+    }
+
+    #[test]
+    fn test_fan_groups_round_trip() {
+        let (points, triangles) = fixture();
+        let fans = triangles_to_fan_groups(&triangles, &points).unwrap();
+        let (indices, offsets) = (fans.indices(), fans.offsets());
+        assert_eq!(offsets.len(), fans.fan_count() + 1);
+        let mut drawn = Vec::new();
+        for w in offsets.windows(2) {
+            let fan = &indices[w[0] as usize..w[1] as usize];
+            assert!(fan.len() >= 3);
+            drawn.extend(fan[1..].windows(2).map(|r| [fan[0], r[0], r[1]]));
+        }
+        let input: Vec<[u32; 3]> = triangles
+            .chunks(3)
+            .filter(|t| !is_degenerate(t))
+            .map(|t| [t[0], t[1], t[2]])
+            .collect();
+        assert_eq!(canonical(drawn), canonical(input.clone()));
+        assert!(fans.fan_count() * 3 < input.len(), "{} fans", fans.fan_count());
+
+        // Around one interior vertex the fan closes on itself
+        let hub = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, -1.0, 0.0, 0.0, -1.0];
+        let fans = triangles_to_fan_groups(&[0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 1], &hub).unwrap();
+        assert_eq!((fans.indices(), fans.offsets()), (vec![0, 1, 2, 3, 4, 1], vec![0, 6]));
+
+        assert_eq!(code_of(triangles_to_fan_groups(&[0, 1, 9], &hub)), ErrorCode::IndexOutOfRange);
+        let odd = triangles_to_fan_groups(&[0, 1, 2], &hub[..3]);
+        assert_eq!(code_of(odd), ErrorCode::InvalidLength);
+        let nan = [f64::NAN, 0.0, 1.0, 0.0, 0.0, 1.0];
+        assert_eq!(code_of(triangles_to_fan_groups(&[0, 1, 2], &nan)), ErrorCode::NonFiniteInput);
+    }
+}
@@ -0,0 +1,162 @@
+//! Tapered stroke geometry for edges, ready for `TRIANGLE_STRIP` draws
+//!
+//! Each edge becomes `segments + 1` stations from its first vertex to its
+//! second, and each station two vertices `[x, y, u, alpha]`, one either
+//! side of the edge at half the station's width along its normal: the
+//! `(-dy, dx)` side of the direction `(dx, dy)` first, then the other, so
+//! consecutive pairs form the strip's quads. Edge `e` owns vertices
+//! `e × 2 × (segments + 1)` onwards, so it is one `drawArrays` of that
+//! many vertices, or one entry of a `WEBGL_multi_draw` batch for all.
+
+use wasm_bindgen::prelude::*;
+
+use crate::buffers::check_out_len;
+use crate::error::{Error, Result};
+use crate::graph::{check_edge_indices, check_points};
+
+/// Most segments per edge `edge_strokes` will lay out
+pub const MAX_STROKE_SEGMENTS: u32 = 1024;
+
+/// Floats `edge_strokes` writes for `edge_count` edges
+fn stroke_len(edge_count: usize, segments: u32) -> usize {
+    edge_count * 2 * (segments as usize + 1) * 4
+}
+
+/// Stroke strips for the edges `[a0, b0, a1, b1, ...]` of `points_flat`,
+/// tapering linearly from `width_start` at `a` to `width_end` at `b`
+///
+/// `u` runs from 0 at `a` to 1 at `b`, and `alpha` is the station's
+/// width over the wider end's, so the thin end also fades; both are 1
+/// along an untapered stroke. A zero-length edge has no normal and
+/// gives a strip of coincident vertices, which draws nothing.
+#[wasm_bindgen]
+pub fn edge_strokes(
+    points_flat: &[f64],
+    edge_indices: &[u32],
+    width_start: f64,
+    width_end: f64,
+    segments: u32,
+) -> Result<Vec<f32>> {
+    let capped = segments.min(MAX_STROKE_SEGMENTS);
+    let mut out = vec![0.0; stroke_len(edge_indices.len() / 2, capped)];
+    edge_strokes_into(points_flat, edge_indices, width_start, width_end, segments, &mut out)?;
+    Ok(out)
+}
+
+/// `edge_strokes` writing into the front of `out`; returns the number of
+/// values written
+///
+/// `out` needs `8 × (segments + 1)` values per edge, and may be longer so
+/// one buffer can be reused frame after frame.
+#[wasm_bindgen]
+pub fn edge_strokes_into(
+    points_flat: &[f64],
+    edge_indices: &[u32],
+    width_start: f64,
+    width_end: f64,
+    segments: u32,
+    out: &mut [f32],
+) -> Result<usize> {
+    let n = check_points(points_flat)?;
+    check_edge_indices(edge_indices, n)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    for (name, w) in [("start", width_start), ("end", width_end)] {
+        if !w.is_finite() || w < 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "{name} width must be finite and non-negative, got {w}"
+            )));
+        }
+    }
+    if segments == 0 || segments > MAX_STROKE_SEGMENTS {
+        return Err(Error::InvalidArgument(format!(
+            "segments must be in 1..={MAX_STROKE_SEGMENTS}, got {segments}"
+        )));
+    }
+    let needed = stroke_len(edge_indices.len() / 2, segments);
+    check_out_len(needed, out.len())?;
+
+    let widest = width_start.max(width_end);
+    let stations = segments as usize + 1;
+    let at = |v: u32| (points_flat[v as usize * 2], points_flat[v as usize * 2 + 1]);
+    for (e, strip) in edge_indices.chunks_exact(2).zip(out.chunks_exact_mut(stations * 8)) {
+        let ((ax, ay), (bx, by)) = (at(e[0]), at(e[1]));
+        let (dx, dy) = (bx - ax, by - ay);
+        let length = dx.hypot(dy);
+        // Unit normal to a → b, or none for a point
+        let (nx, ny) = if length > 0.0 { (-dy / length, dx / length) } else { (0.0, 0.0) };
+        for (k, pair) in strip.chunks_exact_mut(8).enumerate() {
+            let u = k as f64 / segments as f64;
+            let width = width_start + (width_end - width_start) * u;
+            let alpha = if widest > 0.0 { width / widest } else { 1.0 };
+            let (x, y, half) = (ax + dx * u, ay + dy * u, width / 2.0);
+            pair.copy_from_slice(&[
+                (x + nx * half) as f32,
+                (y + ny * half) as f32,
+                u as f32,
+                alpha as f32,
+                (x - nx * half) as f32,
+                (y - ny * half) as f32,
+                u as f32,
+                alpha as f32,
+            ]);
+        }
+    }
+    Ok(needed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_strokes_taper_along_the_edge() {
+        // Edge 0 runs right along y = 0, edge 1 is a single point
+        let points = [0.0, 0.0, 10.0, 0.0, 5.0, 5.0];
+        let out = edge_strokes(&points, &[0, 1, 2, 2], 4.0, 2.0, 2).unwrap();
+        assert_eq!(out.len(), 2 * 2 * 3 * 4);
+        let first: Vec<&[f32]> = out[..24].chunks(4).collect();
+        let want: [[f32; 4]; 6] = [
+            [0.0, 2.0, 0.0, 1.0],
+            [0.0, -2.0, 0.0, 1.0],
+            [5.0, 1.5, 0.5, 0.75],
+            [5.0, -1.5, 0.5, 0.75],
+            [10.0, 1.0, 1.0, 0.5],
+            [10.0, -1.0, 1.0, 0.5],
+        ];
+        assert_eq!(first, want);
+        // The point's strip is degenerate but finite
+        for v in out[24..].chunks(4) {
+            assert_eq!((v[0], v[1]), (5.0, 5.0));
+        }
+        assert!(out.iter().all(|v| v.is_finite()));
+
+        // Zero widths draw nothing but keep alpha defined
+        let flat = edge_strokes(&points, &[0, 1], 0.0, 0.0, 1).unwrap();
+        let ends = [[0.0, 0.0, 0.0, 1.0], [10.0, 0.0, 1.0, 1.0]];
+        assert_eq!(flat, [ends[0], ends[0], ends[1], ends[1]].concat());
+        let mut buffer = vec![-1.0; out.len() + 3];
+        let written = edge_strokes_into(&points, &[0, 1, 2, 2], 4.0, 2.0, 2, &mut buffer);
+        assert_eq!(written, Ok(out.len()));
+        assert_eq!((&buffer[..out.len()], buffer[out.len()]), (&out[..], -1.0));
+    }
+
+    #[test]
+    fn test_stroke_errors() {
+        let points = [0.0, 0.0, 10.0, 0.0];
+        let code = |p: &[f64], e: &[u32], w: f64, s: u32| code_of(edge_strokes(p, e, w, 1.0, s));
+        assert!(edge_strokes(&points, &[], 1.0, 1.0, 4).unwrap().is_empty());
+        assert_eq!(code(&points, &[0], 1.0, 1), ErrorCode::InvalidLength);
+        assert_eq!(code(&points, &[0, 2], 1.0, 1), ErrorCode::IndexOutOfRange);
+        assert_eq!(code(&[f64::NAN, 0.0, 1.0, 1.0], &[0, 1], 1.0, 1), ErrorCode::NonFiniteInput);
+        assert_eq!(code(&points, &[0, 1], -1.0, 1), ErrorCode::InvalidArgument);
+        assert_eq!(code(&points, &[0, 1], f64::INFINITY, 1), ErrorCode::InvalidArgument);
+        assert_eq!(code(&points, &[0, 1], 1.0, 0), ErrorCode::InvalidArgument);
+        let many = MAX_STROKE_SEGMENTS + 1;
+        assert_eq!(code(&points, &[0, 1], 1.0, many), ErrorCode::InvalidArgument);
+        let short = edge_strokes_into(&points, &[0, 1], 1.0, 1.0, 1, &mut [0.0; 15]);
+        assert_eq!(code_of(short), ErrorCode::InvalidLength);
+    }
+}
@@ -0,0 +1,286 @@
+//! Local refinement of selected triangles
+//!
+//! Midpoint mode splits every selected triangle into four and then closes
+//! the mesh: a neighbour left with a split side would end in a T-junction,
+//! so it is cut at the new midpoints too, in two if one of its sides was
+//! split and in three if two were. A triangle with all three sides split
+//! is cut in four like a selected one. Centroid mode splits each selected
+//! triangle in three about its centroid and touches no side, so nothing
+//! else changes.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles};
+
+/// How `subdivide_triangles` splits a selected triangle
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubdivisionMode {
+    /// Four triangles on the side midpoints, neighbours cut to match
+    Midpoint = 0,
+    /// Three triangles about the centroid
+    Centroid = 1,
+}
+
+/// Refined mesh from `subdivide_triangles`
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Subdivision {
+    points: Vec<f64>,
+    triangles: Vec<u32>,
+    parents: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl Subdivision {
+    /// The input points followed by the new ones, `[x0, y0, x1, y1, ...]`
+    pub fn points(&self) -> Vec<f64> {
+        self.points.clone()
+    }
+
+    /// `[a0, b0, c0, a1, ...]`, each input triangle replaced in place by
+    /// its pieces, wound as it was
+    pub fn triangles(&self) -> Vec<u32> {
+        self.triangles.clone()
+    }
+
+    /// Input triangle each output triangle came from, to inherit
+    /// attributes by
+    pub fn parents(&self) -> Vec<u32> {
+        self.parents.clone()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.parents.len()
+    }
+}
+
+/// Midpoints of split sides, numbered after the input points in the
+/// order the sides are first split
+struct Midpoints {
+    points: Vec<f64>,
+    index: HashMap<(u32, u32), u32>,
+}
+
+impl Midpoints {
+    fn split(&mut self, a: u32, b: u32) {
+        let next = (self.points.len() / 2) as u32;
+        if let Entry::Vacant(e) = self.index.entry((a.min(b), a.max(b))) {
+            e.insert(next);
+            let (a, b) = (a as usize * 2, b as usize * 2);
+            let mid = [0, 1].map(|k| (self.points[a + k] + self.points[b + k]) / 2.0);
+            self.points.extend(mid);
+        }
+    }
+
+    fn get(&self, a: u32, b: u32) -> Option<u32> {
+        self.index.get(&(a.min(b), a.max(b))).copied()
+    }
+
+    fn distance(&self, a: u32, b: u32) -> f64 {
+        let (a, b) = (a as usize * 2, b as usize * 2);
+        (self.points[a] - self.points[b]).hypot(self.points[a + 1] - self.points[b + 1])
+    }
+}
+
+/// Split the selected triangles by `mode`, appending the new points
+///
+/// `selection` holds triangle indices, 0 for the first triple; repeats
+/// are harmless. In midpoint mode the unselected neighbours are cut along
+/// the new midpoints as well, so the result is conforming wherever the
+/// input was: no vertex lies inside another triangle's side, and a side
+/// is shared by two triangles at most. Of the two ways to cut the quad
+/// left beside two split sides, the shorter diagonal is taken.
+#[wasm_bindgen]
+pub fn subdivide_triangles(
+    points_flat: &[f64],
+    triangles: &[u32],
+    selection: &[u32],
+    mode: SubdivisionMode,
+) -> Result<Subdivision> {
+    let n = check_points(points_flat)?;
+    check_triangles(triangles, n)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    let count = triangles.len() / 3;
+    if let Some(&t) = selection.iter().find(|&&t| t as usize >= count) {
+        return Err(Error::IndexOutOfRange(format!(
+            "triangle {t} out of range for {count} triangles"
+        )));
+    }
+    let mut selected = vec![false; count];
+    selection.iter().for_each(|&t| selected[t as usize] = true);
+    let corners = |t: usize| [triangles[t * 3], triangles[t * 3 + 1], triangles[t * 3 + 2]];
+
+    let mut mids = Midpoints { points: points_flat.to_vec(), index: HashMap::new() };
+    if mode == SubdivisionMode::Midpoint {
+        for t in selection {
+            let [a, b, c] = corners(*t as usize);
+            mids.split(a, b);
+            mids.split(b, c);
+            mids.split(c, a);
+        }
+    }
+
+    let mut out = Subdivision { points: Vec::new(), triangles: Vec::new(), parents: Vec::new() };
+    let mut emit = |parent: usize, pieces: &[[u32; 3]]| {
+        for piece in pieces {
+            out.triangles.extend_from_slice(piece);
+            out.parents.push(parent as u32);
+        }
+    };
+    for (t, &chosen) in selected.iter().enumerate() {
+        let [a, b, c] = corners(t);
+        if mode == SubdivisionMode::Centroid {
+            if chosen {
+                let m = (mids.points.len() / 2) as u32;
+                let at = |v: u32, k: usize| points_flat[v as usize * 2 + k];
+                let center = [0, 1].map(|k| (at(a, k) + at(b, k) + at(c, k)) / 3.0);
+                mids.points.extend(center);
+                emit(t, &[[a, b, m], [b, c, m], [c, a, m]]);
+            } else {
+                emit(t, &[[a, b, c]]);
+            }
+            continue;
+        }
+        let v = [a, b, c];
+        let split = [mids.get(a, b), mids.get(b, c), mids.get(c, a)];
+        match split.iter().filter(|m| m.is_some()).count() {
+            0 => emit(t, &[[a, b, c]]),
+            1 => {
+                // Bisect from the opposite corner
+                let k = split.iter().position(|m| m.is_some()).unwrap_or(0);
+                let (p, q, r) = (v[k], v[(k + 1) % 3], v[(k + 2) % 3]);
+                let m = split[k].unwrap_or(0);
+                emit(t, &[[p, m, r], [m, q, r]]);
+            }
+            2 => {
+                // The unsplit side is `(r, p)`, after the two split ones
+                let k = (split.iter().position(|m| m.is_none()).unwrap_or(0) + 1) % 3;
+                let (p, q, r) = (v[k], v[(k + 1) % 3], v[(k + 2) % 3]);
+                let (m1, m2) = (split[k].unwrap_or(0), split[(k + 1) % 3].unwrap_or(0));
+                if mids.distance(p, m2) <= mids.distance(m1, r) {
+                    emit(t, &[[m1, q, m2], [p, m1, m2], [p, m2, r]]);
+                } else {
+                    emit(t, &[[m1, q, m2], [p, m1, r], [m1, m2, r]]);
+                }
+            }
+            _ => {
+                let [mab, mbc, mca] = split.map(|m| m.unwrap_or(0));
+                emit(t, &[[a, mab, mca], [mab, b, mbc], [mca, mbc, c], [mab, mbc, mca]]);
+            }
+        }
+    }
+    out.points = mids.points;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::graph::boundary_edges;
+    use crate::predicates::orient2d;
+    use crate::rng::Rng;
+    use crate::{compute_delaunay, Point};
+
+    fn area(points: &[f64], t: &[u32]) -> f64 {
+        let p = |v: u32| Point::new(points[v as usize * 2], points[v as usize * 2 + 1]);
+        orient2d(p(t[0]), p(t[1]), p(t[2])) / 2.0
+    }
+
+    fn hull_length(points: &[f64], triangles: &[u32]) -> f64 {
+        let length = |(a, b): (u32, u32)| {
+            let (a, b) = (a as usize * 2, b as usize * 2);
+            (points[a] - points[b]).hypot(points[a + 1] - points[b + 1])
+        };
+        boundary_edges(triangles).into_iter().map(length).sum()
+    }
+
+    #[test]
+    fn test_midpoint_refinement_is_conforming() {
+        let mut rng = Rng::new(13);
+        let points: Vec<f64> =
+            (0..300).flat_map(|_| [rng.range(0.0, 400.0), rng.range(0.0, 300.0)]).collect();
+        let triangles = compute_delaunay(&points, 400.0, 300.0);
+        let count = triangles.len() / 3;
+        // Triangles around a cursor, plus a scattering elsewhere
+        let near = |v: u32| {
+            let (x, y) = (points[v as usize * 2], points[v as usize * 2 + 1]);
+            (x - 200.0).hypot(y - 150.0) < 60.0
+        };
+        let mut selection: Vec<u32> = (0..count as u32)
+            .filter(|&t| triangles[t as usize * 3..][..3].iter().all(|&v| near(v)))
+            .collect();
+        selection.extend((0..count as u32).step_by(9));
+        let out = subdivide_triangles(&points, &triangles, &selection, SubdivisionMode::Midpoint)
+            .unwrap();
+        let (new_points, new_triangles, parents) = (out.points(), out.triangles(), out.parents());
+        assert_eq!(new_points[..600], points[..]);
+        assert_eq!(out.triangle_count(), new_triangles.len() / 3);
+
+        // Children tile their parent with its winding
+        let mut child_area = vec![0.0; count];
+        let mut pieces = vec![0; count];
+        for (t, &parent) in new_triangles.chunks(3).zip(&parents) {
+            let a = area(&new_points, t);
+            assert!(a > 0.0);
+            child_area[parent as usize] += a;
+            pieces[parent as usize] += 1;
+        }
+        for (t, tri) in triangles.chunks(3).enumerate() {
+            let a = area(&points, tri);
+            assert!((child_area[t] - a).abs() < 1e-9 * a.max(1.0));
+            assert!((1..=4).contains(&pieces[t]));
+            if selection.contains(&(t as u32)) {
+                assert_eq!(pieces[t], 4);
+            }
+        }
+        assert!(pieces.contains(&2) && pieces.contains(&3));
+
+        // Conforming: each side is shared at most twice and the sides used
+        // once trace the input hull, as a T-junction would add an inner one
+        let mut uses: HashMap<(u32, u32), usize> = HashMap::new();
+        for t in new_triangles.chunks(3) {
+            for k in 0..3 {
+                let (a, b) = (t[k], t[(k + 1) % 3]);
+                *uses.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        assert!(uses.values().all(|&u| u <= 2));
+        let before = hull_length(&points, &triangles);
+        assert!((hull_length(&new_points, &new_triangles) - before).abs() < 1e-9 * before);
+    }
+
+    #[test]
+    fn test_small_subdivisions() {
+        let points = [0.0, 0.0, 3.0, 0.0, 3.0, 3.0, 0.0, 3.0];
+        let square = [0, 1, 2, 0, 2, 3];
+        let mode = SubdivisionMode::Centroid;
+        let out = subdivide_triangles(&points, &square, &[1, 1], mode).unwrap();
+        assert_eq!(out.points()[8..], [1.0, 2.0]);
+        assert_eq!(out.triangles(), vec![0, 1, 2, 0, 2, 4, 2, 3, 4, 3, 0, 4]);
+        assert_eq!(out.parents(), vec![0, 1, 1, 1]);
+
+        // Splitting one half bisects the other across the shared diagonal
+        let mode = SubdivisionMode::Midpoint;
+        let out = subdivide_triangles(&points, &square, &[0], mode).unwrap();
+        assert_eq!(out.points()[8..], [1.5, 0.0, 3.0, 1.5, 1.5, 1.5]);
+        assert_eq!(out.triangles()[12..], [0, 6, 3, 6, 2, 3]);
+        assert_eq!(out.parents(), vec![0, 0, 0, 0, 1, 1]);
+
+        let none = subdivide_triangles(&points, &square, &[], mode).unwrap();
+        assert_eq!((none.triangles(), none.points()), (square.to_vec(), points.to_vec()));
+        let split = |t: &[u32], s: &[u32]| code_of(subdivide_triangles(&points, t, s, mode));
+        assert_eq!(split(&square, &[2]), ErrorCode::IndexOutOfRange);
+        assert_eq!(split(&square[..5], &[]), ErrorCode::InvalidLength);
+        assert_eq!(split(&[0, 1, 4], &[]), ErrorCode::IndexOutOfRange);
+        let bad = subdivide_triangles(&[0.0, f64::NAN, 1.0, 0.0, 0.0, 1.0], &[0, 1, 2], &[], mode);
+        assert_eq!(code_of(bad), ErrorCode::NonFiniteInput);
+    }
+}
@@ -0,0 +1,269 @@
+//! SVG path data for edges, triangles and cell polygons
+//!
+//! Each export returns a single `d` attribute string of absolute `M`, `L`
+//! and `Z` commands, ready to go into a `<path>`. Coordinates are written
+//! as given, in canvas units with y pointing down, rounded to `precision`
+//! decimal places with trailing zeros dropped. Within a subpath a vertex
+//! lying exactly on the straight line from its predecessor to its
+//! successor is left out, since drawing through it changes nothing.
+
+use std::fmt::Write;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, check_triangles};
+use crate::Point;
+
+/// Most decimal places any of the exports will write
+pub const SVG_MAX_PRECISION: u32 = 10;
+
+/// Path data being written, one subpath at a time
+struct PathWriter {
+    d: String,
+    precision: usize,
+    run: Vec<Point>,
+}
+
+impl PathWriter {
+    fn new(precision: u32) -> Result<PathWriter> {
+        if precision > SVG_MAX_PRECISION {
+            return Err(Error::InvalidArgument(format!(
+                "precision {precision} is above the maximum of {SVG_MAX_PRECISION}"
+            )));
+        }
+        Ok(PathWriter { d: String::new(), precision: precision as usize, run: Vec::new() })
+    }
+
+    /// Append `p` to the open subpath, dropping the previous vertex when
+    /// it is a straight-through point between its neighbours
+    fn line_to(&mut self, p: Point) {
+        if let [.., a, b] = self.run[..] {
+            let cross = (b.x - a.x) * (p.y - b.y) - (b.y - a.y) * (p.x - b.x);
+            let dot = (b.x - a.x) * (p.x - b.x) + (b.y - a.y) * (p.y - b.y);
+            if cross == 0.0 && dot > 0.0 {
+                self.run.pop();
+            }
+        }
+        self.run.push(p);
+    }
+
+    /// Write out the open subpath, closed with `Z` when `close` is set
+    fn finish(&mut self, close: bool) {
+        for (i, p) in self.run.iter().enumerate() {
+            self.d.push(if i == 0 { 'M' } else { 'L' });
+            push_number(&mut self.d, p.x, self.precision);
+            self.d.push(' ');
+            push_number(&mut self.d, p.y, self.precision);
+        }
+        if close && !self.run.is_empty() {
+            self.d.push('Z');
+        }
+        self.run.clear();
+    }
+}
+
+/// `v` to `precision` places, without trailing zeros or a negative zero
+fn push_number(d: &mut String, v: f64, precision: usize) {
+    let start = d.len();
+    // Writing to a String cannot fail
+    let _ = write!(d, "{v:.precision$}");
+    if d[start..].contains('.') {
+        let end = d.trim_end_matches('0').trim_end_matches('.').len();
+        d.truncate(end);
+    }
+    if &d[start..] == "-0" {
+        d.remove(start);
+    }
+}
+
+fn parse_points(points_flat: &[f64]) -> Result<Vec<Point>> {
+    check_points(points_flat)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("point coordinates must be finite".into()));
+    }
+    Ok(points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect())
+}
+
+/// Path data drawing each edge `[i0, j0, i1, j1, ...]` of `points_flat`
+///
+/// An edge starting at the vertex where the previous one ended carries
+/// on the same subpath, so chains in order (such as boundary loops or
+/// paths) cost one coordinate pair per vertex; nothing is reordered.
+#[wasm_bindgen]
+pub fn edges_to_svg_path(
+    points_flat: &[f64],
+    edge_indices: &[u32],
+    precision: u32,
+) -> Result<String> {
+    let points = parse_points(points_flat)?;
+    if !edge_indices.len().is_multiple_of(2) {
+        return Err(Error::InvalidLength(format!(
+            "edge index count {} is odd",
+            edge_indices.len()
+        )));
+    }
+    if let Some(&bad) = edge_indices.iter().find(|&&i| i as usize >= points.len()) {
+        return Err(Error::IndexOutOfRange(format!(
+            "edge references vertex {bad} but only {} points were given",
+            points.len()
+        )));
+    }
+    let mut path = PathWriter::new(precision)?;
+    let mut end = None;
+    for e in edge_indices.chunks_exact(2) {
+        if end != Some(e[0]) {
+            path.finish(false);
+            path.line_to(points[e[0] as usize]);
+        }
+        path.line_to(points[e[1] as usize]);
+        end = Some(e[1]);
+    }
+    path.finish(false);
+    Ok(path.d)
+}
+
+/// Path data with one closed subpath per triangle of `points_flat`
+#[wasm_bindgen]
+pub fn triangles_to_svg_path(
+    points_flat: &[f64],
+    triangles: &[u32],
+    precision: u32,
+) -> Result<String> {
+    let points = parse_points(points_flat)?;
+    check_triangles(triangles, points.len())?;
+    let mut path = PathWriter::new(precision)?;
+    for t in triangles.chunks_exact(3) {
+        for &v in t {
+            path.line_to(points[v as usize]);
+        }
+        path.finish(true);
+    }
+    Ok(path.d)
+}
+
+/// Path data with one closed subpath per cell polygon
+///
+/// Takes `vertices` and `offsets` in the layout `compute_voronoi` and
+/// `compute_power_diagram` return; empty cells write nothing. Drawn with
+/// `fill-rule="nonzero"` or `"evenodd"` alike, since cells never overlap.
+#[wasm_bindgen]
+pub fn voronoi_to_svg(vertices: &[f64], offsets: &[u32], precision: u32) -> Result<String> {
+    let points = parse_points(vertices)?;
+    if offsets.last().is_none_or(|&end| end as usize != points.len()) {
+        return Err(Error::InvalidLength(format!(
+            "offsets must end at the vertex count {}",
+            points.len()
+        )));
+    }
+    if offsets.windows(2).any(|w| w[0] > w[1]) {
+        return Err(Error::InvalidArgument("offsets must be non-decreasing".into()));
+    }
+    let mut path = PathWriter::new(precision)?;
+    for w in offsets.windows(2) {
+        for &p in &points[w[0] as usize..w[1] as usize] {
+            path.line_to(p);
+        }
+        path.finish(true);
+    }
+    Ok(path.d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::{compute_delaunay, compute_edge_indices, generate_golden_seeds};
+
+    /// Subpaths of `d` as their vertices and whether each was closed
+    fn parse(d: &str) -> Vec<(Vec<Point>, bool)> {
+        let mut subpaths: Vec<(Vec<Point>, bool)> = Vec::new();
+        let mut rest = d;
+        while let Some(command) = rest.chars().next() {
+            let end = rest[1..].find(['M', 'L', 'Z']).map_or(rest.len(), |i| i + 1);
+            let words = rest[1..end].split(' ').filter(|s| !s.is_empty());
+            let args: Vec<f64> = words.map(|s| s.parse().unwrap()).collect();
+            match command {
+                'M' => subpaths.push((vec![Point::new(args[0], args[1])], false)),
+                'L' => subpaths.last_mut().unwrap().0.push(Point::new(args[0], args[1])),
+                'Z' => subpaths.last_mut().unwrap().1 = true,
+                _ => panic!("unexpected command {command}"),
+            }
+            assert_eq!(args.len(), if command == 'Z' { 0 } else { 2 });
+            rest = &rest[end..];
+        }
+        subpaths
+    }
+
+    fn near(p: Point, x: f64, y: f64, precision: i32) -> bool {
+        let tolerance = 0.5 * 10f64.powi(-precision) + 1e-9;
+        (p.x - x).abs() <= tolerance && (p.y - y).abs() <= tolerance
+    }
+
+    #[test]
+    fn test_paths_round_trip_edges_and_triangles() {
+        let points = generate_golden_seeds(640.0, 480.0, 150);
+        let triangles = compute_delaunay(&points, 640.0, 480.0);
+        let d = triangles_to_svg_path(&points, &triangles, 2).unwrap();
+        let subpaths = parse(&d);
+        assert_eq!(subpaths.len(), triangles.len() / 3);
+        for ((poly, closed), t) in subpaths.iter().zip(triangles.chunks(3)) {
+            assert!(*closed);
+            assert_eq!(poly.len(), 3);
+            for (&p, &v) in poly.iter().zip(t) {
+                assert!(near(p, points[2 * v as usize], points[2 * v as usize + 1], 2));
+            }
+        }
+
+        let edges = compute_edge_indices(&triangles);
+        let subpaths = parse(&edges_to_svg_path(&points, &edges, 3).unwrap());
+        let vertices: usize = subpaths.iter().map(|(poly, _)| poly.len()).sum();
+        assert!(subpaths.iter().all(|(poly, closed)| poly.len() >= 2 && !closed));
+        assert_eq!(vertices - subpaths.len(), edges.len() / 2);
+
+        // A chain continues one subpath, and the straight-through vertex
+        // 1 and the fold back at 3 are handled as drawn
+        let line = [0.0, 0.0, 5.0, 0.0, 10.0, 0.0, 10.0, 5.0, 2.5, -1.25, 0.0, 0.0];
+        let d = edges_to_svg_path(&line, &[0, 1, 1, 2, 2, 3, 3, 2, 5, 4], 2).unwrap();
+        assert_eq!(d, "M0 0L10 0L10 5L10 0M0 0L2.5 -1.25");
+        assert_eq!(edges_to_svg_path(&line, &[0, 1], 0).unwrap(), "M0 0L5 0");
+        assert_eq!(edges_to_svg_path(&[-0.001, 1.0 / 3.0], &[0, 0], 2).unwrap(), "M0 0.33L0 0.33");
+        assert_eq!(edges_to_svg_path(&line, &[], 2).unwrap(), "");
+
+        let edges = |p: &[f64], e: &[u32], precision| code_of(edges_to_svg_path(p, e, precision));
+        assert_eq!(edges(&line, &[0, 1, 2], 2), ErrorCode::InvalidLength);
+        assert_eq!(edges(&line, &[0, 6], 2), ErrorCode::IndexOutOfRange);
+        assert_eq!(edges(&[0.0, f64::NAN], &[], 2), ErrorCode::NonFiniteInput);
+        assert_eq!(edges(&line, &[0, 1], 11), ErrorCode::InvalidArgument);
+        let bad = triangles_to_svg_path(&line, &[0, 1, 7], 2);
+        assert_eq!(code_of(bad), ErrorCode::IndexOutOfRange);
+    }
+
+    #[cfg(feature = "voronoi")]
+    #[test]
+    fn test_cells_round_trip() {
+        let mut points = generate_golden_seeds(400.0, 300.0, 60);
+        // A duplicate seed gets an empty cell, which writes nothing
+        points.extend([points[0], points[1]]);
+        let cells = crate::compute_voronoi(&points, 400.0, 300.0).unwrap();
+        let (vertices, offsets) = (cells.vertices(), cells.offsets());
+        let subpaths = parse(&voronoi_to_svg(&vertices, &offsets, 4).unwrap());
+        let non_empty: Vec<&[u32]> = offsets.windows(2).filter(|w| w[0] < w[1]).collect();
+        assert_eq!(subpaths.len(), non_empty.len());
+        assert_eq!(non_empty.len(), 60);
+        for ((poly, closed), w) in subpaths.iter().zip(non_empty) {
+            assert!(*closed);
+            // Cells are convex, so no vertex is straight-through
+            assert_eq!(poly.len(), (w[1] - w[0]) as usize);
+            let first = w[0] as usize;
+            assert!(near(poly[0], vertices[2 * first], vertices[2 * first + 1], 4));
+        }
+
+        let square = [0.0, 0.0, 5.0, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0];
+        assert_eq!(voronoi_to_svg(&square, &[0, 0, 5], 1).unwrap(), "M0 0L10 0L10 10L0 10Z");
+        let cells = |offsets: &[u32]| code_of(voronoi_to_svg(&square, offsets, 1));
+        assert_eq!(cells(&[]), ErrorCode::InvalidLength);
+        assert_eq!(cells(&[0, 4]), ErrorCode::InvalidLength);
+        assert_eq!(cells(&[0, 3, 2, 5]), ErrorCode::InvalidArgument);
+    }
+}
@@ -0,0 +1,514 @@
+//! Tiled triangulation for renders too large to triangulate at once
+//!
+//! `TileStream` walks the canvas in row-major tiles. For each tile it
+//! triangulates only the seeds within the tile plus a margin ring, emits
+//! the triangles and edges that tile owns, and drops the mesh before the
+//! next tile, so the working set follows the tile size, not the seed
+//! count.
+//!
+//! The global triangulation is that of the seeds plus a frame of guard
+//! points one seed spacing outside the canvas. The frame keeps every
+//! circumcircle local: without it, the long slivers along the hull
+//! depend on seeds anywhere on the same side of the canvas, and border
+//! tiles would need a margin the length of that side. Triangles touching
+//! a guard are not emitted. The rest is a subset of `compute_delaunay`:
+//! everything but the hull slivers whose circumcircle reaches the frame.
+//!
+//! A local triangle is global when no point outside the gathered region
+//! falls inside its circumcircle, which is checked directly against the
+//! bucketed points. A tile is accepted once every local triangle
+//! overlapping it passes. Those triangles then cover the tile, so they
+//! are exactly the global triangles there; otherwise the margin is
+//! doubled and the tile redone. An edge belongs to the tile holding its
+//! midpoint and a triangle to the tile holding its centroid (tiles are
+//! half-open, the last row and column closed), so every edge and triangle
+//! is emitted exactly once. This holds for seeds in general position;
+//! cocircular ties may be broken differently per tile.
+
+use wasm_bindgen::prelude::*;
+
+use crate::centers::circumcircle;
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::mesh::{Mesh, SUPER};
+use crate::predicates::orient2d;
+use crate::Point;
+
+/// Tiles a stream may cut the canvas into
+const MAX_TILES: f64 = (1u32 << 20) as f64;
+
+/// Slack on the circumcircle test, relative to the radius, so rounding in
+/// the circumcenter never accepts a triangle it should not
+const RADIUS_SLACK: f64 = 1e-9;
+
+/// Axis-aligned `[x0, y0, x1, y1]`
+type Rect = [f64; 4];
+
+/// One tile's share of the global triangulation
+#[wasm_bindgen]
+pub struct TileResult {
+    index: u32,
+    rect: Rect,
+    triangles: Vec<u32>,
+    edges: Vec<u32>,
+    margin: f64,
+    local_points: u32,
+}
+
+#[wasm_bindgen]
+impl TileResult {
+    /// Row-major tile number
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Tile rectangle `[x0, y0, x1, y1]`, clamped to the canvas
+    pub fn rect(&self) -> Vec<f64> {
+        self.rect.to_vec()
+    }
+
+    /// Owned triangles as global seed indices, three per triangle
+    pub fn triangles(&self) -> Vec<u32> {
+        self.triangles.clone()
+    }
+
+    /// Owned edges of the emitted triangles as global `(low, high)` seed
+    /// index pairs, ascending
+    pub fn edges(&self) -> Vec<u32> {
+        self.edges.clone()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len() / 3
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len() / 2
+    }
+
+    /// Margin the tile settled on after any doubling
+    pub fn margin(&self) -> f64 {
+        self.margin
+    }
+
+    /// Seeds triangulated for this tile, margin included
+    pub fn local_points(&self) -> u32 {
+        self.local_points
+    }
+}
+
+/// Iterator over the tiles of one large triangulation
+#[wasm_bindgen]
+pub struct TileStream {
+    /// The seeds followed by the guard frame
+    points: Vec<Point>,
+    seeds: u32,
+    /// Rectangle the guards sit on
+    frame: Rect,
+    guard_spacing: f64,
+    /// Point ids bucketed by tile, guards by the nearest one:
+    /// `buckets[offsets[c]..offsets[c + 1]]` for tile cell `c`, ascending
+    buckets: Vec<u32>,
+    offsets: Vec<u32>,
+    cols: u32,
+    rows: u32,
+    tile_size: f64,
+    width: f64,
+    height: f64,
+    margin: f64,
+    next: u32,
+    peak_points: u32,
+    peak_triangles: u32,
+}
+
+#[wasm_bindgen]
+impl TileStream {
+    /// Stream the triangulation of `points` in `tile_size` tiles, starting
+    /// each tile with `margin` of context on every side
+    ///
+    /// A margin of two or three seed spacings is usually enough; tiles
+    /// whose neighbourhood is sparser grow it on their own. Every seed
+    /// must lie on the `width × height` canvas. The guard spacing is the
+    /// mean seed spacing, capped at the tile size.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        points: &[f64],
+        width: f64,
+        height: f64,
+        tile_size: f64,
+        margin: f64,
+    ) -> Result<TileStream> {
+        check_points(points)?;
+        if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+            return Err(Error::InvalidArgument("width and height must be positive".into()));
+        }
+        if !(tile_size.is_finite() && tile_size > 0.0) {
+            return Err(Error::InvalidArgument(format!(
+                "tile size must be positive, got {tile_size}"
+            )));
+        }
+        if !(margin.is_finite() && margin >= 0.0) {
+            return Err(Error::InvalidArgument(format!(
+                "margin must be non-negative, got {margin}"
+            )));
+        }
+        let (cols, rows) = ((width / tile_size).ceil(), (height / tile_size).ceil());
+        if cols * rows > MAX_TILES {
+            return Err(Error::TooLarge(format!("a {cols}x{rows} tile grid is too fine")));
+        }
+        let (cols, rows) = (cols as u32, rows as u32);
+        let mut points: Vec<Point> =
+            points.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+        // The range checks also reject NaN
+        if let Some(i) = points
+            .iter()
+            .position(|p| !(0.0..=width).contains(&p.x) || !(0.0..=height).contains(&p.y))
+        {
+            return Err(Error::InvalidArgument(format!("seed {i} lies off the canvas")));
+        }
+        let seeds = points.len() as u32;
+        let guard_spacing = (width * height / points.len().max(1) as f64).sqrt().min(tile_size);
+        let frame = [-guard_spacing, -guard_spacing, width + guard_spacing, height + guard_spacing];
+        let c = corners(frame);
+        for k in 0..4 {
+            // Each side up to, not including, the next corner
+            let (a, b) = (c[k], c[(k + 1) % 4]);
+            let steps = ((b.x - a.x).abs().max((b.y - a.y).abs()) / guard_spacing).ceil();
+            for i in 0..steps as usize {
+                let t = i as f64 / steps;
+                points.push(Point::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t));
+            }
+        }
+
+        let mut stream = TileStream {
+            points,
+            seeds,
+            frame,
+            guard_spacing,
+            buckets: Vec::new(),
+            offsets: vec![0; (cols * rows) as usize + 1],
+            cols,
+            rows,
+            tile_size,
+            width,
+            height,
+            margin,
+            next: 0,
+            peak_points: 0,
+            peak_triangles: 0,
+        };
+        let cells: Vec<u32> = stream.points.iter().map(|&p| stream.cell_of(p)).collect();
+        for &c in &cells {
+            stream.offsets[c as usize + 1] += 1;
+        }
+        for c in 0..(cols * rows) as usize {
+            stream.offsets[c + 1] += stream.offsets[c];
+        }
+        let mut fill = stream.offsets.clone();
+        stream.buckets = vec![0; cells.len()];
+        for (i, &c) in cells.iter().enumerate() {
+            stream.buckets[fill[c as usize] as usize] = i as u32;
+            fill[c as usize] += 1;
+        }
+        Ok(stream)
+    }
+
+    pub fn tile_count(&self) -> u32 {
+        self.cols * self.rows
+    }
+
+    /// The next tile, or `None` once every tile has been emitted
+    pub fn next_tile(&mut self) -> Option<TileResult> {
+        if self.next >= self.tile_count() {
+            return None;
+        }
+        let index = self.next;
+        self.next += 1;
+        Some(self.tile(index))
+    }
+
+    /// Distance between guards, and from the frame to the canvas
+    pub fn guard_spacing(&self) -> f64 {
+        self.guard_spacing
+    }
+
+    /// Start over from the first tile
+    pub fn reset(&mut self) {
+        self.next = 0;
+    }
+
+    /// Most points, guards included, any tile so far has triangulated at
+    /// once
+    pub fn peak_tile_points(&self) -> u32 {
+        self.peak_points
+    }
+
+    /// Most mesh triangles any tile so far has held at once, super
+    /// triangle included
+    pub fn peak_tile_triangles(&self) -> u32 {
+        self.peak_triangles
+    }
+}
+
+impl TileStream {
+    /// Tile cell of a point, clamped to the grid; the last row and column
+    /// are closed
+    fn cell_of(&self, p: Point) -> u32 {
+        let col = ((p.x / self.tile_size) as u32).min(self.cols - 1);
+        let row = ((p.y / self.tile_size) as u32).min(self.rows - 1);
+        row * self.cols + col
+    }
+
+    fn tile_rect(&self, index: u32) -> Rect {
+        let (col, row) = ((index % self.cols) as f64, (index / self.cols) as f64);
+        let s = self.tile_size;
+        [
+            col * s,
+            row * s,
+            ((col + 1.0) * s).min(self.width),
+            ((row + 1.0) * s).min(self.height),
+        ]
+    }
+
+    /// Ascending ids of the points inside `region`
+    fn gather(&self, region: Rect) -> Vec<u32> {
+        let s = self.tile_size;
+        let col = |x: f64| ((x / s) as u32).min(self.cols - 1);
+        let row = |y: f64| ((y / s) as u32).min(self.rows - 1);
+        let mut ids = Vec::new();
+        for r in row(region[1])..=row(region[3]) {
+            for c in col(region[0])..=col(region[2]) {
+                let cell = (r * self.cols + c) as usize;
+                let span = self.offsets[cell] as usize..self.offsets[cell + 1] as usize;
+                let bucket = self.buckets[span].iter().copied();
+                ids.extend(bucket.filter(|&i| inside(self.points[i as usize], region)));
+            }
+        }
+        ids.sort_unstable();
+        ids
+    }
+
+    fn tile(&mut self, index: u32) -> TileResult {
+        let rect = self.tile_rect(index);
+        let frame = self.frame;
+        let mut margin = self.margin;
+        loop {
+            let region = [
+                (rect[0] - margin).max(frame[0]),
+                (rect[1] - margin).max(frame[1]),
+                (rect[2] + margin).min(frame[2]),
+                (rect[3] + margin).min(frame[3]),
+            ];
+            let ids = self.gather(region);
+            let local: Vec<Point> = ids.iter().map(|&i| self.points[i as usize]).collect();
+            let mesh = Mesh::build(&local, Some(frame));
+            if region == frame || self.settled(&mesh, rect, region) {
+                self.peak_points = self.peak_points.max(ids.len() as u32);
+                self.peak_triangles = self.peak_triangles.max(mesh.tris.len() as u32);
+                return self.emit(index, rect, margin, &mesh, &ids);
+            }
+            // Doubling from zero needs a start; one tile is a natural unit
+            margin = if margin > 0.0 { margin * 2.0 } else { self.tile_size };
+        }
+    }
+
+    /// Whether every local triangle overlapping `rect` is global, given
+    /// that `region` holds every point within it
+    fn settled(&self, mesh: &Mesh, rect: Rect, region: Rect) -> bool {
+        (0..mesh.tris.len()).filter(|&t| mesh.alive[t]).all(|t| {
+            let [a, b, c] = mesh.tris[t].map(|v| mesh.points[v as usize]);
+            if !overlaps(a, b, c, rect) {
+                return true;
+            }
+            let (center, r2) = circumcircle(a, b, c);
+            self.disk_is_clear(center, r2.sqrt() * (1.0 + RADIUS_SLACK), region)
+        })
+    }
+
+    /// Whether no point outside `region` lies within `r` of `center`
+    ///
+    /// Reads the buckets under the disk without building anything, so
+    /// checking a large circle costs time but no memory.
+    fn disk_is_clear(&self, center: Point, r: f64, region: Rect) -> bool {
+        let frame = self.frame;
+        let (x0, y0) = ((center.x - r).max(frame[0]), (center.y - r).max(frame[1]));
+        let (x1, y1) = ((center.x + r).min(frame[2]), (center.y + r).min(frame[3]));
+        if x0 > x1 || y0 > y1 {
+            return true;
+        }
+        let s = self.tile_size;
+        let col = |x: f64| ((x / s) as u32).min(self.cols - 1);
+        let row = |y: f64| ((y / s) as u32).min(self.rows - 1);
+        for rw in row(y0)..=row(y1) {
+            for cl in col(x0)..=col(x1) {
+                let cell = (rw * self.cols + cl) as usize;
+                let span = self.offsets[cell] as usize..self.offsets[cell + 1] as usize;
+                let hit = self.buckets[span].iter().any(|&i| {
+                    let p = self.points[i as usize];
+                    !inside(p, region) && (p.x - center.x).hypot(p.y - center.y) < r
+                });
+                if hit {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn emit(&self, index: u32, rect: Rect, margin: f64, mesh: &Mesh, ids: &[u32]) -> TileResult {
+        let global = |v: u32| ids[(v - SUPER) as usize];
+        let at = |v: u32| mesh.points[v as usize];
+        let (mut triangles, mut edges) = (Vec::new(), Vec::new());
+        for t in (0..mesh.tris.len()).filter(|&t| mesh.is_real_triangle(t)) {
+            let tv = mesh.tris[t];
+            if tv.iter().any(|&v| global(v) >= self.seeds) {
+                continue;
+            }
+            let [pa, pb, pc] = tv.map(at);
+            let centroid = Point::new((pa.x + pb.x + pc.x) / 3.0, (pa.y + pb.y + pc.y) / 3.0);
+            if self.cell_of(centroid) == index {
+                triangles.extend(tv.map(global));
+            }
+            for k in 0..3 {
+                let (a, b) = (tv[k], tv[(k + 1) % 3]);
+                let (pa, pb) = (at(a), at(b));
+                if self.cell_of(Point::new((pa.x + pb.x) / 2.0, (pa.y + pb.y) / 2.0)) == index {
+                    let (ga, gb) = (global(a), global(b));
+                    edges.push((ga.min(gb), ga.max(gb)));
+                }
+            }
+        }
+        // Interior edges were seen from both sides
+        edges.sort_unstable();
+        edges.dedup();
+        let edges = edges.into_iter().flat_map(|(a, b)| [a, b]).collect();
+        TileResult { index, rect, triangles, edges, margin, local_points: ids.len() as u32 }
+    }
+}
+
+fn corners(r: Rect) -> [Point; 4] {
+    [
+        Point::new(r[0], r[1]),
+        Point::new(r[2], r[1]),
+        Point::new(r[2], r[3]),
+        Point::new(r[0], r[3]),
+    ]
+}
+
+fn inside(p: Point, r: Rect) -> bool {
+    p.x >= r[0] && p.x <= r[2] && p.y >= r[1] && p.y <= r[3]
+}
+
+/// Whether CCW triangle `abc` meets the closed rectangle `r`
+///
+/// Separating axes: the rectangle's two, then the triangle's three sides.
+fn overlaps(a: Point, b: Point, c: Point, r: Rect) -> bool {
+    if a.x.max(b.x).max(c.x) < r[0]
+        || a.x.min(b.x).min(c.x) > r[2]
+        || a.y.max(b.y).max(c.y) < r[1]
+        || a.y.min(b.y).min(c.y) > r[3]
+    {
+        return false;
+    }
+    let corners = corners(r);
+    [(a, b), (b, c), (c, a)]
+        .iter()
+        .all(|&(p, q)| corners.iter().any(|&k| orient2d(p, q, k) >= 0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::compute_delaunay;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+
+    fn canonical(tri: &[u32]) -> [u32; 3] {
+        // Rotate so the smallest index leads, keeping the winding
+        let k = (0..3).min_by_key(|&k| tri[k]).unwrap();
+        [tri[k], tri[(k + 1) % 3], tri[(k + 2) % 3]]
+    }
+
+    fn stream_all(stream: &mut TileStream) -> (Vec<[u32; 3]>, Vec<(u32, u32)>) {
+        let (mut triangles, mut edges) = (Vec::new(), Vec::new());
+        while let Some(tile) = stream.next_tile() {
+            triangles.extend(tile.triangles().chunks(3).map(canonical));
+            edges.extend(tile.edges().chunks(2).map(|e| (e[0], e[1])));
+        }
+        (triangles, edges)
+    }
+
+    /// Seed triangles of the whole framed point set, triangulated at once
+    fn reference(stream: &TileStream) -> BTreeSet<[u32; 3]> {
+        let mesh = Mesh::build(&stream.points, Some(stream.frame));
+        let seeds = stream.seeds;
+        mesh.real_triangles()
+            .filter(|t| t.iter().all(|&v| v < seeds))
+            .map(|t| canonical(&t))
+            .collect()
+    }
+
+    fn edges_of(triangles: &BTreeSet<[u32; 3]>) -> BTreeSet<(u32, u32)> {
+        triangles
+            .iter()
+            .flat_map(|t| [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])])
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect()
+    }
+
+    #[test]
+    fn test_tiles_reassemble_the_global_triangulation() {
+        let (w, h) = (2000.0, 1600.0);
+        let mut rng = Rng::new(21);
+        let points: Vec<f64> =
+            (0..20_000).flat_map(|_| [rng.range(0.0, w), rng.range(0.0, h)]).collect();
+        // Deliberately thin margins, so some tiles have to grow theirs
+        let mut stream = TileStream::new(&points, w, h, 200.0, 20.0).unwrap();
+        assert_eq!(stream.tile_count(), 10 * 8);
+        let expected = reference(&stream);
+        let (triangles, edges) = stream_all(&mut stream);
+        // Each item exactly once: no duplicates and nothing missing
+        assert_eq!(triangles.len(), expected.len());
+        assert_eq!(triangles.iter().copied().collect::<BTreeSet<_>>(), expected);
+        let expected_edges = edges_of(&expected);
+        assert_eq!(edges.len(), expected_edges.len());
+        assert_eq!(edges.iter().copied().collect::<BTreeSet<_>>(), expected_edges);
+
+        // The frame only drops hull slivers of the unframed triangulation
+        let plain: BTreeSet<[u32; 3]> =
+            compute_delaunay(&points, w, h).chunks(3).map(canonical).collect();
+        assert!(expected.is_subset(&plain));
+        assert!(expected.len() * 100 > plain.len() * 98, "{} of {}", expected.len(), plain.len());
+
+        // The working set stays proportional to a tile
+        assert!(stream.peak_tile_points() < 20_000 / 10, "{}", stream.peak_tile_points());
+        assert!(stream.next_tile().is_none());
+        stream.reset();
+        assert_eq!(stream.next_tile().unwrap().index(), 0);
+    }
+
+    #[test]
+    fn test_sparse_canvas_and_errors() {
+        // Three seeds on a large canvas force margins up to the whole frame
+        let points = [100.0, 100.0, 900.0, 150.0, 500.0, 800.0];
+        let mut stream = TileStream::new(&points, 1000.0, 1000.0, 100.0, 0.0).unwrap();
+        let expected = reference(&stream);
+        let (triangles, edges) = stream_all(&mut stream);
+        assert_eq!(triangles.iter().copied().collect::<BTreeSet<_>>(), expected);
+        assert_eq!(edges.len(), edges_of(&expected).len());
+        let mut empty = TileStream::new(&[], 10.0, 10.0, 4.0, 1.0).unwrap();
+        assert_eq!(empty.tile_count(), 9);
+        assert!(stream_all(&mut empty).0.is_empty());
+
+        let new = |pts: &[f64], tile: f64, margin: f64| {
+            code_of(TileStream::new(pts, 1000.0, 1000.0, tile, margin))
+        };
+        assert_eq!(new(&points[..5], 100.0, 0.0), ErrorCode::InvalidLength);
+        assert_eq!(new(&[1.0, 1001.0], 100.0, 0.0), ErrorCode::InvalidArgument);
+        assert_eq!(new(&points, 0.0, 0.0), ErrorCode::InvalidArgument);
+        assert_eq!(new(&points, 100.0, -1.0), ErrorCode::InvalidArgument);
+        assert_eq!(new(&points, 1e-3, 0.0), ErrorCode::TooLarge);
+    }
+}
@@ -0,0 +1,263 @@
+//! Rectangular windows onto one scene for multi-canvas layouts
+//!
+//! A view keeps the scene's seed ids, so neighbouring canvases agree on
+//! identity. Edges are clipped from their canonical endpoints with the
+//! clipped coordinate snapped to the boundary. Two views whose rectangles
+//! share a side therefore compute bit-identical crossing points on it, as
+//! long as one view's `x + w` (or `y + h`) is the same `f64` as the other's
+//! `x` (or `y`).
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::mesh::NONE;
+use crate::predicates::orient2d;
+use crate::scene::Scene;
+use crate::Point;
+
+/// Scene geometry intersecting one view rectangle, in view-local pixels
+#[wasm_bindgen]
+pub struct FrameResult {
+    seed_ids: Vec<u32>,
+    positions: Vec<f64>,
+    triangles: Vec<u32>,
+    segments: Vec<f64>,
+    segment_slots: Vec<u32>,
+    motes: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl FrameResult {
+    /// Scene seed id of each local seed, ascending
+    pub fn seed_ids(&self) -> Vec<u32> {
+        self.seed_ids.clone()
+    }
+
+    /// Local seed positions `[x, y, ...]`, aligned with `seed_ids()`; seeds
+    /// of triangles straddling the border lie outside the view
+    pub fn positions(&self) -> Vec<f64> {
+        self.positions.clone()
+    }
+
+    /// Triangles touching the view, as indices into `seed_ids()`
+    pub fn triangles(&self) -> Vec<u32> {
+        self.triangles.clone()
+    }
+
+    /// Edges clipped to the view as `[x0, y0, x1, y1, ...]`
+    pub fn segments(&self) -> Vec<f64> {
+        self.segments.clone()
+    }
+
+    /// Scene edge slot of each segment, aligned with `segments()` and
+    /// indexing `Scene::edge_heat`
+    pub fn segment_slots(&self) -> Vec<u32> {
+        self.segment_slots.clone()
+    }
+
+    /// Motes inside the view as `[x, y, brightness, ...]`
+    pub fn motes(&self) -> Vec<f32> {
+        self.motes.clone()
+    }
+
+    pub fn seed_count(&self) -> usize {
+        self.seed_ids.len()
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segment_slots.len()
+    }
+}
+
+/// View rectangle `[x0, y0, x1, y1]`
+type Rect = [f64; 4];
+
+/// Point where `p → q` crosses side `side` of `rect` (0 left, 1 right,
+/// 2 top, 3 bottom), with the crossed coordinate set exactly
+fn crossing(p: Point, q: Point, rect: &Rect, side: usize) -> Point {
+    let c = rect[[0, 2, 1, 3][side]];
+    if side < 2 {
+        Point { x: c, y: p.y + (c - p.x) * (q.y - p.y) / (q.x - p.x) }
+    } else {
+        Point { x: p.x + (c - p.y) * (q.x - p.x) / (q.y - p.y), y: c }
+    }
+}
+
+/// Part of segment `a–b` inside `rect`, `None` if it misses or only
+/// touches it
+///
+/// Liang–Barsky picks the sides each end is clipped at; the points
+/// themselves come from `crossing` on the endpoints in lexicographic order,
+/// so the result does not depend on which view asks or on the edge's
+/// direction.
+pub(crate) fn clip_segment(a: Point, b: Point, rect: &Rect) -> Option<(Point, Point)> {
+    let swap = (b.x, b.y) < (a.x, a.y);
+    let (p, q) = if swap { (b, a) } else { (a, b) };
+    let (dx, dy) = (q.x - p.x, q.y - p.y);
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    let (mut enter, mut exit) = (None, None);
+    let sides = [
+        (-dx, p.x - rect[0]),
+        (dx, rect[2] - p.x),
+        (-dy, p.y - rect[1]),
+        (dy, rect[3] - p.y),
+    ];
+    for (side, &(den, num)) in sides.iter().enumerate() {
+        if den == 0.0 {
+            if num < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = num / den;
+        if den < 0.0 {
+            if t > t0 {
+                t0 = t;
+                enter = Some(side);
+            }
+        } else if t < t1 {
+            t1 = t;
+            exit = Some(side);
+        }
+    }
+    if t0 >= t1 {
+        return None;
+    }
+    let start = enter.map_or(p, |s| crossing(p, q, rect, s));
+    let end = exit.map_or(q, |s| crossing(p, q, rect, s));
+    Some(if swap { (end, start) } else { (start, end) })
+}
+
+#[wasm_bindgen]
+impl Scene {
+    /// Geometry of the `w × h` window at `(x, y)`, translated so the window
+    /// origin is `(0, 0)`
+    ///
+    /// Triangles are kept when they overlap the window, edges are clipped
+    /// to it, and motes are kept inside `[x, x + w) × [y, y + h)`, so a mote
+    /// on a shared side shows up in exactly one of two adjacent views.
+    pub fn extract_view(&self, x: f64, y: f64, w: f64, h: f64) -> Result<FrameResult> {
+        if !(x.is_finite() && y.is_finite()) {
+            return Err(Error::NonFiniteInput("view origin must be finite".into()));
+        }
+        if !(w.is_finite() && h.is_finite() && w > 0.0 && h > 0.0) {
+            return Err(Error::InvalidArgument("width and height must be positive".into()));
+        }
+        let rect = [x, y, x + w, y + h];
+        let point = |v: u32| Point {
+            x: self.positions[v as usize * 2],
+            y: self.positions[v as usize * 2 + 1],
+        };
+        let local = |p: Point| [p.x - x, p.y - y];
+
+        let mut segments = Vec::new();
+        let mut segment_slots = Vec::new();
+        for (slot, &(a, b)) in self.tri.edges.slots.iter().enumerate() {
+            if a == NONE {
+                continue;
+            }
+            if let Some((p, q)) = clip_segment(point(a), point(b), &rect) {
+                segments.extend(local(p).into_iter().chain(local(q)));
+                segment_slots.push(slot as u32);
+            }
+        }
+
+        let all = self.tri.triangles();
+        let mut kept = Vec::new();
+        for t in all.chunks_exact(3) {
+            let [a, b, c] = [point(t[0]), point(t[1]), point(t[2])];
+            let touches = [(a, b), (b, c), (c, a)]
+                .iter()
+                .any(|&(p, q)| clip_segment(p, q, &rect).is_some())
+                || contains(a, b, c, Point { x, y });
+            if touches {
+                kept.extend_from_slice(t);
+            }
+        }
+        let mut seed_ids = kept.clone();
+        seed_ids.sort_unstable();
+        seed_ids.dedup();
+        let mut index = vec![NONE; self.seed_count()];
+        for (i, &s) in seed_ids.iter().enumerate() {
+            index[s as usize] = i as u32;
+        }
+        let triangles = kept.iter().map(|&v| index[v as usize]).collect();
+        let positions = seed_ids.iter().flat_map(|&s| local(point(s))).collect();
+
+        let motes = self
+            .mote_render()
+            .filter(|m| m[0] >= rect[0] && m[0] < rect[2] && m[1] >= rect[1] && m[1] < rect[3])
+            .flat_map(|m| [(m[0] - x) as f32, (m[1] - y) as f32, m[2] as f32])
+            .collect();
+
+        Ok(FrameResult { seed_ids, positions, triangles, segments, segment_slots, motes })
+    }
+}
+
+/// Whether `p` lies inside or on triangle `abc`
+fn contains(a: Point, b: Point, c: Point, p: Point) -> bool {
+    let (d0, d1, d2) = (orient2d(a, b, p), orient2d(b, c, p), orient2d(c, a, p));
+    (d0 >= 0.0 && d1 >= 0.0 && d2 >= 0.0) || (d0 <= 0.0 && d1 <= 0.0 && d2 <= 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+
+    #[test]
+    fn test_stacked_views_join_without_a_seam() {
+        let mut scene = Scene::new(400.0, 300.0, 300, 100, 9).unwrap();
+        for _ in 0..10 {
+            scene.update(1.0 / 30.0).unwrap();
+        }
+        let header = scene.extract_view(0.0, 0.0, 400.0, 100.0).unwrap();
+        let body = scene.extract_view(0.0, 100.0, 400.0, 200.0).unwrap();
+
+        // (slot, x) of every segment end on the shared line y = 100
+        let seam = |view: &FrameResult, local_y: f64| {
+            let mut ends: Vec<(u32, u64)> = Vec::new();
+            for (seg, &slot) in view.segments().chunks(4).zip(&view.segment_slots()) {
+                for end in seg.chunks(2).filter(|e| e[1] == local_y) {
+                    ends.push((slot, end[0].to_bits()));
+                }
+            }
+            ends.sort_unstable();
+            ends
+        };
+        let (top, bottom) = (seam(&header, 100.0), seam(&body, 0.0));
+        assert!(top.len() > 5);
+        assert_eq!(top, bottom);
+
+        // A seed in both views is the same seed at the same place
+        let (hp, bp) = (header.positions(), body.positions());
+        let mut shared = 0;
+        for (i, id) in header.seed_ids().iter().enumerate() {
+            if let Ok(j) = body.seed_ids().binary_search(id) {
+                assert_eq!(hp[i * 2 + 1] - 100.0, bp[j * 2 + 1]);
+                shared += 1;
+            }
+        }
+        assert!(shared > 0);
+        for view in [&header, &body] {
+            assert!(view.triangles().iter().all(|&v| (v as usize) < view.seed_count()));
+        }
+        assert_eq!(header.motes().len() + body.motes().len(), scene.mote_positions().len());
+        assert_eq!(code_of(scene.extract_view(0.0, 0.0, 0.0, 1.0)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(scene.extract_view(f64::NAN, 0.0, 1.0, 1.0)), ErrorCode::NonFiniteInput);
+    }
+
+    #[test]
+    fn test_clip_ignores_edge_direction() {
+        let rect = [0.0, 0.0, 10.0, 10.0];
+        let pt = |x, y| Point { x, y };
+        let (a, b) = (pt(-3.1, 4.7), pt(13.3, 7.9));
+        let (p, q) = clip_segment(a, b, &rect).unwrap();
+        let (q2, p2) = clip_segment(b, a, &rect).unwrap();
+        assert_eq!((p, q), (p2, q2));
+        assert_eq!((p.x, q.x), (0.0, 10.0));
+        // Touching a corner or running outside yields nothing
+        assert!(clip_segment(pt(-1.0, 1.0), pt(1.0, -1.0), &rect).is_none());
+        assert!(clip_segment(pt(-1.0, 0.0), pt(-1.0, 5.0), &rect).is_none());
+    }
+}
@@ -0,0 +1,712 @@
+//! Voronoi cells clipped to the canvas
+//!
+//! Each cell starts as the canvas rectangle and is cut by the
+//! perpendicular bisector towards every Delaunay neighbor, one half-plane
+//! at a time (Sutherland–Hodgman). Clipping a convex polygon by a
+//! half-plane yields a convex polygon with the same winding, so every
+//! non-empty cell is convex, simple and counter-clockwise by construction;
+//! no circumcenter ordering is involved.
+//!
+//! Output format, shared by every cell-polygon function in the crate:
+//! `vertices` is `[x0, y0, x1, y1, ...]` and `offsets` has one entry per
+//! seed plus one, in vertex units, so cell `i` is vertices
+//! `offsets[i]..offsets[i + 1]`. Polygons are closed implicitly (the first
+//! vertex is not repeated). A seed whose cell clips to fewer than three
+//! distinct vertices, or to zero area, gets an empty range; this is also
+//! the case for a duplicate of an earlier seed and for seeds whose cell
+//! lies entirely off canvas.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::{check_points, Csr};
+use crate::mesh::{Mesh, NONE, SUPER};
+use crate::predicates::signed_area2;
+use crate::{Point, VoronoiDiagram};
+
+/// Clip a convex polygon to the half-plane `(p - origin) · normal <= 0`
+pub(crate) fn clip_half_plane(poly: &[Point], origin: Point, normal: Point, out: &mut Vec<Point>) {
+    out.clear();
+    let side = |p: Point| (p.x - origin.x) * normal.x + (p.y - origin.y) * normal.y;
+    for (i, &cur) in poly.iter().enumerate() {
+        let prev = poly[(i + poly.len() - 1) % poly.len()];
+        let (sp, sc) = (side(prev), side(cur));
+        if (sp <= 0.0) != (sc <= 0.0) {
+            let t = sp / (sp - sc);
+            out.push(Point {
+                x: prev.x + (cur.x - prev.x) * t,
+                y: prev.y + (cur.y - prev.y) * t,
+            });
+        }
+        if sc <= 0.0 {
+            out.push(cur);
+        }
+    }
+}
+
+/// Remove consecutive (and wrap-around) repeated vertices in place
+pub(crate) fn dedup_ring(poly: &mut Vec<Point>) {
+    poly.dedup();
+    while poly.len() > 1 && poly.first() == poly.last() {
+        poly.pop();
+    }
+}
+
+/// Clipped cells for `points` in the shared flat format
+///
+/// The canvas only clips; the mesh is sized from the points, with its
+/// hull closed so seeds off the canvas still bound their neighbours.
+pub(crate) fn voronoi_cells(points: &[Point], width: f64, height: f64) -> (Vec<f64>, Vec<u32>) {
+    let mesh = Mesh::build_closed(points);
+    mesh_cells(&mesh, width, height)
+}
+
+/// `voronoi_cells` for the real points of an existing Delaunay mesh, or
+/// the power cells of a weighted one
+pub(crate) fn mesh_cells(mesh: &Mesh, width: f64, height: f64) -> (Vec<f64>, Vec<u32>) {
+    let points = &mesh.points[SUPER as usize..];
+    let edges: Vec<(u32, u32)> = mesh
+        .real_edges()
+        .into_iter()
+        .map(|(a, b)| (a - SUPER, b - SUPER))
+        .collect();
+    let csr = Csr::from_edges(points.len(), &edges);
+    let rect = [
+        Point { x: 0.0, y: 0.0 },
+        Point { x: width, y: 0.0 },
+        Point { x: width, y: height },
+        Point { x: 0.0, y: height },
+    ];
+
+    let mut vertices = Vec::new();
+    let mut offsets = Vec::with_capacity(points.len() + 1);
+    offsets.push(0u32);
+    let (mut poly, mut scratch) = (Vec::new(), Vec::new());
+    for (i, &s) in points.iter().enumerate() {
+        poly.clear();
+        // Unlinked vertices are duplicates of an earlier seed
+        if mesh.vert_tri[i + SUPER as usize] != NONE {
+            poly.extend_from_slice(&rect);
+            for &j in csr.neighbors(i as u32) {
+                let q = points[j as usize];
+                let mut mid = Point { x: (s.x + q.x) * 0.5, y: (s.y + q.y) * 0.5 };
+                if !mesh.weights.is_empty() {
+                    // The radical axis: the bisector moved towards the
+                    // lighter seed
+                    let w = |v: usize| mesh.weights[v + SUPER as usize];
+                    let (dx, dy) = (q.x - s.x, q.y - s.y);
+                    let shift = (w(i) - w(j as usize)) / (2.0 * (dx * dx + dy * dy));
+                    mid = Point { x: mid.x + dx * shift, y: mid.y + dy * shift };
+                }
+                clip_half_plane(&poly, mid, Point { x: q.x - s.x, y: q.y - s.y }, &mut scratch);
+                std::mem::swap(&mut poly, &mut scratch);
+                if poly.is_empty() {
+                    break;
+                }
+            }
+            dedup_ring(&mut poly);
+        }
+        if poly.len() >= 3 && signed_area2(&poly) > 0.0 {
+            vertices.extend(poly.iter().flat_map(|p| [p.x, p.y]));
+        }
+        offsets.push((vertices.len() / 2) as u32);
+    }
+    (vertices, offsets)
+}
+
+/// Voronoi cell polygons, one (possibly empty) range per seed
+#[wasm_bindgen]
+pub struct VoronoiCells {
+    vertices: Vec<f64>,
+    offsets: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl VoronoiCells {
+    /// All cell vertices `[x0, y0, x1, y1, ...]`, counter-clockwise per cell
+    pub fn vertices(&self) -> Vec<f64> {
+        self.vertices.clone()
+    }
+
+    /// Per-seed start index into `vertices()` in vertex units, plus a final
+    /// end entry; an empty range marks a seed without a cell
+    pub fn offsets(&self) -> Vec<u32> {
+        self.offsets.clone()
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Polygon of seed `i` alone, `[x0, y0, x1, y1, ...]`, ready for a
+    /// canvas path; empty for a seed without a cell
+    pub fn cell(&self, i: usize) -> Result<Vec<f64>> {
+        if i >= self.cell_count() {
+            return Err(Error::IndexOutOfRange(format!(
+                "cell {i} out of range for {} cells",
+                self.cell_count()
+            )));
+        }
+        let (a, b) = (self.offsets[i] as usize, self.offsets[i + 1] as usize);
+        Ok(self.vertices[a * 2..b * 2].to_vec())
+    }
+}
+
+/// Voronoi diagram of `points_flat` clipped to `[0, width] × [0, height]`
+///
+/// Every non-empty cell is a simple, convex, counter-clockwise polygon;
+/// see the module docs for the output layout and the empty-cell sentinel.
+#[wasm_bindgen]
+pub fn compute_voronoi(points_flat: &[f64], width: f64, height: f64) -> Result<VoronoiCells> {
+    check_points(points_flat)?;
+    let points: Vec<Point> = points_flat
+        .chunks_exact(2)
+        .map(|c| Point::new(c[0], c[1]))
+        .collect();
+    let (vertices, offsets) = VoronoiDiagram::new(&points, width, height)?.into_flat();
+    Ok(VoronoiCells { vertices, offsets })
+}
+
+/// Area centroid and area of a CCW polygon `[x0, y0, ...]`, or `None`
+/// when it has no area
+pub(crate) fn centroid_and_area(poly: &[f64]) -> Option<(Point, f64)> {
+    let n = poly.len() / 2;
+    // Relative to the first vertex, so large canvas coordinates do not
+    // swamp the cross products
+    let (ox, oy) = (poly[0], poly[1]);
+    let (mut area2, mut cx, mut cy) = (0.0, 0.0, 0.0);
+    for i in 0..n {
+        let j = (i + 1) % n;
+        let (x0, y0) = (poly[2 * i] - ox, poly[2 * i + 1] - oy);
+        let (x1, y1) = (poly[2 * j] - ox, poly[2 * j + 1] - oy);
+        let cross = x0 * y1 - x1 * y0;
+        area2 += cross;
+        cx += (x0 + x1) * cross;
+        cy += (y0 + y1) * cross;
+    }
+    (area2 > 0.0).then(|| {
+        let c = Point::new(ox + cx / (3.0 * area2), oy + cy / (3.0 * area2));
+        (c, area2 / 2.0)
+    })
+}
+
+/// `[centroid_x, centroid_y, area, perimeter]` of every clipped cell of
+/// `compute_voronoi`, 4 entries per seed
+///
+/// Cells cut by the canvas edge report the clipped polygon, so the areas
+/// add up to `width × height`. A seed without a cell (see the module docs)
+/// reports its own position with zero area and perimeter.
+#[wasm_bindgen]
+pub fn voronoi_cell_metrics(points_flat: &[f64], width: f64, height: f64) -> Result<Vec<f64>> {
+    let cells = compute_voronoi(points_flat, width, height)?;
+    let mut metrics = Vec::with_capacity(points_flat.len() * 2);
+    for (i, w) in cells.offsets.windows(2).enumerate() {
+        let cell = &cells.vertices[w[0] as usize * 2..w[1] as usize * 2];
+        let Some((c, area)) = (cell.len() >= 6).then(|| centroid_and_area(cell)).flatten() else {
+            metrics.extend([points_flat[2 * i], points_flat[2 * i + 1], 0.0, 0.0]);
+            continue;
+        };
+        let n = cell.len() / 2;
+        let perimeter: f64 = (0..n)
+            .map(|k| {
+                let j = (k + 1) % n;
+                (cell[2 * j] - cell[2 * k]).hypot(cell[2 * j + 1] - cell[2 * k + 1])
+            })
+            .sum();
+        metrics.extend([c.x, c.y, area, perimeter]);
+    }
+    Ok(metrics)
+}
+
+/// Check cells in the `compute_voronoi` layout and return how many there are
+fn check_cells(cell_vertices: &[f64], cell_offsets: &[u32]) -> Result<usize> {
+    let vertex_count = check_points(cell_vertices)?;
+    if cell_offsets.first() != Some(&0) || cell_offsets.last() != Some(&(vertex_count as u32)) {
+        return Err(Error::InvalidArgument(format!(
+            "offsets must run from 0 to the vertex count {vertex_count}"
+        )));
+    }
+    if cell_offsets.windows(2).any(|w| w[0] > w[1]) {
+        return Err(Error::InvalidArgument("offsets must be non-decreasing".into()));
+    }
+    if cell_vertices.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("cell coordinates must be finite".into()));
+    }
+    Ok(cell_offsets.len() - 1)
+}
+
+/// The cell spanning the offsets pair `range` as points
+fn cell_points(cell_vertices: &[f64], range: &[u32]) -> Vec<Point> {
+    cell_vertices[range[0] as usize * 2..range[1] as usize * 2]
+        .chunks_exact(2)
+        .map(|c| Point::new(c[0], c[1]))
+        .collect()
+}
+
+/// How `inset_cells` pulls each cell in
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InsetMode {
+    /// Every side moves inward by `inset` canvas units, corners mitred
+    Offset = 0,
+    /// Every vertex moves the fraction `inset` of the way to its seed
+    TowardSeed = 1,
+}
+
+/// Cells in the `compute_voronoi` layout pulled in by `inset`, for a gap
+/// between neighbouring cells
+///
+/// `Offset` intersects the half-planes of the sides moved inward, which is
+/// the exact offset of a convex cell (every cell this crate emits) and
+/// for any other polygon a region inside it that still never
+/// self-intersects. A cell narrower than twice the inset vanishes, as
+/// does every cell at `TowardSeed` with `inset = 1`; vanished cells get
+/// an empty range like seeds without a cell. `points_flat` holds one seed
+/// per cell and is only read by `TowardSeed`.
+#[wasm_bindgen]
+pub fn inset_cells(
+    cell_vertices: &[f64],
+    cell_offsets: &[u32],
+    points_flat: &[f64],
+    inset: f64,
+    mode: InsetMode,
+) -> Result<VoronoiCells> {
+    let seed_count = check_points(points_flat)?;
+    if cell_offsets.len() != seed_count + 1 {
+        return Err(Error::InvalidLength(format!(
+            "{seed_count} seeds need {} offsets, got {}",
+            seed_count + 1,
+            cell_offsets.len()
+        )));
+    }
+    check_cells(cell_vertices, cell_offsets)?;
+    if points_flat.iter().any(|v| !v.is_finite()) {
+        return Err(Error::NonFiniteInput("seed coordinates must be finite".into()));
+    }
+    let limit = if mode == InsetMode::TowardSeed { 1.0 } else { f64::INFINITY };
+    if !(inset >= 0.0 && inset <= limit) {
+        return Err(Error::InvalidArgument(format!(
+            "inset must be in [0, {limit}] for {mode:?}, got {inset}"
+        )));
+    }
+
+    let mut vertices = Vec::with_capacity(cell_vertices.len());
+    let mut offsets = Vec::with_capacity(cell_offsets.len());
+    offsets.push(0u32);
+    let (mut poly, mut scratch) = (Vec::new(), Vec::new());
+    for (i, w) in cell_offsets.windows(2).enumerate() {
+        let cell = cell_points(cell_vertices, w);
+        poly.clear();
+        match mode {
+            InsetMode::TowardSeed => {
+                let s = Point::new(points_flat[2 * i], points_flat[2 * i + 1]);
+                let lerp = |a: f64, b: f64| a + (b - a) * inset;
+                poly.extend(cell.iter().map(|p| Point::new(lerp(p.x, s.x), lerp(p.y, s.y))));
+            }
+            InsetMode::Offset => {
+                poly.extend_from_slice(&cell);
+                // Outward is to the right of a counter-clockwise side
+                let turn = if signed_area2(&cell) < 0.0 { -1.0 } else { 1.0 };
+                for (k, &a) in cell.iter().enumerate() {
+                    let b = cell[(k + 1) % cell.len()];
+                    let len = (b.x - a.x).hypot(b.y - a.y);
+                    if len == 0.0 || poly.is_empty() {
+                        continue;
+                    }
+                    let out = Point::new(turn * (b.y - a.y) / len, turn * (a.x - b.x) / len);
+                    let origin = Point::new(a.x - out.x * inset, a.y - out.y * inset);
+                    clip_half_plane(&poly, origin, out, &mut scratch);
+                    std::mem::swap(&mut poly, &mut scratch);
+                }
+            }
+        }
+        dedup_ring(&mut poly);
+        if poly.len() >= 3 && signed_area2(&poly) != 0.0 {
+            vertices.extend(poly.iter().flat_map(|p| [p.x, p.y]));
+        }
+        offsets.push((vertices.len() / 2) as u32);
+    }
+    Ok(VoronoiCells { vertices, offsets })
+}
+
+/// Vertices `round_cell_corners` may emit in one call
+const MAX_ROUNDED_VERTICES: u64 = 1 << 24;
+
+/// Cells in the `compute_voronoi` layout with every corner replaced by a
+/// circular arc of `radius`, as `segments_per_corner` chords
+///
+/// The arc is tangent to both sides of its corner. Where that would reach
+/// past the middle of either side, the tangent points stop at the middle
+/// and the radius shrinks to fit, so neighbouring arcs never overlap and
+/// a cell rounded with any radius stays simple; a triangle rounded past
+/// its inradius comes out smaller, not folded. Straight corners and
+/// `radius = 0` keep their single vertex.
+#[wasm_bindgen]
+pub fn round_cell_corners(
+    cell_vertices: &[f64],
+    cell_offsets: &[u32],
+    radius: f64,
+    segments_per_corner: u32,
+) -> Result<VoronoiCells> {
+    check_cells(cell_vertices, cell_offsets)?;
+    if !(radius.is_finite() && radius >= 0.0) {
+        return Err(Error::InvalidArgument(format!(
+            "radius must be finite and non-negative, got {radius}"
+        )));
+    }
+    if segments_per_corner == 0 {
+        return Err(Error::InvalidArgument("segments per corner must be at least 1".into()));
+    }
+    let bound = (cell_vertices.len() / 2) as u64 * (segments_per_corner as u64 + 1);
+    if bound > MAX_ROUNDED_VERTICES {
+        return Err(Error::TooLarge(format!(
+            "{segments_per_corner} segments per corner are too many for {} vertices",
+            cell_vertices.len() / 2
+        )));
+    }
+
+    let n = segments_per_corner as f64;
+    let mut vertices = Vec::new();
+    let mut offsets = Vec::with_capacity(cell_offsets.len());
+    offsets.push(0u32);
+    let mut poly = Vec::new();
+    for w in cell_offsets.windows(2) {
+        let mut cell = cell_points(cell_vertices, w);
+        dedup_ring(&mut cell);
+        poly.clear();
+        let len = cell.len();
+        for (k, &v) in cell.iter().enumerate() {
+            if len < 3 {
+                break;
+            }
+            let (prev, next) = (cell[(k + len - 1) % len], cell[(k + 1) % len]);
+            let (l1, l2) = ((prev.x - v.x).hypot(prev.y - v.y), (next.x - v.x).hypot(next.y - v.y));
+            let u1 = Point::new((prev.x - v.x) / l1, (prev.y - v.y) / l1);
+            let u2 = Point::new((next.x - v.x) / l2, (next.y - v.y) / l2);
+            // tan of half the corner angle, from its cosine
+            let cos = (u1.x * u2.x + u1.y * u2.y).clamp(-1.0, 1.0);
+            let tan_half = ((1.0 - cos) / (1.0 + cos)).sqrt();
+            // A zero-angle spike has no tangent circle
+            let t = if tan_half > 0.0 { (radius / tan_half).min(l1.min(l2) / 2.0) } else { 0.0 };
+            if t == 0.0 {
+                poly.push(v);
+                continue;
+            }
+            let r = t * tan_half;
+            let bis = Point::new(u1.x + u2.x, u1.y + u2.y);
+            let (bl, reach) = (bis.x.hypot(bis.y), t.hypot(r));
+            let c = Point::new(v.x + bis.x / bl * reach, v.y + bis.y / bl * reach);
+            let a = Point::new(v.x + u1.x * t, v.y + u1.y * t);
+            let b = Point::new(v.x + u2.x * t, v.y + u2.y * t);
+            let start = (a.y - c.y).atan2(a.x - c.x);
+            let mut sweep = (b.y - c.y).atan2(b.x - c.x) - start;
+            if sweep > std::f64::consts::PI {
+                sweep -= std::f64::consts::TAU;
+            } else if sweep < -std::f64::consts::PI {
+                sweep += std::f64::consts::TAU;
+            }
+            poly.push(a);
+            for s in 1..segments_per_corner {
+                let phi = start + sweep * s as f64 / n;
+                poly.push(Point::new(c.x + r * phi.cos(), c.y + r * phi.sin()));
+            }
+            poly.push(b);
+        }
+        dedup_ring(&mut poly);
+        if poly.len() >= 3 && signed_area2(&poly) != 0.0 {
+            vertices.extend(poly.iter().flat_map(|p| [p.x, p.y]));
+        }
+        offsets.push((vertices.len() / 2) as u32);
+    }
+    Ok(VoronoiCells { vertices, offsets })
+}
+
+/// Panic unless a polygon is simple, closed without repetition, convex
+/// and strictly counter-clockwise
+#[cfg(test)]
+pub(crate) fn assert_simple_ccw(poly: &[Point]) {
+    use crate::crossings::segments_cross;
+    use crate::predicates::orient2d;
+
+    let n = poly.len();
+    assert!(n >= 3, "degenerate polygon {poly:?}");
+    for i in 0..n {
+        for j in i + 1..n {
+            assert_ne!(poly[i], poly[j], "repeated vertex in {poly:?}");
+        }
+    }
+    assert!(signed_area2(poly) > 0.0, "not counter-clockwise: {poly:?}");
+    for i in 0..n {
+        for j in i + 1..n {
+            let adjacent = j == i + 1 || (i == 0 && j == n - 1);
+            if adjacent {
+                continue;
+            }
+            let e = [poly[i], poly[(i + 1) % n]];
+            let f = [poly[j], poly[(j + 1) % n]];
+            assert!(!segments_cross(&e, &f), "self-intersection in {poly:?}");
+        }
+    }
+    // Convexity: no reflex turn anywhere
+    for i in 0..n {
+        let (a, b, c) = (poly[i], poly[(i + 1) % n], poly[(i + 2) % n]);
+        assert!(orient2d(a, b, c) >= -1e-9 * signed_area2(poly).abs(), "reflex vertex");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::generate_golden_seeds;
+    use crate::rng::Rng;
+
+    fn check(points_flat: &[f64], width: f64, height: f64) -> VoronoiCells {
+        let cells = compute_voronoi(points_flat, width, height).unwrap();
+        assert_eq!(cells.cell_count(), points_flat.len() / 2);
+        let mut area = 0.0;
+        for w in cells.offsets.windows(2) {
+            let (a, b) = (w[0] as usize, w[1] as usize);
+            if a == b {
+                continue;
+            }
+            let poly: Vec<Point> = cells.vertices[a * 2..b * 2]
+                .chunks(2)
+                .map(|c| Point { x: c[0], y: c[1] })
+                .collect();
+            assert_simple_ccw(&poly);
+            for p in &poly {
+                assert!((0.0..=width).contains(&p.x) && (0.0..=height).contains(&p.y));
+            }
+            area += signed_area2(&poly) / 2.0;
+        }
+        if points_flat.iter().all(|v| v.is_finite()) && !points_flat.is_empty() {
+            let rel = (area - width * height).abs() / (width * height);
+            assert!(rel < 1e-6, "cells cover {area} of {}", width * height);
+        }
+        cells
+    }
+
+    #[test]
+    fn test_random_seed_sets_give_simple_ccw_cells() {
+        let mut rng = Rng::new(2024);
+        for _ in 0..2000 {
+            let n = 1 + rng.below(40) as usize;
+            let (w, h) = (50.0 + rng.next_f64() * 800.0, 50.0 + rng.next_f64() * 800.0);
+            let points: Vec<f64> =
+                (0..n).flat_map(|_| [rng.next_f64() * w, rng.next_f64() * h]).collect();
+            check(&points, w, h);
+        }
+    }
+
+    #[test]
+    fn test_adversarial_seed_sets_give_simple_ccw_cells() {
+        let (w, h) = (400.0, 300.0);
+        let mut sets: Vec<Vec<f64>> = vec![
+            // Corners and edge midpoints of the canvas
+            vec![0.0, 0.0, w, 0.0, w, h, 0.0, h, w / 2.0, 0.0, w, h / 2.0],
+            // Cocircular lattice
+            (0..100).flat_map(|i| [(i % 10) as f64 * 40.0, (i / 10) as f64 * 30.0]).collect(),
+            // Collinear along a diagonal, and along the bottom edge
+            (0..20).flat_map(|i| [i as f64 * 20.0, i as f64 * 15.0]).collect(),
+            (0..20).flat_map(|i| [i as f64 * 20.0, 0.0]).collect(),
+            // Near-coincident clusters
+            (0..30).flat_map(|i| [200.0 + i as f64 * 1e-9, 150.0 + (i % 3) as f64 * 1e-9]).collect(),
+            generate_golden_seeds(w, h, 300),
+        ];
+        let mut rng = Rng::new(7);
+        for _ in 0..200 {
+            // Clustered in a corner with a few far outliers off canvas
+            let mut s: Vec<f64> =
+                (0..25).flat_map(|_| [rng.next_f64() * 5.0, rng.next_f64() * 5.0]).collect();
+            s.extend_from_slice(&[-500.0, -500.0, w + 800.0, h * 0.5, w * 0.5, h + 300.0]);
+            sets.push(s);
+        }
+        for points in &sets {
+            check(points, w, h);
+        }
+    }
+
+    #[test]
+    fn test_cell_metrics_cover_the_canvas() {
+        let (w, h) = (640.0, 480.0);
+        let mut rng = Rng::new(31);
+        let mut points = generate_golden_seeds(w, h, 250);
+        // Seeds near and beyond the edges get clipped cells
+        points.extend(
+            (0..40).flat_map(|_| [rng.next_f64() * 1.2 * w - 0.1 * w, rng.next_f64() * h]),
+        );
+        let metrics = voronoi_cell_metrics(&points, w, h).unwrap();
+        assert_eq!(metrics.len(), points.len() * 2);
+        let total: f64 = metrics.chunks(4).map(|m| m[2]).sum();
+        assert!((total - w * h).abs() < 1e-9 * w * h, "areas add up to {total}");
+        for m in metrics.chunks(4).filter(|m| m[2] > 0.0) {
+            assert!((0.0..=w).contains(&m[0]) && (0.0..=h).contains(&m[1]));
+            // No shape encloses more area than a circle of the same perimeter
+            assert!(m[3] * m[3] >= 4.0 * std::f64::consts::PI * m[2]);
+        }
+
+        // One seed owns the whole canvas; a twin and a far seed own nothing
+        let lone = voronoi_cell_metrics(&[10.0, 20.0, 10.0, 20.0, -900.0, 0.0], w, h).unwrap();
+        assert_eq!(&lone[..4], &[w / 2.0, h / 2.0, w * h, 2.0 * (w + h)]);
+        assert_eq!(&lone[4..], &[10.0, 20.0, 0.0, 0.0, -900.0, 0.0, 0.0, 0.0]);
+        let bad = voronoi_cell_metrics(&[1.0, f64::NAN], w, h);
+        assert_eq!(code_of(bad), ErrorCode::NonFiniteInput);
+    }
+
+    #[test]
+    fn test_sentinels_for_duplicates_and_off_canvas_seeds() {
+        let points = [100.0, 100.0, 300.0, 200.0, 100.0, 100.0, -900.0, -900.0];
+        let cells = check(&points, 400.0, 300.0);
+        let len = |i: usize| cells.offsets[i + 1] - cells.offsets[i];
+        assert!(len(0) >= 3 && len(1) >= 3);
+        assert_eq!(len(2), 0, "duplicate seed keeps no cell");
+        assert_eq!(len(3), 0, "far off-canvas seed clips away");
+        let first = cells.cell(0).unwrap();
+        assert_eq!(first, cells.vertices[..2 * len(0) as usize]);
+        assert!(cells.cell(2).unwrap().is_empty());
+        assert_eq!(code_of(cells.cell(4)), ErrorCode::IndexOutOfRange);
+        assert_eq!(code_of(compute_voronoi(&points[..3], 400.0, 300.0)), ErrorCode::InvalidLength);
+        assert_eq!(code_of(compute_voronoi(&points, 0.0, 300.0)), ErrorCode::InvalidArgument);
+        let nan = compute_voronoi(&[1.0, f64::NAN], 400.0, 300.0);
+        assert_eq!(code_of(nan), ErrorCode::NonFiniteInput);
+    }
+
+    #[test]
+    fn test_inset_cells_offset_sides_and_collapse() {
+        // A square cell and a thin triangle, both with their seeds inside
+        let vertices =
+            [0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0, 20.0, 0.0, 30.0, 0.0, 20.0, 1.0];
+        let seeds = [5.0, 5.0, 22.0, 0.3];
+        let offset = inset_cells(&vertices, &[0, 4, 7], &seeds, 1.0, InsetMode::Offset).unwrap();
+        let corners = |cells: &VoronoiCells| {
+            let mut c: Vec<(i64, i64)> =
+                cells.vertices().chunks(2).map(|c| (c[0] as i64, c[1] as i64)).collect();
+            c.sort_unstable();
+            c
+        };
+        assert_eq!(offset.offsets(), vec![0, 4, 4]);
+        assert_eq!(corners(&offset), vec![(1, 1), (1, 9), (9, 1), (9, 9)]);
+        assert!(offset.vertices().iter().all(|v| v.fract() == 0.0));
+        // Clockwise input shrinks the same way
+        let clockwise = [0.0, 0.0, 0.0, 10.0, 10.0, 10.0, 10.0, 0.0];
+        let cells = inset_cells(&clockwise, &[0, 4], &seeds[..2], 1.0, InsetMode::Offset).unwrap();
+        assert_eq!(corners(&cells), corners(&offset));
+
+        let half = inset_cells(&vertices, &[0, 4, 7], &seeds, 0.5, InsetMode::TowardSeed).unwrap();
+        assert_eq!(&half.vertices()[..8], &[2.5, 2.5, 7.5, 2.5, 7.5, 7.5, 2.5, 7.5]);
+        assert_eq!(half.offsets(), vec![0, 4, 7]);
+        let gone = inset_cells(&vertices, &[0, 4, 7], &seeds, 1.0, InsetMode::TowardSeed);
+        assert_eq!(gone.unwrap().offsets(), vec![0, 0, 0]);
+        let same = inset_cells(&vertices, &[0, 4, 7], &seeds, 0.0, InsetMode::Offset).unwrap();
+        assert_eq!(same.vertices(), vertices.to_vec());
+    }
+
+    #[test]
+    fn test_inset_voronoi_cells_keep_their_distance() {
+        let (w, h) = (600.0, 400.0);
+        let seeds = generate_golden_seeds(w, h, 150);
+        let cells = compute_voronoi(&seeds, w, h).unwrap();
+        let (vertices, offsets) = (cells.vertices(), cells.offsets());
+        let inset = inset_cells(&vertices, &offsets, &seeds, 3.0, InsetMode::Offset).unwrap();
+        assert_eq!(inset.cell_count(), 150);
+        for i in 0..150 {
+            let (outer, inner) = (cells.cell(i).unwrap(), inset.cell(i).unwrap());
+            if inner.is_empty() {
+                continue;
+            }
+            let poly: Vec<Point> = inner.chunks(2).map(|c| Point::new(c[0], c[1])).collect();
+            assert_simple_ccw(&poly);
+            // Every inset vertex is 3 from the nearest side of its cell
+            let n = outer.len() / 2;
+            for p in &poly {
+                let gap = (0..n)
+                    .map(|k| {
+                        let (ax, ay) = (outer[2 * k], outer[2 * k + 1]);
+                        let (bx, by) = (outer[(2 * k + 2) % (2 * n)], outer[(2 * k + 3) % (2 * n)]);
+                        ((bx - ax) * (p.y - ay) - (by - ay) * (p.x - ax)) / (bx - ax).hypot(by - ay)
+                    })
+                    .fold(f64::INFINITY, f64::min);
+                assert!((gap - 3.0).abs() < 1e-6, "cell {i}: vertex {p:?} is {gap} inside");
+            }
+        }
+
+        let code = |offsets: &[u32], inset: f64, mode| {
+            code_of(inset_cells(&vertices, offsets, &seeds, inset, mode))
+        };
+        assert_eq!(code(&offsets[..150], 1.0, InsetMode::Offset), ErrorCode::InvalidLength);
+        assert_eq!(code(&offsets, -1.0, InsetMode::Offset), ErrorCode::InvalidArgument);
+        assert_eq!(code(&offsets, 1.5, InsetMode::TowardSeed), ErrorCode::InvalidArgument);
+        assert_eq!(code(&offsets, f64::NAN, InsetMode::Offset), ErrorCode::InvalidArgument);
+        let mut shuffled = offsets.clone();
+        shuffled.swap(1, 2);
+        assert_eq!(code(&shuffled, 1.0, InsetMode::Offset), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_round_cell_corners_fits_arcs() {
+        let square = [0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0];
+        let rounded = round_cell_corners(&square, &[0, 4], 2.0, 4).unwrap();
+        let poly: Vec<Point> =
+            rounded.vertices().chunks(2).map(|c| Point::new(c[0], c[1])).collect();
+        assert_eq!(poly.len(), 20);
+        assert_simple_ccw(&poly);
+        // Every vertex lies on the arc around its corner's centre or on a side
+        for p in &poly {
+            let (cx, cy) = (p.x.clamp(2.0, 8.0), p.y.clamp(2.0, 8.0));
+            assert!(((p.x - cx).hypot(p.y - cy) - 2.0).abs() < 1e-9, "{p:?} is off the outline");
+        }
+        // The chords cut a little off the exact rounded square
+        let area = signed_area2(&poly) / 2.0;
+        assert!(area > 96.2 && area < 100.0 - 4.0 * (4.0 - std::f64::consts::PI), "area {area}");
+
+        assert_eq!(round_cell_corners(&square, &[0, 4], 0.0, 4).unwrap().vertices(), square);
+        // A chamfer is one chord per corner
+        assert_eq!(round_cell_corners(&square, &[0, 4], 1.0, 1).unwrap().vertices().len(), 16);
+    }
+
+    #[test]
+    fn test_round_cell_corners_clamps_large_radii() {
+        // The inradius of this triangle is about 2.9
+        let triangle = [0.0, 0.0, 12.0, 0.0, 3.0, 8.0];
+        let cells = round_cell_corners(&triangle, &[0, 0, 3], 50.0, 8).unwrap();
+        assert_eq!(cells.offsets()[..2], [0, 0]);
+        let poly: Vec<Point> =
+            cells.cell(1).unwrap().chunks(2).map(|c| Point::new(c[0], c[1])).collect();
+        assert_simple_ccw(&poly);
+        let corners: Vec<Point> = triangle.chunks(2).map(|c| Point::new(c[0], c[1])).collect();
+        for p in &poly {
+            for k in 0..3 {
+                let side = crate::predicates::orient2d(corners[k], corners[(k + 1) % 3], *p);
+                assert!(side >= -1e-9, "{p:?} is outside the triangle");
+            }
+        }
+        assert!(signed_area2(&poly) < signed_area2(&corners));
+
+        // Real cells stay simple and lose area at the corners only
+        let (w, h) = (500.0, 400.0);
+        let seeds = generate_golden_seeds(w, h, 120);
+        let voronoi = compute_voronoi(&seeds, w, h).unwrap();
+        let (vertices, offsets) = (voronoi.vertices(), voronoi.offsets());
+        let rounded = round_cell_corners(&vertices, &offsets, 6.0, 5).unwrap();
+        assert_eq!(rounded.cell_count(), 120);
+        for i in 0..120 {
+            let (before, after) = (voronoi.cell(i).unwrap(), rounded.cell(i).unwrap());
+            let poly: Vec<Point> = after.chunks(2).map(|c| Point::new(c[0], c[1])).collect();
+            assert_simple_ccw(&poly);
+            let outer: Vec<Point> = before.chunks(2).map(|c| Point::new(c[0], c[1])).collect();
+            assert!(signed_area2(&poly) <= signed_area2(&outer));
+        }
+
+        let code = |offsets: &[u32], radius: f64, segments| {
+            code_of(round_cell_corners(&vertices, offsets, radius, segments))
+        };
+        assert_eq!(code(&offsets, -1.0, 4), ErrorCode::InvalidArgument);
+        assert_eq!(code(&offsets, f64::INFINITY, 4), ErrorCode::InvalidArgument);
+        assert_eq!(code(&offsets, 1.0, 0), ErrorCode::InvalidArgument);
+        assert_eq!(code(&offsets, 1.0, u32::MAX), ErrorCode::TooLarge);
+        assert_eq!(code(&offsets[1..], 1.0, 4), ErrorCode::InvalidArgument);
+        assert_eq!(code(&[], 1.0, 4), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(round_cell_corners(&[1.0], &[0], 1.0, 4)), ErrorCode::InvalidLength);
+    }
+}
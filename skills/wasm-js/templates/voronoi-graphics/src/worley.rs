@@ -0,0 +1,249 @@
+//! Worley (cellular) noise over a set of seed points
+//!
+//! `F1` at a point is its distance to the nearest seed and `F2` to the
+//! second nearest, so `F1` is dark at the seeds and bright along the
+//! Voronoi edges, while `F2 - F1` is zero exactly on the edges and gives
+//! a crackle pattern. Distances are divided by a caller-supplied `scale`,
+//! typically the mean seed spacing, to land roughly in [0, 1]. Seeds are
+//! bucketed in the same grid `SeedIndex` uses, so a sample only looks at
+//! the seeds around it.
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::graph::check_points;
+use crate::spatial::PointGrid;
+use crate::Point;
+
+/// Samples `worley_grid` will compute in one call
+const MAX_SAMPLES: u64 = 1 << 26;
+
+/// Which distance feature a Worley sample returns
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorleyMode {
+    /// Distance to the nearest seed
+    F1 = 0,
+    /// Distance to the second-nearest seed
+    F2 = 1,
+    /// `F2 - F1`, zero on the cell borders
+    F2MinusF1 = 2,
+}
+
+/// How distances to the seeds are measured
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorleyMetric {
+    /// Straight-line distance, giving round cells
+    Euclidean = 0,
+    /// `|dx| + |dy|`, giving diamond-shaped cells with straight borders
+    Manhattan = 1,
+}
+
+/// Seeds indexed for repeated Worley samples
+#[wasm_bindgen]
+pub struct WorleyNoise {
+    grid: PointGrid,
+    count: usize,
+    metric: WorleyMetric,
+    scale: f64,
+}
+
+#[wasm_bindgen]
+impl WorleyNoise {
+    #[wasm_bindgen(constructor)]
+    pub fn new(points_flat: &[f64], metric: WorleyMetric, scale: f64) -> Result<WorleyNoise> {
+        let n = check_points(points_flat)?;
+        if n == 0 {
+            return Err(Error::InvalidArgument("Worley noise needs at least one seed".into()));
+        }
+        if points_flat.iter().any(|v| !v.is_finite()) {
+            return Err(Error::NonFiniteInput("seed coordinates must be finite".into()));
+        }
+        if !(scale.is_finite() && scale > 0.0) {
+            return Err(Error::InvalidArgument(format!("scale must be positive, got {scale}")));
+        }
+        let seeds: Vec<Point> =
+            points_flat.chunks_exact(2).map(|c| Point::new(c[0], c[1])).collect();
+        let first = seeds[0];
+        let grid = PointGrid::new(&seeds, [first.x, first.y, first.x, first.y]);
+        Ok(WorleyNoise { grid, count: n, metric, scale })
+    }
+
+    pub fn seed_count(&self) -> usize {
+        self.count
+    }
+
+    /// The `mode` feature at `(x, y)`, divided by the scale
+    pub fn sample(&self, x: f64, y: f64, mode: WorleyMode) -> Result<f64> {
+        if !(x.is_finite() && y.is_finite()) {
+            return Err(Error::NonFiniteInput(format!("sample point ({x}, {y}) is not finite")));
+        }
+        self.check_mode(mode)?;
+        Ok(self.feature(Point::new(x, y), mode))
+    }
+
+    /// Row-major `grid_w × grid_h` samples over a `width × height` canvas,
+    /// each taken at the centre of its texel, for upload as a texture
+    pub fn grid(
+        &self,
+        grid_w: u32,
+        grid_h: u32,
+        width: f64,
+        height: f64,
+        mode: WorleyMode,
+    ) -> Result<Vec<f32>> {
+        if grid_w == 0 || grid_h == 0 {
+            return Err(Error::InvalidArgument("grid size must be positive".into()));
+        }
+        if grid_w as u64 * grid_h as u64 > MAX_SAMPLES {
+            return Err(Error::TooLarge(format!("a {grid_w}x{grid_h} grid is too large")));
+        }
+        if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+            return Err(Error::InvalidArgument("width and height must be positive".into()));
+        }
+        self.check_mode(mode)?;
+        let (sx, sy) = (width / grid_w as f64, height / grid_h as f64);
+        let mut out = Vec::with_capacity(grid_w as usize * grid_h as usize);
+        for row in 0..grid_h {
+            for col in 0..grid_w {
+                let p = Point::new((col as f64 + 0.5) * sx, (row as f64 + 0.5) * sy);
+                out.push(self.feature(p, mode) as f32);
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl WorleyNoise {
+    fn check_mode(&self, mode: WorleyMode) -> Result<()> {
+        if mode != WorleyMode::F1 && self.count < 2 {
+            return Err(Error::InvalidArgument(format!("{mode:?} needs at least two seeds")));
+        }
+        Ok(())
+    }
+
+    /// Scaled feature at `p`; the seed count is already checked for `mode`
+    fn feature(&self, p: Point, mode: WorleyMode) -> f64 {
+        let k = if mode == WorleyMode::F1 { 1 } else { 2 };
+        let nearest = match self.metric {
+            WorleyMetric::Euclidean => {
+                let d2 = |q: Point| (q.x - p.x).powi(2) + (q.y - p.y).powi(2);
+                let best = self.grid.k_nearest_by(p, k, d2, |reach| reach * reach);
+                best.into_iter().map(|(d2, _)| d2.sqrt()).collect::<Vec<f64>>()
+            }
+            WorleyMetric::Manhattan => {
+                let d = |q: Point| (q.x - p.x).abs() + (q.y - p.y).abs();
+                let best = self.grid.k_nearest_by(p, k, d, |reach| reach);
+                best.into_iter().map(|(d, _)| d).collect()
+            }
+        };
+        let d = match mode {
+            WorleyMode::F1 => nearest[0],
+            WorleyMode::F2 => nearest[1],
+            WorleyMode::F2MinusF1 => nearest[1] - nearest[0],
+        };
+        d / self.scale
+    }
+}
+
+/// A single Worley sample of `points_flat` at `(x, y)`
+///
+/// Builds the seed index for one query; reuse a `WorleyNoise` for many.
+#[wasm_bindgen]
+pub fn worley_noise(
+    points_flat: &[f64],
+    x: f64,
+    y: f64,
+    mode: WorleyMode,
+    metric: WorleyMetric,
+    scale: f64,
+) -> Result<f64> {
+    WorleyNoise::new(points_flat, metric, scale)?.sample(x, y, mode)
+}
+
+/// `WorleyNoise::grid` over `points_flat`, indexing the seeds once
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn worley_grid(
+    points_flat: &[f64],
+    grid_w: u32,
+    grid_h: u32,
+    width: f64,
+    height: f64,
+    mode: WorleyMode,
+    metric: WorleyMetric,
+    scale: f64,
+) -> Result<Vec<f32>> {
+    WorleyNoise::new(points_flat, metric, scale)?.grid(grid_w, grid_h, width, height, mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{code_of, ErrorCode};
+    use crate::rng::Rng;
+
+    /// Sorted distances from `(x, y)` to every seed, by brute force
+    fn brute(seeds: &[f64], x: f64, y: f64, metric: WorleyMetric) -> Vec<f64> {
+        let mut d: Vec<f64> = seeds
+            .chunks(2)
+            .map(|c| match metric {
+                WorleyMetric::Euclidean => (c[0] - x).hypot(c[1] - y),
+                WorleyMetric::Manhattan => (c[0] - x).abs() + (c[1] - y).abs(),
+            })
+            .collect();
+        d.sort_by(f64::total_cmp);
+        d
+    }
+
+    #[test]
+    fn test_features_match_brute_force() {
+        let mut rng = Rng::new(5);
+        let seeds: Vec<f64> =
+            (0..800).flat_map(|_| [rng.range(0.0, 800.0), rng.range(0.0, 600.0)]).collect();
+        for metric in [WorleyMetric::Euclidean, WorleyMetric::Manhattan] {
+            let noise = WorleyNoise::new(&seeds, metric, 25.0).unwrap();
+            for _ in 0..300 {
+                let (x, y) = (rng.range(-200.0, 1000.0), rng.range(-200.0, 800.0));
+                let d = brute(&seeds, x, y, metric);
+                let f1 = noise.sample(x, y, WorleyMode::F1).unwrap();
+                let f2 = noise.sample(x, y, WorleyMode::F2).unwrap();
+                let edge = noise.sample(x, y, WorleyMode::F2MinusF1).unwrap();
+                assert!((f1 - d[0] / 25.0).abs() < 1e-12, "{metric:?} at ({x}, {y})");
+                assert!((f2 - d[1] / 25.0).abs() < 1e-12, "{metric:?} at ({x}, {y})");
+                assert!((edge - (f2 - f1)).abs() < 1e-12);
+            }
+        }
+        let euclidean = WorleyMetric::Euclidean;
+        let one = worley_noise(&seeds, 10.0, 20.0, WorleyMode::F2, euclidean, 1.0).unwrap();
+        assert!((one - brute(&seeds, 10.0, 20.0, euclidean)[1]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_grid_samples_texel_centres() {
+        let seeds = [10.0, 10.0, 30.0, 10.0];
+        let mode = WorleyMode::F1;
+        let texels = worley_grid(&seeds, 4, 2, 40.0, 20.0, mode, WorleyMetric::Manhattan, 10.0);
+        let texels = texels.unwrap();
+        // Centres at x = 5, 15, 25, 35 and y = 5, 15
+        assert_eq!(texels, [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0]);
+        let noise = WorleyNoise::new(&seeds, WorleyMetric::Euclidean, 1.0).unwrap();
+        let edge = noise.grid(4, 1, 40.0, 20.0, WorleyMode::F2MinusF1).unwrap();
+        // Texel centres at y = 10, on the line through both seeds
+        assert_eq!(edge, [20.0, 10.0, 10.0, 20.0]);
+
+        let lone = WorleyNoise::new(&[1.0, 1.0], WorleyMetric::Euclidean, 1.0).unwrap();
+        assert_eq!(lone.sample(4.0, 5.0, WorleyMode::F1).unwrap(), 5.0);
+        assert_eq!(code_of(lone.sample(0.0, 0.0, WorleyMode::F2)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(lone.sample(f64::NAN, 0.0, mode)), ErrorCode::NonFiniteInput);
+        assert_eq!(code_of(noise.grid(0, 4, 1.0, 1.0, mode)), ErrorCode::InvalidArgument);
+        assert_eq!(code_of(noise.grid(1 << 14, 1 << 14, 1.0, 1.0, mode)), ErrorCode::TooLarge);
+        assert_eq!(code_of(noise.grid(4, 4, 0.0, 1.0, mode)), ErrorCode::InvalidArgument);
+        let new = |p: &[f64], scale| code_of(WorleyNoise::new(p, WorleyMetric::Euclidean, scale));
+        assert_eq!(new(&[], 1.0), ErrorCode::InvalidArgument);
+        assert_eq!(new(&[1.0], 1.0), ErrorCode::InvalidLength);
+        assert_eq!(new(&[0.0, f64::INFINITY], 1.0), ErrorCode::NonFiniteInput);
+        assert_eq!(new(&seeds, 0.0), ErrorCode::InvalidArgument);
+    }
+}